@@ -1,6 +1,46 @@
+use std::{env, path::Path, process::Command};
+
 fn main() {
     if cfg!(target_os = "windows") {
         println!("cargo:rustc-link-lib=dylib=mfuuid");
         println!("cargo:rustc-link-lib=dylib=strmiids");
     }
+
+    // Build metadata shown by `--version --verbose` and written to outputs
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // The git directory is elsewhere in worktrees and submodules, and refs are shared by worktrees
+    if let Some(dirs) = git(&["rev-parse", "--git-dir", "--git-common-dir"]) {
+        if let [git_dir, common_dir] = dirs.lines().map(Path::new).collect::<Vec<_>>()[..] {
+            for path in [
+                git_dir.join("HEAD"),
+                common_dir.join("refs"),
+                common_dir.join("packed-refs"),
+            ] {
+                if path.exists() {
+                    println!("cargo:rerun-if-changed={}", path.display());
+                }
+            }
+        }
+    }
+    if let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=ELDEN_ANALYZER_GIT_HASH={hash}");
+    }
+    // Reproducible builds set the build date with `SOURCE_DATE_EPOCH`, and builds of a commit use its date
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .or_else(|| git(&["log", "-1", "--format=%ct"])?.parse().ok());
+    if let Some(timestamp) = timestamp {
+        println!("cargo:rustc-env=ELDEN_ANALYZER_BUILD_TIMESTAMP={timestamp}");
+    }
+}
+
+/// Returns the trimmed output of a git command, or `None` if it is not built in a git repository (e.g. `cargo install`).
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_owned())
 }
@@ -0,0 +1,131 @@
+use std::{collections::BTreeMap, ops::Range};
+
+/// A map from disjoint half-open intervals to values.
+#[derive(Debug, Clone)]
+pub struct IntervalMap<K, V> {
+    /// Start of each interval to its end and value
+    map: BTreeMap<K, (K, V)>,
+}
+
+impl<K, V> Default for IntervalMap<K, V> {
+    fn default() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K, V> IntervalMap<K, V>
+where
+    K: Ord + Clone,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Inserts a value for `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is empty or overlaps with an existing interval.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        assert!(range.start < range.end, "empty interval");
+        if let Some((_, (end, _))) = self.map.range(..=&range.start).next_back() {
+            assert!(*end <= range.start, "overlapping interval");
+        }
+        if let Some((start, _)) = self.map.range(&range.start..).next() {
+            assert!(range.end <= *start, "overlapping interval");
+        }
+        self.map.insert(range.start, (range.end, value));
+    }
+
+    /// Returns the interval containing `key` and its value.
+    pub fn get(&self, key: &K) -> Option<(Range<&K>, &V)> {
+        let (start, (end, value)) = self.map.range(..=key).next_back()?;
+        (key < end).then_some((start..end, value))
+    }
+
+    /// Returns the starts and the ends of the intervals in ascending order.
+    ///
+    /// The end of an interval and the start of the next one are merged if they are equal.
+    pub fn boundaries(&self) -> impl Iterator<Item = &K> {
+        let mut last = None::<&K>;
+        self.map
+            .iter()
+            .flat_map(|(start, (end, _))| [start, end])
+            .filter(move |k| {
+                let dup = last == Some(*k);
+                last = Some(*k);
+                !dup
+            })
+    }
+
+    /// Removes the intervals ending at or before `key`.
+    pub fn remove_until(&mut self, key: &K) {
+        self.map.retain(|_, (end, _)| *end > *key);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Range<&K>, &V)> {
+        self.map
+            .iter()
+            .map(|(start, (end, value))| (start..end, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalMap;
+
+    #[test]
+    fn basic() {
+        let mut map = IntervalMap::new();
+        map.insert(3..5, "a");
+        map.insert(0..1, "b");
+        map.insert(5..8, "c");
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.get(&0), Some((&0..&1, &"b")));
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&4), Some((&3..&5, &"a")));
+        assert_eq!(map.get(&5), Some((&5..&8, &"c")));
+        assert_eq!(map.get(&8), None);
+
+        assert!(map.boundaries().copied().eq([0, 1, 3, 5, 8]));
+
+        map.remove_until(&5);
+        assert!(map.iter().eq([(&5..&8, &"c")]));
+        map.remove_until(&8);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "overlapping interval")]
+    fn overlap_prev() {
+        let mut map = IntervalMap::new();
+        map.insert(0..3, ());
+        map.insert(2..4, ());
+    }
+
+    #[test]
+    #[should_panic(expected = "overlapping interval")]
+    fn overlap_next() {
+        let mut map = IntervalMap::new();
+        map.insert(2..4, ());
+        map.insert(0..3, ());
+    }
+
+    #[test]
+    #[should_panic(expected = "empty interval")]
+    fn empty() {
+        IntervalMap::new().insert(1..1, ());
+    }
+}
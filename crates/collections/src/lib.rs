@@ -1,4 +1,5 @@
 pub mod array;
+pub mod interval_map;
 pub mod seq_buf;
 pub mod seq_iter;
 pub mod vec2d;
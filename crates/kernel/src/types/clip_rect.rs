@@ -1,8 +1,40 @@
+use std::{fmt, str::FromStr};
+
 use num_rational::Ratio;
 
-use super::rect::Rect;
+use super::rect::{Rect, RectParseError};
+
+/// Ratios of the x and y coordinates to the size of the base rect
+pub type RatioPoint = (Ratio<i32>, Ratio<i32>);
+
+/// Denominator of ratios converted from decimal numbers
+const DECIMAL_DENOMINATOR: i32 = 10_000;
 
-#[derive(Debug, Clone, Copy)]
+/// A rectangular region relative to a base rect, which is scaled with the base rect.
+///
+/// A clip rect can be written in two forms:
+///
+/// * Pixels at a reference size of the base rect (`WIDTHxHEIGHT+X+Y@BASE_WIDTHxBASE_HEIGHT`), parsed by [`FromStr`].
+/// * Ratios of the left, top, right and bottom edges to the size of the base rect, measured from its top-left corner
+///   (`[[LEFT, TOP], [RIGHT, BOTTOM]]` in serialized forms). Columns and rows at the right and bottom edges are included.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+/// use num_rational::Ratio;
+///
+/// let base = Rect::at(100, 100).of_size(200, 40);
+/// let clip = "100x20+50+10@200x40".parse::<ClipRect>().unwrap();
+/// assert_eq!(clip.clip(base), Some(Rect::at(150, 110).of_size(100, 20)));
+///
+/// let clip = ClipRect::from_edges(
+///     (Ratio::new(1, 4), Ratio::new(1, 4)),
+///     (Ratio::new(3, 4), Ratio::new(3, 4)),
+/// )
+/// .unwrap();
+/// assert_eq!(clip.clip(base), Some(Rect::at(150, 110).of_size(101, 21)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ClipRect {
     top: Ratio<i32>,
     left: Ratio<i32>,
@@ -38,6 +70,35 @@ impl ClipRect {
         Self::new((left, top), (right, bottom))
     }
 
+    /// Creates a clip rect from ratios of the edges to the size of the base rect, measured from its top-left corner.
+    ///
+    /// Returns an error unless `0 <= left <= right <= 1` and `0 <= top <= bottom <= 1`.
+    pub fn from_edges(
+        (left, top): RatioPoint,
+        (right, bottom): RatioPoint,
+    ) -> Result<Self, ClipRectParseError> {
+        let zero = Ratio::from_integer(0);
+        let one = Ratio::from_integer(1);
+        let ordered = |min, max| zero <= min && min <= max && max <= one;
+        if !ordered(left, right) || !ordered(top, bottom) {
+            return Err(ClipRectParseError::InvalidEdges);
+        }
+        let half = Ratio::new(1, 2);
+        Ok(Self::new(
+            (left - half, top - half),
+            (right - half, bottom - half),
+        ))
+    }
+
+    /// Returns the ratios of the edges to the size of the base rect, measured from its top-left corner.
+    pub fn edges(&self) -> (RatioPoint, RatioPoint) {
+        let half = Ratio::new(1, 2);
+        (
+            (self.left + half, self.top + half),
+            (self.right + half, self.bottom + half),
+        )
+    }
+
     pub fn area(&self) -> Ratio<i32> {
         (self.right - self.left) * (self.bottom - self.top)
     }
@@ -73,3 +134,158 @@ impl ClipRect {
             .then(|| Rect::at(clip_left, clip_top).of_size(clip_width, clip_height))
     }
 }
+
+/// Formats ratios of the edges (`[[LEFT, TOP], [RIGHT, BOTTOM]]`).
+impl fmt::Display for ClipRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((left, top), (right, bottom)) = self.edges();
+        let to_f64 = |r: Ratio<i32>| f64::from(*r.numer()) / f64::from(*r.denom());
+        write!(
+            f,
+            "[[{}, {}], [{}, {}]]",
+            to_f64(left),
+            to_f64(top),
+            to_f64(right),
+            to_f64(bottom)
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClipRectParseError {
+    #[error("Invalid format (expected `WIDTHxHEIGHT+X+Y@BASE_WIDTHxBASE_HEIGHT`)")]
+    InvalidFormat,
+    #[error("Rect is out of the base rect")]
+    OutOfBase,
+    #[error("Edges must be ordered and between 0 and 1")]
+    InvalidEdges,
+    #[error(transparent)]
+    Rect(#[from] RectParseError),
+}
+
+/// Parses pixels at a reference size of the base rect (`WIDTHxHEIGHT+X+Y@BASE_WIDTHxBASE_HEIGHT`).
+impl FromStr for ClipRect {
+    type Err = ClipRectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rect, base) = s.split_once('@').ok_or(Self::Err::InvalidFormat)?;
+        let rect = rect.parse::<Rect>()?;
+        let base = base.parse::<Rect>()?;
+        if base.left() != 0 || base.top() != 0 {
+            return Err(Self::Err::InvalidFormat);
+        }
+        if rect.intersect(base) != Some(rect) {
+            return Err(Self::Err::OutOfBase);
+        }
+        Ok(Self::from_points(
+            (rect.left(), rect.top()),
+            (rect.right(), rect.bottom()),
+            (base.width() as i32, base.height() as i32),
+        ))
+    }
+}
+
+/// Serialized as ratios of the edges (`[[LEFT, TOP], [RIGHT, BOTTOM]]`).
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClipRect {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let ((left, top), (right, bottom)) = self.edges();
+        let to_f64 = |r: Ratio<i32>| f64::from(*r.numer()) / f64::from(*r.denom());
+        [[to_f64(left), to_f64(top)], [to_f64(right), to_f64(bottom)]].serialize(serializer)
+    }
+}
+
+/// Deserialized from either form, a string of pixels or ratios of the edges.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ClipRect {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, SeqAccess, Visitor};
+
+        struct ClipRectVisitor;
+
+        impl<'de> Visitor<'de> for ClipRectVisitor {
+            type Value = ClipRect;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(
+                    "`WIDTHxHEIGHT+X+Y@BASE_WIDTHxBASE_HEIGHT` or `[[LEFT, TOP], [RIGHT, BOTTOM]]`",
+                )
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<ClipRect, E>
+            where
+                E: de::Error,
+            {
+                s.parse()
+                    .map_err(|e| E::custom(format!("invalid clip rect `{s}`: {e}")))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<ClipRect, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut next = |idx| -> Result<[f64; 2], A::Error> {
+                    seq.next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(idx, &self))
+                };
+                let [left, top] = next(0)?;
+                let [right, bottom] = next(1)?;
+                let to_ratio = |v: f64| {
+                    Ratio::new(
+                        (v * f64::from(DECIMAL_DENOMINATOR)).round() as i32,
+                        DECIMAL_DENOMINATOR,
+                    )
+                };
+                ClipRect::from_edges(
+                    (to_ratio(left), to_ratio(top)),
+                    (to_ratio(right), to_ratio(bottom)),
+                )
+                .map_err(|e| {
+                    de::Error::custom(format!(
+                        "invalid clip rect `[[{left}, {top}], [{right}, {bottom}]]`: {e}"
+                    ))
+                })
+            }
+        }
+
+        deserializer.deserialize_any(ClipRectVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pixels() {
+        let base = Rect::at(0, 0).of_size(400, 80);
+        let clip = "100x20+50+10@200x40".parse::<ClipRect>().unwrap();
+        assert_eq!(clip.clip(base), Some(Rect::at(100, 20).of_size(199, 39)));
+
+        assert!("100x20+150+10@200x40".parse::<ClipRect>().is_err());
+        assert!("100x20+50+10".parse::<ClipRect>().is_err());
+        assert!("100x20+50+10@200x40+1+1".parse::<ClipRect>().is_err());
+    }
+
+    #[test]
+    fn edges() {
+        let edges = (
+            (Ratio::new(1, 10), Ratio::new(0, 1)),
+            (Ratio::new(1, 2), Ratio::new(1, 1)),
+        );
+        let clip = ClipRect::from_edges(edges.0, edges.1).unwrap();
+        assert_eq!(clip.edges(), edges);
+        assert_eq!(clip.to_string(), "[[0.1, 0], [0.5, 1]]");
+        let base = Rect::at(0, 0).of_size(100, 10);
+        assert_eq!(clip.clip(base), Some(Rect::at(10, 0).of_size(41, 10)));
+
+        assert!(ClipRect::from_edges(edges.1, edges.0).is_err());
+        assert!(ClipRect::from_edges(edges.0, (Ratio::new(3, 2), Ratio::new(1, 1))).is_err());
+    }
+}
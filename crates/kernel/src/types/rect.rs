@@ -2,7 +2,7 @@
 
 //! Basic manipulation of rectangles.
 
-use std::cmp;
+use std::{cmp, fmt, num::ParseIntError, str::FromStr};
 
 /// A rectangular region of non-zero width and height.
 /// # Examples
@@ -140,6 +140,92 @@ impl Region<f32> for Rect {
     }
 }
 
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}{:+}{:+}",
+            self.width, self.height, self.left, self.top
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RectParseError {
+    #[error("Invalid format (expected `WIDTHxHEIGHT+X+Y`)")]
+    InvalidFormat,
+    #[error("Width and height must be strictly positive")]
+    EmptyRect,
+    #[error(transparent)]
+    ParseInt(#[from] ParseIntError),
+}
+
+/// Parses a rectangle in X11 geometry format (`WIDTHxHEIGHT+X+Y`).
+///
+/// The offset part can be omitted, in which case the rectangle is placed at the origin.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer_kernel::types::rect::Rect;
+///
+/// let rect = "640x480+10+20".parse::<Rect>().unwrap();
+/// assert_eq!(rect, Rect::at(10, 20).of_size(640, 480));
+///
+/// let rect = "640x480".parse::<Rect>().unwrap();
+/// assert_eq!(rect, Rect::at(0, 0).of_size(640, 480));
+/// ```
+impl FromStr for Rect {
+    type Err = RectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let offset_start = s.find(['+', '-']).unwrap_or(s.len());
+        let (size, offset) = s.split_at(offset_start);
+
+        let (width, height) = size.split_once('x').ok_or(Self::Err::InvalidFormat)?;
+        let width = width.parse::<u32>()?;
+        let height = height.parse::<u32>()?;
+        if width == 0 || height == 0 {
+            return Err(Self::Err::EmptyRect);
+        }
+
+        let (x, y) = if offset.is_empty() {
+            (0, 0)
+        } else {
+            let y_start = offset[1..]
+                .find(['+', '-'])
+                .ok_or(Self::Err::InvalidFormat)?
+                + 1;
+            let (x, y) = offset.split_at(y_start);
+            (x.parse::<i32>()?, y.parse::<i32>()?)
+        };
+
+        Ok(Rect::at(x, y).of_size(width, height))
+    }
+}
+
+/// Serialized in X11 geometry format (`WIDTHxHEIGHT+X+Y`).
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rect {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Rect {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| serde::de::Error::custom(format!("invalid rect `{s}`: {e}")))
+    }
+}
+
 /// Position of the top left of a rectangle.
 /// Only used when building a [`Rect`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -184,6 +270,24 @@ mod tests {
         assert!(!r.contains(11, 10));
     }
 
+    #[test]
+    fn test_parse() {
+        let r = "6x7+4+5".parse::<Rect>().unwrap();
+        assert_eq!(r, Rect::at(4, 5).of_size(6, 7));
+        assert_eq!(r.to_string(), "6x7+4+5");
+
+        let r = "6x7-4+5".parse::<Rect>().unwrap();
+        assert_eq!(r, Rect::at(-4, 5).of_size(6, 7));
+        assert_eq!(r.to_string(), "6x7-4+5");
+
+        let r = "6x7".parse::<Rect>().unwrap();
+        assert_eq!(r, Rect::at(0, 0).of_size(6, 7));
+
+        assert!("6x7+4".parse::<Rect>().is_err());
+        assert!("0x7+4+5".parse::<Rect>().is_err());
+        assert!("6+4+5".parse::<Rect>().is_err());
+    }
+
     #[test]
     fn test_contains_f32() {
         let r = Rect::at(5, 5).of_size(6, 6);
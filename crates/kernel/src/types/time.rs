@@ -151,31 +151,56 @@ impl FromStr for TimestampRange {
     }
 }
 
+/// Position of a frame in a video.
+///
+/// The index is computed assuming a constant frame rate,
+/// and the original presentation timestamp (PTS) of the frame may be carried alongside it.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct FramePosition {
     idx: usize,
+    /// Timestamp of the index on the constant frame rate grid
     ts: Timestamp,
+    pts: Option<Timestamp>,
 }
 
 impl fmt::Display for FramePosition {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}[{}]", self.ts, self.idx)
+        write!(f, "{}[{}]", self.timestamp(), self.idx)
     }
 }
 
 impl FramePosition {
     pub fn new(idx: usize, ts: Timestamp) -> Self {
-        Self { idx, ts }
+        Self { idx, ts, pts: None }
+    }
+
+    /// Returns the position carrying the original PTS of the frame.
+    pub fn with_pts(self, pts: Timestamp) -> Self {
+        Self {
+            pts: Some(pts),
+            ..self
+        }
     }
 
     pub fn index(&self) -> usize {
         self.idx
     }
 
+    /// Returns the timestamp used for outputs, which is the PTS if carried.
     pub fn timestamp(&self) -> Timestamp {
+        self.pts.unwrap_or(self.ts)
+    }
+
+    /// Returns the timestamp of the index on the constant frame rate grid.
+    pub fn grid_timestamp(&self) -> Timestamp {
         self.ts
     }
 
+    pub fn pts(&self) -> Option<Timestamp> {
+        self.pts
+    }
+
+    /// Returns the position of the next frame on the grid, without PTS.
     pub fn next(&self, sec_per_frame: Duration) -> FramePosition {
         Self::new(self.idx + 1, self.ts + sec_per_frame)
     }
@@ -194,4 +219,21 @@ mod tests {
         assert_eq!(p("01:23:45"), "01:23:45.000");
         assert_eq!(p("3672"), "01:01:12.000");
     }
+
+    #[test]
+    fn frame_position_pts() {
+        let ts = |ms| Timestamp::new(Ratio::new(ms, 1000));
+        let pos = FramePosition::new(1, Timestamp::new(Ratio::new(1001, 30000)));
+        let pos = pos.with_pts(ts(33));
+        assert_eq!(pos.timestamp(), ts(33));
+        assert_eq!(
+            pos.grid_timestamp(),
+            Timestamp::new(Ratio::new(1001, 30000))
+        );
+        assert_eq!(pos.to_string(), "00:00:00.033[1]");
+
+        let next = pos.next(Duration::new(Ratio::new(1001, 30000)));
+        assert_eq!(next.pts(), None);
+        assert_eq!(next.timestamp(), Timestamp::new(Ratio::new(2002, 30000)));
+    }
 }
@@ -0,0 +1,149 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+
+use crate::{Error, Result};
+
+use super::Frame;
+
+/// Source of decoded video frames used by [`VideoCapture`](super::VideoCapture).
+///
+/// Backends only perform raw seeking and decoding.
+/// Frame-accurate positioning is handled by `VideoCapture`.
+pub trait CaptureBackend: fmt::Debug {
+    fn duration(&self) -> Duration;
+    fn fps(&self) -> Ratio<i64>;
+    fn frames(&self) -> usize;
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+
+    /// Returns `true` if frames are produced in real time (e.g. screen capture).
+    fn is_live(&self) -> bool {
+        false
+    }
+
+    /// Seeks to the key frame at or before `ts`.
+    fn seek(&mut self, ts: Timestamp) -> Result<()>;
+
+    /// Decodes the next frame.
+    ///
+    /// Returns `None` if the end of the stream is reached.
+    fn decode(&mut self) -> Result<Option<DecodedFrame>>;
+
+    /// Writes the last decoded frame into `frame` as a RGB24 image.
+    fn write_frame(&mut self, frame: &mut Frame) -> Result<()>;
+}
+
+/// Frame decoded by [`CaptureBackend::decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedFrame {
+    /// (Rough) presentation timestamp, or `None` if the container does not have it
+    pub pts: Option<Timestamp>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    #[default]
+    Ffmpeg,
+    MediaFoundation,
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendKind::Ffmpeg => write!(f, "ffmpeg"),
+            BackendKind::MediaFoundation => write!(f, "media-foundation"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown capture backend: {0}")]
+pub struct BackendKindParseError(String);
+
+impl FromStr for BackendKind {
+    type Err = BackendKindParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ffmpeg" => Ok(Self::Ffmpeg),
+            "media-foundation" | "mf" => Ok(Self::MediaFoundation),
+            _ => Err(BackendKindParseError(s.to_owned())),
+        }
+    }
+}
+
+impl BackendKind {
+    pub fn is_available(&self) -> bool {
+        match self {
+            BackendKind::Ffmpeg => true,
+            BackendKind::MediaFoundation => cfg!(all(windows, feature = "media-foundation")),
+        }
+    }
+
+    pub(super) fn open(
+        &self,
+        file: &Path,
+        hwaccel: Option<HwAccel>,
+    ) -> Result<Box<dyn CaptureBackend>> {
+        match self {
+            BackendKind::Ffmpeg => Ok(Box::new(super::ffmpeg_backend::FfmpegBackend::open(
+                file, hwaccel,
+            )?)),
+            #[cfg(all(windows, feature = "media-foundation"))]
+            BackendKind::MediaFoundation => {
+                if let Some(hwaccel) = hwaccel {
+                    tracing::warn!(%hwaccel, "hardware acceleration is ignored by this backend");
+                }
+                Ok(Box::new(
+                    super::media_foundation::MediaFoundationBackend::open(file)?,
+                ))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::UnsupportedBackend(*self)),
+        }
+    }
+}
+
+/// Hardware-accelerated decoder requested from FFmpeg.
+///
+/// Videos are decoded by the CPU if the decoder is not available on the machine or for the codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HwAccel {
+    /// Any device supported by the codec, in the order FFmpeg lists them
+    Auto,
+    Vaapi,
+    /// NVIDIA NVDEC, through a CUDA device
+    Nvdec,
+    VideoToolbox,
+}
+
+impl fmt::Display for HwAccel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HwAccel::Auto => write!(f, "auto"),
+            HwAccel::Vaapi => write!(f, "vaapi"),
+            HwAccel::Nvdec => write!(f, "nvdec"),
+            HwAccel::VideoToolbox => write!(f, "videotoolbox"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown hardware acceleration: {0}")]
+pub struct HwAccelParseError(String);
+
+impl FromStr for HwAccel {
+    type Err = HwAccelParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "vaapi" => Ok(Self::Vaapi),
+            "nvdec" | "cuda" => Ok(Self::Nvdec),
+            "videotoolbox" => Ok(Self::VideoToolbox),
+            _ => Err(HwAccelParseError(s.to_owned())),
+        }
+    }
+}
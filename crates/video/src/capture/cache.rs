@@ -0,0 +1,117 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    process,
+    time::UNIX_EPOCH,
+};
+
+use image::{
+    codecs::png::{CompressionType, FilterType, PngDecoder, PngEncoder},
+    ColorType, ExtendedColorType, ImageDecoder as _, ImageEncoder as _,
+};
+
+use super::{Frame, Result};
+
+/// On-disk cache of decoded frames of a video, keyed by frame indices.
+///
+/// Frames are stored as PNG files of RGB24 images, in a directory per video under the cache directory.
+/// The directory is named by a hash of the path, the size and the modification time of the video,
+/// so that frames of a modified video are decoded again instead of being served stale.
+///
+/// Cached frames have no luma plane, so detectors read luma from their RGB pixels.
+#[derive(Debug, Clone)]
+pub struct FrameCache {
+    dir: PathBuf,
+}
+
+impl FrameCache {
+    /// Returns the cache of frames of `file` under `cache_dir`, creating the directory if missing.
+    pub fn open(cache_dir: &Path, file: &Path) -> Result<Self> {
+        let metadata = fs::metadata(file)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut hash = Fnv1a::new();
+        hash.write(fs::canonicalize(file)?.as_os_str().as_encoded_bytes());
+        hash.write(&metadata.len().to_le_bytes());
+        hash.write(&modified.as_nanos().to_le_bytes());
+
+        let dir = cache_dir.join(format!("{:016x}", hash.0));
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, idx: usize) -> PathBuf {
+        self.dir.join(format!("{idx:08}.png"))
+    }
+
+    /// Reads the frame at `idx` into `frame`, returning `false` if it is not cached.
+    ///
+    /// Only the pixels are written, and the position is left to the caller.
+    pub(super) fn load(&self, idx: usize, frame: &mut Frame) -> Result<bool> {
+        let file = match File::open(self.path(idx)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        let decoder = PngDecoder::new(BufReader::new(file))?;
+        if decoder.color_type() != ColorType::Rgb8 {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidData, "cached frame is not RGB24").into(),
+            );
+        }
+        let (width, height) = decoder.dimensions();
+        let buf = frame.rgb_buffer_mut(width, height);
+        decoder.read_image(buf)?;
+        Ok(true)
+    }
+
+    /// Writes the pixels of `frame` as the frame at `idx`.
+    ///
+    /// The image is written to a temporary file and renamed,
+    /// so that interrupted writes and concurrent processes never leave broken frames.
+    pub(super) fn store(&self, idx: usize, frame: &Frame) -> Result<()> {
+        let path = self.path(idx);
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        let (width, height) = (frame.width(), frame.height());
+
+        let encoder = PngEncoder::new_with_quality(
+            BufWriter::new(File::create(&tmp)?),
+            CompressionType::Fast,
+            FilterType::Adaptive,
+        );
+        let len = width as usize * height as usize * 3;
+        encoder.write_image(
+            &frame.rgb_data(0)?[..len],
+            width,
+            height,
+            ExtendedColorType::Rgb8,
+        )?;
+
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// 64-bit FNV-1a hash, whose values are stable across builds unlike the hasher of `std`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    cell::RefCell,
+    ffi::CStr,
+    path::Path,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use ffmpeg::{
+    codec, decoder,
+    ffi::{self, AVHWDeviceType},
+    format, frame, media,
+    rescale::TIME_BASE,
+    software::scaling,
+    threading, Packet, Stream,
+};
+use num_rational::Ratio;
+use tracing::{debug, info, trace, warn};
+
+use crate::Result;
+
+use super::{CaptureBackend, DecodedFrame, Frame, HwAccel, LiveSource, ToRatio as _};
+
+#[derive(custom_debug::Debug)]
+pub(super) struct FfmpegBackend {
+    dur: Duration,
+    fps: Ratio<i64>,
+    frames: usize,
+    stream_time_base: Ratio<i64>,
+    width: u32,
+    height: u32,
+
+    #[debug(skip)]
+    ictx: format::context::Input,
+    video_stream_idx: usize,
+    #[debug(skip)]
+    decoder: decoder::Video,
+    #[debug(skip)]
+    decoded: frame::Video,
+    packet_sent: bool,
+
+    /// Frames are read from a live source
+    live: bool,
+    /// PTS of the first frame of a live source, from which timestamps are measured
+    pts_origin: Option<Ratio<i64>>,
+}
+
+impl FfmpegBackend {
+    pub(super) fn open(file: &Path, hwaccel: Option<HwAccel>) -> Result<Self> {
+        let ictx = crate::open_input(file)?;
+        Self::from_input(ictx, hwaccel, None)
+    }
+
+    /// Opens a live source, which ends after `duration`.
+    pub(super) fn open_live(source: &LiveSource, duration: Duration) -> Result<Self> {
+        let ictx = source.open_input()?;
+        Self::from_input(ictx, None, Some(duration))
+    }
+
+    /// Opens the decoder of the best video stream of `ictx`, which is live if `live_duration` is given.
+    fn from_input(
+        mut ictx: format::context::Input,
+        hwaccel: Option<HwAccel>,
+        live_duration: Option<Duration>,
+    ) -> Result<Self> {
+        let video_stream_idx = ictx
+            .streams()
+            .best(media::Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?
+            .index();
+
+        let mut context_decoder = ffmpeg::codec::context::Context::from_parameters(
+            ictx.stream(video_stream_idx).unwrap().parameters(),
+        )?;
+        #[allow(clippy::needless_update)]
+        context_decoder.set_threading(codec::threading::Config {
+            kind: threading::Type::Frame,
+            count: 16,
+            ..Default::default() // for FFMPEG other than 6.0
+        });
+        if let Some(hwaccel) = hwaccel {
+            match attach_hw_device(&mut context_decoder, hwaccel) {
+                Some(device) => info!(%hwaccel, device, "decoding with hardware acceleration"),
+                None => {
+                    warn!(%hwaccel, "hardware acceleration is not available, decoding by the CPU")
+                }
+            }
+        }
+        let decoder = context_decoder.decoder().video()?;
+
+        // Frames are converted lazily, so fail early if the format is not convertible
+        RgbScaler::new((decoder.format(), decoder.width(), decoder.height(), 1))?;
+
+        let decoded = frame::Video::empty();
+
+        let fps = get_fps(&mut ictx, video_stream_idx).unwrap_or(Ratio::ONE);
+        let (frames, duration) = match live_duration {
+            // Live sources have no duration, and are read until stopped
+            Some(duration) => {
+                let frames = (duration.as_ratio() * fps).floor().to_integer() as usize;
+                (frames, duration)
+            }
+            None => {
+                let frames = get_frames(&mut ictx, video_stream_idx).unwrap_or(1) as usize;
+                let duration = Duration::new(
+                    get_duration(&ictx, video_stream_idx)
+                        .unwrap_or_else(|| Ratio::from_integer(frames as i64) / fps),
+                );
+                (frames, duration)
+            }
+        };
+        let stream_time_base = ictx
+            .stream(video_stream_idx)
+            .unwrap()
+            .time_base()
+            .to_ratio();
+
+        debug!(%duration, %fps, %frames);
+
+        Ok(Self {
+            fps,
+            dur: duration,
+            frames,
+            stream_time_base,
+            width: decoder.width(),
+            height: decoder.height(),
+
+            ictx,
+            video_stream_idx,
+            decoder,
+            decoded,
+            packet_sent: false,
+
+            live: live_duration.is_some(),
+            pts_origin: None,
+        })
+    }
+
+    fn read_video_packet(&mut self) -> Option<(Stream, Packet)> {
+        let video_stream_index = self.video_stream_idx;
+        self.ictx
+            .packets()
+            .find(|(stream, _packet)| stream.index() == video_stream_index)
+    }
+}
+
+impl CaptureBackend for FfmpegBackend {
+    fn duration(&self) -> Duration {
+        self.dur
+    }
+
+    fn fps(&self) -> Ratio<i64> {
+        self.fps
+    }
+
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn is_live(&self) -> bool {
+        self.live
+    }
+
+    fn seek(&mut self, ts: Timestamp) -> Result<()> {
+        if self.live {
+            // Live sources cannot be rewound. Frames before `ts` are skipped by `VideoCapture`.
+            trace!(%ts, "seek is ignored for live source");
+            return Ok(());
+        }
+        let seek_ts = (ts.as_ratio() / TIME_BASE.to_ratio()).floor().to_integer();
+        trace!(%ts, %seek_ts);
+
+        self.ictx.seek(seek_ts, ..seek_ts)?;
+        self.decoder.flush();
+        self.packet_sent = false;
+
+        Ok(())
+    }
+
+    fn decode(&mut self) -> Result<Option<DecodedFrame>> {
+        loop {
+            if !self.packet_sent {
+                match self.read_video_packet() {
+                    Some((_stream, packet)) => self.decoder.send_packet(&packet)?,
+                    None => self.decoder.send_eof()?,
+                }
+                self.packet_sent = true;
+            }
+
+            match self.decoder.receive_frame(&mut self.decoded) {
+                Ok(()) => {
+                    let pts = self.decoded.timestamp().map(|ts| {
+                        let pts = Ratio::from_integer(ts) * self.stream_time_base;
+                        // Devices stamp frames with the system clock, and streams with the time since they started
+                        let origin = if self.live {
+                            *self.pts_origin.get_or_insert(pts)
+                        } else {
+                            Ratio::ZERO
+                        };
+                        Timestamp::new(pts - origin)
+                    });
+                    return Ok(Some(DecodedFrame { pts }));
+                }
+                Err(ffmpeg::Error::Eof) => {
+                    trace!("EOF reached");
+                    return Ok(None);
+                }
+                Err(ffmpeg::Error::Other {
+                    errno: libc::EAGAIN,
+                }) => {
+                    self.packet_sent = false;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn write_frame(&mut self, frame: &mut Frame) -> Result<()> {
+        // SAFETY: `decoded` is a valid frame
+        if unsafe { (*self.decoded.as_ptr()).hw_frames_ctx.is_null() } {
+            return frame.set_decoded(&self.decoded);
+        }
+
+        // Frames decoded by hardware are in the memory of the device
+        let mut transferred = frame::Video::empty();
+        // SAFETY: both are valid frames, and `transferred` is allocated by FFmpeg
+        let ret = unsafe {
+            let ret =
+                ffi::av_hwframe_transfer_data(transferred.as_mut_ptr(), self.decoded.as_ptr(), 0);
+            if ret < 0 {
+                ret
+            } else {
+                ffi::av_frame_copy_props(transferred.as_mut_ptr(), self.decoded.as_ptr())
+            }
+        };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        frame.set_decoded(&transferred)
+    }
+}
+
+/// Attaches a device of `hwaccel` to the decoder, which must not be opened yet.
+///
+/// Returns the name of the device type, or `None` if no device of `hwaccel` supported by the codec can be opened.
+fn attach_hw_device(context: &mut codec::Context, hwaccel: HwAccel) -> Option<&'static str> {
+    let codec = decoder::find(context.id())?;
+    for index in 0.. {
+        // SAFETY: configs are static data of the codec, and the last one is followed by null
+        let config = unsafe { ffi::avcodec_get_hw_config(codec.as_ptr(), index) };
+        if config.is_null() {
+            break;
+        }
+        // SAFETY: `config` is non-null
+        let (methods, ty) = unsafe { ((*config).methods, (*config).device_type) };
+        if methods & ffi::AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX as i32 == 0 {
+            continue;
+        }
+        let requested = match hwaccel {
+            HwAccel::Auto => true,
+            HwAccel::Vaapi => ty == AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI,
+            HwAccel::Nvdec => ty == AVHWDeviceType::AV_HWDEVICE_TYPE_CUDA,
+            HwAccel::VideoToolbox => ty == AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX,
+        };
+        if !requested {
+            continue;
+        }
+        // SAFETY: names of device types are static strings, or null for unknown types
+        let name = unsafe { ffi::av_hwdevice_get_type_name(ty) };
+        let name = if name.is_null() {
+            "unknown"
+        } else {
+            // SAFETY: `name` is a non-null, nul-terminated static string
+            unsafe { CStr::from_ptr(name) }
+                .to_str()
+                .unwrap_or("unknown")
+        };
+
+        let mut device = ptr::null_mut();
+        // SAFETY: the default device of the type is opened
+        let ret = unsafe {
+            ffi::av_hwdevice_ctx_create(&mut device, ty, ptr::null(), ptr::null_mut(), 0)
+        };
+        if ret < 0 {
+            debug!(device = name, err = %ffmpeg::Error::from(ret), "failed to open a device");
+            continue;
+        }
+        // SAFETY: the decoder takes the ownership of the reference, and releases it when freed
+        unsafe { (*context.as_mut_ptr()).hw_device_ctx = device };
+        return Some(name);
+    }
+    None
+}
+
+/// Number of threads each scaler converts a frame with, see [`set_scale_threads`]
+static SCALE_THREADS: AtomicUsize = AtomicUsize::new(1);
+
+/// Sets the number of threads converting each decoded frame to RGB24, `0` for the number of CPUs.
+///
+/// Frames are usually converted in parallel by the threads detecting components,
+/// so more threads only help when CPUs are left, e.g. in a single segment of a 4K video.
+/// Scalers created after the call use the new number.
+pub fn set_scale_threads(threads: usize) {
+    SCALE_THREADS.store(threads, Ordering::Relaxed);
+}
+
+/// Format, size of frames and number of threads of a scaler
+type ScalerKey = (format::Pixel, u32, u32, usize);
+
+/// Scaler of libswscale converting frames to RGB24 of the same size.
+///
+/// Unlike [`ffmpeg::software::scaling::Context`], options are set before the scaler is initialized,
+/// so that frames are converted by slice threads of libswscale.
+struct RgbScaler {
+    ptr: NonNull<ffi::SwsContext>,
+    width: u32,
+    height: u32,
+}
+
+impl RgbScaler {
+    fn new((format, width, height, threads): ScalerKey) -> Result<Self> {
+        // SAFETY: the allocated context is freed on drop
+        let ptr =
+            NonNull::new(unsafe { ffi::sws_alloc_context() }).ok_or(ffmpeg::Error::Other {
+                errno: libc::ENOMEM,
+            })?;
+        let scaler = Self { ptr, width, height };
+        let pixel = |format| ffi::AVPixelFormat::from(format) as i64;
+        let options = [
+            (c"srcw", i64::from(width)),
+            (c"srch", i64::from(height)),
+            (c"src_format", pixel(format)),
+            (c"dstw", i64::from(width)),
+            (c"dsth", i64::from(height)),
+            (c"dst_format", pixel(format::Pixel::RGB24)),
+            (c"sws_flags", i64::from(scaling::Flags::BILINEAR.bits())),
+            (c"threads", threads as i64),
+        ];
+        for (name, value) in options {
+            // SAFETY: the context is an AVClass-enabled struct, and `name` is nul-terminated
+            let ret = unsafe { ffi::av_opt_set_int(ptr.as_ptr().cast(), name.as_ptr(), value, 0) };
+            if ret < 0 {
+                return Err(ffmpeg::Error::from(ret).into());
+            }
+        }
+        // SAFETY: the context is allocated and its options are set
+        let ret = unsafe { ffi::sws_init_context(ptr.as_ptr(), ptr::null_mut(), ptr::null_mut()) };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        Ok(scaler)
+    }
+
+    fn run(&mut self, input: &frame::Video) -> Result<frame::Video> {
+        let mut rgb = frame::Video::new(format::Pixel::RGB24, self.width, self.height);
+        // SAFETY: both frames are allocated with the format and size the context is initialized with
+        let ret =
+            unsafe { ffi::sws_scale_frame(self.ptr.as_ptr(), rgb.as_mut_ptr(), input.as_ptr()) };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        Ok(rgb)
+    }
+}
+
+impl Drop for RgbScaler {
+    fn drop(&mut self) {
+        // SAFETY: the context is allocated by `sws_alloc_context` and not used after this
+        unsafe { ffi::sws_freeContext(self.ptr.as_ptr()) };
+    }
+}
+
+thread_local! {
+    /// Scaler converting decoded frames to RGB24 in the thread, reused while frames have the same format and size
+    static RGB_SCALER: RefCell<Option<(ScalerKey, RgbScaler)>> = const { RefCell::new(None) };
+}
+
+/// Converts a decoded frame to RGB24.
+pub(super) fn to_rgb(decoded: &frame::Video) -> Result<frame::Video> {
+    let key = (
+        decoded.format(),
+        decoded.width(),
+        decoded.height(),
+        SCALE_THREADS.load(Ordering::Relaxed),
+    );
+    RGB_SCALER.with_borrow_mut(|cached| {
+        let scaler = match cached {
+            Some((cached_key, scaler)) if *cached_key == key => scaler,
+            _ => {
+                // Free the previous scaler and its threads first
+                *cached = None;
+                &mut cached.insert((key, RgbScaler::new(key)?)).1
+            }
+        };
+        scaler.run(decoded)
+    })
+}
+
+fn get_duration(ictx: &format::context::Input, stream_idx: usize) -> Option<Ratio<i64>> {
+    // Borrow from OpenCV's implementation
+    // https://github.com/opencv/opencv/blob/1ca526dcdb9c30600c70537e279f0c672057a1b9/modules/videoio/src/cap_ffmpeg_impl.hpp#L1892
+
+    let duration = Ratio::from(ictx.duration()) * TIME_BASE.to_ratio();
+    if duration > Ratio::ZERO {
+        return Some(duration);
+    }
+
+    let stream = ictx.stream(stream_idx)?;
+    let duration = Ratio::from(stream.duration()) * stream.time_base().to_ratio();
+    if duration > Ratio::ZERO {
+        return Some(duration);
+    }
+
+    None
+}
+fn get_fps(ictx: &mut format::context::Input, stream_idx: usize) -> Option<Ratio<i64>> {
+    // Borrow from OpenCV's implementation
+    // https://github.com/opencv/opencv/blob/1ca526dcdb9c30600c70537e279f0c672057a1b9/modules/videoio/src/cap_ffmpeg_impl.hpp#L1909
+
+    let fps = ictx.stream(stream_idx)?.avg_frame_rate().to_ratio();
+    if fps > Ratio::ZERO {
+        return Some(fps);
+    }
+
+    let fps = unsafe {
+        ffmpeg::ffi::av_guess_frame_rate(
+            ictx.as_mut_ptr(),
+            ictx.stream_mut(stream_idx)?.as_mut_ptr(),
+            ptr::null_mut(),
+        )
+    }
+    .to_ratio();
+    if fps > Ratio::ZERO {
+        return Some(fps);
+    }
+
+    let fps = ictx.stream(stream_idx)?.time_base().invert().to_ratio();
+    if fps > Ratio::ZERO {
+        return Some(fps);
+    }
+
+    None
+}
+
+fn get_frames(ictx: &mut format::context::Input, stream_idx: usize) -> Option<i64> {
+    // Borrow from OpenCV's implementation
+    // https://github.com/opencv/opencv/blob/1ca526dcdb9c30600c70537e279f0c672057a1b9/modules/videoio/src/cap_ffmpeg_impl.hpp#L1932
+
+    let frames = ictx.stream(stream_idx)?.frames();
+    if frames > 0 {
+        return Some(frames);
+    }
+
+    let frames = (get_duration(ictx, stream_idx)? * get_fps(ictx, stream_idx)?)
+        .round()
+        .to_integer();
+    if frames > 0 {
+        return Some(frames);
+    }
+
+    None
+}
@@ -0,0 +1,219 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+use tracing::{debug, trace};
+
+use crate::{Error, Result};
+
+use super::{ffmpeg_backend::FfmpegBackend, CaptureBackend, DecodedFrame, Frame};
+
+/// Frame rate assumed for sequences of images unless specified, as frames exported from 30 fps videos
+pub const DEFAULT_IMAGE_FPS: Ratio<i64> = Ratio::new_raw(30, 1);
+
+/// Extensions of image files read as frames
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// Backend reading still images as frames of a video, such as screenshots or frames exported from a video.
+///
+/// The input is one of:
+///
+/// * a directory, whose PNG and JPEG files are read in the order of their names,
+/// * a printf-style pattern such as `frame%05d.png`, numbered consecutively from 0 or 1 as in FFmpeg,
+/// * a PNG or JPEG file, read as a video of a single frame.
+///
+/// Images have no timestamps, so the N-th image is the frame at N / `fps` seconds.
+/// All images must have the size of the first one.
+#[derive(custom_debug::Debug)]
+pub struct ImageSequenceBackend {
+    #[debug(skip)]
+    files: Vec<PathBuf>,
+    fps: Ratio<i64>,
+    width: u32,
+    height: u32,
+    next_idx: usize,
+
+    /// Decoder of the last decoded image
+    #[debug(skip)]
+    image: Option<FfmpegBackend>,
+}
+
+impl ImageSequenceBackend {
+    /// Returns `true` if `path` is read as a sequence of images instead of a video file.
+    pub fn is_image_sequence(path: &Path) -> bool {
+        path.is_dir() || parse_pattern(path).is_some() || is_image_file(path)
+    }
+
+    /// Opens the images of `path` placed at `fps`.
+    pub fn open(path: &Path, fps: Ratio<i64>) -> Result<Self> {
+        let files = list_images(path)?;
+        let first = files
+            .first()
+            .ok_or_else(|| Error::NoImages(path.to_owned()))?;
+        let image = FfmpegBackend::open(first, None)?;
+        let (width, height) = (image.width(), image.height());
+
+        debug!(path = %path.display(), images = files.len(), %fps, width, height);
+
+        Ok(Self {
+            files,
+            fps,
+            width,
+            height,
+            next_idx: 0,
+            image: None,
+        })
+    }
+}
+
+impl CaptureBackend for ImageSequenceBackend {
+    fn duration(&self) -> Duration {
+        Duration::new(Ratio::from_integer(self.files.len() as i64) / self.fps)
+    }
+
+    fn fps(&self) -> Ratio<i64> {
+        self.fps
+    }
+
+    fn frames(&self) -> usize {
+        self.files.len()
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn seek(&mut self, ts: Timestamp) -> Result<()> {
+        // Every image is a key frame, so seeks are exact
+        self.next_idx = (ts.as_ratio() * self.fps).floor().to_integer().max(0) as usize;
+        trace!(%ts, idx = self.next_idx);
+        Ok(())
+    }
+
+    fn decode(&mut self) -> Result<Option<DecodedFrame>> {
+        let Some(file) = self.files.get(self.next_idx) else {
+            return Ok(None);
+        };
+        let mut image = FfmpegBackend::open(file, None)?;
+        if (image.width(), image.height()) != (self.width, self.height) {
+            return Err(Error::ImageSizeMismatch(file.clone()));
+        }
+        if image.decode()?.is_none() {
+            return Err(ffmpeg::Error::InvalidData.into());
+        }
+        self.image = Some(image);
+
+        let pts = Timestamp::new(Ratio::from_integer(self.next_idx as i64) / self.fps);
+        self.next_idx += 1;
+        Ok(Some(DecodedFrame { pts: Some(pts) }))
+    }
+
+    fn write_frame(&mut self, frame: &mut Frame) -> Result<()> {
+        match &mut self.image {
+            Some(image) => image.write_frame(frame),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|image_ext| ext.eq_ignore_ascii_case(image_ext))
+        })
+}
+
+/// Splits the file name of a pattern into the parts before and after `%d` or `%0Nd`, and the width of numbers.
+fn parse_pattern(path: &Path) -> Option<(&str, usize, &str)> {
+    let name = path.file_name()?.to_str()?;
+    let (prefix, rest) = name.split_once('%')?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let suffix = rest[digits..].strip_prefix('d')?;
+    let width = match &rest[..digits] {
+        "" => 0,
+        width => width.strip_prefix('0')?.parse().ok()?,
+    };
+    Some((prefix, width, suffix))
+}
+
+/// Returns the image files of `path`, in the order of frames.
+fn list_images(path: &Path) -> Result<Vec<PathBuf>> {
+    if let Some((prefix, width, suffix)) = parse_pattern(path) {
+        let file = |idx: usize| path.with_file_name(format!("{prefix}{idx:0width$}{suffix}"));
+        // FFmpeg also finds the first image among these numbers
+        let Some(first) = (0..5).find(|&idx| file(idx).is_file()) else {
+            return Ok(vec![]);
+        };
+        return Ok((first..)
+            .map(file)
+            .take_while(|file| file.is_file())
+            .collect());
+    }
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.is_file() && is_image_file(&file) {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_images_of_patterns() {
+        assert_eq!(
+            parse_pattern(Path::new("out/frame%05d.png")),
+            Some(("frame", 5, ".png"))
+        );
+        assert_eq!(parse_pattern(Path::new("%d.jpg")), Some(("", 0, ".jpg")));
+        assert_eq!(parse_pattern(Path::new("100%.png")), None);
+        assert_eq!(parse_pattern(Path::new("frame%5d.png")), None);
+
+        let dir =
+            std::env::temp_dir().join(format!("elden-analyzer-images-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["f001.png", "f002.png", "f003.png", "f005.png", "notes.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let names = |files: Vec<PathBuf>| {
+            files
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        // Numbers of patterns are consecutive from the first image
+        let files = list_images(&dir.join("f%03d.png")).unwrap();
+        assert_eq!(names(files), ["f001.png", "f002.png", "f003.png"]);
+        let files = list_images(&dir).unwrap();
+        assert_eq!(
+            names(files),
+            ["f001.png", "f002.png", "f003.png", "f005.png"]
+        );
+        assert!(list_images(&dir.join("g%03d.png")).unwrap().is_empty());
+        assert!(ImageSequenceBackend::is_image_sequence(&dir));
+        assert!(!ImageSequenceBackend::is_image_sequence(Path::new(
+            "run.mp4"
+        )));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
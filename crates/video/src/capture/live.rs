@@ -0,0 +1,91 @@
+use std::{fmt, sync::Once};
+
+use ffmpeg::{device, format, Dictionary};
+use tracing::debug;
+
+use crate::{Error, Result};
+
+/// Input format of capture devices on this platform, as `ffmpeg -f`.
+pub const DEFAULT_DEVICE_FORMAT: &str = if cfg!(target_os = "macos") {
+    "avfoundation"
+} else if cfg!(windows) {
+    "dshow"
+} else {
+    "v4l2"
+};
+
+/// Live source read by FFmpeg in real time: a capture device or a stream URL.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer_video::capture::LiveSource;
+///
+/// let source = LiveSource::new("srt://0.0.0.0:9000?mode=listener", None);
+/// assert_eq!(source.format, None);
+///
+/// let source = LiveSource::new("video=OBS Virtual Camera", Some("dshow"));
+/// assert_eq!(source.to_string(), "dshow:video=OBS Virtual Camera");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveSource {
+    /// Input format of the device, such as `v4l2`, `avfoundation` or `dshow`, or `None` for URLs
+    pub format: Option<String>,
+    /// Device name or URL, as given to `ffmpeg -i`
+    pub url: String,
+    /// Options of the input format or the protocol, such as `video_size` and `framerate` of devices
+    pub options: Vec<(String, String)>,
+}
+
+impl LiveSource {
+    /// Returns the source of `input`, which is a URL if it has a scheme such as `rtmp://`,
+    /// or a device read by `format` (default: [`DEFAULT_DEVICE_FORMAT`]) otherwise.
+    pub fn new(input: &str, format: Option<&str>) -> Self {
+        let format = match format {
+            Some(format) => Some(format.to_owned()),
+            None if input.contains("://") => None,
+            None => Some(DEFAULT_DEVICE_FORMAT.to_owned()),
+        };
+        Self {
+            format,
+            url: input.to_owned(),
+            options: vec![],
+        }
+    }
+
+    /// Opens the input with the options, without buffering frames so that they are analyzed as soon as possible.
+    pub(super) fn open_input(&self) -> Result<format::context::Input> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            device::register_all();
+            format::network::init();
+        });
+
+        let mut options = Dictionary::new();
+        options.set("fflags", "nobuffer");
+        for (key, value) in &self.options {
+            options.set(key, value);
+        }
+        debug!(source = %self, options = ?self.options, "opening live source");
+
+        let Some(name) = &self.format else {
+            return Ok(format::input_with_dictionary(&self.url, options)?);
+        };
+        let input = device::input::video()
+            .chain(device::input::audio())
+            .find(|input| input.name() == name)
+            .ok_or_else(|| Error::UnknownInputFormat(name.clone()))?;
+        match format::open_with(&self.url, &format::Format::Input(input), options)? {
+            format::context::Context::Input(input) => Ok(input),
+            format::context::Context::Output(_) => unreachable!("input format opens an input"),
+        }
+    }
+}
+
+impl fmt::Display for LiveSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.format {
+            Some(format) => write!(f, "{format}:{}", self.url),
+            None => write!(f, "{}", self.url),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{mem::ManuallyDrop, path::Path, ptr, slice};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+use tracing::{debug, trace};
+use windows::{
+    core::{Interface as _, HSTRING},
+    Win32::{
+        Media::MediaFoundation::{
+            IMF2DBuffer, IMFAttributes, IMFSample, IMFSourceReader, MFCreateAttributes,
+            MFCreateMediaType, MFCreateSourceReaderFromURL, MFMediaType_Video, MFStartup,
+            MFVideoFormat_RGB32, MFSTARTUP_FULL, MF_MT_DEFAULT_STRIDE, MF_MT_FRAME_RATE,
+            MF_MT_FRAME_SIZE, MF_MT_MAJOR_TYPE, MF_MT_SUBTYPE, MF_PD_DURATION,
+            MF_SOURCE_READERF_ENDOFSTREAM, MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING,
+            MF_SOURCE_READER_FIRST_VIDEO_STREAM, MF_SOURCE_READER_MEDIASOURCE, MF_VERSION,
+        },
+        System::{
+            Com::{
+                CoInitializeEx,
+                StructuredStorage::{
+                    PropVariantToUInt64, PROPVARIANT, PROPVARIANT_0, PROPVARIANT_0_0,
+                    PROPVARIANT_0_0_0,
+                },
+                COINIT_MULTITHREADED,
+            },
+            Variant::VT_I8,
+        },
+    },
+};
+
+use crate::Result;
+
+use super::{CaptureBackend, DecodedFrame, Frame};
+
+/// Media Foundation uses 100ns units for timestamps.
+const HNS_PER_SEC: i64 = 10_000_000;
+
+const VIDEO_STREAM: u32 = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+
+#[derive(custom_debug::Debug)]
+pub(super) struct MediaFoundationBackend {
+    dur: Duration,
+    fps: Ratio<i64>,
+    frames: usize,
+    width: u32,
+    height: u32,
+    stride: i32,
+
+    #[debug(skip)]
+    reader: IMFSourceReader,
+    #[debug(skip)]
+    sample: Option<IMFSample>,
+}
+
+impl MediaFoundationBackend {
+    pub(super) fn open(file: &Path) -> Result<Self> {
+        unsafe {
+            // `S_FALSE` is returned if COM is already initialized on this thread
+            CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+            MFStartup(MF_VERSION, MFSTARTUP_FULL)?;
+
+            let mut attributes: Option<IMFAttributes> = None;
+            MFCreateAttributes(&mut attributes, 1)?;
+            let attributes = attributes.unwrap();
+            // Let the source reader convert YUV to RGB32
+            attributes.SetUINT32(&MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, 1)?;
+
+            let url = HSTRING::from(file.as_os_str());
+            let reader = MFCreateSourceReaderFromURL(&url, &attributes)?;
+
+            let media_type = MFCreateMediaType()?;
+            media_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+            media_type.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)?;
+            reader.SetCurrentMediaType(VIDEO_STREAM, None, &media_type)?;
+
+            let current_type = reader.GetCurrentMediaType(VIDEO_STREAM)?;
+            let (width, height) = unpack_u32_pair(current_type.GetUINT64(&MF_MT_FRAME_SIZE)?);
+            let fps = match unpack_u32_pair(current_type.GetUINT64(&MF_MT_FRAME_RATE)?) {
+                (num, den) if num > 0 && den > 0 => Ratio::new(num.into(), den.into()),
+                _ => Ratio::ONE,
+            };
+            let stride = current_type
+                .GetUINT32(&MF_MT_DEFAULT_STRIDE)
+                .map(|stride| stride as i32)
+                .unwrap_or(width as i32 * 4);
+
+            let duration = reader
+                .GetPresentationAttribute(MF_SOURCE_READER_MEDIASOURCE.0 as u32, &MF_PD_DURATION)
+                .and_then(|value| PropVariantToUInt64(&value))
+                .map(|hns| Ratio::new(hns as i64, HNS_PER_SEC))
+                .unwrap_or(Ratio::ZERO);
+            let frames = (duration * fps).round().to_integer().max(1) as usize;
+            let duration = Duration::new(duration);
+
+            debug!(%duration, %fps, %frames, width, height, stride);
+
+            Ok(Self {
+                dur: duration,
+                fps,
+                frames,
+                width,
+                height,
+                stride,
+                reader,
+                sample: None,
+            })
+        }
+    }
+}
+
+impl CaptureBackend for MediaFoundationBackend {
+    fn duration(&self) -> Duration {
+        self.dur
+    }
+
+    fn fps(&self) -> Ratio<i64> {
+        self.fps
+    }
+
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn seek(&mut self, ts: Timestamp) -> Result<()> {
+        let seek_ts = (ts.as_ratio() * HNS_PER_SEC).floor().to_integer();
+        trace!(%ts, %seek_ts);
+
+        let position = PROPVARIANT {
+            Anonymous: PROPVARIANT_0 {
+                Anonymous: ManuallyDrop::new(PROPVARIANT_0_0 {
+                    vt: VT_I8,
+                    Anonymous: PROPVARIANT_0_0_0 { hVal: seek_ts },
+                    ..Default::default()
+                }),
+            },
+        };
+        // `GUID_NULL` means 100ns units
+        unsafe { self.reader.SetCurrentPosition(ptr::null(), &position)? };
+        self.sample = None;
+
+        Ok(())
+    }
+
+    fn decode(&mut self) -> Result<Option<DecodedFrame>> {
+        loop {
+            let mut flags = 0;
+            let mut timestamp = 0;
+            let mut sample = None;
+            unsafe {
+                self.reader.ReadSample(
+                    VIDEO_STREAM,
+                    0,
+                    None,
+                    Some(&mut flags),
+                    Some(&mut timestamp),
+                    Some(&mut sample),
+                )?;
+            }
+
+            if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+                trace!("EOF reached");
+                self.sample = None;
+                return Ok(None);
+            }
+
+            // A sample may be missing on stream ticks or format changes
+            let Some(sample) = sample else {
+                continue;
+            };
+            self.sample = Some(sample);
+            let pts = Timestamp::new(Ratio::new(timestamp, HNS_PER_SEC));
+            return Ok(Some(DecodedFrame { pts: Some(pts) }));
+        }
+    }
+
+    fn write_frame(&mut self, frame: &mut Frame) -> Result<()> {
+        let Some(sample) = &self.sample else {
+            return Ok(());
+        };
+        let (width, height) = (self.width as usize, self.height as usize);
+
+        unsafe {
+            let buffer = sample.ConvertToContiguousBuffer()?;
+            let (scanline0, pitch, buffer_2d) = match buffer.cast::<IMF2DBuffer>() {
+                Ok(buffer_2d) => {
+                    let mut scanline0 = ptr::null_mut();
+                    let mut pitch = 0;
+                    buffer_2d.Lock2D(&mut scanline0, &mut pitch)?;
+                    (scanline0, pitch, Some(buffer_2d))
+                }
+                Err(_) => {
+                    let mut data = ptr::null_mut();
+                    buffer.Lock(&mut data, None, None)?;
+                    // Negative stride means a bottom-up image
+                    let scanline0 = if self.stride < 0 {
+                        data.offset(-(self.stride as isize) * (height as isize - 1))
+                    } else {
+                        data
+                    };
+                    (scanline0, self.stride, None)
+                }
+            };
+
+            // RGB32 is stored as BGRX
+            let dst = frame.rgb_buffer_mut(self.width, self.height);
+            for y in 0..height {
+                let src_row =
+                    slice::from_raw_parts(scanline0.offset(pitch as isize * y as isize), width * 4);
+                let dst_row = &mut dst[y * width * 3..][..width * 3];
+                for (src, dst) in src_row.chunks_exact(4).zip(dst_row.chunks_exact_mut(3)) {
+                    dst.copy_from_slice(&[src[2], src[1], src[0]]);
+                }
+            }
+
+            match buffer_2d {
+                Some(buffer_2d) => buffer_2d.Unlock2D()?,
+                None => buffer.Unlock()?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn unpack_u32_pair(value: u64) -> (u32, u32) {
+    ((value >> 32) as u32, value as u32)
+}
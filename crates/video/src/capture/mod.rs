@@ -0,0 +1,799 @@
+use std::{path::Path, sync::OnceLock};
+
+use elden_analyzer_kernel::types::{
+    rect::Rect,
+    time::{Duration, FramePosition, Timestamp, TimestampRange},
+};
+use ffmpeg::{format::Pixel, frame};
+use num_rational::Ratio;
+use num_traits::Signed;
+use tracing::{debug, trace, warn};
+
+use super::Result;
+
+#[cfg(feature = "screen-capture")]
+pub use self::screen::{ScreenBackend, ScreenTarget};
+pub use self::{
+    backend::*,
+    cache::FrameCache,
+    ffmpeg_backend::set_scale_threads,
+    image_sequence::{ImageSequenceBackend, DEFAULT_IMAGE_FPS},
+    live::{LiveSource, DEFAULT_DEVICE_FORMAT},
+};
+
+mod backend;
+mod cache;
+mod ffmpeg_backend;
+mod image_sequence;
+mod live;
+#[cfg(all(windows, feature = "media-foundation"))]
+mod media_foundation;
+#[cfg(feature = "screen-capture")]
+mod screen;
+
+pub trait ToRatio {
+    fn to_ratio(&self) -> Ratio<i64>;
+}
+
+impl ToRatio for ffmpeg::Rational {
+    fn to_ratio(&self) -> Ratio<i64> {
+        Ratio::new(self.numerator().into(), self.denominator().into())
+    }
+}
+
+impl ToRatio for ffmpeg::ffi::AVRational {
+    fn to_ratio(&self) -> Ratio<i64> {
+        ffmpeg::Rational::from(*self).to_ratio()
+    }
+}
+
+#[derive(custom_debug::Debug)]
+pub struct Frame {
+    pos: FramePosition,
+    dur: Duration,
+    #[debug(skip)]
+    data: FrameData,
+}
+
+enum FrameData {
+    /// Frame decoded by FFmpeg, converted to RGB24 when its pixels are first accessed
+    Ffmpeg {
+        decoded: frame::Video,
+        rgb: OnceLock<frame::Video>,
+    },
+    Rgb {
+        width: u32,
+        height: u32,
+        buf: Vec<u8>,
+    },
+}
+
+impl Frame {
+    pub fn empty() -> Self {
+        Self {
+            pos: FramePosition::default(),
+            dur: Duration::default(),
+            data: FrameData::Rgb {
+                width: 0,
+                height: 0,
+                buf: vec![],
+            },
+        }
+    }
+
+    pub fn position(&self) -> FramePosition {
+        self.pos
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.dur
+    }
+
+    /// Returns the plane of the frame as a RGB24 image.
+    ///
+    /// Decoded frames are converted to RGB24 on the first access, which fails if FFmpeg cannot convert them.
+    pub fn rgb_data(&self, index: usize) -> Result<&[u8]> {
+        match &self.data {
+            FrameData::Ffmpeg { decoded, rgb } => {
+                if let Some(rgb) = rgb.get() {
+                    return Ok(rgb.data(index));
+                }
+                let converted = ffmpeg_backend::to_rgb(decoded)?;
+                Ok(rgb.get_or_init(|| converted).data(index))
+            }
+            FrameData::Rgb { buf, .. } => {
+                assert_eq!(index, 0);
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Returns the luma plane of the decoded frame, or `None` if the frame is not in a 8-bit YUV format.
+    ///
+    /// The plane is read without converting the frame to RGB24,
+    /// so detectors needing only luminance can skip the conversion of frames not showing components.
+    pub fn luma_plane(&self) -> Option<LumaPlane<'_>> {
+        let FrameData::Ffmpeg { decoded, .. } = &self.data else {
+            return None;
+        };
+        let full_range = match decoded.format() {
+            Pixel::YUVJ420P | Pixel::YUVJ422P | Pixel::YUVJ444P => true,
+            Pixel::YUV420P | Pixel::YUV422P | Pixel::YUV444P | Pixel::NV12 | Pixel::NV21 => {
+                decoded.color_range() == ffmpeg::color::Range::JPEG
+            }
+            _ => return None,
+        };
+        Some(LumaPlane {
+            data: decoded.data(0),
+            stride: decoded.stride(0),
+            width: decoded.width(),
+            height: decoded.height(),
+            full_range,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        match &self.data {
+            FrameData::Ffmpeg { decoded, .. } => decoded.width(),
+            FrameData::Rgb { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match &self.data {
+            FrameData::Ffmpeg { decoded, .. } => decoded.height(),
+            FrameData::Rgb { height, .. } => *height,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::at(0, 0).of_size(self.width(), self.height())
+    }
+
+    /// Returns the buffer to write a `width`x`height` RGB24 image into.
+    pub fn rgb_buffer_mut(&mut self, width: u32, height: u32) -> &mut [u8] {
+        let len = width as usize * height as usize * 3;
+        if !matches!(self.data, FrameData::Rgb { .. }) {
+            self.data = FrameData::Rgb {
+                width,
+                height,
+                buf: vec![],
+            };
+        }
+        let FrameData::Rgb {
+            width: w,
+            height: h,
+            buf,
+        } = &mut self.data
+        else {
+            unreachable!()
+        };
+        *w = width;
+        *h = height;
+        buf.resize(len, 0);
+        buf
+    }
+
+    /// Holds a reference to `decoded`, whose buffers are shared rather than copied.
+    fn set_decoded(&mut self, decoded: &frame::Video) -> Result<()> {
+        let mut data = frame::Video::empty();
+        let ret = unsafe { ffmpeg::ffi::av_frame_ref(data.as_mut_ptr(), decoded.as_ptr()) };
+        if ret < 0 {
+            return Err(ffmpeg::Error::from(ret).into());
+        }
+        self.data = FrameData::Ffmpeg {
+            decoded: data,
+            rgb: OnceLock::new(),
+        };
+        Ok(())
+    }
+}
+
+/// Luma (Y) plane of a decoded frame, before conversion to RGB.
+#[derive(Debug, Clone, Copy)]
+pub struct LumaPlane<'a> {
+    data: &'a [u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    /// Values span `0..=255` instead of `16..=235`
+    full_range: bool,
+}
+
+impl LumaPlane<'_> {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the luma of the pixel at (`x`, `y`), scaled to `0..=255` as in RGB images.
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        let v = self.data[y as usize * self.stride + x as usize];
+        if self.full_range {
+            return v;
+        }
+        let v = u32::from(v.clamp(16, 235) - 16);
+        ((v * 255 + 219 / 2) / 219) as u8
+    }
+}
+
+/// How [`VideoCapture::open_with`] opens inputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenOptions {
+    pub backend: BackendKind,
+    /// Hardware acceleration used if it is available
+    pub hwaccel: Option<HwAccel>,
+    /// Frame rate assumed for sequences of images (default: [`DEFAULT_IMAGE_FPS`])
+    pub image_fps: Option<Ratio<i64>>,
+}
+
+#[derive(Debug)]
+pub struct VideoCapture {
+    backend: Box<dyn CaptureBackend>,
+    skip_until: Option<Timestamp>,
+    /// Carry the PTS of decoded frames in their positions
+    exact_pts: bool,
+    /// Index of the last decoded frame since the last seek
+    last_idx: Option<usize>,
+    /// Index assumed for the first frame after seeking if it has no PTS
+    seek_idx: usize,
+    frame_cache: Option<FrameCache>,
+}
+
+impl VideoCapture {
+    pub fn open(file: &Path) -> Result<Self> {
+        Self::open_with_backend(file, BackendKind::default())
+    }
+
+    pub fn open_with_backend(file: &Path, backend: BackendKind) -> Result<Self> {
+        Self::open_with_hwaccel(file, backend, None)
+    }
+
+    /// Opens `file` decoding frames with `hwaccel` if it is available, or by the CPU otherwise.
+    pub fn open_with_hwaccel(
+        file: &Path,
+        backend: BackendKind,
+        hwaccel: Option<HwAccel>,
+    ) -> Result<Self> {
+        Self::open_with(
+            file,
+            &OpenOptions {
+                backend,
+                hwaccel,
+                image_fps: None,
+            },
+        )
+    }
+
+    /// Opens `file` as a video, or as a sequence of images if it is a directory, a pattern or an image
+    /// (see [`ImageSequenceBackend`]).
+    ///
+    /// Images are always decoded by FFmpeg and by the CPU, ignoring the backend and hardware acceleration.
+    pub fn open_with(file: &Path, options: &OpenOptions) -> Result<Self> {
+        let OpenOptions {
+            backend,
+            hwaccel,
+            image_fps,
+        } = *options;
+        if ImageSequenceBackend::is_image_sequence(file) {
+            let fps = image_fps.unwrap_or(DEFAULT_IMAGE_FPS);
+            let backend = ImageSequenceBackend::open(file, fps)?;
+            return Ok(Self::from_backend(Box::new(backend)));
+        }
+        let backend = backend.open(file, hwaccel)?;
+        Ok(Self::from_backend(backend))
+    }
+
+    /// Opens a capture device or a stream read by FFmpeg in real time, which is captured for `duration`.
+    ///
+    /// Timestamps are measured from the first decoded frame, and the capture cannot seek.
+    pub fn open_live(source: &LiveSource, duration: Duration) -> Result<Self> {
+        let backend = ffmpeg_backend::FfmpegBackend::open_live(source, duration)?;
+        Ok(Self::from_backend(Box::new(backend)))
+    }
+
+    pub fn from_backend(backend: Box<dyn CaptureBackend>) -> Self {
+        Self {
+            backend,
+            skip_until: None,
+            exact_pts: false,
+            last_idx: None,
+            seek_idx: 0,
+            frame_cache: None,
+        }
+    }
+
+    /// Sets whether positions of decoded frames carry their PTS.
+    ///
+    /// Frame indices are computed assuming a constant frame rate in either mode,
+    /// but timestamps of positions are the PTS instead of the index multiplied by the frame duration.
+    pub fn set_exact_pts(&mut self, exact_pts: bool) {
+        self.exact_pts = exact_pts;
+    }
+
+    /// Sets the cache of frames read and written by range decoders.
+    ///
+    /// Frames cached by earlier runs are read from the cache without decoding,
+    /// and the backend is used only for frames not cached yet.
+    /// The cache is not used with exact PTS or for live sources, whose frames are not identified by indices.
+    pub fn set_frame_cache(&mut self, cache: Option<FrameCache>) {
+        self.frame_cache = cache;
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.backend.duration()
+    }
+
+    pub fn fps(&self) -> Ratio<i64> {
+        self.backend.fps()
+    }
+
+    pub fn sec_per_frame(&self) -> Duration {
+        Duration::new(self.fps().recip())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.backend.frames()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.backend.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.backend.height()
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::at(0, 0).of_size(self.width(), self.height())
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.backend.is_live()
+    }
+
+    pub fn seek(&mut self, ts: Timestamp) -> Result<()> {
+        self.backend.seek(ts)?;
+        let start = self.to_precise_frame_start(ts);
+        self.skip_until = Some(start.grid_timestamp());
+        self.last_idx = None;
+        self.seek_idx = start.index();
+        Ok(())
+    }
+
+    pub fn range_decoder(&mut self, range: TimestampRange) -> Result<RangeDecoder> {
+        let dur = self.duration();
+        let start = match range {
+            TimestampRange::Full => Timestamp::new(Ratio::ZERO),
+            TimestampRange::Single(ts) => ts,
+            TimestampRange::Range(start, _) => start,
+            TimestampRange::RangeFrom(start) => start,
+            TimestampRange::RangeTo(_) => Timestamp::new(dur.as_ratio()),
+        };
+        let start = self.to_precise_frame_start(start);
+
+        let end = match range {
+            TimestampRange::Full => Timestamp::new(dur.as_ratio()),
+            TimestampRange::Single(ts) => ts + self.sec_per_frame(),
+            TimestampRange::Range(_, end) => end,
+            TimestampRange::RangeFrom(_) => Timestamp::new(dur.as_ratio()),
+            TimestampRange::RangeTo(end) => end,
+        };
+        let end = self.to_precise_frame_end(end);
+
+        // Seeks are deferred until a frame is missing in the cache, so cached ranges are read without the backend
+        let cached = self.frame_cache.is_some() && !self.exact_pts && !self.is_live();
+        if !cached {
+            self.seek(start.grid_timestamp())?;
+        }
+
+        let decoder = RangeDecoder {
+            capture: self,
+            start,
+            end,
+            cached,
+            next_idx: start.index(),
+            seeked: !cached,
+        };
+        Ok(decoder)
+    }
+
+    fn frame_pos_of_index(&self, idx: usize) -> FramePosition {
+        let ts = Timestamp::new(self.sec_per_frame().as_ratio() * idx as i64);
+        FramePosition::new(idx, ts)
+    }
+
+    fn to_precise_frame_pos(&self, rough_ts: Timestamp) -> FramePosition {
+        let frame_idx = (rough_ts.as_ratio() * self.fps()).round().to_integer() as usize;
+        self.frame_pos_of_index(frame_idx)
+    }
+
+    pub fn to_precise_frame_start(&self, rough_ts: Timestamp) -> FramePosition {
+        let precise_pos = self.to_precise_frame_pos(rough_ts);
+        if (rough_ts.as_ratio() - precise_pos.grid_timestamp().as_ratio()).abs()
+            < Ratio::new(1, 1000)
+        {
+            return precise_pos;
+        }
+
+        // If precise position is not close to enough, seek to the frame that contains the timestamp
+        let frame_idx = (rough_ts.as_ratio() * self.fps()).floor().to_integer() as usize;
+        self.frame_pos_of_index(frame_idx)
+    }
+
+    pub fn to_precise_frame_end(&self, rough_ts: Timestamp) -> FramePosition {
+        let precise_pos = self.to_precise_frame_pos(rough_ts);
+        if (rough_ts.as_ratio() - precise_pos.grid_timestamp().as_ratio()).abs()
+            < Ratio::new(1, 1000)
+        {
+            return precise_pos;
+        }
+
+        // If precise position is not close to enough, seek to the frame that contains the timestamp
+        let frame_idx = (rough_ts.as_ratio() * self.fps()).ceil().to_integer() as usize;
+        self.frame_pos_of_index(frame_idx)
+    }
+
+    fn write_frame_common(&mut self, rgb_frame: &mut Frame, pos: FramePosition) {
+        rgb_frame.pos = pos;
+        rgb_frame.dur = self.sec_per_frame();
+    }
+
+    fn write_normal_frame(&mut self, rgb_frame: &mut Frame, pos: FramePosition) -> Result<()> {
+        self.write_frame_common(rgb_frame, pos);
+        self.backend.write_frame(rgb_frame)?;
+
+        Ok(())
+    }
+
+    fn write_eof_frame(&mut self, rgb_frame: &mut Frame, pos: Option<FramePosition>) {
+        let pos = pos.unwrap_or_else(|| {
+            self.to_precise_frame_pos(Timestamp::new(self.duration().as_ratio()))
+        });
+        self.write_frame_common(rgb_frame, pos);
+    }
+
+    /// Returns the position of a decoded frame.
+    ///
+    /// Frames without PTS are placed right after the previous frame.
+    fn frame_pos_of_decoded(&self, decoded: DecodedFrame) -> FramePosition {
+        let Some(pts) = decoded.pts else {
+            let idx = self.last_idx.map_or(self.seek_idx, |idx| idx + 1);
+            trace!(idx, "frame without PTS");
+            return self.frame_pos_of_index(idx);
+        };
+        let pos = self.to_precise_frame_pos(pts);
+        if self.exact_pts {
+            pos.with_pts(pts)
+        } else {
+            pos
+        }
+    }
+
+    fn decode_frame_inner(&mut self) -> Result<Option<FramePosition>> {
+        while let Some(decoded) = self.backend.decode()? {
+            let pos = self.frame_pos_of_decoded(decoded);
+
+            // Jittery PTS may be rounded to the index of the previous frame.
+            // Outputs assume strictly increasing indices, so such frames are dropped.
+            if self.last_idx.is_some_and(|last| pos.index() <= last) {
+                debug!(%pos, "frame index does not increase, dropped");
+                continue;
+            }
+            self.last_idx = Some(pos.index());
+
+            if let Some(until) = self.skip_until {
+                let end_ts = pos.grid_timestamp() + self.sec_per_frame();
+                if end_ts <= until {
+                    trace!(%pos, %end_ts, "skip frame");
+                    continue;
+                }
+                self.skip_until = None;
+            }
+
+            return Ok(Some(pos));
+        }
+        Ok(None)
+    }
+
+    pub fn decode_frame(&mut self, frame: &mut Frame) -> Result<bool> {
+        match self.decode_frame_inner()? {
+            Some(pos) => {
+                self.write_normal_frame(frame, pos)?;
+                Ok(true)
+            }
+            None => {
+                self.write_eof_frame(frame, None);
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RangeDecoder<'a> {
+    capture: &'a mut VideoCapture,
+    start: FramePosition,
+    end: FramePosition,
+    /// Frames are read from and written to the frame cache of the capture
+    cached: bool,
+    /// Index of the frame expected next
+    next_idx: usize,
+    /// The backend is positioned at the frame expected next
+    seeked: bool,
+}
+
+impl RangeDecoder<'_> {
+    pub fn capture(&self) -> &VideoCapture {
+        self.capture
+    }
+
+    pub fn start(&self) -> FramePosition {
+        self.start
+    }
+
+    pub fn end(&self) -> FramePosition {
+        self.end
+    }
+
+    pub fn decode_frame(&mut self, frame: &mut Frame) -> Result<bool> {
+        if self.cached {
+            if self.next_idx >= self.end.index() {
+                self.capture.write_eof_frame(frame, Some(self.end));
+                return Ok(false);
+            }
+            if self.load_cached(frame) {
+                let pos = self.capture.frame_pos_of_index(self.next_idx);
+                self.capture.write_frame_common(frame, pos);
+                self.next_idx += 1;
+                self.seeked = false;
+                return Ok(true);
+            }
+            if !self.seeked {
+                let pos = self.capture.frame_pos_of_index(self.next_idx);
+                self.capture.seek(pos.grid_timestamp())?;
+                self.seeked = true;
+            }
+        }
+
+        match self.capture.decode_frame_inner()? {
+            Some(pos) => {
+                if pos.grid_timestamp() >= self.end.grid_timestamp() {
+                    self.capture.write_eof_frame(frame, Some(pos));
+                    return Ok(false);
+                }
+                self.capture.write_normal_frame(frame, pos)?;
+                if self.cached {
+                    self.store_cached(pos.index(), frame);
+                    self.next_idx = pos.index() + 1;
+                }
+                Ok(true)
+            }
+            None => {
+                self.capture.write_eof_frame(frame, None);
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl RangeDecoder<'_> {
+    /// Reads the frame expected next from the cache, regarding unreadable frames as missing.
+    fn load_cached(&self, frame: &mut Frame) -> bool {
+        let Some(cache) = &self.capture.frame_cache else {
+            return false;
+        };
+        cache.load(self.next_idx, frame).unwrap_or_else(|err| {
+            warn!(idx = self.next_idx, %err, "failed to read a cached frame, decoding it");
+            false
+        })
+    }
+
+    /// Writes the decoded frame to the cache, only warning on failures since the frame is already decoded.
+    fn store_cached(&self, idx: usize, frame: &Frame) {
+        if let Some(cache) = &self.capture.frame_cache {
+            if let Err(err) = cache.store(idx, frame) {
+                warn!(idx, %err, "failed to write a frame to the cache");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    #[test]
+    fn luma_of_limited_range() {
+        let data = [0, 16, 126, 235, 255, 0, 0, 0];
+        let plane = |full_range| LumaPlane {
+            data: &data,
+            stride: 8,
+            width: 5,
+            height: 1,
+            full_range,
+        };
+        let values = |plane: LumaPlane| (0..5).map(|x| plane.get(x, 0)).collect::<Vec<_>>();
+        assert_eq!(values(plane(false)), [0, 0, 128, 255, 255]);
+        assert_eq!(values(plane(true)), [0, 16, 126, 235, 255]);
+    }
+
+    /// Backend decoding frames with the given PTS, whose key frame is only the first one.
+    #[derive(Debug)]
+    struct FakeBackend {
+        fps: Ratio<i64>,
+        pts: Vec<Option<Timestamp>>,
+        queue: VecDeque<Option<Timestamp>>,
+    }
+
+    impl FakeBackend {
+        fn new(fps: Ratio<i64>, pts: Vec<Option<Timestamp>>) -> Self {
+            let queue = pts.iter().copied().collect();
+            Self { fps, pts, queue }
+        }
+    }
+
+    impl CaptureBackend for FakeBackend {
+        fn duration(&self) -> Duration {
+            Duration::new(Ratio::from_integer(self.pts.len() as i64) / self.fps)
+        }
+
+        fn fps(&self) -> Ratio<i64> {
+            self.fps
+        }
+
+        fn frames(&self) -> usize {
+            self.pts.len()
+        }
+
+        fn width(&self) -> u32 {
+            1
+        }
+
+        fn height(&self) -> u32 {
+            1
+        }
+
+        fn seek(&mut self, _ts: Timestamp) -> Result<()> {
+            self.queue = self.pts.iter().copied().collect();
+            Ok(())
+        }
+
+        fn decode(&mut self) -> Result<Option<DecodedFrame>> {
+            Ok(self.queue.pop_front().map(|pts| DecodedFrame { pts }))
+        }
+
+        fn write_frame(&mut self, frame: &mut Frame) -> Result<()> {
+            frame.rgb_buffer_mut(1, 1);
+            Ok(())
+        }
+    }
+
+    fn ms(ms: i64) -> Timestamp {
+        Timestamp::new(Ratio::new(ms, 1000))
+    }
+
+    /// PTS of 29.97 fps frames in a container with millisecond time base
+    fn ntsc_pts(frames: i64) -> Vec<Option<Timestamp>> {
+        (0..frames)
+            .map(|idx| Some(ms((Ratio::new(idx * 1001, 30)).round().to_integer())))
+            .collect()
+    }
+
+    fn decode_all(
+        capture: &mut VideoCapture,
+        range: TimestampRange,
+    ) -> Vec<(usize, Timestamp, Timestamp)> {
+        let mut decoder = capture.range_decoder(range).unwrap();
+        let mut frame = Frame::empty();
+        let mut positions = vec![];
+        while decoder.decode_frame(&mut frame).unwrap() {
+            let pos = frame.position();
+            positions.push((pos.index(), pos.grid_timestamp(), pos.timestamp()));
+        }
+        positions
+    }
+
+    #[test]
+    fn ntsc_frame_rate() {
+        let fps = Ratio::new(30000, 1001);
+        let pts = ntsc_pts(100);
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts.clone())));
+
+        let positions = decode_all(&mut capture, TimestampRange::Full);
+        assert_eq!(positions.len(), 100);
+        for (idx, (pos_idx, grid_ts, ts)) in positions.into_iter().enumerate() {
+            assert_eq!(pos_idx, idx);
+            assert_eq!(grid_ts.as_ratio(), Ratio::from_integer(idx as i64) / fps);
+            assert_eq!(ts, grid_ts);
+        }
+
+        // Frames in the first second are frames 0 to 29, since frame 30 starts at 1.001s
+        let positions = decode_all(&mut capture, TimestampRange::Range(ms(0), ms(1000)));
+        assert_eq!(positions.len(), 30);
+
+        // Range starting at a rounded PTS starts at the frame of the PTS
+        let positions = decode_all(&mut capture, TimestampRange::RangeFrom(ms(1001)));
+        assert_eq!(positions.first().map(|pos| pos.0), Some(30));
+    }
+
+    #[test]
+    fn exact_pts() {
+        let fps = Ratio::new(30000, 1001);
+        let pts = ntsc_pts(100);
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts.clone())));
+        capture.set_exact_pts(true);
+
+        let positions = decode_all(&mut capture, TimestampRange::Full);
+        let indices = positions.iter().map(|pos| pos.0).collect::<Vec<_>>();
+        assert_eq!(indices, (0..100).collect::<Vec<_>>());
+        let timestamps = positions.iter().map(|pos| Some(pos.2)).collect::<Vec<_>>();
+        assert_eq!(timestamps, pts);
+    }
+
+    #[test]
+    fn missing_pts() {
+        let fps = Ratio::from_integer(30);
+        let pts = vec![None, Some(ms(33)), None, Some(ms(100)), None];
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts)));
+        capture.set_exact_pts(true);
+
+        let positions = decode_all(&mut capture, TimestampRange::Full);
+        let indices = positions.iter().map(|pos| pos.0).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+        // Frames without PTS have timestamps on the grid
+        assert_eq!(positions[0].2, ms(0));
+        assert_eq!(positions[1].2, ms(33));
+        assert_eq!(positions[2].2, positions[2].1);
+    }
+
+    #[test]
+    fn cached_frames() {
+        let dir = std::env::temp_dir().join(format!("elden-analyzer-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("video.mp4");
+        std::fs::write(&file, b"video").unwrap();
+        let cache = FrameCache::open(&dir.join("cache"), &file).unwrap();
+
+        let fps = Ratio::from_integer(30);
+        let pts = (0..10)
+            .map(|idx| Some(ms(idx * 100 / 3)))
+            .collect::<Vec<_>>();
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts.clone())));
+        capture.set_frame_cache(Some(cache.clone()));
+        let range = TimestampRange::Range(ms(100), ms(200));
+        let decoded = decode_all(&mut capture, range);
+        assert_eq!(decoded.len(), 3);
+
+        // Frames of the range are read from the cache, since the backend decodes no frames
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, vec![])));
+        capture.set_frame_cache(Some(cache.clone()));
+        assert_eq!(decode_all(&mut capture, range), decoded);
+
+        // Frames around the cached ones are decoded at the same positions as without the cache
+        let range = TimestampRange::Range(ms(0), ms(300));
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts)));
+        let uncached = decode_all(&mut capture, range);
+        capture.set_frame_cache(Some(cache));
+        assert_eq!(decode_all(&mut capture, range), uncached);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn jittery_pts() {
+        let fps = Ratio::from_integer(30);
+        // The third frame is rounded to the index of the second one
+        let pts = vec![Some(ms(0)), Some(ms(33)), Some(ms(49)), Some(ms(100))];
+        let mut capture = VideoCapture::from_backend(Box::new(FakeBackend::new(fps, pts)));
+
+        let positions = decode_all(&mut capture, TimestampRange::Full);
+        let indices = positions.iter().map(|pos| pos.0).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 1, 3]);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fmt, thread,
+    time::{self, Instant},
+};
+
+use elden_analyzer_kernel::types::{
+    rect::Rect,
+    time::{Duration, Timestamp},
+};
+use num_rational::Ratio;
+use num_traits::ToPrimitive as _;
+use tracing::{debug, trace};
+use xcap::{image::RgbaImage, Monitor, Window};
+
+use crate::{Error, Result};
+
+use super::{CaptureBackend, DecodedFrame, Frame};
+
+/// Screen area captured by [`ScreenBackend`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenTarget {
+    PrimaryMonitor,
+    /// Monitor with the given name
+    Monitor(String),
+    /// First non-minimized window whose title contains the given string
+    Window(String),
+}
+
+impl fmt::Display for ScreenTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenTarget::PrimaryMonitor => write!(f, "primary monitor"),
+            ScreenTarget::Monitor(name) => write!(f, "monitor `{name}`"),
+            ScreenTarget::Window(title) => write!(f, "window `{title}`"),
+        }
+    }
+}
+
+enum Source {
+    Monitor(Monitor),
+    Window(Window),
+}
+
+impl Source {
+    fn find(target: &ScreenTarget) -> Result<Self> {
+        let source = match target {
+            ScreenTarget::PrimaryMonitor => Monitor::all()?
+                .into_iter()
+                .find(|monitor| monitor.is_primary().unwrap_or(false))
+                .map(Source::Monitor),
+            ScreenTarget::Monitor(name) => Monitor::all()?
+                .into_iter()
+                .find(|monitor| monitor.name().is_ok_and(|n| n == *name))
+                .map(Source::Monitor),
+            ScreenTarget::Window(title) => Window::all()?
+                .into_iter()
+                .filter(|window| !window.is_minimized().unwrap_or(true))
+                .find(|window| window.title().is_ok_and(|t| t.contains(title.as_str())))
+                .map(Source::Window),
+        };
+        source.ok_or_else(|| Error::ScreenTargetNotFound(target.clone()))
+    }
+
+    fn capture_image(&self) -> Result<RgbaImage> {
+        let image = match self {
+            Source::Monitor(monitor) => monitor.capture_image()?,
+            Source::Window(window) => window.capture_image()?,
+        };
+        Ok(image)
+    }
+}
+
+/// Live capture backend that grabs a monitor or a window at a fixed frame rate.
+///
+/// Timestamps are measured from the first decoded frame.
+/// If capturing falls behind the target frame rate, frames are skipped to keep up with the wall clock.
+#[derive(custom_debug::Debug)]
+pub struct ScreenBackend {
+    target: ScreenTarget,
+    region: Rect,
+    dur: Duration,
+    fps: Ratio<i64>,
+    frames: usize,
+
+    #[debug(skip)]
+    source: Source,
+    #[debug(skip)]
+    image: Option<RgbaImage>,
+    start: Option<Instant>,
+    next_idx: usize,
+}
+
+impl ScreenBackend {
+    /// Opens a live capture of `target`.
+    ///
+    /// If `region` is given, only that area (relative to the top left of `target`) is captured.
+    /// Capturing stops after `duration`.
+    pub fn open(
+        target: ScreenTarget,
+        region: Option<Rect>,
+        fps: Ratio<i64>,
+        duration: Duration,
+    ) -> Result<Self> {
+        let source = Source::find(&target)?;
+        let image = source.capture_image()?;
+        let region =
+            region.unwrap_or_else(|| Rect::at(0, 0).of_size(image.width(), image.height()));
+        let frames = (duration.as_ratio() * fps).floor().to_integer() as usize;
+
+        debug!(%target, %region, %fps, %duration, frames);
+
+        Ok(Self {
+            target,
+            region,
+            dur: duration,
+            fps,
+            frames,
+            source,
+            image: None,
+            start: None,
+            next_idx: 0,
+        })
+    }
+
+    pub fn target(&self) -> &ScreenTarget {
+        &self.target
+    }
+
+    fn wall_clock_of_index(&self, idx: usize) -> time::Duration {
+        let sec = Ratio::from_integer(idx as i64) / self.fps;
+        time::Duration::from_secs_f64(sec.to_f64().unwrap_or_default())
+    }
+}
+
+impl CaptureBackend for ScreenBackend {
+    fn duration(&self) -> Duration {
+        self.dur
+    }
+
+    fn fps(&self) -> Ratio<i64> {
+        self.fps
+    }
+
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn width(&self) -> u32 {
+        self.region.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.region.height()
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    fn seek(&mut self, ts: Timestamp) -> Result<()> {
+        // Live sources cannot be rewound. Frames before `ts` are skipped by `VideoCapture`.
+        trace!(%ts, "seek is ignored for live source");
+        Ok(())
+    }
+
+    fn decode(&mut self) -> Result<Option<DecodedFrame>> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+
+        let elapsed = start.elapsed();
+        let current_idx = (elapsed.as_secs_f64() * self.fps.to_f64().unwrap_or_default()) as usize;
+        if current_idx > self.next_idx {
+            trace!(
+                skipped = current_idx - self.next_idx,
+                "capture is behind the wall clock"
+            );
+            self.next_idx = current_idx;
+        }
+        if self.next_idx >= self.frames {
+            trace!("capture duration reached");
+            return Ok(None);
+        }
+
+        let wait = self
+            .wall_clock_of_index(self.next_idx)
+            .saturating_sub(elapsed);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+
+        self.image = Some(self.source.capture_image()?);
+        let ts = Timestamp::new(Ratio::from_integer(self.next_idx as i64) / self.fps);
+        self.next_idx += 1;
+
+        Ok(Some(DecodedFrame { pts: Some(ts) }))
+    }
+
+    fn write_frame(&mut self, frame: &mut Frame) -> Result<()> {
+        let Some(image) = &self.image else {
+            return Ok(());
+        };
+        let region = self.region;
+
+        // The window may be resized while capturing, so pixels outside the image are filled with black
+        let dst = frame.rgb_buffer_mut(region.width(), region.height());
+        for (i, dst) in dst.chunks_exact_mut(3).enumerate() {
+            let x = region.left() + (i as u32 % region.width()) as i32;
+            let y = region.top() + (i as u32 / region.width()) as i32;
+            let pixel = u32::try_from(x)
+                .ok()
+                .zip(u32::try_from(y).ok())
+                .and_then(|(x, y)| image.get_pixel_checked(x, y));
+            match pixel {
+                Some(pixel) => dst.copy_from_slice(&pixel.0[..3]),
+                None => dst.fill(0),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    ffi::CStr,
+    path::{Path, PathBuf},
+    ptr,
+};
+
 pub mod capture;
 pub mod metadata;
 
@@ -7,6 +13,27 @@ pub enum Error {
     Ffmpeg(#[from] ffmpeg::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[cfg(all(windows, feature = "media-foundation"))]
+    #[error("Media Foundation error: {0}")]
+    MediaFoundation(#[from] windows::core::Error),
+    #[cfg(feature = "screen-capture")]
+    #[error("screen capture error: {0}")]
+    ScreenCapture(#[from] xcap::XCapError),
+    #[cfg(feature = "screen-capture")]
+    #[error("{0} is not found")]
+    ScreenTargetNotFound(capture::ScreenTarget),
+    #[error("capture backend `{0}` is not supported in this build")]
+    UnsupportedBackend(capture::BackendKind),
+    #[error("path is not valid UTF-8: {}", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error("no images found: {}", .0.display())]
+    NoImages(PathBuf),
+    #[error("image size differs from the first image: {}", .0.display())]
+    ImageSizeMismatch(PathBuf),
+    #[error("input format `{0}` is not supported by FFmpeg")]
+    UnknownInputFormat(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -15,3 +42,68 @@ pub fn init() -> Result<()> {
     ffmpeg::init()?;
     Ok(())
 }
+
+/// Opens a media file with FFmpeg.
+///
+/// FFmpeg takes paths as UTF-8, including on Windows where they are converted to wide strings,
+/// so non-ASCII paths are supported but paths not representable in UTF-8 are rejected instead of panicking.
+fn open_input(file: &Path) -> Result<ffmpeg::format::context::Input> {
+    if file.to_str().is_none() {
+        return Err(Error::NonUtf8Path(file.to_owned()));
+    }
+    Ok(ffmpeg::format::input(&file)?)
+}
+
+/// Returns the version of the linked libavformat, e.g. `libavformat 61.7.100`.
+pub fn ffmpeg_version() -> String {
+    let version = ffmpeg::format::version();
+    format!(
+        "libavformat {}.{}.{}",
+        version >> 16,
+        (version >> 8) & 0xff,
+        version & 0xff
+    )
+}
+
+/// Returns the options FFmpeg is configured with.
+pub fn ffmpeg_configuration() -> &'static str {
+    ffmpeg::format::configuration()
+}
+
+/// Returns the names of hardware device types FFmpeg is built with, e.g. `cuda` and `vaapi`,
+/// paired with whether a device of the type can be opened on this machine.
+pub fn ffmpeg_hwaccels() -> Vec<(String, bool)> {
+    use ffmpeg::ffi::{
+        av_buffer_unref, av_hwdevice_ctx_create, av_hwdevice_get_type_name,
+        av_hwdevice_iterate_types, AVHWDeviceType,
+    };
+
+    let mut hwaccels = vec![];
+    let mut ty = AVHWDeviceType::AV_HWDEVICE_TYPE_NONE;
+    loop {
+        // SAFETY: `ty` is a device type returned by FFmpeg or `NONE`
+        ty = unsafe { av_hwdevice_iterate_types(ty) };
+        if ty == AVHWDeviceType::AV_HWDEVICE_TYPE_NONE {
+            break;
+        }
+        // SAFETY: names of device types are static strings, or null for unknown types
+        let name = unsafe { av_hwdevice_get_type_name(ty) };
+        if name.is_null() {
+            continue;
+        }
+        // SAFETY: `name` is a non-null, nul-terminated static string
+        let name = unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned();
+
+        let mut device = ptr::null_mut();
+        // SAFETY: the default device of the type is opened and released immediately
+        let available = unsafe {
+            let ret = av_hwdevice_ctx_create(&mut device, ty, ptr::null(), ptr::null_mut(), 0);
+            av_buffer_unref(&mut device);
+            ret >= 0
+        };
+        hwaccels.push((name, available));
+    }
+    hwaccels
+}
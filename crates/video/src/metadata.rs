@@ -1,113 +1,427 @@
 use std::{io::Write, path::Path};
 
-use ffmpeg::{format, media};
+use ffmpeg::{color, media, rescale::TIME_BASE, DictionaryRef, Rational};
+use num_rational::Ratio;
 
-use super::Result;
+use super::{capture::ToRatio as _, Result};
 
-pub fn dump(mut out: impl Write, file: &impl AsRef<Path>) -> Result<()> {
-    let context = format::input(file)?;
+/// Properties of a media file, read by [`probe`].
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    /// Name of the container format, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
+    pub format: String,
+    pub tags: Vec<(String, String)>,
+    /// Duration in seconds, or `None` if unknown
+    pub duration: Option<Ratio<i64>>,
+    /// Duration in [`TIME_BASE`] units, as recorded in the container
+    pub duration_ts: i64,
+    pub best_video: Option<usize>,
+    pub best_audio: Option<usize>,
+    pub best_subtitle: Option<usize>,
+    pub streams: Vec<StreamMetadata>,
+    pub chapters: Vec<Chapter>,
+}
 
-    writeln!(&mut out, "Metadata")?;
-    for (k, v) in context.metadata().iter() {
-        writeln!(&mut out, "\t{k}: {v}")?;
+#[derive(Debug, Clone)]
+pub struct StreamMetadata {
+    pub index: usize,
+    pub time_base: Ratio<i64>,
+    pub start_time: i64,
+    /// Duration in seconds, or `None` if unknown
+    pub duration: Option<Ratio<i64>>,
+    /// Duration in `time_base` units, as recorded in the container
+    pub duration_ts: i64,
+    pub frames: i64,
+    pub disposition: String,
+    pub discard: String,
+    /// Lowest frame rate that can represent all timestamps (`r_frame_rate`), or `None` if unknown
+    pub rate: Option<Ratio<i64>>,
+    /// Average frame rate, or `None` if unknown
+    pub avg_frame_rate: Option<Ratio<i64>>,
+    pub medium: MediaKind,
+    /// Name of the codec, e.g. `H264`
+    pub codec: String,
+    pub params: CodecParams,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+/// Parameters of the codec specific to the kind of media.
+#[derive(Debug, Clone)]
+pub enum CodecParams {
+    Video(VideoParams),
+    Audio(AudioParams),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoParams {
+    pub bit_rate: usize,
+    pub max_bit_rate: usize,
+    pub delay: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Name of the pixel format, e.g. `YUV420P`
+    pub pixel_format: String,
+    pub has_b_frames: bool,
+    /// Sample aspect ratio, or `None` if unknown
+    pub aspect_ratio: Option<Ratio<i64>>,
+    pub color: ColorInfo,
+    pub references: usize,
+    pub intra_dc_precision: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColorInfo {
+    pub range: ColorRange,
+    pub space: String,
+    pub primaries: String,
+    pub transfer_characteristic: String,
+    pub chroma_location: String,
+}
+
+/// Range of luma and chroma values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRange {
+    Unspecified,
+    /// Limited range (`16..=235` for luma), used by most videos
+    Limited,
+    /// Full range (`0..=255`), used by some captures and screen recordings
+    Full,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioParams {
+    pub bit_rate: usize,
+    pub max_bit_rate: usize,
+    pub delay: usize,
+    pub rate: u32,
+    pub channels: u16,
+    pub format: String,
+    pub frames: usize,
+    pub align: usize,
+    pub channel_layout: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub id: i64,
+    /// Start time in seconds
+    pub start: Ratio<i64>,
+    /// End time in seconds
+    pub end: Ratio<i64>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Returns the best video stream.
+    pub fn video_stream(&self) -> Option<&StreamMetadata> {
+        self.streams.get(self.best_video?)
     }
+}
 
-    if let Some(stream) = context.streams().best(media::Type::Video) {
-        writeln!(&mut out, "\tBest video stream index: {}", stream.index())?;
+impl StreamMetadata {
+    /// Returns the parameters if this is a video stream.
+    pub fn video(&self) -> Option<&VideoParams> {
+        match &self.params {
+            CodecParams::Video(video) => Some(video),
+            _ => None,
+        }
     }
 
-    if let Some(stream) = context.streams().best(media::Type::Audio) {
-        writeln!(&mut out, "\tBest audio stream index: {}", stream.index())?;
+    /// Returns the frame rate, preferring the average frame rate.
+    pub fn fps(&self) -> Option<Ratio<i64>> {
+        self.avg_frame_rate.or(self.rate)
     }
+}
 
-    if let Some(stream) = context.streams().best(media::Type::Subtitle) {
-        writeln!(&mut out, "\tBest subtitle stream index: {}", stream.index())?;
+impl Chapter {
+    /// Returns the title of the chapter, if recorded.
+    pub fn title(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("title"))
+            .map(|(_, value)| value.as_str())
     }
+}
+
+impl From<media::Type> for MediaKind {
+    fn from(value: media::Type) -> Self {
+        match value {
+            media::Type::Video => Self::Video,
+            media::Type::Audio => Self::Audio,
+            media::Type::Subtitle => Self::Subtitle,
+            media::Type::Data => Self::Data,
+            media::Type::Attachment => Self::Attachment,
+            media::Type::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl From<color::Range> for ColorRange {
+    fn from(value: color::Range) -> Self {
+        match value {
+            color::Range::MPEG => Self::Limited,
+            color::Range::JPEG => Self::Full,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+/// Converts a rational value, returning `None` if it is unknown (`0/0`) or zero.
+fn to_ratio(value: Rational) -> Option<Ratio<i64>> {
+    (value.numerator() != 0 && value.denominator() != 0).then(|| value.to_ratio())
+}
+
+/// Converts a duration in `time_base` units to seconds, returning `None` if it is unknown.
+fn to_seconds(duration: i64, time_base: Ratio<i64>) -> Option<Ratio<i64>> {
+    (duration >= 0).then(|| Ratio::from(duration) * time_base)
+}
 
-    writeln!(&mut out, "\tduration (timebase): {}", context.duration())?;
+fn to_tags(dict: DictionaryRef<'_>) -> Vec<(String, String)> {
+    dict.iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+}
+
+/// Reads properties of the media file and its streams.
+pub fn probe(file: &impl AsRef<Path>) -> Result<Metadata> {
+    let context = super::open_input(file.as_ref())?;
+
+    let streams = context
+        .streams()
+        .map(|stream| -> Result<_> {
+            let time_base = stream.time_base().to_ratio();
+            let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+            let medium = codec.medium();
+            let codec_name = format!("{:?}", codec.id());
+            let params = match medium {
+                media::Type::Video => match codec.decoder().video() {
+                    Ok(video) => CodecParams::Video(VideoParams {
+                        bit_rate: video.bit_rate(),
+                        max_bit_rate: video.max_bit_rate(),
+                        delay: video.delay(),
+                        width: video.width(),
+                        height: video.height(),
+                        pixel_format: format!("{:?}", video.format()),
+                        has_b_frames: video.has_b_frames(),
+                        aspect_ratio: to_ratio(video.aspect_ratio()),
+                        color: ColorInfo {
+                            range: video.color_range().into(),
+                            space: format!("{:?}", video.color_space()),
+                            primaries: format!("{:?}", video.color_primaries()),
+                            transfer_characteristic: format!(
+                                "{:?}",
+                                video.color_transfer_characteristic()
+                            ),
+                            chroma_location: format!("{:?}", video.chroma_location()),
+                        },
+                        references: video.references(),
+                        intra_dc_precision: video.intra_dc_precision(),
+                    }),
+                    Err(_) => CodecParams::Other,
+                },
+                media::Type::Audio => match codec.decoder().audio() {
+                    Ok(audio) => CodecParams::Audio(AudioParams {
+                        bit_rate: audio.bit_rate(),
+                        max_bit_rate: audio.max_bit_rate(),
+                        delay: audio.delay(),
+                        rate: audio.rate(),
+                        channels: audio.channels(),
+                        format: format!("{:?}", audio.format()),
+                        frames: audio.frames(),
+                        align: audio.align(),
+                        channel_layout: format!("{:?}", audio.channel_layout()),
+                    }),
+                    Err(_) => CodecParams::Other,
+                },
+                _ => CodecParams::Other,
+            };
+            Ok(StreamMetadata {
+                index: stream.index(),
+                time_base,
+                start_time: stream.start_time(),
+                duration: to_seconds(stream.duration(), time_base),
+                duration_ts: stream.duration(),
+                frames: stream.frames(),
+                disposition: format!("{:?}", stream.disposition()),
+                discard: format!("{:?}", stream.discard()),
+                rate: to_ratio(stream.rate()),
+                avg_frame_rate: to_ratio(stream.avg_frame_rate()),
+                medium: medium.into(),
+                codec: codec_name,
+                params,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let chapters = context
+        .chapters()
+        .map(|chapter| {
+            let time_base = chapter.time_base().to_ratio();
+            Chapter {
+                id: chapter.id(),
+                start: Ratio::from(chapter.start()) * time_base,
+                end: Ratio::from(chapter.end()) * time_base,
+                tags: to_tags(chapter.metadata()),
+            }
+        })
+        .collect();
+
+    let best = |kind| context.streams().best(kind).map(|stream| stream.index());
+    Ok(Metadata {
+        format: context.format().name().to_owned(),
+        tags: to_tags(context.metadata()),
+        duration: to_seconds(context.duration(), TIME_BASE.to_ratio()),
+        duration_ts: context.duration(),
+        best_video: best(media::Type::Video),
+        best_audio: best(media::Type::Audio),
+        best_subtitle: best(media::Type::Subtitle),
+        streams,
+        chapters,
+    })
+}
+
+fn seconds(value: Option<Ratio<i64>>) -> String {
+    match value {
+        Some(value) => format!("{:.6}", *value.numer() as f64 / *value.denom() as f64),
+        None => "unknown".to_owned(),
+    }
+}
+
+fn rate(value: Option<Ratio<i64>>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "unknown".to_owned(),
+    }
+}
+
+pub fn dump(out: impl Write, file: &impl AsRef<Path>) -> Result<()> {
+    write(out, &probe(file)?)
+}
+
+/// Writes `metadata` in a human-readable format.
+pub fn write(mut out: impl Write, metadata: &Metadata) -> Result<()> {
+    writeln!(&mut out, "Metadata")?;
+    writeln!(&mut out, "\tformat: {}", metadata.format)?;
+    for (k, v) in &metadata.tags {
+        writeln!(&mut out, "\t{k}: {v}")?;
+    }
+
+    if let Some(index) = metadata.best_video {
+        writeln!(&mut out, "\tBest video stream index: {index}")?;
+    }
+    if let Some(index) = metadata.best_audio {
+        writeln!(&mut out, "\tBest audio stream index: {index}")?;
+    }
+    if let Some(index) = metadata.best_subtitle {
+        writeln!(&mut out, "\tBest subtitle stream index: {index}")?;
+    }
+
+    writeln!(&mut out, "\tduration (timebase): {}", metadata.duration_ts)?;
     writeln!(
         &mut out,
-        "\tduration (seconds): {:.6}",
-        context.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE)
+        "\tduration (seconds): {}",
+        seconds(metadata.duration)
     )?;
 
-    for stream in context.streams() {
-        writeln!(&mut out, "stream #{}:", stream.index())?;
-        writeln!(&mut out, "\ttime_base: {}", stream.time_base())?;
-        writeln!(&mut out, "\tstart_time: {}", stream.start_time())?;
+    for stream in &metadata.streams {
+        writeln!(&mut out, "stream #{}:", stream.index)?;
+        writeln!(&mut out, "\ttime_base: {}", stream.time_base)?;
+        writeln!(&mut out, "\tstart_time: {}", stream.start_time)?;
         writeln!(
             &mut out,
             "\tduration (stream timebase): {}",
-            stream.duration()
+            stream.duration_ts
         )?;
         writeln!(
             &mut out,
-            "\tduration (seconds): {:.6}",
-            stream.duration() as f64 * f64::from(stream.time_base())
+            "\tduration (seconds): {}",
+            seconds(stream.duration)
         )?;
-        writeln!(&mut out, "\tframes: {}", stream.frames())?;
-        writeln!(&mut out, "\tdisposition: {:?}", stream.disposition())?;
-        writeln!(&mut out, "\tdiscard: {:?}", stream.discard())?;
-        writeln!(&mut out, "\trate: {}", stream.rate())?;
-
-        let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
-        writeln!(&mut out, "\tmedium: {:?}", codec.medium())?;
-        writeln!(&mut out, "\tid: {:?}", codec.id())?;
-
-        match codec.medium() {
-            media::Type::Video => {
-                if let Ok(video) = codec.decoder().video() {
-                    writeln!(&mut out, "\tbit_rate: {}", video.bit_rate())?;
-                    writeln!(&mut out, "\tmax_rate: {}", video.max_bit_rate())?;
-                    writeln!(&mut out, "\tdelay: {}", video.delay())?;
-                    writeln!(&mut out, "\tvideo.width: {}", video.width())?;
-                    writeln!(&mut out, "\tvideo.height: {}", video.height())?;
-                    writeln!(&mut out, "\tvideo.format: {:?}", video.format())?;
-                    writeln!(&mut out, "\tvideo.has_b_frames: {}", video.has_b_frames())?;
-                    writeln!(&mut out, "\tvideo.aspect_ratio: {}", video.aspect_ratio())?;
-                    writeln!(&mut out, "\tvideo.color_space: {:?}", video.color_space())?;
-                    writeln!(&mut out, "\tvideo.color_range: {:?}", video.color_range())?;
-                    writeln!(
-                        &mut out,
-                        "\tvideo.color_primaries: {:?}",
-                        video.color_primaries()
-                    )?;
-                    writeln!(
-                        &mut out,
-                        "\tvideo.color_transfer_characteristic: {:?}",
-                        video.color_transfer_characteristic()
-                    )?;
-                    writeln!(
-                        &mut out,
-                        "\tvideo.chroma_location: {:?}",
-                        video.chroma_location()
-                    )?;
-                    writeln!(&mut out, "\tvideo.references: {}", video.references())?;
-                    writeln!(
-                        &mut out,
-                        "\tvideo.intra_dc_precision: {}",
-                        video.intra_dc_precision()
-                    )?;
-                }
+        writeln!(&mut out, "\tframes: {}", stream.frames)?;
+        writeln!(&mut out, "\tdisposition: {}", stream.disposition)?;
+        writeln!(&mut out, "\tdiscard: {}", stream.discard)?;
+        writeln!(&mut out, "\trate: {}", rate(stream.rate))?;
+        writeln!(
+            &mut out,
+            "\tavg_frame_rate: {}",
+            rate(stream.avg_frame_rate)
+        )?;
+        writeln!(&mut out, "\tmedium: {:?}", stream.medium)?;
+        writeln!(&mut out, "\tid: {}", stream.codec)?;
+
+        match &stream.params {
+            CodecParams::Video(video) => {
+                writeln!(&mut out, "\tbit_rate: {}", video.bit_rate)?;
+                writeln!(&mut out, "\tmax_rate: {}", video.max_bit_rate)?;
+                writeln!(&mut out, "\tdelay: {}", video.delay)?;
+                writeln!(&mut out, "\tvideo.width: {}", video.width)?;
+                writeln!(&mut out, "\tvideo.height: {}", video.height)?;
+                writeln!(&mut out, "\tvideo.format: {}", video.pixel_format)?;
+                writeln!(&mut out, "\tvideo.has_b_frames: {}", video.has_b_frames)?;
+                writeln!(
+                    &mut out,
+                    "\tvideo.aspect_ratio: {}",
+                    rate(video.aspect_ratio)
+                )?;
+                writeln!(&mut out, "\tvideo.color_space: {}", video.color.space)?;
+                writeln!(&mut out, "\tvideo.color_range: {:?}", video.color.range)?;
+                writeln!(
+                    &mut out,
+                    "\tvideo.color_primaries: {}",
+                    video.color.primaries
+                )?;
+                writeln!(
+                    &mut out,
+                    "\tvideo.color_transfer_characteristic: {}",
+                    video.color.transfer_characteristic
+                )?;
+                writeln!(
+                    &mut out,
+                    "\tvideo.chroma_location: {}",
+                    video.color.chroma_location
+                )?;
+                writeln!(&mut out, "\tvideo.references: {}", video.references)?;
+                writeln!(
+                    &mut out,
+                    "\tvideo.intra_dc_precision: {}",
+                    video.intra_dc_precision
+                )?;
             }
-            media::Type::Audio => {
-                if let Ok(audio) = codec.decoder().audio() {
-                    writeln!(&mut out, "\tbit_rate: {}", audio.bit_rate())?;
-                    writeln!(&mut out, "\tmax_rate: {}", audio.max_bit_rate())?;
-                    writeln!(&mut out, "\tdelay: {}", audio.delay())?;
-                    writeln!(&mut out, "\taudio.rate: {}", audio.rate())?;
-                    writeln!(&mut out, "\taudio.channels: {}", audio.channels())?;
-                    writeln!(&mut out, "\taudio.format: {:?}", audio.format())?;
-                    writeln!(&mut out, "\taudio.frames: {}", audio.frames())?;
-                    writeln!(&mut out, "\taudio.align: {}", audio.align())?;
-                    writeln!(
-                        &mut out,
-                        "\taudio.channel_layout: {:?}",
-                        audio.channel_layout()
-                    )?;
-                }
+            CodecParams::Audio(audio) => {
+                writeln!(&mut out, "\tbit_rate: {}", audio.bit_rate)?;
+                writeln!(&mut out, "\tmax_rate: {}", audio.max_bit_rate)?;
+                writeln!(&mut out, "\tdelay: {}", audio.delay)?;
+                writeln!(&mut out, "\taudio.rate: {}", audio.rate)?;
+                writeln!(&mut out, "\taudio.channels: {}", audio.channels)?;
+                writeln!(&mut out, "\taudio.format: {}", audio.format)?;
+                writeln!(&mut out, "\taudio.frames: {}", audio.frames)?;
+                writeln!(&mut out, "\taudio.align: {}", audio.align)?;
+                writeln!(&mut out, "\taudio.channel_layout: {}", audio.channel_layout)?;
             }
-            _ => {}
+            CodecParams::Other => {}
+        }
+    }
+
+    for chapter in &metadata.chapters {
+        writeln!(&mut out, "chapter #{}:", chapter.id)?;
+        writeln!(
+            &mut out,
+            "\tstart (seconds): {}",
+            seconds(Some(chapter.start))
+        )?;
+        writeln!(&mut out, "\tend (seconds): {}", seconds(Some(chapter.end)))?;
+        for (k, v) in &chapter.tags {
+            writeln!(&mut out, "\t{k}: {v}")?;
         }
     }
 
@@ -0,0 +1,183 @@
+//! Settling detections of a component across frames.
+
+use std::collections::VecDeque;
+
+use elden_analyzer_kernel::types::time::FramePosition;
+
+use crate::components::{Detection, DetectionPayload};
+
+/// Possible frames not followed by found frames within this number of frames are regarded as absent,
+/// unless `expire_frames` is given in the `[pipeline]` table of the config
+pub const EXPIRE_FRAMES: usize = 60;
+
+/// Detection of a component in a frame, settled by the frames around it.
+#[derive(Debug)]
+pub enum AccumDetection {
+    Found(Option<DetectionPayload>),
+    /// Absent frame just before a span, where the component may be fading in
+    LeadIn,
+    /// Absent frame within a span, bridged by following frames within the promotion window
+    Bridged,
+    Absent,
+}
+
+/// Settles detections of a component.
+///
+/// Results of frames are emitted in order once they are settled by the following frames.
+#[derive(Debug)]
+pub struct DetectionAccumulator {
+    name: String,
+
+    pending_packets: VecDeque<(usize, AccumDetection)>,
+    found_start: Option<FramePosition>,
+    /// Number of frames after a found frame within which possible or found frames continue the span
+    promote_window: usize,
+    /// Absent frames after a found frame held back until it is known whether the span continues.
+    /// Frames may be dropped for live sources, so the window is counted in received frames rather than frame indices.
+    gap: VecDeque<FramePosition>,
+    possibles: VecDeque<(FramePosition, Option<DetectionPayload>)>,
+    /// Possible frames not followed by found frames within this number of frames are regarded as absent
+    expire_frames: usize,
+    /// Number of absent frames before a span to be recognized as lead-in
+    lead_in: usize,
+    /// Last absent frames held back until it is known whether a span follows them
+    absents: VecDeque<FramePosition>,
+}
+
+impl DetectionAccumulator {
+    pub fn new(name: String, lead_in: usize, promote_window: usize, expire_frames: usize) -> Self {
+        Self {
+            name,
+            pending_packets: VecDeque::new(),
+            found_start: None,
+            promote_window: promote_window.max(1),
+            gap: VecDeque::new(),
+            possibles: VecDeque::new(),
+            expire_frames,
+            lead_in,
+            absents: VecDeque::new(),
+        }
+    }
+
+    /// Returns `true` if the result of the frame at `pos` is settled and can be taken by [`Self::pop_packet`].
+    pub fn handled(&self, pos: FramePosition) -> bool {
+        self.pending_packets
+            .front()
+            .map(|(idx, _)| *idx == pos.index())
+            .unwrap_or_default()
+    }
+
+    /// Takes the settled result of the earliest frame with its index.
+    pub fn pop_packet(&mut self) -> Option<(usize, AccumDetection)> {
+        self.pending_packets.pop_front()
+    }
+
+    pub fn receive_frame(&mut self, pos: FramePosition, result: Detection) {
+        let in_window = self.found_start.is_some() && self.gap.len() < self.promote_window;
+        match (result, in_window) {
+            (Detection::Found(payload), _) | (Detection::Possible(payload), true) => {
+                self.bridge_gap();
+                self.handle_found(pos, payload);
+            }
+            (Detection::Possible(payload), false) => {
+                self.handle_possible(pos, payload);
+            }
+            (Detection::Absent, _) => {
+                if self.found_start.is_some() && self.gap.len() + 1 < self.promote_window {
+                    self.gap.push_back(pos);
+                } else {
+                    self.close_gap();
+                    self.handle_absent(pos);
+                }
+            }
+        }
+    }
+
+    /// Settles all frames received so far. `pos` is the position after the last frame, which is regarded as absent.
+    pub fn receive_end_of_frames(&mut self, pos: FramePosition) {
+        self.close_gap();
+        self.handle_absent(pos);
+        // No span follows the held frames
+        self.pending_packets.extend(
+            self.absents
+                .drain(..)
+                .map(|pos| (pos.index(), AccumDetection::Absent)),
+        );
+    }
+
+    /// Regards the held absent frames as in the span, since the span continues after them.
+    fn bridge_gap(&mut self) {
+        self.pending_packets.extend(
+            self.gap
+                .drain(..)
+                .map(|pos| (pos.index(), AccumDetection::Bridged)),
+        );
+    }
+
+    /// Ends the span at the first held absent frame, since the span does not continue after them.
+    fn close_gap(&mut self) {
+        let gap = self.gap.drain(..).collect::<Vec<_>>();
+        for pos in gap {
+            self.handle_absent(pos);
+        }
+    }
+
+    fn handle_found(&mut self, pos: FramePosition, payload: Option<DetectionPayload>) {
+        if self.found_start.is_none() {
+            if let Some((pos, _)) = self.possibles.front() {
+                self.found_start = Some(*pos);
+            } else {
+                self.found_start = Some(pos);
+            }
+        }
+        self.pending_packets.extend(
+            self.absents
+                .drain(..)
+                .map(|pos| (pos.index(), AccumDetection::LeadIn)),
+        );
+        self.pending_packets.extend(
+            self.possibles
+                .drain(..)
+                .map(|(pos, payload)| (pos.index(), AccumDetection::Found(payload))),
+        );
+        self.pending_packets
+            .push_back((pos.index(), AccumDetection::Found(payload)));
+    }
+
+    fn handle_possible(&mut self, pos: FramePosition, payload: Option<DetectionPayload>) {
+        self.possibles.push_back((pos, payload));
+        let drain_count = self
+            .possibles
+            .iter()
+            .take_while(|(pkt_pos, _)| pkt_pos.index() + self.expire_frames < pos.index())
+            .count();
+        let expired = self.possibles.drain(..drain_count).collect::<Vec<_>>();
+        for (pos, _) in expired {
+            self.push_absent(pos);
+        }
+    }
+
+    fn handle_absent(&mut self, pos: FramePosition) {
+        let possibles = self.possibles.drain(..).collect::<Vec<_>>();
+        for (pos, _) in possibles {
+            self.push_absent(pos);
+        }
+        self.push_absent(pos);
+
+        if let Some(start) = self.found_start.take() {
+            assert!(self.possibles.is_empty());
+            let end = pos;
+            tracing::debug!(name = self.name, %start, %end, "found UI");
+        }
+    }
+
+    /// Holds the absent frame as a lead-in candidate, and emits ones too far from the next frame.
+    fn push_absent(&mut self, pos: FramePosition) {
+        self.absents.push_back(pos);
+        while self.absents.len() > self.lead_in {
+            let pos = self.absents.pop_front().unwrap();
+            self.pending_packets
+                .push_back((pos.index(), AccumDetection::Absent));
+        }
+    }
+}
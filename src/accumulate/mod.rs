@@ -0,0 +1,37 @@
+//! Accumulators turning results of each frame into spans in which components are shown.
+//!
+//! The analysis pipeline of `analyze` detects components in each frame, settles the detections with
+//! [`detection::DetectionAccumulator`], recognizes texts in frames of spans and joins them with [`text::TextAccumulator`].
+//! [`testing::Harness`] drives both accumulators with synthetic results, without decoding videos or recognizing texts.
+//! Texts of components always shown, such as the rune count, are accumulated by value with [`runes::RuneAccumulator`] instead.
+
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+
+use self::text::{AccumResult, FrameTexts};
+
+pub mod detection;
+pub mod runes;
+pub mod testing;
+pub mod text;
+
+/// Accumulator turning texts recognized in frames of a component into spans.
+pub trait AccumulateSpans: Send {
+    fn name(&self) -> &str;
+
+    /// Returns the number of spans emitted so far.
+    fn num_spans(&self) -> usize;
+
+    /// Receives texts of the frame at `pos`, and returns the span ended by the frame if any.
+    fn receive_frame(
+        &mut self,
+        pos: FramePosition,
+        result: FrameTexts,
+        igt: Option<Duration>,
+    ) -> Option<AccumResult>;
+
+    fn receive_end_of_frames(&mut self, pos: FramePosition) -> Option<AccumResult>;
+
+    /// Returns the index of the frame before which all spans of this component have been emitted,
+    /// after receiving the frame at `pos`.
+    fn final_until(&self, pos: FramePosition) -> usize;
+}
@@ -0,0 +1,243 @@
+//! Tracking the number of runes held.
+//!
+//! The rune counter is shown whenever the HUD is, so its spans are not intervals where the component is shown,
+//! but intervals where the number stays the same. Each span has the number as its text.
+
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+
+use super::{
+    text::{AccumResult, FrameTexts, LineText},
+    AccumulateSpans,
+};
+use crate::operator::{Confidence, ConfidenceTally, Recognition};
+
+/// Consecutive readings of a number needed to accept a plausible change to it
+const STABLE_FRAMES: usize = 3;
+
+/// Consecutive readings of a number needed to accept an implausible change to it,
+/// such as losing runes on death or spending them
+const JUMP_FRAMES: usize = 15;
+
+/// Maximum gain of runes at once regarded as plausible, more than any enemy or item gives
+const MAX_GAIN: u64 = 1_000_000;
+
+/// Turns numbers of runes recognized in frames into spans of the same number.
+///
+/// OCR noise is smoothed by accepting a new number only after it is read in several frames in a row,
+/// more frames if the change is not a plausible gain.
+/// Frames where the counter is hidden or unreadable neither end nor interrupt the current number.
+///
+/// The span of the current number is emitted only when the number changes,
+/// so outputs waiting for all components (such as the wide TSV) are held until then.
+#[derive(Debug)]
+pub struct RuneAccumulator {
+    name: String,
+    num_spans: usize,
+    current: Option<Reading>,
+    /// Different number being read, not accepted yet
+    candidate: Option<Reading>,
+}
+
+#[derive(Debug)]
+struct Reading {
+    runes: u64,
+    start: FramePosition,
+    igt: Option<Duration>,
+    tally: ConfidenceTally,
+}
+
+impl RuneAccumulator {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            num_spans: 0,
+            current: None,
+            candidate: None,
+        }
+    }
+
+    fn handle_reading(
+        &mut self,
+        pos: FramePosition,
+        runes: u64,
+        tally: ConfidenceTally,
+        igt: Option<Duration>,
+    ) -> Option<AccumResult> {
+        if let Some(current) = self.current.as_mut().filter(|c| c.runes == runes) {
+            current.tally = current.tally + tally;
+            self.candidate = None;
+            return None;
+        }
+
+        let candidate = match &mut self.candidate {
+            Some(candidate) if candidate.runes == runes => {
+                candidate.tally = candidate.tally + tally;
+                candidate
+            }
+            candidate => candidate.insert(Reading {
+                runes,
+                start: pos,
+                igt,
+                tally,
+            }),
+        };
+        let required = match &self.current {
+            Some(current) if !is_plausible_change(current.runes, runes) => JUMP_FRAMES,
+            _ => STABLE_FRAMES,
+        };
+        if (candidate.tally.count() as usize) < required {
+            return None;
+        }
+
+        let candidate = self.candidate.take().unwrap();
+        tracing::trace!(
+            from = self.current.as_ref().map(|c| c.runes),
+            to = candidate.runes,
+            "rune count changed"
+        );
+        let end = candidate.start;
+        let last = self.current.replace(candidate)?;
+        Some(self.result(last, end))
+    }
+
+    fn result(&mut self, reading: Reading, end: FramePosition) -> AccumResult {
+        self.num_spans += 1;
+        let text = reading.runes.to_string();
+        AccumResult {
+            name: self.name.clone(),
+            start: reading.start,
+            end,
+            text: text.clone(),
+            igt: reading.igt,
+            lines: vec![LineText {
+                text: text.clone(),
+                confidence: reading.tally.mean(),
+                candidates: vec![(text, Confidence::ONE)],
+            }],
+            rarity: None,
+        }
+    }
+}
+
+impl AccumulateSpans for RuneAccumulator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn num_spans(&self) -> usize {
+        self.num_spans
+    }
+
+    fn receive_frame(
+        &mut self,
+        pos: FramePosition,
+        result: FrameTexts,
+        igt: Option<Duration>,
+    ) -> Option<AccumResult> {
+        let FrameTexts::Found(texts) = result else {
+            return None;
+        };
+        let Some(Recognition::Found(text, conf)) = texts.result.first() else {
+            return None;
+        };
+        let runes = text.parse().ok()?;
+        let mut tally = ConfidenceTally::default();
+        tally.push(*conf);
+        self.handle_reading(pos, runes, tally, igt)
+    }
+
+    fn receive_end_of_frames(&mut self, pos: FramePosition) -> Option<AccumResult> {
+        self.candidate = None;
+        let last = self.current.take()?;
+        Some(self.result(last, pos))
+    }
+
+    fn final_until(&self, pos: FramePosition) -> usize {
+        match (&self.current, &self.candidate) {
+            (Some(reading), _) | (None, Some(reading)) => reading.start.index(),
+            (None, None) => pos.index() + 1,
+        }
+    }
+}
+
+/// Returns `true` if the number of runes can change from `from` to `to` by picking up runes.
+fn is_plausible_change(from: u64, to: u64) -> bool {
+    to > from && to - from <= MAX_GAIN
+}
+
+#[cfg(test)]
+mod tests {
+    use elden_analyzer_kernel::types::time::Timestamp;
+    use num_rational::Ratio;
+
+    use crate::accumulate::testing::{found, possible, FPS};
+
+    use super::*;
+
+    fn run(frames: &[Option<&str>]) -> Vec<String> {
+        let pos = |idx: usize| FramePosition::new(idx, Timestamp::new(Ratio::new(idx as i64, FPS)));
+        let mut accum = RuneAccumulator::new("runes".to_owned());
+        let mut spans = vec![];
+        for (idx, text) in frames.iter().enumerate() {
+            let texts = match text {
+                Some(text) => FrameTexts::Found(found([*text])),
+                None => FrameTexts::Found(possible(["?"])),
+            };
+            spans.extend(accum.receive_frame(pos(idx), texts, None));
+        }
+        spans.extend(accum.receive_end_of_frames(pos(frames.len())));
+        assert_eq!(accum.num_spans(), spans.len());
+        spans
+            .into_iter()
+            .map(|result| {
+                format!(
+                    "{}-{} {}",
+                    result.start.index(),
+                    result.end.index(),
+                    result.text
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gains_after_stable_frames() {
+        let mut frames = vec![Some("100"); 4];
+        frames.extend([Some("150"), None, Some("150"), Some("150"), Some("150")]);
+        assert_eq!(run(&frames), ["0-4 100", "4-9 150"]);
+    }
+
+    #[test]
+    fn noise_ignored() {
+        let mut frames = vec![Some("1234"); 4];
+        frames.extend([Some("1284"), Some("1284"), Some("1234"), Some("7234")]);
+        frames.extend([Some("1234"); 4]);
+        assert_eq!(run(&frames), ["0-12 1234"]);
+    }
+
+    #[test]
+    fn loss_after_jump_frames() {
+        let mut frames = vec![Some("5000"); 4];
+        frames.extend(vec![Some("0"); JUMP_FRAMES - 1]);
+        assert_eq!(run(&frames), [format!("0-{} 5000", frames.len())]);
+
+        frames.push(Some("0"));
+        assert_eq!(
+            run(&frames),
+            ["0-4 5000".to_owned(), format!("4-{} 0", frames.len())]
+        );
+    }
+
+    #[test]
+    fn final_until_start_of_current_number() {
+        let pos = |idx: usize| FramePosition::new(idx, Timestamp::new(Ratio::new(idx as i64, FPS)));
+        let mut accum = RuneAccumulator::new("runes".to_owned());
+        assert_eq!(accum.final_until(pos(0)), 1);
+        for idx in 2..10 {
+            accum.receive_frame(pos(idx), FrameTexts::Found(found(["10"])), None);
+        }
+        assert_eq!(accum.final_until(pos(9)), 2);
+        accum.receive_frame(pos(10), FrameTexts::Found(found(["20"])), None);
+        assert_eq!(accum.final_until(pos(10)), 2);
+    }
+}
@@ -0,0 +1,262 @@
+//! Harness driving the accumulators with synthetic results.
+//!
+//! # Examples
+//! ```
+//! use elden_analyzer::{accumulate::testing::{found, Harness}, components::Detection};
+//!
+//! let mut harness = Harness::new("main_item", 0, 1);
+//! harness.frame(Detection::Found(None), found(["聖杯瓶"]));
+//! harness.frame(Detection::Possible(None), found(["聖杯瓶"]));
+//! harness.frame(Detection::Absent, found([]));
+//! let spans = harness.finish();
+//! assert_eq!(spans[0].to_string(), "00:00:00.000-00:00:00.066 聖杯瓶 (main_item)");
+//! ```
+
+use std::collections::VecDeque;
+
+use elden_analyzer_kernel::types::time::{FramePosition, Timestamp};
+use num_rational::Ratio;
+
+use crate::{
+    components::{Detection, ExtractedTexts},
+    io::spans::Span,
+    items::ItemTranslation,
+    operator::{Confidence, Recognition},
+};
+
+use super::{
+    detection::{AccumDetection, DetectionAccumulator, EXPIRE_FRAMES},
+    text::{FrameTexts, TextAccumulator, MIN_POSSIBLE_SHARE},
+    AccumulateSpans,
+};
+
+/// Frame rate of frames given to [`Harness`]
+pub const FPS: i64 = 30;
+
+/// Drives the accumulators of a component as the pipeline of `analyze` does,
+/// with detections and recognized texts given for each frame instead of decoding videos and recognizing texts.
+///
+/// Frames are numbered from 0 at [`FPS`].
+#[derive(Debug)]
+pub struct Harness {
+    detection: DetectionAccumulator,
+    text: TextAccumulator,
+    next_index: usize,
+    /// Frames received but not settled by the detection accumulator yet
+    pending_frames: VecDeque<(FramePosition, ExtractedTexts)>,
+    spans: Vec<Span>,
+}
+
+impl Harness {
+    /// Creates a harness with `lead_in` and `promote_window` as in `analyze --lead-in` and `--promote-window`.
+    pub fn new(name: &str, lead_in: usize, promote_window: usize) -> Self {
+        Self::with_translation(name, lead_in, promote_window, None)
+    }
+
+    pub fn with_translation(
+        name: &str,
+        lead_in: usize,
+        promote_window: usize,
+        translate_items: Option<ItemTranslation>,
+    ) -> Self {
+        Self {
+            detection: DetectionAccumulator::new(
+                name.to_owned(),
+                lead_in,
+                promote_window,
+                EXPIRE_FRAMES,
+            ),
+            text: TextAccumulator::new(name.to_owned(), translate_items, MIN_POSSIBLE_SHARE),
+            next_index: 0,
+            pending_frames: VecDeque::new(),
+            spans: vec![],
+        }
+    }
+
+    /// Receives the next frame, where `texts` are recognized if the frame is in a span or a lead-in.
+    pub fn frame(&mut self, detection: Detection, texts: ExtractedTexts) {
+        let pos = self.next_position();
+        self.pending_frames.push_back((pos, texts));
+        self.detection.receive_frame(pos, detection);
+        self.flush();
+    }
+
+    /// Skips the next `count` frames, as frames dropped from live sources.
+    pub fn drop_frames(&mut self, count: usize) {
+        self.next_index += count;
+    }
+
+    /// Ends the frames, and returns all spans in the order they are emitted.
+    pub fn finish(mut self) -> Vec<Span> {
+        let pos = self.next_position();
+        self.detection.receive_end_of_frames(pos);
+        self.flush();
+        assert!(self.pending_frames.is_empty());
+        assert!(matches!(
+            self.detection.pop_packet(),
+            Some((idx, AccumDetection::Absent)) if idx == pos.index()
+        ));
+        if let Some(result) = self.text.receive_end_of_frames(pos) {
+            self.spans.push(result.into_span(None));
+        }
+        self.spans
+    }
+
+    fn next_position(&mut self) -> FramePosition {
+        let idx = self.next_index;
+        self.next_index += 1;
+        FramePosition::new(idx, Timestamp::new(Ratio::new(idx as i64, FPS)))
+    }
+
+    /// Passes settled frames to the text accumulator.
+    fn flush(&mut self) {
+        while let Some((pos, _)) = self.pending_frames.front() {
+            if !self.detection.handled(*pos) {
+                break;
+            }
+            let (pos, texts) = self.pending_frames.pop_front().unwrap();
+            let (_, detection) = self.detection.pop_packet().unwrap();
+            let texts = match detection {
+                AccumDetection::Found(_) => FrameTexts::Found(texts),
+                AccumDetection::LeadIn => FrameTexts::LeadIn(texts),
+                AccumDetection::Bridged => FrameTexts::Bridged,
+                AccumDetection::Absent => FrameTexts::Absent,
+            };
+            if let Some(result) = self.text.receive_frame(pos, texts, None) {
+                self.spans.push(result.into_span(None));
+            }
+        }
+    }
+}
+
+/// Returns texts of a frame confidently recognized.
+pub fn found<'a>(texts: impl IntoIterator<Item = &'a str>) -> ExtractedTexts {
+    let result = texts
+        .into_iter()
+        .map(|text| Recognition::Found(text.to_owned(), Confidence::new(90)))
+        .collect();
+    ExtractedTexts {
+        result,
+        rarity: None,
+    }
+}
+
+/// Returns texts of a frame not reliably recognized.
+pub fn possible<'a>(texts: impl IntoIterator<Item = &'a str>) -> ExtractedTexts {
+    let result = texts
+        .into_iter()
+        .map(|text| Recognition::Possible(text.to_owned(), Confidence::new(30)))
+        .collect();
+    ExtractedTexts {
+        result,
+        rarity: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::i18n::Lang;
+
+    use super::*;
+
+    fn run(
+        mut harness: Harness,
+        frames: impl IntoIterator<Item = (Detection, ExtractedTexts)>,
+    ) -> Vec<String> {
+        for (detection, texts) in frames {
+            harness.frame(detection, texts);
+        }
+        harness.finish().iter().map(Span::to_string).collect()
+    }
+
+    #[test]
+    fn possible_frames_follow_found_frames() {
+        let frames = [
+            (Detection::Possible(None), possible(["a"])),
+            (Detection::Found(None), found(["a"])),
+            (Detection::Possible(None), possible(["b"])),
+            (Detection::Absent, found([])),
+            (Detection::Possible(None), possible(["c"])),
+            (Detection::Absent, found([])),
+        ];
+        assert_eq!(
+            run(Harness::new("x", 0, 1), frames),
+            ["00:00:00.000-00:00:00.100 a (x)"]
+        );
+    }
+
+    #[test]
+    fn lead_in_starts_at_readable_frame() {
+        let frames = [
+            (Detection::Absent, found(["a"])),
+            (Detection::Absent, possible(["b"])),
+            (Detection::Absent, found(["a"])),
+            (Detection::Found(None), found(["a"])),
+            (Detection::Absent, found([])),
+        ];
+        assert_eq!(
+            run(Harness::new("x", 3, 1), frames),
+            ["00:00:00.066-00:00:00.133 a (x)"]
+        );
+    }
+
+    #[test]
+    fn promote_window_bridges_flicker() {
+        let frames = || {
+            [
+                (Detection::Found(None), found(["a"])),
+                (Detection::Absent, found([])),
+                (Detection::Possible(None), found(["a"])),
+                (Detection::Absent, found([])),
+                (Detection::Absent, found([])),
+                (Detection::Found(None), found(["a"])),
+            ]
+        };
+        assert_eq!(
+            run(Harness::new("x", 0, 1), frames()),
+            [
+                "00:00:00.000-00:00:00.033 a (x)",
+                "00:00:00.166-00:00:00.200 a (x)",
+            ]
+        );
+        assert_eq!(
+            run(Harness::new("x", 0, 2), frames()),
+            [
+                "00:00:00.000-00:00:00.100 a (x)",
+                "00:00:00.166-00:00:00.200 a (x)",
+            ]
+        );
+        assert_eq!(
+            run(Harness::new("x", 0, 3), frames()),
+            ["00:00:00.000-00:00:00.200 a (x)"]
+        );
+    }
+
+    #[test]
+    fn dropped_frames_continue_span() {
+        let mut harness = Harness::new("x", 0, 1);
+        harness.frame(Detection::Found(None), found(["a"]));
+        harness.drop_frames(5);
+        harness.frame(Detection::Possible(None), found(["a"]));
+        assert_eq!(run(harness, []), ["00:00:00.000-00:00:00.233 a (x)"]);
+    }
+
+    #[test]
+    fn optional_lines_and_translation() {
+        let translation = ItemTranslation {
+            lang: Lang::En,
+            replace: false,
+        };
+        let frames = [
+            (Detection::Found(None), found(["緋雫の聖杯瓶", ""])),
+            (Detection::Found(None), found(["緋雫の聖杯瓶", ""])),
+        ];
+        assert_eq!(
+            run(
+                Harness::with_translation("main_item", 0, 1, Some(translation)),
+                frames
+            ),
+            ["00:00:00.000-00:00:00.066 緋雫の聖杯瓶 / Flask of Crimson Tears (main_item)"]
+        );
+    }
+}
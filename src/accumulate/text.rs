@@ -0,0 +1,387 @@
+//! Joining texts recognized in frames of spans.
+
+use std::{borrow::Borrow, cmp::Reverse, collections::HashMap, iter, mem};
+
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+
+use super::AccumulateSpans;
+use crate::{
+    components::ExtractedTexts,
+    io::{event_id::EventId, spans::Span},
+    items::{ItemTranslation, Rarity},
+    operator::{Confidence, ConfidenceTally, Recognition},
+};
+
+/// Minimum share (percentage) of a possible text in the confidences of all possible texts to be output,
+/// unless `min_possible_share` is given in the `[pipeline]` table of the config
+pub const MIN_POSSIBLE_SHARE: i32 = 10;
+
+/// Texts recognized in a frame.
+#[derive(Debug)]
+pub enum FrameTexts {
+    Found(ExtractedTexts),
+    /// Texts of a frame just before a span, which may not be readable yet
+    LeadIn(ExtractedTexts),
+    /// Frame within a span where the component is not shown, whose texts are not recognized
+    Bridged,
+    Absent,
+}
+
+/// A span of a component with texts joined over its frames.
+#[derive(Debug, Clone)]
+pub struct AccumResult {
+    pub name: String,
+    pub start: FramePosition,
+    pub end: FramePosition,
+    pub text: String,
+    /// In-game time at the start of the span
+    pub igt: Option<Duration>,
+    /// Texts of each line joined into `text`
+    pub lines: Vec<LineText>,
+    /// Rarity of the item inferred in most frames of the span
+    pub rarity: Option<Rarity>,
+}
+
+/// Text of a line of a component joined over the frames of a span.
+#[derive(Debug, Clone)]
+pub struct LineText {
+    pub text: String,
+    /// Mean confidence of the recognitions from which the text is chosen
+    pub confidence: Option<Confidence>,
+    /// Candidates of the text and their shares in the confidences of all candidates, most likely first.
+    /// Texts not reliably recognized are prefixed with `??`.
+    pub candidates: Vec<(String, Confidence)>,
+}
+
+impl AccumResult {
+    pub fn into_span(self, id: Option<EventId>) -> Span {
+        Span {
+            name: self.name,
+            start: self.start.timestamp(),
+            end: self.end.timestamp(),
+            text: self.text,
+            igt: self.igt,
+            id,
+        }
+    }
+
+    /// Returns the confidence of the least confident line, or `None` if no line has a confidence.
+    pub fn min_confidence(&self) -> Option<Confidence> {
+        self.lines.iter().filter_map(|line| line.confidence).min()
+    }
+}
+
+/// Joins texts of a component recognized in frames of each span.
+#[derive(Debug)]
+pub struct TextAccumulator {
+    name: String,
+    found_start: Option<FramePosition>,
+    found_igt: Option<Duration>,
+    accum: Vec<InnerAccumulator>,
+    /// Number of frames in which each rarity is inferred
+    rarities: HashMap<Rarity, usize>,
+    num_spans: usize,
+    /// Lead-in frames received since the last absent frame
+    lead_in: Vec<(FramePosition, ExtractedTexts, Option<Duration>)>,
+    translate_items: Option<ItemTranslation>,
+    /// Minimum share (percentage) of a possible text to be output
+    min_possible_share: i32,
+}
+
+impl TextAccumulator {
+    pub fn new(
+        name: String,
+        translate_items: Option<ItemTranslation>,
+        min_possible_share: i32,
+    ) -> Self {
+        Self {
+            name,
+            found_start: None,
+            found_igt: None,
+            accum: vec![],
+            rarities: HashMap::new(),
+            num_spans: 0,
+            lead_in: vec![],
+            translate_items,
+            min_possible_share,
+        }
+    }
+
+    /// Starts the span from the earliest lead-in frame after which all lead-in frames are readable.
+    fn accept_lead_in(&mut self) {
+        let lead_in = mem::take(&mut self.lead_in);
+        let num_readable = lead_in
+            .iter()
+            .rev()
+            .take_while(|(_, text, _)| is_readable(text))
+            .count();
+        let skip = lead_in.len() - num_readable;
+        for (pos, text, igt) in lead_in.into_iter().skip(skip) {
+            self.handle_found(pos, text, igt);
+        }
+    }
+
+    fn handle_found(
+        &mut self,
+        pos: FramePosition,
+        text: ExtractedTexts,
+        igt: Option<Duration>,
+    ) -> Option<AccumResult> {
+        if self.found_start.is_none() {
+            self.found_start = Some(pos);
+        }
+        // The timer may be unreadable at the first frame,
+        // so use the earliest readable one extrapolated back to the start of the span
+        if self.found_igt.is_none() {
+            self.found_igt = igt.map(|igt| {
+                let start = self.found_start.unwrap();
+                Duration::new(igt.as_ratio() - (pos.timestamp() - start.timestamp()).as_ratio())
+            });
+        }
+
+        if self.accum.is_empty() {
+            self.accum
+                .extend(iter::repeat_with(Default::default).take(text.result.len()));
+        }
+        assert_eq!(self.accum.len(), text.result.len());
+
+        if let Some(rarity) = text.rarity {
+            *self.rarities.entry(rarity).or_default() += 1;
+        }
+
+        for (accum, result) in self.accum.iter_mut().zip(text.result) {
+            accum.insert(result);
+        }
+
+        None
+    }
+
+    fn handle_absent(&mut self, pos: FramePosition) -> Option<AccumResult> {
+        self.lead_in.clear();
+        let start = self.found_start.take()?;
+        let end = pos;
+
+        let mut lines = vec![];
+        for accum in &mut self.accum {
+            if accum.is_empty_line() {
+                // Optional lines, such as supplementary lines of items, are omitted
+                accum.reset();
+                continue;
+            }
+            let mut line = accum.get_line(self.min_possible_share);
+            if let Some(translation) = &self.translate_items {
+                line.text = translation.apply(line.text);
+            }
+            lines.push(line);
+            accum.reset();
+        }
+
+        let result = AccumResult {
+            name: self.name.clone(),
+            start,
+            end,
+            text: lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            igt: self.found_igt.take(),
+            lines,
+            rarity: mem::take(&mut self.rarities)
+                .into_iter()
+                .max_by_key(|(rarity, count)| (*count, *rarity as u8))
+                .map(|(rarity, _)| rarity),
+        };
+        self.num_spans += 1;
+        Some(result)
+    }
+}
+
+impl AccumulateSpans for TextAccumulator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn num_spans(&self) -> usize {
+        self.num_spans
+    }
+
+    fn receive_frame(
+        &mut self,
+        pos: FramePosition,
+        result: FrameTexts,
+        igt: Option<Duration>,
+    ) -> Option<AccumResult> {
+        match result {
+            FrameTexts::Found(text) => {
+                self.accept_lead_in();
+                self.handle_found(pos, text, igt)
+            }
+            FrameTexts::LeadIn(text) => {
+                self.lead_in.push((pos, text, igt));
+                None
+            }
+            FrameTexts::Bridged => None,
+            FrameTexts::Absent => self.handle_absent(pos),
+        }
+    }
+
+    fn receive_end_of_frames(&mut self, pos: FramePosition) -> Option<AccumResult> {
+        self.handle_absent(pos)
+    }
+
+    fn final_until(&self, pos: FramePosition) -> usize {
+        let lead_in_start = self.lead_in.first().map(|(start, _, _)| *start);
+        match self.found_start.or(lead_in_start) {
+            Some(start) => start.index(),
+            None => pos.index() + 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct InnerAccumulator {
+    found: HashMap<String, ConfidenceTally>,
+    possible: HashMap<String, ConfidenceTally>,
+}
+
+impl InnerAccumulator {
+    fn insert(&mut self, result: Recognition) {
+        match result {
+            Recognition::Found(text, conf) => {
+                self.found.entry(text).or_default().push(conf);
+            }
+            Recognition::Possible(text, conf) => {
+                self.possible.entry(text).or_default().push(conf);
+            }
+        }
+    }
+
+    fn get_line(&self, min_possible_share: i32) -> LineText {
+        if !self.found.is_empty() {
+            let (total, candidates) = candidates(&self.found, |text| text.to_owned());
+            return LineText {
+                text: join_texts(self.found.keys().map(|s| s.as_str())),
+                confidence: total.mean(),
+                candidates,
+            };
+        }
+
+        // Share of each text in the expected number of correct observations
+        let (total, texts) = candidates(&self.possible, |text| format!("??{text}"));
+        let threshold = Confidence::new(min_possible_share);
+
+        let filtered = texts
+            .iter()
+            .filter(|(_, share)| *share >= threshold)
+            .collect::<Vec<_>>();
+        tracing::debug!(threshold = ?threshold, ?filtered, ?texts);
+
+        let text = if filtered.is_empty() {
+            join_texts(texts.iter().map(|(text, _)| text.as_str()))
+        } else {
+            join_texts(filtered.iter().map(|(text, _)| text.as_str()))
+        };
+        LineText {
+            text,
+            confidence: total.mean(),
+            candidates: texts,
+        }
+    }
+
+    /// Returns `true` if only empty texts are recognized.
+    fn is_empty_line(&self) -> bool {
+        !self.found.is_empty()
+            && self.possible.is_empty()
+            && self.found.keys().all(String::is_empty)
+    }
+
+    fn reset(&mut self) {
+        self.found.clear();
+        self.possible.clear();
+    }
+}
+
+/// Returns the total of `tallies` and the texts formatted by `format` with their shares in the total,
+/// sorted by descending shares.
+fn candidates(
+    tallies: &HashMap<String, ConfidenceTally>,
+    format: impl Fn(&str) -> String,
+) -> (ConfidenceTally, Vec<(String, Confidence)>) {
+    let total = tallies.values().sum::<ConfidenceTally>();
+    let mut texts = tallies
+        .iter()
+        .map(|(text, tally)| (format(text), tally.share_in(&total)))
+        .collect::<Vec<_>>();
+    texts.sort_by_key(|(_, share)| Reverse(*share));
+    (total, texts)
+}
+
+/// Returns `true` if all texts of a frame are recognized confidently.
+fn is_readable(text: &ExtractedTexts) -> bool {
+    text.result
+        .iter()
+        .all(|res| matches!(res, Recognition::Found(text, _) if !text.is_empty()))
+}
+
+fn join_texts<S, I>(texts: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: Borrow<str>,
+{
+    const SEP: &str = "|";
+    const OPEN: &str = "{";
+    const CLOSE: &str = "}";
+
+    let mut texts = texts.into_iter();
+    let Some(first) = texts.next() else {
+        return format!("{OPEN}{CLOSE}");
+    };
+    let Some(second) = texts.next() else {
+        return first.borrow().to_owned();
+    };
+
+    let mut result = format!("{OPEN}{}{SEP}{}", first.borrow(), second.borrow());
+    for text in texts {
+        result += SEP;
+        result += text.borrow();
+    }
+
+    result += CLOSE;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Ratio;
+
+    use super::*;
+
+    #[test]
+    fn test_join_texts() {
+        assert_eq!(join_texts::<&str, _>([]), "{}");
+        assert_eq!(join_texts(["a"]), "a");
+        assert_eq!(join_texts(["a", "b"]), "{a|b}");
+    }
+
+    #[test]
+    fn long_span_possible_texts() {
+        let mut accum = InnerAccumulator::default();
+        for i in 0..1_000_000 {
+            let (text, conf) = match i % 20 {
+                0 => ("noise", Confidence::new(10)),
+                1..=6 => ("bar", Confidence::new(60) * Ratio::new(2, 3)),
+                _ => ("foo", Confidence::new(90) * Ratio::new(6, 7)),
+            };
+            accum.insert(Recognition::Possible(text.to_owned(), conf));
+        }
+        let line = accum.get_line(MIN_POSSIBLE_SHARE);
+        assert_eq!(line.text, "{??foo|??bar}");
+        let candidates = line
+            .candidates
+            .iter()
+            .map(|(text, _)| text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, ["??foo", "??bar", "??noise"]);
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Flag aborting analyses, shared by the caller and the pipelines.
+///
+/// Pipelines stop decoding when it is cancelled, and frames already decoded go through the remaining stages,
+/// so that outputs are written for the frames analyzed so far.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::analyzer::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let cloned = token.clone();
+/// assert!(!cloned.is_cancelled());
+/// token.cancel();
+/// assert!(cloned.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the pipelines sharing the token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
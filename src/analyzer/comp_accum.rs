@@ -0,0 +1,154 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{mpsc, Arc},
+};
+
+use color_eyre::eyre;
+use elden_analyzer_collections::seq_iter::SeqIter;
+use elden_analyzer_kernel::types::time::FramePosition;
+use elden_analyzer_video::capture::Frame;
+
+use crate::{
+    accumulate::detection::{AccumDetection, DetectionAccumulator},
+    components::ComponentContainer,
+};
+
+use super::{comp_detect, scores::ScoreWriter, FrameObserver};
+
+#[derive(Debug)]
+pub enum Packet {
+    Frame {
+        pos: FramePosition,
+        frame: Frame,
+        result: Box<ComponentContainer<AccumDetection>>,
+    },
+    EndOfFrames {
+        pos: FramePosition,
+    },
+}
+
+impl Packet {
+    pub fn position(&self) -> FramePosition {
+        match self {
+            Packet::Frame { pos, .. } => *pos,
+            Packet::EndOfFrames { pos } => *pos,
+        }
+    }
+}
+
+/// Frames that a pipeline analyzing a segment of the video is responsible for.
+///
+/// Frames around the segment are also decoded to settle detections near its boundaries,
+/// but they are not sent to later stages.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// Indices of frames sent to later stages
+    pub frames: Range<usize>,
+    /// Whether the segment is the last one, whose end of frames is sent
+    pub last: bool,
+}
+
+impl Segment {
+    /// Number of frames decoded before and after a segment.
+    ///
+    /// A frame is settled by the frames within `expire_frames`, `lead_in` and `promote_window` frames after it,
+    /// and a run of possible frames following a found frame is continued from before the segment
+    /// unless it is longer than the margin.
+    /// `lead_in` and `promote_window` count frames sampled every `step` frames.
+    pub fn margin(
+        expire_frames: usize,
+        lead_in: usize,
+        promote_window: usize,
+        step: usize,
+    ) -> usize {
+        expire_frames * 5 + (lead_in + promote_window) * step
+    }
+
+    fn sends(&self, packet: &Packet) -> bool {
+        match packet {
+            Packet::Frame { pos, .. } => self.frames.contains(&pos.index()),
+            Packet::EndOfFrames { .. } => self.last,
+        }
+    }
+}
+
+#[tracing::instrument(name = "comp_accum", level = "debug", skip_all)]
+pub fn run(
+    mut accum: ComponentContainer<DetectionAccumulator>,
+    comp_detect_rx: mpsc::Receiver<(usize, comp_detect::Packet)>,
+    comp_accum_tx: mpsc::Sender<(usize, Packet)>,
+    mut score_writer: Option<ScoreWriter>,
+    segment: Option<Segment>,
+    observer: Option<Arc<dyn FrameObserver>>,
+) -> eyre::Result<()> {
+    let mut j = 0;
+    let mut send_packet = move |packet| -> eyre::Result<()> {
+        if let Some(segment) = &segment {
+            if !segment.sends(&packet) {
+                return Ok(());
+            }
+        }
+        if let (Some(observer), Packet::Frame { pos, result, .. }) = (&observer, &packet) {
+            observer.observe_detections(*pos, result);
+        }
+        comp_accum_tx.send((j, packet))?;
+        j += 1;
+        Ok(())
+    };
+
+    let mut pending_packets = VecDeque::new();
+
+    for (_i, packet) in SeqIter::new(comp_detect_rx) {
+        let pos = packet.position();
+        let _span = tracing::trace_span!("frame", %pos).entered();
+
+        match packet {
+            comp_detect::Packet::Frame {
+                pos,
+                frame,
+                result,
+                scores,
+            } => {
+                if let (Some(writer), Some(scores)) = (&mut score_writer, scores) {
+                    writer.write(pos, &scores)?;
+                }
+                pending_packets.push_back((pos, Some(frame)));
+                for (accum, result) in accum.iter_mut().zip(*result) {
+                    accum.receive_frame(pos, result);
+                }
+            }
+            comp_detect::Packet::EndOfFrames { pos } => {
+                pending_packets.push_back((pos, None));
+                for accum in &mut accum {
+                    accum.receive_end_of_frames(pos);
+                }
+            }
+        }
+
+        while let Some((pos, _)) = pending_packets.front() {
+            let completed = accum.iter().all(|accum| accum.handled(*pos));
+            if !completed {
+                break;
+            }
+
+            let (pos, frame) = pending_packets.pop_front().unwrap();
+            let result = accum
+                .iter_mut()
+                .map(|accum| accum.pop_packet().unwrap().1)
+                .collect();
+            let result = Box::new(result);
+
+            if let Some(frame) = frame {
+                send_packet(Packet::Frame { pos, frame, result })?;
+            } else {
+                assert!(result
+                    .iter()
+                    .all(|result| matches!(result, AccumDetection::Absent)));
+                send_packet(Packet::EndOfFrames { pos })?;
+            }
+        }
+    }
+
+    Ok(())
+}
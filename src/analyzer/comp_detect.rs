@@ -0,0 +1,101 @@
+use std::{iter, time::Instant};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::time::FramePosition;
+use elden_analyzer_video::capture::Frame;
+
+use crate::{
+    components::{self, CascadeStats, Component, ComponentContainer, Components, Detection},
+    operator::DetectionScore,
+};
+
+use super::decode;
+
+#[derive(Debug)]
+pub enum Packet {
+    Frame {
+        pos: FramePosition,
+        frame: Frame,
+        result: Box<ComponentContainer<Detection>>,
+        /// Detection scores, measured only if they are written
+        scores: Option<Box<ComponentContainer<Vec<DetectionScore>>>>,
+    },
+    EndOfFrames {
+        pos: FramePosition,
+    },
+}
+
+impl Packet {
+    pub fn position(&self) -> FramePosition {
+        match self {
+            Self::Frame { pos, .. } => *pos,
+            Self::EndOfFrames { pos } => *pos,
+        }
+    }
+}
+
+#[tracing::instrument(name = "comp_detect", level = "trace", skip_all, fields(pos = %packet.position()))]
+pub fn run(
+    components: &Components,
+    stats: &ComponentContainer<CascadeStats>,
+    with_scores: bool,
+    packet: decode::Packet,
+) -> eyre::Result<Packet> {
+    let packet = match packet {
+        decode::Packet::Frame {
+            pos,
+            frame,
+            detected,
+        } => {
+            let result = match detected {
+                Some(result) => result,
+                None => detect_all(components, stats, &frame)?,
+            };
+            let scores = with_scores
+                .then(|| {
+                    let scores = components
+                        .iter()
+                        .map(|component| component.scores(&frame))
+                        .collect::<eyre::Result<_>>()?;
+                    eyre::Ok(Box::new(scores))
+                })
+                .transpose()?;
+            Packet::Frame {
+                pos,
+                frame,
+                result,
+                scores,
+            }
+        }
+        decode::Packet::EndOfFrames { pos } => Packet::EndOfFrames { pos },
+    };
+    Ok(packet)
+}
+
+/// Detects all components in `frame`.
+pub fn detect_all(
+    components: &Components,
+    stats: &ComponentContainer<CascadeStats>,
+    frame: &Frame,
+) -> eyre::Result<Box<ComponentContainer<Detection>>> {
+    // User-defined components may be added by reloading the config, which are not counted
+    let stats = stats.iter().map(Some).chain(iter::repeat(None));
+    let result = iter::zip(components.iter(), stats)
+        .map(|(component, stats)| judge(&**component, frame, stats))
+        .collect::<eyre::Result<_>>()?;
+    Ok(Box::new(result))
+}
+
+fn judge(
+    component: &dyn Component,
+    frame: &Frame,
+    stats: Option<&CascadeStats>,
+) -> eyre::Result<Detection> {
+    let start = Instant::now();
+    let result = components::detect_cascade(component, frame, stats)?;
+    let elapsed = start.elapsed();
+
+    tracing::trace!(name = component.name(), result = %result.kind(), ?elapsed);
+
+    Ok(result)
+}
@@ -0,0 +1,287 @@
+use std::{collections::VecDeque, mem, str::FromStr, sync::mpsc, thread};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::time::FramePosition;
+use elden_analyzer_video::capture::{Frame, RangeDecoder};
+use num_rational::Ratio;
+use num_traits::ToPrimitive as _;
+
+use crate::{
+    components::{ComponentContainer, Detection},
+    i18n::Message,
+    operator::DetectionKind,
+};
+
+use super::{throttle::Throttle, CancellationToken};
+
+const CANCELLED: Message = Message::new(
+    "analysis is cancelled, writing results of the frames analyzed so far",
+    "解析が中断されたため、解析済みのフレームの結果を書き出します",
+);
+
+#[derive(Debug)]
+pub enum Packet {
+    Frame {
+        pos: FramePosition,
+        frame: Frame,
+        /// Components detected to decide rescans, reused instead of detecting them again
+        detected: Option<Box<ComponentContainer<Detection>>>,
+    },
+    EndOfFrames {
+        pos: FramePosition,
+    },
+}
+
+/// Receives the position of each decoded frame.
+pub type ProgressFn<'a> = dyn Fn(FramePosition) + 'a;
+
+/// Detects all components in a frame.
+pub type DetectFn<'a> =
+    dyn Fn(&Frame) -> eyre::Result<Box<ComponentContainer<Detection>>> + Sync + 'a;
+
+impl Packet {
+    pub fn position(&self) -> FramePosition {
+        match self {
+            Packet::Frame { pos, .. } => *pos,
+            Packet::EndOfFrames { pos } => *pos,
+        }
+    }
+}
+
+/// Rate of frames analyzed, given by `--sample-rate`.
+///
+/// `N` analyzes every Nth frame, and `Xfps` analyzes about X frames per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleRate {
+    EveryNth(usize),
+    Fps(f64),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SampleRateParseError {
+    #[error("expected `N` or `Xfps`")]
+    Syntax,
+    #[error("sample rate must be positive")]
+    NotPositive,
+}
+
+impl FromStr for SampleRate {
+    type Err = SampleRateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rate = match s.strip_suffix("fps") {
+            Some(fps) => {
+                let fps = fps
+                    .parse::<f64>()
+                    .map_err(|_| SampleRateParseError::Syntax)?;
+                if !fps.is_finite() || fps <= 0.0 {
+                    return Err(SampleRateParseError::NotPositive);
+                }
+                Self::Fps(fps)
+            }
+            None => {
+                let n = s
+                    .parse::<usize>()
+                    .map_err(|_| SampleRateParseError::Syntax)?;
+                if n == 0 {
+                    return Err(SampleRateParseError::NotPositive);
+                }
+                Self::EveryNth(n)
+            }
+        };
+        Ok(rate)
+    }
+}
+
+impl SampleRate {
+    /// Returns the number of frames of a video at `fps` per analyzed frame, at least `1`.
+    pub fn step(&self, fps: Ratio<i64>) -> usize {
+        match *self {
+            Self::EveryNth(n) => n,
+            Self::Fps(target) => {
+                let fps = fps.to_f64().unwrap_or(target);
+                ((fps / target).round() as usize).max(1)
+            }
+        }
+    }
+}
+
+/// Decodes frames and sends the ones whose indices are multiples of `step` to the pipeline.
+///
+/// Frames are sampled on the grid of indices rather than from the start of the range,
+/// so that segments of a video analyzed in parallel sample the same frames.
+///
+/// If `rescan` is given, components are detected in sampled frames by it, and the frames skipped since
+/// the last sampled frame are also sent when the detections change, so that spans start and end at exact frames.
+///
+/// If `throttle` is given, sampled frames wait for room in the pipeline in another thread,
+/// and frames decoded while waiting replace the waiting one.
+///
+/// If `cancel` is cancelled, decoding stops and the end of frames is sent at the frame after the last decoded one.
+#[tracing::instrument(name = "decode", level = "debug", skip_all)]
+pub fn run(
+    progress: &ProgressFn,
+    cap_tx: mpsc::Sender<(usize, Packet)>,
+    decoder: &mut RangeDecoder,
+    step: usize,
+    rescan: Option<&DetectFn>,
+    throttle: Option<&Throttle>,
+    cancel: Option<&CancellationToken>,
+) -> eyre::Result<()> {
+    let mut sender = FrameSender {
+        cap_tx,
+        rescan,
+        i: 0,
+        last_kinds: None,
+    };
+    let keep_skipped = rescan.is_some();
+    let end = match throttle {
+        None => decode_frames(
+            progress,
+            decoder,
+            step,
+            keep_skipped,
+            cancel,
+            &mut |frame, skipped| sender.send(frame, skipped),
+        )?,
+        Some(throttle) => thread::scope(|s| {
+            let span = tracing::Span::current();
+            let sender = &mut sender;
+            let handle = s.spawn(move || {
+                let _span = span.enter();
+                let result = (|| {
+                    while let Some((frame, skipped)) = throttle.next() {
+                        sender.send(frame, skipped)?;
+                    }
+                    eyre::Ok(())
+                })();
+                // Stops decoding if the pipeline is closed
+                throttle.close();
+                result
+            });
+            let end = decode_frames(
+                progress,
+                decoder,
+                step,
+                keep_skipped,
+                cancel,
+                &mut |frame, skipped| {
+                    if !throttle.offer(frame, skipped) {
+                        eyre::bail!("pipeline is closed");
+                    }
+                    Ok(())
+                },
+            );
+            throttle.close();
+            // Errors of sending are the causes of failing to offer frames
+            handle.join().unwrap()?;
+            end
+        })?,
+    };
+    sender
+        .cap_tx
+        .send((sender.i, Packet::EndOfFrames { pos: end }))?;
+    Ok(())
+}
+
+/// Decodes frames and passes the sampled ones to `sink` with the frames skipped before them,
+/// returning the position after the last decoded frame.
+fn decode_frames(
+    progress: &ProgressFn,
+    decoder: &mut RangeDecoder,
+    step: usize,
+    keep_skipped: bool,
+    cancel: Option<&CancellationToken>,
+    sink: &mut dyn FnMut(Frame, VecDeque<Frame>) -> eyre::Result<()>,
+) -> eyre::Result<FramePosition> {
+    let mut next_pos = decoder.start();
+    // Frames skipped since the last sampled frame, kept only for rescans
+    let mut skipped = VecDeque::new();
+    loop {
+        let _span = tracing::trace_span!("frame", pos = %next_pos).entered();
+
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            tracing::warn!(pos = %next_pos, "{CANCELLED}");
+            return Ok(next_pos);
+        }
+
+        let mut frame = Frame::empty();
+        if !decoder.decode_frame(&mut frame)? {
+            let pos = decoder.end();
+            progress(pos);
+            return Ok(pos);
+        }
+
+        let pos = frame.position();
+        next_pos = pos.next(decoder.capture().sec_per_frame());
+        progress(pos);
+        if pos.index() % step != 0 {
+            if keep_skipped {
+                skipped.push_back(frame);
+            }
+            continue;
+        }
+        sink(frame, mem::take(&mut skipped))?;
+    }
+}
+
+/// Sends sampled frames to the pipeline in order.
+struct FrameSender<'a> {
+    cap_tx: mpsc::Sender<(usize, Packet)>,
+    rescan: Option<&'a DetectFn<'a>>,
+    i: usize,
+    /// Detections of the last sampled frame
+    last_kinds: Option<Vec<DetectionKind>>,
+}
+
+impl FrameSender<'_> {
+    /// Sends the sampled `frame`, preceded by the `skipped` frames if the detections changed since the last sampled frame.
+    fn send(&mut self, frame: Frame, skipped: VecDeque<Frame>) -> eyre::Result<()> {
+        let detected = self.rescan.map(|detect| detect(&frame)).transpose()?;
+        if let Some(detected) = &detected {
+            let kinds = detected.iter().map(Detection::kind).collect::<Vec<_>>();
+            if self.last_kinds.as_ref().is_some_and(|last| *last != kinds) {
+                tracing::trace!(frames = skipped.len(), "rescan");
+                for frame in skipped {
+                    let packet = Packet::Frame {
+                        pos: frame.position(),
+                        frame,
+                        detected: None,
+                    };
+                    self.cap_tx.send((self.i, packet))?;
+                    self.i += 1;
+                }
+            }
+            self.last_kinds = Some(kinds);
+        }
+
+        let packet = Packet::Frame {
+            pos: frame.position(),
+            frame,
+            detected,
+        };
+        self.cap_tx.send((self.i, packet))?;
+        self.i += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sample_rate() {
+        let rate = |s: &str| s.parse::<SampleRate>();
+        assert_eq!(rate("3").unwrap(), SampleRate::EveryNth(3));
+        assert_eq!(rate("7.5fps").unwrap(), SampleRate::Fps(7.5));
+        assert!(rate("0").is_err());
+        assert!(rate("0fps").is_err());
+        assert!(rate("fast").is_err());
+
+        let fps = Ratio::new(60000, 1001);
+        assert_eq!(rate("4").unwrap().step(fps), 4);
+        assert_eq!(rate("10fps").unwrap().step(fps), 6);
+        assert_eq!(rate("120fps").unwrap().step(fps), 1);
+    }
+}
@@ -0,0 +1,69 @@
+//! The analysis pipeline of `analyze`, usable without the command line interface.
+//!
+//! Frames are decoded by [`decode::run`], components are detected in them by [`comp_detect`] and settled by
+//! [`comp_accum`], texts are recognized by [`text_recognize`], and they are joined into spans by [`text_accum::run`].
+//! Each stage runs in its own threads connected by channels, spawned by [`Stages`].
+//! Analyses are aborted by a [`CancellationToken`], after which the stages finish with the frames decoded so far.
+//!
+//! [`Pipeline`] runs all stages on a video and returns found spans as [`Event`]s, which is enough for most uses.
+//! The stages are public for callers composing them differently, such as analyzing segments of a video in parallel.
+
+use std::iter;
+
+use elden_analyzer_kernel::types::time::FramePosition;
+use elden_analyzer_video::capture::Frame;
+
+use crate::{
+    accumulate::{detection::AccumDetection, text::FrameTexts},
+    components::{ComponentContainer, Components},
+};
+
+pub use self::{cancel::*, pipeline::*, stages::*, stop::*};
+
+mod cancel;
+pub mod comp_accum;
+pub mod comp_detect;
+pub mod decode;
+mod pipeline;
+pub mod reload;
+pub mod scores;
+mod stages;
+mod stop;
+pub mod text_accum;
+pub mod text_recognize;
+pub mod throttle;
+
+/// Receives frames whose texts are recognized, e.g. to display them while analyzing.
+pub trait FrameObserver: Send + Sync {
+    /// Called from threads recognizing texts, so frames may be received out of order.
+    fn observe(
+        &self,
+        pos: FramePosition,
+        frame: Frame,
+        components: &Components,
+        texts: &ComponentContainer<FrameTexts>,
+    );
+
+    /// Receives the detections of each frame in order, once they are settled by the frames after it.
+    fn observe_detections(
+        &self,
+        _pos: FramePosition,
+        _detections: &ComponentContainer<AccumDetection>,
+    ) {
+    }
+}
+
+/// Result of the analysis pipeline.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub names: ComponentContainer<String>,
+    /// Number of spans found for each component
+    pub num_spans: ComponentContainer<usize>,
+}
+
+impl Summary {
+    /// Returns pairs of the name of each component and the number of its spans.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        iter::zip(&self.names, &self.num_spans).map(|(name, n)| (name.as_str(), *n))
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    fmt,
+    ops::ControlFlow,
+    path::Path,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::time::{FramePosition, TimestampRange};
+use elden_analyzer_video::capture::{BackendKind, Frame, VideoCapture};
+use num_rational::Ratio;
+use tracing::Span;
+
+use crate::{
+    accumulate::{
+        detection::AccumDetection,
+        text::{AccumResult, FrameTexts},
+    },
+    components::{ComponentContainer, Components},
+    config::Config,
+    igt::IgtReaderBuilder,
+    io::event_id::{EventId, SourceId},
+    items::{ItemCategory, ItemTranslation},
+};
+
+use super::{
+    decode::{self, SampleRate},
+    spawn_accumulate_thread,
+    text_accum::{self, Output},
+    CancellationToken, FrameObserver, Stages, Summary,
+};
+
+/// Receives the progress of decoding, called for each decoded frame.
+pub type ProgressCallback = dyn Fn(Progress) + Send + Sync;
+
+/// Position of the frame being decoded in the range of frames analyzed.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub start: FramePosition,
+    pub end: FramePosition,
+    pub position: FramePosition,
+}
+
+impl Progress {
+    /// Returns the ratio of decoded frames in the range, from `0.0` to `1.0`.
+    pub fn ratio(&self) -> f64 {
+        let total = self.end.index().saturating_sub(self.start.index());
+        if total == 0 {
+            return 1.0;
+        }
+        let done = self.position.index().saturating_sub(self.start.index());
+        (done as f64 / total as f64).min(1.0)
+    }
+}
+
+/// Hooks called by a [`Pipeline`] while analyzing, e.g. to update a UI or to write results to custom sinks.
+///
+/// Hooks are called from the threads of the stages, so they should return quickly.
+/// Returning [`ControlFlow::Break`] cancels the analysis as [`Events::cancellation_token`] does,
+/// e.g. to stop after the first boss is defeated. Frames already decoded are still analyzed,
+/// so hooks may be called a few more times after that.
+///
+/// # Examples
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// use elden_analyzer::{accumulate::text::AccumResult, analyzer::PipelineHooks, io::event_id::EventId};
+///
+/// /// Stops the analysis after the first span of a boss
+/// struct FirstBoss;
+///
+/// impl PipelineHooks for FirstBoss {
+///     fn on_span_closed(&self, _id: &EventId, result: &AccumResult) -> ControlFlow<()> {
+///         if result.name == "boss_bar" {
+///             return ControlFlow::Break(());
+///         }
+///         ControlFlow::Continue(())
+///     }
+/// }
+/// ```
+pub trait PipelineHooks: Send + Sync {
+    /// Called for each decoded frame in order, including frames skipped by the sample rate.
+    fn on_frame_decoded(&self, _pos: FramePosition) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for each analyzed frame in order, with the detections of components settled by the frames after it.
+    fn on_detection(
+        &self,
+        _pos: FramePosition,
+        _detections: &ComponentContainer<AccumDetection>,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for each span in order before it is returned as [`Event::Span`].
+    fn on_span_closed(&self, _id: &EventId, _result: &AccumResult) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called for each decoded frame with the progress of decoding, after [`PipelineBuilder::progress`].
+    fn on_progress(&self, _progress: Progress) {}
+}
+
+/// Event of an analysis, returned by [`Events`] in order.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Frames from `start` to `end` of the video at `fps` are going to be analyzed
+    Started {
+        start: FramePosition,
+        end: FramePosition,
+        fps: Ratio<i64>,
+    },
+    /// Span of a component is found.
+    ///
+    /// Spans are ordered by their ends, and [`AccumResult::into_span`] converts them into lines of span files.
+    Span { id: EventId, result: AccumResult },
+    /// All frames are analyzed
+    Finished(Box<Summary>),
+}
+
+/// Options of a [`Pipeline`].
+///
+/// # Examples
+/// ```no_run
+/// use elden_analyzer::{
+///     analyzer::{Event, PipelineBuilder},
+///     config::Config,
+/// };
+/// use elden_analyzer_video::capture::BackendKind;
+///
+/// let mut builder = PipelineBuilder::new(Config::default());
+/// builder.only_categories = vec![elden_analyzer::items::ItemCategory::KeyItems];
+/// builder.progress = Some(std::sync::Arc::new(|progress| {
+///     eprint!("\r{:.1}%", progress.ratio() * 100.0);
+/// }));
+/// let pipeline = builder.build().unwrap();
+///
+/// for event in pipeline.analyze_file("input.mp4".as_ref(), BackendKind::Ffmpeg).unwrap() {
+///     if let Event::Span { id, result } = event.unwrap() {
+///         println!("{}", result.into_span(Some(id)));
+///     }
+/// }
+/// ```
+pub struct PipelineBuilder {
+    pub config: Config,
+    /// Range of the video analyzed
+    pub range: TimestampRange,
+    /// Use the PTS of frames as their timestamps
+    pub exact_pts: bool,
+    /// Rate of frames analyzed, or all frames if `None`
+    pub sample_rate: Option<SampleRate>,
+    /// Analyze skipped frames where detections of sampled frames change
+    pub rescan: bool,
+    /// Number of frames before each span to recognize texts in
+    pub lead_in: usize,
+    /// Number of frames after a found frame within which possible frames are regarded as found
+    pub promote_window: usize,
+    /// Skip text recognition and only detect components
+    pub detection_only: bool,
+    pub igt: Option<IgtReaderBuilder>,
+    pub translate_items: Option<ItemTranslation>,
+    /// Categories of items whose spans are reported, or empty to report all spans
+    pub only_categories: Vec<ItemCategory>,
+    pub progress: Option<Arc<ProgressCallback>>,
+    pub hooks: Option<Arc<dyn PipelineHooks>>,
+}
+
+impl fmt::Debug for PipelineBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineBuilder")
+            .field("config", &self.config)
+            .field("range", &self.range)
+            .field("exact_pts", &self.exact_pts)
+            .field("sample_rate", &self.sample_rate)
+            .field("rescan", &self.rescan)
+            .field("lead_in", &self.lead_in)
+            .field("promote_window", &self.promote_window)
+            .field("detection_only", &self.detection_only)
+            .field("igt", &self.igt)
+            .field("translate_items", &self.translate_items)
+            .field("only_categories", &self.only_categories)
+            .field("progress", &self.progress.is_some())
+            .field("hooks", &self.hooks.is_some())
+            .finish()
+    }
+}
+
+impl PipelineBuilder {
+    /// Returns the options analyzing all frames of videos with `config`, as `analyze` does without options.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            range: TimestampRange::Full,
+            exact_pts: false,
+            sample_rate: None,
+            rescan: false,
+            lead_in: 0,
+            promote_window: 1,
+            detection_only: false,
+            igt: None,
+            translate_items: None,
+            only_categories: vec![],
+            progress: None,
+            hooks: None,
+        }
+    }
+
+    pub fn build(self) -> eyre::Result<Pipeline> {
+        if self.rescan && self.sample_rate.is_none() {
+            eyre::bail!("rescans require a sample rate");
+        }
+        Ok(Pipeline(Arc::new(self)))
+    }
+}
+
+/// Analysis of videos, running the stages in background threads.
+///
+/// A pipeline can analyze any number of videos, and each call of [`Pipeline::analyze`] runs independently.
+#[derive(Debug, Clone)]
+pub struct Pipeline(Arc<PipelineBuilder>);
+
+impl Pipeline {
+    /// Analyzes the video file or the sequence of images at `path`, identifying events by the content of the file.
+    pub fn analyze_file(&self, path: &Path, backend: BackendKind) -> eyre::Result<Events> {
+        let source = SourceId::of_input(path)?;
+        let path = path.to_owned();
+        Ok(self.analyze(source, move || {
+            Ok(VideoCapture::open_with_backend(&path, backend)?)
+        }))
+    }
+
+    /// Analyzes the video opened by `open`, identifying events by `source`.
+    ///
+    /// Captures are not [`Send`], so `open` is called in the thread decoding frames.
+    /// Analysis is cancelled when the returned [`Events`] is dropped, or by [`Events::cancellation_token`].
+    pub fn analyze(
+        &self,
+        source: SourceId,
+        open: impl FnOnce() -> eyre::Result<VideoCapture> + Send + 'static,
+    ) -> Events {
+        let (tx, rx) = mpsc::channel();
+        let cancel = CancellationToken::new();
+        let options = Arc::clone(&self.0);
+        let root_span = Span::current();
+        let spawned = thread::Builder::new().name("pipeline".into()).spawn({
+            let tx = tx.clone();
+            let cancel = cancel.clone();
+            move || {
+                let _span = root_span.enter();
+                if let Err(err) = run(&options, source, open, &cancel, &tx) {
+                    let _ = tx.send(Err(err));
+                }
+            }
+        });
+        if let Err(err) = spawned {
+            let _ = tx.send(Err(err.into()));
+        }
+        Events { rx, cancel }
+    }
+}
+
+/// Events of an analysis, ending after [`Event::Finished`] or an error.
+#[derive(Debug)]
+pub struct Events {
+    rx: mpsc::Receiver<eyre::Result<Event>>,
+    cancel: CancellationToken,
+}
+
+impl Events {
+    /// Returns the token cancelling the analysis, e.g. from another thread while iterating events.
+    ///
+    /// Spans of the frames analyzed before cancelling are still returned, followed by [`Event::Finished`].
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Iterator for Events {
+    type Item = eyre::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+fn run(
+    options: &PipelineBuilder,
+    source: SourceId,
+    open: impl FnOnce() -> eyre::Result<VideoCapture>,
+    cancel: &CancellationToken,
+    tx: &mpsc::Sender<eyre::Result<Event>>,
+) -> eyre::Result<()> {
+    let send = |tx: &mpsc::Sender<_>, event| {
+        tx.send(Ok(event))
+            .map_err(|_| eyre::eyre!("events are no longer received"))
+    };
+
+    let mut capture = open()?;
+    capture.set_exact_pts(options.exact_pts);
+    let mut decoder = capture.range_decoder(options.range)?;
+    let (start, end) = (decoder.start(), decoder.end());
+    let fps = decoder.capture().fps();
+    let step = options.sample_rate.map_or(1, |rate| rate.step(fps));
+
+    let stages = Stages::new(
+        decoder.capture().rect(),
+        &options.config,
+        options.igt.clone(),
+        options.detection_only,
+        options.lead_in,
+        options.promote_window,
+    )?;
+    send(tx, Event::Started { start, end, fps })?;
+
+    let hooks = options.hooks.clone().map(|hooks| HookObserver {
+        hooks,
+        cancel: cancel.clone(),
+    });
+    let observer = hooks
+        .clone()
+        .map(|hooks| Arc::new(hooks) as Arc<dyn FrameObserver>);
+
+    let (text_recognize_tx, text_recognize_rx) = mpsc::channel();
+    let (cap_tx, front) = stages.spawn_front(None, None, observer, text_recognize_tx)?;
+    let text_accum_thread = spawn_accumulate_thread("text_accum", {
+        let names = stages.names.clone();
+        let min_possible_share = stages.pipeline.min_possible_share;
+        let translate_items = options.translate_items;
+        let only_categories = options.only_categories.clone();
+        let hooks = hooks.clone();
+        let tx = tx.clone();
+        move || {
+            text_accum::run(
+                names,
+                text_recognize_rx,
+                step,
+                min_possible_share,
+                translate_items,
+                &only_categories,
+                None,
+                |output| {
+                    if let Output::Span { result, .. } = output {
+                        let id = EventId::new(source, &result.name, result.start.index());
+                        if let Some(hooks) = &hooks {
+                            hooks.check(hooks.hooks.on_span_closed(&id, &result));
+                        }
+                        send(&tx, Event::Span { id, result })?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    })?;
+
+    let progress = |position: FramePosition| {
+        let progress = Progress {
+            start,
+            end,
+            position,
+        };
+        if let Some(callback) = &options.progress {
+            callback(progress);
+        }
+        if let Some(hooks) = &hooks {
+            // The end of frames is reported as progress, but it is not a decoded frame
+            if position.index() < end.index() {
+                hooks.check(hooks.hooks.on_frame_decoded(position));
+            }
+            hooks.hooks.on_progress(progress);
+        }
+    };
+    let detect = options.rescan.then(|| stages.detect_fn());
+    let decoded = decode::run(
+        &progress,
+        cap_tx,
+        &mut decoder,
+        step,
+        detect.as_ref().map(|detect| detect as &decode::DetectFn),
+        None,
+        Some(cancel),
+    );
+
+    // Failures of later stages close the channels from earlier stages, so they are the causes
+    let front = front.join();
+    let num_spans = text_accum_thread.join().unwrap()?;
+    front?;
+    decoded?;
+
+    stages.report_cascade();
+    send(
+        tx,
+        Event::Finished(Box::new(Summary {
+            names: stages.names,
+            num_spans,
+        })),
+    )
+}
+
+/// Calls hooks from the stages, cancelling the analysis when a hook breaks.
+#[derive(Clone)]
+struct HookObserver {
+    hooks: Arc<dyn PipelineHooks>,
+    cancel: CancellationToken,
+}
+
+impl HookObserver {
+    fn check(&self, flow: ControlFlow<()>) {
+        if flow.is_break() {
+            self.cancel.cancel();
+        }
+    }
+}
+
+impl FrameObserver for HookObserver {
+    fn observe(
+        &self,
+        _pos: FramePosition,
+        _frame: Frame,
+        _components: &Components,
+        _texts: &ComponentContainer<FrameTexts>,
+    ) {
+    }
+
+    fn observe_detections(
+        &self,
+        pos: FramePosition,
+        detections: &ComponentContainer<AccumDetection>,
+    ) {
+        self.check(self.hooks.on_detection(pos, detections));
+    }
+}
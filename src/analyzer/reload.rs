@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer_kernel::types::rect::Rect;
+use tracing::Span;
+
+use crate::{
+    components::Components,
+    config::{Config, ThresholdOverrides},
+    i18n::Message,
+    items::{self, ItemList},
+    operator::PostProcess,
+    profile::Profile,
+};
+
+/// Interval of checking the modification of the config file
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const RELOAD_FAILED: Message = Message::new(
+    "failed to reload config",
+    "設定ファイルの再読み込みに失敗しました",
+);
+
+/// Components shared by pipeline stages.
+///
+/// Components can be replaced while the pipeline is running.
+/// Each frame is processed by the components at the time the stage receives it.
+#[derive(Debug)]
+pub struct SharedComponents {
+    current: RwLock<Arc<Components>>,
+}
+
+impl SharedComponents {
+    pub fn new(components: Components) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(components)),
+        }
+    }
+
+    pub fn get(&self) -> Arc<Components> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    fn replace(&self, components: Components) {
+        *self.current.write().unwrap() = Arc::new(components);
+    }
+}
+
+/// Reloads the config file when it is modified, and replaces the shared components and the rules correcting item names.
+///
+/// The item list and the item corrections given by the config file are also watched, and reloaded with the config file.
+/// The set of components cannot be changed because the accumulators in the pipeline depend on it.
+/// The polling thread stops when this value is dropped.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    stop_tx: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Spawns the polling thread of the config file `path`.
+    ///
+    /// `item_list` overrides `item_list` of the config file, as `--item-list` does.
+    pub fn spawn(
+        path: &Path,
+        profile: Option<&'static Profile>,
+        overrides: ThresholdOverrides,
+        ocr_lang: &str,
+        item_list: Option<&Path>,
+        frame_rect: Rect,
+        components: Arc<SharedComponents>,
+    ) -> eyre::Result<Self> {
+        let reloader = Reloader {
+            path: path.to_owned(),
+            profile,
+            overrides,
+            ocr_lang: ocr_lang.to_owned(),
+            item_list: item_list.map(Path::to_owned),
+            frame_rect,
+            components,
+        };
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let root_span = Span::current();
+        let handle = thread::Builder::new()
+            .name("config_watcher".into())
+            .spawn(move || {
+                let _span = root_span.enter();
+                reloader.watch(stop_rx);
+            })?;
+        Ok(Self {
+            stop_tx: Some(stop_tx),
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        drop(self.stop_tx.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Config file and the options applied to it whenever it is reloaded.
+#[derive(Debug)]
+struct Reloader {
+    path: PathBuf,
+    profile: Option<&'static Profile>,
+    overrides: ThresholdOverrides,
+    /// Language Tesseract is already initialized with
+    ocr_lang: String,
+    item_list: Option<PathBuf>,
+    frame_rect: Rect,
+    components: Arc<SharedComponents>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+impl Reloader {
+    fn watch(&self, stop_rx: mpsc::Receiver<()>) {
+        let mut watched = self.watched_files(Config::load(&self.path).ok().as_ref());
+        while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(POLL_INTERVAL) {
+            if watched.iter().all(|(path, last)| modified(path) == *last) {
+                continue;
+            }
+
+            match self.reload() {
+                Ok(config) => {
+                    tracing::info!(path = %self.path.display(), "config reloaded");
+                    watched = self.watched_files(Some(&config));
+                }
+                Err(err) => {
+                    tracing::warn!(path = %self.path.display(), "{RELOAD_FAILED}: {err:#}");
+                    for (path, last) in &mut watched {
+                        *last = modified(path);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the files reloaded with `config`, and their modification times.
+    fn watched_files(&self, config: Option<&Config>) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let item_list = self
+            .item_list
+            .as_ref()
+            .or(config.and_then(|config| config.item_list.as_ref()));
+        let item_corrections = config.and_then(|config| config.item_corrections.as_ref());
+        [Some(&self.path), item_list, item_corrections]
+            .into_iter()
+            .flatten()
+            .map(|path| (path.clone(), modified(path)))
+            .collect()
+    }
+
+    /// Reloads the config file, and replaces the components and the rules correcting item names if all of them are valid.
+    fn reload(&self) -> eyre::Result<Config> {
+        let mut config = Config::load(&self.path)?;
+        if let Some(profile) = self.profile {
+            profile.apply(&mut config)?;
+        }
+        config.apply_overrides(&self.overrides)?;
+        config.ocr_lang = Some(self.ocr_lang.clone());
+        let new = Components::new(self.frame_rect, &config).ok_or_eyre("invalid frame size")?;
+
+        let current = self.components.get();
+        let same_names = current
+            .iter()
+            .map(|c| c.name())
+            .eq(new.iter().map(|c| c.name()));
+        if !same_names {
+            eyre::bail!("components cannot be added or removed while running");
+        }
+
+        let item_list = match self.item_list.as_ref().or(config.item_list.as_ref()) {
+            Some(path) => ItemList::load(path)?,
+            None => ItemList::Bundled(config.text_lang()),
+        };
+
+        self.components.replace(new);
+        items::reload_item_list(item_list);
+        PostProcess::rebuild_item_rules(config.item_corrections().clone());
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operator::{Confidence, Recognition};
+
+    use super::*;
+
+    #[test]
+    fn reload_item_corrections() {
+        let dir = std::env::temp_dir().join(format!("config-watcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let corrections = dir.join("corrections.tsv");
+        fs::write(&path, "item_corrections = \"corrections.tsv\"\n").unwrap();
+        fs::write(&corrections, "# no corrections yet\n").unwrap();
+
+        let frame_rect = Rect::at(0, 0).of_size(1920, 1080);
+        let config = Config::load(&path).unwrap();
+        let components = Components::new(frame_rect, &config).unwrap();
+        let reloader = Reloader {
+            path: path.clone(),
+            profile: None,
+            overrides: ThresholdOverrides::default(),
+            ocr_lang: "jpn".into(),
+            item_list: None,
+            frame_rect,
+            components: Arc::new(SharedComponents::new(components)),
+        };
+        let watched = reloader.watched_files(Some(&config));
+        assert!(watched.iter().any(|(file, _)| *file == corrections));
+
+        let run = || PostProcess::ItemText.run("緋雫の聖杯瓶＃＃", Confidence::new(90));
+        reloader.reload().unwrap();
+        assert!(matches!(run(), Recognition::Possible(..)));
+
+        fs::write(&corrections, "＃＃$\t\n").unwrap();
+        reloader.reload().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(run(), Recognition::Found(text, _) if text == "緋雫の聖杯瓶"));
+    }
+}
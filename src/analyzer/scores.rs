@@ -0,0 +1,52 @@
+use std::{fs::File, io::Write as _};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::time::FramePosition;
+
+use crate::{components::ComponentContainer, operator::DetectionScore};
+
+/// Writes detection scores of each frame as CSV.
+///
+/// Each column is a score named `COMPONENT.SCORE`, normalized so that `1.0` or more means found.
+/// The header is written with the first frame, since score names are only known after measuring.
+#[derive(Debug)]
+pub struct ScoreWriter {
+    output: File,
+    names: ComponentContainer<String>,
+    header_written: bool,
+}
+
+impl ScoreWriter {
+    pub fn new(output: File, names: ComponentContainer<String>) -> Self {
+        Self {
+            output,
+            names,
+            header_written: false,
+        }
+    }
+
+    pub fn write(
+        &mut self,
+        pos: FramePosition,
+        scores: &ComponentContainer<Vec<DetectionScore>>,
+    ) -> eyre::Result<()> {
+        if !self.header_written {
+            let mut header = vec!["frame".to_owned(), "timestamp".to_owned()];
+            for (name, scores) in self.names.iter().zip(scores) {
+                header.extend(scores.iter().map(|score| format!("{name}.{}", score.name)));
+            }
+            writeln!(self.output, "{}", header.join(","))?;
+            self.header_written = true;
+        }
+
+        let mut row = vec![pos.index().to_string(), pos.timestamp().to_string()];
+        row.extend(
+            scores
+                .iter()
+                .flatten()
+                .map(|score| format!("{:.3}", score.normalized())),
+        );
+        writeln!(self.output, "{}", row.join(","))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    iter,
+    sync::{mpsc, Arc, LazyLock, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer_kernel::types::rect::Rect;
+use elden_analyzer_video::capture::Frame;
+use lockfree_object_pool::LinearObjectPool;
+use rayon::{prelude::*, ThreadPoolBuilder};
+use tracing::Span;
+
+use crate::{
+    accumulate::detection::DetectionAccumulator,
+    components::{CascadeStats, ComponentContainer, Components, Detection},
+    config::{Config, PerformanceConfig, PipelineConfig},
+    i18n::Message,
+    igt::{IgtReader, IgtReaderBuilder},
+    image_process::tesseract::Tesseract,
+    operator::PostProcess,
+};
+
+use super::{
+    comp_accum::{self, Segment},
+    comp_detect, decode,
+    reload::SharedComponents,
+    scores::ScoreWriter,
+    text_recognize, FrameObserver,
+};
+
+const OCR_UNSUPPORTED_DETECTION_ONLY: Message = Message::new(
+    "OCR is not supported in this build, only detecting components",
+    "このビルドはOCRに対応していないため、コンポーネントの検出のみ行います",
+);
+const OCR_UNSUPPORTED_NO_IGT: Message = Message::new(
+    "OCR is not supported in this build, in-game time is not recorded",
+    "このビルドはOCRに対応していないため、ゲーム内時間は記録されません",
+);
+
+/// Components and readers shared by the stages of pipelines.
+///
+/// Frames decoded by [`decode::run`] go through the stages spawned by [`Stages::spawn_front`],
+/// and texts recognized by them are accumulated into spans by [`text_accum::run`](super::text_accum::run).
+#[derive(Debug)]
+pub struct Stages {
+    pub components: Arc<SharedComponents>,
+    pub names: ComponentContainer<String>,
+    /// Rejections of each stage of the detection cascade, shared by all frames
+    cascade_stats: Arc<ComponentContainer<CascadeStats>>,
+    igt: Option<Arc<IgtReader>>,
+    detection_only: bool,
+    lead_in: usize,
+    promote_window: usize,
+    performance: PerformanceConfig,
+    pub pipeline: PipelineConfig,
+    /// Language of the Tesseract trained data
+    pub ocr_lang: String,
+}
+
+impl Stages {
+    pub fn new(
+        base_rect: Rect,
+        config: &Config,
+        igt: Option<IgtReaderBuilder>,
+        detection_only: bool,
+        lead_in: usize,
+        promote_window: usize,
+    ) -> eyre::Result<Self> {
+        let detection_only = detection_only || {
+            let available = Tesseract::is_available();
+            if !available {
+                tracing::warn!("{OCR_UNSUPPORTED_DETECTION_ONLY}");
+            }
+            !available
+        };
+        let igt = match igt {
+            Some(_) if detection_only => {
+                tracing::warn!("{OCR_UNSUPPORTED_NO_IGT}");
+                None
+            }
+            Some(igt) => Some(Arc::new(igt.build(base_rect)?)),
+            None => None,
+        };
+        if !detection_only {
+            let elapsed = PostProcess::preload();
+            tracing::info!(?elapsed, "loaded post-process tables");
+        }
+
+        let components = Components::new(base_rect, config).ok_or_eyre("invalid frame size")?;
+        let names = components.each_ref().map(|c| c.name().to_owned());
+        let cascade_stats = components.each_ref().map(|_| CascadeStats::default());
+        Ok(Self {
+            components: Arc::new(SharedComponents::new(components)),
+            names,
+            cascade_stats: Arc::new(cascade_stats),
+            igt,
+            detection_only,
+            lead_in,
+            promote_window,
+            performance: config.performance,
+            pipeline: config.pipeline,
+            ocr_lang: config.ocr_lang().to_owned(),
+        })
+    }
+
+    /// Returns `true` if the in-game time is read in frames where any component is shown.
+    pub fn reads_igt(&self) -> bool {
+        self.igt.is_some()
+    }
+
+    /// Spawns the stages from detecting components to recognizing texts, which send results to `tx`.
+    ///
+    /// Returns the sender of decoded frames and the spawned threads.
+    pub fn spawn_front(
+        &self,
+        score_writer: Option<ScoreWriter>,
+        segment: Option<Segment>,
+        observer: Option<Arc<dyn FrameObserver>>,
+        tx: mpsc::Sender<(usize, text_recognize::Packet)>,
+    ) -> eyre::Result<(mpsc::Sender<(usize, decode::Packet)>, FrontThreads)> {
+        let (cap_tx, cap_rx) = mpsc::channel();
+        let (comp_detect_tx, comp_detect_rx) = mpsc::channel();
+        let (comp_accum_tx, comp_accum_rx) = mpsc::channel();
+
+        let with_scores = score_writer.is_some();
+        let comp_detect = tracing::info_span!("comp_tedect").in_scope(|| {
+            let components = Arc::clone(&self.components);
+            let stats = Arc::clone(&self.cascade_stats);
+            spawn_streaming_thread(
+                cap_rx,
+                comp_detect_tx,
+                "comp_detect",
+                self.performance.detect_threads,
+                move |packet| comp_detect::run(&components.get(), &stats, with_scores, packet),
+            )
+        });
+
+        let comp_accum = spawn_accumulate_thread("comp_accum", {
+            let accum = self.names.clone().map(|name| {
+                DetectionAccumulator::new(
+                    name,
+                    self.lead_in,
+                    self.promote_window,
+                    self.pipeline.expire_frames,
+                )
+            });
+            let observer = observer.clone();
+            move || {
+                comp_accum::run(
+                    accum,
+                    comp_detect_rx,
+                    comp_accum_tx,
+                    score_writer,
+                    segment,
+                    observer,
+                )
+            }
+        })?;
+
+        let text_recognize = tracing::info_span!("text_recognize").in_scope(|| {
+            let components = Arc::clone(&self.components);
+            let ocr_lang = self.ocr_lang.clone();
+            let ocr_timeout = self.pipeline.ocr_timeout();
+            let tess = (!self.detection_only).then(|| {
+                LinearObjectPool::new(
+                    move || {
+                        let ocr_lang = ocr_lang.clone();
+                        LazyLock::new(move || {
+                            let mut tess = Tesseract::new(None, Some(&ocr_lang)).unwrap();
+                            tess.set_timeout(ocr_timeout);
+                            Mutex::new(tess)
+                        })
+                    },
+                    |_v| {},
+                )
+            });
+            let igt = self.igt.clone();
+            spawn_streaming_thread(
+                comp_accum_rx,
+                tx,
+                "text_recognize",
+                self.performance.ocr_threads,
+                move |packet| {
+                    text_recognize::run(
+                        &components.get(),
+                        tess.as_ref(),
+                        igt.as_deref(),
+                        observer.as_deref(),
+                        packet,
+                    )
+                },
+            )
+        });
+
+        let threads = FrontThreads {
+            comp_detect,
+            comp_accum,
+            text_recognize,
+        };
+        Ok((cap_tx, threads))
+    }
+
+    /// Returns a function detecting all components as the detection stage does, for rescans while decoding.
+    pub fn detect_fn(
+        &self,
+    ) -> impl Fn(&Frame) -> eyre::Result<Box<ComponentContainer<Detection>>> + Send + Sync {
+        let components = Arc::clone(&self.components);
+        let stats = Arc::clone(&self.cascade_stats);
+        move |frame| comp_detect::detect_all(&components.get(), &stats, frame)
+    }
+
+    /// Logs the rates of frames rejected by each stage of the detection cascade of each component.
+    pub fn report_cascade(&self) {
+        for (name, stats) in iter::zip(&self.names, &*self.cascade_stats) {
+            tracing::info!(
+                name = name.as_str(),
+                frames = stats.frames(),
+                prefilter = format!("{:.1}%", stats.prefilter_rejection_rate() * 100.0),
+                detector = format!("{:.1}%", stats.detector_rejection_rate() * 100.0),
+                "frames rejected by detection stages"
+            );
+        }
+    }
+}
+
+/// Threads of the stages spawned by [`Stages::spawn_front`].
+#[derive(Debug)]
+pub struct FrontThreads {
+    comp_detect: JoinHandle<eyre::Result<()>>,
+    comp_accum: JoinHandle<eyre::Result<()>>,
+    text_recognize: JoinHandle<eyre::Result<()>>,
+}
+
+impl FrontThreads {
+    pub fn join(self) -> eyre::Result<()> {
+        self.comp_detect.join().unwrap()?;
+        self.comp_accum.join().unwrap()?;
+        self.text_recognize.join().unwrap()?;
+        Ok(())
+    }
+}
+
+fn spawn_streaming_thread<Input, Output, F>(
+    rx: mpsc::Receiver<(usize, Input)>,
+    tx: mpsc::Sender<(usize, Output)>,
+    name: &'static str,
+    num_threads: Option<usize>,
+    f: F,
+) -> JoinHandle<eyre::Result<()>>
+where
+    Input: Send + Sync + 'static,
+    Output: Send + Sync + 'static,
+    F: Fn(Input) -> eyre::Result<Output> + Send + Sync + 'static,
+{
+    let root_span = Span::current();
+    thread::spawn(move || -> eyre::Result<()> {
+        let _span = root_span.clone().entered();
+        ThreadPoolBuilder::default()
+            .thread_name(move |n| format!("{name}#{n}"))
+            // 0 lets rayon choose the number of threads
+            .num_threads(num_threads.unwrap_or(0))
+            .build()?
+            .install(move || -> eyre::Result<()> {
+                rx.into_iter().par_bridge().try_for_each(
+                    move |(i, packet)| -> eyre::Result<_> {
+                        let _span = root_span.enter();
+                        let packet = f(packet)?;
+                        tx.send((i, packet))?;
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            })?;
+        Ok(())
+    })
+}
+
+/// Spawns a thread running `f` in the current tracing span.
+pub fn spawn_accumulate_thread<F, T>(name: &str, f: F) -> eyre::Result<JoinHandle<eyre::Result<T>>>
+where
+    F: FnOnce() -> eyre::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let root_span = Span::current();
+    let handler = thread::Builder::new()
+        .name(name.into())
+        .spawn(move || -> eyre::Result<T> {
+            let _span = root_span.enter();
+            f()
+        })?;
+    Ok(handler)
+}
@@ -0,0 +1,213 @@
+use std::{fmt, slice, str::FromStr};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use regex::Regex;
+
+use crate::{
+    components::ComponentFilter,
+    config::Config,
+    inference::{Rule as _, Rules, SequenceRule},
+    io::spans::Span,
+};
+
+/// Condition stopping an analysis once a span satisfying it is closed, written as `KIND:VALUE`.
+///
+/// * `component:NAME` is met by a span of the component, which may be a group of components or a pattern
+///   (see [`ComponentFilter`]).
+/// * `text:REGEX` is met by a span whose text matches the regular expression, such as the name of an item.
+/// * `event:NAME` is met when an event of the rule named `NAME` in a rule file (see [`Rules`])
+///   is inferred from the spans so far.
+///
+/// Spans whose texts are not reliably recognized never meet `text:` conditions.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::analyzer::StopCondition;
+///
+/// let condition = "text:ストームヴィルの兜".parse::<StopCondition>().unwrap();
+/// assert_eq!(condition.to_string(), "text:ストームヴィルの兜");
+/// assert!("boss_bar".parse::<StopCondition>().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub enum StopCondition {
+    Component(String),
+    Text(Regex),
+    Event(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StopConditionParseError {
+    #[error("expected `component:NAME`, `text:REGEX` or `event:NAME`")]
+    Syntax,
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+}
+
+impl FromStr for StopCondition {
+    type Err = StopConditionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.split_once(':').ok_or(StopConditionParseError::Syntax)?;
+        if value.is_empty() {
+            return Err(StopConditionParseError::Syntax);
+        }
+        let condition = match kind {
+            "component" => Self::Component(value.to_owned()),
+            "text" => Self::Text(Regex::new(value)?),
+            "event" => Self::Event(value.to_owned()),
+            _ => return Err(StopConditionParseError::Syntax),
+        };
+        Ok(condition)
+    }
+}
+
+impl fmt::Display for StopCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Component(name) => write!(f, "component:{name}"),
+            Self::Text(re) => write!(f, "text:{re}"),
+            Self::Event(name) => write!(f, "event:{name}"),
+        }
+    }
+}
+
+/// Checks spans closed by an analysis against stop conditions and a maximum number of events.
+#[derive(Debug)]
+pub struct Stopper {
+    conditions: Vec<(StopCondition, Check)>,
+    max_events: Option<usize>,
+    num_events: usize,
+    /// Spans received so far sorted by their starts, kept only to infer events
+    spans: Vec<Span>,
+}
+
+#[derive(Debug)]
+enum Check {
+    Component(ComponentFilter),
+    Text(Regex),
+    Event(SequenceRule),
+}
+
+impl Stopper {
+    /// Returns the checker of `conditions`, which stops after `max_events` spans if given.
+    ///
+    /// Groups of components are resolved by `config`, and events are looked up in `rules`.
+    pub fn new(
+        conditions: &[StopCondition],
+        max_events: Option<usize>,
+        config: &Config,
+        rules: Option<&Rules>,
+    ) -> eyre::Result<Self> {
+        let conditions = conditions
+            .iter()
+            .map(|condition| {
+                let check = match condition {
+                    StopCondition::Component(name) => {
+                        Check::Component(config.component_filter(slice::from_ref(name))?)
+                    }
+                    StopCondition::Text(re) => Check::Text(re.clone()),
+                    StopCondition::Event(name) => Check::Event(
+                        rules
+                            .and_then(|rules| rules.rules.iter().find(|rule| rule.name == *name))
+                            .cloned()
+                            .ok_or_eyre(format!("event `{name}` is not defined in rules"))?,
+                    ),
+                };
+                Ok((condition.clone(), check))
+            })
+            .collect::<eyre::Result<_>>()?;
+        Ok(Self {
+            conditions,
+            max_events,
+            num_events: 0,
+            spans: vec![],
+        })
+    }
+
+    /// Returns `true` if no span stops the analysis.
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty() && self.max_events.is_none()
+    }
+
+    /// Receives a span closed by the analysis, returning the reason to stop if it meets a condition.
+    ///
+    /// Spans are counted as events except intervals of dropped frames.
+    pub fn receive(&mut self, span: &Span) -> Option<String> {
+        if span.is_skipped() {
+            return None;
+        }
+        self.num_events += 1;
+        if self.conditions.iter().any(|(_, check)| match check {
+            Check::Event(rule) => rule.steps.iter().any(|s| s.components.contains(&span.name)),
+            _ => false,
+        }) {
+            let idx = self.spans.partition_point(|s| s.start <= span.start);
+            self.spans.insert(idx, span.clone());
+        }
+        for (condition, check) in &self.conditions {
+            let met = match check {
+                Check::Component(filter) => filter.matches(&span.name),
+                Check::Text(re) => !span.is_possible() && re.is_match(&span.text),
+                Check::Event(rule) => !rule.infer(&self.spans).is_empty(),
+            };
+            if met {
+                return Some(format!("`{condition}` is met"));
+            }
+        }
+        if self.max_events.is_some_and(|max| self.num_events >= max) {
+            return Some(format!("{} events are found", self.num_events));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_by_conditions() {
+        let rules = toml::from_str::<Rules>(
+            r#"
+            [[rules]]
+            name = "boss_defeated"
+            steps = [{ components = ["boss_bar"] }, { components = ["runes"], max_gap = "00:00:05" }]
+            "#,
+        )
+        .unwrap();
+        let conditions = ["event:boss_defeated", "text:聖杯瓶"].map(|s| s.parse().unwrap());
+        let mut stopper =
+            Stopper::new(&conditions, None, &Config::default(), Some(&rules)).unwrap();
+        let mut receive = |s: &str| stopper.receive(&s.parse().unwrap());
+        assert_eq!(
+            receive("00:01:00.000-00:01:30.000 マルギット (boss_bar)"),
+            None
+        );
+        assert_eq!(
+            receive("00:01:31.000-00:01:33.000 ??聖杯瓶 (main_item)"),
+            None
+        );
+        assert_eq!(
+            receive("00:01:32.000-00:01:36.000 12345 (runes)").as_deref(),
+            Some("`event:boss_defeated` is met")
+        );
+
+        let err = Stopper::new(&conditions, None, &Config::default(), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "event `boss_defeated` is not defined in rules"
+        );
+
+        let mut stopper = Stopper::new(&[], Some(2), &Config::default(), None).unwrap();
+        let mut receive = |s: &str| stopper.receive(&s.parse().unwrap());
+        assert_eq!(receive("00:00:01.000-00:00:02.000 dropped (skipped)"), None);
+        assert_eq!(
+            receive("00:00:03.000-00:00:04.000 聖杯瓶 (main_item)"),
+            None
+        );
+        assert_eq!(
+            receive("00:00:05.000-00:00:06.000 ??剣 (side_item0)").as_deref(),
+            Some("2 events are found")
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::{mpsc, Arc};
+
+use color_eyre::eyre;
+use elden_analyzer_collections::seq_iter::SeqIter;
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+
+use crate::{
+    accumulate::{
+        runes::RuneAccumulator,
+        text::{AccumResult, FrameTexts, TextAccumulator},
+        AccumulateSpans,
+    },
+    components::{self, ComponentContainer},
+    io::spans,
+    items::{self, ItemCategory, ItemTranslation},
+};
+
+use super::{text_recognize, throttle::Throttle};
+
+/// Result of accumulating texts, passed to the handler of [`run`] in order.
+#[derive(Debug)]
+pub enum Output {
+    /// Frame is received, with whether each component is found in it and the in-game time if read
+    Frame {
+        pos: FramePosition,
+        found: ComponentContainer<bool>,
+        igt: Option<Duration>,
+    },
+    /// Span of the component at `column` is finalized
+    Span { column: usize, result: AccumResult },
+    /// Frames are dropped in the interval, only for throttled pipelines
+    Dropped(AccumResult),
+    /// No more spans start before the frame at the index
+    Settled(usize),
+    /// All frames are received at the position
+    Finished(FramePosition),
+}
+
+/// Accumulates texts recognized in frames received from `rx` into spans, and passes them to `handler`.
+///
+/// Returns the number of spans found for each component.
+#[tracing::instrument(name = "text_accum", level = "debug", skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    names: ComponentContainer<String>,
+    rx: mpsc::Receiver<(usize, text_recognize::Packet)>,
+    step: usize,
+    min_possible_share: i32,
+    translate_items: Option<ItemTranslation>,
+    only_categories: &[ItemCategory],
+    throttle: Option<Arc<Throttle>>,
+    mut handler: impl FnMut(Output) -> eyre::Result<()>,
+) -> eyre::Result<ComponentContainer<usize>> {
+    let mut accum = accumulators(names, translate_items, min_possible_share);
+
+    let mut last_pos = None;
+    for (_i, packet) in SeqIter::new(rx) {
+        let pos = packet.position();
+        let _span = tracing::trace_span!("frame", %pos).entered();
+
+        if let (Some(_), Some(last)) = (&throttle, last_pos) {
+            if let Some(result) = dropped_interval(last, pos, step) {
+                handler(Output::Dropped(result))?;
+            }
+        }
+        last_pos = Some(pos);
+
+        match packet {
+            text_recognize::Packet::Frame { pos, result, igt } => {
+                if let Some(throttle) = &throttle {
+                    throttle.complete(pos);
+                }
+                let found = result
+                    .iter()
+                    .map(|texts| matches!(texts, FrameTexts::Found(_)))
+                    .collect();
+                handler(Output::Frame { pos, found, igt })?;
+                for (column, (accum, result)) in accum.iter_mut().zip(*result).enumerate() {
+                    let result = accum.receive_frame(pos, result, igt);
+                    if let Some(result) = result.filter(|r| in_categories(r, only_categories)) {
+                        handler(Output::Span { column, result })?;
+                    }
+                }
+                let final_until = accum
+                    .iter()
+                    .map(|accum| accum.final_until(pos))
+                    .min()
+                    .unwrap();
+                handler(Output::Settled(final_until))?;
+            }
+            text_recognize::Packet::EndOfFrames { pos } => {
+                for (column, accum) in accum.iter_mut().enumerate() {
+                    let result = accum.receive_end_of_frames(pos);
+                    if let Some(result) = result.filter(|r| in_categories(r, only_categories)) {
+                        handler(Output::Span { column, result })?;
+                    }
+                }
+                handler(Output::Finished(pos))?;
+            }
+        }
+    }
+
+    Ok(accum.map(|accum| accum.num_spans()))
+}
+
+/// Returns the accumulators of spans of the components named `names`, as used by [`run`].
+///
+/// Texts of the rune count are accumulated by value, and the others by texts recognized in each frame.
+pub fn accumulators(
+    names: ComponentContainer<String>,
+    translate_items: Option<ItemTranslation>,
+    min_possible_share: i32,
+) -> ComponentContainer<Box<dyn AccumulateSpans>> {
+    names.map(|name| {
+        if components::is_runes_name(&name) {
+            return Box::new(RuneAccumulator::new(name)) as Box<dyn AccumulateSpans>;
+        }
+        Box::new(TextAccumulator::new(
+            name,
+            translate_items,
+            min_possible_share,
+        ))
+    })
+}
+
+/// Returns `true` if the span is of an item in `categories`, or `categories` is empty.
+///
+/// Intervals of dropped frames are not filtered, since they tell where events may be missing.
+fn in_categories(result: &AccumResult, categories: &[ItemCategory]) -> bool {
+    categories.is_empty()
+        || items::category(&result.text).is_some_and(|category| categories.contains(&category))
+}
+
+/// Returns the interval of frames dropped between the frames at `last` and `pos` received in order,
+/// where frames are sampled every `step` frames.
+fn dropped_interval(last: FramePosition, pos: FramePosition, step: usize) -> Option<AccumResult> {
+    let num_frames = pos.index().saturating_sub(last.index());
+    if num_frames <= step {
+        return None;
+    }
+    // Dropped frames are not decoded, so the timestamp of the first one is interpolated
+    let step_ts = (pos.timestamp() - last.timestamp()).as_ratio() * step as i64 / num_frames as i64;
+    let start = FramePosition::new(
+        last.index() + step,
+        last.timestamp() + Duration::new(step_ts),
+    );
+    Some(AccumResult {
+        name: spans::SKIPPED.to_owned(),
+        start,
+        end: pos,
+        text: spans::DROPPED.to_owned(),
+        igt: None,
+        lines: vec![],
+        rarity: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use elden_analyzer_kernel::types::time::Timestamp;
+    use num_rational::Ratio;
+
+    use super::*;
+
+    #[test]
+    fn dropped_frames_between_received_ones() {
+        let pos = |idx: i64| FramePosition::new(idx as usize, Timestamp::new(Ratio::new(idx, 10)));
+        assert!(dropped_interval(pos(1), pos(2), 1).is_none());
+
+        let result = dropped_interval(pos(1), pos(5), 1).unwrap();
+        assert_eq!(result.start.index(), 2);
+        assert_eq!(
+            result.into_span(None).to_string(),
+            "00:00:00.200-00:00:00.500 dropped (skipped)"
+        );
+
+        // Frames skipped by sampling are not dropped
+        assert!(dropped_interval(pos(3), pos(6), 3).is_none());
+        let result = dropped_interval(pos(3), pos(12), 3).unwrap();
+        assert_eq!(result.start.index(), 6);
+        assert_eq!(
+            result.into_span(None).to_string(),
+            "00:00:00.600-00:00:01.200 dropped (skipped)"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{LazyLock, Mutex};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+use elden_analyzer_video::capture::Frame;
+use lockfree_object_pool::LinearObjectPool;
+
+use crate::{
+    accumulate::{detection::AccumDetection, text::FrameTexts},
+    components::{Component, ComponentContainer, Components, DetectionPayload, ExtractedTexts},
+    igt::IgtReader,
+    image_process::tesseract::Tesseract,
+    util::ImageLogger,
+};
+
+use super::{comp_accum, FrameObserver};
+
+#[derive(Debug)]
+pub enum Packet {
+    Frame {
+        pos: FramePosition,
+        result: Box<ComponentContainer<FrameTexts>>,
+        igt: Option<Duration>,
+    },
+    EndOfFrames {
+        pos: FramePosition,
+    },
+}
+
+impl Packet {
+    pub fn position(&self) -> FramePosition {
+        match self {
+            Self::Frame { pos, .. } => *pos,
+            Self::EndOfFrames { pos } => *pos,
+        }
+    }
+}
+
+#[tracing::instrument(name = "text_recognize", level = "trace", skip_all, fields(pos = %packet.position()))]
+pub fn run(
+    components: &Components,
+    tess: Option<&LinearObjectPool<LazyLock<Mutex<Tesseract>, impl FnOnce() -> Mutex<Tesseract>>>>,
+    igt: Option<&IgtReader>,
+    observer: Option<&dyn FrameObserver>,
+    packet: comp_accum::Packet,
+) -> eyre::Result<Packet> {
+    let packet = match packet {
+        comp_accum::Packet::Frame { pos, frame, result } => {
+            let result = result
+                .into_iter()
+                .zip(components)
+                .map(|(found, component)| -> eyre::Result<FrameTexts> {
+                    let texts = match (found, tess) {
+                        (AccumDetection::Found(_), None) => {
+                            FrameTexts::Found(ExtractedTexts::default())
+                        }
+                        (AccumDetection::Found(payload), Some(tess)) => {
+                            FrameTexts::Found(recognize(&**component, tess, pos, &frame, payload)?)
+                        }
+                        // Lead-in frames are only useful if texts are recognized
+                        (AccumDetection::LeadIn, Some(tess)) => {
+                            FrameTexts::LeadIn(recognize(&**component, tess, pos, &frame, None)?)
+                        }
+                        (AccumDetection::Bridged, _) => FrameTexts::Bridged,
+                        (AccumDetection::LeadIn, None) | (AccumDetection::Absent, _) => {
+                            FrameTexts::Absent
+                        }
+                    };
+                    Ok(texts)
+                })
+                .collect::<eyre::Result<ComponentContainer<_>>>()?;
+
+            // In-game time is only needed for frames in spans
+            let igt = match (igt, tess) {
+                (Some(igt), Some(tess))
+                    if result.iter().any(|t| !matches!(t, FrameTexts::Absent)) =>
+                {
+                    let tess = tess.pull();
+                    let mut tess = tess.lock().unwrap();
+                    igt.read(&mut tess, &frame)?
+                }
+                _ => None,
+            };
+
+            if let Some(observer) = observer {
+                observer.observe(pos, frame, components, &result);
+            }
+
+            let result = Box::new(result);
+            Packet::Frame { pos, result, igt }
+        }
+        comp_accum::Packet::EndOfFrames { pos } => Packet::EndOfFrames { pos },
+    };
+    Ok(packet)
+}
+
+fn recognize(
+    component: &dyn Component,
+    tess: &LinearObjectPool<LazyLock<Mutex<Tesseract>, impl FnOnce() -> Mutex<Tesseract>>>,
+    pos: FramePosition,
+    frame: &Frame,
+    payload: Option<DetectionPayload>,
+) -> eyre::Result<ExtractedTexts> {
+    let tess = tess.pull();
+    let mut tess = tess.lock().unwrap();
+    let _subject = ImageLogger::enter_subject(pos.index(), component.name());
+    let result = component.extract_text(&mut tess, frame, payload)?;
+    tracing::trace!(name = component.name(), %pos, ?result);
+    Ok(result)
+}
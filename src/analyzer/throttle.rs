@@ -0,0 +1,168 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, AtomicUsize, Ordering},
+        Condvar, Mutex, OnceLock,
+    },
+    time::Instant,
+};
+
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+use elden_analyzer_video::capture::Frame;
+use num_rational::Ratio;
+
+/// Interval of lag reports in stream time (msec)
+const REPORT_INTERVAL_MSEC: i64 = 10_000;
+
+/// Drops frames in the decode stage when the pipeline cannot keep up with a live source.
+///
+/// At most `max_pending_frames` frames are in the pipeline at a time.
+/// A frame decoded while the pipeline is full waits for room, replacing the frame waiting before it,
+/// so the pipeline always receives the latest frame once it catches up.
+#[derive(Debug)]
+pub struct Throttle {
+    max_pending_frames: usize,
+    state: Mutex<State>,
+    changed: Condvar,
+    dropped_frames: AtomicUsize,
+    max_lag_msec: AtomicI64,
+    last_report_msec: AtomicI64,
+    start: OnceLock<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    pending_frames: usize,
+    /// Frame waiting for room in the pipeline, with the frames skipped before it
+    latest: Option<(Frame, VecDeque<Frame>)>,
+    closed: bool,
+}
+
+impl Throttle {
+    pub fn new(max_pending_frames: usize) -> Self {
+        Self {
+            max_pending_frames,
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+            dropped_frames: AtomicUsize::new(0),
+            max_lag_msec: AtomicI64::new(0),
+            last_report_msec: AtomicI64::new(0),
+            start: OnceLock::new(),
+        }
+    }
+
+    /// Offers the decoded frame to the pipeline, with the frames skipped before it.
+    ///
+    /// The frame waiting for room is dropped and replaced if any.
+    /// Returns `false` if the throttle is closed.
+    pub fn offer(&self, frame: Frame, skipped: VecDeque<Frame>) -> bool {
+        self.start.get_or_init(Instant::now);
+
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        if let Some((dropped, _)) = state.latest.replace((frame, skipped)) {
+            let pending = state.pending_frames;
+            let dropped_frames = self.dropped_frames.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::trace!(pos = %dropped.position(), pending, dropped_frames, "drop frame");
+        }
+        self.changed.notify_all();
+        true
+    }
+
+    /// Waits until the pipeline has room, and returns the latest offered frame to send to it.
+    ///
+    /// Returns `None` once the throttle is closed, dropping the frame waiting for room if any.
+    pub fn next(&self) -> Option<(Frame, VecDeque<Frame>)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                if state.latest.take().is_some() {
+                    self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                }
+                return None;
+            }
+            if state.pending_frames < self.max_pending_frames {
+                if let Some(latest) = state.latest.take() {
+                    state.pending_frames += 1;
+                    return Some(latest);
+                }
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Stops offering frames, and wakes up the waiting [`Self::next`].
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+
+    /// Notifies that the frame at `pos` has gone through the pipeline.
+    pub fn complete(&self, pos: FramePosition) {
+        self.state.lock().unwrap().pending_frames -= 1;
+        self.changed.notify_all();
+
+        let lag = self.lag(pos);
+        let lag_msec = lag.as_msec();
+        self.max_lag_msec.fetch_max(lag_msec, Ordering::Relaxed);
+
+        let ts_msec = pos.timestamp().as_ratio() * 1000;
+        let ts_msec = ts_msec.to_integer();
+        let last_report = self.last_report_msec.load(Ordering::Relaxed);
+        if ts_msec - last_report >= REPORT_INTERVAL_MSEC {
+            self.last_report_msec.store(ts_msec, Ordering::Relaxed);
+            tracing::info!(
+                %pos,
+                %lag,
+                dropped_frames = self.dropped_frames.load(Ordering::Relaxed),
+                "live capture status"
+            );
+        }
+    }
+
+    /// Reports the total number of dropped frames and the maximum lag.
+    pub fn report(&self) {
+        let max_lag = Duration::new(Ratio::new(self.max_lag_msec.load(Ordering::Relaxed), 1000));
+        tracing::info!(
+            dropped_frames = self.dropped_frames.load(Ordering::Relaxed),
+            %max_lag,
+            "live capture finished"
+        );
+    }
+
+    /// Delay between the frame captured and processed.
+    fn lag(&self, pos: FramePosition) -> Duration {
+        let Some(start) = self.start.get() else {
+            return Duration::default();
+        };
+        let elapsed = Ratio::new(start.elapsed().as_millis() as i64, 1000);
+        Duration::new((elapsed - pos.timestamp().as_ratio()).max(Ratio::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_frame_wins() {
+        let throttle = Throttle::new(1);
+        assert!(throttle.offer(Frame::empty(), VecDeque::new()));
+        let (frame, _) = throttle.next().unwrap();
+
+        // The pipeline is full, so the second frame is replaced by the third one
+        assert!(throttle.offer(Frame::empty(), VecDeque::new()));
+        assert!(throttle.offer(Frame::empty(), VecDeque::from([Frame::empty()])));
+        assert_eq!(throttle.dropped_frames.load(Ordering::Relaxed), 1);
+
+        throttle.complete(frame.position());
+        let (_, skipped) = throttle.next().unwrap();
+        assert_eq!(skipped.len(), 1);
+
+        throttle.close();
+        assert!(throttle.next().is_none());
+        assert!(!throttle.offer(Frame::empty(), VecDeque::new()));
+    }
+}
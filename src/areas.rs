@@ -0,0 +1,42 @@
+//! Names of areas shown in banners when entering them.
+
+use std::{collections::HashSet, sync::LazyLock};
+
+use crate::assets::{self, Asset};
+
+static AREA_NAMES: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    assets::text(Asset::Areas)
+        .lines()
+        .filter(|x| !x.is_empty() && !x.starts_with("#"))
+        .collect()
+});
+
+/// Builds the set of area names, which is built at first use otherwise.
+pub fn preload() {
+    LazyLock::force(&AREA_NAMES);
+}
+
+/// Returns `true` if `name` is a known area name.
+pub fn is_area_name(name: &str) -> bool {
+    AREA_NAMES.contains(name)
+}
+
+/// Returns the known area name differing from `name` in exactly one character, if it is the only one.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::areas;
+///
+/// assert_eq!(areas::correct("リムグレイプ"), Some("リムグレイブ"));
+/// assert_eq!(areas::correct("リムグレイブ"), None);
+/// assert_eq!(areas::correct("関門前の廃墟"), None);
+/// ```
+pub fn correct(name: &str) -> Option<&'static str> {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut candidates = AREA_NAMES.iter().copied().filter(|known| {
+        known.chars().count() == chars.len()
+            && known.chars().zip(&chars).filter(|(a, b)| a != *b).count() == 1
+    });
+    let first = candidates.next()?;
+    candidates.next().is_none().then_some(first)
+}
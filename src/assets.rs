@@ -0,0 +1,320 @@
+//! Dictionaries and the default configuration bundled in `assets/`, which can be replaced by newer files between releases.
+//!
+//! Files in the directory chosen by [`use_assets_dir`] take the place of the bundled files of the same names,
+//! and the other dictionaries stay bundled. Files are validated when the directory is chosen,
+//! so that malformed entries are reported with their lines before the analysis starts.
+//!
+//! If the directory has a `SHA256SUMS` file in the format written by `sha256sum`, every dictionary in the directory
+//! must be listed in it with the right checksum, and every file listed must exist,
+//! so that files broken or missed while downloading are never used.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use color_eyre::eyre::{self, bail, eyre, WrapErr as _};
+use sha2::{Digest as _, Sha256};
+
+use crate::{config::Config, data_package, items::ItemDatabase};
+
+/// Name of the file listing checksums of files in the asset directory
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Dictionary read from the assets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Asset {
+    /// Database of known items (`items.tsv`)
+    Items,
+    /// English names of items (`item_en.tsv`)
+    ItemNamesEn,
+    /// Names of areas (`area.txt`)
+    Areas,
+    /// Default configuration, into which config files are merged (`config.toml`)
+    DefaultConfig,
+}
+
+impl Asset {
+    pub const ALL: [Self; 4] = [
+        Self::Items,
+        Self::ItemNamesEn,
+        Self::Areas,
+        Self::DefaultConfig,
+    ];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Items => "items.tsv",
+            Self::ItemNamesEn => "item_en.tsv",
+            Self::Areas => "area.txt",
+            Self::DefaultConfig => "config.toml",
+        }
+    }
+
+    fn bundled(self) -> &'static str {
+        match self {
+            Self::Items => include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items.tsv")),
+            Self::ItemNamesEn => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/item_en.tsv"))
+            }
+            Self::Areas => include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/area.txt")),
+            Self::DefaultConfig => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/config.toml"))
+            }
+        }
+    }
+
+    /// Checks that every entry of `text` is well-formed.
+    pub(crate) fn validate(self, text: &str) -> eyre::Result<()> {
+        match self {
+            Self::Items => {
+                ItemDatabase::parse(text)?;
+            }
+            Self::ItemNamesEn => {
+                let mut names = HashSet::new();
+                for (lineno, line) in entries(text) {
+                    let Some((ja, en)) = line
+                        .split_once('\t')
+                        .filter(|(ja, en)| !ja.is_empty() && !en.is_empty() && !en.contains('\t'))
+                    else {
+                        bail!("line {lineno} must be a Japanese name and an English name separated by a tab");
+                    };
+                    check_trimmed(ja, lineno)?;
+                    check_trimmed(en, lineno)?;
+                    if !names.insert(ja) {
+                        bail!("duplicated name `{ja}` at line {lineno}");
+                    }
+                }
+            }
+            Self::Areas => {
+                for (lineno, line) in entries(text) {
+                    if line.contains('\t') {
+                        bail!("line {lineno} must be a single name without tabs");
+                    }
+                    check_trimmed(line, lineno)?;
+                }
+            }
+            Self::DefaultConfig => {
+                toml::from_str::<Config>(text)?.validate()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.file_name())
+    }
+}
+
+/// Returns the lines of entries with their numbers, skipping empty lines and comments.
+fn entries(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn check_trimmed(name: &str, lineno: usize) -> eyre::Result<()> {
+    if name.trim() != name {
+        bail!("`{name}` at line {lineno} has leading or trailing spaces");
+    }
+    Ok(())
+}
+
+/// Where a dictionary is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Bundled,
+    File(PathBuf),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bundled => write!(f, "bundled"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Error returned by [`use_assets_dir`] when the dictionaries are already used.
+#[derive(Debug, thiserror::Error)]
+#[error("asset directory cannot be changed after dictionaries are used")]
+pub struct AssetsInUse;
+
+/// Assets replaced by files of the asset directory
+#[derive(Debug, Default)]
+pub(crate) struct Overrides(HashMap<Asset, (PathBuf, &'static str)>);
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Replaces the bundled dictionaries with the files of `dir`, after validating and verifying them.
+///
+/// This must be called before the dictionaries are used, as in [`items::use_item_list`](crate::items::use_item_list).
+pub fn use_assets_dir(dir: &Path) -> eyre::Result<()> {
+    if OVERRIDES.get().is_some() {
+        return Err(AssetsInUse.into());
+    }
+    let overrides =
+        load_dir(dir).wrap_err_with(|| format!("failed to load assets: {}", dir.display()))?;
+    for (asset, (path, text)) in &overrides.0 {
+        tracing::info!(%asset, path = %path.display(), checksum = checksum_of(text), "using asset");
+    }
+    OVERRIDES.set(overrides).map_err(|_| AssetsInUse)?;
+    Ok(())
+}
+
+pub(crate) fn load_dir(dir: &Path) -> eyre::Result<Overrides> {
+    if !dir.is_dir() {
+        bail!("not a directory");
+    }
+    let checksums = match fs::read_to_string(dir.join(CHECKSUMS_FILE)) {
+        Ok(text) => {
+            Some(parse_checksums(&text).wrap_err_with(|| format!("invalid {CHECKSUMS_FILE}"))?)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err).wrap_err_with(|| format!("failed to read {CHECKSUMS_FILE}")),
+    };
+
+    let mut overrides = Overrides::default();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name == CHECKSUMS_FILE || name == data_package::MANIFEST_FILE {
+            continue;
+        }
+        let Some(asset) = Asset::ALL
+            .into_iter()
+            .find(|asset| asset.file_name() == name)
+        else {
+            tracing::warn!(path = %path.display(), "unknown file in the asset directory is ignored");
+            continue;
+        };
+        let text = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        if let Some(checksums) = &checksums {
+            let expected = checksums
+                .get(&*name)
+                .ok_or_else(|| eyre!("{name} is not listed in {CHECKSUMS_FILE}"))?;
+            let actual = checksum_of(&text);
+            if *expected != actual {
+                bail!("checksum of {name} does not match {CHECKSUMS_FILE} (expected {expected}, found {actual})");
+            }
+        }
+        asset
+            .validate(&text)
+            .wrap_err_with(|| format!("invalid {}", path.display()))?;
+        // Dictionaries are used until the end of the process, as the bundled ones
+        overrides.0.insert(asset, (path, String::leak(text)));
+    }
+    if let Some(checksums) = &checksums {
+        if let Some(name) = checksums.keys().find(|name| !dir.join(name).is_file()) {
+            bail!("{name} listed in {CHECKSUMS_FILE} is missing");
+        }
+    }
+    Ok(overrides)
+}
+
+/// Parses lines of `sha256sum`, each of which is a checksum and a file name separated by ` ` or ` *`.
+fn parse_checksums(text: &str) -> eyre::Result<HashMap<String, String>> {
+    let mut checksums = HashMap::new();
+    for (lineno, line) in entries(text) {
+        let Some((checksum, name)) = line
+            .split_once("  ")
+            .or_else(|| line.split_once(" *"))
+            .filter(|(checksum, _)| {
+                checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit())
+            })
+        else {
+            bail!("line {lineno} must be a SHA-256 checksum and a file name");
+        };
+        checksums.insert(name.to_owned(), checksum.to_ascii_lowercase());
+    }
+    Ok(checksums)
+}
+
+pub(crate) fn checksum_of(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// Returns the text of the dictionary, from the asset directory if it has the file.
+pub fn text(asset: Asset) -> &'static str {
+    match OVERRIDES.get_or_init(Overrides::default).0.get(&asset) {
+        Some((_, text)) => text,
+        None => asset.bundled(),
+    }
+}
+
+/// Returns where the dictionary is read from.
+pub fn origin(asset: Asset) -> Origin {
+    match OVERRIDES.get_or_init(Overrides::default).0.get(&asset) {
+        Some((path, _)) => Origin::File(path.clone()),
+        None => Origin::Bundled,
+    }
+}
+
+/// Returns the SHA-256 checksum of the dictionary in hex, to tell which version of it is used.
+pub fn checksum(asset: Asset) -> String {
+    checksum_of(text(asset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_assets_are_valid() {
+        for asset in Asset::ALL {
+            asset.validate(asset.bundled()).unwrap();
+        }
+    }
+
+    #[test]
+    fn validate_entries() {
+        let err = Asset::ItemNamesEn
+            .validate("# comment\n緋雫の聖杯瓶\tFlask of Crimson Tears\n青雫の聖杯瓶 Flask of Cerulean Tears\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{err}");
+        let err = Asset::ItemNamesEn
+            .validate("緋雫の聖杯瓶\tA\n緋雫の聖杯瓶\tB\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("duplicated"), "{err}");
+        let err = Asset::Areas
+            .validate("リムグレイブ\n嵐の丘 \n")
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn load_dir_with_checksums() {
+        let dir =
+            std::env::temp_dir().join(format!("elden-analyzer-assets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let areas = "リムグレイブ\n嵐の丘\n";
+        fs::write(dir.join("area.txt"), areas).unwrap();
+
+        let overrides = load_dir(&dir).unwrap();
+        assert_eq!(overrides.0[&Asset::Areas].1, areas);
+        assert!(!overrides.0.contains_key(&Asset::Items));
+
+        let sums = format!("{}  area.txt\n", checksum_of(areas));
+        fs::write(dir.join(CHECKSUMS_FILE), &sums).unwrap();
+        load_dir(&dir).unwrap();
+
+        fs::write(dir.join("area.txt"), "リムグレイブ\n").unwrap();
+        let err = load_dir(&dir).unwrap_err();
+        assert!(format!("{err:#}").contains("does not match"), "{err:#}");
+
+        fs::write(dir.join("area.txt"), areas).unwrap();
+        let sums = format!("{sums}{}  items.tsv\n", "0".repeat(64));
+        fs::write(dir.join(CHECKSUMS_FILE), sums).unwrap();
+        let err = load_dir(&dir).unwrap_err();
+        assert!(format!("{err:#}").contains("items.tsv listed"), "{err:#}");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
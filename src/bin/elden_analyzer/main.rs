@@ -1,12 +1,24 @@
 use std::{
+    env,
     fs::{self, File},
-    path::PathBuf,
-    sync::Arc,
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, OnceLock},
 };
 
 use chrono::Utc;
-use clap::Parser as _;
-use color_eyre::eyre::{self};
+use clap::{CommandFactory as _, Parser as _};
+use color_eyre::{
+    config::{HookBuilder, Theme},
+    eyre::{self, WrapErr as _},
+};
+use elden_analyzer::{
+    analyzer::CancellationToken,
+    assets, build_info, data_package,
+    i18n::{Lang, Message},
+    util::{self, ImageLogger},
+};
 use tracing::level_filters::LevelFilter;
 use tracing_error::ErrorLayer;
 use tracing_indicatif::IndicatifLayer;
@@ -21,12 +33,66 @@ use crate::subcommand::Subcommand;
 mod subcommand;
 mod tui;
 
+static TMP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Returns the directory for temporary files.
+pub(crate) fn tmp_dir() -> &'static Path {
+    TMP_DIR.get_or_init(env::temp_dir)
+}
+
+static CANCELLATION: OnceLock<CancellationToken> = OnceLock::new();
+
+/// Returns the token cancelled by Ctrl-C, installing the handler on the first call.
+///
+/// The handler is installed only while analyzing, so that Ctrl-C exits other subcommands immediately.
+/// Pressing Ctrl-C again exits without waiting for the pipeline to finish.
+pub(crate) fn cancellation() -> &'static CancellationToken {
+    CANCELLATION.get_or_init(|| {
+        let token = CancellationToken::new();
+        let handler = {
+            let token = token.clone();
+            move || {
+                if token.is_cancelled() {
+                    process::exit(130);
+                }
+                token.cancel();
+            }
+        };
+        if let Err(err) = ctrlc::set_handler(handler) {
+            tracing::warn!(%err, "failed to set the Ctrl-C handler");
+        }
+        token
+    })
+}
+
 #[derive(clap::Parser, Debug)]
 struct Args {
+    /// Run without a display or a terminal, e.g. in containers and CI pipelines.
+    /// Images are not displayed, and logs and progress are written to stderr as JSON lines without colors.
+    #[clap(long, global = true)]
+    headless: bool,
+    /// Directory for temporary files (default: the system temporary directory)
+    #[clap(long, global = true)]
+    tmp_dir: Option<PathBuf>,
+    /// Directory of dictionaries replacing the bundled ones of the same names (`items.tsv`, `item_en.tsv`, `area.txt`, `config.toml`),
+    /// to use dictionaries updated between releases. Files are verified against `SHA256SUMS` in the directory if it exists.
+    /// [default: the data package installed by `update-data`, if any]
+    #[clap(long, global = true)]
+    assets_dir: Option<PathBuf>,
+    /// Language of summaries and warnings (`en` or `ja`, default: from the locale).
+    /// Output files are not localized.
+    #[clap(long, global = true)]
+    ui_lang: Option<Lang>,
+    /// Print version
+    #[clap(long, short = 'V')]
+    version: bool,
+    /// Print the commit, the build date and the enabled features with `--version`
+    #[clap(long, requires = "version")]
+    verbose: bool,
     #[clap(flatten)]
     log_args: LogArgs,
     #[command(subcommand)]
-    subcommand: Subcommand,
+    subcommand: Option<Subcommand>,
 }
 
 #[derive(clap::Parser, Debug)]
@@ -42,22 +108,79 @@ struct LogArgs {
 }
 
 fn main() -> eyre::Result<()> {
-    color_eyre::install()?;
-
     let Args {
+        headless,
+        tmp_dir,
+        assets_dir,
+        ui_lang,
+        version,
+        verbose,
         log_args,
         subcommand,
     } = Args::parse();
 
-    init_log(log_args)?;
+    if version {
+        match verbose {
+            true => println!("{}", build_info::verbose()),
+            false => println!("elden-analyzer {}", build_info::VERSION),
+        }
+        return Ok(());
+    }
+    let Some(subcommand) = subcommand else {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingSubcommand,
+                "a subcommand is required",
+            )
+            .exit();
+    };
+
+    ui_lang.unwrap_or_else(Lang::from_env).set();
+
+    if headless {
+        HookBuilder::default().theme(Theme::new()).install()?;
+        ImageLogger::disable_display();
+        tui::set_headless();
+    } else {
+        color_eyre::install()?;
+    }
+    if let Some(tmp_dir) = tmp_dir {
+        fs::create_dir_all(&tmp_dir).wrap_err_with(|| {
+            format!(
+                "failed to create temporary directory: {}",
+                tmp_dir.display()
+            )
+        })?;
+        TMP_DIR.set(tmp_dir).unwrap();
+    }
+
+    init_log(log_args, headless)?;
     elden_analyzer_video::init()?;
+    match &assets_dir {
+        Some(assets_dir) => assets::use_assets_dir(assets_dir)?,
+        None => use_installed_data(),
+    }
 
     subcommand.run()?;
 
     Ok(())
 }
 
-fn init_log(args: LogArgs) -> eyre::Result<()> {
+/// Uses the data package installed by `update-data` as the asset directory, falling back to the bundled assets.
+fn use_installed_data() {
+    let Some(dir) = data_package::installed_dir().filter(|dir| dir.is_dir()) else {
+        return;
+    };
+    if let Err(err) = assets::use_assets_dir(&dir) {
+        const MESSAGE: Message = Message::new(
+            "failed to load the installed data package, using the bundled assets instead",
+            "インストール済みのデータパッケージを読み込めなかったため、同梱のアセットを使います",
+        );
+        tracing::warn!(?err, dir = %dir.display(), "{MESSAGE}");
+    }
+}
+
+fn init_log(args: LogArgs, headless: bool) -> eyre::Result<()> {
     let LogArgs {
         console_filter,
         emit_log,
@@ -76,9 +199,20 @@ fn init_log(args: LogArgs) -> eyre::Result<()> {
     let console_layer = fmt::layer()
         .with_timer(fmt::time::Uptime::default())
         .with_target(false)
-        .with_span_events(FmtSpan::CLOSE)
-        .with_writer(indicatif_layer.get_stderr_writer())
-        .with_filter(console_filter);
+        .with_span_events(FmtSpan::CLOSE);
+    let console_layer = if headless {
+        console_layer
+            .with_ansi(false)
+            .json()
+            .with_writer(io::stderr)
+            .with_filter(console_filter)
+            .boxed()
+    } else {
+        console_layer
+            .with_writer(indicatif_layer.get_stderr_writer())
+            .with_filter(console_filter)
+            .boxed()
+    };
 
     let log_filter = log_filter
         .map(|f| Arc::into_inner(f).unwrap())
@@ -90,13 +224,21 @@ fn init_log(args: LogArgs) -> eyre::Result<()> {
 
     let error_layer = ErrorLayer::default();
 
+    // The working directory may be read-only in containers
+    let mut log_fallback = None;
     let log_layer = emit_log
         .then(|| -> eyre::Result<_> {
             let utc = Utc::now();
-            let timestamp = utc.format("%Y-%m-%d_%H-%M-%S");
-            let log_path = log_dir.join(format!("{timestamp}.log"));
-            fs::create_dir_all(&log_dir)?;
-            let log_file = File::create(log_path)?;
+            let file_name = format!("{}.log", utc.format("%Y-%m-%d_%H-%M-%S"));
+            let log_file = match create_log_file(&log_dir, &file_name) {
+                Ok(file) => file,
+                Err(err) => {
+                    let fallback = tmp_dir().join("elden-analyzer-log");
+                    let file = create_log_file(&fallback, &file_name)?;
+                    log_fallback = Some((err, fallback));
+                    file
+                }
+            };
 
             let layer = fmt::layer()
                 .with_ansi(false)
@@ -110,13 +252,30 @@ fn init_log(args: LogArgs) -> eyre::Result<()> {
     tracing_subscriber::registry()
         .with(console_layer)
         .with(log_layer)
-        .with(indicatif_layer)
+        .with((!headless).then_some(indicatif_layer))
         .with(error_layer)
         .init();
 
+    if let Some((err, fallback)) = log_fallback {
+        const MESSAGE: Message = Message::new(
+            "failed to create a log file, writing it to the temporary directory instead",
+            "ログファイルを作成できなかったため、一時ディレクトリに書き込みます",
+        );
+        tracing::warn!(
+            ?err,
+            log_dir = %log_dir.display(),
+            fallback = %fallback.display(),
+            "{MESSAGE}"
+        );
+    }
+
     Ok(())
 }
 
+fn create_log_file(log_dir: &Path, file_name: &str) -> io::Result<File> {
+    util::create_file(&log_dir.join(file_name))
+}
+
 fn parse_filter_arg(s: &str) -> eyre::Result<Arc<EnvFilter>> {
     let filter = EnvFilter::try_new(s)?;
     Ok(Arc::new(filter))
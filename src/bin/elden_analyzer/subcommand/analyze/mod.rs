@@ -1,35 +1,75 @@
 use std::{
-    fs::File,
     path::{Path, PathBuf},
-    sync::{mpsc, Arc, LazyLock, Mutex},
-    thread::{self, JoinHandle},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
 };
 
-use color_eyre::eyre::{self, OptionExt as _};
+use chrono::Utc;
+use color_eyre::eyre::{self, bail, OptionExt as _, WrapErr as _};
 use elden_analyzer::{
-    components::Components, image_process::tesseract::Tesseract, util::ImageLogger,
+    analyzer::{
+        decode::{self, SampleRate},
+        reload::ConfigWatcher,
+        scores::ScoreWriter,
+        spawn_accumulate_thread,
+        throttle::Throttle,
+        FrameObserver, Stages, StopCondition, Stopper, Summary,
+    },
+    components,
+    config::{AreaThreshold, Config, ThresholdOverrides},
+    environment::Environment,
+    i18n::{Lang, Message},
+    igt::{IgtFormat, IgtReaderBuilder},
+    inference::Rules,
+    io::{
+        event_id::SourceId,
+        event_log::{EventLog, Rotation},
+        event_stream::{self, EventStream},
+        timeline::{Layout, UncertainCells},
+    },
+    items::{ItemCategory, ItemTranslation},
+    profile::Profile,
+    util::{self, ImageLogger},
 };
-use elden_analyzer_kernel::types::time::TimestampRange;
-use elden_analyzer_video::capture::VideoCapture;
-use lockfree_object_pool::LinearObjectPool;
-use rayon::{prelude::*, ThreadPoolBuilder};
+use elden_analyzer_kernel::types::{
+    rect::Rect,
+    time::{Duration, Timestamp, TimestampRange},
+};
+use elden_analyzer_video::{
+    capture::{HwAccel, ImageSequenceBackend, LiveSource, OpenOptions, VideoCapture},
+    metadata,
+};
+use num_rational::Ratio;
 use tracing::Span;
 
-use crate::tui::ProgressBarBuilder;
+use crate::tui::{FileProgress, ProgressBarBuilder};
+
+use self::{preview::Preview, sweep::SweepParam};
 
-mod comp_accum;
-mod comp_detect;
-mod decode;
+use super::{BackendArgs, ConfigArgs};
+
+mod preview;
+mod segment;
+mod sweep;
 mod text_accum;
-mod text_recognize;
+mod timeline;
+
+/// Maximum number of frames in the pipeline of live sources unless specified
+pub(super) const DEFAULT_MAX_PENDING_FRAMES: usize = 30;
 
 /// Analyze the video files to extract information
 #[derive(clap::Parser, Debug)]
 pub struct Args {
-    /// Input file to process
-    input: PathBuf,
-    /// Frames to process
-    #[clap(default_value = "-")]
+    /// Input files to process.
+    /// Several files are analyzed concurrently, and each output file is named after the input as `STEM.NAME`,
+    /// e.g. `--output-span out/spans.txt` writes `out/run1.spans.txt` for `run1.mp4`.
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Frames to process (`START-END`, `START-`, `-END` or a single timestamp)
+    #[clap(long, default_value = "-")]
     timestamp: TimestampRange,
     /// Output span file
     #[clap(long)]
@@ -37,6 +77,257 @@ pub struct Args {
     /// Output TSV file
     #[clap(long)]
     output_tsv: Option<PathBuf>,
+    /// Output NDJSON file of spans with the confidences and alternative candidates of texts
+    #[clap(long)]
+    output_json: Option<PathBuf>,
+    /// Layout of the output TSV file (`wide`: a column for each component, `long`: a row for each change of texts)
+    #[clap(long, default_value_t)]
+    tsv_layout: Layout,
+    /// How texts not reliably recognized are written in the output TSV file (`prefix`: `??text`, `empty`,
+    /// `suffix`: `text?`, `percent`: `text (NN%)`, `columns`: `text` with a column of confidences)
+    #[clap(long, default_value_t)]
+    tsv_uncertain: UncertainCells,
+    /// Output only spans of items in the categories, such as `weapons,talismans`, in all outputs
+    /// (tools, remembrances, ashes, crafting-materials, upgrade-materials, key-items, sorceries, incantations,
+    /// ashes-of-war, weapons, ammunition, shields, armor, talismans, info, gestures)
+    #[clap(long, value_delimiter = ',')]
+    only_category: Vec<ItemCategory>,
+    /// Output SubRip subtitle file of spans, to check recognitions by playing the video with it
+    #[clap(long)]
+    output_srt: Option<PathBuf>,
+    /// Output WebVTT subtitle file of spans, to check recognitions by playing the video with it
+    #[clap(long)]
+    output_vtt: Option<PathBuf>,
+    /// Output span file of pickups, where spans of a side item moving upward across rows are merged into one
+    #[clap(long)]
+    output_pickups: Option<PathBuf>,
+    /// Output CSV file of the numbers of pickups, deaths, bosses and runes gained in each window of `--bucket-window`,
+    /// for charts of the run
+    #[clap(long)]
+    output_buckets: Option<PathBuf>,
+    /// Length of windows counting events in `--output-buckets`
+    #[clap(long, default_value = "00:01:00")]
+    bucket_window: Timestamp,
+    /// Output CSV file of detection scores of each frame, including ones below thresholds
+    #[clap(long)]
+    output_scores: Option<PathBuf>,
+    /// Send each span as a JSON object to a WebSocket server (`ws://HOST:PORT/PATH`) or a Unix domain socket (a path)
+    /// as soon as it is finalized, for overlays and stream bots. The receiver must be listening when the analysis starts.
+    #[clap(long, value_name = "TARGET")]
+    emit_events: Option<event_stream::Target>,
+    /// Record versions of FFmpeg, Tesseract and its trained data, the OS and the CPU in the header of output files,
+    /// since recognitions differ subtly across versions
+    #[clap(long)]
+    record_environment: bool,
+    /// Do not write the index of analyzed videos (`VIDEO.elden-index`), which `search` answers from without decoding.
+    /// The index is written only when the whole video is analyzed with no filters or stop conditions.
+    #[clap(long)]
+    no_index: bool,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Decode with hardware acceleration (`auto`, `vaapi`, `nvdec` or `videotoolbox`).
+    /// Falls back to decoding by the CPU if it is not available.
+    #[clap(long)]
+    hwaccel: Option<HwAccel>,
+    /// Frame rate of inputs of images (`N` or `N/D`), which have no timestamps [default: 30]
+    #[clap(long)]
+    image_fps: Option<Ratio<i64>>,
+    /// Output the PTS of frames instead of timestamps computed from frame indices.
+    /// Useful for videos with an inaccurate frame rate.
+    #[clap(long)]
+    exact_pts: bool,
+    /// Number of frames before each span to recognize texts in, since components fade in.
+    /// Spans start from the earliest of these frames where texts are readable.
+    /// [default: `lead_in` of the profile, or 0]
+    #[clap(long)]
+    lead_in: Option<usize>,
+    /// Number of frames after a found frame within which possible or found frames continue the span,
+    /// regarding absent frames between them as found. 2 or 3 helps with flickering detections in 30 fps videos.
+    /// [default: `promote_window` of the profile, or 1]
+    #[clap(long)]
+    promote_window: Option<usize>,
+    /// Analyze every Nth frame (`N`) or about X frames per second (`Xfps`) instead of all frames.
+    /// Components stay on screen for many frames, so sampling saves most of the computation.
+    /// `--lead-in` and `--promote-window` count sampled frames.
+    #[clap(long)]
+    sample_rate: Option<SampleRate>,
+    /// With `--sample-rate`, also analyze the frames skipped before a sampled frame
+    /// where a component appears or disappears, so that spans start and end at exact frames.
+    #[clap(long, requires = "sample_rate")]
+    rescan: bool,
+    /// Split the frames into this number of contiguous segments analyzed in parallel, each decoded separately.
+    /// Useful for machines with many cores.
+    /// [default: `parallel_segments` in the `[performance]` table of the config file, or 1]
+    #[clap(long, conflicts_with_all = ["output_scores", "preview"])]
+    parallel_segments: Option<usize>,
+    /// Number of input files analyzed concurrently, so that cores left idle at the end of a file are used by the next one.
+    /// Each file has its own progress bar.
+    /// [default: `parallel_files` in the `[performance]` table of the config file, or 2]
+    #[clap(long)]
+    parallel_files: Option<usize>,
+    /// Stop the analysis once a span meets the condition, such as `event:boss_defeated`, writing outputs up to there.
+    /// `component:NAME` is met by a span of the component or group, `text:REGEX` by a span whose text matches,
+    /// and `event:NAME` by an event of the rule defined in `--rules`. Can be specified multiple times to stop at any of them.
+    #[clap(long, conflicts_with = "sweep")]
+    stop_after: Vec<StopCondition>,
+    /// Stop the analysis once this number of spans are found, except intervals of dropped frames
+    #[clap(long, conflicts_with = "sweep")]
+    max_events: Option<usize>,
+    /// Rule file defining the events of `--stop-after event:NAME`
+    #[clap(long)]
+    rules: Option<PathBuf>,
+    /// Analyze the input in real time as a live source read by FFmpeg: a capture device such as `/dev/video0` (Linux),
+    /// `0` (macOS) or `video=OBS Virtual Camera` (Windows), or a stream URL such as `rtmp://...` or `srt://...`.
+    /// Spans are logged and written to the output span file as soon as they end.
+    #[clap(long, conflicts_with_all = ["sweep", "parallel_segments"])]
+    live: bool,
+    /// Input format of the live device, such as `v4l2`, `avfoundation` or `dshow`
+    /// [default: the format of capture devices on this platform, or none for URLs]
+    #[clap(long, requires = "live")]
+    live_format: Option<String>,
+    /// Option of the live input format or protocol (`KEY=VALUE`), such as `video_size=1920x1080` or `framerate=60`.
+    /// Can be specified multiple times.
+    #[clap(long, requires = "live", value_parser = parse_live_option)]
+    live_option: Vec<(String, String)>,
+    /// Stop the live analysis after the given time
+    #[clap(long, default_value = "24:00:00", requires = "live")]
+    live_duration: Timestamp,
+    /// Maximum number of frames in the analysis pipeline of the live source.
+    /// While the pipeline is full, only the latest captured frame waits for room and older ones are dropped.
+    /// [default: `max_pending_frames` in the `[performance]` table of the config file, or 30]
+    #[clap(long, requires = "live")]
+    max_pending_frames: Option<usize>,
+    #[clap(flatten)]
+    thresholds: ThresholdArgs,
+    #[clap(flatten)]
+    igt: IgtArgs,
+    #[clap(flatten)]
+    translate: TranslateArgs,
+    #[clap(flatten)]
+    preview: PreviewArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+    /// Sweep the threshold of a detector area (`DETECTOR.AREA=START:END:STEP`) and report the number of spans for each value.
+    /// Can be specified multiple times to sweep a grid. Output files are not written while sweeping.
+    #[clap(long)]
+    sweep: Vec<SweepParam>,
+    /// Output TSV file of the sweep report
+    #[clap(long, requires = "sweep")]
+    sweep_report: Option<PathBuf>,
+    /// Span file of the expected spans, against which spans found with each value are evaluated.
+    /// The numbers of matched spans, the precision, the recall and the F1 score are added to the report.
+    #[clap(long, requires = "sweep")]
+    sweep_reference: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub(super) struct ThresholdArgs {
+    /// Ratio of the filled lines of the main item frame above which it is found,
+    /// overriding `found_threshold` of `[pipeline.main_item]` in the config file [default: 0.8]
+    #[clap(long)]
+    found_threshold: Option<f32>,
+    /// Ratio of the filled lines of the main item frame above which it is possibly shown and its texts are recognized,
+    /// overriding `possible_threshold` of `[pipeline.main_item]` in the config file [default: 0.2]
+    #[clap(long)]
+    possible_threshold: Option<f32>,
+    /// Minimum share (percentage) of a text not reliably recognized in the confidences of all candidates to be output,
+    /// overriding `min_possible_share` of `[pipeline]` in the config file [default: 10]
+    #[clap(long)]
+    min_possible_share: Option<i32>,
+    /// Threshold of an area of a histogram-based detector (`DETECTOR.AREA=THRESHOLD`), such as `main_item_count.DIGIT_LETTER=0.04`,
+    /// overriding the config file and the profile. Lower thresholds find more in noisy captures with more false detections.
+    /// Can be specified multiple times.
+    #[clap(long)]
+    area_threshold: Vec<AreaThreshold>,
+}
+
+impl ThresholdArgs {
+    pub(super) fn overrides(&self) -> ThresholdOverrides {
+        ThresholdOverrides {
+            found_threshold: self.found_threshold,
+            possible_threshold: self.possible_threshold,
+            min_possible_share: self.min_possible_share,
+            areas: self.area_threshold.clone(),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(super) struct IgtArgs {
+    /// Region of the in-game timer overlay (`WIDTHxHEIGHT+X+Y`).
+    /// If specified, the in-game time at the start of each span is recorded, and written in the `igt` column of `--output-tsv`.
+    /// The region must be within the frame.
+    #[clap(long)]
+    igt_rect: Option<Rect>,
+    /// Format of the in-game timer overlay.
+    /// `H`, `M`, `S` and `f` denote hours, minutes, seconds and fractional digits, and `[...]` is optional.
+    #[clap(long, default_value = "[H:]MM:SS")]
+    igt_format: IgtFormat,
+}
+
+impl IgtArgs {
+    pub(super) fn builder(&self) -> Option<IgtReaderBuilder> {
+        Some(IgtReaderBuilder {
+            rect: self.igt_rect?,
+            format: self.igt_format.clone(),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(super) struct TranslateArgs {
+    /// Translate recognized item names into the language (`en`) in all outputs.
+    /// Translations are appended to the names as `NAME / TRANSLATION`, and names without bundled translations are kept as is.
+    /// [default: `translate_items` in the config file or the profile]
+    #[clap(long)]
+    translate_items: Option<Lang>,
+    /// Replace item names with the translations instead of appending them
+    #[clap(long)]
+    replace_item_names: bool,
+}
+
+impl TranslateArgs {
+    pub(super) fn translation(
+        &self,
+        config: &Config,
+        profile: Option<&Profile>,
+    ) -> Option<ItemTranslation> {
+        Some(ItemTranslation {
+            lang: self
+                .translate_items
+                .or(config.translate_items)
+                .or_else(|| profile?.translate_items)?,
+            replace: self.replace_item_names,
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(super) struct PreviewArgs {
+    /// Display frames being analyzed in a window, with rectangles of components colored by their states
+    /// (green: found, yellow: lead-in, blue: bridged, gray: absent) and their last recognized texts.
+    /// Frames are skipped while the window is drawing, so the analysis is not slowed down.
+    #[clap(long)]
+    preview: bool,
+    /// Font file (TTF or OTF) containing Japanese glyphs, to draw recognized texts under the components in the preview.
+    /// If not specified, the texts are shown in the title of the window.
+    #[clap(long, requires = "preview")]
+    preview_font: Option<PathBuf>,
+}
+
+impl PreviewArgs {
+    pub(super) fn options(&self) -> Option<PreviewOptions<'_>> {
+        if !self.preview {
+            return None;
+        }
+        if !ImageLogger::display_available() {
+            tracing::warn!("{}", ImageLogger::DISPLAY_DISABLED_MESSAGE);
+            return None;
+        }
+        Some(PreviewOptions {
+            font: self.preview_font.as_deref(),
+        })
+    }
 }
 
 impl Args {
@@ -44,137 +335,506 @@ impl Args {
     pub(crate) fn run(&self) -> eyre::Result<()> {
         ImageLogger::init(false)?;
 
-        process_file(
-            &self.input,
-            self.timestamp,
-            self.output_span.as_deref(),
-            self.output_tsv.as_deref(),
-        )?;
+        let mut config = self.config.load()?;
+        let profile = self.config.profile(&config);
+        let thresholds = self.thresholds.overrides();
+        config
+            .apply_overrides(&thresholds)
+            .wrap_err("invalid thresholds")?;
+        if let [input] = self.inputs.as_slice() {
+            if !self.sweep.is_empty() {
+                let mut capture = VideoCapture::open_with(input, &self.open_options())?;
+                sweep::run(
+                    &mut capture,
+                    &config,
+                    &self.sweep,
+                    self.timestamp,
+                    self.sweep_report.as_deref(),
+                    self.sweep_reference.as_deref(),
+                )?;
+                return Ok(());
+            }
+            let summary = self.run_file(input, None, &config, profile)?;
+            print_summary(&summary);
+            return Ok(());
+        }
+
+        if !self.sweep.is_empty()
+            || !self.stop_after.is_empty()
+            || self.max_events.is_some()
+            || self.live
+        {
+            bail!("`--sweep`, `--stop-after`, `--max-events` and `--live` take a single input");
+        }
+        if self.preview.preview {
+            bail!("`--preview` takes a single input");
+        }
+        // Names of videos may not be valid on the file system of the output directories
+        let stems = self
+            .inputs
+            .iter()
+            .map(|path| {
+                let stem = path.file_stem().ok_or_eyre("input is not a file")?;
+                Ok(util::sanitize_file_name(&stem.to_string_lossy()))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        if let Some(stem) = stems
+            .iter()
+            .enumerate()
+            .find_map(|(i, stem)| stems[..i].contains(stem).then_some(stem))
+        {
+            bail!("inputs named `{stem}` would write the same output files");
+        }
+
+        let parallel_files = self
+            .parallel_files
+            .or(config.performance.parallel_files)
+            .unwrap_or(2)
+            .clamp(1, self.inputs.len());
+        let progress = FileProgress::new(Span::current(), self.inputs.len());
+        let queue = Mutex::new(self.inputs.iter().zip(&stems).enumerate());
+        // Summaries are printed after all files so that they are not interleaved
+        let summaries = Mutex::new(vec![None; self.inputs.len()]);
+        let failures = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..parallel_files {
+                let span = Span::current();
+                scope.spawn(|| {
+                    let _span = span.entered();
+                    // Files left in the queue are not started after cancelling
+                    while !crate::cancellation().is_cancelled() {
+                        let Some((i, (input, stem))) = queue.lock().unwrap().next() else {
+                            break;
+                        };
+                        match self.run_file(input, Some(stem), &config, profile) {
+                            Ok(summary) => summaries.lock().unwrap()[i] = Some(summary),
+                            Err(err) => {
+                                tracing::error!(path = %input.display(), "failed to analyze: {err:?}");
+                                failures.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                        progress.finish_file();
+                    }
+                });
+            }
+        });
+
+        for (input, summary) in self.inputs.iter().zip(summaries.into_inner().unwrap()) {
+            if let Some(summary) = summary {
+                println!("{}:", input.display());
+                print_summary(&summary);
+            }
+        }
+        let failures = failures.into_inner();
+        if failures > 0 {
+            bail!("failed to analyze {failures} files");
+        }
         Ok(())
     }
+
+    /// Returns `true` if the index of `input` is written, which requires all spans of the whole video.
+    fn writes_index(&self, input: &Path) -> bool {
+        !self.no_index
+            && !self.live
+            && input.is_file()
+            && !ImageSequenceBackend::is_image_sequence(input)
+            && matches!(self.timestamp, TimestampRange::Full)
+            && self.stop_after.is_empty()
+            && self.max_events.is_none()
+            && self.only_category.is_empty()
+    }
+
+    fn open_options(&self) -> OpenOptions {
+        OpenOptions {
+            backend: self.backend.kind(),
+            hwaccel: self.hwaccel,
+            image_fps: self.image_fps,
+        }
+    }
+
+    /// Analyzes `input`, prefixing the names of output files with `stem` if given.
+    fn run_file(
+        &self,
+        input: &Path,
+        stem: Option<&str>,
+        config: &Config,
+        profile: Option<&'static Profile>,
+    ) -> eyre::Result<Summary> {
+        let output = |path: &Option<PathBuf>| -> Option<PathBuf> {
+            let path = path.as_deref()?;
+            Some(match stem {
+                Some(stem) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    path.with_file_name(format!("{stem}.{name}"))
+                }
+                None => path.to_owned(),
+            })
+        };
+        let output_span = output(&self.output_span);
+        let output_tsv = output(&self.output_tsv);
+        let output_json = output(&self.output_json);
+        let output_pickups = output(&self.output_pickups);
+        let output_buckets = output(&self.output_buckets);
+        let output_srt = output(&self.output_srt);
+        let output_vtt = output(&self.output_vtt);
+        let output_scores = output(&self.output_scores);
+
+        let live = self
+            .live
+            .then(|| -> eyre::Result<_> {
+                let input = input
+                    .to_str()
+                    .ok_or_eyre("live source is not valid UTF-8")?;
+                let mut source = LiveSource::new(input, self.live_format.as_deref());
+                source.options.clone_from(&self.live_option);
+                Ok(source)
+            })
+            .transpose()?;
+        let source = match &live {
+            // Captures of the same source are distinguished by the time they started
+            Some(live) => SourceId::of_name(&format!("{live}@{}", Utc::now().to_rfc3339())),
+            None => SourceId::of_input(input)?,
+        };
+
+        // Reloaded configs of live sources are overridden too
+        let thresholds = self.thresholds.overrides();
+        let rules = self.rules.as_deref().map(Rules::load).transpose()?;
+        let stop = Stopper::new(&self.stop_after, self.max_events, config, rules.as_ref())?;
+        let options = Options {
+            source,
+            timestamp: self.timestamp,
+            output_span: output_span.as_deref(),
+            output_tsv: output_tsv.as_deref(),
+            output_json: output_json.as_deref(),
+            tsv_layout: self.tsv_layout,
+            tsv_uncertain: self.tsv_uncertain,
+            output_pickups: output_pickups.as_deref(),
+            output_buckets: output_buckets.as_deref(),
+            bucket_window: Duration::new(self.bucket_window.as_ratio()),
+            output_srt: output_srt.as_deref(),
+            output_vtt: output_vtt.as_deref(),
+            translate_items: self.translate.translation(config, profile),
+            only_categories: self.only_category.clone(),
+            environment: self
+                .record_environment
+                .then(|| Environment::capture(config.ocr_lang())),
+            output_scores: output_scores.as_deref(),
+            exact_pts: self.exact_pts,
+            lead_in: self.lead_in.or_else(|| profile?.lead_in).unwrap_or(0),
+            promote_window: self
+                .promote_window
+                .or_else(|| profile?.promote_window)
+                .unwrap_or(1),
+            sample_rate: self.sample_rate,
+            rescan: self.rescan,
+            igt: self.igt.builder(),
+            detection_only: false,
+            live: live.is_some().then(|| LiveOptions {
+                max_pending_frames: self
+                    .max_pending_frames
+                    .or(config.performance.max_pending_frames)
+                    .unwrap_or(DEFAULT_MAX_PENDING_FRAMES),
+                config_path: self.config.path(),
+                profile,
+                item_list: self.config.item_list(),
+                thresholds: Some(&thresholds),
+                event_log: None,
+            }),
+            preview: self.preview.options(),
+            stop: (!stop.is_empty()).then_some(stop),
+            index: self.writes_index(input).then_some(input),
+            emit_events: self.emit_events.as_ref(),
+        };
+        if let Some(live) = &live {
+            let duration = Duration::new(self.live_duration.as_ratio());
+            let mut capture = VideoCapture::open_live(live, duration)?;
+            tracing::info!(source = %live, "start analyzing live source");
+            return process_capture(&mut capture, config, options);
+        }
+        // Detection scores and previews are not supported with segments
+        let default_segments = match (&self.output_scores, &options.preview) {
+            (None, None) => config.performance.parallel_segments,
+            _ => None,
+        };
+        let parallel_segments = self.parallel_segments.or(default_segments).unwrap_or(1);
+        if parallel_segments > 1 {
+            segment::run(
+                input,
+                &self.open_options(),
+                config,
+                options,
+                parallel_segments,
+            )
+        } else {
+            process_file(input, &self.open_options(), config, options)
+        }
+    }
 }
 
 #[tracing::instrument(name = "file", skip_all, fields(path = %file.file_name().unwrap_or_default().to_string_lossy()))]
-fn process_file(
+pub(super) fn process_file(
     file: &Path,
-    timestamp: TimestampRange,
-    output_span: Option<&Path>,
-    output_tsv: Option<&Path>,
-) -> eyre::Result<()> {
-    let mut capture = VideoCapture::open(file)?;
-    let mut decoder = capture.range_decoder(timestamp)?;
-    let base_rect = decoder.capture().rect();
+    open: &OpenOptions,
+    config: &Config,
+    options: Options<'_>,
+) -> eyre::Result<Summary> {
+    // The capture reports its own errors, so failures of probing are only logged.
+    // Images are not probed, since FFmpeg reports the frame rate of its own image reader for them.
+    if !ImageSequenceBackend::is_image_sequence(file) {
+        match metadata::probe(&file) {
+            Ok(metadata) => {
+                if let Some(stream) = metadata.video_stream() {
+                    let fps = stream.fps().map(|fps| fps.to_string());
+                    let color_range = stream.video().map(|video| video.color.range);
+                    tracing::info!(?fps, ?color_range, duration = ?metadata.duration.map(|d| d.to_string()), "probed");
+                }
+            }
+            Err(err) => tracing::debug!(%err, "failed to probe"),
+        }
+    }
+    let mut capture = VideoCapture::open_with(file, open)?;
+    process_capture(&mut capture, config, options)
+}
+
+/// Parses an option of live sources given as `KEY=VALUE`.
+fn parse_live_option(s: &str) -> eyre::Result<(String, String)> {
+    let (key, value) = s.split_once('=').ok_or_eyre("expected `KEY=VALUE`")?;
+    Ok((key.to_owned(), value.to_owned()))
+}
 
-    let tess = LinearObjectPool::new(
-        move || LazyLock::new(move || Mutex::new(Tesseract::new(None, Some("jpn")).unwrap())),
-        |_v| {},
-    );
+/// Options of the analysis pipeline.
+#[derive(Debug)]
+pub(super) struct Options<'a> {
+    /// Source of frames, used to identify events
+    pub(super) source: SourceId,
+    pub(super) timestamp: TimestampRange,
+    pub(super) output_span: Option<&'a Path>,
+    pub(super) output_tsv: Option<&'a Path>,
+    pub(super) output_json: Option<&'a Path>,
+    pub(super) tsv_layout: Layout,
+    pub(super) tsv_uncertain: UncertainCells,
+    pub(super) output_pickups: Option<&'a Path>,
+    /// Output CSV file of counts of events in windows of `bucket_window`
+    pub(super) output_buckets: Option<&'a Path>,
+    pub(super) bucket_window: Duration,
+    pub(super) output_srt: Option<&'a Path>,
+    pub(super) output_vtt: Option<&'a Path>,
+    pub(super) translate_items: Option<ItemTranslation>,
+    /// Categories of items whose spans are output, or empty to output all spans
+    pub(super) only_categories: Vec<ItemCategory>,
+    /// Environment recorded in the header of output files
+    pub(super) environment: Option<Environment>,
+    pub(super) output_scores: Option<&'a Path>,
+    /// Use the PTS of frames as their timestamps
+    pub(super) exact_pts: bool,
+    /// Number of frames before each span to recognize texts in
+    pub(super) lead_in: usize,
+    /// Number of frames after a found frame within which possible frames are regarded as found
+    pub(super) promote_window: usize,
+    /// Rate of frames analyzed, or all frames if `None`
+    pub(super) sample_rate: Option<SampleRate>,
+    /// Analyze skipped frames where detections of sampled frames change
+    pub(super) rescan: bool,
+    pub(super) igt: Option<IgtReaderBuilder>,
+    /// Skip text recognition and only detect components
+    pub(super) detection_only: bool,
+    pub(super) live: Option<LiveOptions<'a>>,
+    pub(super) preview: Option<PreviewOptions<'a>>,
+    /// Conditions stopping the analysis once a span meets one of them
+    pub(super) stop: Option<Stopper>,
+    /// Video whose index is written next to it if the whole video is analyzed
+    pub(super) index: Option<&'a Path>,
+    /// Receiver of spans sent as soon as they are finalized
+    pub(super) emit_events: Option<&'a event_stream::Target>,
+}
+
+/// Options for analyzing live sources.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct LiveOptions<'a> {
+    /// Frames are dropped when more than this number of frames are in the pipeline.
+    /// This keeps the analysis real-time.
+    pub(super) max_pending_frames: usize,
+    /// Config file reloaded when it is modified
+    pub(super) config_path: Option<&'a Path>,
+    /// Profile applied to the reloaded config
+    pub(super) profile: Option<&'static Profile>,
+    /// Item list overriding the one of the reloaded config
+    pub(super) item_list: Option<&'a Path>,
+    /// Thresholds overriding the reloaded config
+    pub(super) thresholds: Option<&'a ThresholdOverrides>,
+    /// Event log to append spans to, and its limits
+    pub(super) event_log: Option<(&'a Path, Rotation)>,
+}
 
-    let output_span = output_span.map(File::create).transpose()?;
-    let output_tsv = output_tsv.map(File::create).transpose()?;
+/// Options of the window displaying frames being analyzed.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PreviewOptions<'a> {
+    /// Font to draw recognized texts with
+    pub(super) font: Option<&'a Path>,
+}
+
+/// Runs the analysis pipeline on `capture`.
+pub(super) fn process_capture(
+    capture: &mut VideoCapture,
+    config: &Config,
+    options: Options<'_>,
+) -> eyre::Result<Summary> {
+    let Options {
+        source,
+        timestamp,
+        output_span,
+        output_tsv,
+        output_json,
+        tsv_layout,
+        tsv_uncertain,
+        output_pickups,
+        output_buckets,
+        bucket_window,
+        output_srt,
+        output_vtt,
+        translate_items,
+        only_categories,
+        environment,
+        output_scores,
+        exact_pts,
+        lead_in,
+        promote_window,
+        sample_rate,
+        rescan,
+        igt,
+        detection_only,
+        live,
+        preview,
+        stop,
+        index,
+        emit_events,
+    } = options;
+
+    capture.set_exact_pts(exact_pts);
+    let mut decoder = capture.range_decoder(timestamp)?;
+    let base_rect = decoder.capture().rect();
+    let step = sample_rate.map_or(1, |rate| rate.step(decoder.capture().fps()));
 
-    let components = Arc::new(Components::new(base_rect).ok_or_eyre("invalid frame size")?);
+    let stages = Stages::new(
+        base_rect,
+        config,
+        igt,
+        detection_only,
+        lead_in,
+        promote_window,
+    )?;
+    let sinks = text_accum::Sinks {
+        source,
+        output_span: output_span.map(util::create_file).transpose()?,
+        output_tsv: output_tsv.map(util::create_file).transpose()?,
+        output_json: output_json.map(util::create_file).transpose()?,
+        tsv_layout,
+        tsv_igt: stages.reads_igt(),
+        tsv_uncertain,
+        output_pickups: output_pickups.map(util::create_file).transpose()?,
+        output_buckets: output_buckets.map(util::create_file).transpose()?,
+        bucket_window,
+        output_srt: output_srt.map(util::create_file).transpose()?,
+        output_vtt: output_vtt.map(util::create_file).transpose()?,
+        translate_items,
+        only_categories,
+        environment,
+        event_log: live
+            .and_then(|live| live.event_log)
+            .map(|(path, rotation)| EventLog::open(path, rotation))
+            .transpose()?,
+        event_stream: emit_events
+            .map(|target| EventStream::connect(target.clone()))
+            .transpose()?,
+        stop,
+        index: index.map(Path::to_owned),
+    };
+    let score_writer = output_scores
+        .map(util::create_file)
+        .transpose()?
+        .map(|output| ScoreWriter::new(output, stages.names.clone()));
+    let throttle = live.map(|live| Arc::new(Throttle::new(live.max_pending_frames)));
+    let _config_watcher = live
+        .and_then(|live| Some((live.config_path?, live)))
+        .map(|(path, live)| {
+            ConfigWatcher::spawn(
+                path,
+                live.profile,
+                live.thresholds.cloned().unwrap_or_default(),
+                &stages.ocr_lang,
+                live.item_list,
+                base_rect,
+                Arc::clone(&stages.components),
+            )
+        })
+        .transpose()?;
 
     let start = decoder.start();
     let end = decoder.end();
     let fps = decoder.capture().fps();
-    let sec_per_frame = decoder.capture().sec_per_frame();
 
     let pbar_builder = ProgressBarBuilder::new(start, end, fps);
     let pbar = pbar_builder.build(Span::current());
 
-    let (cap_tx, cap_rx) = mpsc::channel();
-    let (comp_detect_tx, comp_detect_rx) = mpsc::channel();
-    let (comp_accum_tx, comp_accum_rx) = mpsc::channel();
-    let (text_recognize_tx, text_recognize_rx) = mpsc::channel();
+    let (preview, preview_thread) = preview.map(Preview::spawn).transpose()?.unzip();
+    let observer = preview.map(|preview| Arc::new(preview) as Arc<dyn FrameObserver>);
 
-    let comp_detect_thread = tracing::info_span!("comp_tedect").in_scope(|| {
-        let components = Arc::clone(&components);
-        spawn_streaming_thread(cap_rx, comp_detect_tx, "comp_detect", move |packet| {
-            comp_detect::run(&components, packet)
-        })
-    });
+    let (text_recognize_tx, text_recognize_rx) = mpsc::channel();
+    let (cap_tx, front) = stages.spawn_front(score_writer, None, observer, text_recognize_tx)?;
 
-    let comp_accum_thread = spawn_accumulate_thread("comp_accum", move || {
-        comp_accum::run(comp_detect_rx, comp_accum_tx)
+    let text_accum_thread = spawn_accumulate_thread("text_accum", {
+        let names = stages.names.clone();
+        let throttle = throttle.clone();
+        let min_possible_share = stages.pipeline.min_possible_share;
+        move || {
+            text_accum::run(
+                names,
+                text_recognize_rx,
+                start,
+                step,
+                min_possible_share,
+                sinks,
+                throttle,
+            )
+        }
     })?;
 
-    let text_recognize_thread = tracing::info_span!("text_recognize").in_scope(|| {
-        let components = Arc::clone(&components);
-        spawn_streaming_thread(
-            comp_accum_rx,
-            text_recognize_tx,
-            "text_recognize",
-            move |packet| text_recognize::run(&components, &tess, packet),
-        )
-    });
-
-    let text_accum_thread = spawn_accumulate_thread("text_accum", move || {
-        text_accum::run(
-            text_recognize_rx,
-            start,
-            sec_per_frame,
-            output_span,
-            output_tsv,
-        )
-    })?;
+    tracing::info!(%start, %end, %fps, step, "capture start");
 
-    tracing::info!(%start, %end, %fps, "capture start");
+    let detect = rescan.then(|| stages.detect_fn());
+    decode::run(
+        &|pos| pbar.set_position(pos),
+        cap_tx,
+        &mut decoder,
+        step,
+        detect.as_ref().map(|detect| detect as &decode::DetectFn),
+        throttle.as_deref(),
+        Some(crate::cancellation()),
+    )?;
 
-    decode::run(&pbar, cap_tx, &mut decoder)?;
+    front.join()?;
+    let num_spans = text_accum_thread.join().unwrap()?;
+    if let Some(thread) = preview_thread {
+        thread.join().unwrap();
+    }
 
-    comp_detect_thread.join().unwrap()?;
-    comp_accum_thread.join().unwrap()?;
-    text_recognize_thread.join().unwrap()?;
-    text_accum_thread.join().unwrap()?;
+    if let Some(throttle) = &throttle {
+        throttle.report();
+    }
+    stages.report_cascade();
 
     tracing::info!("completed");
-
-    Ok(())
-}
-
-fn spawn_streaming_thread<Input, Output, F>(
-    rx: mpsc::Receiver<(usize, Input)>,
-    tx: mpsc::Sender<(usize, Output)>,
-    name: &'static str,
-    f: F,
-) -> JoinHandle<eyre::Result<()>>
-where
-    Input: Send + Sync + 'static,
-    Output: Send + Sync + 'static,
-    F: Fn(Input) -> eyre::Result<Output> + Send + Sync + 'static,
-{
-    let root_span = Span::current();
-    thread::spawn(move || -> eyre::Result<()> {
-        let _span = root_span.clone().entered();
-        ThreadPoolBuilder::default()
-            .thread_name(move |n| format!("{name}#{n}"))
-            .build()?
-            .install(move || -> eyre::Result<()> {
-                rx.into_iter().par_bridge().try_for_each(
-                    move |(i, packet)| -> eyre::Result<_> {
-                        let _span = root_span.enter();
-                        let packet = f(packet)?;
-                        tx.send((i, packet))?;
-                        Ok(())
-                    },
-                )?;
-                Ok(())
-            })?;
-        Ok(())
+    Ok(Summary {
+        names: stages.names,
+        num_spans,
     })
 }
 
-fn spawn_accumulate_thread<F>(name: &str, f: F) -> eyre::Result<JoinHandle<eyre::Result<()>>>
-where
-    F: FnOnce() -> eyre::Result<()> + Send + 'static,
-{
-    let root_span = Span::current();
-    let handler = thread::Builder::new()
-        .name(name.into())
-        .spawn(move || -> eyre::Result<()> {
-            let _span = root_span.enter();
-            f()
-        })?;
-    Ok(handler)
+/// Prints the number of spans found for each component in the language of the UI.
+pub(super) fn print_summary(summary: &Summary) {
+    const HEADER: Message = Message::new("spans found:", "検出されたスパン:");
+    println!("{HEADER}");
+    for (name, n) in summary.iter() {
+        println!("  {}: {n}", components::display_name(name));
+    }
 }
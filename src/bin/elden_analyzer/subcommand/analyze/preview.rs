@@ -0,0 +1,243 @@
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use ab_glyph::FontVec;
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use elden_analyzer::{
+    accumulate::text::FrameTexts,
+    analyzer::FrameObserver,
+    components::{ComponentContainer, Components, ExtractedTexts},
+    image_process::overlay,
+    operator::Recognition,
+    video_capture::FrameExt as _,
+};
+use elden_analyzer_kernel::types::{rect::Rect, time::FramePosition};
+use elden_analyzer_video::capture::Frame;
+use imageproc::image::{buffer::ConvertBuffer as _, Rgb, RgbImage};
+use sdl2::{event::Event, pixels::PixelFormatEnum};
+
+use super::PreviewOptions;
+
+/// Initial size of the window, which is smaller than full HD frames to fit in screens
+const MAX_WINDOW_SIZE: (u32, u32) = (1280, 720);
+
+/// Interval to handle events of the window while no frames are sent
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Height of texts drawn in the frame
+const TEXT_SCALE: f32 = 28.0;
+
+/// Window displaying frames being analyzed, drawn in a separate thread.
+#[derive(Debug)]
+pub(super) struct Preview {
+    tx: mpsc::SyncSender<Update>,
+    /// `true` while the window is drawing a frame, or after it is closed
+    busy: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct Update {
+    pos: FramePosition,
+    frame: Frame,
+    components: Vec<ComponentState>,
+}
+
+#[derive(Debug)]
+struct ComponentState {
+    name: String,
+    rect: Rect,
+    color: Rgb<u8>,
+    /// Texts recognized in the frame, or `None` if not recognized
+    text: Option<String>,
+}
+
+impl Preview {
+    pub(super) fn spawn(options: PreviewOptions<'_>) -> eyre::Result<(Self, JoinHandle<()>)> {
+        let font = options
+            .font
+            .map(|path| -> eyre::Result<_> {
+                let data = fs::read(path)
+                    .wrap_err_with(|| format!("failed to read font: {}", path.display()))?;
+                FontVec::try_from_vec(data)
+                    .wrap_err_with(|| format!("invalid font: {}", path.display()))
+            })
+            .transpose()?;
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        let busy = Arc::new(AtomicBool::new(false));
+        let thread = thread::Builder::new().name("preview".into()).spawn({
+            let busy = Arc::clone(&busy);
+            move || {
+                if let Err(err) = run_window(&rx, &busy, font.as_ref()) {
+                    tracing::warn!("preview is closed: {err:#}");
+                }
+                // Frames are no longer sent after the window is closed
+                busy.store(true, Ordering::Relaxed);
+            }
+        })?;
+        Ok((Self { tx, busy }, thread))
+    }
+}
+
+impl FrameObserver for Preview {
+    /// Sends the frame to the window if it is not busy, otherwise drops it.
+    fn observe(
+        &self,
+        pos: FramePosition,
+        frame: Frame,
+        components: &Components,
+        texts: &ComponentContainer<FrameTexts>,
+    ) {
+        if self.busy.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let components = components
+            .iter()
+            .zip(texts)
+            .map(|(component, texts)| {
+                let (color, text) = match texts {
+                    FrameTexts::Found(texts) => (Rgb([0, 255, 0]), Some(join_texts(texts))),
+                    FrameTexts::LeadIn(texts) => (Rgb([255, 255, 0]), Some(join_texts(texts))),
+                    FrameTexts::Bridged => (Rgb([0, 128, 255]), None),
+                    FrameTexts::Absent => (Rgb([128, 128, 128]), None),
+                };
+                ComponentState {
+                    name: component.name().to_owned(),
+                    rect: component.rect(),
+                    color,
+                    text: text.filter(|text| !text.is_empty()),
+                }
+            })
+            .collect();
+        let _ = self.tx.send(Update {
+            pos,
+            frame,
+            components,
+        });
+    }
+}
+
+fn run_window(
+    rx: &mpsc::Receiver<Update>,
+    busy: &AtomicBool,
+    font: Option<&FontVec>,
+) -> eyre::Result<()> {
+    let sdl = sdl2::init().map_err(|e| eyre!(e))?;
+    let video = sdl.video().map_err(|e| eyre!(e))?;
+    let mut events = sdl.event_pump().map_err(|e| eyre!(e))?;
+
+    let Ok(first) = rx.recv() else {
+        return Ok(());
+    };
+    let (width, height) = (first.frame.width(), first.frame.height());
+    let window = video
+        .window(
+            "preview",
+            width.min(MAX_WINDOW_SIZE.0),
+            height.min(MAX_WINDOW_SIZE.1),
+        )
+        .position_centered()
+        .resizable()
+        .build()?;
+    let mut canvas = window.into_canvas().build()?;
+    let texture_creator = canvas.texture_creator();
+    let mut texture =
+        texture_creator.create_texture_streaming(PixelFormatEnum::RGB24, width, height)?;
+
+    // Texts are kept until the component is recognized again, so that they can be read
+    let mut last_texts = Vec::<Option<String>>::new();
+    let mut update = Some(first);
+    loop {
+        for event in events.poll_iter() {
+            if let Event::Quit { .. } = event {
+                return Ok(());
+            }
+        }
+
+        let Some(Update {
+            pos,
+            frame,
+            components,
+        }) = update.take()
+        else {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(next) => update = Some(next),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            continue;
+        };
+
+        last_texts.resize(components.len(), None);
+        for (last, component) in last_texts.iter_mut().zip(&components) {
+            if let Some(text) = &component.text {
+                *last = Some(text.clone());
+            }
+        }
+
+        let mut image: RgbImage = frame.to_rgb_image()?.convert();
+        drop(frame);
+        for (component, text) in components.iter().zip(&last_texts) {
+            draw_component(&mut image, component, text.as_deref(), font);
+        }
+
+        let mut title = format!("preview [{pos}]");
+        if font.is_none() {
+            for (component, text) in components.iter().zip(&last_texts) {
+                if let Some(text) = text {
+                    title += &format!(" | {}: {text}", component.name);
+                }
+            }
+        }
+        canvas.window_mut().set_title(&title)?;
+        if (image.width(), image.height()) == (width, height) {
+            texture.update(None, image.as_raw(), width as usize * 3)?;
+        }
+        canvas.copy(&texture, None, None).map_err(|e| eyre!(e))?;
+        canvas.present();
+        busy.store(false, Ordering::Relaxed);
+    }
+}
+
+fn draw_component(
+    image: &mut RgbImage,
+    component: &ComponentState,
+    text: Option<&str>,
+    font: Option<&FontVec>,
+) {
+    match (text, font) {
+        (Some(text), Some(font)) => {
+            overlay::draw_labeled_rect(
+                image,
+                component.rect,
+                component.color,
+                text,
+                font,
+                TEXT_SCALE,
+            );
+        }
+        _ => overlay::draw_rect(image, component.rect, component.color, 2),
+    }
+}
+
+/// Joins texts of a component with spaces, marking ones not reliably recognized with `??`.
+fn join_texts(texts: &ExtractedTexts) -> String {
+    texts
+        .result
+        .iter()
+        .map(|res| match res {
+            Recognition::Found(text, _) => text.clone(),
+            Recognition::Possible(text, _) => format!("??{text}"),
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
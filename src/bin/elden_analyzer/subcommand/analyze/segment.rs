@@ -0,0 +1,226 @@
+use std::{path::Path, sync::mpsc, thread};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    analyzer::{
+        comp_accum::Segment, decode, spawn_accumulate_thread, text_recognize, Stages, Summary,
+    },
+    config::Config,
+    io::event_stream::EventStream,
+    util,
+};
+use elden_analyzer_collections::seq_iter::SeqIter;
+use elden_analyzer_kernel::types::time::{Timestamp, TimestampRange};
+use elden_analyzer_video::capture::{OpenOptions, VideoCapture};
+
+use crate::tui::ProgressBarBuilder;
+
+use super::{text_accum, Options};
+
+/// Runs the analysis pipeline on `num_segments` contiguous segments of the range in parallel.
+///
+/// Each segment is decoded with its own capture, and texts recognized in all segments are accumulated in order,
+/// so spans crossing the boundaries of segments are output as in a single pipeline.
+#[tracing::instrument(name = "segments", skip_all)]
+pub(super) fn run(
+    file: &Path,
+    open: &OpenOptions,
+    config: &Config,
+    options: Options<'_>,
+    num_segments: usize,
+) -> eyre::Result<Summary> {
+    let Options {
+        source,
+        timestamp,
+        output_span,
+        output_tsv,
+        output_json,
+        tsv_layout,
+        tsv_uncertain,
+        output_pickups,
+        output_buckets,
+        bucket_window,
+        output_srt,
+        output_vtt,
+        translate_items,
+        only_categories,
+        environment,
+        output_scores,
+        exact_pts,
+        lead_in,
+        promote_window,
+        sample_rate,
+        rescan,
+        igt,
+        detection_only,
+        live,
+        preview,
+        stop,
+        index,
+        emit_events,
+    } = options;
+    if output_scores.is_some() || live.is_some() || preview.is_some() {
+        eyre::bail!("detection scores, live sources and previews are not supported with segments");
+    }
+
+    let mut capture = VideoCapture::open_with(
+        file,
+        &OpenOptions {
+            hwaccel: None,
+            ..*open
+        },
+    )?;
+    let (start, end) = {
+        let decoder = capture.range_decoder(timestamp)?;
+        (decoder.start(), decoder.end())
+    };
+    let base_rect = capture.rect();
+    let fps = capture.fps();
+    let sec_per_frame = capture.sec_per_frame();
+    let step = sample_rate.map_or(1, |rate| rate.step(fps));
+    drop(capture);
+
+    let stages = Stages::new(
+        base_rect,
+        config,
+        igt,
+        detection_only,
+        lead_in,
+        promote_window,
+    )?;
+    let sinks = text_accum::Sinks {
+        source,
+        output_span: output_span.map(util::create_file).transpose()?,
+        output_tsv: output_tsv.map(util::create_file).transpose()?,
+        output_json: output_json.map(util::create_file).transpose()?,
+        tsv_layout,
+        tsv_igt: stages.reads_igt(),
+        tsv_uncertain,
+        output_pickups: output_pickups.map(util::create_file).transpose()?,
+        output_buckets: output_buckets.map(util::create_file).transpose()?,
+        bucket_window,
+        output_srt: output_srt.map(util::create_file).transpose()?,
+        output_vtt: output_vtt.map(util::create_file).transpose()?,
+        translate_items,
+        only_categories,
+        environment,
+        // Segments are analyzed only for video files
+        event_log: None,
+        event_stream: emit_events
+            .map(|target| EventStream::connect(target.clone()))
+            .transpose()?,
+        stop,
+        index: index.map(Path::to_owned),
+    };
+
+    let (first, last) = (start.index(), end.index().max(start.index()));
+    let num_segments = num_segments.clamp(1, (last - first).max(1));
+    let margin = Segment::margin(config.pipeline.expire_frames, lead_in, promote_window, step);
+    let segments = (0..num_segments)
+        .map(|k| {
+            let frames = first + (last - first) * k / num_segments
+                ..first + (last - first) * (k + 1) / num_segments;
+            Segment {
+                frames,
+                last: k + 1 == num_segments,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (text_recognize_tx, text_recognize_rx) = mpsc::channel();
+    let text_accum_thread = spawn_accumulate_thread("text_accum", {
+        let names = stages.names.clone();
+        let min_possible_share = config.pipeline.min_possible_share;
+        move || {
+            text_accum::run(
+                names,
+                text_recognize_rx,
+                start,
+                step,
+                min_possible_share,
+                sinks,
+                None,
+            )
+        }
+    })?;
+
+    tracing::info!(%start, %end, %fps, step, num_segments, "capture start");
+
+    let cancel = crate::cancellation();
+    let result = thread::scope(|scope| -> eyre::Result<_> {
+        let mut handles = vec![];
+        let mut segment_rxs = vec![];
+        for (k, segment) in segments.into_iter().enumerate() {
+            let decode_frames = segment.frames.start.saturating_sub(margin).max(first)
+                ..(segment.frames.end + margin).min(last);
+            let to_timestamp = |idx: usize| Timestamp::new(sec_per_frame.as_ratio() * idx as i64);
+            let range = TimestampRange::Range(
+                to_timestamp(decode_frames.start),
+                to_timestamp(decode_frames.end),
+            );
+
+            let (tx, rx) = mpsc::channel();
+            let (cap_tx, front) = stages.spawn_front(None, Some(segment), None, tx)?;
+            segment_rxs.push(rx);
+
+            let detect = rescan.then(|| stages.detect_fn());
+            let span = tracing::info_span!("segment", k);
+            handles.push(scope.spawn(move || -> eyre::Result<()> {
+                let _span = span.clone().entered();
+                let mut capture = VideoCapture::open_with(file, open)?;
+                capture.set_exact_pts(exact_pts);
+                let mut decoder = capture.range_decoder(range)?;
+                let pbar = ProgressBarBuilder::new(decoder.start(), decoder.end(), fps).build(span);
+                let detect = detect.as_ref().map(|detect| detect as &decode::DetectFn);
+                decode::run(
+                    &|pos| pbar.set_position(pos),
+                    cap_tx,
+                    &mut decoder,
+                    step,
+                    detect,
+                    None,
+                    Some(cancel),
+                )?;
+                front.join()
+            }));
+        }
+
+        // Forward recognized texts in order while segments are analyzed.
+        // If cancelled, texts are forwarded up to the end of a segment so that the timeline has no gaps.
+        let mut i = 0;
+        let mut next_pos = start;
+        let mut ended = false;
+        for rx in segment_rxs {
+            if ended {
+                rx.into_iter().for_each(drop);
+                continue;
+            }
+            for (_j, packet) in SeqIter::new(rx) {
+                ended = matches!(packet, text_recognize::Packet::EndOfFrames { .. });
+                next_pos = packet.position().next(sec_per_frame);
+                text_recognize_tx.send((i, packet))?;
+                i += 1;
+            }
+            if !ended && cancel.is_cancelled() {
+                let packet = text_recognize::Packet::EndOfFrames { pos: next_pos };
+                text_recognize_tx.send((i, packet))?;
+                ended = true;
+            }
+        }
+        drop(text_recognize_tx);
+
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        Ok(())
+    });
+    result?;
+
+    let num_spans = text_accum_thread.join().unwrap()?;
+    stages.report_cascade();
+    tracing::info!("completed");
+    Ok(Summary {
+        names: stages.names,
+        num_spans,
+    })
+}
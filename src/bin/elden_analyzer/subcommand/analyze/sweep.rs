@@ -0,0 +1,485 @@
+use std::{collections::VecDeque, fmt, io::Write as _, iter, path::Path, str::FromStr};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer::{
+    accumulate::{
+        detection::{AccumDetection, DetectionAccumulator},
+        text::FrameTexts,
+        AccumulateSpans,
+    },
+    analyzer::{comp_detect, text_accum},
+    components::{CascadeStats, ComponentContainer, Components, ExtractedTexts},
+    config::Config,
+    io::spans::{self, Span},
+    operator::HistogramBasedComponentDetector,
+    util,
+};
+use elden_analyzer_kernel::types::{
+    rect::Rect,
+    time::{FramePosition, TimestampRange},
+};
+use elden_analyzer_video::capture::{Frame, VideoCapture};
+
+use crate::tui::ProgressBarBuilder;
+
+/// Frames before a span recognized as lead-in, none as texts are not recognized
+const LEAD_IN: usize = 0;
+/// Frames within which possible frames continue a span, same as the default of `analyze`
+const PROMOTE_WINDOW: usize = 1;
+
+/// Threshold of a detector area swept over a range of values.
+#[derive(Debug, Clone)]
+pub(super) struct SweepParam {
+    detector: String,
+    area: String,
+    start: f64,
+    end: f64,
+    step: f64,
+    /// Number of fractional digits used to display values
+    precision: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(super) enum SweepParamParseError {
+    #[error("expected `DETECTOR.AREA=START:END:STEP`")]
+    Syntax,
+    #[error("invalid number: {0}")]
+    Number(#[from] std::num::ParseFloatError),
+    #[error("step must be positive and start must not be greater than end")]
+    Range,
+}
+
+impl FromStr for SweepParam {
+    type Err = SweepParamParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, range) = s.split_once('=').ok_or(SweepParamParseError::Syntax)?;
+        let (detector, area) = name.split_once('.').ok_or(SweepParamParseError::Syntax)?;
+        let [start, end, step] = range
+            .split(':')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| SweepParamParseError::Syntax)?;
+
+        let precision = [start, end, step]
+            .iter()
+            .map(|s| s.split_once('.').map_or(0, |(_, frac)| frac.len()))
+            .max()
+            .unwrap();
+        let (start, end, step) = (start.parse()?, end.parse()?, step.parse()?);
+        if !(step > 0.0 && start <= end) {
+            return Err(SweepParamParseError::Range);
+        }
+
+        Ok(Self {
+            detector: detector.to_owned(),
+            area: area.to_owned(),
+            start,
+            end,
+            step,
+            precision,
+        })
+    }
+}
+
+impl SweepParam {
+    fn name(&self) -> String {
+        format!("{}.{}", self.detector, self.area)
+    }
+
+    fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        // Allow a small error so that `end` is included
+        let count = ((self.end - self.start) / self.step + 1e-9).floor() as usize + 1;
+        (0..count).map(|i| self.start + self.step * i as f64)
+    }
+
+    fn format_value(&self, value: f64) -> String {
+        format!("{value:.prec$}", prec = self.precision)
+    }
+
+    fn apply(&self, config: &mut Config, value: f64) -> eyre::Result<()> {
+        config.set_area_threshold(&self.detector, &self.area, value as f32)
+    }
+}
+
+/// Detection with the thresholds of a point of the grid, settled into spans as the pipeline of `analyze` does.
+struct GridPoint {
+    /// Formatted values of the parameters
+    values: Vec<String>,
+    components: Components,
+    stats: ComponentContainer<CascadeStats>,
+    detection: ComponentContainer<DetectionAccumulator>,
+    spans: ComponentContainer<Box<dyn AccumulateSpans>>,
+    /// Frames received but not settled by the detection accumulators yet
+    pending: VecDeque<FramePosition>,
+    found: Vec<Span>,
+}
+
+impl GridPoint {
+    fn new(base_rect: Rect, config: &Config, values: Vec<String>) -> eyre::Result<Self> {
+        let components = Components::new(base_rect, config).ok_or_eyre("invalid frame size")?;
+        let names = components.each_ref().map(|c| c.name().to_owned());
+        let stats = components.each_ref().map(|_| CascadeStats::default());
+        let detection = names.clone().map(|name| {
+            DetectionAccumulator::new(name, LEAD_IN, PROMOTE_WINDOW, config.pipeline.expire_frames)
+        });
+        let spans = text_accum::accumulators(names, None, config.pipeline.min_possible_share);
+        Ok(Self {
+            values,
+            components,
+            stats,
+            detection,
+            spans,
+            pending: VecDeque::new(),
+            found: vec![],
+        })
+    }
+
+    fn receive_frame(&mut self, frame: &Frame) -> eyre::Result<()> {
+        let pos = frame.position();
+        let result = comp_detect::detect_all(&self.components, &self.stats, frame)?;
+        self.pending.push_back(pos);
+        for (accum, result) in iter::zip(&mut self.detection, *result) {
+            accum.receive_frame(pos, result);
+        }
+        self.flush();
+        Ok(())
+    }
+
+    fn receive_end_of_frames(&mut self, pos: FramePosition) {
+        for accum in &mut self.detection {
+            accum.receive_end_of_frames(pos);
+        }
+        self.flush();
+        for accum in &mut self.spans {
+            if let Some(result) = accum.receive_end_of_frames(pos) {
+                self.found.push(result.into_span(None));
+            }
+        }
+    }
+
+    /// Passes settled frames to the span accumulators, as texts are passed without recognizing them.
+    fn flush(&mut self) {
+        while let Some(pos) = self.pending.front() {
+            if !self.detection.iter().all(|accum| accum.handled(*pos)) {
+                break;
+            }
+            let pos = self.pending.pop_front().unwrap();
+            for (detection, spans) in iter::zip(&mut self.detection, &mut self.spans) {
+                let (_, detection) = detection.pop_packet().unwrap();
+                let texts = match detection {
+                    AccumDetection::Found(_) => FrameTexts::Found(ExtractedTexts::default()),
+                    AccumDetection::Bridged => FrameTexts::Bridged,
+                    AccumDetection::LeadIn | AccumDetection::Absent => FrameTexts::Absent,
+                };
+                if let Some(result) = spans.receive_frame(pos, texts, None) {
+                    self.found.push(result.into_span(None));
+                }
+            }
+        }
+    }
+
+    /// Returns the number of spans found for each component.
+    fn num_spans(&self) -> ComponentContainer<usize> {
+        self.spans.each_ref().map(|accum| accum.num_spans())
+    }
+}
+
+/// Runs the detection for every combination of the parameter values,
+/// and reports the number of spans found for each component.
+///
+/// Frames are decoded once and detected with the thresholds of every combination,
+/// where the found ratios of areas are measured once for each frame and shared by the combinations.
+/// Text recognition is skipped because spans are determined only by the detection.
+/// If `reference` is given, the spans are also evaluated against the spans in the file.
+pub(super) fn run(
+    capture: &mut VideoCapture,
+    config: &Config,
+    params: &[SweepParam],
+    timestamp: TimestampRange,
+    report: Option<&Path>,
+    reference: Option<&Path>,
+) -> eyre::Result<()> {
+    let reference = reference
+        .map(|path| -> eyre::Result<_> {
+            let spans = spans::read_file(path)?;
+            Ok(spans
+                .into_iter()
+                .filter(|span| !span.is_skipped())
+                .collect::<Vec<_>>())
+        })
+        .transpose()?;
+
+    capture.set_exact_pts(false);
+    let mut decoder = capture.range_decoder(timestamp)?;
+    let base_rect = decoder.capture().rect();
+
+    let mut points = grid(params)
+        .into_iter()
+        .map(|values| {
+            let mut config = config.clone();
+            for (param, value) in iter::zip(params, &values) {
+                param.apply(&mut config, *value)?;
+            }
+            let values = iter::zip(params, &values)
+                .map(|(param, value)| param.format_value(*value))
+                .collect();
+            GridPoint::new(base_rect, &config, values)
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let names = points[0]
+        .components
+        .iter()
+        .map(|c| c.name().to_owned())
+        .collect::<Vec<_>>();
+
+    let mut report = report.map(util::create_file).transpose()?;
+    if let Some(report) = &mut report {
+        let header = params
+            .iter()
+            .map(SweepParam::name)
+            .chain(names.iter().cloned())
+            .chain(
+                reference
+                    .is_some()
+                    .then(|| ["matched", "precision", "recall", "f1"].map(str::to_owned))
+                    .into_iter()
+                    .flatten(),
+            )
+            .collect::<Vec<_>>();
+        writeln!(report, "{}", header.join("\t"))?;
+    }
+
+    let start = decoder.start();
+    let end = decoder.end();
+    let fps = decoder.capture().fps();
+    let pbar = ProgressBarBuilder::new(start, end, fps).build(tracing::Span::current());
+    tracing::info!(%start, %end, %fps, points = points.len(), "sweep start");
+
+    let _cache = HistogramBasedComponentDetector::cache_ratios();
+    loop {
+        // Counts of a cancelled run are partial, so they are not reported
+        if crate::cancellation().is_cancelled() {
+            return Ok(());
+        }
+        let mut frame = Frame::empty();
+        if !decoder.decode_frame(&mut frame)? {
+            let pos = decoder.end();
+            pbar.set_position(pos);
+            for point in &mut points {
+                point.receive_end_of_frames(pos);
+            }
+            break;
+        }
+        pbar.set_position(frame.position());
+        for point in &mut points {
+            point.receive_frame(&frame)?;
+        }
+    }
+
+    for point in &points {
+        let num_spans = point.num_spans();
+        let summary = iter::zip(&names, &num_spans)
+            .map(|(name, n)| format!("{name}={n}"))
+            .collect::<Vec<_>>();
+        let evaluation = reference
+            .as_deref()
+            .map(|reference| Evaluation::of(&point.found, reference));
+        match &evaluation {
+            Some(evaluation) => tracing::info!(
+                "{} => {} ({evaluation})",
+                point.values.join(","),
+                summary.join(" ")
+            ),
+            None => tracing::info!("{} => {}", point.values.join(","), summary.join(" ")),
+        }
+        if let Some(report) = &mut report {
+            let row = point
+                .values
+                .iter()
+                .cloned()
+                .chain(num_spans.iter().map(usize::to_string))
+                .chain(evaluation.iter().flat_map(|evaluation| {
+                    [
+                        evaluation.matched.to_string(),
+                        format!("{:.3}", evaluation.precision()),
+                        format!("{:.3}", evaluation.recall()),
+                        format!("{:.3}", evaluation.f1()),
+                    ]
+                }))
+                .collect::<Vec<_>>();
+            writeln!(report, "{}", row.join("\t"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Agreement of spans found with the spans in a reference file.
+///
+/// A found span matches a reference span of the same component overlapping it, each matched at most once.
+/// Only components with spans in the reference are evaluated, so that references may list some components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Evaluation {
+    matched: usize,
+    /// Number of found spans of the evaluated components
+    found: usize,
+    /// Number of reference spans
+    expected: usize,
+}
+
+impl Evaluation {
+    fn of(found: &[Span], reference: &[Span]) -> Self {
+        let found = found
+            .iter()
+            .filter(|span| reference.iter().any(|r| r.name == span.name))
+            .collect::<Vec<_>>();
+        let mut used = vec![false; found.len()];
+        let mut matched = 0;
+        for expected in reference {
+            let hit = found.iter().enumerate().position(|(i, span)| {
+                !used[i]
+                    && span.name == expected.name
+                    && span.start < expected.end
+                    && expected.start < span.end
+            });
+            if let Some(i) = hit {
+                used[i] = true;
+                matched += 1;
+            }
+        }
+        Self {
+            matched,
+            found: found.len(),
+            expected: reference.len(),
+        }
+    }
+
+    /// Ratio of found spans matching reference spans, or `1` if no spans are found
+    fn precision(&self) -> f64 {
+        ratio_or_one(self.matched, self.found)
+    }
+
+    /// Ratio of reference spans matched by found spans, or `1` if the reference is empty
+    fn recall(&self) -> f64 {
+        ratio_or_one(self.matched, self.expected)
+    }
+
+    /// Harmonic mean of the precision and the recall
+    fn f1(&self) -> f64 {
+        let (p, r) = (self.precision(), self.recall());
+        if p + r == 0.0 {
+            0.0
+        } else {
+            2.0 * p * r / (p + r)
+        }
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "matched {}/{} found, {} expected, precision {:.3}, recall {:.3}, f1 {:.3}",
+            self.matched,
+            self.found,
+            self.expected,
+            self.precision(),
+            self.recall(),
+            self.f1()
+        )
+    }
+}
+
+fn ratio_or_one(n: usize, d: usize) -> f64 {
+    if d == 0 {
+        1.0
+    } else {
+        n as f64 / d as f64
+    }
+}
+
+/// Returns the cartesian product of the parameter values.
+fn grid(params: &[SweepParam]) -> Vec<Vec<f64>> {
+    params.iter().fold(vec![vec![]], |grid, param| {
+        grid.iter()
+            .flat_map(|prefix| {
+                param.values().map(move |value| {
+                    let mut values = prefix.clone();
+                    values.push(value);
+                    values
+                })
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_param() {
+        let param = "side_item_one_digit.BG=0.90:1.00:0.05"
+            .parse::<SweepParam>()
+            .unwrap();
+        assert_eq!(param.name(), "side_item_one_digit.BG");
+        let values = param
+            .values()
+            .map(|v| param.format_value(v))
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["0.90", "0.95", "1.00"]);
+
+        assert!("side_item_one_digit=0:1:0.1".parse::<SweepParam>().is_err());
+        assert!("a.b=0:1".parse::<SweepParam>().is_err());
+        assert!("a.b=1:0:0.1".parse::<SweepParam>().is_err());
+        assert!("a.b=0:1:0".parse::<SweepParam>().is_err());
+    }
+
+    #[test]
+    fn grid_product() {
+        let params = ["a.x=0:1:1", "b.y=0:2:1"].map(|s| s.parse::<SweepParam>().unwrap());
+        let grid = grid(&params);
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[0], [0.0, 0.0]);
+        assert_eq!(grid[5], [1.0, 2.0]);
+    }
+
+    #[test]
+    fn evaluate_spans() {
+        let spans = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|line| line.parse::<Span>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        let reference = spans(&[
+            "00:00:01.000-00:00:02.000 a (main_item)",
+            "00:00:03.000-00:00:04.000 b (main_item)",
+        ]);
+        let found = spans(&[
+            // Matches only one of the reference spans it overlaps
+            "00:00:01.500-00:00:03.500 x (main_item)",
+            "00:00:05.000-00:00:06.000 y (main_item)",
+            // Components not in the reference are not evaluated
+            "00:00:01.000-00:00:02.000 z (side_item0)",
+        ]);
+        let evaluation = Evaluation::of(&found, &reference);
+        assert_eq!(
+            evaluation,
+            Evaluation {
+                matched: 1,
+                found: 2,
+                expected: 2
+            }
+        );
+        assert_eq!(evaluation.precision(), 0.5);
+        assert_eq!(evaluation.recall(), 0.5);
+        assert_eq!(evaluation.f1(), 0.5);
+
+        // Touching spans do not overlap
+        let found = spans(&["00:00:02.000-00:00:03.000 x (main_item)"]);
+        assert_eq!(Evaluation::of(&found, &reference).matched, 0);
+        assert_eq!(Evaluation::of(&found, &reference).f1(), 0.0);
+    }
+}
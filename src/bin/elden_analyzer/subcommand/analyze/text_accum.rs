@@ -1,344 +1,307 @@
 use std::{
-    borrow::Borrow,
-    collections::{HashMap, HashSet, VecDeque},
     fs::File,
     io::Write as _,
-    iter,
-    sync::mpsc,
+    path::PathBuf,
+    sync::{mpsc, Arc},
 };
 
-use color_eyre::eyre;
+use color_eyre::eyre::{self, WrapErr as _};
 
 use elden_analyzer::{
-    components::{ComponentContainer, ExtractedTexts},
-    operator::Recognition,
+    accumulate::text::AccumResult,
+    analyzer::{
+        text_accum::{self, Output},
+        text_recognize,
+        throttle::Throttle,
+        Stopper,
+    },
+    components::ComponentContainer,
+    environment::Environment,
+    io::{
+        self, buckets,
+        event_id::{EventId, SourceId},
+        event_log::EventLog,
+        event_stream::EventStream,
+        index::IndexBuilder,
+        json, spans,
+        subtitles::{self, SubtitleFormat},
+        timeline::{self, Layout, UncertainCells},
+    },
+    items::{ItemCategory, ItemTranslation},
 };
-use elden_analyzer_collections::seq_iter::SeqIter;
 use elden_analyzer_kernel::types::time::{Duration, FramePosition};
-use num_rational::Ratio;
 
-use super::text_recognize::{self};
+use super::timeline::{Row, TimelineBuilder};
 
-#[tracing::instrument(name = "text_accum", level = "debug", skip_all)]
+/// Outputs of the analysis results.
+#[derive(Debug)]
+pub(super) struct Sinks {
+    /// Source of frames, used to identify events in all outputs
+    pub(super) source: SourceId,
+    pub(super) output_span: Option<File>,
+    pub(super) output_tsv: Option<File>,
+    /// Output NDJSON file of spans with details of recognized texts
+    pub(super) output_json: Option<File>,
+    pub(super) tsv_layout: Layout,
+    /// Whether the in-game time is read, which is written in the `igt` column of the TSV file
+    pub(super) tsv_igt: bool,
+    /// How texts not reliably recognized are written in the TSV file
+    pub(super) tsv_uncertain: UncertainCells,
+    /// Output span file of side items tracked across rows, written at the end
+    pub(super) output_pickups: Option<File>,
+    /// Output subtitle files of spans, written at the end to order cues by their starts
+    pub(super) output_srt: Option<File>,
+    pub(super) output_vtt: Option<File>,
+    /// Output CSV file of counts of events in windows of `bucket_window`, written at the end
+    pub(super) output_buckets: Option<File>,
+    pub(super) bucket_window: Duration,
+    pub(super) translate_items: Option<ItemTranslation>,
+    /// Categories of items whose spans are output, or empty to output all spans
+    pub(super) only_categories: Vec<ItemCategory>,
+    /// Environment recorded in the header of outputs
+    pub(super) environment: Option<Environment>,
+    /// Log to append spans to as soon as they are finalized
+    pub(super) event_log: Option<EventLog>,
+    /// Receiver of events sent as soon as spans are finalized
+    pub(super) event_stream: Option<EventStream>,
+    /// Conditions cancelling the analysis once a span meets one of them
+    pub(super) stop: Option<Stopper>,
+    /// Video whose index is written next to it if the analysis completes
+    pub(super) index: Option<PathBuf>,
+}
+
+/// Accumulates texts received from `rx` into spans, and writes them to `sinks`.
 pub(super) fn run(
+    names: ComponentContainer<String>,
     rx: mpsc::Receiver<(usize, text_recognize::Packet)>,
     start: FramePosition,
-    sec_per_frame: Duration,
-    mut output_span: Option<File>,
-    mut output_tsv: Option<File>,
-) -> eyre::Result<()> {
-    let mut check_pos = start;
-    let mut last_updated = start;
-    let mut accum = ComponentContainer::from_fn(Accumulator::new);
-
-    let mut write_span = |result| -> eyre::Result<()> {
-        let AccumResult {
-            name,
-            start,
-            end,
-            text,
-        } = result;
-
-        tracing::info!(
-            name,
-            "{start}-{end} {text}",
-            start = start.timestamp(),
-            end = end.timestamp()
-        );
-        if let Some(output) = &mut output_span {
-            writeln!(
-                output,
-                "{start}-{end} {text} ({name})",
-                start = start.timestamp(),
-                end = end.timestamp()
-            )?;
-        }
-        Ok(())
-    };
-
-    if let Some(output) = &mut output_tsv {
-        let header_text = accum
-            .iter()
-            .map(|accum| accum.name)
-            .collect::<Vec<_>>()
-            .join("\t");
-        writeln!(output, "timestamp\t{header_text}")?;
+    step: usize,
+    min_possible_share: i32,
+    sinks: Sinks,
+    throttle: Option<Arc<Throttle>>,
+) -> eyre::Result<ComponentContainer<usize>> {
+    let Sinks {
+        source,
+        mut output_span,
+        mut output_tsv,
+        mut output_json,
+        tsv_layout,
+        tsv_igt,
+        tsv_uncertain,
+        output_pickups,
+        output_srt,
+        output_vtt,
+        output_buckets,
+        bucket_window,
+        translate_items,
+        only_categories,
+        environment,
+        mut event_log,
+        mut event_stream,
+        mut stop,
+        index,
+    } = sinks;
+    for output in [&mut output_span, &mut output_tsv].into_iter().flatten() {
+        io::write_header(output, environment.as_ref())?;
     }
-
-    let mut write_tsv = |start: FramePosition, results: Vec<&str>| -> eyre::Result<()> {
-        tracing::debug!("{start} {results:?}", start = start.timestamp(),);
-        if let Some(output) = &mut output_tsv {
-            let results_text = results.join("\t");
-            writeln!(output, "{start}\t{results_text}", start = start.timestamp(),)?;
+    let num_components = names.iter().count();
+    // Frames are dropped only for live sources, whose timeline has a column of skipped intervals after components
+    let skipped_column = throttle.is_some().then_some(num_components);
+    // Each column of texts is followed by the column of their confidences if any
+    let stride = if tsv_uncertain.has_confidence_columns() {
+        2
+    } else {
+        1
+    };
+    let mut timeline = TimelineBuilder::new(
+        (num_components + usize::from(skipped_column.is_some())) * stride,
+        start,
+    );
+    let insert_span = |timeline: &mut TimelineBuilder, idx: usize, result: &AccumResult| {
+        let confidence = result.min_confidence().map(|conf| conf.to_f64());
+        let (text, confidence) = tsv_uncertain.render(&result.text, confidence);
+        timeline.insert_span(idx * stride, result.start, result.end, text);
+        if let Some(confidence) = confidence {
+            timeline.insert_span(idx * stride + 1, result.start, result.end, confidence);
         }
-        Ok(())
     };
 
-    for (_i, packet) in SeqIter::new(rx) {
-        let pos = packet.position();
-        let _span = tracing::trace_span!("frame", %pos).entered();
-
-        match packet {
-            text_recognize::Packet::Frame { pos, result } => {
-                for (accum, result) in accum.iter_mut().zip(*result) {
-                    if let Some(result) = accum.receive_frame(pos, result) {
-                        write_span(result)?;
-                    }
-                }
+    // Spans of all components, kept to track side items and write subtitles at the end
+    let keep_spans = output_pickups.is_some()
+        || output_srt.is_some()
+        || output_vtt.is_some()
+        || output_buckets.is_some()
+        || index.is_some();
+    let mut all_spans = vec![];
+    let mut write_span = |result: AccumResult| -> eyre::Result<()> {
+        let rarity = result.rarity;
+        let id = (result.name != spans::SKIPPED)
+            .then(|| EventId::new(source, &result.name, result.start.index()));
+        if output_json.is_some() || event_stream.is_some() {
+            let record = json::Record::new(&result, id.as_ref());
+            if let Some(output) = &mut output_json {
+                let mut line = serde_json::to_vec(&record)?;
+                line.push(b'\n');
+                output.write_all(&line)?;
             }
-            text_recognize::Packet::EndOfFrames { pos } => {
-                for accum in &mut accum {
-                    if let Some(result) = accum.receive_end_of_frames(pos) {
-                        write_span(result)?;
-                    }
-                }
+            if let Some(stream) = &mut event_stream {
+                stream.send(&record)?;
             }
         }
-
-        while check_pos.index() <= pos.index() {
-            let all_available = accum
-                .iter()
-                .all(|accum| accum.prev_span_available(check_pos));
-            if !all_available {
-                break;
-            }
-
-            let mut updated = false;
-            for accum in &accum {
-                updated |= accum.is_span_end(check_pos);
-            }
-
-            if updated {
-                let results = accum
-                    .iter()
-                    .map(|accum| accum.prev_span_result(check_pos).unwrap_or(""))
-                    .collect::<Vec<_>>();
-                write_tsv(last_updated, results)?;
-                last_updated = check_pos;
-            }
-
-            check_pos = check_pos.next(sec_per_frame);
-            for accum in &mut accum {
-                accum.seek_result_to(check_pos);
-            }
+        let span = if let Some(id) = id {
+            let span = result.into_span(Some(id.clone()));
+            tracing::info!(
+                name = span.name,
+                %id,
+                "{start}-{end} {text}{igt}",
+                start = span.start,
+                end = span.end,
+                text = span.text,
+                igt = span.igt.map(|igt| format!(" @{igt}")).unwrap_or_default()
+            );
+            span
+        } else {
+            let span = result.into_span(None);
+            tracing::info!("{}-{} not analyzed ({})", span.start, span.end, span.text);
+            span
+        };
+        if let Some(output) = &mut output_span {
+            writeln!(output, "{span}")?;
         }
-    }
-
-    Ok(())
-}
-
-#[derive(Debug, Clone)]
-struct AccumResult {
-    name: &'static str,
-    start: FramePosition,
-    end: FramePosition,
-    text: String,
-}
-
-#[derive(Debug)]
-struct Accumulator {
-    name: &'static str,
-    end_of_frames: Option<FramePosition>,
-    found_start: Option<FramePosition>,
-    accum: Vec<InnerAccumulator>,
-    results: VecDeque<AccumResult>,
-}
-
-impl Accumulator {
-    fn new(name: &'static str) -> Self {
-        Self {
-            name,
-            end_of_frames: None,
-            found_start: None,
-            accum: vec![],
-            results: VecDeque::new(),
+        if let Some(log) = &mut event_log {
+            log.append(&span, rarity)
+                .wrap_err_with(|| format!("failed to write event log: {}", log.path().display()))?;
         }
-    }
-
-    fn receive_frame(
-        &mut self,
-        pos: FramePosition,
-        result: Option<ExtractedTexts>,
-    ) -> Option<AccumResult> {
-        match result {
-            Some(text) => self.handle_found(pos, text),
-            None => self.handle_absent(pos),
+        // Frames already decoded are still analyzed and written after cancelling
+        if let Some(reason) = stop.as_mut().and_then(|stop| stop.receive(&span)) {
+            tracing::info!(%reason, "stopping the analysis");
+            crate::cancellation().cancel();
+            stop = None;
         }
-    }
-
-    fn receive_end_of_frames(&mut self, pos: FramePosition) -> Option<AccumResult> {
-        self.end_of_frames = Some(pos);
-        self.handle_absent(pos)
-    }
-
-    fn prev_span_available(&self, end: FramePosition) -> bool {
-        if self.end_of_frames.is_some() {
-            return true;
+        if keep_spans {
+            all_spans.push(span);
         }
+        Ok(())
+    };
 
-        let in_found_span = self
-            .found_start
-            .is_some_and(|start| start.index() >= end.index());
-        !in_found_span
-    }
-
-    fn prev_span_result(&self, end_pos: FramePosition) -> Option<&str> {
-        self.results
-            .front()
-            .filter(|result| {
-                result.start.index() < end_pos.index() && result.end.index() >= end_pos.index()
-            })
-            .map(|res| res.text.as_str())
-    }
-
-    fn is_span_end(&self, end: FramePosition) -> bool {
-        self.end_of_frames
-            .is_some_and(|eof| eof.index() == end.index())
-            || self
-                .found_start
-                .is_some_and(|start| start.index() == end.index())
-            || self.results.front().is_some_and(|result| {
-                result.start.index() == end.index() || result.end.index() == end.index()
-            })
+    let column_names = names
+        .iter()
+        .cloned()
+        .chain(skipped_column.map(|_| spans::SKIPPED.to_owned()))
+        .chain(tsv_igt.then(|| timeline::IGT_COLUMN.to_owned()))
+        .collect::<Vec<_>>();
+    if let Some(output) = &mut output_tsv {
+        writeln!(
+            output,
+            "{}",
+            timeline::header(&column_names, tsv_layout, tsv_uncertain)
+        )?;
     }
 
-    fn seek_result_to(&mut self, pos: FramePosition) {
-        while self
-            .results
-            .front()
-            .is_some_and(|result| result.end.index() < pos.index())
+    // Texts of the last row, used to write only changes in the long layout
+    let mut last_texts = vec![String::new(); column_names.len() * stride];
+    let mut write_tsv = |rows: Vec<Row>| -> eyre::Result<()> {
+        for Row {
+            start,
+            mut texts,
+            igt,
+        } in rows
         {
-            self.results.pop_front();
-        }
-    }
-
-    fn handle_found(&mut self, pos: FramePosition, text: ExtractedTexts) -> Option<AccumResult> {
-        if self.found_start.is_none() {
-            self.found_start = Some(pos);
-        }
-
-        if self.accum.is_empty() {
-            self.accum
-                .extend(iter::repeat_with(Default::default).take(text.result.len()));
+            if tsv_igt {
+                texts.push(igt.map(|igt| igt.to_string()).unwrap_or_default());
+                // The in-game time has no confidence
+                texts.resize(texts.len() + stride - 1, String::new());
+            }
+            let start = start.timestamp();
+            tracing::debug!("{start} {texts:?}");
+            if let Some(output) = &mut output_tsv {
+                match tsv_layout {
+                    Layout::Wide => writeln!(output, "{start}\t{}", texts.join("\t"))?,
+                    Layout::Long => {
+                        let columns = texts.chunks(stride).zip(last_texts.chunks(stride));
+                        for (name, (cells, last)) in column_names.iter().zip(columns) {
+                            if cells != last {
+                                writeln!(output, "{start}\t{name}\t{}", cells.join("\t"))?;
+                            }
+                        }
+                    }
+                }
+            }
+            last_texts = texts;
         }
-        assert_eq!(self.accum.len(), text.result.len());
+        Ok(())
+    };
 
-        for (accum, result) in self.accum.iter_mut().zip(text.result) {
-            accum.insert(result);
+    let mut index = index.map(|video| (video, IndexBuilder::new(source, &names, step)));
+    let mut end = None;
+    let num_spans = text_accum::run(
+        names,
+        rx,
+        step,
+        min_possible_share,
+        translate_items,
+        &only_categories,
+        throttle,
+        |output| {
+            match output {
+                Output::Frame { pos, found, igt } => {
+                    if let Some((_, builder)) = &mut index {
+                        builder.receive_frame(pos, &found);
+                    }
+                    if let Some(igt) = igt {
+                        timeline.receive_igt(pos, igt);
+                    }
+                }
+                Output::Span { column, result } => {
+                    insert_span(&mut timeline, column, &result);
+                    write_span(result)?;
+                }
+                Output::Dropped(result) => {
+                    insert_span(&mut timeline, num_components, &result);
+                    write_span(result)?;
+                }
+                Output::Settled(final_until) => write_tsv(timeline.advance(final_until))?,
+                Output::Finished(pos) => {
+                    write_tsv(timeline.finish(pos))?;
+                    end = Some(pos);
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    for (output, format) in [
+        (output_srt, SubtitleFormat::Srt),
+        (output_vtt, SubtitleFormat::Vtt),
+    ] {
+        if let Some(mut output) = output {
+            subtitles::write(&mut output, format, &all_spans)?;
         }
-
-        None
     }
-
-    fn handle_absent(&mut self, pos: FramePosition) -> Option<AccumResult> {
-        let start = self.found_start.take()?;
-        let end = pos;
-
-        let mut segments = vec![];
-        for accum in &mut self.accum {
-            let text = accum.get_text();
-            segments.push(text);
-            accum.reset();
-        }
-
-        let result = AccumResult {
-            name: self.name,
-            start,
-            end,
-            text: segments.join(" "),
-        };
-        self.results.push_back(result.clone());
-        Some(result)
+    if let Some(mut output) = output_buckets {
+        let buckets = buckets::aggregate(all_spans.iter().cloned(), bucket_window);
+        buckets::write(&mut output, &buckets)?;
     }
-}
-
-#[derive(Debug, Default)]
-struct InnerAccumulator {
-    found: HashSet<String>,
-    possible: HashMap<String, Ratio<i32>>,
-}
-
-impl InnerAccumulator {
-    fn insert(&mut self, result: Recognition) {
-        match result {
-            Recognition::Found(text, _) => {
-                self.found.insert(text);
+    // Indexes of videos analyzed partway would answer searches wrongly
+    if let (Some((video, mut builder)), Some(end)) = (index, end) {
+        if !crate::cancellation().is_cancelled() {
+            for span in all_spans.iter().filter(|span| span.id.is_some()) {
+                builder.receive_span(span);
             }
-            Recognition::Possible(text, conf) => {
-                *self.possible.entry(text).or_default() += conf.as_ratio();
+            // The outputs are complete without the index, so it is not an error of the analysis
+            match builder.finish(end).save_for(&video) {
+                Ok(()) => tracing::info!(path = %video.display(), "index written"),
+                Err(err) => tracing::warn!("{err:?}"),
             }
         }
     }
-
-    fn get_text(&self) -> String {
-        if !self.found.is_empty() {
-            return join_texts(self.found.iter().map(|s| s.as_str()));
+    if let Some(mut output) = output_pickups {
+        io::write_header(&mut output, environment.as_ref())?;
+        let window = Duration::new(spans::PICKUP_WINDOW);
+        for span in spans::track_side_items(all_spans, window) {
+            writeln!(output, "{span}")?;
         }
-
-        let total_conf = self.possible.values().sum::<Ratio<i32>>();
-
-        let mut texts = self
-            .possible
-            .iter()
-            .map(|(text, conf)| (format!("??{text}"), *conf))
-            .collect::<Vec<_>>();
-        let threshold = total_conf * Ratio::new(1, 10);
-
-        texts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        let filtered = texts
-            .iter()
-            .filter(|(_, weight)| *weight >= threshold)
-            .collect::<Vec<_>>();
-        tracing::debug!(threshold = ?threshold, ?filtered, ?texts);
-
-        if filtered.is_empty() {
-            return join_texts(texts.iter().map(|(text, _)| text.as_str()));
-        }
-
-        join_texts(filtered.iter().map(|(text, _)| text.as_str()))
     }
 
-    fn reset(&mut self) {
-        self.found.clear();
-        self.possible.clear();
-    }
-}
-
-fn join_texts<S, I>(texts: I) -> String
-where
-    I: IntoIterator<Item = S>,
-    S: Borrow<str>,
-{
-    const SEP: &str = "|";
-    const OPEN: &str = "{";
-    const CLOSE: &str = "}";
-
-    let mut texts = texts.into_iter();
-    let Some(first) = texts.next() else {
-        return format!("{OPEN}{CLOSE}");
-    };
-    let Some(second) = texts.next() else {
-        return first.borrow().to_owned();
-    };
-
-    let mut result = format!("{OPEN}{}{SEP}{}", first.borrow(), second.borrow());
-    for text in texts {
-        result += SEP;
-        result += text.borrow();
-    }
-
-    result += CLOSE;
-    result
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_join_texts() {
-        assert_eq!(join_texts::<&str, _>([]), "{}");
-        assert_eq!(join_texts(["a"]), "a");
-        assert_eq!(join_texts(["a", "b"]), "{a|b}");
-    }
+    Ok(num_spans)
 }
@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+
+use elden_analyzer_collections::interval_map::IntervalMap;
+use elden_analyzer_kernel::types::time::{Duration, FramePosition};
+
+/// Builds rows of the timeline TSV from spans of components.
+///
+/// Rows are emitted by the following rules:
+///
+/// * A row starts at the start of the analysis and at every frame where a span of any component starts or ends.
+/// * Each row lasts until the start of the next row, and has the texts of spans covering it (empty if absent).
+/// * A row is emitted once the spans of all components are final until the end of the row,
+///   so rows are emitted in order, and never duplicated nor skipped.
+/// * At the end of frames, the last row is emitted unless it is empty.
+///
+/// The in-game time at the start of each row is extrapolated from the last reading at or before it,
+/// since the timer is read only in frames where any component is shown.
+#[derive(Debug)]
+pub(super) struct TimelineBuilder {
+    spans: Vec<IntervalMap<Pos, String>>,
+    /// Readings of the in-game time, from the last one at or before the start of the row
+    igt: Vec<(FramePosition, Duration)>,
+    row_start: Pos,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Row {
+    pub(super) start: FramePosition,
+    pub(super) texts: Vec<String>,
+    pub(super) igt: Option<Duration>,
+}
+
+/// Frame position ordered by the frame index
+#[derive(Debug, Clone, Copy)]
+struct Pos(FramePosition);
+
+impl PartialEq for Pos {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.index() == other.0.index()
+    }
+}
+
+impl Eq for Pos {}
+
+impl PartialOrd for Pos {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pos {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.index().cmp(&other.0.index())
+    }
+}
+
+impl TimelineBuilder {
+    pub(super) fn new(num_components: usize, start: FramePosition) -> Self {
+        Self {
+            spans: (0..num_components).map(|_| IntervalMap::new()).collect(),
+            igt: vec![],
+            row_start: Pos(start),
+        }
+    }
+
+    /// Adds a span of the `idx`-th component.
+    ///
+    /// Spans must not start before the last emitted row.
+    pub(super) fn insert_span(
+        &mut self,
+        idx: usize,
+        start: FramePosition,
+        end: FramePosition,
+        text: String,
+    ) {
+        assert!(start.index() >= self.row_start.0.index());
+        self.spans[idx].insert(Pos(start)..Pos(end), text);
+    }
+
+    /// Adds the in-game time read in the frame at `pos`.
+    ///
+    /// Frames must be received in order, before rows starting after them are emitted.
+    pub(super) fn receive_igt(&mut self, pos: FramePosition, igt: Duration) {
+        self.igt.push((pos, igt));
+    }
+
+    /// Emits rows ending at or before the frame `final_until`.
+    ///
+    /// Spans of all components before the frame `final_until` must have been inserted.
+    pub(super) fn advance(&mut self, final_until: usize) -> Vec<Row> {
+        let mut boundaries = self
+            .spans
+            .iter()
+            .flat_map(|spans| spans.boundaries())
+            .filter(|pos| *pos > &self.row_start && pos.0.index() <= final_until)
+            .copied()
+            .collect::<Vec<_>>();
+        boundaries.sort();
+        boundaries.dedup();
+
+        let mut rows = vec![];
+        for boundary in boundaries {
+            rows.push(self.row());
+            self.row_start = boundary;
+        }
+        for spans in &mut self.spans {
+            spans.remove_until(&self.row_start);
+        }
+        let passed = self
+            .igt
+            .iter()
+            .take_while(|(pos, _)| Pos(*pos) <= self.row_start)
+            .count();
+        self.igt.drain(..passed.saturating_sub(1));
+        rows
+    }
+
+    /// Emits all remaining rows, where `end` is the position after the last frame.
+    pub(super) fn finish(&mut self, end: FramePosition) -> Vec<Row> {
+        let mut rows = self.advance(end.index());
+        if self.row_start < Pos(end) {
+            rows.push(self.row());
+            self.row_start = Pos(end);
+        }
+        rows
+    }
+
+    fn row(&self) -> Row {
+        let texts = self
+            .spans
+            .iter()
+            .map(|spans| {
+                spans
+                    .get(&self.row_start)
+                    .map(|(_, text)| text.clone())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let start = self.row_start.0;
+        let igt = self
+            .igt
+            .iter()
+            .take_while(|(pos, _)| Pos(*pos) <= self.row_start)
+            .last()
+            .map(|(pos, igt)| {
+                Duration::new(igt.as_ratio() + (start.timestamp() - pos.timestamp()).as_ratio())
+            });
+        Row { start, texts, igt }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elden_analyzer_kernel::types::time::Timestamp;
+    use num_rational::Ratio;
+
+    use super::*;
+
+    fn pos(idx: usize) -> FramePosition {
+        FramePosition::new(idx, Timestamp::new(Ratio::from_integer(idx as i64)))
+    }
+
+    fn row(start: usize, texts: &[&str]) -> (usize, Vec<String>) {
+        (start, texts.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn indexed(rows: Vec<Row>) -> Vec<(usize, Vec<String>)> {
+        rows.into_iter()
+            .map(|row| (row.start.index(), row.texts))
+            .collect()
+    }
+
+    fn span(b: &mut TimelineBuilder, idx: usize, start: usize, end: usize, text: &str) {
+        b.insert_span(idx, pos(start), pos(end), text.into());
+    }
+
+    #[test]
+    fn no_spans() {
+        let mut b = TimelineBuilder::new(2, pos(0));
+        assert_eq!(indexed(b.advance(5)), []);
+        assert_eq!(indexed(b.finish(pos(10))), [row(0, &["", ""])]);
+        assert_eq!(indexed(b.finish(pos(10))), []);
+    }
+
+    #[test]
+    fn empty_range() {
+        let mut b = TimelineBuilder::new(1, pos(3));
+        assert_eq!(indexed(b.finish(pos(3))), []);
+    }
+
+    #[test]
+    fn span_at_start() {
+        let mut b = TimelineBuilder::new(2, pos(0));
+        span(&mut b, 0, 0, 4, "a");
+        // The row at the start is not duplicated
+        assert_eq!(indexed(b.advance(4)), [row(0, &["a", ""])]);
+        assert_eq!(indexed(b.finish(pos(6))), [row(4, &["", ""])]);
+    }
+
+    #[test]
+    fn span_until_end() {
+        let mut b = TimelineBuilder::new(1, pos(0));
+        assert_eq!(indexed(b.advance(2)), []);
+        span(&mut b, 0, 2, 5, "a");
+        // No empty row at the end
+        assert_eq!(indexed(b.finish(pos(5))), [row(0, &[""]), row(2, &["a"])]);
+    }
+
+    #[test]
+    fn simultaneous_boundaries() {
+        let mut b = TimelineBuilder::new(2, pos(0));
+        span(&mut b, 0, 1, 3, "a");
+        span(&mut b, 1, 3, 5, "b");
+        span(&mut b, 0, 5, 6, "c");
+        assert_eq!(
+            indexed(b.finish(pos(6))),
+            [
+                row(0, &["", ""]),
+                row(1, &["a", ""]),
+                row(3, &["", "b"]),
+                row(5, &["c", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_spans() {
+        let mut b = TimelineBuilder::new(2, pos(0));
+        span(&mut b, 0, 1, 4, "a");
+        span(&mut b, 1, 2, 6, "b");
+        assert_eq!(
+            indexed(b.finish(pos(8))),
+            [
+                row(0, &["", ""]),
+                row(1, &["a", ""]),
+                row(2, &["a", "b"]),
+                row(4, &["", "b"]),
+                row(6, &["", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_for_open_span() {
+        let mut b = TimelineBuilder::new(2, pos(0));
+        span(&mut b, 0, 1, 3, "a");
+        // The second component has an open span from frame 2
+        assert_eq!(indexed(b.advance(2)), [row(0, &["", ""])]);
+        // Frame 3 is not final yet, so the row from frame 1 is kept
+        assert_eq!(indexed(b.advance(2)), []);
+        span(&mut b, 1, 2, 7, "b");
+        assert_eq!(
+            indexed(b.advance(8)),
+            [row(1, &["a", ""]), row(2, &["a", "b"]), row(3, &["", "b"])]
+        );
+        assert_eq!(indexed(b.finish(pos(9))), [row(7, &["", ""])]);
+    }
+
+    #[test]
+    fn incremental_equals_batch() {
+        let spans = [
+            (0, 0, 2, "a"),
+            (1, 1, 3, "b"),
+            (0, 3, 6, "c"),
+            (1, 6, 9, "d"),
+        ];
+
+        let mut batch = TimelineBuilder::new(2, pos(0));
+        for (idx, start, end, text) in spans {
+            span(&mut batch, idx, start, end, text);
+        }
+        let batch = indexed(batch.finish(pos(10)));
+
+        let mut incremental = TimelineBuilder::new(2, pos(0));
+        let mut rows = vec![];
+        for (idx, start, end, text) in spans {
+            span(&mut incremental, idx, start, end, text);
+            rows.extend(indexed(incremental.advance(start)));
+        }
+        rows.extend(indexed(incremental.finish(pos(10))));
+
+        assert_eq!(rows, batch);
+        assert_eq!(batch.len(), 6);
+    }
+
+    #[test]
+    fn igt_at_row_start() {
+        let igt = |sec: i64| Duration::new(Ratio::from_integer(sec));
+        let mut b = TimelineBuilder::new(1, pos(0));
+        span(&mut b, 0, 2, 4, "a");
+        b.receive_igt(pos(2), igt(100));
+        b.receive_igt(pos(3), igt(101));
+        assert_eq!(
+            b.advance(4)
+                .into_iter()
+                .map(|row| row.igt)
+                .collect::<Vec<_>>(),
+            [None, Some(igt(100))]
+        );
+        // The timer is not read after the span, so the time is extrapolated from the last reading
+        assert_eq!(
+            b.finish(pos(6))
+                .into_iter()
+                .map(|row| row.igt)
+                .collect::<Vec<_>>(),
+            [Some(igt(102))]
+        );
+    }
+}
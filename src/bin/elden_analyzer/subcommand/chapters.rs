@@ -0,0 +1,81 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    io::{
+        chapters::{self, Grouping},
+        spans,
+    },
+    util,
+};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+
+use super::{CorrectionsArgs, GroupsArgs};
+
+/// Minimum number of chapters for YouTube to show them
+const MIN_CHAPTERS: usize = 3;
+
+/// Write chapters of a video for its description on YouTube
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files (outputs of `analyze --output-span` or `--output-pickups`) of the video, in order of time
+    #[clap(required = true)]
+    spans: Vec<PathBuf>,
+    /// Output text file of the chapters (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Components or groups of them whose spans start chapters. Side items are named `side_item` after tracked across rows
+    #[clap(
+        long = "component",
+        default_values = ["main_item", "side_item", "boss_bar", "grace_rest"],
+        conflicts_with = "area"
+    )]
+    components: Vec<String>,
+    /// Group events into chapters of in-game areas, started by spans of the component such as `area_banner`
+    #[clap(long)]
+    area: Option<String>,
+    /// Minimum length of chapters, within which later events are merged into the chapter
+    #[clap(long, default_value = "00:00:10")]
+    min_length: Timestamp,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+    #[clap(flatten)]
+    groups: GroupsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "chapters", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = vec![];
+        for path in &self.spans {
+            spans.extend(spans::read_file(path)?);
+        }
+        self.corrections.apply(&mut spans)?;
+        let components = self.groups.component_filter(&self.components)?;
+        let grouping = match &self.area {
+            Some(area) => Grouping::Area(area),
+            None => Grouping::Events(&components),
+        };
+        let min_length = Duration::new(self.min_length.as_ratio());
+        let chapters = chapters::from_spans(spans, grouping, min_length);
+        if chapters.len() < MIN_CHAPTERS {
+            tracing::warn!(
+                chapters = chapters.len(),
+                "YouTube shows chapters only if there are {MIN_CHAPTERS} or more"
+            );
+        }
+        if min_length < Duration::new(chapters::MIN_LENGTH) {
+            tracing::warn!(%min_length, "YouTube shows chapters only if they are 10 seconds or longer");
+        }
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        chapters::write(&mut output, &chapters)?;
+        Ok(())
+    }
+}
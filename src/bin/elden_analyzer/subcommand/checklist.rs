@@ -0,0 +1,74 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{checklist::Checklist, io::spans, items::ItemCategory, util};
+
+use super::CorrectionsArgs;
+
+/// Produce a checklist of known items with their first pickups
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files (outputs of `analyze --output-span`) of a run, in order of time
+    #[clap(required = true)]
+    spans: Vec<PathBuf>,
+    /// Categories of items in the checklist, such as `weapons,talismans` (default: all categories)
+    #[clap(long, value_delimiter = ',')]
+    only_category: Vec<ItemCategory>,
+    /// Output TSV file of the checklist (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "checklist", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = vec![];
+        for path in &self.spans {
+            spans.extend(spans::read_file(path)?);
+        }
+        self.corrections.apply(&mut spans)?;
+        let checklist = Checklist::new(&spans, &self.only_category);
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        writeln!(output, "status\tcategory\titem\ttime\tigt\tspan")?;
+        for entry in &checklist.entries {
+            let status = if entry.first.is_some() {
+                "obtained"
+            } else {
+                "missing"
+            };
+            writeln!(
+                output,
+                "{status}\t{category}\t{item}\t{time}\t{igt}\t{span}",
+                category = entry.category,
+                item = entry.item,
+                time = display(entry.first.map(|span| span.start)),
+                igt = display(entry.first.and_then(|span| span.igt)),
+                span = display(entry.first),
+            )?;
+        }
+
+        let categories = if self.only_category.is_empty() {
+            &ItemCategory::ALL[..]
+        } else {
+            &self.only_category
+        };
+        for &category in categories {
+            let (obtained, total) = checklist.progress(category);
+            tracing::info!(%category, obtained, total, "checklist");
+        }
+        Ok(())
+    }
+}
+
+fn display<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,86 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{io::spans, route::Route, util};
+use elden_analyzer_kernel::types::time::Duration;
+use num_rational::Ratio;
+
+use super::{CorrectionsArgs, GroupsArgs};
+
+/// Compare spans against a route and report missed and extra events
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span file (output of `analyze --output-span`)
+    spans: PathBuf,
+    /// Route file listing the expected events in order
+    #[clap(long)]
+    route: PathBuf,
+    /// Output TSV file of the report (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+    #[clap(flatten)]
+    groups: GroupsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "compare_route", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = spans::read_file(&self.spans)?;
+        self.corrections.apply(&mut spans)?;
+        let route = Route::load(&self.route)?;
+        let components = self.groups.component_filter(&route.components)?;
+        let comparison = route.compare_with(&spans, &components);
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        writeln!(output, "status\tlabel\ttarget\tactual\tdelta\tspan")?;
+        for res in &comparison.checkpoints {
+            let status = if res.span.is_some() { "ok" } else { "missed" };
+            writeln!(
+                output,
+                "{status}\t{label}\t{target}\t{actual}\t{delta}\t{span}",
+                label = res.checkpoint.label(),
+                target = display(res.checkpoint.target),
+                actual = display(res.actual()),
+                delta = display(res.delta().map(format_delta)),
+                span = display(res.span),
+            )?;
+        }
+        for span in &comparison.extras {
+            writeln!(output, "extra\t\t\t{}\t\t{span}", span.start)?;
+        }
+
+        let num_missed = comparison
+            .checkpoints
+            .iter()
+            .filter(|res| res.span.is_none())
+            .count();
+        tracing::info!(
+            checkpoints = comparison.checkpoints.len(),
+            missed = num_missed,
+            extra = comparison.extras.len(),
+            "compared spans against the route"
+        );
+        Ok(())
+    }
+}
+
+fn display<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Formats a signed difference of times, e.g. `+00:00:01.500`.
+fn format_delta(delta: Ratio<i64>) -> String {
+    if delta < Ratio::ZERO {
+        format!("-{}", Duration::new(-delta))
+    } else {
+        format!("+{}", Duration::new(delta))
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    io::{corrections, spans},
+    util,
+};
+
+use super::CorrectionsArgs;
+
+/// Write an editable TSV file of texts of spans, read by `--corrections` of other subcommands
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files (outputs of `analyze --output-span`)
+    #[clap(required = true)]
+    spans: Vec<PathBuf>,
+    /// Output TSV file of the texts to edit (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Previous corrections of the spans, kept in the output
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "corrections", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = vec![];
+        for path in &self.spans {
+            spans.extend(spans::read_file(path)?);
+        }
+        let corrections = self.corrections.load()?;
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        corrections::write(&mut output, &spans, corrections.as_ref())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer::{
+    assets::{self, Asset},
+    components::{self, Components, Detection},
+    config::{Config, DEFAULT_OCR_LANG},
+    environment,
+    image_process::tesseract::Tesseract,
+    util::ImageLogger,
+};
+use elden_analyzer_kernel::types::time::TimestampRange;
+use elden_analyzer_video::capture::{Frame, VideoCapture};
+
+use super::{BackendArgs, ConfigArgs};
+
+/// Diagnose FFmpeg, Tesseract and the configuration, and suggest how to fix problems
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Video file to open and measure the speed of the analysis with
+    sample: Option<PathBuf>,
+    /// Number of frames of the sample to measure with
+    #[clap(long, default_value = "100")]
+    frames: usize,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Result of a diagnosis.
+#[derive(Debug)]
+pub(super) struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    /// How to fix the problem
+    remedy: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            remedy: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warning,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+
+    fn error(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Error,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+
+    pub(super) fn print(&self) {
+        println!("[{}] {}: {}", self.status, self.name, self.detail);
+        if let Some(remedy) = &self.remedy {
+            println!("    => {remedy}");
+        }
+    }
+}
+
+/// Checks FFmpeg, Tesseract and the trained data of `ocr_lang`.
+pub(super) fn check_libraries(ocr_lang: &str) -> Vec<Check> {
+    let mut checks = vec![Check::ok("ffmpeg", elden_analyzer_video::ffmpeg_version())];
+
+    let hwaccels = elden_analyzer_video::ffmpeg_hwaccels();
+    let check = if hwaccels.is_empty() {
+        Check::ok("hwaccels", "none (frames are decoded by the CPU)")
+    } else {
+        let list = hwaccels
+            .iter()
+            .map(|(name, available)| match available {
+                true => format!("{name} (available)"),
+                false => format!("{name} (no device)"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Check::ok("hwaccels", list)
+    };
+    checks.push(check);
+
+    let Some(version) = Tesseract::version() else {
+        checks.push(Check::error(
+            "tesseract",
+            "not available in this build",
+            "build with the `ocr-tesseract` feature to recognize texts",
+        ));
+        return checks;
+    };
+    checks.push(Check::ok("tesseract", version));
+
+    let install_hint = format!(
+        "install the trained data (e.g. `tesseract-ocr-{ocr_lang}` package), \
+         or set `TESSDATA_PREFIX` to the directory containing `{ocr_lang}.traineddata`"
+    );
+    let check = match environment::traineddata_languages() {
+        None => Check::error(
+            "tessdata",
+            "no trained data directory is found",
+            &install_hint,
+        ),
+        Some((dir, languages)) if !languages.iter().any(|lang| lang == ocr_lang) => Check::error(
+            "tessdata",
+            format!(
+                "{ocr_lang} is not in {} (found: {})",
+                dir.display(),
+                languages.join(", ")
+            ),
+            &install_hint,
+        ),
+        Some((dir, languages)) => Check::ok(
+            "tessdata",
+            format!("{} in {}", languages.join(", "), dir.display()),
+        ),
+    };
+    let tessdata_ok = check.status == Status::Ok;
+    checks.push(check);
+
+    if tessdata_ok {
+        let check = match Tesseract::new(None, Some(ocr_lang)) {
+            Ok(_) => Check::ok("ocr", format!("{ocr_lang} is loaded")),
+            Err(err) => Check::error(
+                "ocr",
+                format!("failed to load {ocr_lang}: {err:#}"),
+                "the trained data may be corrupted or for an incompatible version of Tesseract; reinstall it",
+            ),
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+/// Reports where dictionaries are read from, with the heads of their checksums to tell their versions.
+fn check_assets() -> Check {
+    let list = Asset::ALL
+        .into_iter()
+        .map(|asset| {
+            let checksum = assets::checksum(asset);
+            format!("{asset} ({}, {})", assets::origin(asset), &checksum[..12])
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Check::ok("assets", list)
+}
+
+impl Args {
+    #[tracing::instrument(name = "doctor", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        ImageLogger::init(false)?;
+
+        let config = self.config.load();
+        let ocr_lang = config.as_ref().map_or(DEFAULT_OCR_LANG, Config::ocr_lang);
+        let mut checks = check_libraries(ocr_lang);
+        checks.push(check_assets());
+        let config = match config {
+            Ok(config) => {
+                let profile = self
+                    .config
+                    .profile(&config)
+                    .map_or("no profile", |profile| &profile.description);
+                checks.push(Check::ok(
+                    "config",
+                    format!(
+                        "{} components defined in the config, {profile}",
+                        config.components.len()
+                    ),
+                ));
+                Some(config)
+            }
+            Err(err) => {
+                checks.push(Check::error(
+                    "config",
+                    format!("{err:#}"),
+                    "fix the config file, or write a new one with `setup`",
+                ));
+                None
+            }
+        };
+
+        if let (Some(path), Some(config)) = (&self.sample, &config) {
+            checks.extend(self.check_sample(path, config));
+        }
+
+        for check in &checks {
+            check.print();
+        }
+        let errors = checks.iter().filter(|c| c.status == Status::Error).count();
+        if errors > 0 {
+            eyre::bail!("{errors} problems found");
+        }
+        Ok(())
+    }
+
+    /// Opens the sample and measures the speed of decoding, detection and recognition.
+    fn check_sample(&self, path: &Path, config: &Config) -> Vec<Check> {
+        let mut capture = match self.backend.open(path) {
+            Ok(capture) => capture,
+            Err(err) => {
+                return vec![Check::error(
+                    "sample",
+                    format!("failed to open {}: {err:#}", path.display()),
+                    "check that the file is a video FFmpeg can decode (`metadata` shows its streams), \
+                     or try another `--backend`",
+                )];
+            }
+        };
+        let fps = capture.fps();
+        let fps = *fps.numer() as f64 / *fps.denom() as f64;
+        let mut checks = vec![Check::ok(
+            "sample",
+            format!("{}x{} {fps:.2} fps", capture.width(), capture.height()),
+        )];
+        match self.benchmark(&mut capture, config, fps) {
+            Ok(check) => checks.push(check),
+            Err(err) => checks.push(Check::error(
+                "benchmark",
+                format!("{err:#}"),
+                "the video may be corrupted; try another file or `--backend`",
+            )),
+        }
+        checks
+    }
+
+    fn benchmark(
+        &self,
+        capture: &mut VideoCapture,
+        config: &Config,
+        video_fps: f64,
+    ) -> eyre::Result<Check> {
+        let components =
+            Components::new(capture.rect(), config).ok_or_eyre("invalid frame size")?;
+        let mut tess = Tesseract::is_available()
+            .then(|| Tesseract::new(None, Some(config.ocr_lang())))
+            .and_then(Result::ok);
+        if let Some(tess) = &mut tess {
+            tess.set_timeout(config.pipeline.ocr_timeout());
+        }
+        let mut decoder = capture.range_decoder(TimestampRange::Full)?;
+
+        let (mut decode, mut detect, mut ocr) = (Duration::ZERO, Duration::ZERO, None);
+        let mut frames = 0;
+        while frames < self.frames {
+            let mut frame = Frame::empty();
+            let start = Instant::now();
+            if !decoder.decode_frame(&mut frame)? {
+                break;
+            }
+            decode += start.elapsed();
+
+            let start = Instant::now();
+            let detections = components
+                .iter()
+                .map(|component| components::detect_cascade(&**component, &frame, None))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            detect += start.elapsed();
+
+            // Recognize texts of all components once, regardless of detections
+            if let Some(tess) = tess.as_mut().filter(|_| ocr.is_none()) {
+                let start = Instant::now();
+                let mut recognized = 0;
+                for (component, detection) in components.iter().zip(detections) {
+                    let payload = match detection {
+                        Detection::Found(payload) | Detection::Possible(payload) => payload,
+                        Detection::Absent => None,
+                    };
+                    component.extract_text(tess, &frame, payload)?;
+                    recognized += 1;
+                }
+                ocr = Some(start.elapsed() / recognized.max(1));
+            }
+            frames += 1;
+        }
+        if frames == 0 {
+            eyre::bail!("no frames are decoded");
+        }
+
+        let per_sec = |elapsed: Duration| frames as f64 / elapsed.as_secs_f64().max(1e-9);
+        let throughput = per_sec(decode + detect);
+        let mut detail = format!(
+            "decode {:.0} fps, detect {:.0} fps",
+            per_sec(decode),
+            per_sec(detect)
+        );
+        if let Some(ocr) = ocr {
+            detail += &format!(", OCR {:.0} ms per component", ocr.as_secs_f64() * 1000.0);
+        }
+        let check = if throughput < video_fps {
+            Check::warning(
+                "benchmark",
+                detail,
+                "the analysis is slower than real time and `watch` drops frames; \
+                 run `tune-performance` to use more threads, or `analyze --parallel-segments`",
+            )
+        } else {
+            Check::ok("benchmark", detail)
+        };
+        Ok(check)
+    }
+}
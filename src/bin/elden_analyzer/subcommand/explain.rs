@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{self, eyre, OptionExt as _};
+use elden_analyzer::{
+    components::{self, Components, Detection},
+    image_process::tesseract::Tesseract,
+    util::{Explainer, ImageLogger},
+};
+use elden_analyzer_kernel::types::time::{Timestamp, TimestampRange};
+use elden_analyzer_video::capture::Frame;
+
+use super::{BackendArgs, ConfigArgs, DumpImagesArgs};
+
+/// Explain how a component is detected and recognized in a frame
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// The input file to process
+    file: PathBuf,
+    /// The frame to process
+    timestamp: Timestamp,
+    /// Name of the component to explain
+    component: String,
+    /// Display intermediate images numbered as in the explanation
+    #[clap(long, default_value = "false")]
+    display_image: bool,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    #[clap(flatten)]
+    dump_images: DumpImagesArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "explain", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        self.dump_images.init_logger(self.display_image)?;
+
+        let config = self.config.load()?;
+        let mut capture = self.backend.open(&self.file)?;
+        let components =
+            Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")?;
+        let component = components
+            .iter()
+            .find(|c| c.name() == self.component)
+            .ok_or_else(|| {
+                let names = components.iter().map(|c| c.name()).collect::<Vec<_>>();
+                eyre!(
+                    "unknown component `{}` (available: {})",
+                    self.component,
+                    names.join(", ")
+                )
+            })?;
+
+        let mut frame = Frame::empty();
+        let mut decoder = capture.range_decoder(TimestampRange::Single(self.timestamp))?;
+        if !decoder.decode_frame(&mut frame)? {
+            eyre::bail!("no frame at {}", self.timestamp);
+        }
+
+        let explainer = Explainer::get();
+        explainer.start(format!("{} [{}]", component.name(), frame.position()));
+
+        let detection = explainer.scope(
+            || "detect".into(),
+            || components::detect_cascade(&**component, &frame, None),
+        )?;
+        explainer.note(|| format!("detection: {}", detection.kind()));
+
+        let payload = match detection {
+            Detection::Found(payload) | Detection::Possible(payload) => Some(payload),
+            Detection::Absent => None,
+        };
+        match payload {
+            Some(_) if !Tesseract::is_available() => {
+                explainer.note(|| "OCR is not supported in this build".into());
+            }
+            Some(payload) => {
+                let mut tess = Tesseract::new(None, Some(config.ocr_lang()))?;
+                tess.set_timeout(config.pipeline.ocr_timeout());
+                let _subject =
+                    ImageLogger::enter_subject(frame.position().index(), component.name());
+                let texts = explainer.scope(
+                    || "extract text".into(),
+                    || component.extract_text(&mut tess, &frame, payload),
+                )?;
+                explainer.note(|| format!("texts: {texts}"));
+            }
+            None => explainer.note(|| "text is not extracted from absent component".into()),
+        }
+
+        let tree = explainer.finish().unwrap();
+        print!("{tree}");
+        ImageLogger::get().display(&format!("explain [{}]", frame.position()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write as _,
+    path::PathBuf,
+};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer::{
+    components::{Component, Components},
+    io::{
+        event_id::EventId,
+        spans::{self, Span},
+    },
+    util,
+    video_capture::FrameExt as _,
+};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp, TimestampRange};
+use elden_analyzer_video::capture::Frame;
+use num_rational::Ratio;
+
+use super::{BackendArgs, ConfigArgs, CorrectionsArgs};
+
+/// Export component crops labeled by span files as a training dataset
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Input video file
+    input: PathBuf,
+    /// Span file of the input video (output of `analyze --output-span`)
+    #[clap(long)]
+    spans: PathBuf,
+    /// Output dataset directory
+    #[clap(long)]
+    output_dir: PathBuf,
+    /// Number of positive samples taken from each span
+    #[clap(long, default_value = "5")]
+    samples_per_span: usize,
+    /// Process only the components or groups of them (default: all components)
+    #[clap(long, value_delimiter = ',')]
+    filter: Vec<String>,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "export_training_data", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = spans::read_file(&self.spans)?;
+        self.corrections.apply(&mut spans)?;
+        let config = self.config.load()?;
+
+        let mut capture = self.backend.open(&self.input)?;
+        let components =
+            Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")?;
+        let filter = config.component_filter(&self.filter)?;
+        let video_end = Timestamp::new(capture.duration().as_ratio());
+        let sec_per_frame = capture.sec_per_frame();
+
+        let mut samples = BTreeMap::<Timestamp, Vec<Sample>>::new();
+        for component in &components {
+            let name = component.name();
+            if !filter.matches(name) {
+                continue;
+            }
+
+            let spans = spans
+                .iter()
+                .filter(|span| span.name == name || span.is_skipped())
+                .collect::<Vec<_>>();
+
+            // Possible spans are not reliable enough to be used as positive samples,
+            // but they are still excluded from negative samples, as well as skipped intervals.
+            let mut num_positives = 0;
+            for span in spans
+                .iter()
+                .filter(|span| !span.is_possible() && !span.is_skipped())
+            {
+                for ts in sample_evenly(
+                    &[(span.start, span.end)],
+                    self.samples_per_span,
+                    sec_per_frame,
+                ) {
+                    samples.entry(ts).or_default().push(Sample {
+                        component: &**component,
+                        label: Label::Positive,
+                        text: &span.text,
+                        event: span.id.as_ref(),
+                    });
+                    num_positives += 1;
+                }
+            }
+
+            let gaps = negative_ranges(&spans, video_end);
+            for ts in sample_evenly(&gaps, num_positives, sec_per_frame) {
+                samples.entry(ts).or_default().push(Sample {
+                    component: &**component,
+                    label: Label::Negative,
+                    text: "",
+                    event: None,
+                });
+            }
+
+            tracing::info!(name, num_positives, "collected samples");
+        }
+
+        let mut labels = util::create_file(&self.output_dir.join("labels.tsv"))?;
+        writeln!(labels, "path\tcomponent\tlabel\ttimestamp\ttext\tevent")?;
+
+        let mut counter = HashMap::new();
+        let mut frame = Frame::empty();
+        for (ts, samples) in &samples {
+            let mut decoder = capture.range_decoder(TimestampRange::Single(*ts))?;
+            if !decoder.decode_frame(&mut frame)? {
+                tracing::warn!(%ts, "failed to decode frame");
+                continue;
+            }
+
+            for sample in samples {
+                let name = sample.component.name();
+                let label = sample.label.as_str();
+                let image = frame.to_rgb_image_within(sample.component.rect())?;
+
+                let idx = counter.entry((name, label)).or_insert(0);
+                let path = format!("{name}/{label}/{idx:06}.png");
+                *idx += 1;
+
+                let output_path = util::long_path(&self.output_dir.join(&path)).into_owned();
+                fs::create_dir_all(output_path.parent().unwrap())?;
+                image.save(&output_path)?;
+                writeln!(
+                    labels,
+                    "{path}\t{name}\t{label}\t{ts}\t{text}\t{event}",
+                    ts = frame.position().timestamp(),
+                    text = sample.text,
+                    event = sample.event.map(ToString::to_string).unwrap_or_default(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Label {
+    Positive,
+    Negative,
+}
+
+impl Label {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Label::Positive => "positive",
+            Label::Negative => "negative",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Sample<'a> {
+    component: &'a dyn Component,
+    label: Label,
+    text: &'a str,
+    /// Event of the span the sample is taken from
+    event: Option<&'a EventId>,
+}
+
+/// Returns the ranges of the video outside `spans` and margins around them, in order.
+fn negative_ranges(spans: &[&Span], video_end: Timestamp) -> Vec<(Timestamp, Timestamp)> {
+    // Keep a margin around spans, since frames near the span boundaries may be fading in/out
+    let margin = Ratio::from_integer(1);
+
+    let mut spans = spans
+        .iter()
+        .map(|span| (span.start.as_ratio() - margin, span.end.as_ratio() + margin))
+        .collect::<Vec<_>>();
+    spans.sort();
+
+    let mut ranges = vec![];
+    let mut cur = Ratio::ZERO;
+    for (start, end) in spans {
+        if start > cur {
+            ranges.push((Timestamp::new(cur), Timestamp::new(start)));
+        }
+        cur = cur.max(end);
+    }
+    if cur < video_end.as_ratio() {
+        ranges.push((Timestamp::new(cur), video_end));
+    }
+    ranges
+}
+
+/// Returns `count` timestamps evenly spaced over `ranges` sorted in order.
+///
+/// At most one timestamp is taken from each frame lasting `sec_per_frame`,
+/// so that fewer timestamps are returned than `count` if the ranges are shorter than `count` frames.
+fn sample_evenly(
+    ranges: &[(Timestamp, Timestamp)],
+    count: usize,
+    sec_per_frame: Duration,
+) -> Vec<Timestamp> {
+    let total = ranges
+        .iter()
+        .map(|(start, end)| (*end - *start).as_ratio())
+        .sum::<Ratio<i64>>();
+    if count == 0 || total <= Ratio::ZERO {
+        return vec![];
+    }
+
+    let frame_index = |ts: Ratio<i64>| (ts / sec_per_frame.as_ratio()).floor();
+    let mut samples = vec![];
+    let mut last_frame = None;
+    for k in 0..count as i64 {
+        let mut offset = total * Ratio::new(2 * k + 1, 2 * count as i64);
+        for (start, end) in ranges {
+            let len = (*end - *start).as_ratio();
+            if offset < len {
+                let ts = start.as_ratio() + offset;
+                if last_frame.replace(frame_index(ts)) != Some(frame_index(ts)) {
+                    samples.push(Timestamp::new(ts));
+                }
+                break;
+            }
+            offset -= len;
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(sec: i64) -> Timestamp {
+        Timestamp::new(Ratio::from_integer(sec))
+    }
+
+    fn span(start: i64, end: i64) -> Span {
+        format!("{}-{} a (x)", ts(start), ts(end)).parse().unwrap()
+    }
+
+    #[test]
+    fn negative_ranges_around_spans() {
+        let negatives =
+            |spans: &[Span], end: i64| negative_ranges(&spans.iter().collect::<Vec<_>>(), ts(end));
+        assert_eq!(negatives(&[], 10), [(ts(0), ts(10))]);
+        assert_eq!(
+            negatives(&[span(5, 6)], 10),
+            [(ts(0), ts(4)), (ts(7), ts(10))]
+        );
+        // Touching and overlapping spans leave no gap between them
+        assert_eq!(
+            negatives(&[span(5, 6), span(6, 7)], 20),
+            [(ts(0), ts(4)), (ts(8), ts(20))]
+        );
+        assert_eq!(
+            negatives(&[span(9, 12), span(5, 10)], 20),
+            [(ts(0), ts(4)), (ts(13), ts(20))]
+        );
+        // Margins are within the video
+        assert_eq!(negatives(&[span(0, 3)], 10), [(ts(4), ts(10))]);
+        assert_eq!(negatives(&[span(5, 10)], 10), [(ts(0), ts(4))]);
+        assert_eq!(negatives(&[span(1, 9)], 10), []);
+    }
+
+    #[test]
+    fn sample_evenly_over_ranges() {
+        let sec = Duration::new(Ratio::from_integer(1));
+        let half = |sec: i64| Timestamp::new(Ratio::new(2 * sec + 1, 2));
+        assert_eq!(sample_evenly(&[(ts(0), ts(4))], 2, sec), [ts(1), ts(3)]);
+        assert_eq!(
+            sample_evenly(&[(ts(0), ts(2)), (ts(6), ts(8))], 4, sec),
+            [half(0), half(1), half(6), half(7)]
+        );
+        assert_eq!(sample_evenly(&[(ts(0), ts(4))], 0, sec), []);
+        assert_eq!(sample_evenly(&[], 3, sec), []);
+        // Each frame is sampled at most once
+        assert_eq!(
+            sample_evenly(&[(ts(0), ts(2))], 10, sec),
+            [
+                Timestamp::new(Ratio::new(1, 10)),
+                Timestamp::new(Ratio::new(11, 10))
+            ]
+        );
+    }
+}
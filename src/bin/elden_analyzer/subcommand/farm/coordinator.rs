@@ -0,0 +1,216 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    io::{BufReader, BufWriter, Write as _},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use color_eyre::eyre::{self, OptionExt as _};
+use elden_analyzer::{
+    io::{
+        self,
+        spans::{self, Span},
+    },
+    util,
+};
+use elden_analyzer_kernel::types::time::Timestamp;
+
+use crate::subcommand::BackendArgs;
+
+use super::{path_field, Request, Response, Task};
+
+/// Split videos into chunks and distribute them to workers connected over TCP
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Input files to process.
+    /// Workers must access them at the same paths.
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Address to listen for workers on.
+    /// Only local workers can connect by default; pass e.g. `0.0.0.0:7878` to accept workers on other machines.
+    /// The protocol has no authentication, so expose it only to trusted networks.
+    #[clap(long, default_value = "127.0.0.1:7878")]
+    listen: String,
+    /// Duration of each chunk
+    #[clap(long, default_value = "00:30:00")]
+    chunk_duration: Timestamp,
+    /// Output directory of span files, named after the input files
+    #[clap(long)]
+    output_dir: PathBuf,
+    #[clap(flatten)]
+    backend: BackendArgs,
+}
+
+/// Progress of chunks shared by connections.
+#[derive(Debug, Default)]
+struct State {
+    pending: VecDeque<Task>,
+    running: usize,
+    results: BTreeMap<usize, Vec<Span>>,
+    failures: BTreeMap<usize, String>,
+}
+
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+impl Args {
+    #[tracing::instrument(name = "coordinator", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut tasks = vec![];
+        // Task IDs of each input
+        let mut chunks = vec![];
+        for path in &self.inputs {
+            // Inputs which cannot be sent to workers are rejected before analyzing any of them
+            path_field(path)?;
+            let ids = self.split(path, &mut tasks)?;
+            tracing::info!(path = %path.display(), chunks = ids.len(), "split into chunks");
+            chunks.push(ids);
+        }
+        fs::create_dir_all(&self.output_dir)?;
+
+        let shared: Shared = Arc::new((
+            Mutex::new(State {
+                pending: tasks.into(),
+                ..State::default()
+            }),
+            Condvar::new(),
+        ));
+
+        let listener = TcpListener::bind(&self.listen)?;
+        tracing::info!(listen = self.listen, "waiting for workers");
+        thread::spawn({
+            let shared = Arc::clone(&shared);
+            move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let shared = Arc::clone(&shared);
+                    thread::spawn(move || {
+                        let peer = stream.peer_addr().ok();
+                        let _span = tracing::info_span!("worker", ?peer).entered();
+                        if let Err(err) = serve(stream, &shared) {
+                            tracing::warn!(?err, "worker disconnected");
+                        }
+                    });
+                }
+            }
+        });
+
+        let (lock, cvar) = &*shared;
+        let mut state = cvar
+            .wait_while(lock.lock().unwrap(), |state| {
+                !state.pending.is_empty() || state.running > 0
+            })
+            .unwrap();
+
+        for (path, ids) in self.inputs.iter().zip(&chunks) {
+            if let Some((id, message)) = ids
+                .iter()
+                .find_map(|id| Some((id, state.failures.get(id)?)))
+            {
+                tracing::error!(path = %path.display(), id, message, "failed to analyze a chunk");
+                continue;
+            }
+            let spans = spans::join_chunks(
+                ids.iter()
+                    .map(|id| state.results.remove(id).unwrap_or_default()),
+            );
+            // Names of videos may not be valid on the file system of the output directory
+            let stem = path.file_stem().ok_or_eyre("input is not a file")?;
+            let name = util::sanitize_file_name(&stem.to_string_lossy());
+            let output = self.output_dir.join(format!("{name}.txt"));
+            let mut writer = BufWriter::new(util::create_file(&output)?);
+            io::write_header(&mut writer, None)?;
+            for span in &spans {
+                writeln!(writer, "{span}")?;
+            }
+            writer.flush()?;
+            tracing::info!(output = %output.display(), spans = spans.len(), "written");
+        }
+
+        if !state.failures.is_empty() {
+            eyre::bail!("failed to analyze {} chunks", state.failures.len());
+        }
+        Ok(())
+    }
+
+    /// Appends chunks of the input to `tasks`, and returns their IDs.
+    ///
+    /// Chunks are aligned to frames, so spans crossing their boundaries can be joined.
+    fn split(&self, path: &Path, tasks: &mut Vec<Task>) -> eyre::Result<Vec<usize>> {
+        let capture = self.backend.open(path)?;
+        let sec_per_frame = capture.sec_per_frame().as_ratio();
+        let frames = capture.frames();
+        let chunk_frames = (self.chunk_duration.as_ratio() / sec_per_frame)
+            .ceil()
+            .to_integer()
+            .max(1) as usize;
+
+        let mut ids = vec![];
+        for start in (0..frames).step_by(chunk_frames) {
+            let end = (start + chunk_frames).min(frames);
+            let id = tasks.len();
+            tasks.push(Task {
+                id,
+                start: Timestamp::new(sec_per_frame * start as i64),
+                end: Timestamp::new(sec_per_frame * end as i64),
+                path: path.to_owned(),
+            });
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Assigns chunks to a worker until all chunks are analyzed.
+fn serve(stream: TcpStream, shared: &Shared) -> eyre::Result<()> {
+    let (lock, cvar) = &**shared;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    loop {
+        let task = {
+            let mut state = cvar
+                .wait_while(lock.lock().unwrap(), |state| {
+                    state.pending.is_empty() && state.running > 0
+                })
+                .unwrap();
+            let Some(task) = state.pending.pop_front() else {
+                break;
+            };
+            state.running += 1;
+            task
+        };
+
+        let response = Request::Task(task.clone())
+            .write(&mut writer)
+            .and_then(|()| Response::read(&mut reader));
+        let mut state = lock.lock().unwrap();
+        state.running -= 1;
+        match response {
+            Ok(Some(Response::Spans(id, spans))) if id == task.id => {
+                tracing::info!(id, spans = spans.len(), "chunk analyzed");
+                state.results.insert(id, spans);
+            }
+            Ok(Some(Response::Error(id, message))) if id == task.id => {
+                tracing::warn!(id, message, "worker failed to analyze a chunk");
+                state.failures.insert(id, message);
+            }
+            response => {
+                // Assign the chunk to another worker
+                state.pending.push_back(task);
+                cvar.notify_all();
+                drop(state);
+                response?.ok_or_eyre("connection closed")?;
+                eyre::bail!("unexpected response");
+            }
+        }
+        cvar.notify_all();
+    }
+
+    Request::Done.write(&mut writer)?;
+    Ok(())
+}
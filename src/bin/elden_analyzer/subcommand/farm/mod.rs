@@ -0,0 +1,209 @@
+//! Processing videos across machines.
+//!
+//! A coordinator splits videos into chunks and assigns them to workers connected over TCP.
+//! Messages are lines of tab-separated fields:
+//!
+//! ```text
+//! coordinator -> worker: task ID START END PATH
+//!                        done
+//! worker -> coordinator: spans ID COUNT      (followed by COUNT lines of spans)
+//!                        error ID MESSAGE
+//! ```
+//!
+//! Paths of videos are sent as is, so workers must access them at the same paths as the coordinator.
+//! Paths which are not valid UTF-8 or contain tabs or line breaks cannot be sent, and are rejected by the coordinator.
+
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, bail, eyre, OptionExt as _};
+use elden_analyzer::io::spans::Span;
+use elden_analyzer_kernel::types::time::Timestamp;
+
+pub(super) mod coordinator;
+pub(super) mod worker;
+
+/// Chunk of a video assigned to a worker.
+#[derive(Debug, Clone)]
+struct Task {
+    id: usize,
+    start: Timestamp,
+    end: Timestamp,
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+enum Request {
+    Task(Task),
+    Done,
+}
+
+#[derive(Debug)]
+enum Response {
+    Spans(usize, Vec<Span>),
+    Error(usize, String),
+}
+
+/// Returns the field of `path` in messages, failing if it cannot be sent.
+fn path_field(path: &Path) -> eyre::Result<&str> {
+    let field = path
+        .to_str()
+        .ok_or_eyre(format!("path is not valid UTF-8: {}", path.display()))?;
+    if field.contains(['\t', '\n', '\r']) {
+        bail!("path contains tabs or line breaks: {field:?}");
+    }
+    Ok(field)
+}
+
+impl Request {
+    fn read(reader: &mut impl BufRead) -> eyre::Result<Option<Self>> {
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let request = match fields[..] {
+            ["task", id, start, end, path] => Self::Task(Task {
+                id: id.parse()?,
+                start: start.parse()?,
+                end: end.parse()?,
+                path: path.into(),
+            }),
+            ["done"] => Self::Done,
+            _ => eyre::bail!("invalid request: {line:?}"),
+        };
+        Ok(Some(request))
+    }
+
+    fn write(&self, writer: &mut impl Write) -> eyre::Result<()> {
+        match self {
+            Self::Task(Task {
+                id,
+                start,
+                end,
+                path,
+            }) => {
+                let path = path_field(path)?;
+                writeln!(writer, "task\t{id}\t{start}\t{end}\t{path}")?;
+            }
+            Self::Done => writeln!(writer, "done")?,
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Response {
+    fn read(reader: &mut impl BufRead) -> eyre::Result<Option<Self>> {
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        let fields = line.splitn(3, '\t').collect::<Vec<_>>();
+        let response = match fields[..] {
+            ["spans", id, count] => {
+                let count = count.parse::<usize>()?;
+                let spans = (0..count)
+                    .map(|_| -> eyre::Result<Span> {
+                        let line = read_line(reader)?.ok_or_else(|| eyre!("missing spans"))?;
+                        Ok(line.parse()?)
+                    })
+                    .collect::<eyre::Result<_>>()?;
+                Self::Spans(id.parse()?, spans)
+            }
+            ["error", id, message] => Self::Error(id.parse()?, message.to_owned()),
+            _ => eyre::bail!("invalid response: {line:?}"),
+        };
+        Ok(Some(response))
+    }
+
+    fn write(&self, writer: &mut impl Write) -> eyre::Result<()> {
+        match self {
+            Self::Spans(id, spans) => {
+                writeln!(writer, "spans\t{id}\t{}", spans.len())?;
+                for span in spans {
+                    writeln!(writer, "{span}")?;
+                }
+            }
+            Self::Error(id, message) => {
+                let message = message.replace(['\n', '\r'], " ");
+                writeln!(writer, "error\t{id}\t{message}")?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a line without the line terminator, or `None` at the end of the stream.
+fn read_line(reader: &mut impl BufRead) -> eyre::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut buf = vec![];
+        let task = Task {
+            id: 3,
+            start: "00:30:00".parse().unwrap(),
+            end: "01:00:00".parse().unwrap(),
+            path: "videos/run 1.mp4".into(),
+        };
+        Request::Task(task).write(&mut buf).unwrap();
+        Request::Done.write(&mut buf).unwrap();
+        let mut reader = &buf[..];
+        let Some(Request::Task(task)) = Request::read(&mut reader).unwrap() else {
+            panic!("not a task");
+        };
+        assert_eq!((task.id, task.path), (3, "videos/run 1.mp4".into()));
+        assert_eq!(task.end.to_string(), "01:00:00.000");
+        assert!(matches!(
+            Request::read(&mut reader).unwrap(),
+            Some(Request::Done)
+        ));
+        assert!(Request::read(&mut reader).unwrap().is_none());
+
+        let mut buf = vec![];
+        let span = "00:00:01.000-00:00:02.000 a (x)".parse::<Span>().unwrap();
+        Response::Spans(3, vec![span.clone()])
+            .write(&mut buf)
+            .unwrap();
+        Response::Error(4, "no\nframe".into())
+            .write(&mut buf)
+            .unwrap();
+        let mut reader = &buf[..];
+        let Some(Response::Spans(3, spans)) = Response::read(&mut reader).unwrap() else {
+            panic!("not spans");
+        };
+        assert_eq!(spans, [span]);
+        let Some(Response::Error(4, message)) = Response::read(&mut reader).unwrap() else {
+            panic!("not an error");
+        };
+        assert_eq!(message, "no frame");
+    }
+
+    #[test]
+    fn unsendable_paths() {
+        let task = |path: &str| Task {
+            id: 0,
+            start: Timestamp::default(),
+            end: Timestamp::default(),
+            path: path.into(),
+        };
+        for path in ["run\t1.mp4", "run\n1.mp4", "run\r1.mp4"] {
+            let mut buf = vec![];
+            assert!(Request::Task(task(path)).write(&mut buf).is_err());
+            assert!(buf.is_empty());
+        }
+    }
+}
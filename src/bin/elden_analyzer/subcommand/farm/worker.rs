@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    io::{BufReader, BufWriter},
+    net::TcpStream,
+    process,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    config::Config,
+    io::{
+        buckets,
+        event_id::SourceId,
+        spans,
+        timeline::{Layout, UncertainCells},
+    },
+    util::ImageLogger,
+};
+use elden_analyzer_kernel::types::time::{Duration, TimestampRange};
+use elden_analyzer_video::capture::{HwAccel, OpenOptions};
+
+use crate::subcommand::{
+    analyze::{self, IgtArgs, Options},
+    BackendArgs, ConfigArgs,
+};
+
+use super::{Request, Response, Task};
+
+/// Analyze chunks of videos assigned by a coordinator
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Address of the coordinator (`HOST:PORT`)
+    #[clap(long)]
+    connect: String,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Decode with hardware acceleration (`auto`, `vaapi`, `nvdec` or `videotoolbox`).
+    /// Falls back to decoding by the CPU if it is not available.
+    #[clap(long)]
+    hwaccel: Option<HwAccel>,
+    /// Number of frames before each span to recognize texts in
+    #[clap(long, default_value = "0")]
+    lead_in: usize,
+    /// Number of frames after a found frame within which possible or found frames continue the span
+    #[clap(long, default_value = "1")]
+    promote_window: usize,
+    #[clap(flatten)]
+    igt: IgtArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "worker", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        ImageLogger::init(false)?;
+
+        let config = self.config.load()?;
+        let stream = TcpStream::connect(&self.connect)?;
+        tracing::info!(coordinator = self.connect, "connected");
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        while let Some(request) = Request::read(&mut reader)? {
+            let task = match request {
+                Request::Task(task) => task,
+                Request::Done => break,
+            };
+            let _span = tracing::info_span!("task", id = task.id).entered();
+            let response = match self.analyze(&config, &task) {
+                // Spans of a cancelled chunk are partial, so the chunk is reported as failed
+                Ok(_) if crate::cancellation().is_cancelled() => {
+                    Response::Error(task.id, "cancelled".to_owned())
+                }
+                Ok(spans) => Response::Spans(task.id, spans),
+                Err(err) => {
+                    tracing::error!(?err, "failed to analyze the chunk");
+                    Response::Error(task.id, format!("{err:#}"))
+                }
+            };
+            response.write(&mut writer)?;
+            if crate::cancellation().is_cancelled() {
+                eyre::bail!("cancelled");
+            }
+        }
+
+        tracing::info!("all chunks are analyzed");
+        Ok(())
+    }
+
+    fn analyze(&self, config: &Config, task: &Task) -> eyre::Result<Vec<spans::Span>> {
+        let output = crate::tmp_dir().join(format!(
+            "elden-analyzer-worker-{}-{}.txt",
+            process::id(),
+            task.id
+        ));
+        let options = Options {
+            source: SourceId::of_file(&task.path)?,
+            timestamp: TimestampRange::Range(task.start, task.end),
+            output_span: Some(&output),
+            output_tsv: None,
+            output_json: None,
+            tsv_layout: Layout::default(),
+            tsv_uncertain: UncertainCells::default(),
+            output_pickups: None,
+            output_buckets: None,
+            bucket_window: Duration::new(buckets::DEFAULT_WINDOW),
+            output_srt: None,
+            output_vtt: None,
+            translate_items: None,
+            only_categories: vec![],
+            environment: None,
+            output_scores: None,
+            exact_pts: false,
+            lead_in: self.lead_in,
+            promote_window: self.promote_window,
+            sample_rate: None,
+            rescan: false,
+            igt: self.igt.builder(),
+            detection_only: false,
+            live: None,
+            preview: None,
+            stop: None,
+            index: None,
+            emit_events: None,
+        };
+        let open = OpenOptions {
+            backend: self.backend.kind(),
+            hwaccel: self.hwaccel,
+            image_fps: None,
+        };
+        let result = analyze::process_file(&task.path, &open, config, options)
+            .and_then(|_summary| spans::read_file(&output));
+        let _ = fs::remove_file(&output);
+        result
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer::{io::filter::Filter, items::ItemCategory, util};
+use elden_analyzer_kernel::types::time::TimestampRange;
+use regex::Regex;
+
+use super::GroupsArgs;
+
+/// Filter spans in a span file or a JSON output of `analyze`
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span file or JSON output (outputs of `analyze --output-span`, `--output-pickups` or `--output-json`)
+    input: PathBuf,
+    /// Output file of the kept spans in the same format as the input (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Keep only spans of the components or groups of them (`side_item` matches side items of all rows,
+    /// and `NAME*` matches components whose names start with `NAME`)
+    #[clap(long, value_delimiter = ',')]
+    component: Vec<String>,
+    /// Keep only spans whose least confident line has at least this confidence (0 to 1), only for JSON outputs
+    #[clap(long)]
+    min_confidence: Option<f64>,
+    /// Keep only spans overlapping the times (`START-END`, `START-`, `-END` or a single timestamp)
+    #[clap(long)]
+    time: Option<TimestampRange>,
+    /// Keep only spans of items in the categories, such as `weapons,talismans`
+    #[clap(long, value_delimiter = ',')]
+    category: Vec<ItemCategory>,
+    /// Keep only spans whose texts match the regular expression
+    #[clap(long)]
+    text: Option<Regex>,
+    /// Drop spans whose texts are not reliably recognized
+    #[clap(long)]
+    exclude_possible: bool,
+    #[clap(flatten)]
+    groups: GroupsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "filter", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let filter = Filter {
+            components: self.groups.component_filter(&self.component)?,
+            min_confidence: self.min_confidence,
+            time: self.time,
+            categories: self.category.clone(),
+            text: self.text.clone(),
+            exclude_possible: self.exclude_possible,
+        };
+        let input = fs::read_to_string(&self.input)
+            .wrap_err_with(|| format!("failed to read file: {}", self.input.display()))?;
+        // JSON outputs have no comment lines, and their lines are objects
+        let is_json = input
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| line.starts_with('{'));
+        if !is_json && filter.min_confidence.is_some() {
+            eyre::bail!(
+                "`--min-confidence` requires a JSON output, since span files have no confidences"
+            );
+        }
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        let stats = if is_json {
+            filter.filter_json(input.as_bytes(), &mut output)?
+        } else {
+            filter.filter_spans(input.as_bytes(), &mut output)?
+        };
+        tracing::info!(read = stats.read, kept = stats.kept, "filtered spans");
+        Ok(())
+    }
+}
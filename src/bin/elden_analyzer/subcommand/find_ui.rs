@@ -1,15 +1,23 @@
 use std::path::PathBuf;
 
 use color_eyre::eyre::{self, OptionExt};
-use elden_analyzer::{components::Components, util::ImageLogger};
+use elden_analyzer::{
+    components::{self, ComponentFilter, Components},
+    util::ImageLogger,
+};
 use elden_analyzer_kernel::types::time::TimestampRange;
-use elden_analyzer_video::capture::{Frame, VideoCapture};
+use elden_analyzer_video::capture::{
+    Frame, FrameCache, ImageSequenceBackend, OpenOptions, VideoCapture,
+};
+use num_rational::Ratio;
 use tracing::info;
 
+use super::{BackendArgs, ConfigArgs, DumpImagesArgs};
+
 /// Analyze the video files to extract information
 #[derive(clap::Parser, Debug)]
 pub struct Args {
-    /// The input file to process
+    /// The input file to process: a video, an image, a directory of images or a pattern such as `frame%05d.png`
     file: PathBuf,
     /// The frame to process
     #[clap(default_value = "-")]
@@ -17,18 +25,48 @@ pub struct Args {
     /// Display output image
     #[clap(long, default_value = "false")]
     display_image: bool,
+    /// Process only the components or groups of them (default: all components)
     #[clap(long, value_delimiter = ',')]
-    filter: Option<Vec<String>>,
+    filter: Vec<String>,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Frame rate of inputs of images (`N` or `N/D`), which have no timestamps [default: 30]
+    #[clap(long)]
+    image_fps: Option<Ratio<i64>>,
+    /// Directory caching decoded frames, so that repeated runs on the same frames skip decoding
+    #[clap(long)]
+    frame_cache: Option<PathBuf>,
+    #[clap(flatten)]
+    dump_images: DumpImagesArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
 }
 
 impl Args {
     #[tracing::instrument(name = "find_ui", skip_all)]
     pub(crate) fn run(&self) -> eyre::Result<()> {
-        ImageLogger::init(self.display_image)?;
+        self.dump_images.init_logger(self.display_image)?;
 
-        let mut capture =
-            tracing::trace_span!("open").in_scope(|| VideoCapture::open(&self.file))?;
-        let components = Components::new(capture.rect()).ok_or_eyre("invalid frame size")?;
+        let config = self.config.load()?;
+        let mut capture = tracing::trace_span!("open").in_scope(|| {
+            let options = OpenOptions {
+                backend: self.backend.kind(),
+                hwaccel: None,
+                image_fps: self.image_fps,
+            };
+            VideoCapture::open_with(&self.file, &options)
+        })?;
+        // Images are read as quickly as cached frames
+        if let Some(dir) = self
+            .frame_cache
+            .as_deref()
+            .filter(|_| !ImageSequenceBackend::is_image_sequence(&self.file))
+        {
+            capture.set_frame_cache(Some(FrameCache::open(dir, &self.file)?));
+        }
+        let components =
+            Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")?;
+        let filter = config.component_filter(&self.filter)?;
 
         let mut frame = Frame::empty();
         for ts_range in &self.timestamp {
@@ -36,7 +74,7 @@ impl Args {
             while tracing::trace_span!("decode-frame")
                 .in_scope(|| decoder.decode_frame(&mut frame))?
             {
-                process_frame(&components, &frame, self.filter.as_deref())?;
+                process_frame(&components, &frame, &filter)?;
             }
         }
 
@@ -48,20 +86,18 @@ impl Args {
 fn process_frame(
     components: &Components,
     frame: &Frame,
-    filter: Option<&[String]>,
+    filter: &ComponentFilter,
 ) -> eyre::Result<()> {
     let logger = ImageLogger::get();
 
     for component in components {
-        if let Some(filter) = filter {
-            if !filter.iter().any(|s| *s == component.name()) {
-                continue;
-            }
+        if !filter.matches(component.name()) {
+            continue;
         }
 
         tracing::info_span!("detect-ui", name = component.name()).in_scope(
             || -> eyre::Result<()> {
-                let result = component.detect(frame)?;
+                let result = components::detect_cascade(&**component, frame, None)?;
                 info!(result = %result.kind());
                 Ok(())
             },
@@ -0,0 +1,89 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    inference::{FastTravel, Rule, Rules},
+    io::{self as output_io, spans},
+    util,
+};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+
+use super::CorrectionsArgs;
+
+/// Infer events such as fast travels from spans of components
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span file (output of `analyze --output-span`)
+    spans: PathBuf,
+    /// Output span file of the inferred events (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Rule file defining events of sequences of spans, inferred in addition to fast travels
+    #[clap(long)]
+    rules: Option<PathBuf>,
+    /// Component of the map screen
+    #[clap(long, default_value = "map")]
+    map: String,
+    /// Component of the loading screen
+    #[clap(long, default_value = "loading")]
+    loading: String,
+    /// Components whose texts are the destination of fast travels
+    #[clap(long = "destination", default_values = ["area_banner", "grace_rest"])]
+    destinations: Vec<String>,
+    /// Maximum time between the map and the loading screen of a fast travel
+    #[clap(long, default_value = "00:00:02")]
+    max_gap: Timestamp,
+    /// Maximum time between the loading screen and the destination of a fast travel
+    #[clap(long, default_value = "00:00:30")]
+    max_delay: Timestamp,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "infer_events", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = spans::read_file(&self.spans)?;
+        self.corrections.apply(&mut spans)?;
+        spans.sort_by_key(|span| span.start);
+
+        let fast_travel = FastTravel {
+            map: self.map.clone(),
+            loading: self.loading.clone(),
+            destinations: self.destinations.clone(),
+            max_gap: Duration::new(self.max_gap.as_ratio()),
+            max_delay: Duration::new(self.max_delay.as_ratio()),
+        };
+        let rules = self.rules.as_deref().map(Rules::load).transpose()?;
+        let mut events = fast_travel.infer(&spans);
+        let unknown = events.iter().filter(|event| event.is_possible()).count();
+        tracing::info!(
+            events = events.len(),
+            unknown_destinations = unknown,
+            "inferred fast travels"
+        );
+        for rule in rules.iter().flat_map(|rules| &rules.rules) {
+            let inferred = rule.infer(&spans);
+            tracing::info!(
+                rule = rule.name,
+                events = inferred.len(),
+                "inferred events of rule"
+            );
+            events.extend(inferred);
+        }
+        events.sort_by_key(|event| event.start);
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        output_io::write_header(&mut output, None)?;
+        for event in &events {
+            writeln!(output, "{event}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    io::{self, BufRead as _, Write as _},
+    path::PathBuf,
+};
+
+use color_eyre::eyre::{self, eyre, OptionExt as _};
+use elden_analyzer::{
+    components::{self, Component, Components, Detection},
+    config::Config,
+    image_process::tesseract::Tesseract,
+    util::ImageLogger,
+    video_capture::FrameExt as _,
+};
+use elden_analyzer_kernel::types::time::{Timestamp, TimestampRange};
+use elden_analyzer_video::capture::{Frame, VideoCapture};
+
+use super::{BackendArgs, ConfigArgs};
+
+/// Open a video file once and inspect frames with commands at a prompt
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// The input file to process
+    file: PathBuf,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+/// Commands accepted at the prompt
+#[derive(clap::Parser, Debug)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+enum Command {
+    /// Decode the frame at the timestamp
+    Seek { timestamp: Timestamp },
+    /// Decode the next frame
+    Next,
+    /// Detect components in the current frame
+    Detect {
+        /// Names of the components or groups of them (default: all components)
+        components: Vec<String>,
+    },
+    /// Recognize texts of a component in the current frame, even if it is not detected
+    Ocr { component: String },
+    /// Display the current frame and images logged by preceding commands
+    Show,
+    /// Change settings
+    #[command(subcommand)]
+    Set(SetCommand),
+    /// Exit the prompt
+    #[command(alias = "exit")]
+    Quit,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum SetCommand {
+    /// Change the threshold of an area of a detector
+    Threshold {
+        detector: String,
+        area: String,
+        threshold: f32,
+    },
+}
+
+const NO_FRAME: &str = "no frame is decoded yet, use `seek` first";
+
+struct Session {
+    capture: VideoCapture,
+    config: Config,
+    components: Components,
+    frame: Option<Frame>,
+    tess: Option<Tesseract>,
+}
+
+impl Args {
+    #[tracing::instrument(name = "interactive", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        // Images are logged only to be shown by `show`
+        ImageLogger::init(true)?;
+
+        let config = self.config.load()?;
+        let capture = self.backend.open(&self.file)?;
+        let components =
+            Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")?;
+        let mut session = Session {
+            capture,
+            config,
+            components,
+            frame: None,
+            tess: None,
+        };
+
+        let stdin = io::stdin();
+        let mut line = String::new();
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
+            line.clear();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                break;
+            }
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            if words.is_empty() {
+                continue;
+            }
+            let command = match <Command as clap::Parser>::try_parse_from(words) {
+                Ok(command) => command,
+                Err(err) => {
+                    err.print()?;
+                    continue;
+                }
+            };
+            if matches!(command, Command::Quit) {
+                break;
+            }
+            if let Err(err) = session.run(command) {
+                eprintln!("error: {err:#}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Session {
+    fn run(&mut self, command: Command) -> eyre::Result<()> {
+        match command {
+            Command::Seek { timestamp } => {
+                let mut frame = Frame::empty();
+                let mut decoder = self
+                    .capture
+                    .range_decoder(TimestampRange::Single(timestamp))?;
+                if !decoder.decode_frame(&mut frame)? {
+                    eyre::bail!("no frame at {timestamp}");
+                }
+                println!("frame: {}", frame.position());
+                self.frame = Some(frame);
+            }
+            Command::Next => {
+                if self.frame.is_none() {
+                    eyre::bail!(NO_FRAME);
+                }
+                let mut frame = Frame::empty();
+                if !self.capture.decode_frame(&mut frame)? {
+                    eyre::bail!("end of video");
+                }
+                println!("frame: {}", frame.position());
+                self.frame = Some(frame);
+            }
+            Command::Detect { components } => {
+                let filter = self.config.component_filter(&components)?;
+                let frame = self.frame()?;
+                for component in &self.components {
+                    if !filter.matches(component.name()) {
+                        continue;
+                    }
+                    let detection = components::detect_cascade(&**component, frame, None)?;
+                    println!("{}: {}", component.name(), detection.kind().label());
+                }
+            }
+            Command::Ocr { component } => {
+                if !Tesseract::is_available() {
+                    eyre::bail!("OCR is not supported in this build");
+                }
+                if self.tess.is_none() {
+                    let mut tess = Tesseract::new(None, Some(self.config.ocr_lang()))?;
+                    tess.set_timeout(self.config.pipeline.ocr_timeout());
+                    self.tess = Some(tess);
+                }
+                let Self {
+                    components,
+                    frame,
+                    tess,
+                    ..
+                } = self;
+                let component = find_component(components, &component)?;
+                let frame = frame.as_ref().ok_or_eyre(NO_FRAME)?;
+                let payload = match components::detect_cascade(component, frame, None)? {
+                    Detection::Found(payload) | Detection::Possible(payload) => payload,
+                    Detection::Absent => None,
+                };
+                let tess = tess.as_mut().unwrap();
+                let texts = component.extract_text(tess, frame, payload)?;
+                println!("{}: {texts}", component.name());
+            }
+            Command::Show => {
+                let frame = self.frame()?;
+                let logger = ImageLogger::get();
+                if !logger.display_image() {
+                    eyre::bail!(ImageLogger::DISPLAY_DISABLED_MESSAGE.get());
+                }
+                logger.end_column();
+                logger.log_with_caption(frame.to_rgb_image()?, || {
+                    format!("frame [{}]", frame.position())
+                });
+                logger.display(&format!("interactive [{}]", frame.position()));
+            }
+            Command::Set(SetCommand::Threshold {
+                detector,
+                area,
+                threshold,
+            }) => {
+                let mut config = self.config.clone();
+                let detector_config = config
+                    .detectors
+                    .get_mut(&detector)
+                    .ok_or_else(|| eyre!("unknown detector `{detector}`"))?;
+                let area_config = detector_config
+                    .areas
+                    .iter_mut()
+                    .find(|a| a.name == area)
+                    .ok_or_else(|| eyre!("unknown area `{area}` of detector `{detector}`"))?;
+                let old = area_config.threshold;
+                area_config.threshold = threshold;
+                self.components = Components::new(self.capture.rect(), &config)
+                    .ok_or_eyre("invalid frame size")?;
+                self.config = config;
+                println!("{detector}.{area}: {old} -> {threshold}");
+            }
+            Command::Quit => {}
+        }
+        Ok(())
+    }
+
+    fn frame(&self) -> eyre::Result<&Frame> {
+        self.frame.as_ref().ok_or_eyre(NO_FRAME)
+    }
+}
+
+fn find_component<'a>(components: &'a Components, name: &str) -> eyre::Result<&'a dyn Component> {
+    components
+        .iter()
+        .find(|c| c.name() == name)
+        .map(|c| &**c)
+        .ok_or_else(|| {
+            let names = components.iter().map(|c| c.name()).collect::<Vec<_>>();
+            eyre!(
+                "unknown component `{name}` (available: {})",
+                names.join(", ")
+            )
+        })
+}
@@ -0,0 +1,35 @@
+use std::io::{self, Write as _};
+
+use color_eyre::eyre;
+use elden_analyzer::items::ItemDictionary;
+
+/// List known item names
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Only list item names completing a partial text, such as a possible OCR result.
+    /// `?` matches any character and `*` matches any sequence of characters.
+    #[clap(long)]
+    complete: Option<String>,
+}
+
+impl Args {
+    #[tracing::instrument(name = "items", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let items = ItemDictionary::get();
+        let names = match &self.complete {
+            Some(partial) => items.complete(partial),
+            None => items.iter().collect(),
+        };
+
+        let mut output = io::stdout().lock();
+        for name in &names {
+            writeln!(output, "{name}")?;
+        }
+        tracing::debug!(
+            count = names.len(),
+            total = items.len(),
+            "listed item names"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{io::spans, materials, util};
+use elden_analyzer_kernel::types::time::Duration;
+
+use super::CorrectionsArgs;
+
+/// Produce running totals of upgrade materials picked up
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files (outputs of `analyze --output-span` or `--output-pickups`) of a run, in order of time
+    #[clap(required = true)]
+    spans: Vec<PathBuf>,
+    /// Output TSV file of the pickups with running totals (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "materials", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = vec![];
+        for path in &self.spans {
+            spans.extend(spans::read_file(path)?);
+        }
+        self.corrections.apply(&mut spans)?;
+        let window = Duration::new(spans::PICKUP_WINDOW);
+        let pickups = materials::running_totals(spans, window);
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        writeln!(output, "time\tigt\titem\tcount\ttotal\tspan")?;
+        for pickup in &pickups {
+            writeln!(
+                output,
+                "{time}\t{igt}\t{item}\t{count}\t{total}\t{span}",
+                time = pickup.span.start,
+                igt = pickup
+                    .span
+                    .igt
+                    .map(|igt| igt.to_string())
+                    .unwrap_or_default(),
+                item = pickup.item,
+                count = pickup.count,
+                total = pickup.total,
+                span = pickup.span,
+            )?;
+        }
+
+        for (item, total) in materials::final_totals(&pickups) {
+            tracing::info!(item, total, "materials");
+        }
+        Ok(())
+    }
+}
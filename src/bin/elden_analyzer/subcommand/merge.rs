@@ -0,0 +1,138 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use color_eyre::eyre::{self, OptionExt as _, WrapErr as _};
+use elden_analyzer::{
+    io::{
+        self,
+        merge::{self, Manifest, ManifestPart},
+        spans,
+    },
+    util,
+};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use elden_analyzer_video::metadata;
+
+/// Merge outputs of `analyze` for parts of a run, such as a VOD split into several videos, into a single timeline
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files or JSON outputs of the parts, all in the same format
+    #[clap(required_unless_present = "manifest", conflicts_with = "manifest")]
+    inputs: Vec<PathBuf>,
+    /// Offsets of the parts in the merged timeline, one for each input
+    #[clap(long, value_delimiter = ',', conflicts_with = "video")]
+    offset: Vec<Timestamp>,
+    /// Videos of the parts, one for each input, whose creation times give the offsets relative to the earliest one
+    #[clap(long, value_delimiter = ',')]
+    video: Vec<PathBuf>,
+    /// Manifest file listing the parts with their offsets or videos
+    #[clap(long)]
+    manifest: Option<PathBuf>,
+    /// Output file of the merged spans in the same format as the inputs (default: stdout)
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    #[tracing::instrument(name = "merge", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let parts = match &self.manifest {
+            Some(path) => Manifest::load(path)?.parts,
+            None => self.parts()?,
+        };
+        let offsets = offsets(&parts)?;
+
+        let mut texts = vec![];
+        for part in &parts {
+            let text = fs::read_to_string(&part.output)
+                .wrap_err_with(|| format!("failed to read file: {}", part.output.display()))?;
+            texts.push(text);
+        }
+        // JSON outputs have no comment lines, and their lines are objects
+        let is_json = |text: &str| {
+            text.lines()
+                .find(|line| !line.trim().is_empty())
+                .is_some_and(|line| line.starts_with('{'))
+        };
+        let json = texts.iter().filter(|text| is_json(text)).count();
+        if json != 0 && json != texts.len() {
+            eyre::bail!("span files and JSON outputs cannot be merged together");
+        }
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(util::create_file(path)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        if json != 0 {
+            let lines = merge::merge_json(texts.iter().map(String::as_str).zip(offsets))?;
+            for line in &lines {
+                writeln!(output, "{line}")?;
+            }
+            tracing::info!(parts = parts.len(), spans = lines.len(), "merged outputs");
+        } else {
+            let mut chunks = vec![];
+            for (part, text) in parts.iter().zip(&texts) {
+                let spans = spans::read(text.as_bytes())
+                    .wrap_err_with(|| format!("failed to read {}", part.output.display()))?;
+                chunks.push(spans);
+            }
+            let spans = merge::merge_spans(chunks.into_iter().zip(offsets.iter().copied()));
+            io::write_header(&mut output, None)?;
+            for (part, offset) in parts.iter().zip(&offsets) {
+                writeln!(output, "# part: {} at {offset}", part.output.display())?;
+            }
+            for span in &spans {
+                writeln!(output, "{span}")?;
+            }
+            tracing::info!(parts = parts.len(), spans = spans.len(), "merged outputs");
+        }
+        Ok(())
+    }
+
+    /// Returns the parts given on the command line.
+    fn parts(&self) -> eyre::Result<Vec<ManifestPart>> {
+        let given = usize::max(self.offset.len(), self.video.len());
+        if given != self.inputs.len() {
+            eyre::bail!(
+                "{} inputs are given with {given} offsets or videos, but each input requires one",
+                self.inputs.len()
+            );
+        }
+        let parts = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| ManifestPart {
+                output: input.clone(),
+                offset: self.offset.get(i).copied(),
+                video: self.video.get(i).cloned(),
+            })
+            .collect();
+        Ok(parts)
+    }
+}
+
+/// Returns the offsets of `parts`, taken from the creation times of the videos of parts without offsets.
+fn offsets(parts: &[ManifestPart]) -> eyre::Result<Vec<Duration>> {
+    let mut times = vec![];
+    for part in parts.iter().filter(|part| part.offset.is_none()) {
+        let video = part.video.as_ref().ok_or_else(|| {
+            eyre::eyre!("no offset or video is given for {}", part.output.display())
+        })?;
+        let meta = metadata::probe(video)
+            .wrap_err_with(|| format!("failed to read metadata: {}", video.display()))?;
+        let time = merge::creation_time(&meta)
+            .ok_or_eyre(format!("no creation time is recorded: {}", video.display()))?;
+        tracing::debug!(video = %video.display(), %time, "read creation time");
+        times.push(time);
+    }
+
+    let mut from_times = merge::offsets_from_creation_times(&times).into_iter();
+    let offsets = parts
+        .iter()
+        .map(|part| match part.offset {
+            Some(offset) => offset - Timestamp::default(),
+            None => from_times.next().unwrap(),
+        })
+        .collect();
+    Ok(offsets)
+}
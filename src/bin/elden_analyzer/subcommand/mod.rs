@@ -1,27 +1,263 @@
-use color_eyre::eyre;
+use std::path::{Path, PathBuf};
+
+use clap::builder::PossibleValuesParser;
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer::{
+    components::ComponentFilter,
+    config::Config,
+    io::{corrections::Corrections, spans::Span},
+    items::{use_item_list, ItemList},
+    operator::PostProcess,
+    profile::Profile,
+    util::{ImageDump, ImageLogger},
+};
+use elden_analyzer_video::capture::{self, BackendKind, VideoCapture};
 
 mod analyze;
+mod chapters;
+mod checklist;
+mod compare_route;
+mod corrections;
+mod doctor;
+mod explain;
+mod export_training_data;
+mod farm;
+mod filter;
 mod find_ui;
+mod infer_events;
+mod interactive;
+mod items;
+mod materials;
+mod merge;
 mod metadata;
 mod recognize_text;
+mod search;
+mod setup;
+mod subtitles;
+mod train_digits;
+mod tune_performance;
+mod update_data;
+#[cfg(feature = "screen-capture")]
+mod watch;
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Subcommand {
-    Analyze(analyze::Args),
+    Analyze(Box<analyze::Args>),
+    Chapters(chapters::Args),
+    Checklist(checklist::Args),
+    CompareRoute(compare_route::Args),
+    Coordinator(farm::coordinator::Args),
+    Corrections(corrections::Args),
+    Doctor(doctor::Args),
+    Explain(explain::Args),
+    ExportTrainingData(export_training_data::Args),
+    Filter(filter::Args),
     FindUi(find_ui::Args),
+    InferEvents(infer_events::Args),
+    Interactive(interactive::Args),
+    Items(items::Args),
+    Materials(materials::Args),
+    Merge(merge::Args),
     RecognizeText(recognize_text::Args),
+    Search(search::Args),
+    Setup(setup::Args),
     Metadata(metadata::Args),
+    Subtitles(subtitles::Args),
+    TrainDigits(train_digits::Args),
+    TunePerformance(tune_performance::Args),
+    UpdateData(update_data::Args),
+    #[cfg(feature = "screen-capture")]
+    Watch(Box<watch::Args>),
+    Worker(farm::worker::Args),
 }
 
 impl Subcommand {
     pub fn run(&self) -> eyre::Result<()> {
         match self {
             Subcommand::Analyze(args) => args.run()?,
+            Subcommand::Chapters(args) => args.run()?,
+            Subcommand::Checklist(args) => args.run()?,
+            Subcommand::CompareRoute(args) => args.run()?,
+            Subcommand::Coordinator(args) => args.run()?,
+            Subcommand::Corrections(args) => args.run()?,
+            Subcommand::Doctor(args) => args.run()?,
+            Subcommand::Explain(args) => args.run()?,
+            Subcommand::ExportTrainingData(args) => args.run()?,
+            Subcommand::Filter(args) => args.run()?,
             Subcommand::FindUi(args) => args.run()?,
+            Subcommand::InferEvents(args) => args.run()?,
+            Subcommand::Interactive(args) => args.run()?,
+            Subcommand::Items(args) => args.run()?,
+            Subcommand::Materials(args) => args.run()?,
+            Subcommand::Merge(args) => args.run()?,
             Subcommand::RecognizeText(args) => args.run()?,
+            Subcommand::Search(args) => args.run()?,
+            Subcommand::Setup(args) => args.run()?,
             Subcommand::Metadata(args) => args.run()?,
+            Subcommand::Subtitles(args) => args.run()?,
+            Subcommand::TrainDigits(args) => args.run()?,
+            Subcommand::TunePerformance(args) => args.run()?,
+            Subcommand::UpdateData(args) => args.run()?,
+            #[cfg(feature = "screen-capture")]
+            Subcommand::Watch(args) => args.run()?,
+            Subcommand::Worker(args) => args.run()?,
         }
 
         Ok(())
     }
 }
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct ConfigArgs {
+    /// Configuration file defining additional components.
+    /// Live analyses (`watch` and `analyze --live`) reload it with the item list and the item corrections
+    /// when any of them is modified.
+    #[clap(long)]
+    config: Option<PathBuf>,
+    /// Profile of the recording setup, providing thresholds of detectors and defaults of options.
+    /// Options specified on the command line take precedence over the profile.
+    /// [default: `profile` in the config file]
+    #[clap(long, value_parser = PossibleValuesParser::new(Profile::names()))]
+    profile: Option<String>,
+    /// Language of the Tesseract trained data to recognize texts with, such as `eng` for the game displayed in English
+    /// [default: `ocr_lang` in the config file, or `jpn`]
+    #[clap(long)]
+    ocr_lang: Option<String>,
+    /// Item database file formatted as `assets/items.tsv`, used to validate recognized item names
+    /// [default: `item_list` in the config file, or the bundled names in the language of `--ocr-lang`]
+    #[clap(long)]
+    item_list: Option<PathBuf>,
+}
+
+impl ConfigArgs {
+    pub(crate) fn path(&self) -> Option<&std::path::Path> {
+        self.config.as_deref()
+    }
+
+    /// Returns the item list given by `--item-list`, overriding the config file.
+    pub(crate) fn item_list(&self) -> Option<&Path> {
+        self.item_list.as_deref()
+    }
+
+    pub(crate) fn profile(&self, config: &Config) -> Option<&'static Profile> {
+        self.profile
+            .as_deref()
+            .or(config.profile.as_deref())
+            .and_then(Profile::get)
+    }
+
+    pub(crate) fn load(&self) -> eyre::Result<Config> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        if let Some(profile) = self.profile(&config) {
+            profile.apply(&mut config)?;
+        }
+        if let Some(ocr_lang) = &self.ocr_lang {
+            config.ocr_lang = Some(ocr_lang.clone());
+        }
+        if let Some(item_list) = &self.item_list {
+            config.item_list = Some(item_list.clone());
+        }
+
+        let list = match &config.item_list {
+            Some(path) => ItemList::load(path)
+                .wrap_err_with(|| format!("failed to read item list: {}", path.display()))?,
+            None => ItemList::Bundled(config.text_lang()),
+        };
+        use_item_list(list)?;
+        PostProcess::rebuild_item_rules(config.item_corrections().clone());
+        capture::set_scale_threads(config.performance.scale_threads.unwrap_or(1));
+        Ok(config)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct BackendArgs {
+    /// Video capture backend (`ffmpeg` or `media-foundation`)
+    #[clap(long, default_value_t)]
+    backend: BackendKind,
+}
+
+impl BackendArgs {
+    pub(crate) fn kind(&self) -> BackendKind {
+        self.backend
+    }
+
+    /// Opens the video file with the backend.
+    pub(crate) fn open(&self, file: &Path) -> eyre::Result<VideoCapture> {
+        Ok(VideoCapture::open_with_backend(file, self.backend)?)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct DumpImagesArgs {
+    /// Directory to write intermediate images into as PNG files, as well as or instead of displaying them.
+    /// Files are named by the order, the frame, the component and the stages, e.g. `000042_f001234_main_item_recognize.png`
+    #[clap(long)]
+    dump_images: Option<PathBuf>,
+    /// Write images of only the stages to `--dump-images`, including stages nested in them [default: all stages]
+    #[clap(
+        long,
+        value_delimiter = ',',
+        requires = "dump_images",
+        value_parser = PossibleValuesParser::new(ImageLogger::STAGES)
+    )]
+    dump_stages: Vec<String>,
+}
+
+impl DumpImagesArgs {
+    /// Initializes [`ImageLogger`], displaying images if `display_image` is true.
+    pub(crate) fn init_logger(&self, display_image: bool) -> eyre::Result<()> {
+        let dump = self.dump_images.as_ref().map(|dir| ImageDump {
+            dir: dir.clone(),
+            stages: self.dump_stages.clone(),
+        });
+        ImageLogger::init_with_dump(display_image, dump)
+    }
+}
+
+/// Config of subcommands reading outputs of `analyze`, which only use its groups of components.
+#[derive(clap::Args, Debug)]
+pub(crate) struct GroupsArgs {
+    /// Configuration file defining groups of components usable in place of component names
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+impl GroupsArgs {
+    /// Returns the filter selecting components by `names`, which may be names of groups or patterns.
+    pub(crate) fn component_filter(&self, names: &[String]) -> eyre::Result<ComponentFilter> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        config.component_filter(names)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct CorrectionsArgs {
+    /// Corrections file (output of `corrections`, edited) replacing texts of spans
+    #[clap(long)]
+    corrections: Option<PathBuf>,
+}
+
+impl CorrectionsArgs {
+    pub(crate) fn load(&self) -> eyre::Result<Option<Corrections>> {
+        self.corrections
+            .as_deref()
+            .map(Corrections::load)
+            .transpose()
+    }
+
+    /// Replaces the texts of `spans` by the corrections, if given.
+    pub(crate) fn apply(&self, spans: &mut Vec<Span>) -> eyre::Result<()> {
+        if let Some(corrections) = self.load()? {
+            let corrected = corrections.apply(spans);
+            tracing::info!(corrected, "applied corrections");
+        }
+        Ok(())
+    }
+}
@@ -2,13 +2,17 @@ use std::path::PathBuf;
 
 use color_eyre::eyre::{self, OptionExt as _};
 use elden_analyzer_kernel::types::time::TimestampRange;
-use elden_analyzer_video::capture::{Frame, VideoCapture};
+use elden_analyzer_video::capture::{Frame, FrameCache, ImageSequenceBackend};
 use tracing::info;
 
 use elden_analyzer::{
-    components::Components, image_process::tesseract::Tesseract, util::ImageLogger,
+    components::{ComponentFilter, Components},
+    image_process::tesseract::Tesseract,
+    util::ImageLogger,
 };
 
+use super::{BackendArgs, ConfigArgs, DumpImagesArgs};
+
 /// Analyze the video files to extract information
 #[derive(clap::Parser, Debug)]
 pub struct Args {
@@ -20,19 +24,41 @@ pub struct Args {
     /// Display output image
     #[clap(long, default_value = "false")]
     display_image: bool,
+    /// Process only the components or groups of them (default: all components)
     #[clap(long, value_delimiter = ',')]
-    filter: Option<Vec<String>>,
+    filter: Vec<String>,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Directory caching decoded frames, so that repeated runs on the same frames skip decoding
+    #[clap(long)]
+    frame_cache: Option<PathBuf>,
+    #[clap(flatten)]
+    dump_images: DumpImagesArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
 }
 
 impl Args {
     #[tracing::instrument(name = "recognize_text", skip_all)]
     pub(crate) fn run(&self) -> eyre::Result<()> {
-        ImageLogger::init(self.display_image)?;
+        self.dump_images.init_logger(self.display_image)?;
 
-        let mut tess = Tesseract::new(None, Some("jpn"))?;
+        let config = self.config.load()?;
+        let mut tess = Tesseract::new(None, Some(config.ocr_lang()))?;
+        tess.set_timeout(config.pipeline.ocr_timeout());
         let mut capture = tracing::trace_span!("open", file = %self.file.display())
-            .in_scope(|| VideoCapture::open(&self.file))?;
-        let components = Components::new(capture.rect()).ok_or_eyre("invalid frame size")?;
+            .in_scope(|| self.backend.open(&self.file))?;
+        // Images are read as quickly as cached frames
+        if let Some(dir) = self
+            .frame_cache
+            .as_deref()
+            .filter(|_| !ImageSequenceBackend::is_image_sequence(&self.file))
+        {
+            capture.set_frame_cache(Some(FrameCache::open(dir, &self.file)?));
+        }
+        let components =
+            Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")?;
+        let filter = config.component_filter(&self.filter)?;
 
         let mut frame = Frame::empty();
         for ts_range in &self.timestamp {
@@ -40,7 +66,7 @@ impl Args {
             while tracing::trace_span!("decode-frame")
                 .in_scope(|| decoder.decode_frame(&mut frame))?
             {
-                process_frame(&mut tess, &components, &frame, self.filter.as_deref())?;
+                process_frame(&mut tess, &components, &frame, &filter)?;
             }
         }
 
@@ -53,19 +79,19 @@ fn process_frame(
     tess: &mut Tesseract,
     components: &Components,
     frame: &Frame,
-    filter: Option<&[String]>,
+    filter: &ComponentFilter,
 ) -> eyre::Result<()> {
     let logger = ImageLogger::get();
 
     for component in components {
-        if let Some(filter) = filter {
-            if !filter.iter().any(|s| *s == component.name()) {
-                continue;
-            }
+        if !filter.matches(component.name()) {
+            continue;
         }
 
         tracing::info_span!("extract-text", name = component.name()).in_scope(
             || -> eyre::Result<()> {
+                let _subject =
+                    ImageLogger::enter_subject(frame.position().index(), component.name());
                 let result = component.extract_text(tess, frame, None)?;
                 info!(%result);
                 Ok(())
@@ -0,0 +1,254 @@
+use std::{
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    analyzer::{decode::SampleRate, CancellationToken, Event, PipelineBuilder, PipelineHooks},
+    config::Config,
+    i18n::Message,
+    io::{index::AnalysisIndex, spans},
+    route::{Bisection, Route},
+};
+use elden_analyzer_kernel::types::time::{Duration, FramePosition, Timestamp, TimestampRange};
+use num_rational::Ratio;
+use regex::Regex;
+use tracing::{info, Span};
+
+use crate::tui::{ProgressBar, ProgressBarBuilder};
+
+use super::{BackendArgs, ConfigArgs};
+
+/// Length in seconds of the range bisection ends at, which contains a few checkpoints at most
+const MIN_WINDOW_SECS: i64 = 120;
+
+/// Find when an item is picked up or a text is shown, stopping as soon as it is found
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Item name or text to search for, matching a part of recognized texts
+    text: String,
+    /// Input file to search
+    input: PathBuf,
+    /// Match texts by `TEXT` as a regular expression
+    #[clap(long)]
+    regex: bool,
+    /// Find the last occurrence instead of the first one, which analyzes the video to the end
+    #[clap(long)]
+    last: bool,
+    /// Frames to search (`START-END`, `START-`, `-END` or a single timestamp)
+    #[clap(long, default_value = "-")]
+    timestamp: TimestampRange,
+    /// Search only spans of the components or groups of them, such as `pickups` (default: all components)
+    #[clap(long, value_delimiter = ',')]
+    component: Vec<String>,
+    /// Analyze every Nth frame (`N`) or about X frames per second (`Xfps`).
+    /// Banners stay on screen for a second or more, so a low rate finds them much faster than analyzing all frames.
+    #[clap(long, default_value = "4fps")]
+    sample_rate: SampleRate,
+    /// Also analyze the frames skipped before a sampled frame where a component appears or disappears,
+    /// so that the time found is exact instead of accurate to the sampling interval
+    #[clap(long)]
+    rescan: bool,
+    /// Route file whose checkpoints are passed in order, where `TEXT` is the text or label of a checkpoint.
+    /// The video is bisected by finding other checkpoints before scanning the remaining range for `TEXT`,
+    /// which is much faster for events late in long videos
+    #[clap(long, conflicts_with_all = ["regex", "last", "component"])]
+    route: Option<PathBuf>,
+    /// Analyze the video even if it has an index written by `analyze`, which answers searches without decoding
+    #[clap(long)]
+    no_index: bool,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "search", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let config = self.config.load()?;
+        let index = if self.no_index {
+            None
+        } else {
+            AnalysisIndex::load_for(&self.input)?
+        };
+        if index.is_some() {
+            info!("searching the index");
+        }
+        let index = index.as_ref();
+        let found = match &self.route {
+            Some(path) => self.bisect(config, index, path)?,
+            None => {
+                let pattern = if self.regex {
+                    Regex::new(&self.text)?
+                } else {
+                    Regex::new(&regex::escape(&self.text))?
+                };
+                let filter = config.component_filter(&self.component)?;
+                self.scan(config, index, self.timestamp, self.last, |span| {
+                    filter.matches(&span.name) && pattern.is_match(&span.text)
+                })?
+            }
+        };
+
+        const NOT_FOUND: Message = Message::new("not found", "見つかりませんでした");
+        match found {
+            Some(span) => println!("{span}"),
+            None => println!("{NOT_FOUND}"),
+        }
+        Ok(())
+    }
+
+    /// Narrows the range by bisection over the checkpoints of the route, then scans the rest.
+    fn bisect(
+        &self,
+        config: Config,
+        index: Option<&AnalysisIndex>,
+        path: &Path,
+    ) -> eyre::Result<Option<spans::Span>> {
+        let route = Route::load(path)?;
+        let filter = config.component_filter(&route.components)?;
+        let mut targets = route
+            .checkpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, checkpoint)| {
+                checkpoint.text == self.text || checkpoint.label() == self.text
+            })
+            .map(|(idx, _)| idx);
+        let (Some(target), None) = (targets.next(), targets.next()) else {
+            eyre::bail!(
+                "`{}` must be the text or label of exactly one checkpoint of the route",
+                self.text
+            );
+        };
+        let checkpoint = &route.checkpoints[target];
+
+        let duration = match index {
+            Some(index) => Duration::new(index.duration.as_ratio()),
+            None => self.backend.open(&self.input)?.duration(),
+        };
+        let (start, end) = match self.timestamp {
+            TimestampRange::Full => (Timestamp::default(), Timestamp::new(duration.as_ratio())),
+            TimestampRange::Single(ts) => (ts, ts),
+            TimestampRange::Range(start, end) => (start, end),
+            TimestampRange::RangeFrom(start) => (start, Timestamp::new(duration.as_ratio())),
+            TimestampRange::RangeTo(end) => (Timestamp::default(), end),
+        };
+        let min_window = Duration::new(Ratio::from_integer(MIN_WINDOW_SECS));
+        let bisection = route.bisect(target, start, end, min_window, |from, to| {
+            self.scan(
+                config.clone(),
+                index,
+                TimestampRange::Range(from, to),
+                false,
+                |span| filter.matches(&span.name) && route.checkpoint_index(span).is_some(),
+            )
+        })?;
+        match bisection {
+            Bisection::Found(span) => Ok(Some(span)),
+            Bisection::Window(start, end) => {
+                info!(%start, %end, "scanning the rest");
+                let range = TimestampRange::Range(start, end);
+                self.scan(config, index, range, false, |span| {
+                    filter.matches(&span.name) && checkpoint.matches(span)
+                })
+            }
+        }
+    }
+
+    /// Analyzes `range` of the input, returning the first span accepted by `accept`, or the last one if `last` is set.
+    /// Spans are looked up in `index` instead if given.
+    ///
+    /// Spans whose texts are not reliably recognized are never accepted.
+    fn scan(
+        &self,
+        config: Config,
+        index: Option<&AnalysisIndex>,
+        range: TimestampRange,
+        last: bool,
+        mut accept: impl FnMut(&spans::Span) -> bool,
+    ) -> eyre::Result<Option<spans::Span>> {
+        if let Some(index) = index {
+            let mut spans = index
+                .spans
+                .iter()
+                .filter(|span| overlaps(range, span) && !span.is_possible() && accept(span));
+            let found = if last { spans.last() } else { spans.next() };
+            return Ok(found.cloned());
+        }
+
+        let profile = self.config.profile(&config);
+
+        // The bar is drawn once the range of frames is known
+        let pbar = Arc::new(OnceLock::<ProgressBar>::new());
+        let mut builder = PipelineBuilder::new(config);
+        builder.range = range;
+        builder.sample_rate = Some(self.sample_rate);
+        builder.rescan = self.rescan;
+        builder.lead_in = profile.and_then(|profile| profile.lead_in).unwrap_or(0);
+        builder.promote_window = profile
+            .and_then(|profile| profile.promote_window)
+            .unwrap_or(1);
+        builder.progress = Some(Arc::new({
+            let pbar = Arc::clone(&pbar);
+            move |progress| {
+                if let Some(pbar) = pbar.get() {
+                    pbar.set_position(progress.position);
+                }
+            }
+        }));
+        builder.hooks = Some(Arc::new(Interrupt(crate::cancellation())));
+        let pipeline = builder.build()?;
+
+        let mut found = None;
+        for event in pipeline.analyze_file(&self.input, self.backend.kind())? {
+            match event? {
+                Event::Started { start, end, fps } => {
+                    let _ =
+                        pbar.set(ProgressBarBuilder::new(start, end, fps).build(Span::current()));
+                }
+                Event::Span { id, result } => {
+                    let span = result.into_span(Some(id));
+                    if span.is_possible() || !accept(&span) {
+                        continue;
+                    }
+                    tracing::info!(%span, "found");
+                    found = Some(span);
+                    // Dropping the events stops the analysis
+                    if !last {
+                        break;
+                    }
+                }
+                Event::Finished(_) => {}
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Returns `true` if `span` is in `range` at least partly, as spans found by analyzing the range.
+fn overlaps(range: TimestampRange, span: &spans::Span) -> bool {
+    let (start, end) = match range {
+        TimestampRange::Full => return true,
+        TimestampRange::Single(ts) => (Some(ts), Some(ts)),
+        TimestampRange::Range(start, end) => (Some(start), Some(end)),
+        TimestampRange::RangeFrom(start) => (Some(start), None),
+        TimestampRange::RangeTo(end) => (None, Some(end)),
+    };
+    start.map_or(true, |start| span.end > start) && end.map_or(true, |end| span.start <= end)
+}
+
+/// Stops the search on Ctrl-C, reporting the occurrences found so far.
+struct Interrupt(&'static CancellationToken);
+
+impl PipelineHooks for Interrupt {
+    fn on_frame_decoded(&self, _pos: FramePosition) -> ControlFlow<()> {
+        if self.0.is_cancelled() {
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+}
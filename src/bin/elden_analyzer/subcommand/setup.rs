@@ -0,0 +1,207 @@
+use std::{
+    fs,
+    io::{self, BufRead as _, Write as _},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, OptionExt as _, WrapErr as _};
+use elden_analyzer::{
+    components::{self, Components, Detection},
+    config::{Config, DEFAULT_OCR_LANG},
+    i18n::Lang,
+    profile::Profile,
+    util::ImageLogger,
+};
+use elden_analyzer_kernel::types::time::TimestampRange;
+use elden_analyzer_video::capture::Frame;
+use num_rational::Ratio;
+
+use super::{doctor, BackendArgs};
+
+/// Check FFmpeg and Tesseract, choose a profile and write an initial config file
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Video file of your own footage, used to suggest a profile
+    sample: Option<PathBuf>,
+    /// Frames of the sample to detect components in
+    #[clap(long, default_value = "-")]
+    timestamp: TimestampRange,
+    /// Maximum number of frames of the sample to detect components in
+    #[clap(long, default_value = "300")]
+    frames: usize,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Config file to write
+    #[clap(long, default_value = "elden-analyzer.toml")]
+    output: PathBuf,
+}
+
+/// Frames of the sample where each profile finds components.
+#[derive(Debug)]
+struct SampleResult {
+    frame_size: (u32, u32),
+    fps: Ratio<i64>,
+    frames: usize,
+    found_frames: Vec<usize>,
+}
+
+impl Args {
+    #[tracing::instrument(name = "setup", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        ImageLogger::init(false)?;
+
+        for check in doctor::check_libraries(DEFAULT_OCR_LANG) {
+            check.print();
+        }
+
+        let profiles = Profile::all().collect::<Vec<_>>();
+        let sample = self
+            .sample
+            .as_deref()
+            .map(|path| self.detect_sample(path, &profiles))
+            .transpose()?;
+
+        let suggested = match &sample {
+            Some(sample) => {
+                let fps = *sample.fps.numer() as f64 / *sample.fps.denom() as f64;
+                println!(
+                    "sample: {}x{} {fps:.2} fps, {} frames",
+                    sample.frame_size.0, sample.frame_size.1, sample.frames
+                );
+                Profile::suggest(sample.frame_size, fps)
+            }
+            None => profiles[0].0,
+        };
+
+        println!();
+        println!("profiles:");
+        for (i, (name, profile)) in profiles.iter().enumerate() {
+            let mark = if *name == suggested { "*" } else { " " };
+            print!("{mark} {}) {name}: {}", i + 1, profile.description);
+            if let Some(sample) = &sample {
+                print!(
+                    " (components found in {}/{} frames)",
+                    sample.found_frames[i], sample.frames
+                );
+            }
+            println!();
+        }
+        let (profile_name, profile) = loop {
+            let answer = prompt(&format!("profile [{suggested}]"))?;
+            let answer = answer.as_deref().unwrap_or(suggested);
+            let found = match answer.parse::<usize>() {
+                Ok(i) => i.checked_sub(1).and_then(|i| profiles.get(i)),
+                Err(_) => profiles.iter().find(|(name, _)| *name == answer),
+            };
+            match found {
+                Some(found) => break *found,
+                None => println!("unknown profile: {answer}"),
+            }
+        };
+
+        let default_lang = profile.translate_items.unwrap_or(Lang::Ja);
+        let lang = loop {
+            let answer = prompt(&format!(
+                "language of item names in outputs (ja: as shown in the game, en: translated) [{default_lang}]"
+            ))?;
+            match answer.map(|answer| answer.parse::<Lang>()) {
+                None => break default_lang,
+                Some(Ok(lang)) => break lang,
+                Some(Err(err)) => println!("{err}"),
+            }
+        };
+
+        if self.output.exists() {
+            let answer = prompt(&format!(
+                "{} exists, overwrite? [y/N]",
+                self.output.display()
+            ))?;
+            if !matches!(answer.as_deref(), Some("y" | "Y" | "yes")) {
+                println!("config file is not written");
+                return Ok(());
+            }
+        }
+        write_config(&self.output, profile_name, lang)?;
+        println!("wrote {}", self.output.display());
+        println!();
+        println!("analyze videos with:");
+        println!(
+            "  elden-analyzer analyze VIDEO --config {} --output-span spans.txt",
+            self.output.display()
+        );
+        Ok(())
+    }
+
+    /// Detects components in frames of the sample with the config of each profile.
+    fn detect_sample(
+        &self,
+        path: &Path,
+        profiles: &[(&str, &Profile)],
+    ) -> eyre::Result<SampleResult> {
+        let mut capture = self.backend.open(path)?;
+        let components = profiles
+            .iter()
+            .map(|(_, profile)| {
+                let mut config = Config::default();
+                profile.apply(&mut config)?;
+                Components::new(capture.rect(), &config).ok_or_eyre("invalid frame size")
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let frame_size = (capture.width(), capture.height());
+        let fps = capture.fps();
+        let mut decoder = capture.range_decoder(self.timestamp)?;
+        let mut frame = Frame::empty();
+        let mut frames = 0;
+        let mut found_frames = vec![0; profiles.len()];
+        while frames < self.frames && decoder.decode_frame(&mut frame)? {
+            for (components, found_frames) in components.iter().zip(&mut found_frames) {
+                for component in components {
+                    if let Detection::Found(_) =
+                        components::detect_cascade(&**component, &frame, None)?
+                    {
+                        *found_frames += 1;
+                        break;
+                    }
+                }
+            }
+            frames += 1;
+        }
+        if frames == 0 {
+            eyre::bail!("no frames in the sample: {}", path.display());
+        }
+
+        Ok(SampleResult {
+            frame_size,
+            fps,
+            frames,
+            found_frames,
+        })
+    }
+}
+
+/// Prints `message` and reads a line, returning `None` if it is empty.
+fn prompt(message: &str) -> eyre::Result<Option<String>> {
+    print!("{message}: ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        eyre::bail!("input is closed");
+    }
+    let line = line.trim();
+    Ok((!line.is_empty()).then(|| line.to_owned()))
+}
+
+fn write_config(path: &Path, profile: &str, lang: Lang) -> eyre::Result<()> {
+    let contents = format!(
+        "# Generated by `elden-analyzer setup`.\n\
+         # Components and detectors can be added as described in the README.\n\
+         profile = \"{profile}\"\n\
+         translate_items = \"{lang}\"\n"
+    );
+    fs::write(path, contents)
+        .wrap_err_with(|| format!("failed to write config file: {}", path.display()))?;
+    // Make sure the written file is valid
+    Config::load(path)?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use elden_analyzer::{
+    io::{
+        spans,
+        subtitles::{self, SubtitleFormat},
+    },
+    util,
+};
+
+use super::CorrectionsArgs;
+
+/// Write subtitle files of spans, to check texts by playing the video with them
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Span files (outputs of `analyze --output-span`) of the video
+    #[clap(required = true)]
+    spans: Vec<PathBuf>,
+    /// Output SubRip subtitle file
+    #[clap(long, required_unless_present = "output_vtt")]
+    output_srt: Option<PathBuf>,
+    /// Output WebVTT subtitle file
+    #[clap(long)]
+    output_vtt: Option<PathBuf>,
+    #[clap(flatten)]
+    corrections: CorrectionsArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "subtitles", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let mut spans = vec![];
+        for path in &self.spans {
+            spans.extend(spans::read_file(path)?);
+        }
+        self.corrections.apply(&mut spans)?;
+
+        let outputs = [
+            (SubtitleFormat::Srt, &self.output_srt),
+            (SubtitleFormat::Vtt, &self.output_vtt),
+        ];
+        for (format, path) in outputs {
+            if let Some(path) = path {
+                subtitles::write(&mut util::create_file(path)?, format, &spans)?;
+            }
+        }
+        Ok(())
+    }
+}
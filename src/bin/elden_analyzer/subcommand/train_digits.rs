@@ -0,0 +1,79 @@
+use std::{fs, path::PathBuf};
+
+use color_eyre::eyre::{self, bail, WrapErr as _};
+use elden_analyzer::{components, operator::GlyphAtlas};
+use elden_analyzer_kernel::types::rect::Rect;
+use imageproc::image::{self, imageops};
+
+/// Make a glyph atlas of digits of counts from a dataset of `export-training-data`
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Dataset directory (output of `export-training-data`) with samples of `main_item` or side items
+    dataset: PathBuf,
+    /// Output index file of the atlas (such as `glyphs.tsv`, written with `glyphs.png`), referred by `glyph_atlas` in the config file
+    #[clap(long)]
+    output: PathBuf,
+}
+
+impl Args {
+    #[tracing::instrument(name = "train_digits", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        let labels_path = self.dataset.join("labels.tsv");
+        let labels = fs::read_to_string(&labels_path)
+            .wrap_err_with(|| format!("failed to read {}", labels_path.display()))?;
+
+        let mut samples = vec![];
+        for line in labels.lines().skip(1) {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let [path, component, "positive", _timestamp, text, ..] = fields[..] else {
+                continue;
+            };
+            // Counts are the last segment of texts, such as `緋雫の聖杯瓶 ×3`
+            let Some(count) = text.rsplit(' ').next().and_then(|s| s.strip_prefix('×')) else {
+                continue;
+            };
+            if count.is_empty() || !count.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let Some(count_rect) = components::count_rect(component, count.len()) else {
+                continue;
+            };
+
+            let image = image::open(self.dataset.join(path))
+                .wrap_err_with(|| format!("failed to read sample: {path}"))?
+                .to_luma8();
+            let Some(rect) = count_rect.clip(Rect::at(0, 0).of_size(image.width(), image.height()))
+            else {
+                continue;
+            };
+            let crop = imageops::crop_imm(
+                &image,
+                rect.left() as u32,
+                rect.top() as u32,
+                rect.width(),
+                rect.height(),
+            )
+            .to_image();
+            samples.push((crop, count.to_owned()));
+        }
+        if samples.is_empty() {
+            bail!("no samples with counts in {}", labels_path.display());
+        }
+
+        let (atlas, skipped) =
+            GlyphAtlas::train(samples.iter().map(|(image, label)| (image, label.as_str())));
+        let chars = atlas.chars().collect::<String>();
+        tracing::info!(
+            samples = samples.len(),
+            skipped,
+            chars,
+            "trained glyph atlas"
+        );
+        if let Some(ch) = ('0'..='9').find(|ch| !chars.contains(*ch)) {
+            tracing::warn!("no sample of `{ch}`; add videos with more counts to the dataset");
+        }
+        let image = atlas.save(&self.output)?;
+        tracing::info!(index = %self.output.display(), image = %image.display(), "glyph atlas written");
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{self, OptionExt as _, WrapErr as _};
+use elden_analyzer::{
+    components::{self, CascadeStats, Components, Detection},
+    config::{Config, PerformanceConfig},
+    image_process::tesseract::Tesseract,
+    util::ImageLogger,
+};
+use elden_analyzer_kernel::types::time::TimestampRange;
+use elden_analyzer_video::capture::{self, Frame};
+
+use super::BackendArgs;
+
+/// Texts are recognized in every this number of frames, since recognition is much slower than detection
+const OCR_INTERVAL: usize = 10;
+
+/// Header of the table written to the config file
+const TABLE_HEADER: &str = "[performance]";
+
+/// Measure the throughput of the analysis on this machine and recommend threads and queues
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Input file to measure with, preferably a gameplay video like ones to be analyzed
+    input: PathBuf,
+    /// Frames to measure
+    #[clap(default_value = "-")]
+    timestamp: TimestampRange,
+    /// Maximum number of frames to measure
+    #[clap(long, default_value = "300")]
+    frames: usize,
+    #[clap(flatten)]
+    backend: BackendArgs,
+    /// Configuration file to write the recommended `[performance]` table into.
+    /// An existing `[performance]` table is replaced and other contents are kept.
+    /// If not specified, the table is printed to stdout.
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+/// Time spent in each stage of the pipeline.
+#[derive(Debug, Default)]
+struct Measurement {
+    frames: usize,
+    decode: Duration,
+    /// Time to convert frames to RGB with a thread, and the number of frames converted
+    convert: (Duration, usize),
+    /// Time to convert frames to RGB with `scale_threads` threads, and the number of frames converted
+    threaded_convert: (Duration, usize),
+    scale_threads: usize,
+    detect: Duration,
+    /// Number of components found or possibly found, whose texts are recognized in the pipeline
+    detected: usize,
+    /// Rejections by each stage of the detection cascade, counted for each component in each frame
+    cascade: CascadeStats,
+    ocr: Duration,
+    /// Number of components whose texts are recognized in the measurement
+    recognized: usize,
+}
+
+impl Args {
+    #[tracing::instrument(name = "tune-performance", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        ImageLogger::init(false)?;
+
+        let config = match &self.config {
+            Some(path) if path.exists() => Config::load(path)?,
+            _ => Config::default(),
+        };
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        let measurement = self.measure(&config, cpus)?;
+        if measurement.frames == 0 {
+            eyre::bail!("no frames to measure");
+        }
+        measurement.print();
+
+        let performance = measurement.recommend(cpus);
+        let table = toml::to_string(&performance)?;
+        match &self.config {
+            Some(path) => {
+                write_table(path, &table)?;
+                // Make sure the written file is still valid
+                Config::load(path)?;
+                println!("wrote {TABLE_HEADER} to {}", path.display());
+            }
+            None => print!("{TABLE_HEADER}\n{table}"),
+        }
+        Ok(())
+    }
+
+    /// Measures the stages, converting frames with a thread in the first half and with `cpus` threads in the rest.
+    fn measure(&self, config: &Config, cpus: usize) -> eyre::Result<Measurement> {
+        let mut capture = self.backend.open(&self.input)?;
+        let components =
+            Components::new(capture.rect(), config).ok_or_eyre("invalid frame size")?;
+        let mut tess = Tesseract::is_available()
+            .then(|| Tesseract::new(None, Some(config.ocr_lang())))
+            .transpose()?;
+        if let Some(tess) = &mut tess {
+            tess.set_timeout(config.pipeline.ocr_timeout());
+        }
+        let mut decoder = capture.range_decoder(self.timestamp)?;
+
+        let mut measurement = Measurement {
+            scale_threads: cpus,
+            ..Measurement::default()
+        };
+        capture::set_scale_threads(1);
+        while measurement.frames < self.frames {
+            if measurement.frames == self.frames / 2 {
+                capture::set_scale_threads(cpus);
+            }
+            let mut frame = Frame::empty();
+            let start = Instant::now();
+            if !decoder.decode_frame(&mut frame)? {
+                break;
+            }
+            measurement.decode += start.elapsed();
+
+            // Convert the frame before the detection, which would convert it lazily, to measure the conversion apart
+            let start = Instant::now();
+            frame.rgb_data(0)?;
+            let convert = match measurement.frames < self.frames / 2 {
+                true => &mut measurement.convert,
+                false => &mut measurement.threaded_convert,
+            };
+            convert.0 += start.elapsed();
+            convert.1 += 1;
+
+            let start = Instant::now();
+            let detections = components
+                .iter()
+                .map(|component| {
+                    components::detect_cascade(&**component, &frame, Some(&measurement.cascade))
+                })
+                .collect::<eyre::Result<Vec<_>>>()?;
+            measurement.detect += start.elapsed();
+            measurement.detected += detections
+                .iter()
+                .filter(|detection| !matches!(detection, Detection::Absent))
+                .count();
+
+            // Recognize texts of all components regardless of detections, to measure enough recognitions
+            if let Some(tess) = tess
+                .as_mut()
+                .filter(|_| measurement.frames % OCR_INTERVAL == 0)
+            {
+                let start = Instant::now();
+                for (component, detection) in components.iter().zip(detections) {
+                    let payload = match detection {
+                        Detection::Found(payload) | Detection::Possible(payload) => payload,
+                        Detection::Absent => None,
+                    };
+                    component.extract_text(tess, &frame, payload)?;
+                    measurement.recognized += 1;
+                }
+                measurement.ocr += start.elapsed();
+            }
+
+            measurement.frames += 1;
+        }
+        capture::set_scale_threads(config.performance.scale_threads.unwrap_or(1));
+        Ok(measurement)
+    }
+}
+
+impl Measurement {
+    fn per_frame(&self, total: Duration) -> f64 {
+        total.as_secs_f64() / self.frames as f64
+    }
+
+    /// Returns the time to convert a frame to RGB, or `None` if no frames are converted.
+    fn per_conversion((total, frames): (Duration, usize)) -> Option<f64> {
+        (frames > 0).then(|| total.as_secs_f64() / frames as f64)
+    }
+
+    /// Returns the time to recognize texts of a component, or `None` if OCR is not available.
+    fn per_recognition(&self) -> Option<f64> {
+        (self.recognized > 0).then(|| self.ocr.as_secs_f64() / self.recognized as f64)
+    }
+
+    fn print(&self) {
+        let msec = |secs: f64| secs * 1000.0;
+        println!("frames:  {}", self.frames);
+        println!("decode:  {:.2} ms/frame", msec(self.per_frame(self.decode)));
+        match (
+            Self::per_conversion(self.convert),
+            Self::per_conversion(self.threaded_convert),
+        ) {
+            (Some(single), Some(threaded)) => println!(
+                "convert: {:.2} ms/frame, {:.2} ms/frame with {} threads",
+                msec(single),
+                msec(threaded),
+                self.scale_threads
+            ),
+            (Some(single), None) => println!("convert: {:.2} ms/frame", msec(single)),
+            _ => {}
+        }
+        println!("detect:  {:.2} ms/frame", msec(self.per_frame(self.detect)));
+        match self.per_recognition() {
+            Some(secs) => println!("ocr:     {:.2} ms/component", msec(secs)),
+            None => println!("ocr:     not supported in this build"),
+        }
+        println!(
+            "detected components: {:.2}/frame",
+            self.detected as f64 / self.frames as f64
+        );
+        println!(
+            "rejected components: {:.1}% by prefilter, {:.1}% of the rest by detectors",
+            self.cascade.prefilter_rejection_rate() * 100.0,
+            self.cascade.detector_rejection_rate() * 100.0
+        );
+    }
+
+    /// Recommends the configuration to keep up with decoding on `cpus` CPUs.
+    ///
+    /// Each segment decodes frames in a thread, and detection and OCR need enough threads to process
+    /// frames at the decoding rate. Segments are added while CPUs are left.
+    fn recommend(&self, cpus: usize) -> PerformanceConfig {
+        let decode = self.per_frame(self.decode).max(f64::EPSILON);
+        let convert = Self::per_conversion(self.convert).unwrap_or_default();
+        // Number of CPUs needed for each stage to keep up with decoding, where frames are converted to RGB
+        // by the threads detecting components (at most, since frames rejected by the prefilter are not converted)
+        let detect_load = (self.per_frame(self.detect) + convert) / decode;
+        let ocr_load = self.per_recognition().map(|secs| {
+            let detected = self.detected as f64 / self.frames as f64;
+            secs * detected / decode
+        });
+        let segment_load = 1.0 + detect_load + ocr_load.unwrap_or_default();
+
+        let cpus = cpus as f64;
+        let parallel_segments = (cpus / segment_load).floor().max(1.0);
+        // Stages share the CPUs if a segment cannot keep up with decoding
+        let scale = (cpus / parallel_segments / segment_load).min(1.0);
+        let round = if scale < 1.0 { f64::floor } else { f64::ceil };
+        let threads = |load: f64| (round(load * scale) as usize).max(1);
+        let detect_threads = threads(detect_load);
+        let ocr_threads = ocr_load.map(threads);
+
+        PerformanceConfig {
+            detect_threads: Some(detect_threads),
+            ocr_threads,
+            parallel_segments: Some(parallel_segments as usize),
+            parallel_files: None,
+            // Keep all threads busy while frames wait for the accumulation
+            max_pending_frames: Some(
+                (2 * (detect_threads + ocr_threads.unwrap_or_default())).max(30),
+            ),
+            // Segments keep all CPUs busy, so threads converting each frame only add overhead
+            scale_threads: None,
+        }
+    }
+}
+
+/// Writes `table` as the `[performance]` table of the config file, replacing the existing one.
+fn write_table(path: &Path, table: &str) -> eyre::Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("failed to read config file: {}", path.display()))
+        }
+    };
+
+    let mut lines = vec![];
+    let mut in_table = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_table = trimmed == TABLE_HEADER;
+        }
+        if !in_table {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text += "\n\n";
+    }
+    text += TABLE_HEADER;
+    text += "\n";
+    text += table;
+    fs::write(path, text)
+        .wrap_err_with(|| format!("failed to write config file: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommend_threads() {
+        let ms = Duration::from_millis;
+        let measurement = Measurement {
+            frames: 100,
+            decode: ms(500),
+            detect: ms(1000),
+            detected: 50,
+            ocr: ms(400),
+            recognized: 10,
+            cascade: CascadeStats::default(),
+            convert: (Duration::ZERO, 0),
+            threaded_convert: (Duration::ZERO, 0),
+            scale_threads: 0,
+        };
+        // Per frame: decode 5 ms, detect 10 ms, OCR 0.5 × 40 ms
+        let performance = measurement.recommend(16);
+        assert_eq!(performance.detect_threads, Some(2));
+        assert_eq!(performance.ocr_threads, Some(4));
+        assert_eq!(performance.parallel_segments, Some(2));
+
+        // A segment cannot keep up with decoding
+        let performance = measurement.recommend(4);
+        assert_eq!(performance.detect_threads, Some(1));
+        assert_eq!(performance.ocr_threads, Some(2));
+        assert_eq!(performance.parallel_segments, Some(1));
+
+        let measurement = Measurement {
+            recognized: 0,
+            ..measurement
+        };
+        assert_eq!(measurement.recommend(16).ocr_threads, None);
+
+        // Conversion to RGB is a part of the detection
+        let measurement = Measurement {
+            convert: (ms(250), 50),
+            ..measurement
+        };
+        let performance = measurement.recommend(16);
+        assert_eq!(performance.detect_threads, Some(3));
+        assert_eq!(performance.scale_threads, None);
+    }
+
+    #[test]
+    fn replace_table() {
+        let path = std::env::temp_dir().join(format!(
+            "elden-analyzer-tune-performance-{}.toml",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "# comment\n[performance]\nocr_threads = 1\n\n[detectors.x]\nlevel_width = 16\n",
+        )
+        .unwrap();
+        write_table(&path, "ocr_threads = 3\n").unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            text,
+            "# comment\n[detectors.x]\nlevel_width = 16\n\n[performance]\nocr_threads = 3\n"
+        );
+    }
+}
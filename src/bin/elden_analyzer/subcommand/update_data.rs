@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{self, bail, OptionExt as _};
+use elden_analyzer::{
+    data_package::{self, DataPackage},
+    i18n::Message,
+};
+
+/// Install a data package of dictionaries and the default configuration, or show the installed one
+///
+/// The installed package is used in place of the bundled assets unless `--assets-dir` is specified.
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Data package to install (`.tar` or `.tar.gz`)
+    package: Option<PathBuf>,
+    /// Install the package even if it is older than the installed one
+    #[clap(long)]
+    force: bool,
+    /// Remove the installed package to use the bundled assets again
+    #[clap(long, conflicts_with_all = ["package", "force"])]
+    remove: bool,
+}
+
+impl Args {
+    #[tracing::instrument(name = "update-data", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        const INSTALLED: Message = Message::new(
+            "installed data package",
+            "インストール済みのデータパッケージ",
+        );
+        const NOT_INSTALLED: Message = Message::new(
+            "no data package is installed, using the bundled assets",
+            "データパッケージはインストールされていないため、同梱のアセットを使います",
+        );
+        const REMOVED: Message =
+            Message::new("removed data package", "データパッケージを削除しました");
+
+        let dir = data_package::installed_dir()
+            .ok_or_eyre("data directory of this platform is unknown")?;
+        if self.remove {
+            match data_package::uninstall(&dir)? {
+                true => println!("{REMOVED}: {}", dir.display()),
+                false => println!("{NOT_INSTALLED}"),
+            }
+            return Ok(());
+        }
+
+        let current = data_package::installed(&dir)?;
+        let Some(path) = &self.package else {
+            match &current {
+                Some(manifest) => {
+                    println!("{INSTALLED}: {} ({})", manifest.version, dir.display());
+                    if let Some(description) = &manifest.description {
+                        println!("  {description}");
+                    }
+                }
+                None => println!("{NOT_INSTALLED}"),
+            }
+            return Ok(());
+        };
+
+        let package = DataPackage::read(path)?;
+        if let Some(current) = &current {
+            if package.manifest.is_older_than(current) && !self.force {
+                bail!(
+                    "data package {} is older than the installed one ({}), use --force to install it anyway",
+                    package.manifest.version,
+                    current.version
+                );
+            }
+        }
+        package.install(&dir)?;
+        let assets = package
+            .assets()
+            .map(|asset| asset.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{INSTALLED}: {} [{assets}] ({})",
+            package.manifest.version,
+            dir.display()
+        );
+        if let Some(current) = &current {
+            tracing::info!(
+                from = current.version,
+                to = package.manifest.version,
+                "updated data package"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use color_eyre::eyre;
+use elden_analyzer::{
+    io::{
+        buckets,
+        event_id::SourceId,
+        event_log::Rotation,
+        timeline::{Layout, UncertainCells},
+    },
+    items::ItemCategory,
+    util::ImageLogger,
+};
+use elden_analyzer_kernel::types::{
+    rect::Rect,
+    time::{Duration, Timestamp, TimestampRange},
+};
+use elden_analyzer_video::capture::{ScreenBackend, ScreenTarget, VideoCapture};
+use num_rational::Ratio;
+
+use super::{
+    analyze::{self, IgtArgs, LiveOptions, PreviewArgs, TranslateArgs, DEFAULT_MAX_PENDING_FRAMES},
+    ConfigArgs,
+};
+
+/// Analyze a monitor or a window in real time
+#[derive(clap::Parser, Debug)]
+pub struct Args {
+    /// Capture the monitor with the given name (default: primary monitor)
+    #[clap(long, conflicts_with = "window")]
+    monitor: Option<String>,
+    /// Capture the first window whose title contains the given string
+    #[clap(long)]
+    window: Option<String>,
+    /// Capture only the given region of the monitor or window (`WIDTHxHEIGHT+X+Y`)
+    #[clap(long)]
+    region: Option<Rect>,
+    /// Capture frame rate
+    #[clap(long, default_value = "30")]
+    fps: u32,
+    /// Stop capturing after the given time
+    #[clap(long, default_value = "24:00:00")]
+    duration: Timestamp,
+    /// Maximum number of frames in the analysis pipeline.
+    /// While the pipeline is full, only the latest captured frame waits for room and older ones are dropped.
+    /// [default: `max_pending_frames` in the `[performance]` table of the config file, or 30]
+    #[clap(long)]
+    max_pending_frames: Option<usize>,
+    /// Output span file
+    #[clap(long)]
+    output_span: Option<PathBuf>,
+    /// Output TSV file
+    #[clap(long)]
+    output_tsv: Option<PathBuf>,
+    /// Layout of the output TSV file (`wide` or `long`)
+    #[clap(long, default_value_t)]
+    tsv_layout: Layout,
+    /// How texts not reliably recognized are written in the output TSV file
+    /// (`prefix`, `empty`, `suffix`, `percent` or `columns`)
+    #[clap(long, default_value_t)]
+    tsv_uncertain: UncertainCells,
+    /// Output only spans of items in the categories, such as `weapons,talismans`, in all outputs
+    /// (tools, remembrances, ashes, crafting-materials, upgrade-materials, key-items, sorceries, incantations,
+    /// ashes-of-war, weapons, ammunition, shields, armor, talismans, info, gestures)
+    #[clap(long, value_delimiter = ',')]
+    only_category: Vec<ItemCategory>,
+    /// Output span file of pickups tracked across rows of side items, written when watching stops
+    #[clap(long)]
+    output_pickups: Option<PathBuf>,
+    /// Event log file, to which spans are appended as NDJSON as soon as they are finalized
+    #[clap(long)]
+    event_log: Option<PathBuf>,
+    /// Rotate the event log when it grows larger than the given size in MiB (0: no limit)
+    #[clap(long, default_value = "64", requires = "event_log")]
+    event_log_max_size: u64,
+    /// Rotate the event log when the given time passes since it is started (0: no limit)
+    #[clap(long, default_value = "24:00:00", requires = "event_log")]
+    event_log_max_age: Timestamp,
+    /// Number of rotated event logs to keep
+    #[clap(long, default_value = "7", requires = "event_log")]
+    event_log_keep: usize,
+    #[clap(flatten)]
+    igt: IgtArgs,
+    #[clap(flatten)]
+    translate: TranslateArgs,
+    #[clap(flatten)]
+    preview: PreviewArgs,
+    #[clap(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    #[tracing::instrument(name = "watch", skip_all)]
+    pub(crate) fn run(&self) -> eyre::Result<()> {
+        ImageLogger::init(false)?;
+
+        let config = self.config.load()?;
+        let profile = self.config.profile(&config);
+        let target = match (&self.monitor, &self.window) {
+            (Some(name), _) => ScreenTarget::Monitor(name.clone()),
+            (None, Some(title)) => ScreenTarget::Window(title.clone()),
+            (None, None) => ScreenTarget::PrimaryMonitor,
+        };
+        let backend = ScreenBackend::open(
+            target,
+            self.region,
+            Ratio::from_integer(self.fps.into()),
+            Duration::new(self.duration.as_ratio()),
+        )?;
+        tracing::info!(target = %backend.target(), "start watching");
+        // Captures of the same screen are distinguished by the time they started
+        let source =
+            SourceId::of_name(&format!("{}@{}", backend.target(), Utc::now().to_rfc3339()));
+
+        let mut capture = VideoCapture::from_backend(Box::new(backend));
+        let options = analyze::Options {
+            source,
+            timestamp: TimestampRange::Full,
+            output_span: self.output_span.as_deref(),
+            output_tsv: self.output_tsv.as_deref(),
+            output_json: None,
+            tsv_layout: self.tsv_layout,
+            tsv_uncertain: self.tsv_uncertain,
+            output_pickups: self.output_pickups.as_deref(),
+            output_buckets: None,
+            bucket_window: Duration::new(buckets::DEFAULT_WINDOW),
+            output_srt: None,
+            output_vtt: None,
+            translate_items: self.translate.translation(&config, profile),
+            only_categories: self.only_category.clone(),
+            environment: None,
+            output_scores: None,
+            exact_pts: false,
+            lead_in: 0,
+            promote_window: 1,
+            sample_rate: None,
+            rescan: false,
+            igt: self.igt.builder(),
+            detection_only: false,
+            live: Some(LiveOptions {
+                max_pending_frames: self
+                    .max_pending_frames
+                    .or(config.performance.max_pending_frames)
+                    .unwrap_or(DEFAULT_MAX_PENDING_FRAMES),
+                config_path: self.config.path(),
+                profile,
+                item_list: self.config.item_list(),
+                thresholds: None,
+                event_log: self
+                    .event_log
+                    .as_deref()
+                    .map(|path| (path, self.event_log_rotation())),
+            }),
+            preview: self.preview.options(),
+            stop: None,
+            index: None,
+            emit_events: None,
+        };
+        let summary = analyze::process_capture(&mut capture, &config, options)?;
+        analyze::print_summary(&summary);
+        Ok(())
+    }
+
+    fn event_log_rotation(&self) -> Rotation {
+        let max_age = Duration::new(self.event_log_max_age.as_ratio()).as_msec();
+        Rotation {
+            max_bytes: (self.event_log_max_size > 0).then_some(self.event_log_max_size << 20),
+            max_age: (max_age > 0).then(|| chrono::Duration::milliseconds(max_age)),
+            keep: self.event_log_keep,
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::fmt;
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering},
+};
 
 use elden_analyzer_kernel::types::time::{Duration, FramePosition, Timestamp};
 use indicatif::{ProgressState, ProgressStyle};
@@ -6,6 +9,13 @@ use num_rational::Ratio;
 use tracing::Span;
 use tracing_indicatif::span_ext::IndicatifSpanExt;
 
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// Logs progress as events instead of drawing progress bars.
+pub fn set_headless() {
+    HEADLESS.store(true, Ordering::Relaxed);
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ProgressBarBuilder {
     start: FramePosition,
@@ -23,15 +33,21 @@ impl ProgressBarBuilder {
     }
 
     pub fn build(&self, span: Span) -> ProgressBar {
-        pb_setup(
-            &span,
-            self.start.timestamp(),
-            self.end.timestamp(),
-            self.avg_fps,
-        );
+        let headless = HEADLESS.load(Ordering::Relaxed);
+        if !headless {
+            pb_setup(
+                &span,
+                self.start.timestamp(),
+                self.end.timestamp(),
+                self.avg_fps,
+            );
+        }
         ProgressBar {
             start: self.start,
+            end: self.end,
             span,
+            headless,
+            last_percent: AtomicI64::new(-1),
         }
     }
 }
@@ -39,12 +55,67 @@ impl ProgressBarBuilder {
 #[derive(Debug)]
 pub struct ProgressBar {
     start: FramePosition,
+    end: FramePosition,
     span: Span,
+    /// Log each percent of progress instead of drawing the bar
+    headless: bool,
+    last_percent: AtomicI64,
 }
 
 impl ProgressBar {
     pub fn set_position(&self, pos: FramePosition) {
-        pb_set_position(&self.span, pos, self.start.timestamp())
+        if self.headless {
+            self.log_position(pos);
+        } else {
+            pb_set_position(&self.span, pos, self.start.timestamp())
+        }
+    }
+
+    fn log_position(&self, pos: FramePosition) {
+        let start = self.start.timestamp();
+        let end = self.end.timestamp();
+        let total = (end - start).as_msec().max(1);
+        let percent = ((pos.timestamp() - start).as_msec() * 100 / total).clamp(0, 100);
+        if self.last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+            let _span = self.span.enter();
+            tracing::info!(position = %pos.timestamp(), %end, percent, "progress");
+        }
+    }
+}
+
+/// Progress of files analyzed concurrently, drawn above the progress bars of the files.
+#[derive(Debug)]
+pub struct FileProgress {
+    span: Span,
+    headless: bool,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl FileProgress {
+    pub fn new(span: Span, total: usize) -> Self {
+        let headless = HEADLESS.load(Ordering::Relaxed);
+        if !headless {
+            static TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] {pos}/{len} files";
+            span.pb_set_style(&ProgressStyle::with_template(TEMPLATE).unwrap());
+            span.pb_set_length(total as u64);
+        }
+        Self {
+            span,
+            headless,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn finish_file(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.headless {
+            let _span = self.span.enter();
+            tracing::info!(done, total = self.total, "files progress");
+        } else {
+            self.span.pb_inc(1);
+        }
     }
 }
 
@@ -53,7 +124,8 @@ fn pb_setup(span: &Span, start: Timestamp, end: Timestamp, avg_fps: Ratio<i64>)
         return;
     }
 
-    static TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] {wide_bar:.cyan/blue}\n    {cur_pos}/{end_pos} ({per_sec}, {fps}, ETA: {eta_precise})";
+    // Bars of files and segments analyzed concurrently are told apart by the fields of their spans
+    static TEMPLATE: &str = "{span_child_prefix}{spinner:.green} {span_name}{{{span_fields}}} [{elapsed_precise}] {wide_bar:.cyan/blue}\n    {cur_pos}/{end_pos} ({per_sec}, {fps}, ETA: {eta_precise})";
     span.pb_set_style(
         &ProgressStyle::with_template(TEMPLATE)
             .unwrap()
@@ -0,0 +1,61 @@
+//! Metadata of the build, to match issue reports and archived outputs with exact builds.
+
+use chrono::{DateTime, NaiveDate};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit hash of the source, or `None` if it is not built in a git repository
+pub const GIT_HASH: Option<&str> = option_env!("ELDEN_ANALYZER_GIT_HASH");
+
+const BUILD_TIMESTAMP: Option<&str> = option_env!("ELDEN_ANALYZER_BUILD_TIMESTAMP");
+
+/// Returns the date (UTC) of the build, given by `SOURCE_DATE_EPOCH` or the date of the commit built,
+/// so that builds of the same source are identical.
+pub fn build_date() -> Option<NaiveDate> {
+    let secs = BUILD_TIMESTAMP?.parse().ok()?;
+    Some(DateTime::from_timestamp(secs, 0)?.date_naive())
+}
+
+/// Returns the names of the enabled cargo features.
+pub fn features() -> Vec<&'static str> {
+    [
+        ("ocr-tesseract", cfg!(feature = "ocr-tesseract")),
+        ("static-ffmpeg", cfg!(feature = "static-ffmpeg")),
+        ("media-foundation", cfg!(feature = "media-foundation")),
+        ("screen-capture", cfg!(feature = "screen-capture")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+/// Returns a line describing the build, written at the top of output files.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::build_info;
+///
+/// assert!(build_info::stamp().starts_with(&format!("elden-analyzer {}", build_info::VERSION)));
+/// ```
+pub fn stamp() -> String {
+    let (commit, date) = commit_and_date();
+    format!(
+        "elden-analyzer {VERSION} (commit {commit}, built {date}, features: {})",
+        features().join(",")
+    )
+}
+
+/// Returns the description of the build shown by `--version --verbose`.
+pub fn verbose() -> String {
+    let (commit, date) = commit_and_date();
+    format!(
+        "elden-analyzer {VERSION}\ncommit: {commit}\nbuild date: {date}\nfeatures: {}",
+        features().join(", ")
+    )
+}
+
+fn commit_and_date() -> (&'static str, String) {
+    let commit = GIT_HASH.unwrap_or("unknown");
+    let date = build_date().map_or_else(|| "unknown".to_owned(), |date| date.to_string());
+    (commit, date)
+}
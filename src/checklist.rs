@@ -0,0 +1,101 @@
+//! Checklist of items obtained in a run.
+
+use std::collections::HashMap;
+
+use crate::{
+    io::spans::Span,
+    items::{self, ItemCategory},
+};
+
+/// Known items of some categories with their first pickups.
+#[derive(Debug)]
+pub struct Checklist<'a> {
+    pub entries: Vec<Entry<'a>>,
+}
+
+/// Item in a [`Checklist`].
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub category: ItemCategory,
+    /// Name of the item in Japanese
+    pub item: &'static str,
+    /// The first span of the item, or `None` if it is not obtained
+    pub first: Option<&'a Span>,
+}
+
+impl<'a> Checklist<'a> {
+    /// Builds the checklist of the items in `categories` (all categories if empty) from spans in order of time.
+    ///
+    /// Spans of all components whose texts are known items are pickups, including translated ones.
+    pub fn new(spans: &'a [Span], categories: &[ItemCategory]) -> Self {
+        let mut firsts = HashMap::new();
+        for span in spans {
+            if let Some(item) = items::item_of(&span.text) {
+                firsts.entry(item.name.as_str()).or_insert(span);
+            }
+        }
+
+        let categories = if categories.is_empty() {
+            &ItemCategory::ALL[..]
+        } else {
+            categories
+        };
+        let entries = categories
+            .iter()
+            .flat_map(|&category| items::items_in(category).map(move |item| (category, item)))
+            .map(|(category, item)| Entry {
+                category,
+                item,
+                first: firsts.get(item).copied(),
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Returns the numbers of obtained items and all items in `category`.
+    pub fn progress(&self, category: ItemCategory) -> (usize, usize) {
+        let entries = self.entries.iter().filter(|e| e.category == category);
+        let (obtained, total) = entries.fold((0, 0), |(obtained, total), e| {
+            (obtained + usize::from(e.first.is_some()), total + 1)
+        });
+        (obtained, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_pickups() {
+        let spans = [
+            "00:01:00.000-00:01:02.000 祖霊の王の追憶 (main_item)",
+            "00:02:00.000-00:02:02.000 ??祖霊の王の追憶 (main_item)",
+            "00:03:00.000-00:03:02.000 緋雫の聖杯瓶 ×2 (side_item0)",
+            "00:04:00.000-00:04:02.000 Flask of Crimson Tears (side_item1)",
+            "00:05:00.000-00:05:02.000 関門前の廃墟 (grace_rest)",
+        ]
+        .map(|line| line.parse::<Span>().unwrap());
+        let checklist = Checklist::new(&spans, &[ItemCategory::Remembrances, ItemCategory::Tools]);
+        let first = |item: &str| {
+            checklist
+                .entries
+                .iter()
+                .find(|e| e.item == item)
+                .unwrap()
+                .first
+                .map(|span| span.start.to_string())
+        };
+        assert_eq!(first("祖霊の王の追憶").as_deref(), Some("00:01:00.000"));
+        assert_eq!(first("緋雫の聖杯瓶").as_deref(), Some("00:03:00.000"));
+        assert_eq!(first("青雫の聖杯瓶"), None);
+        assert!(checklist
+            .entries
+            .iter()
+            .all(|e| e.category != ItemCategory::Talismans));
+
+        let (obtained, total) = checklist.progress(ItemCategory::Remembrances);
+        assert_eq!(obtained, 1);
+        assert_eq!(total, items::items_in(ItemCategory::Remembrances).count());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::rect::Rect;
+use elden_analyzer_video::capture::Frame;
+
+use crate::{
+    config::{ComponentConfig, Config, REFERENCE_FRAME_SIZE},
+    image_process::tesseract::Tesseract,
+    operator::{
+        DetectionScore, ExtractText, HistogramBasedComponentDetector, PostProcess, Recognition,
+    },
+    util::Explainer,
+};
+
+use super::{
+    prefixed_scores, text_extractor, Component, Detection, DetectionPayload, ExtractedTexts,
+};
+
+pub(super) fn components(frame_rect: Rect, config: &Config) -> Option<Vec<Box<dyn Component>>> {
+    config
+        .components
+        .iter()
+        .map(|c| {
+            let c = CustomComponent::new(c, config, frame_rect)?;
+            Some(Box::new(c) as Box<_>)
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct CustomComponent {
+    name: String,
+    rect: Rect,
+    /// Name and instance of the detector
+    detector: Option<(String, HistogramBasedComponentDetector)>,
+    extractor: Box<dyn ExtractText>,
+    post_process: PostProcess,
+    charset: Option<HashSet<char>>,
+}
+
+impl Component for CustomComponent {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn detect(&self, frame: &Frame) -> eyre::Result<Detection> {
+        let explainer = Explainer::get();
+        if let Some((_, detector)) = &self.detector {
+            if !explainer.scope(|| "detector".into(), || detector.detect(frame))? {
+                return Ok(Detection::Absent);
+            }
+        } else {
+            explainer.note(|| "no detector => always found".into());
+        }
+        Ok(Detection::Found(None))
+    }
+
+    fn prefilter(&self, frame: &Frame) -> bool {
+        self.detector
+            .as_ref()
+            .map_or(true, |(_, detector)| detector.prefilter(frame))
+    }
+
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        let scores = match &self.detector {
+            Some((name, detector)) => prefixed_scores(name, detector.scores(frame)?).collect(),
+            None => vec![],
+        };
+        Ok(scores)
+    }
+
+    fn extract_text(
+        &self,
+        tess: &mut Tesseract,
+        frame: &Frame,
+        _payload: Option<DetectionPayload>,
+    ) -> eyre::Result<ExtractedTexts> {
+        let res = self.extractor.extract_text(tess, frame, None)?;
+        let res = self.check_charset(res);
+        Ok(ExtractedTexts {
+            result: vec![res],
+            rarity: None,
+        })
+    }
+}
+
+impl CustomComponent {
+    fn new(component: &ComponentConfig, config: &Config, frame_rect: Rect) -> Option<Self> {
+        let (width, height) = REFERENCE_FRAME_SIZE;
+        let reference_rect = Rect::at(0, 0).of_size(width, height);
+        let base = component.base_rect.to_rect(reference_rect)?;
+        let base_rect = component.base_rect.to_clip_rect(reference_rect)?;
+        let text_rect = component.text_rect.to_clip_rect(base)?;
+
+        let rect = base_rect.clip(frame_rect)?;
+        let detector = match &component.detector {
+            Some(name) => {
+                let base_size = (base.width(), base.height());
+                let builder = config.detectors.get(name)?.builder(base_rect, base_size)?;
+                Some((name.clone(), builder.build(frame_rect)?))
+            }
+            None => None,
+        };
+        let post_process = component.post_process.localized(config.text_lang());
+        let extractor = text_extractor(
+            frame_rect,
+            base_rect,
+            text_rect,
+            post_process,
+            component.align,
+            config.pipeline.ocr_text_height,
+            config.glyph_atlas_for(component.recognizer),
+        )?;
+
+        Some(Self {
+            name: component.name.clone(),
+            rect,
+            detector,
+            extractor,
+            post_process,
+            charset: component.charset.as_ref().map(|s| s.chars().collect()),
+        })
+    }
+
+    fn check_charset(&self, res: Recognition) -> Recognition {
+        let Some(charset) = &self.charset else {
+            return res;
+        };
+        let explainer = Explainer::get();
+        match res {
+            Recognition::Found(text, conf) | Recognition::Possible(text, conf)
+                if text.is_empty() || !text.chars().all(|ch| charset.contains(&ch)) =>
+            {
+                explainer.note(|| format!("{text:?} has characters out of charset => possible"));
+                Recognition::Possible(text, conf)
+            }
+            // Without post-processing, the charset is the only clue to validate the text
+            Recognition::Possible(text, conf) if self.post_process == PostProcess::None => {
+                explainer.note(|| format!("{text:?} matches charset => found"));
+                Recognition::Found(text, conf)
+            }
+            res => res,
+        }
+    }
+}
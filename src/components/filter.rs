@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::{self, bail};
+
+use crate::io::spans;
+
+use super::side_item_row;
+
+/// Components selected by names, patterns and groups, such as `--component` of subcommands.
+///
+/// * A name selects the component of the name. [`spans::TRACKED_SIDE_ITEM`] also selects the side items of all rows.
+/// * A pattern ending with `*` selects components whose names start with the rest, such as `side_item*`.
+/// * A name of a group defined in `groups` of the config selects its members, which may be other groups.
+///
+/// An empty filter selects all components.
+///
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use elden_analyzer::components::ComponentFilter;
+///
+/// let groups = BTreeMap::from([(
+///     "hud".to_owned(),
+///     vec!["side_item*".to_owned(), "main_item".to_owned()],
+/// )]);
+/// let filter = ComponentFilter::with_groups(&["hud".to_owned()], &groups).unwrap();
+/// assert!(filter.matches("side_item2"));
+/// assert!(filter.matches("main_item"));
+/// assert!(!filter.matches("boss_bar"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ComponentFilter {
+    patterns: Vec<String>,
+}
+
+impl ComponentFilter {
+    /// Returns the filter selecting components by names and patterns, without groups.
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the filter selecting components by names, patterns and names of `groups`.
+    ///
+    /// Fails if a group contains itself.
+    pub fn with_groups(
+        names: &[String],
+        groups: &BTreeMap<String, Vec<String>>,
+    ) -> eyre::Result<Self> {
+        let mut patterns = vec![];
+        for name in names {
+            expand(name, groups, &mut vec![], &mut patterns)?;
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Returns `true` if the filter selects all components.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns `true` if the component named `name` is selected.
+    pub fn matches(&self, name: &str) -> bool {
+        self.is_empty()
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern_matches(pattern, name))
+    }
+}
+
+/// Appends the names and patterns selected by `name` to `patterns`, where `stack` is the groups being expanded.
+fn expand<'a>(
+    name: &'a str,
+    groups: &'a BTreeMap<String, Vec<String>>,
+    stack: &mut Vec<&'a str>,
+    patterns: &mut Vec<String>,
+) -> eyre::Result<()> {
+    let Some(members) = groups.get(name) else {
+        if !patterns.iter().any(|pattern| pattern == name) {
+            patterns.push(name.to_owned());
+        }
+        return Ok(());
+    };
+    if stack.contains(&name) {
+        bail!("group `{name}` contains itself");
+    }
+    stack.push(name);
+    for member in members {
+        expand(member, groups, stack, patterns)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => {
+            pattern == name
+                || (pattern == spans::TRACKED_SIDE_ITEM && side_item_row(name).is_some())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_and_recursive_groups() {
+        let group = |name: &str, members: &[&str]| {
+            (
+                name.to_owned(),
+                members.iter().map(|m| m.to_string()).collect(),
+            )
+        };
+        let groups = BTreeMap::from([
+            group("hud", &["side_item*", "main_item"]),
+            group("combat", &["boss_bar", "respawn"]),
+            group("all", &["hud", "combat", "main_item"]),
+            group("a", &["b"]),
+            group("b", &["a"]),
+        ]);
+
+        let filter = ComponentFilter::with_groups(&["all".to_owned()], &groups).unwrap();
+        assert_eq!(
+            filter.patterns,
+            ["side_item*", "main_item", "boss_bar", "respawn"]
+        );
+        assert!(filter.matches("side_item0"));
+        assert!(!filter.matches("runes"));
+
+        let filter = ComponentFilter::new(["side_item"]);
+        assert!(filter.matches("side_item3"));
+        assert!(filter.matches("side_item"));
+        assert!(!filter.matches("main_item"));
+        assert!(ComponentFilter::default().matches("runes"));
+
+        let err = ComponentFilter::with_groups(&["a".to_owned()], &groups).unwrap_err();
+        assert_eq!(err.to_string(), "group `a` contains itself");
+    }
+}
@@ -1,35 +1,67 @@
-use color_eyre::eyre;
+use std::sync::Arc;
+
+use color_eyre::eyre::{self, eyre};
 use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
 use elden_analyzer_video::capture::Frame;
 use num_rational::Ratio;
 
 use crate::{
     algorithm::FindLineSegments,
+    config::{Config, DetectorConfig, LineDetectorConfig, PipelineConfig},
+    i18n::Lang,
     image_process::{
         h_lines::{HLineType, HLines},
         line_finder::LineFinder,
         tesseract::Tesseract,
     },
     operator::{
-        DetectComponent, DetectionKind, ExtractText, LineBasedComponentDetectorBuilder,
-        PostProcess, RectTextExtractorBuilder, TextAlign,
+        Confidence, DetectComponent, DetectionKind, DetectionScore, ExtractText, GlyphAtlas,
+        HistogramBasedComponentDetector, LineBasedComponentDetectorBuilder, PostProcess,
+        RarityClassifier, RarityClassifierBuilder, Recognition, RectTextExtractorBuilder,
+        TextAlign,
     },
+    util::Explainer,
 };
 
-use super::{Component, Detection, DetectionPayload, ExtractedTexts};
+use super::{
+    frame_scale, prefixed_scores, text_extractor, Component, Detection, DetectionPayload,
+    ExtractedTexts,
+};
 
 pub(super) const NAME: &str = "main_item";
 
-pub(super) fn component(frame_rect: Rect) -> Option<Box<dyn Component>> {
-    let c = MainItemComponent::new(frame_rect)?;
+/// Name of the detector in the config for counts of stackable items
+const COUNT_DETECTOR: &str = "main_item_count";
+pub(super) const DETECTORS: &[(&str, (u32, u32))] = &[(COUNT_DETECTOR, MAIN_ITEM_BOX_SIZE)];
+
+pub(super) fn component(frame_rect: Rect, config: &Config) -> Option<Box<dyn Component>> {
+    let count_config = config.detectors.get(COUNT_DETECTOR)?;
+    let c = MainItemComponent::new(
+        frame_rect,
+        count_config,
+        &config.pipeline,
+        config.text_lang(),
+        config.glyph_atlas_for(config.count_recognizer),
+    )?;
     Some(Box::new(c) as _)
 }
 
+#[derive(Debug)]
+struct Payload {
+    has_count: bool,
+}
+
 #[derive(Debug)]
 struct MainItemComponent {
     name: String,
+    rect: Rect,
     detector: Box<dyn DetectComponent>,
+    count_detector: HistogramBasedComponentDetector,
     extractor: Box<dyn ExtractText>,
+    rarity_classifier: RarityClassifier,
+    count_extractor: Box<dyn ExtractText>,
+    /// Extractors of the supplementary lines under the item name
+    note_extractors: Vec<Box<dyn ExtractText>>,
 }
 
 impl Component for MainItemComponent {
@@ -37,72 +69,186 @@ impl Component for MainItemComponent {
         &self.name
     }
 
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
     fn detect(&self, frame: &Frame) -> eyre::Result<Detection> {
-        let det = match self.detector.detect(frame)? {
-            DetectionKind::Found => Detection::Found(None),
-            DetectionKind::Possible => Detection::Possible(None),
-            DetectionKind::Absent => Detection::Absent,
+        let kind = self.detector.detect(frame)?;
+        if kind == DetectionKind::Absent {
+            return Ok(Detection::Absent);
+        }
+        let has_count = Explainer::get().scope(
+            || format!("detector {COUNT_DETECTOR}"),
+            || self.count_detector.detect(frame),
+        )?;
+        let payload = Box::new(Payload { has_count });
+        let det = match kind {
+            DetectionKind::Found => Detection::Found(Some(payload)),
+            DetectionKind::Possible => Detection::Possible(Some(payload)),
+            DetectionKind::Absent => unreachable!(),
         };
         Ok(det)
     }
 
+    fn prefilter(&self, frame: &Frame) -> bool {
+        self.detector.prefilter(frame)
+    }
+
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        let mut scores = self.detector.scores(frame)?;
+        scores.extend(prefixed_scores(
+            COUNT_DETECTOR,
+            self.count_detector.scores(frame)?,
+        ));
+        Ok(scores)
+    }
+
     fn extract_text(
         &self,
         tess: &mut Tesseract,
         frame: &Frame,
-        _payload: Option<DetectionPayload>,
+        payload: Option<DetectionPayload>,
     ) -> eyre::Result<ExtractedTexts> {
-        let res = self.extractor.extract_text(tess, frame, None)?;
-        Ok(ExtractedTexts { result: vec![res] })
+        let payload = payload
+            .map(|p| {
+                p.downcast::<Payload>()
+                    .map_err(|_| eyre!("invalid payload"))
+            })
+            .transpose()?;
+
+        let explainer = Explainer::get();
+        let text = explainer.scope(
+            || "name".into(),
+            || self.extractor.extract_text(tess, frame, None),
+        )?;
+        let rarity = self.rarity_classifier.classify(frame)?;
+        // Texts are recognized even if the component is not detected, e.g. in `interactive`
+        let count = if payload.map(|p| p.has_count) != Some(false) {
+            explainer
+                .scope(
+                    || "count".into(),
+                    || self.count_extractor.extract_text(tess, frame, None),
+                )?
+                .map_text(|text| format!("×{text}"))
+        } else {
+            explainer.note(|| "no count".into());
+            Recognition::Found(String::new(), Confidence::new(100))
+        };
+        let mut result = vec![text, count];
+        for (i, extractor) in self.note_extractors.iter().enumerate() {
+            result.push(explainer.scope(
+                || format!("note {i}"),
+                || extractor.extract_text(tess, frame, None),
+            )?);
+        }
+        Ok(ExtractedTexts { result, rarity })
     }
 }
 
 impl MainItemComponent {
-    fn new(frame_rect: Rect) -> Option<Self> {
-        let detector = new_detector(frame_rect)?;
-        let extractor = new_extractor(frame_rect)?;
+    fn new(
+        frame_rect: Rect,
+        count_config: &DetectorConfig,
+        pipeline: &PipelineConfig,
+        lang: Lang,
+        count_atlas: Option<&Arc<GlyphAtlas>>,
+    ) -> Option<Self> {
+        let rect = MAIN_ITEM_BOX_IN_FRAME.clip(frame_rect)?;
+        let detector = new_detector(frame_rect, &pipeline.main_item)?;
+        let count_detector = count_config
+            .builder(MAIN_ITEM_BOX_IN_FRAME, MAIN_ITEM_BOX_SIZE)?
+            .build(frame_rect)?;
+        let extractor = new_extractor(
+            frame_rect,
+            MAIN_ITEM_TEXT_IN_BOX,
+            PostProcess::ItemText.localized(lang),
+            pipeline.ocr_text_height,
+        )?;
+        let rarity_classifier = RarityClassifierBuilder {
+            base_rect: MAIN_ITEM_BOX_IN_FRAME,
+            text_rect: MAIN_ITEM_TEXT_IN_BOX,
+        }
+        .build(frame_rect)?;
+        let count_extractor = text_extractor(
+            frame_rect,
+            MAIN_ITEM_BOX_IN_FRAME,
+            MAIN_ITEM_COUNT_IN_BOX,
+            PostProcess::Digits,
+            TextAlign::Center,
+            pipeline.ocr_text_height,
+            count_atlas,
+        )?;
+        let note_extractors = MAIN_ITEM_NOTES_IN_BOX
+            .iter()
+            .map(|rect| {
+                new_extractor(
+                    frame_rect,
+                    *rect,
+                    PostProcess::ItemNote,
+                    pipeline.ocr_text_height,
+                )
+            })
+            .collect::<Option<_>>()?;
 
         Some(Self {
             name: NAME.to_string(),
+            rect,
             detector,
+            count_detector,
             extractor,
+            rarity_classifier,
+            count_extractor,
+            note_extractors,
         })
     }
 }
 
-fn new_detector(frame_rect: Rect) -> Option<Box<dyn DetectComponent>> {
+pub(super) fn count_rect() -> ClipRect {
+    MAIN_ITEM_COUNT_IN_BOX
+}
+
+fn new_detector(frame_rect: Rect, config: &LineDetectorConfig) -> Option<Box<dyn DetectComponent>> {
     let mut horizontal_line_clip_rect = MAIN_ITEM_HBARS_IN_BOX.to_vec();
     horizontal_line_clip_rect.sort_by_key(|(_ty, rect)| ClipRect::area(rect)); // sort by ascending area
 
     let d = LineBasedComponentDetectorBuilder {
         line_finder: LineFinder {
+            median_radius: 5,
             h_canny: HLines {
-                sigma: 1.0,
-                low_threshold: 0,
-                high_threshold: 10,
+                sigma: config.sigma,
+                low_threshold: config.low_threshold,
+                high_threshold: config.high_threshold,
             },
             find_line_segments: FindLineSegments {
                 vote_threshold: 60,
                 min_line_len: 10,
                 max_line_gap: 15,
             },
-        },
+        }
+        .scaled(frame_scale(frame_rect)),
         base_rect: MAIN_ITEM_BOX_IN_FRAME,
         horizontal_line_clip_rect,
 
-        found_threshold: 0.80,
-        possible_threshold: 0.20,
+        found_threshold: config.found_threshold,
+        possible_threshold: config.possible_threshold,
     }
     .build(frame_rect)?;
     Some(Box::new(d))
 }
 
-fn new_extractor(frame_rect: Rect) -> Option<Box<dyn ExtractText>> {
+fn new_extractor(
+    frame_rect: Rect,
+    text_rect: ClipRect,
+    post_process: PostProcess,
+    text_height: u32,
+) -> Option<Box<dyn ExtractText>> {
     let e = RectTextExtractorBuilder {
         base_rect: MAIN_ITEM_BOX_IN_FRAME,
-        text_rect: MAIN_ITEM_TEXT_IN_BOX,
-        post_process: PostProcess::ItemText,
+        text_rect,
+        post_process,
         align: TextAlign::Center,
+        text_height,
     }
     .build(frame_rect)?;
     Some(Box::new(e))
@@ -113,6 +259,9 @@ const MAIN_ITEM_BOX_IN_FRAME: ClipRect = ClipRect::new(
     (Ratio::new_raw(1750, 10000), Ratio::new_raw(3150, 10000)),
 );
 
+/// Size of the main item box in 1920x1080 frames, used for rects of detector areas in the config
+const MAIN_ITEM_BOX_SIZE: (u32, u32) = (672, 273);
+
 const MAIN_ITEM_HBARS_IN_BOX: &[(HLineType, ClipRect)] = &[
     // TOP
     (
@@ -151,3 +300,21 @@ const MAIN_ITEM_TEXT_IN_BOX: ClipRect = ClipRect::new(
     (Ratio::new_raw(-24, 100), Ratio::new_raw(-35, 100)),
     (Ratio::new_raw(24, 100), Ratio::new_raw(-26, 100)),
 );
+
+/// Digits of the count following `×` right of the item name
+const MAIN_ITEM_COUNT_IN_BOX: ClipRect = ClipRect::new(
+    (Ratio::new_raw(27, 100), Ratio::new_raw(-35, 100)),
+    (Ratio::new_raw(33, 100), Ratio::new_raw(-26, 100)),
+);
+
+/// Supplementary lines shown under the icon of rare items
+const MAIN_ITEM_NOTES_IN_BOX: [ClipRect; 2] = [
+    ClipRect::new(
+        (Ratio::new_raw(-40, 100), Ratio::new_raw(24, 100)),
+        (Ratio::new_raw(40, 100), Ratio::new_raw(32, 100)),
+    ),
+    ClipRect::new(
+        (Ratio::new_raw(-40, 100), Ratio::new_raw(33, 100)),
+        (Ratio::new_raw(40, 100), Ratio::new_raw(41, 100)),
+    ),
+];
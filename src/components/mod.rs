@@ -1,18 +1,42 @@
-use std::{any::Any, array, fmt, iter};
+use std::{
+    any::Any,
+    array, fmt, iter, slice,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    vec,
+};
 
 use color_eyre::eyre;
 use elden_analyzer_collections::array::array_from_iter;
-use elden_analyzer_kernel::types::rect::Rect;
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
 use elden_analyzer_video::capture::Frame;
 
 use crate::{
+    config::{Config, REFERENCE_FRAME_SIZE},
+    i18n::{Lang, Message},
     image_process::tesseract::Tesseract,
-    operator::{DetectionKind, ExtractText, Recognition},
+    io::spans,
+    items::Rarity,
+    operator::{
+        DetectionKind, DetectionScore, ExtractText, GlyphAtlas, GlyphMatchExtractorBuilder,
+        PostProcess, Recognition, RectTextExtractorBuilder, TextAlign,
+    },
+    util::{Explainer, ImageLogger},
 };
 
+mod custom;
+mod filter;
 mod main_item;
+mod respawn;
 mod side_item;
 
+pub use self::{
+    filter::ComponentFilter,
+    respawn::{RespawnKind, RespawnKindParseError},
+};
+
 pub type DetectionPayload = Box<dyn Any + Send + Sync + 'static>;
 
 #[derive(Debug)]
@@ -35,6 +59,8 @@ impl Detection {
 #[derive(Debug, Default, Clone)]
 pub struct ExtractedTexts {
     pub result: Vec<Recognition>,
+    /// Rarity of the item inferred from the color of its name, for components showing items
+    pub rarity: Option<Rarity>,
 }
 
 impl fmt::Display for ExtractedTexts {
@@ -48,13 +74,26 @@ impl fmt::Display for ExtractedTexts {
 
         f.debug_list()
             .entries(self.result.iter().map(DebugElem))
-            .finish()
+            .finish()?;
+        if let Some(rarity) = self.rarity {
+            write!(f, " ({rarity})")?;
+        }
+        Ok(())
     }
 }
 
 pub trait Component: fmt::Debug + Send + Sync + 'static {
     fn name(&self) -> &str;
+    fn rect(&self) -> Rect;
     fn detect(&self, frame: &Frame) -> eyre::Result<Detection>;
+    /// Returns `false` if the component is clearly absent, checked by sampling a few pixels of the luma plane.
+    ///
+    /// This is the first stage of [`detect_cascade`], which is cheap enough to run on every frame.
+    fn prefilter(&self, _frame: &Frame) -> bool {
+        true
+    }
+    /// Measures the scores of all detectors of the component.
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>>;
     fn extract_text(
         &self,
         tess: &mut Tesseract,
@@ -63,31 +102,259 @@ pub trait Component: fmt::Debug + Send + Sync + 'static {
     ) -> eyre::Result<ExtractedTexts>;
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Detects `component` in a cascade, where the prefilter rejects clearly absent frames before `detect` runs.
+///
+/// Rejections of each stage are counted in `stats` if given.
+pub fn detect_cascade(
+    component: &dyn Component,
+    frame: &Frame,
+    stats: Option<&CascadeStats>,
+) -> eyre::Result<Detection> {
+    let passed = Explainer::get().scope(|| "prefilter".into(), || component.prefilter(frame));
+    let detection = if passed {
+        let _subject = ImageLogger::enter_subject(frame.position().index(), component.name());
+        let _stage = ImageLogger::enter_stage("detect");
+        component.detect(frame)?
+    } else {
+        Detection::Absent
+    };
+    if let Some(stats) = stats {
+        stats.frames.fetch_add(1, Ordering::Relaxed);
+        let rejected = match (passed, &detection) {
+            (false, _) => &stats.prefilter_rejected,
+            (true, Detection::Absent) => &stats.detector_rejected,
+            (true, _) => return Ok(detection),
+        };
+        rejected.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(detection)
+}
+
+/// Numbers of frames rejected by each stage of [`detect_cascade`].
+#[derive(Debug, Default)]
+pub struct CascadeStats {
+    frames: AtomicUsize,
+    prefilter_rejected: AtomicUsize,
+    /// Frames passing the prefilter and rejected by the detectors
+    detector_rejected: AtomicUsize,
+}
+
+impl CascadeStats {
+    pub fn frames(&self) -> usize {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    /// Returns the ratio of frames rejected by the prefilter to all frames.
+    pub fn prefilter_rejection_rate(&self) -> f64 {
+        self.prefilter_rejected.load(Ordering::Relaxed) as f64 / self.frames().max(1) as f64
+    }
+
+    /// Returns the ratio of frames rejected by the detectors to frames passing the prefilter.
+    pub fn detector_rejection_rate(&self) -> f64 {
+        let passed = self.frames() - self.prefilter_rejected.load(Ordering::Relaxed);
+        self.detector_rejected.load(Ordering::Relaxed) as f64 / passed.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ComponentContainer<T> {
     pub main_item: T,
     pub side_item: [T; side_item::COUNT],
+    /// Dialog choosing where to respawn after deaths near a stake of Marika
+    pub respawn: T,
+    /// Components defined in the config, including the ones of the default config such as `grace_rest` and `runes`
+    pub custom: Vec<T>,
 }
 
-const COMPONENTS_COUNT: usize = 1 + side_item::COUNT;
-
 pub type Components = ComponentContainer<Box<dyn Component>>;
 pub type TextRecognizerComponents = ComponentContainer<Box<dyn ExtractText>>;
 
 impl Components {
-    pub fn new(frame_rect: Rect) -> Option<Self> {
+    pub fn new(frame_rect: Rect, config: &Config) -> Option<Self> {
         Some(Self {
-            main_item: main_item::component(frame_rect)?,
-            side_item: side_item::components(frame_rect)?,
+            main_item: main_item::component(frame_rect, config)?,
+            side_item: side_item::components(frame_rect, config)?,
+            respawn: respawn::component(frame_rect, config)?,
+            custom: custom::components(frame_rect, config)?,
         })
     }
 }
 
+/// Returns the detectors in the config required by built-in components, with the names of the components
+/// and their sizes in 1920x1080 frames, within which areas of the detectors must be.
+pub(crate) fn builtin_detectors() -> impl Iterator<Item = (&'static str, &'static str, (u32, u32))>
+{
+    [
+        (main_item::NAME, main_item::DETECTORS),
+        ("side_item*", side_item::DETECTORS),
+        (respawn::NAME, respawn::DETECTORS),
+    ]
+    .into_iter()
+    .flat_map(|(component, detectors)| {
+        detectors
+            .iter()
+            .map(move |(detector, size)| (component, *detector, *size))
+    })
+}
+
+pub(crate) fn is_builtin_name(name: &str) -> bool {
+    [main_item::NAME, respawn::NAME].contains(&name) || side_item::NAMES.contains(&name)
+}
+
+/// Returns the rect of the digits of a count with `digits` digits in the component named `name`,
+/// or `None` if the component has no count.
+pub fn count_rect(name: &str, digits: usize) -> Option<ClipRect> {
+    if name == main_item::NAME {
+        return Some(main_item::count_rect());
+    }
+    side_item_row(name).and_then(|_| side_item::count_rect(digits))
+}
+
+/// Returns the row of the side item component named `name`, counted from the bottom.
+pub fn side_item_row(name: &str) -> Option<usize> {
+    side_item::NAMES.iter().position(|n| *n == name)
+}
+
+/// Returns whether spans of the component named `name` are pickups of items,
+/// including side items tracked across rows (see [`spans::track_side_items`]).
+pub fn is_pickup_name(name: &str) -> bool {
+    name == main_item::NAME || name == spans::TRACKED_SIDE_ITEM || side_item_row(name).is_some()
+}
+
+/// Returns whether spans of the component named `name` are numbers of runes held.
+pub fn is_runes_name(name: &str) -> bool {
+    name == RUNES_NAME
+}
+
+/// Returns whether spans of the component named `name` are encounters with bosses.
+pub fn is_boss_name(name: &str) -> bool {
+    name == BOSS_BAR_NAME
+}
+
+/// Name of the rune counter in the default config
+const RUNES_NAME: &str = "runes";
+/// Name of the health bar of bosses in the default config
+const BOSS_BAR_NAME: &str = "boss_bar";
+
+const MAIN_ITEM_LABEL: Message = Message::new("main item", "メインアイテム");
+const SIDE_ITEM_LABEL: Message = Message::new("side item", "サイドアイテム");
+const RESPAWN_LABEL: Message = Message::new("respawn", "復活");
+/// Names shown to users of the components in the default config
+const DEFAULT_CONFIG_LABELS: [(&str, Message); 5] = [
+    ("grace_rest", Message::new("grace rest", "祝福で休息")),
+    (BOSS_BAR_NAME, Message::new("boss", "ボス")),
+    (RUNES_NAME, Message::new("runes", "ルーン")),
+    ("area_banner", Message::new("area", "地名")),
+    (
+        "grace_discovered",
+        Message::new("grace discovered", "祝福発見"),
+    ),
+];
+
+/// Returns the name of the component shown to users in the current language.
+///
+/// User-defined components are shown with their names in the config.
+pub fn display_name(name: &str) -> String {
+    if name == main_item::NAME {
+        return MAIN_ITEM_LABEL.to_string();
+    }
+    if name == respawn::NAME {
+        return RESPAWN_LABEL.to_string();
+    }
+    if let Some((_, label)) = DEFAULT_CONFIG_LABELS.iter().find(|(n, _)| *n == name) {
+        return label.to_string();
+    }
+    match side_item_row(name) {
+        Some(i) if Lang::current() == Lang::Ja => format!("{SIDE_ITEM_LABEL}{i}"),
+        Some(i) => format!("{SIDE_ITEM_LABEL} {i}"),
+        None => name.to_owned(),
+    }
+}
+
+/// Size of the reference frame, in which positions of built-in components are written
+const REFERENCE_SIZE: (i32, i32) = (REFERENCE_FRAME_SIZE.0 as i32, REFERENCE_FRAME_SIZE.1 as i32);
+
+/// Returns the ratio of the height of the frame to the reference frame.
+///
+/// Rects of built-in components are relative to the frame and scale by themselves,
+/// but lengths in pixels in parameters of image processing are tuned for the reference frame and scaled by this ratio.
+fn frame_scale(frame_rect: Rect) -> f32 {
+    frame_rect.height() as f32 / REFERENCE_FRAME_SIZE.1 as f32
+}
+
+/// Creates the extractor of a text, recognized by matching against `atlas` if any, or by Tesseract otherwise.
+fn text_extractor(
+    frame_rect: Rect,
+    base_rect: ClipRect,
+    text_rect: ClipRect,
+    post_process: PostProcess,
+    align: TextAlign,
+    text_height: u32,
+    atlas: Option<&Arc<GlyphAtlas>>,
+) -> Option<Box<dyn ExtractText>> {
+    let e: Box<dyn ExtractText> = match atlas {
+        Some(atlas) => Box::new(
+            GlyphMatchExtractorBuilder {
+                base_rect,
+                text_rect,
+                atlas: Arc::clone(atlas),
+                post_process,
+            }
+            .build(frame_rect)?,
+        ),
+        None => Box::new(
+            RectTextExtractorBuilder {
+                base_rect,
+                text_rect,
+                post_process,
+                align,
+                text_height,
+            }
+            .build(frame_rect)?,
+        ),
+    };
+    Some(e)
+}
+
+/// Names scores of a histogram-based detector as `DETECTOR.AREA`, same as `analyze --sweep`.
+fn prefixed_scores(
+    detector: &str,
+    scores: Vec<DetectionScore>,
+) -> impl Iterator<Item = DetectionScore> + '_ {
+    scores.into_iter().map(move |score| DetectionScore {
+        name: format!("{detector}.{}", score.name),
+        ..score
+    })
+}
+
 impl<T> ComponentContainer<T> {
-    pub fn from_fn(mut f: impl FnMut(&'static str) -> T) -> Self {
-        Self {
-            main_item: f(main_item::NAME),
-            side_item: side_item::NAMES.map(f),
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> ComponentContainer<U> {
+        let Self {
+            main_item,
+            side_item,
+            respawn,
+            custom,
+        } = self;
+        ComponentContainer {
+            main_item: f(main_item),
+            side_item: side_item.map(&mut f),
+            respawn: f(respawn),
+            custom: custom.into_iter().map(f).collect(),
+        }
+    }
+
+    pub fn each_ref(&self) -> ComponentContainer<&T> {
+        let Self {
+            main_item,
+            side_item,
+            respawn,
+            custom,
+        } = self;
+        ComponentContainer {
+            main_item,
+            side_item: side_item.each_ref(),
+            respawn,
+            custom: custom.iter().collect(),
         }
     }
 
@@ -95,9 +362,13 @@ impl<T> ComponentContainer<T> {
         let Self {
             main_item,
             side_item,
+            respawn,
+            custom,
         } = self;
-        let it = iter::once(main_item).chain(side_item);
-        let iter = array_from_iter(it).into_iter();
+        let iter = iter::once(main_item)
+            .chain(side_item)
+            .chain(iter::once(respawn))
+            .chain(custom);
         Iter { iter }
     }
 
@@ -105,9 +376,13 @@ impl<T> ComponentContainer<T> {
         let Self {
             main_item,
             side_item,
+            respawn,
+            custom,
         } = self;
-        let it = iter::once(main_item).chain(side_item);
-        let iter = array_from_iter(it).into_iter();
+        let iter = iter::once(main_item)
+            .chain(side_item)
+            .chain(iter::once(respawn))
+            .chain(custom);
         IterMut { iter }
     }
 }
@@ -120,11 +395,14 @@ impl<A> FromIterator<A> for ComponentContainer<A> {
         let mut iter = iter.into_iter();
         let main_item = iter.next().unwrap();
         let side_item = array_from_iter(iter.by_ref().take(side_item::COUNT));
-        assert!(iter.next().is_none());
+        let respawn = iter.next().unwrap();
+        let custom = iter.collect();
 
         ComponentContainer {
             main_item,
             side_item,
+            respawn,
+            custom,
         }
     }
 }
@@ -137,9 +415,13 @@ impl<T> IntoIterator for ComponentContainer<T> {
         let Self {
             main_item,
             side_item,
+            respawn,
+            custom,
         } = self;
-        let it = iter::once(main_item).chain(side_item);
-        let iter = array_from_iter(it).into_iter();
+        let iter = iter::once(main_item)
+            .chain(side_item)
+            .chain(iter::once(respawn))
+            .chain(custom);
         IntoIter { iter }
     }
 }
@@ -162,9 +444,12 @@ impl<'a, T> IntoIterator for &'a mut ComponentContainer<T> {
     }
 }
 
+/// Iterator over the built-in components, where `S` iterates over the side items
+type BuiltinIter<T, S> = iter::Chain<iter::Chain<iter::Once<T>, S>, iter::Once<T>>;
+
 #[derive(Debug)]
 pub struct IntoIter<T> {
-    iter: array::IntoIter<T, COMPONENTS_COUNT>,
+    iter: iter::Chain<BuiltinIter<T, array::IntoIter<T, { side_item::COUNT }>>, vec::IntoIter<T>>,
 }
 
 impl<T> Iterator for IntoIter<T> {
@@ -177,7 +462,7 @@ impl<T> Iterator for IntoIter<T> {
 
 #[derive(Debug)]
 pub struct Iter<'a, T> {
-    iter: array::IntoIter<&'a T, COMPONENTS_COUNT>,
+    iter: iter::Chain<BuiltinIter<&'a T, slice::Iter<'a, T>>, slice::Iter<'a, T>>,
 }
 
 impl<'a, T> Iterator for Iter<'a, T> {
@@ -190,7 +475,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
 #[derive(Debug)]
 pub struct IterMut<'a, T> {
-    iter: array::IntoIter<&'a mut T, COMPONENTS_COUNT>,
+    iter: iter::Chain<BuiltinIter<&'a mut T, slice::IterMut<'a, T>>, slice::IterMut<'a, T>>,
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
@@ -200,3 +485,112 @@ impl<'a, T> Iterator for IterMut<'a, T> {
         self.iter.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `rect` scaled from a frame of `from` height to `to` height.
+    fn scale_rect(rect: Rect, from: u32, to: u32) -> (i64, i64, i64, i64) {
+        let scale = |v: i64| v * i64::from(to) / i64::from(from);
+        (
+            scale(rect.left().into()),
+            scale(rect.top().into()),
+            scale(i64::from(rect.right()) + 1),
+            scale(i64::from(rect.bottom()) + 1),
+        )
+    }
+
+    #[test]
+    fn builtin_rects_scale_with_frame() {
+        let config = Config::default();
+        let (width, height) = REFERENCE_FRAME_SIZE;
+        let reference = Components::new(Rect::at(0, 0).of_size(width, height), &config).unwrap();
+        for (width, height) in [(1280, 720), (2560, 1440), (3840, 2160)] {
+            let components =
+                Components::new(Rect::at(0, 0).of_size(width, height), &config).unwrap();
+            for (c, r) in components.iter().zip(&reference) {
+                let (l0, t0, r0, b0) = scale_rect(c.rect(), height, REFERENCE_FRAME_SIZE.1);
+                let (l1, t1, r1, b1) = scale_rect(r.rect(), 1, 1);
+                for (a, b) in [(l0, l1), (t0, t1), (r0, r1), (b0, b1)] {
+                    assert!(
+                        (a - b).abs() <= 2,
+                        "{} at {width}x{height}: {a} != {b}",
+                        c.name()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frame_scale() {
+        assert_eq!(super::frame_scale(Rect::at(0, 0).of_size(1920, 1080)), 1.0);
+        assert_eq!(super::frame_scale(Rect::at(0, 0).of_size(3840, 2160)), 2.0);
+    }
+
+    /// Component whose stages of the cascade always give the same results.
+    #[derive(Debug)]
+    struct FixedComponent {
+        prefilter: bool,
+        detection: DetectionKind,
+    }
+
+    impl Component for FixedComponent {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn rect(&self) -> Rect {
+            Rect::at(0, 0).of_size(1, 1)
+        }
+
+        fn detect(&self, _frame: &Frame) -> eyre::Result<Detection> {
+            Ok(match self.detection {
+                DetectionKind::Found => Detection::Found(None),
+                DetectionKind::Possible => Detection::Possible(None),
+                DetectionKind::Absent => Detection::Absent,
+            })
+        }
+
+        fn prefilter(&self, _frame: &Frame) -> bool {
+            self.prefilter
+        }
+
+        fn scores(&self, _frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+            Ok(vec![])
+        }
+
+        fn extract_text(
+            &self,
+            _tess: &mut Tesseract,
+            _frame: &Frame,
+            _payload: Option<DetectionPayload>,
+        ) -> eyre::Result<ExtractedTexts> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn cascade_rejection_rates() {
+        let stats = CascadeStats::default();
+        let frame = Frame::empty();
+        let cases = [
+            (false, DetectionKind::Found, DetectionKind::Absent),
+            (true, DetectionKind::Absent, DetectionKind::Absent),
+            (true, DetectionKind::Found, DetectionKind::Found),
+            (true, DetectionKind::Possible, DetectionKind::Possible),
+        ];
+        for (prefilter, detection, expected) in cases {
+            let component = FixedComponent {
+                prefilter,
+                detection,
+            };
+            let result = detect_cascade(&component, &frame, Some(&stats)).unwrap();
+            assert_eq!(result.kind(), expected);
+        }
+        assert_eq!(stats.frames(), 4);
+        assert_eq!(stats.prefilter_rejection_rate(), 0.25);
+        assert_eq!(stats.detector_rejection_rate(), 1.0 / 3.0);
+    }
+}
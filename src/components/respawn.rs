@@ -0,0 +1,194 @@
+use std::{fmt, str::FromStr};
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+use elden_analyzer_video::capture::Frame;
+
+use crate::{
+    config::{Config, DetectorConfig},
+    image_process::tesseract::Tesseract,
+    operator::{Confidence, DetectionScore, HistogramBasedComponentDetector, Recognition},
+    util::Explainer,
+};
+
+use super::{
+    prefixed_scores, Component, Detection, DetectionPayload, ExtractedTexts, REFERENCE_SIZE,
+};
+
+pub(super) const NAME: &str = "respawn";
+
+/// Name of the detector in the config for the dialog
+const DIALOG_DETECTOR: &str = "respawn_dialog";
+/// Names of the detectors in the config for the highlighted option of each kind of respawn
+const OPTION_DETECTORS: [(RespawnKind, &str); 2] = [
+    (RespawnKind::Stake, "respawn_stake"),
+    (RespawnKind::Grace, "respawn_grace"),
+];
+pub(super) const DETECTORS: &[(&str, (u32, u32))] = &[
+    (DIALOG_DETECTOR, RESPAWN_DIALOG_SIZE),
+    (OPTION_DETECTORS[0].1, RESPAWN_DIALOG_SIZE),
+    (OPTION_DETECTORS[1].1, RESPAWN_DIALOG_SIZE),
+];
+
+/// Where the player chooses to respawn in the dialog shown after deaths near a stake of Marika.
+///
+/// Spans of the `respawn` component have the highlighted option as their texts.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::components::RespawnKind;
+///
+/// assert_eq!("stake".parse::<RespawnKind>().unwrap(), RespawnKind::Stake);
+/// assert_eq!(RespawnKind::Grace.to_string(), "grace");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RespawnKind {
+    /// Respawn at the stake of Marika
+    Stake,
+    /// Respawn at the last site of grace visited
+    Grace,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid respawn kind `{0}` (expected `stake` or `grace`)")]
+pub struct RespawnKindParseError(String);
+
+impl RespawnKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stake => "stake",
+            Self::Grace => "grace",
+        }
+    }
+}
+
+impl fmt::Display for RespawnKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RespawnKind {
+    type Err = RespawnKindParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stake" => Ok(Self::Stake),
+            "grace" => Ok(Self::Grace),
+            _ => Err(RespawnKindParseError(s.to_owned())),
+        }
+    }
+}
+
+pub(super) fn component(frame_rect: Rect, config: &Config) -> Option<Box<dyn Component>> {
+    let c = RespawnComponent::new(frame_rect, config)?;
+    Some(Box::new(c) as _)
+}
+
+/// Dialog asking whether to respawn at the stake of Marika or at the last site of grace.
+///
+/// The options are recognized by their highlighted backgrounds rather than their texts,
+/// so they do not depend on the language of the game.
+#[derive(Debug)]
+struct RespawnComponent {
+    name: String,
+    rect: Rect,
+    detector: HistogramBasedComponentDetector,
+    option_detectors: Vec<(RespawnKind, HistogramBasedComponentDetector)>,
+}
+
+impl Component for RespawnComponent {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn detect(&self, frame: &Frame) -> eyre::Result<Detection> {
+        let found = Explainer::get().scope(
+            || format!("detector {DIALOG_DETECTOR}"),
+            || self.detector.detect(frame),
+        )?;
+        Ok(if found {
+            Detection::Found(None)
+        } else {
+            Detection::Absent
+        })
+    }
+
+    fn prefilter(&self, frame: &Frame) -> bool {
+        self.detector.prefilter(frame)
+    }
+
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        let mut scores: Vec<_> =
+            prefixed_scores(DIALOG_DETECTOR, self.detector.scores(frame)?).collect();
+        for ((_, name), (_, detector)) in OPTION_DETECTORS.iter().zip(&self.option_detectors) {
+            scores.extend(prefixed_scores(name, detector.scores(frame)?));
+        }
+        Ok(scores)
+    }
+
+    fn extract_text(
+        &self,
+        _tess: &mut Tesseract,
+        frame: &Frame,
+        _payload: Option<DetectionPayload>,
+    ) -> eyre::Result<ExtractedTexts> {
+        let explainer = Explainer::get();
+        let mut highlighted = vec![];
+        for (kind, detector) in &self.option_detectors {
+            if explainer.scope(|| format!("option {kind}"), || detector.detect(frame))? {
+                highlighted.push(*kind);
+            }
+        }
+        let res = match highlighted[..] {
+            [kind] => Recognition::Found(kind.to_string(), Confidence::ONE),
+            _ => {
+                explainer.note(|| format!("{} options highlighted => possible", highlighted.len()));
+                Recognition::Possible(String::new(), Confidence::ZERO)
+            }
+        };
+        Ok(ExtractedTexts {
+            result: vec![res],
+            rarity: None,
+        })
+    }
+}
+
+impl RespawnComponent {
+    fn new(frame_rect: Rect, config: &Config) -> Option<Self> {
+        let rect = RESPAWN_DIALOG_IN_FRAME.clip(frame_rect)?;
+        let detector = new_detector(frame_rect, config.detectors.get(DIALOG_DETECTOR)?)?;
+        let option_detectors = OPTION_DETECTORS
+            .iter()
+            .map(|(kind, name)| {
+                let detector = new_detector(frame_rect, config.detectors.get(*name)?)?;
+                Some((*kind, detector))
+            })
+            .collect::<Option<_>>()?;
+        Some(Self {
+            name: NAME.to_string(),
+            rect,
+            detector,
+            option_detectors,
+        })
+    }
+}
+
+fn new_detector(
+    frame_rect: Rect,
+    config: &DetectorConfig,
+) -> Option<HistogramBasedComponentDetector> {
+    config
+        .builder(RESPAWN_DIALOG_IN_FRAME, RESPAWN_DIALOG_SIZE)?
+        .build(frame_rect)
+}
+
+const RESPAWN_DIALOG_IN_FRAME: ClipRect =
+    ClipRect::from_points((560, 400), (1359, 679), REFERENCE_SIZE);
+
+/// Size of the dialog in 1920x1080 frames, used for rects of detector areas in the config
+const RESPAWN_DIALOG_SIZE: (u32, u32) = (800, 280);
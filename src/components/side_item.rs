@@ -1,17 +1,24 @@
+use std::sync::Arc;
+
 use color_eyre::eyre::{self, eyre};
 use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
 use elden_analyzer_video::capture::Frame;
-use num_rational::Ratio;
 
 use crate::{
+    config::{Config, DetectorConfig},
+    i18n::Lang,
     image_process::tesseract::Tesseract,
     operator::{
-        ExtractText, HistogramBasedComponentDetector, HistogramBasedComponentDetectorBuilder,
-        HistogramThreshold, PostProcess, Recognition, RectTextExtractorBuilder, TextAlign,
+        DetectionScore, ExtractText, GlyphAtlas, HistogramBasedComponentDetector, PostProcess,
+        RarityClassifier, RarityClassifierBuilder, Recognition, RectTextExtractorBuilder,
+        TextAlign,
     },
+    util::Explainer,
 };
 
-use super::{Component, Detection, DetectionPayload, ExtractedTexts};
+use super::{
+    prefixed_scores, Component, Detection, DetectionPayload, ExtractedTexts, REFERENCE_SIZE,
+};
 
 pub(super) const COUNT: usize = 10;
 pub(super) const NAMES: [&str; COUNT] = [
@@ -27,12 +34,29 @@ pub(super) const NAMES: [&str; COUNT] = [
     "side_item9",
 ];
 
-pub(super) fn components(frame_rect: Rect) -> Option<[Box<dyn Component>; COUNT]> {
+/// Name of the detector in the config for side items with one-digit counts
+const D1_DETECTOR: &str = "side_item_one_digit";
+/// Name of the detector in the config for side items with two-digit counts
+const D2_DETECTOR: &str = "side_item_two_digits";
+pub(super) const DETECTORS: &[(&str, (u32, u32))] =
+    &[(D1_DETECTOR, SIDE_ITEM_SIZE), (D2_DETECTOR, SIDE_ITEM_SIZE)];
+
+pub(super) fn components(frame_rect: Rect, config: &Config) -> Option<[Box<dyn Component>; COUNT]> {
+    let d1_config = config.detectors.get(D1_DETECTOR)?;
+    let d2_config = config.detectors.get(D2_DETECTOR)?;
     let uis = NAMES
         .iter()
         .zip(SIDE_ITEM_BOX_IN_FRAME)
         .map(|(name, base_rect)| {
-            let c = SideItemComponent::new(name.to_string(), base_rect, frame_rect)?;
+            let c = SideItemComponent::new(
+                name.to_string(),
+                base_rect,
+                frame_rect,
+                [d1_config, d2_config],
+                config.text_lang(),
+                config.pipeline.ocr_text_height,
+                config.glyph_atlas_for(config.count_recognizer),
+            )?;
             Some(Box::new(c) as Box<_>)
         })
         .collect::<Option<Vec<_>>>()?;
@@ -53,9 +77,11 @@ enum CountDigits {
 #[derive(Debug)]
 struct SideItemComponent {
     name: String,
+    rect: Rect,
     d1_detector: HistogramBasedComponentDetector,
     d2_detector: HistogramBasedComponentDetector,
     text_extractor: Box<dyn ExtractText>,
+    rarity_classifier: RarityClassifier,
     d1_extractor: Box<dyn ExtractText>,
     d2_extractor: Box<dyn ExtractText>,
 }
@@ -65,14 +91,25 @@ impl Component for SideItemComponent {
         &self.name
     }
 
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
     fn detect(&self, frame: &Frame) -> eyre::Result<Detection> {
-        if self.d1_detector.detect(frame) {
+        let explainer = Explainer::get();
+        if explainer.scope(
+            || format!("detector {D1_DETECTOR}"),
+            || self.d1_detector.detect(frame),
+        )? {
             let payload = Payload {
                 count_digits: CountDigits::One,
             };
             return Ok(Detection::Found(Some(Box::new(payload))));
         }
-        if self.d2_detector.detect(frame) {
+        if explainer.scope(
+            || format!("detector {D2_DETECTOR}"),
+            || self.d2_detector.detect(frame),
+        )? {
             let payload = Payload {
                 count_digits: CountDigits::Two,
             };
@@ -81,6 +118,16 @@ impl Component for SideItemComponent {
         Ok(Detection::Absent)
     }
 
+    fn prefilter(&self, frame: &Frame) -> bool {
+        self.d1_detector.prefilter(frame) || self.d2_detector.prefilter(frame)
+    }
+
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        let d1 = prefixed_scores(D1_DETECTOR, self.d1_detector.scores(frame)?);
+        let d2 = prefixed_scores(D2_DETECTOR, self.d2_detector.scores(frame)?);
+        Ok(d1.chain(d2).collect())
+    }
+
     fn extract_text(
         &self,
         tess: &mut Tesseract,
@@ -94,33 +141,73 @@ impl Component for SideItemComponent {
             })
             .transpose()?;
 
-        let text = self.text_extractor.extract_text(tess, frame, None)?;
+        let explainer = Explainer::get();
+        let text = explainer.scope(
+            || "item name".into(),
+            || self.text_extractor.extract_text(tess, frame, None),
+        )?;
+        let rarity = self.rarity_classifier.classify(frame)?;
 
         let count = match payload.as_ref().map(|p| p.count_digits) {
-            Some(CountDigits::One) => self.d1_extractor.extract_text(tess, frame, Some(1))?,
-            Some(CountDigits::Two) => self.d2_extractor.extract_text(tess, frame, Some(2))?,
+            Some(CountDigits::One) => explainer.scope(
+                || "count (one digit)".into(),
+                || self.d1_extractor.extract_text(tess, frame, Some(1)),
+            )?,
+            Some(CountDigits::Two) => explainer.scope(
+                || "count (two digits)".into(),
+                || self.d2_extractor.extract_text(tess, frame, Some(2)),
+            )?,
             _ => self.extract_count_chain(tess, frame)?,
         };
         let count = count.map_text(|text| format!("×{}", text));
 
         Ok(ExtractedTexts {
             result: vec![text, count],
+            rarity,
         })
     }
 }
 
 impl SideItemComponent {
-    fn new(name: String, base_rect: ClipRect, frame_rect: Rect) -> Option<Self> {
-        let d1_detector = new_detector(base_rect, frame_rect, SIDE_ITEM_AREAS_IN_BOX[0])?;
-        let d2_detector = new_detector(base_rect, frame_rect, SIDE_ITEM_AREAS_IN_BOX[1])?;
-        let text_extractor = new_extractor(base_rect, frame_rect, TEXT_IN_BOX[0])?;
-        let d1_extractor = new_extractor(base_rect, frame_rect, TEXT_IN_BOX[1])?;
-        let d2_extractor = new_extractor(base_rect, frame_rect, TEXT_IN_BOX[2])?;
+    fn new(
+        name: String,
+        base_rect: ClipRect,
+        frame_rect: Rect,
+        [d1_config, d2_config]: [&DetectorConfig; 2],
+        lang: Lang,
+        text_height: u32,
+        count_atlas: Option<&Arc<GlyphAtlas>>,
+    ) -> Option<Self> {
+        let rect = base_rect.clip(frame_rect)?;
+        let d1_detector = new_detector(base_rect, frame_rect, d1_config)?;
+        let d2_detector = new_detector(base_rect, frame_rect, d2_config)?;
+        let text_extractor =
+            new_extractor(base_rect, frame_rect, TEXT_IN_BOX[0], lang, text_height)?;
+        let rarity_classifier = RarityClassifierBuilder {
+            base_rect,
+            text_rect: TEXT_IN_BOX[0].0,
+        }
+        .build(frame_rect)?;
+        let count_extractor = |(text_rect, pp, align): (ClipRect, PostProcess, TextAlign)| {
+            super::text_extractor(
+                frame_rect,
+                base_rect,
+                text_rect,
+                pp,
+                align,
+                text_height,
+                count_atlas,
+            )
+        };
+        let d1_extractor = count_extractor(TEXT_IN_BOX[1])?;
+        let d2_extractor = count_extractor(TEXT_IN_BOX[2])?;
         Some(Self {
             name,
+            rect,
             d1_detector,
             d2_detector,
             text_extractor,
+            rarity_classifier,
             d1_extractor,
             d2_extractor,
         })
@@ -131,13 +218,20 @@ impl SideItemComponent {
         tess: &mut Tesseract,
         frame: &Frame,
     ) -> eyre::Result<Recognition> {
-        let d1 = self.d1_extractor.extract_text(tess, frame, Some(1))?;
+        let explainer = Explainer::get();
+        let d1 = explainer.scope(
+            || "count (one digit, digits unknown)".into(),
+            || self.d1_extractor.extract_text(tess, frame, Some(1)),
+        )?;
         let (text1, conf1) = match d1 {
             Recognition::Found(text, conf) => return Ok(Recognition::Found(text, conf)),
             Recognition::Possible(text, conf) => (text, conf),
         };
 
-        let d2 = self.d2_extractor.extract_text(tess, frame, Some(2))?;
+        let d2 = explainer.scope(
+            || "count (two digits, digits unknown)".into(),
+            || self.d2_extractor.extract_text(tess, frame, Some(2)),
+        )?;
         let (text2, conf2) = match d2 {
             Recognition::Found(text, conf) => return Ok(Recognition::Found(text, conf)),
             Recognition::Possible(text, conf) => (text, conf),
@@ -151,35 +245,35 @@ impl SideItemComponent {
     }
 }
 
+pub(super) fn count_rect(digits: usize) -> Option<ClipRect> {
+    match digits {
+        1 => Some(TEXT_IN_BOX[1].0),
+        2 => Some(TEXT_IN_BOX[2].0),
+        _ => None,
+    }
+}
+
 fn new_detector(
     base_rect: ClipRect,
     frame_rect: Rect,
-    areas: &[(HistogramThreshold, &[ClipRect])],
+    config: &DetectorConfig,
 ) -> Option<HistogramBasedComponentDetector> {
-    let mut areas: Vec<_> = areas
-        .iter()
-        .map(|(thr, rects)| (thr.clone(), rects.to_vec()))
-        .collect();
-    areas.sort_by_key(|(_thr, rects)| -> Ratio<i32> { rects.iter().map(ClipRect::area).sum() }); // sort by ascending area
-
-    HistogramBasedComponentDetectorBuilder {
-        base_rect,
-        level_width: SIDE_ITEM_LEVEL_WIDTH,
-        areas,
-    }
-    .build(frame_rect)
+    config.builder(base_rect, SIDE_ITEM_SIZE)?.build(frame_rect)
 }
 
 fn new_extractor(
     base_rect: ClipRect,
     frame_rect: Rect,
     rect: (ClipRect, PostProcess, TextAlign),
+    lang: Lang,
+    text_height: u32,
 ) -> Option<Box<dyn ExtractText>> {
     let e = RectTextExtractorBuilder {
         base_rect,
         text_rect: rect.0, //TEXT_IN_BOX.to_vec(),
-        post_process: rect.1,
+        post_process: rect.1.localized(lang),
         align: rect.2,
+        text_height,
     }
     .build(frame_rect)?;
     Some(Box::new(e) as _)
@@ -189,17 +283,17 @@ const SIDE_ITEM_X0_IN_FRAME: i32 = 1364;
 const SIDE_ITEM0_Y0_IN_FRAME: i32 = 822;
 const SIDE_ITEM_WIDTH: i32 = 556;
 const SIDE_ITEM_HEIGHT: i32 = 44;
+/// Size of a side item in 1920x1080 frames, used for rects of detector areas in the config
+const SIDE_ITEM_SIZE: (u32, u32) = (SIDE_ITEM_WIDTH as u32, SIDE_ITEM_HEIGHT as u32);
 
 const SIDE_ITEM_BOX_IN_FRAME: [ClipRect; COUNT] = {
-    const WIDTH: i32 = 1920;
-    const HEIGHT: i32 = 1080;
     const X0: i32 = SIDE_ITEM_X0_IN_FRAME;
 
     const fn rect((x0, y0): (i32, i32)) -> ClipRect {
         ClipRect::from_points(
             (x0, y0),
             (x0 + SIDE_ITEM_WIDTH - 1, y0 + SIDE_ITEM_HEIGHT - 1),
-            (WIDTH, HEIGHT),
+            REFERENCE_SIZE,
         )
     }
 
@@ -217,99 +311,6 @@ const SIDE_ITEM_BOX_IN_FRAME: [ClipRect; COUNT] = {
     ]
 };
 
-const SIDE_ITEM_LEVEL_WIDTH: u8 = 16;
-const SIDE_ITEM_AREAS_IN_BOX: &[&[(HistogramThreshold, &[ClipRect])]] = {
-    const WIDTH: i32 = SIDE_ITEM_WIDTH;
-    const HEIGHT: i32 = SIDE_ITEM_HEIGHT;
-
-    const X0: i32 = SIDE_ITEM_X0_IN_FRAME;
-    const Y0: i32 = SIDE_ITEM0_Y0_IN_FRAME;
-
-    const ITEM_X0: i32 = 1385;
-    const ITEM_X1: i32 = 1710;
-    const ITEM_WIDTH: i32 = ITEM_X1 + 1 - ITEM_X0;
-    const TEXT_Y0: i32 = 838;
-    const TEXT_Y1: i32 = 865;
-
-    const TIMES1_X0: i32 = 1745;
-    const TIMES1_X1: i32 = TIMES1_X0 + 15;
-    const TIMES_Y0: i32 = TEXT_Y0 + 6;
-    const TIMES_Y1: i32 = TEXT_Y1 - 6;
-    const DIGIT1_X0: i32 = TIMES1_X1 + 5;
-    const DIGIT1_X1: i32 = DIGIT1_X0 + 14;
-
-    const TIMES2_X0: i32 = TIMES1_X0 - 16;
-    const TIMES2_X1: i32 = TIMES1_X1 - 16;
-    const DIGIT2_X0: i32 = DIGIT1_X0 - 16;
-    const DIGIT2_X1: i32 = DIGIT1_X1;
-
-    const fn rect((x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> ClipRect {
-        ClipRect::from_points((x0 - X0, y0 - Y0), (x1 - X0, y1 - Y0), (WIDTH, HEIGHT))
-    }
-
-    const TOP_BLANK0: ClipRect = rect((1600, Y0), (1792, TEXT_Y0 - 1));
-    const TOP_BLANK1: ClipRect = rect((1849, Y0), (1919, TEXT_Y0 - 1));
-
-    const LAST_LETTER: ClipRect = rect((ITEM_X1 - ITEM_WIDTH / 6, TEXT_Y0), (ITEM_X1, TEXT_Y1));
-    const TIMES1_LETTER: ClipRect = rect((TIMES1_X0, TIMES_Y0), (TIMES1_X1, TIMES_Y1));
-    const DIGIT1_LETTER: ClipRect = rect((DIGIT1_X0, TEXT_Y0), (DIGIT1_X1, TEXT_Y1));
-
-    const TIMES2_LETTER: ClipRect = rect((TIMES2_X0, TIMES_Y0), (TIMES2_X1, TIMES_Y1));
-    const DIGIT2_LETTER: ClipRect = rect((DIGIT2_X0, TEXT_Y0), (DIGIT2_X1, TEXT_Y1));
-
-    const ALL_BLANK1: &[ClipRect] = &[
-        TOP_BLANK0,
-        TOP_BLANK1,
-        rect((ITEM_X1 + 1, TEXT_Y0), (TIMES1_X0 - 1, TEXT_Y1)),
-        rect((TIMES1_X1 + 1, TEXT_Y0), (DIGIT1_X0 - 1, TEXT_Y1)),
-        rect((DIGIT1_X1 + 1, TEXT_Y0), (1792, TEXT_Y1)),
-        rect((TIMES1_X0, TEXT_Y0), (TIMES1_X1, TIMES_Y0 - 1)),
-        rect((TIMES1_X0, TIMES_Y1 + 1), (TIMES1_X1, TEXT_Y1)),
-    ];
-
-    const ALL_BLANK2: &[ClipRect] = &[
-        TOP_BLANK0,
-        TOP_BLANK1,
-        rect((ITEM_X1 + 1, TEXT_Y0), (TIMES2_X0 - 1, TEXT_Y1)),
-        rect((TIMES2_X1 + 1, TEXT_Y0), (DIGIT2_X0 - 1, TEXT_Y1)),
-        rect((DIGIT2_X1 + 1, TEXT_Y0), (1792, TEXT_Y1)),
-        rect((TIMES2_X0, TEXT_Y0), (TIMES2_X1, TIMES_Y0 - 1)),
-        rect((TIMES2_X0, TIMES_Y1 + 1), (TIMES2_X1, TEXT_Y1)),
-    ];
-
-    const fn bg(name: &'static str) -> HistogramThreshold {
-        HistogramThreshold::new(name, &[([0..=6, 0..=6, 0..=6], 0..=6)], 1.00)
-    }
-
-    const fn letter(name: &'static str) -> HistogramThreshold {
-        HistogramThreshold::new(name, &[([11..=15, 11..=15, 11..=15], 12..=15)], 0.010)
-    }
-
-    const fn times_letter(name: &'static str) -> HistogramThreshold {
-        // `×` => 0.084375 = (12 + 12) / 16 * 16 * 0.9
-        HistogramThreshold::new(name, &[([11..=15, 11..=15, 11..=15], 11..=15)], 0.084)
-    }
-
-    const fn digit_letter(name: &'static str) -> HistogramThreshold {
-        HistogramThreshold::new(name, &[([12..=15, 12..=15, 12..=15], 12..=15)], 0.045)
-    }
-
-    &[
-        &[
-            (bg("BG"), ALL_BLANK1),
-            (letter("LAST_LETTER"), &[LAST_LETTER]),
-            (times_letter("TIMES_LETTER"), &[TIMES1_LETTER]),
-            (digit_letter("DIGIT_LETTER"), &[DIGIT1_LETTER]),
-        ],
-        &[
-            (bg("BG"), ALL_BLANK2),
-            (letter("LAST_LETTER"), &[LAST_LETTER]),
-            (times_letter("TIMES_LETTER"), &[TIMES2_LETTER]),
-            (digit_letter("DIGIT_LETTER"), &[DIGIT2_LETTER]),
-        ],
-    ]
-};
-
 const TEXT_IN_BOX: &[(ClipRect, PostProcess, TextAlign)] = {
     const WIDTH: i32 = SIDE_ITEM_WIDTH;
     const HEIGHT: i32 = SIDE_ITEM_HEIGHT;
@@ -0,0 +1,1096 @@
+//! Configuration file.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    num::ParseFloatError,
+    path::{Path, PathBuf},
+    slice,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use color_eyre::eyre::{self, bail, WrapErr as _};
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+use num_rational::Ratio;
+use serde::{
+    de::{
+        self,
+        value::{SeqAccessDeserializer, StrDeserializer},
+        SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    accumulate::{detection::EXPIRE_FRAMES, text::MIN_POSSIBLE_SHARE},
+    assets::{self, Asset},
+    components::{self, ComponentFilter},
+    i18n::Lang,
+    image_process::tesseract,
+    operator::{
+        GlyphAtlas, HistogramBasedComponentDetectorBuilder, HistogramThreshold, ItemCorrections,
+        LevelRanges, PostProcess, TextAlign, DEFAULT_TEXT_HEIGHT,
+    },
+    profile::Profile,
+};
+
+/// Width and height of the frame that rects in the configuration file are measured on.
+///
+/// Rects are scaled to the actual frame size.
+pub const REFERENCE_FRAME_SIZE: (u32, u32) = (1920, 1080);
+
+/// Language of the Tesseract trained data used unless `--ocr-lang` is specified
+pub const DEFAULT_OCR_LANG: &str = "jpn";
+
+/// Contents of the configuration file.
+///
+/// The configuration file is merged into the default configuration (`assets/config.toml`).
+/// Detectors with the same name as the default ones replace them.
+///
+/// Rects are written in pixels of the reference frame (see [`ConfigRect`]),
+/// or in ratios of the edges to the size of the containing rect, such as `[[0.2, 0.125], [0.95, 0.875]]`.
+///
+/// # Examples
+/// ```toml
+/// profile = "ps5-1080p-jp"
+/// translate_items = "en"
+///
+/// [detectors.rune_count]
+/// level_width = 16
+///
+/// [[detectors.rune_count.areas]]
+/// name = "DIGIT"
+/// ranges = [{ rgb = [[12, 15], [12, 15], [12, 15]], luma = [12, 15] }]
+/// threshold = 0.05
+/// rects = ["150x30+40+5"]
+///
+/// [[components]]
+/// name = "rune_count"
+/// base_rect = "200x40+1700+1010"
+/// text_rect = "150x30+1740+1015"
+/// detector = "rune_count"
+/// post_process = "none"
+/// align = "right"
+/// charset = "0123456789"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Built-in profile used unless `--profile` is specified
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Default of `--translate-items`, taking precedence over the profile
+    #[serde(default)]
+    pub translate_items: Option<Lang>,
+    /// Default of `--ocr-lang`
+    #[serde(default)]
+    pub ocr_lang: Option<String>,
+    /// Default of `--item-list`
+    #[serde(default)]
+    pub item_list: Option<PathBuf>,
+    /// Histogram-based detectors referred by components
+    #[serde(default)]
+    pub detectors: BTreeMap<String, DetectorConfig>,
+    /// User-defined components
+    #[serde(default)]
+    pub components: Vec<ComponentConfig>,
+    /// Groups of components usable in place of component names in filters (see [`ComponentFilter`])
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    /// Glyph atlas of the game font for the `glyph-match` recognizer, relative to the configuration file
+    #[serde(default)]
+    pub glyph_atlas: Option<PathBuf>,
+    /// Recognizer of counts of items (`×N`)
+    #[serde(default)]
+    pub count_recognizer: Recognizer,
+    /// Corrections of misread item names (see [`ItemCorrections`]), relative to the configuration file
+    #[serde(default)]
+    pub item_corrections: Option<PathBuf>,
+    /// `glyph_atlas` loaded with the configuration file
+    #[serde(skip)]
+    loaded_atlas: Option<Arc<GlyphAtlas>>,
+    /// `item_corrections` loaded with the configuration file
+    #[serde(skip)]
+    loaded_corrections: ItemCorrections,
+}
+
+/// Recognizer of texts of components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Recognizer {
+    /// Tesseract, for texts in any font
+    #[default]
+    Tesseract,
+    /// Matching against glyphs of `glyph_atlas`, for fixed-style HUD texts such as counts
+    GlyphMatch,
+}
+
+/// Threads and queues of the analysis pipeline, recommended by `tune-performance`.
+///
+/// Unspecified values are chosen at runtime.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PerformanceConfig {
+    /// Number of threads detecting components in each segment (default: number of CPUs)
+    pub detect_threads: Option<usize>,
+    /// Number of threads recognizing texts in each segment (default: number of CPUs)
+    pub ocr_threads: Option<usize>,
+    /// Default of `analyze --parallel-segments`
+    pub parallel_segments: Option<usize>,
+    /// Default of `analyze --parallel-files`
+    pub parallel_files: Option<usize>,
+    /// Default of `watch --max-pending-frames` and `analyze --live --max-pending-frames`
+    pub max_pending_frames: Option<usize>,
+    /// Number of threads converting each decoded frame to RGB, `0` for the number of CPUs (default: 1)
+    pub scale_threads: Option<usize>,
+}
+
+/// Parameters of the analysis pipeline, tuned for the built-in components.
+///
+/// Unspecified values are the defaults, so that only the parameters being tuned need to be written.
+///
+/// # Examples
+/// ```toml
+/// [pipeline]
+/// expire_frames = 90
+/// ocr_text_height = 48
+///
+/// [pipeline.main_item]
+/// high_threshold = 12
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Possible frames not followed by found frames within this number of frames are regarded as absent
+    pub expire_frames: usize,
+    /// Minimum share (percentage) of a possible text in the confidences of all possible texts to be output
+    pub min_possible_share: i32,
+    /// Height in pixels that texts are resized to before being recognized by Tesseract
+    pub ocr_text_height: u32,
+    /// Recognitions of a text taking longer than this number of milliseconds are abandoned and regarded as possible
+    pub ocr_timeout_ms: u64,
+    /// Line-based detector of the main item
+    pub main_item: LineDetectorConfig,
+}
+
+impl PipelineConfig {
+    /// Returns the timeout of recognitions of Tesseract.
+    pub fn ocr_timeout(&self) -> Duration {
+        Duration::from_millis(self.ocr_timeout_ms)
+    }
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            expire_frames: EXPIRE_FRAMES,
+            min_possible_share: MIN_POSSIBLE_SHARE,
+            ocr_text_height: DEFAULT_TEXT_HEIGHT,
+            ocr_timeout_ms: tesseract::DEFAULT_TIMEOUT.as_millis() as u64,
+            main_item: LineDetectorConfig::default(),
+        }
+    }
+}
+
+/// Line-based detector, which finds a component by the horizontal lines of its frame.
+///
+/// Lengths are in pixels of the reference frame and scaled to the actual frame size.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LineDetectorConfig {
+    /// Standard deviation of the Gaussian blur before finding edges
+    pub sigma: f32,
+    /// Low threshold of the hysteresis of the Canny edge detector
+    pub low_threshold: u16,
+    /// High threshold of the hysteresis of the Canny edge detector
+    pub high_threshold: u16,
+    /// The component is found if the lines are filled more than this ratio
+    pub found_threshold: f32,
+    /// The component is possibly shown if the lines are filled more than this ratio
+    pub possible_threshold: f32,
+}
+
+impl Default for LineDetectorConfig {
+    fn default() -> Self {
+        Self {
+            sigma: 1.0,
+            low_threshold: 0,
+            high_threshold: 10,
+            found_threshold: 0.80,
+            possible_threshold: 0.20,
+        }
+    }
+}
+
+/// Thresholds of detections overriding the configuration file and the profile, such as options of `analyze`.
+///
+/// Lower thresholds find more components in noisy captures at the cost of false detections.
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdOverrides {
+    /// `found_threshold` of `pipeline.main_item`
+    pub found_threshold: Option<f32>,
+    /// `possible_threshold` of `pipeline.main_item`
+    pub possible_threshold: Option<f32>,
+    /// `pipeline.min_possible_share`
+    pub min_possible_share: Option<i32>,
+    /// Thresholds of areas of histogram-based detectors
+    pub areas: Vec<AreaThreshold>,
+}
+
+impl ThresholdOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.found_threshold.is_none()
+            && self.possible_threshold.is_none()
+            && self.min_possible_share.is_none()
+            && self.areas.is_empty()
+    }
+}
+
+/// Threshold of an area of a histogram-based detector, written as `DETECTOR.AREA=THRESHOLD`.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::config::AreaThreshold;
+///
+/// let threshold = "main_item_count.DIGIT_LETTER=0.04".parse::<AreaThreshold>().unwrap();
+/// assert_eq!(threshold.detector, "main_item_count");
+/// assert_eq!(threshold.area, "DIGIT_LETTER");
+/// assert_eq!(threshold.threshold, 0.04);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaThreshold {
+    pub detector: String,
+    pub area: String,
+    pub threshold: f32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AreaThresholdParseError {
+    #[error("expected `DETECTOR.AREA=THRESHOLD`")]
+    Syntax,
+    #[error("invalid threshold: {0}")]
+    Number(#[from] ParseFloatError),
+}
+
+impl FromStr for AreaThreshold {
+    type Err = AreaThresholdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, threshold) = s.split_once('=').ok_or(AreaThresholdParseError::Syntax)?;
+        let (detector, area) = name
+            .split_once('.')
+            .ok_or(AreaThresholdParseError::Syntax)?;
+        Ok(Self {
+            detector: detector.to_owned(),
+            area: area.to_owned(),
+            threshold: threshold.parse()?,
+        })
+    }
+}
+
+/// Histogram-based detector.
+///
+/// The component is found if the ratio of pixels whose levels are in `ranges` exceeds `threshold` in every area.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectorConfig {
+    /// Pixel values are divided by this value to compute levels
+    pub level_width: u8,
+    pub areas: Vec<DetectorAreaConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectorAreaConfig {
+    pub name: String,
+    /// Levels of pixels counted as found
+    pub ranges: Vec<LevelRange>,
+    /// Minimum ratio of found pixels
+    pub threshold: f32,
+    /// Regions of the area, in pixels relative to the top-left corner of the component or in ratios to the component
+    pub rects: Vec<ConfigRect>,
+}
+
+/// Inclusive ranges of levels of each RGB channel and luma.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelRange {
+    /// Any levels if omitted.
+    /// Areas whose ranges limit only luma are checked on the decoded luma plane without RGB conversion.
+    #[serde(default)]
+    pub rgb: Option<[[u8; 2]; 3]>,
+    pub luma: [u8; 2],
+    /// Range of the difference between the highest and lowest levels of the RGB channels.
+    /// Tinted texts, such as names of rare items, are told from gray ones by this.
+    #[serde(default = "LevelRange::any_chroma")]
+    pub chroma: [u8; 2],
+}
+
+impl LevelRange {
+    fn any_chroma() -> [u8; 2] {
+        [0, u8::MAX]
+    }
+}
+
+/// User-defined component whose text is read from a fixed region of the frame.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentConfig {
+    pub name: String,
+    /// Region of the component, in pixels of the reference frame or in ratios to the frame
+    pub base_rect: ConfigRect,
+    /// Region of the text, in pixels of the reference frame or in ratios to `base_rect`.
+    /// Must be inside `base_rect`.
+    pub text_rect: ConfigRect,
+    /// Name of the detector in `detectors`.
+    /// If not specified, the component is treated as always shown.
+    #[serde(default)]
+    pub detector: Option<String>,
+    #[serde(default)]
+    pub post_process: PostProcess,
+    #[serde(default)]
+    pub align: TextAlign,
+    #[serde(default)]
+    pub recognizer: Recognizer,
+    /// Characters expected in the text.
+    /// If specified, texts consisting only of these characters are regarded as found,
+    /// and other texts are regarded as possible.
+    #[serde(default)]
+    pub charset: Option<String>,
+}
+
+/// Rect written in the configuration file.
+///
+/// * Pixels (`WIDTHxHEIGHT+X+Y`) in the coordinates of the containing rect, which are scaled with the frame.
+/// * Pixels at a reference size of the containing rect (`WIDTHxHEIGHT+X+Y@WIDTHxHEIGHT`).
+/// * Ratios of the edges to the size of the containing rect, measured from its top-left corner
+///   (`[[LEFT, TOP], [RIGHT, BOTTOM]]`). Columns and rows at the right and bottom edges are included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigRect {
+    Pixels(Rect),
+    Relative(ClipRect),
+}
+
+impl fmt::Display for ConfigRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pixels(rect) => write!(f, "{rect}"),
+            Self::Relative(rect) => write!(f, "{rect}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigRect {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ConfigRectVisitor;
+
+        impl<'de> Visitor<'de> for ConfigRectVisitor {
+            type Value = ConfigRect;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("`WIDTHxHEIGHT+X+Y`, `WIDTHxHEIGHT+X+Y@WIDTHxHEIGHT` or `[[LEFT, TOP], [RIGHT, BOTTOM]]`")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<ConfigRect, E>
+            where
+                E: de::Error,
+            {
+                if s.contains('@') {
+                    ClipRect::deserialize(StrDeserializer::new(s)).map(ConfigRect::Relative)
+                } else {
+                    s.parse()
+                        .map(ConfigRect::Pixels)
+                        .map_err(|e| E::custom(format!("invalid rect `{s}`: {e}")))
+                }
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<ConfigRect, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                ClipRect::deserialize(SeqAccessDeserializer::new(seq)).map(ConfigRect::Relative)
+            }
+        }
+
+        deserializer.deserialize_any(ConfigRectVisitor)
+    }
+}
+
+impl ConfigRect {
+    /// Returns the region in the coordinates of `container`, or `None` if it is out of `container`.
+    pub fn to_rect(&self, container: Rect) -> Option<Rect> {
+        match self {
+            Self::Pixels(rect) => (rect.intersect(container) == Some(*rect)).then_some(*rect),
+            Self::Relative(rect) => rect.clip(container),
+        }
+    }
+
+    /// Returns the region relative to `container`, or `None` if it is out of `container`.
+    pub fn to_clip_rect(&self, container: Rect) -> Option<ClipRect> {
+        match self {
+            Self::Pixels(rect) => clip_rect_in(*rect, container),
+            Self::Relative(rect) => Some(*rect),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Replacements of the default config are validated when they are chosen
+        toml::from_str(assets::text(Asset::DefaultConfig)).expect("default config must be valid")
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read config file: {}", path.display()))?;
+        let config = toml::from_str::<Self>(&text)
+            .wrap_err_with(|| format!("failed to parse config file: {}", path.display()))?;
+
+        let mut merged = config.merged_with_default();
+        merged.glyph_atlas = merged.glyph_atlas.map(|atlas| path.with_file_name(atlas));
+        merged
+            .validate()
+            .wrap_err_with(|| format!("invalid config file: {}", path.display()))?;
+        if let Some(atlas) = &merged.glyph_atlas {
+            let loaded = GlyphAtlas::load(atlas)
+                .wrap_err_with(|| format!("invalid config file: {}", path.display()))?;
+            merged.loaded_atlas = Some(Arc::new(loaded));
+        }
+        merged.item_corrections = merged
+            .item_corrections
+            .map(|corrections| path.with_file_name(corrections));
+        if let Some(corrections) = &merged.item_corrections {
+            merged.loaded_corrections = ItemCorrections::load(corrections)
+                .wrap_err_with(|| format!("invalid config file: {}", path.display()))?;
+        }
+        Ok(merged)
+    }
+
+    /// Returns the default config with detectors, components and groups added or replaced by the ones of `self`,
+    /// and the other tables replaced.
+    fn merged_with_default(self) -> Self {
+        let mut merged = Self::default();
+        merged.detectors.extend(self.detectors);
+        let defaults = merged.components.len();
+        for component in self.components {
+            match (merged.components[..defaults].iter_mut()).find(|c| c.name == component.name) {
+                Some(default) => *default = component,
+                None => merged.components.push(component),
+            }
+        }
+        merged.groups.extend(self.groups);
+        merged.performance = self.performance;
+        merged.pipeline = self.pipeline;
+        merged.profile = self.profile;
+        merged.translate_items = self.translate_items;
+        merged.ocr_lang = self.ocr_lang;
+        merged.item_list = self.item_list;
+        merged.count_recognizer = self.count_recognizer;
+        merged.glyph_atlas = self.glyph_atlas;
+        merged.item_corrections = self.item_corrections;
+        merged
+    }
+
+    /// Returns the language of the Tesseract trained data to recognize texts with.
+    pub fn ocr_lang(&self) -> &str {
+        self.ocr_lang.as_deref().unwrap_or(DEFAULT_OCR_LANG)
+    }
+
+    /// Returns the filter selecting components by `names`, which may be names of groups or patterns.
+    pub fn component_filter(&self, names: &[String]) -> eyre::Result<ComponentFilter> {
+        ComponentFilter::with_groups(names, &self.groups)
+    }
+
+    /// Returns the language the game is displayed in, which selects the post-processes of item names.
+    pub fn text_lang(&self) -> Lang {
+        Lang::of_ocr_lang(self.ocr_lang())
+    }
+
+    /// Returns the corrections of misread item names loaded from `item_corrections`, which are empty by default.
+    pub fn item_corrections(&self) -> &ItemCorrections {
+        &self.loaded_corrections
+    }
+
+    /// Returns the glyph atlas if texts are recognized by `recognizer` with the atlas rather than Tesseract.
+    pub fn glyph_atlas_for(&self, recognizer: Recognizer) -> Option<&Arc<GlyphAtlas>> {
+        match recognizer {
+            Recognizer::Tesseract => None,
+            Recognizer::GlyphMatch => self.loaded_atlas.as_ref(),
+        }
+    }
+
+    /// Sets the threshold of the areas named `area` of the histogram-based detector `detector`.
+    pub fn set_area_threshold(
+        &mut self,
+        detector: &str,
+        area: &str,
+        threshold: f32,
+    ) -> eyre::Result<()> {
+        let Some(config) = self.detectors.get_mut(detector) else {
+            bail!("detector `{detector}` is not defined");
+        };
+        let mut found = false;
+        for config in config.areas.iter_mut().filter(|a| a.name == area) {
+            config.threshold = threshold;
+            found = true;
+        }
+        if !found {
+            bail!("area `{area}` is not defined in detector `{detector}`");
+        }
+        Ok(())
+    }
+
+    /// Overrides thresholds of detections, and validates the configuration with them.
+    pub fn apply_overrides(&mut self, overrides: &ThresholdOverrides) -> eyre::Result<()> {
+        let main_item = &mut self.pipeline.main_item;
+        if let Some(threshold) = overrides.found_threshold {
+            main_item.found_threshold = threshold;
+        }
+        if let Some(threshold) = overrides.possible_threshold {
+            main_item.possible_threshold = threshold;
+        }
+        if let Some(share) = overrides.min_possible_share {
+            self.pipeline.min_possible_share = share;
+        }
+        for AreaThreshold {
+            detector,
+            area,
+            threshold,
+        } in &overrides.areas
+        {
+            self.set_area_threshold(detector, area, *threshold)?;
+        }
+        self.validate()
+    }
+
+    pub(crate) fn validate(&self) -> eyre::Result<()> {
+        let (width, height) = REFERENCE_FRAME_SIZE;
+        let frame_rect = Rect::at(0, 0).of_size(width, height);
+
+        if let Some(profile) = &self.profile {
+            if Profile::get(profile).is_none() {
+                let names = Profile::names().collect::<Vec<_>>().join(", ");
+                bail!("profile `{profile}` is not defined (available: {names})");
+            }
+        }
+
+        let uses_atlas = self.count_recognizer == Recognizer::GlyphMatch
+            || (self.components.iter()).any(|c| c.recognizer == Recognizer::GlyphMatch);
+        if uses_atlas && self.glyph_atlas.is_none() {
+            bail!("`glyph-match` recognizer requires `glyph_atlas`");
+        }
+
+        let LineDetectorConfig {
+            found_threshold,
+            possible_threshold,
+            ..
+        } = self.pipeline.main_item;
+        if !(0.0..=found_threshold).contains(&possible_threshold) {
+            bail!(
+                "possible_threshold of `pipeline.main_item` must be between 0 and found_threshold"
+            );
+        }
+        if self.pipeline.ocr_text_height == 0 {
+            bail!("`pipeline.ocr_text_height` must be positive");
+        }
+        if self.pipeline.ocr_timeout_ms == 0 {
+            bail!("`pipeline.ocr_timeout_ms` must be positive");
+        }
+
+        for (component, detector, size) in components::builtin_detectors() {
+            let Some(config) = self.detectors.get(detector) else {
+                bail!("detector `{detector}` required by built-in component `{component}` is not defined");
+            };
+            config.validate_areas(detector, component, size)?;
+        }
+
+        for name in self.groups.keys() {
+            if name.ends_with('*') {
+                bail!("group `{name}` must not end with `*`");
+            }
+            if components::is_builtin_name(name) || self.components.iter().any(|c| c.name == *name)
+            {
+                bail!("group `{name}` conflicts with a component");
+            }
+            self.component_filter(slice::from_ref(name))?;
+        }
+
+        let mut names = HashSet::new();
+        for component in &self.components {
+            let ComponentConfig {
+                name,
+                base_rect,
+                text_rect,
+                detector,
+                ..
+            } = component;
+            if components::is_builtin_name(name) {
+                bail!("component `{name}` conflicts with a built-in component");
+            }
+            if !names.insert(name.as_str()) {
+                bail!("component `{name}` is defined more than once");
+            }
+            let Some(base) = base_rect.to_rect(frame_rect) else {
+                bail!(
+                    "base_rect `{base_rect}` of component `{name}` is out of {width}x{height} frame"
+                );
+            };
+            if text_rect.to_rect(base).is_none() {
+                bail!("text_rect `{text_rect}` of component `{name}` is out of base_rect `{base_rect}`");
+            }
+            if let Some(detector) = detector {
+                let Some(config) = self.detectors.get(detector) else {
+                    bail!("detector `{detector}` of component `{name}` is not defined");
+                };
+                config.validate_areas(detector, name, (base.width(), base.height()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DetectorConfig {
+    /// Checks that the areas of the detector `name` are in `component` of `base_size` in the reference frame.
+    fn validate_areas(
+        &self,
+        name: &str,
+        component: &str,
+        base_size: (u32, u32),
+    ) -> eyre::Result<()> {
+        let base = Rect::at(0, 0).of_size(base_size.0, base_size.1);
+        for area in &self.areas {
+            for (i, rect) in area.rects.iter().enumerate() {
+                if rect.to_rect(base).is_none() {
+                    bail!(
+                        "rects[{i}] `{rect}` of area `{}` in detector `{name}` is out of component `{component}` ({}x{})",
+                        area.name,
+                        base.width(),
+                        base.height()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a builder of the detector for the component at `base_rect`.
+    ///
+    /// `base_size` is the size of the component in the reference frame.
+    /// Returns `None` if some areas are out of the component.
+    pub fn builder(
+        &self,
+        base_rect: ClipRect,
+        base_size: (u32, u32),
+    ) -> Option<HistogramBasedComponentDetectorBuilder> {
+        let base = Rect::at(0, 0).of_size(base_size.0, base_size.1);
+        let mut areas = self
+            .areas
+            .iter()
+            .map(|area| {
+                let thr = HistogramThreshold {
+                    name: area.name.clone(),
+                    found_range: area
+                        .ranges
+                        .iter()
+                        .map(|range| LevelRanges {
+                            rgb: range.rgb.map(|rgb| rgb.map(|[min, max]| min..=max)),
+                            luma: range.luma[0]..=range.luma[1],
+                            chroma: range.chroma[0]..=range.chroma[1],
+                        })
+                        .collect(),
+                    found_threshold: area.threshold,
+                };
+                let rects = area
+                    .rects
+                    .iter()
+                    .map(|rect| rect.to_clip_rect(base))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((thr, rects))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        areas.sort_by_key(|(_thr, rects)| -> Ratio<i32> { rects.iter().map(ClipRect::area).sum() }); // sort by ascending area
+
+        Some(HistogramBasedComponentDetectorBuilder {
+            base_rect,
+            level_width: self.level_width,
+            areas,
+        })
+    }
+}
+
+/// Converts `rect` to the relative position in `base`.
+///
+/// Returns `None` if `rect` is not inside `base`.
+pub(crate) fn clip_rect_in(rect: Rect, base: Rect) -> Option<ClipRect> {
+    if rect.intersect(base) != Some(rect) {
+        return None;
+    }
+    Some(ClipRect::from_points(
+        (rect.left() - base.left(), rect.top() - base.top()),
+        (rect.right() - base.left(), rect.bottom() - base.top()),
+        (base.width() as i32, base.height() as i32),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a config file merged with the default config, as [`Config::load`] does.
+    fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        Ok(toml::from_str::<Config>(text)?.merged_with_default())
+    }
+
+    #[test]
+    fn default_config() {
+        let config = Config::default();
+        config.validate().unwrap();
+        assert!(config.detectors.contains_key("side_item_one_digit"));
+        assert!(config.detectors.contains_key("side_item_two_digits"));
+    }
+
+    #[test]
+    fn parse_recognizers() {
+        let config = parse(
+            r#"
+            glyph_atlas = "glyphs.tsv"
+            count_recognizer = "glyph-match"
+
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1740+1015"
+            recognizer = "glyph-match"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.count_recognizer, Recognizer::GlyphMatch);
+        assert_eq!(
+            config.components.last().unwrap().recognizer,
+            Recognizer::GlyphMatch
+        );
+        assert!(config.glyph_atlas_for(Recognizer::Tesseract).is_none());
+        assert_eq!(Config::default().count_recognizer, Recognizer::Tesseract);
+
+        let config = parse(r#"count_recognizer = "glyph-match""#).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("glyph_atlas"));
+    }
+
+    #[test]
+    fn parse_pipeline() {
+        let config = parse(
+            r#"
+            [pipeline]
+            expire_frames = 90
+            ocr_timeout_ms = 500
+
+            [pipeline.main_item]
+            high_threshold = 12
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let pipeline = config.pipeline;
+        assert_eq!(pipeline.expire_frames, 90);
+        assert_eq!(pipeline.ocr_text_height, DEFAULT_TEXT_HEIGHT);
+        assert_eq!(pipeline.ocr_timeout(), Duration::from_millis(500));
+        assert_eq!(pipeline.main_item.high_threshold, 12);
+        assert_eq!(pipeline.main_item.low_threshold, 0);
+        assert_eq!(Config::default().pipeline, PipelineConfig::default());
+
+        let config = parse(
+            r#"
+            [pipeline.main_item]
+            possible_threshold = 0.9
+            "#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("possible_threshold"));
+    }
+
+    #[test]
+    fn apply_threshold_overrides() {
+        let mut config = Config::default();
+        let overrides = ThresholdOverrides {
+            found_threshold: Some(0.6),
+            possible_threshold: Some(0.1),
+            min_possible_share: Some(30),
+            areas: vec!["main_item_count.DIGIT_LETTER=0.04".parse().unwrap()],
+        };
+        config.apply_overrides(&overrides).unwrap();
+        assert_eq!(config.pipeline.main_item.found_threshold, 0.6);
+        assert_eq!(config.pipeline.main_item.possible_threshold, 0.1);
+        assert_eq!(config.pipeline.min_possible_share, 30);
+        let area = &config.detectors["main_item_count"].areas;
+        assert!(area
+            .iter()
+            .any(|a| a.name == "DIGIT_LETTER" && a.threshold == 0.04));
+
+        let overrides = ThresholdOverrides {
+            possible_threshold: Some(0.9),
+            ..ThresholdOverrides::default()
+        };
+        let err = config.clone().apply_overrides(&overrides).unwrap_err();
+        assert!(err.to_string().contains("possible_threshold"));
+        let overrides = ThresholdOverrides {
+            areas: vec!["main_item_count.unknown=0.1".parse().unwrap()],
+            ..ThresholdOverrides::default()
+        };
+        let err = config.apply_overrides(&overrides).unwrap_err();
+        assert!(err.to_string().contains("unknown"));
+    }
+
+    #[test]
+    fn parse_luma_only_range() {
+        let config = parse(
+            r#"
+            [detectors.rune_count]
+            level_width = 16
+
+            [[detectors.rune_count.areas]]
+            name = "DIGIT"
+            ranges = [{ luma = [12, 15] }]
+            threshold = 0.05
+            rects = ["150x30+40+5"]
+            "#,
+        )
+        .unwrap();
+        let range = config.detectors["rune_count"].areas[0].ranges[0];
+        assert!(range.rgb.is_none());
+        assert_eq!(range.luma, [12, 15]);
+        assert_eq!(range.chroma, [0, u8::MAX]);
+    }
+
+    #[test]
+    fn parse_components() {
+        let config = parse(
+            r#"
+            [detectors.rune_count]
+            level_width = 16
+
+            [[detectors.rune_count.areas]]
+            name = "DIGIT"
+            ranges = [{ rgb = [[12, 15], [12, 15], [12, 15]], luma = [12, 15] }]
+            threshold = 0.05
+            rects = ["150x30+40+5"]
+
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1740+1015"
+            detector = "rune_count"
+            align = "right"
+            charset = "0123456789"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let component = config.components.last().unwrap();
+        assert_eq!(component.name, "rune_count");
+        assert_eq!(
+            component.base_rect,
+            ConfigRect::Pixels(Rect::at(1700, 1010).of_size(200, 40))
+        );
+        assert_eq!(component.post_process, PostProcess::None);
+        assert_eq!(component.align, TextAlign::Right);
+
+        let config = parse(
+            r#"
+            [detectors.rune_count]
+            level_width = 16
+
+            [[detectors.rune_count.areas]]
+            name = "DIGIT"
+            ranges = [{ rgb = [[12, 15], [12, 15], [12, 15]], luma = [12, 15] }]
+            threshold = 0.05
+            rects = [[[0.2, 0.125], [0.95, 0.875]], "100x20+0+0@200x40"]
+
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = [[0.2, 0.125], [0.95, 0.875]]
+            detector = "rune_count"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let base = Rect::at(1700, 1010).of_size(200, 40);
+        let component = config.components.last().unwrap();
+        assert_eq!(
+            component.text_rect.to_rect(base),
+            Some(Rect::at(1740, 1015).of_size(151, 31))
+        );
+        let area = &config.detectors["rune_count"].areas[0];
+        assert_eq!(
+            area.rects[1].to_rect(Rect::at(0, 0).of_size(200, 40)),
+            Some(Rect::at(0, 0).of_size(100, 20))
+        );
+
+        let err = toml::from_str::<Config>(
+            r#"
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = [[0.5, 0.125], [0.25, 0.875]]
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("[[0.5, 0.125], [0.25, 0.875]]"));
+
+        let config = parse(
+            r#"
+            [detectors.rune_count]
+            level_width = 16
+
+            [[detectors.rune_count.areas]]
+            name = "DIGIT"
+            ranges = [{ rgb = [[12, 15], [12, 15], [12, 15]], luma = [12, 15] }]
+            threshold = 0.05
+            rects = ["150x30+40+5", "150x30+60+5"]
+
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1740+1015"
+            detector = "rune_count"
+            "#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("rects[1] `150x30+60+5`"));
+
+        let config = parse(
+            r#"
+            [[components]]
+            name = "main_item"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1740+1015"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+
+        let config = parse(
+            r#"
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1760+1015"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+
+        let config = parse(
+            r#"
+            [[components]]
+            name = "rune_count"
+            base_rect = "200x40+1700+1010"
+            text_rect = "150x30+1740+1015"
+            detector = "undefined"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn replace_default_components() {
+        let defaults = Config::default().components.len();
+        let config = parse(
+            r#"
+            [[components]]
+            name = "runes"
+            base_rect = "224x48+1660+990"
+            text_rect = "160x36+56+6@224x48"
+            detector = "runes"
+            post_process = "rune-count"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.components.len(), defaults);
+        let runes = config.components.iter().find(|c| c.name == "runes");
+        assert_eq!(
+            runes.unwrap().base_rect,
+            ConfigRect::Pixels(Rect::at(1660, 990).of_size(224, 48))
+        );
+    }
+
+    #[test]
+    fn invalid_builtin_detectors() {
+        let config = parse(
+            r#"
+            [detectors.side_item_two_digits]
+            level_width = 16
+
+            [[detectors.side_item_two_digits.areas]]
+            name = "BG"
+            ranges = [{ luma = [0, 3] }]
+            threshold = 0.9
+            rects = ["100x50+0+0"]
+            "#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rects[0] `100x50+0+0` of area `BG` in detector `side_item_two_digits` is out of component `side_item*` (556x44)"
+        );
+
+        let mut config = Config::default();
+        config.detectors.remove("side_item_one_digit");
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "detector `side_item_one_digit` required by built-in component `side_item*` is not defined"
+        );
+    }
+
+    #[test]
+    fn invalid_groups() {
+        let config = Config::default();
+        let filter = config.component_filter(&["pickups".to_owned()]).unwrap();
+        assert!(filter.matches("side_item1"));
+        assert!(!filter.matches("boss_bar"));
+
+        let config = parse(
+            r#"
+            [groups]
+            boss_bar = ["boss_bar", "respawn"]
+            "#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "group `boss_bar` conflicts with a component"
+        );
+
+        let config = parse(
+            r#"
+            [groups]
+            hud = ["main_item", "combat"]
+            combat = ["boss_bar", "hud"]
+            "#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.to_string(), "group `combat` contains itself");
+    }
+}
@@ -0,0 +1,376 @@
+//! Data packages of dictionaries and the default configuration, released apart from the analyzer
+//! so that new patches and DLCs of the game are supported without updating the analyzer.
+//!
+//! A package is a tar archive, optionally compressed with gzip, of files at its top level:
+//!
+//! * `manifest.toml`, describing the package and listing the SHA-256 checksums of the other files,
+//! * any of the files of [`Asset`], replacing the bundled ones of the same names.
+//!
+//! ```toml
+//! format = 1
+//! version = "1.12.3"
+//! description = "Shadow of the Erdtree items"
+//! min_analyzer_version = "0.1.0"
+//!
+//! [files]
+//! "items.tsv" = "0123456789abcdef..."
+//! ```
+//!
+//! `update-data` installs a package into [`installed_dir`], with a `SHA256SUMS` file,
+//! which is used as the asset directory unless `--assets-dir` is specified.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufReader, Read as _},
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, bail, eyre, WrapErr as _};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{self, Asset, CHECKSUMS_FILE},
+    build_info,
+};
+
+/// Name of the manifest file of packages
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Version of the package format, incremented when older analyzers cannot read packages
+pub const FORMAT: u32 = 1;
+
+/// Maximum size of a file in packages, to reject broken archives before reading them into memory
+const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Contents of `manifest.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Version of the package format
+    pub format: u32,
+    /// Version of the package, numbers separated by dots such as `1.12.3`
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Oldest version of the analyzer that can use the package
+    #[serde(default)]
+    pub min_analyzer_version: Option<String>,
+    /// SHA-256 checksums of the files in the package
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    fn parse(text: &str) -> eyre::Result<Self> {
+        let manifest = toml::from_str::<Self>(text)?;
+        if manifest.format != FORMAT {
+            bail!(
+                "package format {} is not supported (supported: {FORMAT})",
+                manifest.format
+            );
+        }
+        parse_version(&manifest.version).wrap_err("invalid `version`")?;
+        if let Some(min) = &manifest.min_analyzer_version {
+            let min_version = parse_version(min).wrap_err("invalid `min_analyzer_version`")?;
+            if parse_version(build_info::VERSION)? < min_version {
+                bail!(
+                    "package requires elden-analyzer {min} or later (running {})",
+                    build_info::VERSION
+                );
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Returns true if the package is older than `other`.
+    pub fn is_older_than(&self, other: &Self) -> bool {
+        match (parse_version(&self.version), parse_version(&other.version)) {
+            (Ok(version), Ok(other)) => version < other,
+            _ => false,
+        }
+    }
+}
+
+/// Parses a version of numbers separated by dots, ignoring trailing zeros so that `1.2` equals `1.2.0`.
+fn parse_version(version: &str) -> eyre::Result<Vec<u64>> {
+    let mut numbers = version
+        .split('.')
+        .map(|n| {
+            n.parse::<u64>()
+                .map_err(|_| eyre!("`{version}` must be numbers separated by dots"))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    while numbers.last() == Some(&0) {
+        numbers.pop();
+    }
+    Ok(numbers)
+}
+
+/// Package read and verified from an archive.
+#[derive(Debug, Clone)]
+pub struct DataPackage {
+    pub manifest: Manifest,
+    manifest_text: String,
+    files: Vec<(Asset, String)>,
+}
+
+impl DataPackage {
+    /// Reads the package at `path`, verifying the files against the manifest and validating them.
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        (|| {
+            let file = fs::File::open(path)?;
+            Self::from_reader(BufReader::new(file))
+        })()
+        .wrap_err_with(|| format!("invalid data package: {}", path.display()))
+    }
+
+    fn from_reader(mut reader: impl io::BufRead) -> eyre::Result<Self> {
+        let reader: Box<dyn io::Read> = if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        let mut texts = BTreeMap::new();
+        for entry in tar::Archive::new(reader).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let mut components = path
+                .components()
+                .filter(|component| *component != Component::CurDir);
+            let name = match (components.next(), components.next()) {
+                (None, _) if entry.header().entry_type().is_dir() => continue,
+                (Some(Component::Normal(name)), None) if entry.header().entry_type().is_file() => {
+                    name.to_string_lossy().into_owned()
+                }
+                _ => bail!(
+                    "{} must be a file at the top level of the package",
+                    path.display()
+                ),
+            };
+            if entry.size() > MAX_FILE_SIZE {
+                bail!("{name} is too large");
+            }
+            let mut text = String::new();
+            entry
+                .read_to_string(&mut text)
+                .wrap_err_with(|| format!("failed to read {name}"))?;
+            if texts.insert(name.clone(), text).is_some() {
+                bail!("{name} appears more than once");
+            }
+        }
+
+        let manifest_text = texts
+            .remove(MANIFEST_FILE)
+            .ok_or_else(|| eyre!("{MANIFEST_FILE} is missing"))?;
+        let manifest =
+            Manifest::parse(&manifest_text).wrap_err_with(|| format!("invalid {MANIFEST_FILE}"))?;
+        if let Some(name) = manifest
+            .files
+            .keys()
+            .find(|name| !texts.contains_key(*name))
+        {
+            bail!("{name} listed in {MANIFEST_FILE} is missing");
+        }
+        let mut files = vec![];
+        for (name, text) in texts {
+            let asset = Asset::ALL
+                .into_iter()
+                .find(|asset| asset.file_name() == name)
+                .ok_or_else(|| eyre!("{name} is not a known asset"))?;
+            let expected = manifest
+                .files
+                .get(&name)
+                .ok_or_else(|| eyre!("{name} is not listed in {MANIFEST_FILE}"))?;
+            let actual = assets::checksum_of(&text);
+            if !expected.eq_ignore_ascii_case(&actual) {
+                bail!("checksum of {name} does not match {MANIFEST_FILE} (expected {expected}, found {actual})");
+            }
+            asset
+                .validate(&text)
+                .wrap_err_with(|| format!("invalid {name}"))?;
+            files.push((asset, text));
+        }
+        Ok(Self {
+            manifest,
+            manifest_text,
+            files,
+        })
+    }
+
+    /// Returns the assets the package replaces.
+    pub fn assets(&self) -> impl Iterator<Item = Asset> + '_ {
+        self.files.iter().map(|(asset, _)| *asset)
+    }
+
+    /// Installs the package into `dir`, replacing the package installed there.
+    ///
+    /// The files are written into a sibling directory swapped with `dir` at the end,
+    /// so that an interrupted installation leaves the previous package.
+    pub fn install(&self, dir: &Path) -> eyre::Result<()> {
+        let staging = sibling(dir, "new");
+        let old = sibling(dir, "old");
+        (|| -> eyre::Result<()> {
+            remove_dir_if_exists(&staging)?;
+            fs::create_dir_all(&staging)?;
+            let mut checksums = String::new();
+            for (asset, text) in &self.files {
+                fs::write(staging.join(asset.file_name()), text)?;
+                checksums.push_str(&format!("{}  {asset}\n", assets::checksum_of(text)));
+            }
+            fs::write(staging.join(CHECKSUMS_FILE), checksums)?;
+            fs::write(staging.join(MANIFEST_FILE), &self.manifest_text)?;
+
+            remove_dir_if_exists(&old)?;
+            if dir.exists() {
+                fs::rename(dir, &old)?;
+            }
+            fs::rename(&staging, dir)?;
+            remove_dir_if_exists(&old)?;
+            Ok(())
+        })()
+        .wrap_err_with(|| format!("failed to install data package: {}", dir.display()))
+    }
+}
+
+/// Returns the directory packages are installed into, in the data directory of the platform.
+pub fn installed_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("elden-analyzer").join("data"))
+}
+
+/// Reads the manifest of the package installed in `dir`, or returns `None` if no package is installed.
+pub fn installed(dir: &Path) -> eyre::Result<Option<Manifest>> {
+    let path = dir.join(MANIFEST_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).wrap_err_with(|| format!("failed to read {}", path.display())),
+    };
+    let manifest = toml::from_str(&text).wrap_err_with(|| format!("invalid {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+/// Removes the package installed in `dir`, returning false if no package is installed.
+pub fn uninstall(dir: &Path) -> eyre::Result<bool> {
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(dir)
+        .wrap_err_with(|| format!("failed to remove data package: {}", dir.display()))?;
+    Ok(true)
+}
+
+fn sibling(dir: &Path, extension: &str) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_owned();
+    name.push(".");
+    name.push(extension);
+    dir.with_file_name(name)
+}
+
+fn remove_dir_if_exists(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    const AREAS: &str = "リムグレイブ\n嵐の丘\n影の地\n";
+
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, text) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(text.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, text.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn manifest(version: &str, files: &[(&str, &str)]) -> String {
+        let mut manifest = format!("format = 1\nversion = \"{version}\"\n\n[files]\n");
+        for (name, text) in files {
+            manifest.push_str(&format!("\"{name}\" = \"{}\"\n", assets::checksum_of(text)));
+        }
+        manifest
+    }
+
+    #[test]
+    fn compare_versions() {
+        assert_eq!(
+            parse_version("1.2.0").unwrap(),
+            parse_version("1.2").unwrap()
+        );
+        assert!(parse_version("1.10").unwrap() > parse_version("1.9.9").unwrap());
+        assert!(parse_version("1.x").is_err());
+    }
+
+    #[test]
+    fn read_package() {
+        let manifest = manifest("1.12.3", &[("area.txt", AREAS)]);
+        let tar = archive(&[(MANIFEST_FILE, &manifest), ("area.txt", AREAS)]);
+        let package = DataPackage::from_reader(tar.as_slice()).unwrap();
+        assert_eq!(package.manifest.version, "1.12.3");
+        assert_eq!(package.assets().collect::<Vec<_>>(), [Asset::Areas]);
+
+        let mut gz = GzEncoder::new(vec![], Compression::default());
+        io::Write::write_all(&mut gz, &tar).unwrap();
+        let tar_gz = gz.finish().unwrap();
+        DataPackage::from_reader(tar_gz.as_slice()).unwrap();
+
+        let err = |files: &[(&str, &str)]| {
+            let err = DataPackage::from_reader(archive(files).as_slice()).unwrap_err();
+            format!("{err:#}")
+        };
+        let broken = err(&[(MANIFEST_FILE, &manifest), ("area.txt", "リムグレイブ\n")]);
+        assert!(broken.contains("does not match"), "{broken}");
+        let missing = err(&[(MANIFEST_FILE, &manifest)]);
+        assert!(missing.contains("area.txt listed"), "{missing}");
+        let unlisted = err(&[
+            (MANIFEST_FILE, &manifest),
+            ("area.txt", AREAS),
+            ("item_en.tsv", ""),
+        ]);
+        assert!(unlisted.contains("not listed"), "{unlisted}");
+        let nested = err(&[(MANIFEST_FILE, &manifest), ("data/area.txt", AREAS)]);
+        assert!(nested.contains("top level"), "{nested}");
+        let newer = manifest.replace("format = 1", "format = 2");
+        let newer = err(&[(MANIFEST_FILE, &newer), ("area.txt", AREAS)]);
+        assert!(newer.contains("not supported"), "{newer}");
+    }
+
+    #[test]
+    fn install_package() {
+        let root =
+            std::env::temp_dir().join(format!("elden-analyzer-package-{}", std::process::id()));
+        let dir = root.join("data");
+        assert!(installed(&dir).unwrap().is_none());
+
+        for version in ["1.12.0", "1.12.3"] {
+            let manifest = manifest(version, &[("area.txt", AREAS)]);
+            let tar = archive(&[(MANIFEST_FILE, &manifest), ("area.txt", AREAS)]);
+            let package = DataPackage::from_reader(tar.as_slice()).unwrap();
+            package.install(&dir).unwrap();
+            assert_eq!(installed(&dir).unwrap().unwrap().version, version);
+        }
+        assert_eq!(fs::read_to_string(dir.join("area.txt")).unwrap(), AREAS);
+        assert!(!sibling(&dir, "new").exists());
+        assert!(!sibling(&dir, "old").exists());
+        // The installed directory is an asset directory verified by its checksums
+        assets::load_dir(&dir).unwrap();
+
+        assert!(uninstall(&dir).unwrap());
+        assert!(!uninstall(&dir).unwrap());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+//! Runtime environment recorded in outputs, since recognitions differ subtly across versions of the libraries.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::image_process::tesseract::Tesseract;
+
+/// Directories searched for trained data if `TESSDATA_PREFIX` is not set
+const TESSDATA_DIRS: &[&str] = &[
+    "/usr/share/tesseract-ocr/5/tessdata",
+    "/usr/share/tesseract-ocr/4.00/tessdata",
+    "/usr/share/tessdata",
+    "/usr/local/share/tessdata",
+];
+
+/// Index of the version string in the table of contents of trained data files (`TESSDATA_VERSION`)
+const TESSDATA_VERSION: usize = 23;
+
+/// Versions of the libraries and the machine running the analysis.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    entries: Vec<(&'static str, String)>,
+}
+
+impl Environment {
+    /// Collects the environment of the running process recognizing texts with the trained data of `ocr_lang`.
+    pub fn capture(ocr_lang: &str) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut entries = vec![
+            ("os", format!("{} {}", env::consts::OS, env::consts::ARCH)),
+            (
+                "cpu",
+                format!(
+                    "{} ({threads} threads)",
+                    cpu_model().as_deref().unwrap_or("unknown")
+                ),
+            ),
+            ("ffmpeg", elden_analyzer_video::ffmpeg_version()),
+            (
+                "ffmpeg configuration",
+                elden_analyzer_video::ffmpeg_configuration().to_owned(),
+            ),
+        ];
+        if let Some(version) = Tesseract::version() {
+            entries.push(("tesseract", version));
+            let traineddata = match find_traineddata(ocr_lang) {
+                Some(path) => {
+                    let version = match traineddata_version(&path) {
+                        Ok(Some(version)) => version,
+                        Ok(None) => "unknown version".to_owned(),
+                        Err(err) => format!("unreadable: {err}"),
+                    };
+                    format!("{version} ({})", path.display())
+                }
+                None => "not found".to_owned(),
+            };
+            entries.push(("tessdata", format!("{ocr_lang} {traineddata}")));
+        }
+        Self { entries }
+    }
+
+    /// Returns the pairs of the name and the description of each item.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+    }
+}
+
+fn cpu_model() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "model name").then(|| value.trim().to_owned())
+        })
+    } else {
+        env::var("PROCESSOR_IDENTIFIER").ok()
+    }
+}
+
+/// Returns the directories Tesseract is likely to load trained data from, in the order of priority.
+fn tessdata_dirs() -> Vec<PathBuf> {
+    match env::var_os("TESSDATA_PREFIX") {
+        Some(prefix) => vec![PathBuf::from(prefix)],
+        None => TESSDATA_DIRS.iter().map(PathBuf::from).collect(),
+    }
+}
+
+/// Returns the path of the trained data of `language` Tesseract is likely to load.
+pub fn find_traineddata(language: &str) -> Option<PathBuf> {
+    let file = format!("{language}.traineddata");
+    tessdata_dirs()
+        .into_iter()
+        .map(|dir| dir.join(&file))
+        .find(|path| path.exists())
+}
+
+/// Returns the first existing trained data directory and the languages of trained data in it.
+pub fn traineddata_languages() -> Option<(PathBuf, Vec<String>)> {
+    let dir = tessdata_dirs().into_iter().find(|dir| dir.is_dir())?;
+    let mut languages = fs::read_dir(&dir)
+        .ok()?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let language = path.file_name()?.to_str()?.strip_suffix(".traineddata")?;
+            Some(language.to_owned())
+        })
+        .collect::<Vec<_>>();
+    languages.sort();
+    Some((dir, languages))
+}
+
+/// Reads the version string of a trained data file, or `None` if it is not recorded.
+fn traineddata_version(path: &Path) -> io::Result<Option<String>> {
+    read_traineddata_version(File::open(path)?)
+}
+
+/// Reads the version string from the table of contents at the start of trained data.
+///
+/// Trained data starts with the number of entries and the offset of each entry (`-1` if absent),
+/// and an entry continues until the next present entry or the end of the data.
+fn read_traineddata_version(mut reader: impl Read + Seek) -> io::Result<Option<String>> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf[..4])?;
+    let num_entries = i32::from_le_bytes(buf[..4].try_into().unwrap());
+    let Ok(num_entries) = usize::try_from(num_entries) else {
+        return Ok(None);
+    };
+    if num_entries <= TESSDATA_VERSION || num_entries > 1000 {
+        return Ok(None);
+    }
+    let mut offsets = Vec::with_capacity(num_entries);
+    for _ in 0..num_entries {
+        reader.read_exact(&mut buf)?;
+        offsets.push(i64::from_le_bytes(buf));
+    }
+
+    let Ok(start) = u64::try_from(offsets[TESSDATA_VERSION]) else {
+        return Ok(None);
+    };
+    let end = match offsets[TESSDATA_VERSION + 1..]
+        .iter()
+        .find_map(|&offset| u64::try_from(offset).ok())
+    {
+        Some(end) => end,
+        None => reader.seek(SeekFrom::End(0))?,
+    };
+    let mut version = vec![0; end.saturating_sub(start).min(1024) as usize];
+    reader.seek(SeekFrom::Start(start))?;
+    reader.read_exact(&mut version)?;
+    let version = String::from_utf8_lossy(&version);
+    let version = version.trim_end_matches('\0').trim();
+    Ok((!version.is_empty()).then(|| version.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn traineddata(entries: &[Option<&[u8]>]) -> Vec<u8> {
+        let header_len = 4 + 8 * entries.len();
+        let mut data = (entries.len() as i32).to_le_bytes().to_vec();
+        let mut offset = header_len as i64;
+        for entry in entries {
+            match entry {
+                Some(entry) => {
+                    data.extend(offset.to_le_bytes());
+                    offset += entry.len() as i64;
+                }
+                None => data.extend((-1i64).to_le_bytes()),
+            }
+        }
+        data.extend(entries.iter().flatten().copied().flatten());
+        data
+    }
+
+    #[test]
+    fn read_version() {
+        let version = b"4.00.00alpha:jpn:synth20170629";
+        let mut entries: Vec<Option<&[u8]>> = vec![None; 24];
+        entries[0] = Some(b"config");
+        entries[TESSDATA_VERSION] = Some(version);
+        let data = traineddata(&entries);
+        assert_eq!(
+            read_traineddata_version(Cursor::new(data))
+                .unwrap()
+                .unwrap(),
+            "4.00.00alpha:jpn:synth20170629"
+        );
+
+        // Followed by an entry
+        entries.push(Some(b"lstm"));
+        let data = traineddata(&entries);
+        assert_eq!(
+            read_traineddata_version(Cursor::new(data))
+                .unwrap()
+                .unwrap(),
+            "4.00.00alpha:jpn:synth20170629"
+        );
+
+        entries[TESSDATA_VERSION] = None;
+        let data = traineddata(&entries);
+        assert_eq!(read_traineddata_version(Cursor::new(data)).unwrap(), None);
+
+        // Legacy data without the version entry
+        let data = traineddata(&entries[..10]);
+        assert_eq!(read_traineddata_version(Cursor::new(data)).unwrap(), None);
+    }
+}
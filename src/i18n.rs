@@ -0,0 +1,142 @@
+//! Language of messages shown to users.
+
+use std::{
+    env, fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+/// Language of messages shown to users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown language: {0}")]
+pub struct LangParseError(String);
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::En => write!(f, "en"),
+            Self::Ja => write!(f, "ja"),
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = LangParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "ja" => Ok(Self::Ja),
+            _ => Err(LangParseError(s.to_owned())),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Lang {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl serde::Serialize for Lang {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl Lang {
+    /// Returns the language of the locale in the environment (`LC_ALL`, `LC_MESSAGES` or `LANG`).
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|key| env::var(key).ok().filter(|v| !v.is_empty()));
+        match locale {
+            Some(locale) if locale.starts_with("ja") => Self::Ja,
+            _ => Self::En,
+        }
+    }
+
+    /// Returns the language of messages, which is English unless [`Lang::set`] is called.
+    pub fn current() -> Self {
+        match LANG.load(Ordering::Relaxed) {
+            x if x == Self::Ja as u8 => Self::Ja,
+            _ => Self::En,
+        }
+    }
+
+    pub fn set(self) {
+        LANG.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Returns the language of texts recognized with the Tesseract trained data `ocr_lang`, such as `jpn` or `eng`.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::i18n::Lang;
+    ///
+    /// assert_eq!(Lang::of_ocr_lang("eng"), Lang::En);
+    /// assert_eq!(Lang::of_ocr_lang("jpn"), Lang::Ja);
+    /// ```
+    pub fn of_ocr_lang(ocr_lang: &str) -> Self {
+        if ocr_lang.starts_with("eng") {
+            Self::En
+        } else {
+            Self::Ja
+        }
+    }
+}
+
+/// Message shown to users in each language.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::i18n::{Lang, Message};
+///
+/// const GREETING: Message = Message::new("hello", "こんにちは");
+/// assert_eq!(GREETING.get_in(Lang::Ja), "こんにちは");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Message {
+    en: &'static str,
+    ja: &'static str,
+}
+
+impl Message {
+    pub const fn new(en: &'static str, ja: &'static str) -> Self {
+        Self { en, ja }
+    }
+
+    /// Returns the message in the current language.
+    pub fn get(&self) -> &'static str {
+        self.get_in(Lang::current())
+    }
+
+    pub fn get_in(&self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::En => self.en,
+            Lang::Ja => self.ja,
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.get())
+    }
+}
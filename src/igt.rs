@@ -0,0 +1,248 @@
+//! In-game time (IGT) read from a timer overlay.
+
+use std::{fmt, str::FromStr};
+
+use color_eyre::eyre::{self, bail, OptionExt as _};
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect, time::Duration};
+use elden_analyzer_video::capture::Frame;
+use num_rational::Ratio;
+use regex::Regex;
+
+use crate::{
+    image_process::tesseract::Tesseract,
+    operator::{
+        ExtractText as _, PostProcess, Recognition, RectTextExtractor, RectTextExtractorBuilder,
+        TextAlign, DEFAULT_TEXT_HEIGHT,
+    },
+    util::ImageLogger,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Hours,
+    Minutes,
+    Seconds,
+    Fraction(u32),
+}
+
+/// Format of the timer overlay.
+///
+/// `H`, `M`, `S` denote hours, minutes and seconds, and a run of `f` denotes fractional digits.
+/// Parts enclosed in `[` and `]` are optional, and other characters must match literally.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::igt::IgtFormat;
+///
+/// let format = "[H:]MM:SS.ff".parse::<IgtFormat>().unwrap();
+/// assert_eq!(format.parse("1:02:03.45").unwrap().as_msec(), 3_723_450);
+/// assert_eq!(format.parse("02:03.45").unwrap().as_msec(), 123_450);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IgtFormat {
+    format: String,
+    re: Regex,
+    fields: Vec<Field>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IgtFormatParseError {
+    #[error("`{0}` appears more than once")]
+    DuplicatedField(char),
+    #[error("unbalanced brackets")]
+    UnbalancedBrackets,
+    #[error("no fields in format")]
+    NoFields,
+}
+
+impl FromStr for IgtFormat {
+    type Err = IgtFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pattern = String::from("^");
+        let mut fields = vec![];
+        let mut in_optional = false;
+
+        let mut chars = s.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let field = match ch {
+                'H' => Field::Hours,
+                'M' => Field::Minutes,
+                'S' => Field::Seconds,
+                'f' => Field::Fraction(0),
+                '[' if !in_optional => {
+                    in_optional = true;
+                    pattern.push_str("(?:");
+                    continue;
+                }
+                ']' if in_optional => {
+                    in_optional = false;
+                    pattern.push_str(")?");
+                    continue;
+                }
+                '[' | ']' => return Err(IgtFormatParseError::UnbalancedBrackets),
+                _ => {
+                    pattern.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4])));
+                    continue;
+                }
+            };
+
+            let mut len = 1;
+            while chars.next_if_eq(&ch).is_some() {
+                len += 1;
+            }
+            if fields
+                .iter()
+                .any(|f| std::mem::discriminant(f) == std::mem::discriminant(&field))
+            {
+                return Err(IgtFormatParseError::DuplicatedField(ch));
+            }
+
+            let (field, group) = match field {
+                Field::Hours => (field, r"(\d+)".to_owned()),
+                Field::Minutes | Field::Seconds => (field, format!(r"(\d{{1,{len}}})")),
+                Field::Fraction(_) => (Field::Fraction(len), format!(r"(\d{{{len}}})")),
+            };
+            fields.push(field);
+            pattern.push_str(&group);
+        }
+        if in_optional {
+            return Err(IgtFormatParseError::UnbalancedBrackets);
+        }
+        if fields.is_empty() {
+            return Err(IgtFormatParseError::NoFields);
+        }
+        pattern.push('$');
+
+        Ok(Self {
+            format: s.to_owned(),
+            re: Regex::new(&pattern).unwrap(),
+            fields,
+        })
+    }
+}
+
+impl fmt::Display for IgtFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format)
+    }
+}
+
+impl IgtFormat {
+    /// Parses the timer text, returning `None` if it does not match the format.
+    pub fn parse(&self, text: &str) -> Option<Duration> {
+        let caps = self.re.captures(text)?;
+
+        let mut sec = Ratio::ZERO;
+        for (field, cap) in self.fields.iter().zip(caps.iter().skip(1)) {
+            let Some(cap) = cap else {
+                continue;
+            };
+            let value = cap.as_str().parse::<i64>().ok()?;
+            sec += match field {
+                Field::Hours => Ratio::from_integer(value * 3600),
+                Field::Minutes => Ratio::from_integer(value * 60),
+                Field::Seconds => Ratio::from_integer(value),
+                Field::Fraction(len) => Ratio::new(value, 10i64.pow(*len)),
+            };
+        }
+        Some(Duration::new(sec))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IgtReaderBuilder {
+    pub rect: Rect,
+    pub format: IgtFormat,
+}
+
+impl IgtReaderBuilder {
+    /// Builds the reader for frames of `frame_rect`, failing if the timer region is not within the frame.
+    pub fn build(&self, frame_rect: Rect) -> eyre::Result<IgtReader> {
+        let rect = self.rect;
+        if rect.intersect(frame_rect) != Some(rect) {
+            bail!("in-game timer region {rect} is out of the frame {frame_rect}");
+        }
+        let (width, height) = (frame_rect.width() as i32, frame_rect.height() as i32);
+        let extractor = RectTextExtractorBuilder {
+            base_rect: ClipRect::from_points((0, 0), (width - 1, height - 1), (width, height)),
+            text_rect: ClipRect::from_points(
+                (rect.left(), rect.top()),
+                (rect.right(), rect.bottom()),
+                (width, height),
+            ),
+            post_process: PostProcess::None,
+            align: TextAlign::Unspecified,
+            text_height: DEFAULT_TEXT_HEIGHT,
+        }
+        .build(frame_rect)
+        .ok_or_eyre("invalid frame size")?;
+        Ok(IgtReader {
+            extractor,
+            format: self.format.clone(),
+        })
+    }
+}
+
+/// Reads the in-game time from a timer overlay in the frame.
+#[derive(Debug)]
+pub struct IgtReader {
+    extractor: RectTextExtractor,
+    format: IgtFormat,
+}
+
+impl IgtReader {
+    #[tracing::instrument(name = "igt", level = "trace", skip_all)]
+    pub fn read(&self, tess: &mut Tesseract, frame: &Frame) -> eyre::Result<Option<Duration>> {
+        let _subject = ImageLogger::enter_subject(frame.position().index(), "igt");
+        let text = match self.extractor.extract_text(tess, frame, None)? {
+            Recognition::Found(text, _) | Recognition::Possible(text, _) => text,
+        };
+        let igt = self.format.parse(&text);
+        tracing::trace!(text, ?igt);
+        Ok(igt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_format() {
+        let format = "H:MM:SS".parse::<IgtFormat>().unwrap();
+        assert_eq!(format.parse("1:02:03").unwrap().as_msec(), 3_723_000);
+        assert_eq!(format.parse("12:34:56").unwrap().as_msec(), 45_296_000);
+        assert!(format.parse("02:03").is_none());
+        assert!(format.parse("1:02:03.4").is_none());
+
+        let format = "[H:]MM:SS.fff".parse::<IgtFormat>().unwrap();
+        assert_eq!(format.parse("1:02:03.456").unwrap().as_msec(), 3_723_456);
+        assert_eq!(format.parse("2:03.456").unwrap().as_msec(), 123_456);
+        assert!(format.parse("2:03.45").is_none());
+
+        assert!("H:MM:HH".parse::<IgtFormat>().is_err());
+        assert!("[H:MM:SS".parse::<IgtFormat>().is_err());
+        assert!("H:MM]:SS".parse::<IgtFormat>().is_err());
+        assert!("::".parse::<IgtFormat>().is_err());
+    }
+
+    #[test]
+    fn build_within_frame() {
+        let frame_rect = Rect::at(0, 0).of_size(1920, 1080);
+        let builder = |rect| IgtReaderBuilder {
+            rect,
+            format: "[H:]MM:SS".parse().unwrap(),
+        };
+        assert!(builder(Rect::at(1700, 40).of_size(180, 40))
+            .build(frame_rect)
+            .is_ok());
+        // Regions partly out of the frame are not clipped
+        assert!(builder(Rect::at(1800, 40).of_size(180, 40))
+            .build(frame_rect)
+            .is_err());
+        assert!(builder(Rect::at(2000, 40).of_size(180, 40))
+            .build(frame_rect)
+            .is_err());
+    }
+}
@@ -27,6 +27,7 @@ pub struct HLines {
 impl HLines {
     pub fn run(&self, ty: HLineType, image: &GrayImage) -> Vec2d<u8> {
         let logger = ImageLogger::get();
+        let _stage = ImageLogger::enter_stage("h_lines");
 
         let image = tracing::trace_span!("blur")
             .in_scope(|| logger.log(filter::gaussian_blur_f32(image, self.sigma)));
@@ -53,7 +54,7 @@ impl HLines {
                 }
             });
 
-            if logger.display_image() {
+            if logger.enabled() {
                 logger.log(GrayImage::from_fn(image.width(), image.height(), |x, y| {
                     let fgy = filtered_gy[(x as usize, y as usize)];
                     let gy = gy[(x, y)][0];
@@ -97,7 +98,7 @@ impl HLines {
                 }
             }
 
-            if logger.display_image() {
+            if logger.enabled() {
                 logger.log(RgbImage::from_fn(image.width(), image.height(), |x, y| {
                     let v = local_maximum[(x as usize, y as usize)];
                     if gy.get_pixel(x, y)[0] > 0 {
@@ -1,3 +1,4 @@
+use color_eyre::eyre;
 use elden_analyzer_kernel::types::rect::Rect;
 use elden_analyzer_video::capture::Frame;
 use imageproc::{
@@ -15,19 +16,50 @@ use super::h_lines::{HLineType, HLines};
 
 #[derive(Debug, Clone)]
 pub struct LineFinder {
+    /// Horizontal radius of the median filter removing noises before finding edges
+    pub median_radius: u32,
     pub h_canny: HLines,
     pub find_line_segments: FindLineSegments,
 }
 
 impl LineFinder {
+    /// Returns the line finder with lengths in pixels multiplied by `scale`, for frames of other sizes.
+    pub fn scaled(&self, scale: f32) -> Self {
+        let px = |len: f32| (len * scale).round().max(1.0);
+        let FindLineSegments {
+            vote_threshold,
+            min_line_len,
+            max_line_gap,
+        } = self.find_line_segments;
+        Self {
+            median_radius: px(self.median_radius as f32) as u32,
+            h_canny: HLines {
+                sigma: self.h_canny.sigma * scale,
+                ..self.h_canny
+            },
+            find_line_segments: FindLineSegments {
+                vote_threshold: px(vote_threshold as f32) as u32,
+                min_line_len: px(min_line_len as f32) as i32,
+                max_line_gap: px(max_line_gap as f32) as i32,
+            },
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn measure_in(&self, frame: &Frame, ty: HLineType, clip_rect: Rect) -> FilledLength {
+    pub fn measure_in(
+        &self,
+        frame: &Frame,
+        ty: HLineType,
+        clip_rect: Rect,
+    ) -> eyre::Result<FilledLength> {
         let logger = ImageLogger::get();
+        let _stage = ImageLogger::enter_stage("line_finder");
 
-        let gray_image = tracing::trace_span!("gray")
-            .in_scope(|| logger.log(frame.to_min_gray_image_within(clip_rect).unwrap()));
+        let gray_image =
+            tracing::trace_span!("gray").in_scope(|| frame.to_min_gray_image_within(clip_rect))?;
+        let gray_image = logger.log(gray_image);
         let gray_image = tracing::trace_span!("median")
-            .in_scope(|| logger.log(filter::median_filter(&gray_image, 5, 0)));
+            .in_scope(|| logger.log(filter::median_filter(&gray_image, self.median_radius, 0)));
         let gray_image = tracing::trace_span!("lines").in_scope(|| {
             let data = self.h_canny.run(ty, &gray_image);
             let image =
@@ -36,7 +68,7 @@ impl LineFinder {
             logger.log(image)
         });
 
-        let lines = tracing::trace_span!("find-line-segments").in_scope(|| {
+        let filled = tracing::trace_span!("find-line-segments").in_scope(|| {
             let lines = (0..)
                 .zip(gray_image.rows())
                 .flat_map(|(y, row)| {
@@ -45,8 +77,11 @@ impl LineFinder {
                 })
                 .collect::<Vec<_>>();
 
-            if logger.display_image() {
-                logger.log({
+            let target_rect = Rect::at(0, 0).of_size(gray_image.width(), gray_image.height());
+            let filled = MeasureFilledLength::from_rect(target_rect).measure(lines.iter().cloned());
+
+            if logger.enabled() {
+                let image = {
                     let mut rgb_image: RgbImage = gray_image.convert();
                     for (xs, y) in &lines {
                         for x in xs.clone() {
@@ -60,13 +95,18 @@ impl LineFinder {
                         }
                     }
                     rgb_image
+                };
+                logger.log_with_caption(image, || {
+                    format!(
+                        "{ty:?} line segments (red: edges, green: gaps): filled {}/{} px",
+                        filled.filled_len(),
+                        filled.base_len()
+                    )
                 });
             }
 
-            lines
+            filled
         });
-
-        let target_rect = Rect::at(0, 0).of_size(gray_image.width(), gray_image.height());
-        MeasureFilledLength::from_rect(target_rect).measure(lines)
+        Ok(filled)
     }
 }
@@ -1,3 +1,4 @@
 pub mod h_lines;
 pub mod line_finder;
+pub mod overlay;
 pub mod tesseract;
@@ -0,0 +1,140 @@
+//! Annotations drawn onto frames, such as rectangles of components, texts and detection scores.
+
+use ab_glyph::{Font, PxScale};
+use elden_analyzer_kernel::types::rect::Rect;
+use imageproc::{
+    drawing,
+    image::{Rgb, RgbImage},
+};
+
+/// Space between texts and the edges of their boxes
+const TEXT_PADDING: u32 = 4;
+
+/// Scores at the right end of score bars, where scores are normalized so that `1.0` is the threshold
+const MAX_SCORE: f32 = 2.0;
+
+fn to_imageproc(rect: Rect) -> imageproc::rect::Rect {
+    imageproc::rect::Rect::at(rect.left(), rect.top()).of_size(rect.width(), rect.height())
+}
+
+/// Draws the outline of `rect`, growing outward by `thickness` pixels.
+pub fn draw_rect(image: &mut RgbImage, rect: Rect, color: Rgb<u8>, thickness: u32) {
+    for offset in 0..thickness {
+        let rect = Rect::at(rect.left() - offset as i32, rect.top() - offset as i32)
+            .of_size(rect.width() + offset * 2, rect.height() + offset * 2);
+        drawing::draw_hollow_rect_mut(image, to_imageproc(rect), color);
+    }
+}
+
+/// Draws `text` in a box filled with `background`, whose top-left corner is at `(x, y)`.
+///
+/// Returns the rect of the box.
+pub fn draw_text_box(
+    image: &mut RgbImage,
+    (x, y): (i32, i32),
+    text: &str,
+    font: &impl Font,
+    scale: f32,
+    color: Rgb<u8>,
+    background: Rgb<u8>,
+) -> Rect {
+    let scale = PxScale::from(scale);
+    let (width, height) = drawing::text_size(scale, font, text);
+    let rect = Rect::at(x, y).of_size(width + TEXT_PADDING * 2, height + TEXT_PADDING * 2);
+    drawing::draw_filled_rect_mut(image, to_imageproc(rect), background);
+    let padding = TEXT_PADDING as i32;
+    drawing::draw_text_mut(image, color, x + padding, y + padding, scale, font, text);
+    rect
+}
+
+/// Draws the outline of `rect` with `label` in a box under it, or above it if there is no room under it.
+pub fn draw_labeled_rect(
+    image: &mut RgbImage,
+    rect: Rect,
+    color: Rgb<u8>,
+    label: &str,
+    font: &impl Font,
+    scale: f32,
+) {
+    const THICKNESS: u32 = 2;
+    draw_rect(image, rect, color, THICKNESS);
+
+    let (_, text_height) = drawing::text_size(PxScale::from(scale), font, label);
+    let box_height = (text_height + TEXT_PADDING * 2) as i32;
+    let margin = THICKNESS as i32 + 1;
+    let below = rect.bottom() + margin;
+    let y = if below + box_height <= image.height() as i32 {
+        below
+    } else {
+        rect.top() - margin - box_height
+    };
+    draw_text_box(
+        image,
+        (rect.left(), y),
+        label,
+        font,
+        scale,
+        color,
+        Rgb([0, 0, 0]),
+    );
+}
+
+/// Draws a horizontal bar of `score` in `rect`, where the score is normalized so that `1.0` passes the threshold.
+///
+/// The bar is green if the score passes the threshold and red otherwise, and the threshold is marked with a white line.
+pub fn draw_score_bar(image: &mut RgbImage, rect: Rect, score: f32) {
+    drawing::draw_filled_rect_mut(image, to_imageproc(rect), Rgb([32, 32, 32]));
+
+    let width_of =
+        |score: f32| (rect.width() as f32 * score.clamp(0.0, MAX_SCORE) / MAX_SCORE) as u32;
+    let bar_width = width_of(score);
+    if bar_width > 0 {
+        let color = if score >= 1.0 {
+            Rgb([0, 192, 0])
+        } else {
+            Rgb([192, 0, 0])
+        };
+        let bar = Rect::at(rect.left(), rect.top()).of_size(bar_width, rect.height());
+        drawing::draw_filled_rect_mut(image, to_imageproc(bar), color);
+    }
+
+    let x = (rect.left() + width_of(1.0) as i32) as f32;
+    drawing::draw_line_segment_mut(
+        image,
+        (x, rect.top() as f32),
+        (x, rect.bottom() as f32),
+        Rgb([255, 255, 255]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_bar() {
+        let mut image = RgbImage::new(100, 10);
+        draw_score_bar(&mut image, Rect::at(0, 0).of_size(100, 10), 0.5);
+        assert_eq!(image[(10, 5)], Rgb([192, 0, 0]));
+        assert_eq!(image[(30, 5)], Rgb([32, 32, 32]));
+        assert_eq!(image[(50, 5)], Rgb([255, 255, 255]));
+
+        draw_score_bar(&mut image, Rect::at(0, 0).of_size(100, 10), 3.0);
+        assert_eq!(image[(10, 5)], Rgb([0, 192, 0]));
+        assert_eq!(image[(99, 5)], Rgb([0, 192, 0]));
+    }
+
+    #[test]
+    fn rect_out_of_image() {
+        let mut image = RgbImage::new(10, 10);
+        draw_rect(
+            &mut image,
+            Rect::at(-5, 8).of_size(20, 20),
+            Rgb([255, 0, 0]),
+            2,
+        );
+        assert_eq!(image[(0, 8)], Rgb([255, 0, 0]));
+        assert_eq!(image[(0, 7)], Rgb([255, 0, 0]));
+        assert_eq!(image[(5, 5)], Rgb([0, 0, 0]));
+    }
+}
@@ -1,44 +1,201 @@
-use std::ffi::CString;
+use std::time::Duration;
+#[cfg(feature = "ocr-tesseract")]
+use std::{
+    ffi::{CStr, CString},
+    ptr::{self, NonNull},
+    time::Instant,
+};
 
 use color_eyre::eyre;
+#[cfg(feature = "ocr-tesseract")]
+use color_eyre::eyre::OptionExt as _;
 use imageproc::image::GrayImage;
-use tesseract_plumbing::{tesseract_sys::TessPageSegMode_PSM_SINGLE_LINE, TessBaseApi};
+#[cfg(feature = "ocr-tesseract")]
+use tesseract_plumbing::tesseract_sys::{
+    TessBaseAPI, TessBaseAPICreate, TessBaseAPIDelete, TessBaseAPIGetUTF8Text, TessBaseAPIInit3,
+    TessBaseAPIMeanTextConf, TessBaseAPIRecognize, TessBaseAPISetImage, TessBaseAPISetPageSegMode,
+    TessDeleteText, TessMonitorCreate, TessMonitorDelete, TessMonitorSetDeadlineMSecs,
+    TessPageSegMode_PSM_SINGLE_LINE, ETEXT_DESC,
+};
 
+#[cfg(feature = "ocr-tesseract")]
+use crate::i18n::Lang;
+
+/// Recognitions of an image taking longer than this are abandoned by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Error returned by [`Tesseract::recognize`] when the recognition of an image is abandoned.
+#[derive(Debug, thiserror::Error)]
+#[error("text recognition timed out after {0:?}")]
+pub struct RecognizeTimeout(pub Duration);
+
+/// Tesseract recognizing texts in the calling thread.
+///
+/// Recognitions taking too long are abandoned by the deadline of the progress monitor of Tesseract,
+/// which is checked between words.
+#[cfg(feature = "ocr-tesseract")]
 #[derive(Debug)]
 pub struct Tesseract {
-    tess: TessBaseApi,
+    handle: NonNull<TessBaseAPI>,
+    /// Tesseract inserts spaces between Japanese characters, while English words are separated by spaces
+    keep_spaces: bool,
+    timeout: Duration,
+}
+
+// SAFETY: the API is not tied to the thread creating it, and `&mut self` serializes the calls to it
+#[cfg(feature = "ocr-tesseract")]
+unsafe impl Send for Tesseract {}
+
+#[cfg(feature = "ocr-tesseract")]
+impl Drop for Tesseract {
+    fn drop(&mut self) {
+        // SAFETY: the handle is created by `TessBaseAPICreate` and deleted only here
+        unsafe { TessBaseAPIDelete(self.handle.as_ptr()) };
+    }
 }
 
+#[cfg(feature = "ocr-tesseract")]
 impl Tesseract {
+    pub fn is_available() -> bool {
+        true
+    }
+
+    /// Returns the version of the linked Tesseract library.
+    pub fn version() -> Option<String> {
+        Some(tesseract_plumbing::version().to_string_lossy().into_owned())
+    }
+
     pub fn new(datapath: Option<&str>, language: Option<&str>) -> eyre::Result<Self> {
-        let mut tess = TessBaseApi::create();
-        tess.init_2(
-            datapath.map(CString::new).transpose()?.as_deref(),
-            language.map(CString::new).transpose()?.as_deref(),
-        )?;
-        tess.set_page_seg_mode(TessPageSegMode_PSM_SINGLE_LINE);
-        Ok(Self { tess })
+        let keep_spaces = language.is_some_and(|lang| Lang::of_ocr_lang(lang) == Lang::En);
+        let datapath = datapath.map(CString::new).transpose()?;
+        let language = language.map(CString::new).transpose()?;
+        // SAFETY: no preconditions
+        let handle = NonNull::new(unsafe { TessBaseAPICreate() })
+            .ok_or_eyre("failed to create Tesseract API")?;
+        let tess = Self {
+            handle,
+            keep_spaces,
+            timeout: DEFAULT_TIMEOUT,
+        };
+        // SAFETY: the strings outlive the call, and null pointers select the defaults
+        let res = unsafe {
+            TessBaseAPIInit3(
+                handle.as_ptr(),
+                datapath.as_deref().map_or(ptr::null(), CStr::as_ptr),
+                language.as_deref().map_or(ptr::null(), CStr::as_ptr),
+            )
+        };
+        if res != 0 {
+            eyre::bail!("failed to initialize Tesseract");
+        }
+        // SAFETY: the handle is valid
+        unsafe { TessBaseAPISetPageSegMode(handle.as_ptr(), TessPageSegMode_PSM_SINGLE_LINE) };
+        Ok(tess)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
     }
 
+    /// Recognizes the text in `image`, and returns it with the mean confidence.
+    ///
+    /// Returns [`RecognizeTimeout`] if the recognition takes longer than the timeout.
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn recognize(&mut self, image: &GrayImage) -> eyre::Result<(String, i32)> {
-        self.tess.set_image(
-            image.as_raw(),
-            image.width() as i32,
-            image.height() as i32,
-            1,
-            image.width() as i32,
-        )?;
-
-        let conf = self.tess.mean_text_conf();
-
-        let text = self
-            .tess
-            .get_utf8_text()?
-            .as_ref()
-            .to_string_lossy()
-            .replace(|ch: char| ch.is_whitespace(), "");
+        let handle = self.handle.as_ptr();
+        let width = i32::try_from(image.width())?;
+        let height = i32::try_from(image.height())?;
+        // SAFETY: the image outlives the recognition, which reads `height` rows of `width` bytes
+        unsafe { TessBaseAPISetImage(handle, image.as_raw().as_ptr(), width, height, 1, width) };
+
+        let monitor = Monitor::with_deadline(self.timeout)?;
+        let start = Instant::now();
+        // SAFETY: the handle and the monitor are valid
+        let res = unsafe { TessBaseAPIRecognize(handle, monitor.0.as_ptr()) };
+        if res != 0 {
+            if start.elapsed() >= self.timeout {
+                return Err(RecognizeTimeout(self.timeout).into());
+            }
+            eyre::bail!("text recognition failed");
+        }
+
+        // SAFETY: the handle is valid
+        let conf = unsafe { TessBaseAPIMeanTextConf(handle) };
+        // SAFETY: the handle is valid, and the returned text is deleted after copied
+        let text = unsafe {
+            let ptr = NonNull::new(TessBaseAPIGetUTF8Text(handle))
+                .ok_or_eyre("failed to get recognized text")?;
+            let text = CStr::from_ptr(ptr.as_ptr()).to_string_lossy().into_owned();
+            TessDeleteText(ptr.as_ptr());
+            text
+        };
+        let text = if self.keep_spaces {
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            text.replace(|ch: char| ch.is_whitespace(), "")
+        };
         tracing::trace!(text, conf);
         Ok((text, conf))
     }
 }
+
+/// Progress monitor of a recognition, which is cancelled after the deadline.
+#[cfg(feature = "ocr-tesseract")]
+struct Monitor(NonNull<ETEXT_DESC>);
+
+#[cfg(feature = "ocr-tesseract")]
+impl Monitor {
+    fn with_deadline(timeout: Duration) -> eyre::Result<Self> {
+        // SAFETY: no preconditions
+        let monitor = NonNull::new(unsafe { TessMonitorCreate() })
+            .ok_or_eyre("failed to create Tesseract monitor")?;
+        let monitor = Self(monitor);
+        // A deadline of zero is ignored by Tesseract
+        let msecs = i32::try_from(timeout.as_millis())
+            .unwrap_or(i32::MAX)
+            .max(1);
+        // SAFETY: the monitor is valid
+        unsafe { TessMonitorSetDeadlineMSecs(monitor.0.as_ptr(), msecs) };
+        Ok(monitor)
+    }
+}
+
+#[cfg(feature = "ocr-tesseract")]
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        // SAFETY: the monitor is created by `TessMonitorCreate` and deleted only here
+        unsafe { TessMonitorDelete(self.0.as_ptr()) };
+    }
+}
+
+/// Placeholder used when no OCR backend is compiled in.
+///
+/// [`Tesseract::new`] always fails, so values of this type never exist.
+#[cfg(not(feature = "ocr-tesseract"))]
+#[derive(Debug)]
+pub struct Tesseract {
+    _never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "ocr-tesseract"))]
+impl Tesseract {
+    pub fn is_available() -> bool {
+        false
+    }
+
+    pub fn version() -> Option<String> {
+        None
+    }
+
+    pub fn new(_datapath: Option<&str>, _language: Option<&str>) -> eyre::Result<Self> {
+        eyre::bail!("OCR is not supported in this build (enable the `ocr-tesseract` feature)")
+    }
+
+    pub fn set_timeout(&mut self, _timeout: Duration) {
+        match self._never {}
+    }
+
+    pub fn recognize(&mut self, _image: &GrayImage) -> eyre::Result<(String, i32)> {
+        match self._never {}
+    }
+}
@@ -0,0 +1,375 @@
+//! Higher-level events inferred from spans of components.
+//!
+//! Rules combine spans of several components into spans of events, such as fast travels,
+//! which are written in the same format as spans of components.
+//! Besides the built-in [`FastTravel`], rules of sequences of spans are defined in a rule file (see [`Rules`]).
+
+use std::{collections::HashSet, fs, path::Path};
+
+use color_eyre::eyre::{self, bail, WrapErr as _};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+use serde::{Deserialize, Deserializer};
+
+use crate::io::{event_id::EventId, spans::Span};
+
+/// Name of spans of fast travels inferred by [`FastTravel`]
+pub const FAST_TRAVEL: &str = "fast_travel";
+
+/// Rule inferring events from spans of components.
+pub trait Rule {
+    /// Returns spans of the inferred events, sorted by the start.
+    ///
+    /// `spans` must be sorted by the start.
+    fn infer(&self, spans: &[Span]) -> Vec<Span>;
+}
+
+/// Infers fast travels from loading screens shown just after the map.
+///
+/// A span of the loading screen starting within `max_gap` after the end of a span of the map is a fast travel.
+/// Its text is the destination, read from the first span of `destinations` starting within `max_delay`
+/// after the loading screen, or `??` if not found.
+/// The span covers the loading screen.
+///
+/// The map and the loading screen are not built-in components, and are defined in the config file.
+#[derive(Debug, Clone)]
+pub struct FastTravel {
+    /// Name of the component of the map screen
+    pub map: String,
+    /// Name of the component of the loading screen
+    pub loading: String,
+    /// Names of the components whose texts are the destination, such as area banners and menus of sites of grace
+    pub destinations: Vec<String>,
+    /// Maximum time between the end of the map and the start of the loading screen
+    pub max_gap: Duration,
+    /// Maximum time between the end of the loading screen and the start of the destination
+    pub max_delay: Duration,
+}
+
+impl Default for FastTravel {
+    fn default() -> Self {
+        Self {
+            map: "map".to_owned(),
+            loading: "loading".to_owned(),
+            destinations: vec!["area_banner".to_owned(), "grace_rest".to_owned()],
+            max_gap: Duration::new(Ratio::from_integer(2)),
+            max_delay: Duration::new(Ratio::from_integer(30)),
+        }
+    }
+}
+
+impl FastTravel {
+    /// Returns the first span of the destination shown within `max_delay` after `end`.
+    fn destination<'a>(&self, spans: &'a [Span], end: Timestamp) -> Option<&'a Span> {
+        let limit = end + self.max_delay;
+        spans
+            .iter()
+            .skip_while(|span| span.start < end)
+            .take_while(|span| span.start <= limit)
+            .find(|span| !span.is_possible() && self.destinations.contains(&span.name))
+    }
+}
+
+impl Rule for FastTravel {
+    fn infer(&self, spans: &[Span]) -> Vec<Span> {
+        let mut events = vec![];
+        let mut last_map = None::<&Span>;
+        for (i, span) in spans.iter().enumerate() {
+            if span.name == self.map {
+                last_map = Some(span);
+                continue;
+            }
+            if span.name != self.loading {
+                continue;
+            }
+            let Some(map) = last_map.take() else {
+                continue;
+            };
+            if span.start < map.end || span.start > map.end + self.max_gap {
+                continue;
+            }
+            let text = match self.destination(&spans[i + 1..], span.end) {
+                Some(dest) => dest.text.clone(),
+                None => "??".to_owned(),
+            };
+            let id = span
+                .id
+                .as_ref()
+                .map(|id| EventId::new(id.source, FAST_TRAVEL, id.start_frame));
+            events.push(Span {
+                name: FAST_TRAVEL.to_owned(),
+                start: span.start,
+                end: span.end,
+                text,
+                igt: span.igt,
+                id,
+            });
+        }
+        events
+    }
+}
+
+/// Rules defined in a rule file.
+///
+/// # Examples
+/// ```toml
+/// [[rules]]
+/// name = "death"
+/// steps = [
+///     { components = ["you_died"] },
+///     { components = ["loading"], max_gap = "00:00:05" },
+///     { components = ["respawn"], max_gap = "00:00:10", optional = true, text = true },
+/// ]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    pub rules: Vec<SequenceRule>,
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read rule file: {}", path.display()))?;
+        let rules = toml::from_str::<Self>(&text)
+            .wrap_err_with(|| format!("failed to parse rule file: {}", path.display()))?;
+        rules
+            .validate()
+            .wrap_err_with(|| format!("invalid rule file: {}", path.display()))?;
+        Ok(rules)
+    }
+
+    fn validate(&self) -> eyre::Result<()> {
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            let name = &rule.name;
+            if name == FAST_TRAVEL {
+                bail!("rule `{name}` conflicts with a built-in rule");
+            }
+            if !names.insert(name.as_str()) {
+                bail!("rule `{name}` is defined more than once");
+            }
+            let Some(first) = rule.steps.first() else {
+                bail!("rule `{name}` has no steps");
+            };
+            if first.optional {
+                bail!("the first step of rule `{name}` must not be optional");
+            }
+            if let Some(i) = rule
+                .steps
+                .iter()
+                .position(|step| step.components.is_empty())
+            {
+                bail!("steps[{i}] of rule `{name}` has no components");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rule inferring events from sequences of spans matching its steps in order.
+///
+/// Each span of a sequence starts no earlier than the previous one, and within `max_gap` of the step after its end.
+/// Optional steps may be missing from sequences.
+/// The span of the event is from the start of the first span to the end of the last one,
+/// and keeps the in-game time and the source of the ID of the first span.
+/// Its text is the texts of the steps with `text = true` separated by spaces, where missing ones are `??`.
+///
+/// Spans of an event are not used by other events of the same rule,
+/// and events of the same rule do not overlap.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceRule {
+    /// Name of spans of the events
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+/// Step of a [`SequenceRule`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Components whose spans match the step
+    pub components: Vec<String>,
+    /// Maximum time between the end of the span of the previous step and the start of this one (default: 0)
+    #[serde(default, deserialize_with = "duration")]
+    pub max_gap: Duration,
+    /// The step may be missing
+    #[serde(default)]
+    pub optional: bool,
+    /// The text of the span is included in the text of the event
+    #[serde(default)]
+    pub text: bool,
+}
+
+impl SequenceRule {
+    /// Returns the indices of spans matching the steps after the first one, or `None` if a required step is missing.
+    fn match_rest(&self, spans: &[Span], first: usize) -> Option<Vec<Option<usize>>> {
+        let mut matched = vec![Some(first)];
+        let mut prev = first;
+        for step in &self.steps[1..] {
+            let limit = spans[prev].end + step.max_gap;
+            let found = (prev + 1..spans.len())
+                .take_while(|&j| spans[j].start <= limit)
+                .find(|&j| step.components.contains(&spans[j].name));
+            match found {
+                Some(j) => {
+                    matched.push(Some(j));
+                    prev = j;
+                }
+                None if step.optional => matched.push(None),
+                None => return None,
+            }
+        }
+        Some(matched)
+    }
+}
+
+impl Rule for SequenceRule {
+    fn infer(&self, spans: &[Span]) -> Vec<Span> {
+        let mut events = vec![];
+        let mut next = 0;
+        for (i, span) in spans.iter().enumerate() {
+            if i < next || !self.steps[0].components.contains(&span.name) {
+                continue;
+            }
+            let Some(matched) = self.match_rest(spans, i) else {
+                continue;
+            };
+            let last = matched.iter().flatten().last().copied().unwrap_or(i);
+            let text = self
+                .steps
+                .iter()
+                .zip(&matched)
+                .filter(|(step, _)| step.text)
+                .map(|(_, j)| j.map_or("??", |j| spans[j].text.as_str()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let id = span
+                .id
+                .as_ref()
+                .map(|id| EventId::new(id.source, &self.name, id.start_frame));
+            events.push(Span {
+                name: self.name.clone(),
+                start: span.start,
+                end: spans[last].end,
+                text,
+                igt: span.igt,
+                id,
+            });
+            // Later events start after this one ends
+            next = spans
+                .iter()
+                .position(|s| s.start >= spans[last].end)
+                .unwrap_or(spans.len())
+                .max(last + 1);
+        }
+        events
+    }
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let ts = s.parse::<Timestamp>().map_err(serde::de::Error::custom)?;
+    Ok(Duration::new(ts.as_ratio()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(lines: &[&str]) -> Vec<Span> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn fast_travel() {
+        let spans = spans(&[
+            "00:01:00.000-00:01:10.000  (map)",
+            "00:01:10.500-00:01:20.000  (loading)",
+            "00:01:21.000-00:01:50.000 関門前の廃墟 (grace_rest)",
+            // loading without the map, e.g. after deaths
+            "00:02:00.000-00:02:10.000  (loading)",
+            "00:02:11.000-00:02:20.000 関門前の廃墟 (grace_rest)",
+            // the map closed long before the loading screen
+            "00:03:00.000-00:03:10.000  (map)",
+            "00:03:20.000-00:03:30.000  (loading)",
+            // no destination
+            "00:04:00.000-00:04:10.000  (map) #0000000000000001-map-7200",
+            "00:04:10.000-00:04:20.000  (loading) #0000000000000001-loading-7500",
+        ]);
+        let events = FastTravel::default()
+            .infer(&spans)
+            .iter()
+            .map(|span| span.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "00:01:10.500-00:01:20.000 関門前の廃墟 (fast_travel)",
+                "00:04:10.000-00:04:20.000 ?? (fast_travel) #0000000000000001-fast_travel-7500",
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_rule() {
+        let rules = toml::from_str::<Rules>(
+            r#"
+            [[rules]]
+            name = "death"
+            steps = [
+                { components = ["you_died"] },
+                { components = ["loading"], max_gap = "00:00:05" },
+                { components = ["respawn"], max_gap = "00:00:10", optional = true, text = true },
+            ]
+            "#,
+        )
+        .unwrap();
+        rules.validate().unwrap();
+        let spans = spans(&[
+            "00:01:00.000-00:01:03.000  (you_died) #0000000000000001-you_died-1800",
+            "00:01:02.000-00:01:02.500 聖杯瓶 (side_item0)",
+            "00:01:06.000-00:01:15.000  (loading)",
+            "00:01:20.000-00:01:22.000 stake (respawn)",
+            // no loading screen
+            "00:02:00.000-00:02:03.000  (you_died)",
+            "00:02:30.000-00:02:40.000  (loading)",
+            // no respawn dialog
+            "00:03:00.000-00:03:03.000  (you_died)",
+            "00:03:04.000-00:03:10.000  (loading)",
+        ]);
+        let events = rules.rules[0]
+            .infer(&spans)
+            .iter()
+            .map(|span| span.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "00:01:00.000-00:01:22.000 stake (death) #0000000000000001-death-1800",
+                "00:03:00.000-00:03:10.000 ?? (death)",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for rules in [
+            r#"rules = [{ name = "fast_travel", steps = [{ components = ["map"] }] }]"#,
+            r#"rules = [{ name = "empty", steps = [] }]"#,
+            r#"rules = [{ name = "optional", steps = [{ components = ["map"], optional = true }] }]"#,
+            r#"rules = [{ name = "nothing", steps = [{ components = [] }] }]"#,
+        ] {
+            let rules = toml::from_str::<Rules>(rules).unwrap();
+            assert!(rules.validate().is_err(), "{rules:?}");
+        }
+        assert!(toml::from_str::<Rules>(
+            r#"rules = [{ name = "gap", steps = [{ components = ["map"], max_gap = "2s" }] }]"#
+        )
+        .is_err());
+    }
+}
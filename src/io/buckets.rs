@@ -0,0 +1,158 @@
+//! Counts of events in fixed windows of time, written by `analyze --output-buckets` as CSV for charts.
+//!
+//! Each row is a window starting at `start`, with the events starting in it:
+//!
+//! ```text
+//! start,pickups,deaths,bosses,runes_gained
+//! 00:00:00.000,4,0,0,350
+//! 00:01:00.000,1,1,1,0
+//! ```
+//!
+//! Windows without events are written as zeros, so that rows are evenly spaced.
+//! Unlike other outputs, the file has no comment lines, so that it can be read by any CSV reader.
+
+use std::io::{self, Write};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+
+use crate::components;
+
+use super::spans::{self, Span};
+
+/// Length of windows unless `--bucket-window` is given, in seconds
+pub const DEFAULT_WINDOW: Ratio<i64> = Ratio::new_raw(60, 1);
+
+/// Header row of the columns
+const HEADER: &str = "start,pickups,deaths,bosses,runes_gained";
+
+/// Counts of events in a window of time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: Timestamp,
+    /// Items picked up, including ones not reliably recognized
+    pub pickups: u32,
+    /// Deaths, where the runes held fall to zero
+    pub deaths: u32,
+    /// Encounters with bosses
+    pub bosses: u32,
+    /// Sum of increases of the runes held
+    pub runes_gained: u64,
+}
+
+impl Bucket {
+    fn new(start: Timestamp) -> Self {
+        Self {
+            start,
+            pickups: 0,
+            deaths: 0,
+            bosses: 0,
+            runes_gained: 0,
+        }
+    }
+}
+
+/// Counts events in `spans` in windows of `window` from the window of the first span to the end of the last one.
+///
+/// Spans of side items moving across rows are linked first (see [`spans::track_side_items`]),
+/// so that each pickup is counted once. Changes of runes are read from consecutive spans of the `runes` component,
+/// and counted in the window where the later span starts. Possible and skipped spans of runes are ignored,
+/// and spending all runes held is also counted as a death.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::{buckets, spans::Span};
+/// use elden_analyzer_kernel::types::time::Duration;
+/// use num_rational::Ratio;
+///
+/// let spans = [
+///     "00:00:10.000-00:00:12.000 聖杯瓶 (main_item)",
+///     "00:00:00.000-00:00:20.000 100 (runes)",
+///     "00:00:20.000-00:01:05.000 450 (runes)",
+///     "00:01:05.000-00:01:30.000 0 (runes)",
+/// ]
+/// .map(|s| s.parse::<Span>().unwrap());
+/// let buckets = buckets::aggregate(spans, Duration::new(Ratio::from_integer(60)));
+/// assert_eq!(buckets.len(), 2);
+/// assert_eq!((buckets[0].pickups, buckets[0].runes_gained), (1, 350));
+/// assert_eq!((buckets[1].deaths, buckets[1].runes_gained), (1, 0));
+/// ```
+pub fn aggregate(spans: impl IntoIterator<Item = Span>, window: Duration) -> Vec<Bucket> {
+    let mut spans = spans::track_side_items(spans, Duration::new(spans::PICKUP_WINDOW));
+    spans.retain(|span| !span.is_skipped());
+    spans.sort_by_key(|span| span.start);
+    let (Some(first), Some(end)) = (spans.first(), spans.iter().map(|span| span.end).max()) else {
+        return vec![];
+    };
+
+    let window = window.as_ratio();
+    let zero = Timestamp::default();
+    let index = |ts: Timestamp| ((ts - zero).as_ratio() / window).floor().to_integer();
+    let first_index = index(first.start);
+    // The end is excluded, so a span ending at a boundary does not add a window
+    let last_index = ((end - zero).as_ratio() / window).ceil().to_integer() - 1;
+    let mut buckets = (first_index..=last_index.max(first_index))
+        .map(|i| Bucket::new(Timestamp::new(window * i)))
+        .collect::<Vec<_>>();
+
+    let mut last_runes = None;
+    for span in &spans {
+        let bucket = &mut buckets[(index(span.start) - first_index) as usize];
+        if components::is_pickup_name(&span.name) {
+            bucket.pickups += 1;
+        } else if components::is_boss_name(&span.name) && !span.is_possible() {
+            bucket.bosses += 1;
+        } else if components::is_runes_name(&span.name) {
+            let Ok(runes) = span.text.parse::<u64>() else {
+                continue;
+            };
+            if let Some(last) = last_runes.replace(runes) {
+                bucket.runes_gained += runes.saturating_sub(last);
+                if runes == 0 && last > 0 {
+                    bucket.deaths += 1;
+                }
+            }
+        }
+    }
+    buckets
+}
+
+/// Writes `buckets` as CSV with the header row.
+pub fn write(writer: &mut impl Write, buckets: &[Bucket]) -> io::Result<()> {
+    writeln!(writer, "{HEADER}")?;
+    for bucket in buckets {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            bucket.start, bucket.pickups, bucket.deaths, bucket.bosses, bucket.runes_gained
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_windows_and_side_items() {
+        let spans = [
+            "00:01:10.000-00:01:12.000 聖杯瓶 (side_item0)",
+            "00:01:12.000-00:01:14.000 聖杯瓶 (side_item1)",
+            "00:01:20.000-00:01:22.000 ??ルーン (main_item)",
+            "00:03:00.000-00:03:30.000 忌み鬼、マルギット (boss_bar)",
+            "00:03:40.000-00:04:00.000 dropped (skipped)",
+        ]
+        .map(|s| s.parse::<Span>().unwrap());
+        let buckets = aggregate(spans, Duration::new(Ratio::from_integer(60)));
+        let mut output = vec![];
+        write(&mut output, &buckets).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "start,pickups,deaths,bosses,runes_gained\n\
+             00:01:00.000,2,0,0,0\n\
+             00:02:00.000,0,0,0,0\n\
+             00:03:00.000,0,0,1,0\n"
+        );
+    }
+}
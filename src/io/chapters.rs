@@ -0,0 +1,172 @@
+//! Chapters of videos written by the `chapters` subcommand, pasted into descriptions of YouTube videos.
+//!
+//! Each line is the start of a chapter and its title:
+//!
+//! ```text
+//! 00:00:00 Start
+//! 00:12:01 Picked up 緋雫の聖杯瓶 ×3
+//! 00:21:03 マルギット
+//! ```
+//!
+//! YouTube requires the first chapter to start at `00:00:00` and each chapter to be at least 10 seconds long,
+//! so a `Start` chapter is inserted and events close to the previous chapter are merged into it.
+
+use std::io::{self, Write};
+
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+
+use crate::components::{self, ComponentFilter};
+
+use super::spans::{self, Span};
+
+/// Minimum length of chapters accepted by YouTube, in seconds
+pub const MIN_LENGTH: Ratio<i64> = Ratio::new_raw(10, 1);
+
+/// Title of the chapter inserted at the start of videos
+const START_TITLE: &str = "Start";
+
+/// Separator of titles of events merged into a chapter
+const TITLE_SEPARATOR: &str = " / ";
+
+/// A chapter of a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start: Timestamp,
+    pub title: String,
+}
+
+/// How spans are turned into chapters.
+#[derive(Debug, Clone, Copy)]
+pub enum Grouping<'a> {
+    /// Each span of the selected components starts a chapter.
+    Events(&'a ComponentFilter),
+    /// Each span of the component, such as banners of areas, starts a chapter titled with its text.
+    Area(&'a str),
+}
+
+/// Returns the chapters of the video analyzed into `spans`.
+///
+/// Spans of side items moving across rows are linked first (see [`spans::track_side_items`]), so that they are
+/// named [`spans::TRACKED_SIDE_ITEM`] and each pickup starts one chapter. Possible and skipped spans are ignored.
+/// Chapters starting within `min_length` after the previous chapter are merged into it, and consecutive chapters
+/// with the same title are merged.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::{
+///     components::ComponentFilter,
+///     io::{chapters::{self, Grouping}, spans::Span},
+/// };
+/// use elden_analyzer_kernel::types::time::Duration;
+/// use num_rational::Ratio;
+///
+/// let spans = [
+///     "00:00:30.000-00:00:32.000 聖杯瓶 (main_item)",
+///     "00:00:35.000-00:00:37.000 ルーン弧 (main_item)",
+///     "00:01:00.000-00:01:40.000 マルギット (boss_bar)",
+/// ]
+/// .map(|s| s.parse::<Span>().unwrap());
+/// let components = ComponentFilter::new(["main_item", "boss_bar"]);
+/// let min_length = Duration::new(Ratio::from_integer(10));
+/// let chapters = chapters::from_spans(spans, Grouping::Events(&components), min_length);
+/// let titles = chapters.iter().map(|c| c.title.as_str()).collect::<Vec<_>>();
+/// assert_eq!(titles, ["Start", "Picked up 聖杯瓶 / Picked up ルーン弧", "マルギット"]);
+/// ```
+pub fn from_spans(
+    spans: impl IntoIterator<Item = Span>,
+    grouping: Grouping<'_>,
+    min_length: Duration,
+) -> Vec<Chapter> {
+    let window = Duration::new(spans::PICKUP_WINDOW);
+    let mut spans = spans::track_side_items(spans, window);
+    spans.retain(|span| !span.is_possible() && !span.is_skipped());
+    spans.sort_by_key(|span| span.start);
+
+    let mut chapters = Vec::<Chapter>::new();
+    for span in spans {
+        let title = match grouping {
+            Grouping::Events(components) if components.matches(&span.name) => event_title(&span),
+            Grouping::Area(name) if span.name == name => span.text,
+            _ => continue,
+        };
+        let start = truncate(span.start);
+        match chapters.last_mut() {
+            Some(last) if last.title.rsplit(TITLE_SEPARATOR).next() == Some(&*title) => {}
+            Some(last) if start - last.start < min_length => {
+                last.title.push_str(TITLE_SEPARATOR);
+                last.title.push_str(&title);
+            }
+            _ => chapters.push(Chapter { start, title }),
+        }
+    }
+
+    // The first chapter must start at the start of the video
+    let zero = Timestamp::default();
+    match chapters.first_mut() {
+        Some(first) if first.start - zero < min_length => first.start = zero,
+        Some(_) => chapters.insert(
+            0,
+            Chapter {
+                start: zero,
+                title: START_TITLE.to_owned(),
+            },
+        ),
+        None => {}
+    }
+    chapters
+}
+
+/// Writes `chapters` as lines of `HH:MM:SS TITLE`.
+pub fn write(writer: &mut impl Write, chapters: &[Chapter]) -> io::Result<()> {
+    for chapter in chapters {
+        let total_sec = chapter.start.as_ratio().to_integer();
+        let (hour, min, sec) = (total_sec / 3600, (total_sec / 60) % 60, total_sec % 60);
+        writeln!(writer, "{hour:02}:{min:02}:{sec:02} {}", chapter.title)?;
+    }
+    Ok(())
+}
+
+/// Returns the title of the chapter started by `span`.
+fn event_title(span: &Span) -> String {
+    if components::is_pickup_name(&span.name) {
+        format!("Picked up {}", span.text)
+    } else {
+        span.text.clone()
+    }
+}
+
+/// Truncates `ts` to seconds, which chapters are written in.
+fn truncate(ts: Timestamp) -> Timestamp {
+    Timestamp::new(ts.as_ratio().trunc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spans: &[&str]) -> Vec<Span> {
+        spans.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn group_by_area() {
+        let spans = parse(&[
+            "00:00:03.000-00:00:05.000 リムグレイブ (area_banner)",
+            "00:00:20.000-00:00:22.000 聖杯瓶 (main_item)",
+            "00:01:00.000-00:01:02.000 ??関門前 (area_banner)",
+            "00:02:00.000-00:02:02.000 リムグレイブ (area_banner)",
+            "00:03:00.500-00:03:02.000 嵐の丘 (area_banner)",
+            "00:03:05.000-00:03:07.000 ストームヴィル城 (area_banner)",
+        ]);
+        let min_length = Duration::new(MIN_LENGTH);
+        let chapters = from_spans(spans, Grouping::Area("area_banner"), min_length);
+
+        let mut output = vec![];
+        write(&mut output, &chapters).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "00:00:00 リムグレイブ\n00:03:00 嵐の丘 / ストームヴィル城\n"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Corrections of texts of spans, written by the `corrections` subcommand and edited by users.
+//!
+//! Each line is a span with its recognized text and the text to use instead, separated by tabs:
+//!
+//! ```text
+//! ID START COMPONENT RECOGNIZED TEXT
+//! ```
+//!
+//! Only the last column is edited, and [`REMOVED`] there removes the span, such as a false detection.
+//! Spans are matched by their IDs, or by their starts and components if they have no IDs.
+//! Span files are not rewritten, so the recognized texts are kept and corrections are applied when spans are read
+//! by `--corrections` of other subcommands.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::{self, OptionExt as _, WrapErr as _};
+use elden_analyzer_kernel::types::time::Timestamp;
+use num_rational::Ratio;
+
+use super::{event_id::EventId, spans::Span};
+
+/// Text removing the span
+pub const REMOVED: &str = "-";
+
+/// Header row of the columns
+const HEADER: &str = "id\tstart\tcomponent\trecognized\ttext";
+
+/// Key identifying a span.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Id(EventId),
+    Start(Ratio<i64>, String),
+}
+
+impl Key {
+    fn of_span(span: &Span) -> Self {
+        match &span.id {
+            Some(id) => Self::Id(id.clone()),
+            None => Self::Start(span.start.as_ratio(), span.name.clone()),
+        }
+    }
+}
+
+/// Texts of spans edited by users.
+#[derive(Debug, Default)]
+pub struct Corrections {
+    texts: HashMap<Key, String>,
+}
+
+impl Corrections {
+    /// Reads a corrections file.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+        Self::read(BufReader::new(file))
+            .wrap_err_with(|| format!("failed to read {}", path.display()))
+    }
+
+    /// Reads corrections from a reader, keeping only the lines whose texts are edited.
+    /// Empty lines, comment lines and the header row are skipped.
+    pub fn read(reader: impl BufRead) -> eyre::Result<Self> {
+        let mut texts = HashMap::new();
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || super::is_comment(&line) || line == HEADER {
+                continue;
+            }
+            let (key, recognized, text) = parse_line(&line)
+                .wrap_err_with(|| format!("invalid correction at line {}", lineno + 1))?;
+            if text != recognized {
+                texts.insert(key, text.to_owned());
+            }
+        }
+        Ok(Self { texts })
+    }
+
+    /// Returns the number of corrected spans.
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// Returns the corrected text of `span`, or `None` if it is not corrected.
+    pub fn get(&self, span: &Span) -> Option<&str> {
+        self.texts.get(&Key::of_span(span)).map(String::as_str)
+    }
+
+    /// Replaces the texts of corrected spans in `spans`, removing spans corrected to [`REMOVED`].
+    ///
+    /// Returns the number of spans replaced or removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::io::{corrections::Corrections, spans::Span};
+    ///
+    /// let text = "id\tstart\tcomponent\trecognized\ttext\n\
+    ///             \t00:00:01.000\tmain_item\t??聖杯\t聖杯瓶\n\
+    ///             \t00:00:05.000\tboss_bar\t??マル\t-\n";
+    /// let corrections = Corrections::read(text.as_bytes()).unwrap();
+    /// let mut spans = vec![
+    ///     "00:00:01.000-00:00:02.000 ??聖杯 (main_item)".parse::<Span>().unwrap(),
+    ///     "00:00:05.000-00:00:06.000 ??マル (boss_bar)".parse::<Span>().unwrap(),
+    /// ];
+    /// assert_eq!(corrections.apply(&mut spans), 2);
+    /// assert_eq!(spans.len(), 1);
+    /// assert_eq!(spans[0].text, "聖杯瓶");
+    /// ```
+    pub fn apply(&self, spans: &mut Vec<Span>) -> usize {
+        let mut count = 0;
+        spans.retain_mut(|span| {
+            let Some(text) = self.get(span) else {
+                return true;
+            };
+            count += 1;
+            if text == REMOVED {
+                return false;
+            }
+            span.text = text.to_owned();
+            true
+        });
+        count
+    }
+}
+
+/// Writes the lines of `spans` to be edited, excluding skipped intervals.
+///
+/// Texts already corrected by `corrections` are written as corrected, so that edits are kept when the file is
+/// written again for updated spans.
+pub fn write(
+    writer: &mut impl Write,
+    spans: &[Span],
+    corrections: Option<&Corrections>,
+) -> eyre::Result<()> {
+    writeln!(
+        writer,
+        "# Edit the last column to correct the text, or write `{REMOVED}` to remove the span"
+    )?;
+    writeln!(writer, "{HEADER}")?;
+    for span in spans.iter().filter(|span| !span.is_skipped()) {
+        if span.text.contains('\t') {
+            eyre::bail!("text of the span has a tab: {span}");
+        }
+        let text = corrections.and_then(|c| c.get(span)).unwrap_or(&span.text);
+        let id = span.id.as_ref().map(EventId::to_string).unwrap_or_default();
+        writeln!(
+            writer,
+            "{id}\t{start}\t{name}\t{recognized}\t{text}",
+            start = span.start,
+            name = span.name,
+            recognized = span.text,
+        )?;
+    }
+    Ok(())
+}
+
+fn parse_line(line: &str) -> eyre::Result<(Key, &str, &str)> {
+    let mut columns = line.split('\t');
+    let mut next = |name: &str| columns.next().ok_or_eyre(format!("missing {name}"));
+    let (id, start, name) = (next("id")?, next("start")?, next("component")?);
+    let (recognized, text) = (next("recognized text")?, next("text")?);
+    let key = if id.is_empty() {
+        Key::Start(start.parse::<Timestamp>()?.as_ratio(), name.to_owned())
+    } else {
+        Key::Id(id.parse()?)
+    };
+    Ok((key, recognized, text.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let spans = [
+            "00:00:01.000-00:00:02.000 ??聖杯 (main_item) #00000000000000ff-main_item-30",
+            "00:00:03.000-00:00:04.000 ルーン弧 (main_item) #00000000000000ff-main_item-90",
+            "00:00:05.000-00:00:06.000 dropped (skipped)",
+        ]
+        .map(|s| s.parse::<Span>().unwrap());
+        let mut output = vec![];
+        write(&mut output, &spans, None).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 4);
+
+        // Edited by the user
+        let edited = output.replace("??聖杯\t??聖杯", "??聖杯\t聖杯瓶");
+        let corrections = Corrections::read(edited.as_bytes()).unwrap();
+        assert_eq!(corrections.len(), 1);
+
+        let mut rewritten = vec![];
+        write(&mut rewritten, &spans, Some(&corrections)).unwrap();
+        assert_eq!(String::from_utf8(rewritten).unwrap(), edited);
+
+        let mut corrected = spans.to_vec();
+        assert_eq!(corrections.apply(&mut corrected), 1);
+        assert_eq!(corrected[0].text, "聖杯瓶");
+        assert_eq!(corrected[1], spans[1]);
+    }
+}
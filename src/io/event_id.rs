@@ -0,0 +1,183 @@
+//! Identifiers of events shared by output files.
+//!
+//! An event is a span in which a component is shown.
+//! Its ID is derived from the source video, the component and the start frame,
+//! so analyzing the same video again yields the same IDs and outputs referring to the same event can be joined.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read as _, Seek as _, SeekFrom},
+    num::ParseIntError,
+    path::Path,
+    str::FromStr,
+};
+
+/// Number of bytes hashed from each end of a file
+const FILE_HASH_CHUNK: u64 = 1024 * 1024;
+
+/// Identifier of the source of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceId(u64);
+
+impl SourceId {
+    /// Computes the ID of a video file.
+    ///
+    /// Only the size and the first and last 1 MiB of the file are hashed, so large videos are identified quickly.
+    pub fn of_file(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        let mut hasher = Fnv1a::new();
+        hasher.write(&len.to_le_bytes());
+        let mut buf = vec![];
+        file.by_ref().take(FILE_HASH_CHUNK).read_to_end(&mut buf)?;
+        if len > FILE_HASH_CHUNK {
+            file.seek(SeekFrom::Start(u64::max(
+                FILE_HASH_CHUNK,
+                len - FILE_HASH_CHUNK,
+            )))?;
+            file.read_to_end(&mut buf)?;
+        }
+        hasher.write(&buf);
+        Ok(Self(hasher.finish()))
+    }
+
+    /// Computes the ID of an input of the analysis, which is a video file or a sequence of images.
+    ///
+    /// Directories and patterns of images are identified by their paths, since hashing all images would be slow.
+    pub fn of_input(path: &Path) -> io::Result<Self> {
+        if path.is_file() {
+            Self::of_file(path)
+        } else {
+            Ok(Self::of_name(&path.to_string_lossy()))
+        }
+    }
+
+    /// Computes the ID of a source identified by a name, such as a captured screen.
+    pub fn of_name(name: &str) -> Self {
+        let mut hasher = Fnv1a::new();
+        hasher.write(name.as_bytes());
+        Self(hasher.finish())
+    }
+}
+
+impl fmt::Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for SourceId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+/// Identifier of an event, formatted as `SOURCE-COMPONENT-FRAME`.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::event_id::{EventId, SourceId};
+///
+/// let id = EventId::new(SourceId::of_name("video"), "side_item0", 1234);
+/// let text = id.to_string();
+/// assert!(text.ends_with("-side_item0-1234"));
+/// assert_eq!(text.parse::<EventId>().unwrap(), id);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId {
+    pub source: SourceId,
+    pub component: String,
+    /// Index of the first frame of the event
+    pub start_frame: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EventIdParseError {
+    #[error("expected `SOURCE-COMPONENT-FRAME`")]
+    Syntax,
+    #[error("invalid number: {0}")]
+    Number(#[from] ParseIntError),
+}
+
+impl EventId {
+    pub fn new(source: SourceId, component: impl Into<String>, start_frame: usize) -> Self {
+        Self {
+            source,
+            component: component.into(),
+            start_frame,
+        }
+    }
+}
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            source,
+            component,
+            start_frame,
+        } = self;
+        write!(f, "{source}-{component}-{start_frame}")
+    }
+}
+
+impl FromStr for EventId {
+    type Err = EventIdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Component names may contain `-`, but source IDs and frame indices do not
+        let (source, rest) = s.split_once('-').ok_or(EventIdParseError::Syntax)?;
+        let (component, start_frame) = rest.rsplit_once('-').ok_or(EventIdParseError::Syntax)?;
+        if component.is_empty() {
+            return Err(EventIdParseError::Syntax);
+        }
+        Ok(Self {
+            source: source.parse()?,
+            component: component.to_owned(),
+            start_frame: start_frame.parse()?,
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash, which is stable across platforms and Rust versions unlike `DefaultHasher`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_hash() {
+        // Test vectors of FNV-1a
+        assert_eq!(SourceId::of_name("").0, 0xcbf2_9ce4_8422_2325);
+        assert_eq!(SourceId::of_name("a").0, 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn parse_event_id() {
+        let id = "00000000000000ff-rune-count-12".parse::<EventId>().unwrap();
+        assert_eq!(id, EventId::new(SourceId(0xff), "rune-count", 12));
+        assert!("00000000000000ff-12".parse::<EventId>().is_err());
+        assert!("xyz-main_item-12".parse::<EventId>().is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Event log written by `watch --event-log`.
+//!
+//! Each line is a JSON object (NDJSON) describing a span, appended as soon as the span is finalized:
+//!
+//! ```text
+//! {"logged_at":"2025-01-01T00:00:00.000Z","name":"main_item","start":"00:00:01.000","end":"00:00:03.500","text":"緋雫の聖杯瓶","igt":"00:12:34.000","id":"…","possible":false,"category":"tools","item_id":1,"rarity":"common"}
+//! ```
+//!
+//! `rarity` is inferred from the color of the item name (`common`, `uncommon`, `rare` or `legendary`),
+//! and is omitted for components not showing items.
+//!
+//! The log is rotated when it grows larger or older than the limits of [`Rotation`]:
+//! the current file is renamed to `PATH.1`, older ones are shifted to `PATH.2`, `PATH.3`, …,
+//! and the ones beyond [`Rotation::keep`] are removed.
+//!
+//! Each line is written with a single `write` to the file opened in append mode and synced before the next one,
+//! so a crash loses at most the line being written.
+//! An incomplete line left by a crash is removed when the log is opened again.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read as _, Seek as _, SeekFrom, Write as _},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::items::{self, ItemCategory, ItemId, Rarity};
+
+use super::spans::Span;
+
+/// Limits of the event log, beyond which it is rotated.
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    /// Maximum size of a file in bytes
+    pub max_bytes: Option<u64>,
+    /// Maximum time since a file is started
+    pub max_age: Option<chrono::Duration>,
+    /// Number of rotated files to keep
+    pub keep: usize,
+}
+
+/// A line of the event log.
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    /// Time the record is written, in RFC 3339
+    pub logged_at: String,
+    pub name: &'a str,
+    pub start: String,
+    pub end: String,
+    pub text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub igt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The text is not reliably recognized
+    pub possible: bool,
+    /// Category of the item, if the text is a known item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<ItemCategory>,
+    /// ID of the item in the item database, if the text is a known item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<ItemId>,
+    /// Rarity of the item inferred from the color of its name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(span: &'a Span, rarity: Option<Rarity>, logged_at: DateTime<Utc>) -> Self {
+        Self {
+            logged_at: logged_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            name: &span.name,
+            start: span.start.to_string(),
+            end: span.end.to_string(),
+            text: &span.text,
+            igt: span.igt.map(|igt| igt.to_string()),
+            id: span.id.as_ref().map(|id| id.to_string()),
+            possible: span.is_possible(),
+            category: items::category(&span.text),
+            item_id: items::item_id(&span.text),
+            rarity,
+        }
+    }
+}
+
+/// Appends records to a rotating NDJSON file.
+#[derive(Debug)]
+pub struct EventLog {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    started_at: DateTime<Utc>,
+}
+
+impl EventLog {
+    /// Opens the event log at `path` to append records to, creating it if it does not exist.
+    pub fn open(path: impl Into<PathBuf>, rotation: Rotation) -> io::Result<Self> {
+        let path = path.into();
+        let (file, size) = open_append(&path)?;
+        // Files written before restarts are rotated by the time they were created
+        let started_at = file
+            .metadata()
+            .and_then(|metadata| metadata.created())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size,
+            started_at,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a record of `span` with the `rarity` of its item, rotating the log beforehand if it exceeds the limits.
+    pub fn append(&mut self, span: &Span, rarity: Option<Rarity>) -> io::Result<()> {
+        let now = Utc::now();
+        let mut line = serde_json::to_vec(&Record::new(span, rarity, now))?;
+        line.push(b'\n');
+
+        if self.needs_rotation(now, line.len() as u64) {
+            self.rotate(now)?;
+        }
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn needs_rotation(&self, now: DateTime<Utc>, len: u64) -> bool {
+        // Records larger than the limit are written to an empty file rather than rotated forever
+        if self.size == 0 {
+            return false;
+        }
+        let Rotation {
+            max_bytes, max_age, ..
+        } = self.rotation;
+        max_bytes.is_some_and(|max| self.size + len > max)
+            || max_age.is_some_and(|max| now - self.started_at >= max)
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        self.file.sync_all()?;
+        let keep = self.rotation.keep;
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            remove_if_exists(&rotated_path(&self.path, keep))?;
+            for i in (1..keep).rev() {
+                let from = rotated_path(&self.path, i);
+                if from.exists() {
+                    fs::rename(from, rotated_path(&self.path, i + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        let (file, size) = open_append(&self.path)?;
+        tracing::info!(path = %self.path.display(), "event log rotated");
+        self.file = file;
+        self.size = size;
+        self.started_at = now;
+        Ok(())
+    }
+}
+
+/// Returns the path of the `n`-th newest rotated file of the log at `path`.
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Opens `path` in append mode, truncating an incomplete last line.
+fn open_append(path: &Path) -> io::Result<(File, u64)> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let size = file.metadata()?.len();
+    let complete = complete_len(&mut file, size)?;
+    if complete < size {
+        tracing::warn!(
+            path = %path.display(),
+            bytes = size - complete,
+            "incomplete record removed from event log"
+        );
+        file.set_len(complete)?;
+        file.sync_all()?;
+    }
+    Ok((file, complete))
+}
+
+/// Returns the length of `file` up to the end of its last complete line.
+fn complete_len(file: &mut File, size: u64) -> io::Result<u64> {
+    const CHUNK: u64 = 4096;
+    let mut end = size;
+    let mut buf = vec![0; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let buf = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(buf)?;
+        if let Some(i) = buf.iter().rposition(|&b| b == b'\n') {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("elden-analyzer-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn span(text: &str) -> Span {
+        format!("00:00:01.000-00:00:03.500 {text} (main_item)")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn record() {
+        let possible = span("??聖杯瓶");
+        let at = DateTime::from_timestamp(0, 0).unwrap();
+        assert_eq!(
+            serde_json::to_string(&Record::new(&possible, None, at)).unwrap(),
+            r#"{"logged_at":"1970-01-01T00:00:00.000Z","name":"main_item","start":"00:00:01.000","end":"00:00:03.500","text":"??聖杯瓶","possible":true}"#
+        );
+        let seed = span("黄金の種子");
+        assert_eq!(
+            serde_json::to_string(&Record::new(&seed, Some(Rarity::Legendary), at)).unwrap(),
+            r#"{"logged_at":"1970-01-01T00:00:00.000Z","name":"main_item","start":"00:00:01.000","end":"00:00:03.500","text":"黄金の種子","possible":false,"category":"upgrade-materials","item_id":477,"rarity":"legendary"}"#
+        );
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = temp_dir("rotate-by-size");
+        let path = dir.join("events.ndjson");
+        let rotation = Rotation {
+            max_bytes: Some(200),
+            max_age: None,
+            keep: 2,
+        };
+        let mut log = EventLog::open(&path, rotation).unwrap();
+        for i in 0..10 {
+            log.append(&span(&format!("item{i}")), None).unwrap();
+        }
+        let lines = |path: &Path| fs::read_to_string(path).unwrap().lines().count();
+        assert!(fs::metadata(&path).unwrap().len() <= 200);
+        assert!(lines(&rotated_path(&path, 1)) > 0);
+        assert!(lines(&rotated_path(&path, 2)) > 0);
+        assert!(!rotated_path(&path, 3).exists());
+        assert!(fs::read_to_string(&path).unwrap().contains("item9"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncate_incomplete_line() {
+        let dir = temp_dir("truncate-incomplete-line");
+        let path = dir.join("events.ndjson");
+        fs::write(&path, "{\"name\":\"a\"}\n{\"name\":").unwrap();
+        let rotation = Rotation {
+            max_bytes: None,
+            max_age: None,
+            keep: 1,
+        };
+        let mut log = EventLog::open(&path, rotation).unwrap();
+        log.append(&span("item"), None).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "{\"name\":\"a\"}");
+        assert!(lines[1].contains("\"text\":\"item\""));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+//! Events streamed by `analyze --emit-events` to overlay tools and stream bots.
+//!
+//! Each span is sent as soon as it is finalized, as a JSON object of the same fields as the lines of
+//! `--output-json` (see [`json::Record`]):
+//!
+//! * to a WebSocket server (`ws://HOST:PORT/PATH`), in a text message,
+//! * to a Unix domain socket (a path), as a line.
+//!
+//! The receiver listens before the analysis starts.
+//! If it goes away during the analysis, events are dropped until it is reconnected on a later event,
+//! so that restarting an overlay does not stop the analysis.
+//! Reconnection is retried at increasing intervals, so an unreachable receiver does not stall the analysis.
+
+use std::{
+    fmt,
+    io::{self, Write as _},
+    net::{TcpStream, ToSocketAddrs as _},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{self, WrapErr as _};
+use tungstenite::{http::Uri, Message, WebSocket};
+
+use super::json;
+
+/// Time to wait for the receiver to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time to wait for the receiver to accept an event or to answer the handshake before it is regarded as gone
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval before the first reconnection attempt, doubled on each failure
+const MIN_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Upper limit of the interval between reconnection attempts
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Receiver of events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// URL of a WebSocket server
+    WebSocket(String),
+    /// Path of a Unix domain socket
+    Unix(PathBuf),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseTargetError {
+    #[error("invalid WebSocket URL: {0}")]
+    InvalidUrl(String),
+    #[error("secure WebSockets (`wss://`) are not supported")]
+    SecureWebSocket,
+    #[error("Unix domain sockets are not supported on this platform")]
+    UnixUnsupported,
+}
+
+impl FromStr for Target {
+    type Err = ParseTargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("wss://") {
+            return Err(ParseTargetError::SecureWebSocket);
+        }
+        if s.starts_with("ws://") {
+            let uri = s
+                .parse::<Uri>()
+                .map_err(|_| ParseTargetError::InvalidUrl(s.to_owned()))?;
+            if uri.host().is_none() {
+                return Err(ParseTargetError::InvalidUrl(s.to_owned()));
+            }
+            return Ok(Self::WebSocket(s.to_owned()));
+        }
+        if cfg!(unix) {
+            Ok(Self::Unix(PathBuf::from(s)))
+        } else {
+            Err(ParseTargetError::UnixUnsupported)
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WebSocket(url) => write!(f, "{url}"),
+            Self::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Connection {
+    WebSocket(Box<WebSocket<TcpStream>>),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Connection {
+    fn open(target: &Target) -> eyre::Result<Self> {
+        match target {
+            Target::WebSocket(url) => {
+                let uri = url.parse::<Uri>()?;
+                let host = uri.host().unwrap_or_default();
+                let stream = connect_tcp((host, uri.port_u16().unwrap_or(80)))?;
+                stream.set_nodelay(true)?;
+                // Set before the handshake, which waits for the response of the receiver
+                stream.set_read_timeout(Some(IO_TIMEOUT))?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                let (socket, _response) =
+                    tungstenite::client(url.as_str(), stream).map_err(|err| match err {
+                        tungstenite::HandshakeError::Failure(err) => eyre::Report::new(err),
+                        tungstenite::HandshakeError::Interrupted(_) => {
+                            eyre::eyre!("handshake interrupted")
+                        }
+                    })?;
+                Ok(Self::WebSocket(Box::new(socket)))
+            }
+            #[cfg(unix)]
+            Target::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                Ok(Self::Unix(stream))
+            }
+            #[cfg(not(unix))]
+            Target::Unix(_) => Err(io::Error::from(io::ErrorKind::Unsupported).into()),
+        }
+    }
+
+    fn send(&mut self, event: String) -> eyre::Result<()> {
+        match self {
+            Self::WebSocket(socket) => socket.send(Message::text(event))?,
+            #[cfg(unix)]
+            Self::Unix(stream) => {
+                let mut line = event.into_bytes();
+                line.push(b'\n');
+                stream.write_all(&line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn connect_tcp(addr: (&str, u16)) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable)))
+}
+
+/// Connection sending events to a receiver.
+#[derive(Debug)]
+pub struct EventStream {
+    target: Target,
+    connection: Option<Connection>,
+    retry_interval: Duration,
+    retry_at: Option<Instant>,
+}
+
+impl EventStream {
+    /// Connects to `target`, which must be listening.
+    pub fn connect(target: Target) -> eyre::Result<Self> {
+        let connection =
+            Connection::open(&target).wrap_err_with(|| format!("failed to connect to {target}"))?;
+        tracing::info!(%target, "streaming events");
+        Ok(Self {
+            target,
+            connection: Some(connection),
+            retry_interval: MIN_RETRY_INTERVAL,
+            retry_at: None,
+        })
+    }
+
+    /// Sends the event of a span, reconnecting to the receiver if it has gone.
+    ///
+    /// Failures are logged and the event is dropped, since the analysis goes on without the receiver.
+    /// While disconnected, events are dropped without reconnecting until the retry interval passes.
+    pub fn send(&mut self, record: &json::Record<'_>) -> io::Result<()> {
+        let event = serde_json::to_string(record)?;
+        if self.connection.is_none() {
+            if self
+                .retry_at
+                .is_some_and(|retry_at| Instant::now() < retry_at)
+            {
+                return Ok(());
+            }
+            match Connection::open(&self.target) {
+                Ok(connection) => {
+                    tracing::info!(target = %self.target, "reconnected to the event receiver");
+                    self.connection = Some(connection);
+                    self.retry_interval = MIN_RETRY_INTERVAL;
+                    self.retry_at = None;
+                }
+                Err(_) => {
+                    self.retry_interval = (self.retry_interval * 2).min(MAX_RETRY_INTERVAL);
+                    self.schedule_retry();
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(connection) = &mut self.connection {
+            if let Err(err) = connection.send(event) {
+                tracing::warn!(target = %self.target, "event receiver has gone, dropping events: {err:#}");
+                self.connection = None;
+                self.schedule_retry();
+            }
+        }
+        Ok(())
+    }
+
+    fn schedule_retry(&mut self) {
+        self.retry_at = Some(Instant::now() + self.retry_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use elden_analyzer_kernel::types::time::{FramePosition, Timestamp};
+    use num_rational::Ratio;
+
+    use crate::accumulate::text::AccumResult;
+
+    use super::*;
+
+    fn result() -> AccumResult {
+        let pos = |idx: i64| FramePosition::new(idx as usize, Timestamp::new(Ratio::new(idx, 10)));
+        AccumResult {
+            name: "main_item".to_owned(),
+            start: pos(10),
+            end: pos(25),
+            text: "聖杯瓶".to_owned(),
+            igt: None,
+            lines: vec![],
+            rarity: None,
+        }
+    }
+
+    #[test]
+    fn parse_target() {
+        assert_eq!(
+            "ws://127.0.0.1:8080/events".parse::<Target>().unwrap(),
+            Target::WebSocket("ws://127.0.0.1:8080/events".to_owned())
+        );
+        assert!(matches!(
+            "wss://example.com".parse::<Target>(),
+            Err(ParseTargetError::SecureWebSocket)
+        ));
+        assert!(matches!(
+            "ws://".parse::<Target>(),
+            Err(ParseTargetError::InvalidUrl(_))
+        ));
+        #[cfg(unix)]
+        assert_eq!(
+            "/tmp/elden.sock".parse::<Target>().unwrap(),
+            Target::Unix(PathBuf::from("/tmp/elden.sock"))
+        );
+    }
+
+    #[test]
+    fn send_to_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/events", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            socket.read().unwrap().into_text().unwrap().to_string()
+        });
+
+        let mut stream = EventStream::connect(url.parse().unwrap()).unwrap();
+        let result = result();
+        stream.send(&json::Record::new(&result, None)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(value["name"], "main_item");
+        assert_eq!(value["text"], "聖杯瓶");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn send_to_unix_socket() {
+        use std::{
+            io::{BufRead as _, BufReader},
+            os::unix::net::UnixListener,
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("elden-analyzer-events-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut stream = EventStream::connect(Target::Unix(path.clone())).unwrap();
+        let (receiver, _) = listener.accept().unwrap();
+
+        let result = result();
+        stream.send(&json::Record::new(&result, None)).unwrap();
+        let mut line = String::new();
+        BufReader::new(receiver).read_line(&mut line).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["text"], "聖杯瓶");
+
+        // Events are dropped once the receiver has gone, without failing
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+        for _ in 0..3 {
+            stream.send(&json::Record::new(&result, None)).unwrap();
+        }
+        assert!(stream.connection.is_none());
+        assert!(stream.retry_at.is_some());
+
+        // Reconnection waits for the retry interval
+        let listener = UnixListener::bind(&path).unwrap();
+        stream.send(&json::Record::new(&result, None)).unwrap();
+        assert!(stream.connection.is_none());
+        stream.retry_at = Some(Instant::now());
+        stream.send(&json::Record::new(&result, None)).unwrap();
+        assert!(stream.connection.is_some());
+        assert_eq!(stream.retry_interval, MIN_RETRY_INTERVAL);
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
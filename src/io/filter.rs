@@ -0,0 +1,251 @@
+//! Filters of spans in outputs of `analyze`, used by the `filter` subcommand.
+//!
+//! Both span files and JSON outputs are filtered line by line, and lines of kept spans are written unchanged,
+//! so that filtered files can be read as the original ones.
+//! Comment lines of span files are kept.
+
+use std::io::{BufRead, Write};
+
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer_kernel::types::time::{Timestamp, TimestampRange};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    components::ComponentFilter,
+    items::{self, ItemCategory},
+};
+
+use super::spans::Span;
+
+/// Conditions of spans to keep, all of which must be satisfied.
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// Components the span is of, or empty to keep spans of all components
+    pub components: ComponentFilter,
+    /// Minimum confidence of the least confident line, between `0` and `1`.
+    /// Spans without confidences, including all spans of span files, are not kept.
+    pub min_confidence: Option<f64>,
+    /// Times the span overlaps
+    pub time: Option<TimestampRange>,
+    /// Categories of items, any of which the text is, or empty to keep spans of any texts
+    pub categories: Vec<ItemCategory>,
+    /// Pattern found in the text
+    pub text: Option<Regex>,
+    /// Drop spans whose texts are not reliably recognized
+    pub exclude_possible: bool,
+}
+
+/// Properties of a span tested by filters.
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub name: &'a str,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub text: &'a str,
+    /// Confidence of the least confident line, if recognized
+    pub confidence: Option<f64>,
+}
+
+impl<'a> Entry<'a> {
+    fn of_span(span: &'a Span) -> Self {
+        Self {
+            name: &span.name,
+            start: span.start,
+            end: span.end,
+            text: &span.text,
+            confidence: None,
+        }
+    }
+}
+
+/// Number of spans read and kept by a filter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilterStats {
+    pub read: usize,
+    pub kept: usize,
+}
+
+/// A line of the JSON output, with the fields tested by filters.
+#[derive(Debug, Deserialize)]
+struct JsonSpan {
+    name: String,
+    start: String,
+    end: String,
+    text: String,
+    #[serde(default)]
+    lines: Vec<JsonLine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonLine {
+    confidence: Option<f64>,
+}
+
+impl Filter {
+    /// Returns `true` if `entry` satisfies all conditions.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::{
+    ///     components::ComponentFilter,
+    ///     io::filter::{Entry, Filter},
+    /// };
+    /// use regex::Regex;
+    ///
+    /// let filter = Filter {
+    ///     components: ComponentFilter::new(["side_item"]),
+    ///     text: Some(Regex::new("聖杯瓶").unwrap()),
+    ///     ..Filter::default()
+    /// };
+    /// let entry = Entry {
+    ///     name: "side_item2",
+    ///     start: "00:12:01.200".parse().unwrap(),
+    ///     end: "00:12:03.467".parse().unwrap(),
+    ///     text: "緋雫の聖杯瓶 ×3",
+    ///     confidence: None,
+    /// };
+    /// assert!(filter.matches(&entry));
+    /// ```
+    pub fn matches(&self, entry: &Entry<'_>) -> bool {
+        if !self.components.matches(entry.name) {
+            return false;
+        }
+        if let Some(min) = self.min_confidence {
+            if entry.confidence.map_or(true, |conf| conf < min) {
+                return false;
+            }
+        }
+        if let Some(time) = self.time {
+            let overlaps = match time {
+                TimestampRange::Full => true,
+                TimestampRange::Single(ts) => entry.start <= ts && ts < entry.end,
+                TimestampRange::Range(start, end) => entry.start < end && start < entry.end,
+                TimestampRange::RangeFrom(start) => start < entry.end,
+                TimestampRange::RangeTo(end) => entry.start < end,
+            };
+            if !overlaps {
+                return false;
+            }
+        }
+        if !self.categories.is_empty()
+            && !items::category(entry.text).is_some_and(|c| self.categories.contains(&c))
+        {
+            return false;
+        }
+        if self
+            .text
+            .as_ref()
+            .is_some_and(|re| !re.is_match(entry.text))
+        {
+            return false;
+        }
+        if self.exclude_possible && entry.text.contains("??") {
+            return false;
+        }
+        true
+    }
+
+    /// Writes the lines of the span file read from `reader` whose spans are kept, and the comment lines.
+    pub fn filter_spans(
+        &self,
+        reader: impl BufRead,
+        writer: &mut impl Write,
+    ) -> eyre::Result<FilterStats> {
+        let mut stats = FilterStats::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if !super::is_comment(&line) && !line.trim().is_empty() {
+                let span = line
+                    .parse::<Span>()
+                    .wrap_err_with(|| format!("invalid span at line {}", i + 1))?;
+                stats.read += 1;
+                if !self.matches(&Entry::of_span(&span)) {
+                    continue;
+                }
+                stats.kept += 1;
+            }
+            writeln!(writer, "{line}")?;
+        }
+        Ok(stats)
+    }
+
+    /// Writes the lines of the JSON output read from `reader` whose spans are kept.
+    pub fn filter_json(
+        &self,
+        reader: impl BufRead,
+        writer: &mut impl Write,
+    ) -> eyre::Result<FilterStats> {
+        let mut stats = FilterStats::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let span = serde_json::from_str::<JsonSpan>(&line)
+                .wrap_err_with(|| format!("invalid JSON at line {}", i + 1))?;
+            let entry = Entry {
+                name: &span.name,
+                start: span.start.parse()?,
+                end: span.end.parse()?,
+                text: &span.text,
+                confidence: (span.lines.iter())
+                    .filter_map(|line| line.confidence)
+                    .min_by(f64::total_cmp),
+            };
+            stats.read += 1;
+            if self.matches(&entry) {
+                stats.kept += 1;
+                writeln!(writer, "{line}")?;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_json_by_confidence_and_time() {
+        let input = concat!(
+            r#"{"name":"main_item","start":"00:00:01.000","end":"00:00:02.000","text":"聖杯瓶","lines":[{"text":"聖杯瓶","confidence":0.9,"alternatives":[]}]}"#,
+            "\n",
+            r#"{"name":"main_item","start":"00:00:05.000","end":"00:00:06.000","text":"??剣","lines":[{"text":"??剣","confidence":0.4,"alternatives":[]}]}"#,
+            "\n",
+            r#"{"name":"boss_bar","start":"00:01:00.000","end":"00:01:30.000","text":"マルギット","lines":[{"text":"マルギット","confidence":0.8,"alternatives":[]}]}"#,
+            "\n",
+        );
+        let filter = Filter {
+            min_confidence: Some(0.5),
+            time: Some("00:00:00-00:00:10".parse().unwrap()),
+            ..Filter::default()
+        };
+        let mut output = vec![];
+        let stats = filter.filter_json(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(stats, FilterStats { read: 3, kept: 1 });
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            input.lines().next().unwrap().to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn filter_spans_keeps_comments() {
+        let input = "# elden-analyzer 0.1.0\n\
+                     00:00:01.000-00:00:02.000 聖杯瓶 (main_item)\n\
+                     00:00:05.000-00:00:06.000 ??剣 (side_item1)\n";
+        let filter = Filter {
+            exclude_possible: true,
+            ..Filter::default()
+        };
+        let mut output = vec![];
+        let stats = filter.filter_spans(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(stats, FilterStats { read: 2, kept: 1 });
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# elden-analyzer 0.1.0\n00:00:01.000-00:00:02.000 聖杯瓶 (main_item)\n"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Index of an analyzed video, written next to it by `analyze` so that later queries are answered without decoding.
+//!
+//! The index of `run.mp4` is `run.mp4.elden-index`, a text file of the video it is built from, the spans found,
+//! and a curve of detections of each component, with a keyword at the start of each line:
+//!
+//! ```text
+//! # elden-analyzer 0.1.0 (commit 0123456789ab, built 2024-06-01, features: ocr-tesseract)
+//! version 1
+//! source 0123456789abcdef
+//! duration 03:00:00.000
+//! step 1
+//! curve main_item 000fff80000...
+//! span 00:00:01.000-00:00:03.000 聖杯瓶 (main_item) #0123456789abcdef-main_item-30
+//! ```
+//!
+//! Each digit of a curve is the share of analyzed frames in a second where the component is found, from `0` to `f`.
+//! Indexes are used only while the video has the same [`SourceId`] and the index has the current version,
+//! and are rebuilt by analyzing the whole video again.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use elden_analyzer_kernel::types::time::{FramePosition, Timestamp};
+use num_rational::Ratio;
+
+use crate::{components::ComponentContainer, util};
+
+use super::{event_id::SourceId, spans::Span};
+
+/// Version of the format, incremented when indexes of older versions cannot be read
+pub const VERSION: u32 = 1;
+
+/// Extension appended to the name of the video
+pub const EXTENSION: &str = "elden-index";
+
+/// Highest level of curves, where the component is found in all analyzed frames of the second
+pub const MAX_LEVEL: u8 = 15;
+
+/// Spans and detections of a whole video.
+#[derive(Debug, Clone)]
+pub struct AnalysisIndex {
+    pub source: SourceId,
+    /// Length of the analyzed video
+    pub duration: Timestamp,
+    /// Frames were analyzed every `step` frames
+    pub step: usize,
+    /// Curves of detections of components, in the order of columns of the analysis
+    pub curves: Vec<Curve>,
+    /// Spans of all components in the order they are found
+    pub spans: Vec<Span>,
+}
+
+/// Detections of a component in each second of a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Curve {
+    pub name: String,
+    /// Share of analyzed frames where the component is found in each second, from `0` to [`MAX_LEVEL`]
+    pub levels: Vec<u8>,
+}
+
+impl Curve {
+    /// Returns the level of the second containing `ts`, or 0 after the end.
+    pub fn level_at(&self, ts: Timestamp) -> u8 {
+        let sec = ts.as_ratio().floor().to_integer();
+        usize::try_from(sec)
+            .ok()
+            .and_then(|sec| self.levels.get(sec))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl AnalysisIndex {
+    /// Returns the path of the index of `video`.
+    pub fn path_of(video: &Path) -> PathBuf {
+        let mut name = video.file_name().unwrap_or_default().to_owned();
+        name.push(".");
+        name.push(EXTENSION);
+        video.with_file_name(name)
+    }
+
+    /// Reads the index of `video` if it is built from the current content of the video.
+    ///
+    /// Returns `None` if the index is missing, stale, or of another version.
+    pub fn load_for(video: &Path) -> eyre::Result<Option<Self>> {
+        let path = Self::path_of(video);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("failed to open {}", path.display()))
+            }
+        };
+        let Some(index) = Self::read(BufReader::new(file))
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?
+        else {
+            tracing::debug!(path = %path.display(), "index of another version is ignored");
+            return Ok(None);
+        };
+        if index.source != SourceId::of_file(video)? {
+            tracing::debug!(path = %path.display(), "index of modified video is ignored");
+            return Ok(None);
+        }
+        Ok(Some(index))
+    }
+
+    /// Writes the index of `video` next to it.
+    ///
+    /// The index is written to a temporary file renamed at the end, so that an interrupted write leaves no index.
+    pub fn save_for(&self, video: &Path) -> eyre::Result<()> {
+        let path = Self::path_of(video);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        (|| -> eyre::Result<()> {
+            let mut writer = BufWriter::new(util::create_file(&tmp)?);
+            self.write(&mut writer)?;
+            writer.into_inner().map_err(|err| err.into_error())?;
+            fs::rename(&tmp, &path)?;
+            Ok(())
+        })()
+        .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Writes the index, starting with the comment line describing the build.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        super::write_header(writer, None)?;
+        writeln!(writer, "version {VERSION}")?;
+        writeln!(writer, "source {}", self.source)?;
+        writeln!(writer, "duration {}", self.duration)?;
+        writeln!(writer, "step {}", self.step)?;
+        for curve in &self.curves {
+            let levels = curve
+                .levels
+                .iter()
+                .map(|level| char::from_digit(u32::from(*level), 16).unwrap_or('f'))
+                .collect::<String>();
+            writeln!(writer, "curve {} {levels}", curve.name)?;
+        }
+        for span in &self.spans {
+            writeln!(writer, "span {span}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads an index, returning `None` if it is of another version.
+    pub fn read(reader: impl BufRead) -> eyre::Result<Option<Self>> {
+        let mut version = None;
+        let mut source = None;
+        let mut duration = None;
+        let mut step = None;
+        let mut curves = vec![];
+        let mut spans = vec![];
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || super::is_comment(&line) {
+                continue;
+            }
+            let result = (|| -> eyre::Result<()> {
+                let (key, value) = line.split_once(' ').unwrap_or((&line, ""));
+                match key {
+                    "version" => version = Some(value.parse::<u32>()?),
+                    "source" => source = Some(value.parse::<SourceId>()?),
+                    "duration" => duration = Some(value.parse::<Timestamp>()?),
+                    "step" => step = Some(value.parse::<usize>()?),
+                    "curve" => {
+                        let (name, levels) = value.split_once(' ').unwrap_or((value, ""));
+                        let levels = levels
+                            .chars()
+                            .map(|c| {
+                                c.to_digit(16)
+                                    .map(|level| level as u8)
+                                    .ok_or_else(|| eyre!("invalid level `{c}`"))
+                            })
+                            .collect::<eyre::Result<_>>()?;
+                        curves.push(Curve {
+                            name: name.to_owned(),
+                            levels,
+                        });
+                    }
+                    "span" => spans.push(value.parse::<Span>()?),
+                    _ => eyre::bail!("unknown keyword `{key}`"),
+                }
+                Ok(())
+            })();
+            result.wrap_err_with(|| format!("invalid index at line {}", lineno + 1))?;
+            // Later lines may be of a format this version cannot read
+            if version.is_some_and(|version| version != VERSION) {
+                return Ok(None);
+            }
+        }
+        let missing = |key: &str| eyre!("missing `{key}` in index");
+        if version.is_none() {
+            return Err(missing("version"));
+        }
+        Ok(Some(Self {
+            source: source.ok_or_else(|| missing("source"))?,
+            duration: duration.ok_or_else(|| missing("duration"))?,
+            step: step.ok_or_else(|| missing("step"))?,
+            curves,
+            spans,
+        }))
+    }
+}
+
+/// Builds the index of a video from the results of its analysis.
+#[derive(Debug)]
+pub struct IndexBuilder {
+    source: SourceId,
+    step: usize,
+    names: Vec<String>,
+    /// Numbers of analyzed frames and frames where each component is found in each second
+    counts: Vec<(u32, Vec<u32>)>,
+    spans: Vec<Span>,
+}
+
+impl IndexBuilder {
+    pub fn new(source: SourceId, names: &ComponentContainer<String>, step: usize) -> Self {
+        Self {
+            source,
+            step,
+            names: names.iter().cloned().collect(),
+            counts: vec![],
+            spans: vec![],
+        }
+    }
+
+    /// Receives an analyzed frame with whether each component is found in it.
+    pub fn receive_frame(&mut self, pos: FramePosition, found: &ComponentContainer<bool>) {
+        let Ok(sec) = usize::try_from(pos.timestamp().as_ratio().floor().to_integer()) else {
+            return;
+        };
+        if self.counts.len() <= sec {
+            let empty = (0, vec![0; self.names.len()]);
+            self.counts.resize(sec + 1, empty);
+        }
+        let (frames, counts) = &mut self.counts[sec];
+        *frames += 1;
+        for (count, found) in counts.iter_mut().zip(found.iter()) {
+            *count += u32::from(*found);
+        }
+    }
+
+    pub fn receive_span(&mut self, span: &Span) {
+        self.spans.push(span.clone());
+    }
+
+    /// Returns the index of the video analyzed until `end`.
+    pub fn finish(self, end: FramePosition) -> AnalysisIndex {
+        let curves = self
+            .names
+            .into_iter()
+            .enumerate()
+            .map(|(column, name)| {
+                let levels = self
+                    .counts
+                    .iter()
+                    .map(|(frames, counts)| {
+                        let share = Ratio::new(counts[column], (*frames).max(1));
+                        (share * u32::from(MAX_LEVEL)).round().to_integer() as u8
+                    })
+                    .collect();
+                Curve { name, levels }
+            })
+            .collect();
+        AnalysisIndex {
+            source: self.source,
+            duration: end.timestamp(),
+            step: self.step,
+            curves,
+            spans: self.spans,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elden_analyzer_kernel::types::rect::Rect;
+
+    use crate::{components::Components, config::Config};
+
+    use super::*;
+
+    #[test]
+    fn build_and_read() {
+        let components =
+            Components::new(Rect::at(0, 0).of_size(1920, 1080), &Config::default()).unwrap();
+        let names = components.each_ref().map(|c| c.name().to_owned());
+        let mut builder = IndexBuilder::new(SourceId::of_name("run"), &names, 1);
+        for idx in 0..60 {
+            let pos = FramePosition::new(idx, Timestamp::new(Ratio::new(idx as i64, 30)));
+            let found = names
+                .iter()
+                .map(|name| name == "main_item" && (15..45).contains(&idx))
+                .collect();
+            builder.receive_frame(pos, &found);
+        }
+        let span = "00:00:00.500-00:00:01.500 聖杯瓶 (main_item)"
+            .parse()
+            .unwrap();
+        builder.receive_span(&span);
+        let end = FramePosition::new(60, Timestamp::new(Ratio::from_integer(2)));
+        let index = builder.finish(end);
+
+        let main_item = &index.curves[0];
+        assert_eq!(main_item.name, "main_item");
+        assert_eq!(main_item.levels, [8, 8]);
+        assert_eq!(main_item.level_at(Timestamp::new(Ratio::new(3, 2))), 8);
+        assert_eq!(
+            main_item.level_at(Timestamp::new(Ratio::from_integer(5))),
+            0
+        );
+        assert!(index.curves[1..].iter().all(|curve| curve.levels == [0, 0]));
+
+        let mut buf = vec![];
+        index.write(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("\ncurve main_item 88\n"));
+        let read = AnalysisIndex::read(text.as_bytes()).unwrap().unwrap();
+        assert_eq!(read.source, index.source);
+        assert_eq!(read.duration, index.duration);
+        assert_eq!(read.curves, index.curves);
+        assert_eq!(read.spans[0].to_string(), span.to_string());
+
+        // Indexes of other versions are ignored
+        let text = text.replace("version 1", "version 999");
+        assert!(AnalysisIndex::read(text.as_bytes()).unwrap().is_none());
+    }
+}
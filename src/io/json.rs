@@ -0,0 +1,143 @@
+//! Span records written by `analyze --output-json`.
+//!
+//! Each line is a JSON object (NDJSON) describing a span, with the texts of each line of the component,
+//! their confidences and alternative candidates:
+//!
+//! ```text
+//! {"name":"side_item0","start":"00:12:01.200","end":"00:12:03.467","start_frame":21636,"end_frame":21704,"text":"緋雫の聖杯瓶 ×3","id":"…","possible":false,"category":"tools","item_id":1,"rarity":"common","lines":[{"text":"緋雫の聖杯瓶","confidence":0.93,"alternatives":[{"text":"緋雫の聖杯瓶","share":1.0}]},…]}
+//! ```
+//!
+//! Frames of a span are from `start_frame` up to but not including `end_frame`.
+//! Unlike other outputs, the file has no comment lines, so that it can be read by any JSON reader.
+
+use serde::Serialize;
+
+use crate::{
+    accumulate::text::{AccumResult, LineText},
+    items::{self, ItemCategory, ItemId, Rarity},
+};
+
+use super::event_id::EventId;
+
+/// A line of the JSON output.
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    pub name: &'a str,
+    pub start: String,
+    pub end: String,
+    pub start_frame: usize,
+    pub end_frame: usize,
+    pub text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub igt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The text is not reliably recognized
+    pub possible: bool,
+    /// Category of the item, if the text is a known item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<ItemCategory>,
+    /// ID of the item in the item database, if the text is a known item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<ItemId>,
+    /// Rarity of the item inferred from the color of its name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+    pub lines: Vec<LineRecord<'a>>,
+}
+
+/// Text of a line of a component.
+#[derive(Debug, Serialize)]
+pub struct LineRecord<'a> {
+    pub text: &'a str,
+    /// Mean confidence of the recognitions, between `0` and `1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Candidates of the text, most likely first
+    pub alternatives: Vec<Alternative<'a>>,
+}
+
+/// Candidate of the text of a line.
+#[derive(Debug, Serialize)]
+pub struct Alternative<'a> {
+    pub text: &'a str,
+    /// Share in the confidences of all candidates, between `0` and `1`
+    pub share: f64,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(result: &'a AccumResult, id: Option<&EventId>) -> Self {
+        Self {
+            name: &result.name,
+            start: result.start.timestamp().to_string(),
+            end: result.end.timestamp().to_string(),
+            start_frame: result.start.index(),
+            end_frame: result.end.index(),
+            text: &result.text,
+            igt: result.igt.map(|igt| igt.to_string()),
+            id: id.map(|id| id.to_string()),
+            possible: result.text.contains("??"),
+            category: items::category(&result.text),
+            item_id: items::item_id(&result.text),
+            rarity: result.rarity,
+            lines: result.lines.iter().map(LineRecord::new).collect(),
+        }
+    }
+}
+
+impl<'a> LineRecord<'a> {
+    fn new(line: &'a LineText) -> Self {
+        Self {
+            text: &line.text,
+            confidence: line.confidence.map(|conf| conf.to_f64()),
+            alternatives: line
+                .candidates
+                .iter()
+                .map(|(text, share)| Alternative {
+                    text,
+                    share: share.to_f64(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elden_analyzer_kernel::types::time::{FramePosition, Timestamp};
+    use num_rational::Ratio;
+
+    use crate::operator::Confidence;
+
+    use super::*;
+
+    #[test]
+    fn record_of_possible_span() {
+        let pos = |idx: i64| FramePosition::new(idx as usize, Timestamp::new(Ratio::new(idx, 10)));
+        let result = AccumResult {
+            name: "main_item".to_owned(),
+            start: pos(10),
+            end: pos(25),
+            text: "{??foo|??bar}".to_owned(),
+            igt: None,
+            lines: vec![LineText {
+                text: "{??foo|??bar}".to_owned(),
+                confidence: Some(Confidence::new(60)),
+                candidates: vec![
+                    ("??foo".to_owned(), Confidence::new(75)),
+                    ("??bar".to_owned(), Confidence::new(25)),
+                ],
+            }],
+            rarity: Some(Rarity::Rare),
+        };
+        let json = serde_json::to_string(&Record::new(&result, None)).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"name":"main_item","start":"00:00:01.000","end":"00:00:02.500","start_frame":10,"end_frame":25,"#,
+                r#""text":"{??foo|??bar}","possible":true,"rarity":"rare","lines":[{"text":"{??foo|??bar}","confidence":0.6,"#,
+                r#""alternatives":[{"text":"??foo","share":0.75},{"text":"??bar","share":0.25}]}]}"#,
+            )
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Merging of outputs of `analyze` for parts of a run into a single timeline, used by the `merge` subcommand.
+//!
+//! A run recorded as several videos, such as a VOD split into parts, is analyzed part by part.
+//! Spans of each part are shifted by the offset of the part, the start of its video in the merged timeline.
+//! Frame numbers of JSON outputs and event IDs are kept, since they refer to the video of the part.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use elden_analyzer_video::metadata::Metadata;
+use num_rational::Ratio;
+use serde::{Deserialize, Deserializer};
+
+use super::spans::{self, Span};
+
+/// Tag of containers recording when the video is created
+const CREATION_TIME_TAG: &str = "creation_time";
+
+/// Parts of a run and their offsets, read by `merge --manifest`.
+///
+/// # Examples
+/// ```toml
+/// [[parts]]
+/// output = "part1.txt"
+/// offset = "00:00:00"
+///
+/// # The offset is taken from the creation time of the video
+/// [[parts]]
+/// output = "part2.txt"
+/// video = "part2.mp4"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub parts: Vec<ManifestPart>,
+}
+
+/// Part of a run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestPart {
+    /// Span file or JSON output of the part
+    pub output: PathBuf,
+    /// Start of the part in the merged timeline
+    #[serde(default, deserialize_with = "opt_timestamp")]
+    pub offset: Option<Timestamp>,
+    /// Video of the part, whose creation time gives the offset unless `offset` is given
+    #[serde(default)]
+    pub video: Option<PathBuf>,
+}
+
+impl Manifest {
+    /// Reads a manifest file. Relative paths in the file are resolved from the directory of the file.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read manifest file: {}", path.display()))?;
+        let mut manifest = toml::from_str::<Self>(&text)
+            .wrap_err_with(|| format!("failed to parse manifest file: {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for part in &mut manifest.parts {
+            part.output = base.join(&part.output);
+            if let Some(video) = &mut part.video {
+                *video = base.join(&*video);
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// Returns the creation time recorded in the container of a video, if any.
+pub fn creation_time(metadata: &Metadata) -> Option<DateTime<Utc>> {
+    let (_, value) = metadata
+        .tags
+        .iter()
+        .find(|(key, _)| key == CREATION_TIME_TAG)?;
+    let time = DateTime::parse_from_rfc3339(value).ok()?;
+    Some(time.with_timezone(&Utc))
+}
+
+/// Returns the offsets of parts created at `times`, relative to the earliest one.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::merge;
+///
+/// let times = ["2024-03-01T12:00:00Z", "2024-03-01T13:02:03.500Z"].map(|t| t.parse().unwrap());
+/// let offsets = merge::offsets_from_creation_times(&times);
+/// assert_eq!(offsets[1].to_string(), "01:02:03.500");
+/// ```
+pub fn offsets_from_creation_times(times: &[DateTime<Utc>]) -> Vec<Duration> {
+    let Some(earliest) = times.iter().min() else {
+        return vec![];
+    };
+    times
+        .iter()
+        .map(|time| Duration::new(Ratio::new((*time - *earliest).num_milliseconds(), 1000)))
+        .collect()
+}
+
+/// Merges spans of parts shifted by their offsets.
+///
+/// Parts are ordered by their offsets, and a span ending at the end of a part is joined with the span continuing
+/// at the start of the next part (see [`spans::join_chunks`]). Returned spans are sorted by the start.
+pub fn merge_spans(parts: impl IntoIterator<Item = (Vec<Span>, Duration)>) -> Vec<Span> {
+    let mut parts = parts.into_iter().collect::<Vec<_>>();
+    parts.sort_by_key(|(_, offset)| *offset);
+    let chunks = parts.into_iter().map(|(spans, offset)| {
+        spans
+            .into_iter()
+            .map(|span| Span {
+                start: span.start + offset,
+                end: span.end + offset,
+                ..span
+            })
+            .collect()
+    });
+    let mut merged = spans::join_chunks(chunks);
+    merged.sort_by(|a, b| (a.start, a.end, &a.name).cmp(&(b.start, b.end, &b.name)));
+    merged
+}
+
+/// A line of the JSON output, with the fields shifted by offsets.
+#[derive(Debug, Deserialize)]
+struct JsonSpan {
+    start: String,
+    end: String,
+}
+
+/// Merges lines of JSON outputs of parts shifted by their offsets.
+///
+/// Only `start` and `end` of each line are rewritten, and other fields are kept unchanged.
+/// Returned lines are sorted by the start.
+pub fn merge_json<'a>(
+    parts: impl IntoIterator<Item = (&'a str, Duration)>,
+) -> eyre::Result<Vec<String>> {
+    let mut merged = vec![];
+    for (text, offset) in parts {
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let span = serde_json::from_str::<JsonSpan>(line)
+                .wrap_err_with(|| format!("invalid JSON at line {}", i + 1))?;
+            let start = span.start.parse::<Timestamp>()? + offset;
+            let end = span.end.parse::<Timestamp>()? + offset;
+            // Quotes in strings are escaped, so the patterns match only the keys
+            let line = line
+                .replacen(
+                    &format!(r#""start":"{}""#, span.start),
+                    &format!(r#""start":"{start}""#),
+                    1,
+                )
+                .replacen(
+                    &format!(r#""end":"{}""#, span.end),
+                    &format!(r#""end":"{end}""#),
+                    1,
+                );
+            merged.push((start, end, line));
+        }
+    }
+    merged.sort_by_key(|(start, end, _)| (*start, *end));
+    Ok(merged.into_iter().map(|(_, _, line)| line).collect())
+}
+
+fn opt_timestamp<'de, D>(deserializer: D) -> Result<Option<Timestamp>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(s) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    s.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(s: &str) -> Duration {
+        s.parse::<Timestamp>().unwrap() - Timestamp::default()
+    }
+
+    #[test]
+    fn merge_spans_across_parts() {
+        let part1 = [
+            "00:00:01.000-00:00:02.000 聖杯瓶 (main_item)",
+            "00:59:50.000-01:00:00.000 ??マルギ (boss_bar)",
+        ]
+        .map(|s| s.parse::<Span>().unwrap());
+        let part2 = ["00:00:00.000-00:00:30.000 忌み鬼、マルギット (boss_bar)"]
+            .map(|s| s.parse::<Span>().unwrap());
+        let merged = merge_spans([
+            (part2.to_vec(), offset("01:00:00")),
+            (part1.to_vec(), offset("00:00:00")),
+        ]);
+        let merged = merged.iter().map(Span::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            merged,
+            [
+                "00:00:01.000-00:00:02.000 聖杯瓶 (main_item)",
+                "00:59:50.000-01:00:30.000 忌み鬼、マルギット (boss_bar)",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_json_shifts_times() {
+        let part1 = r#"{"name":"main_item","start":"00:00:05.000","end":"00:00:06.000","start_frame":150,"end_frame":180,"text":"聖杯瓶"}"#;
+        let part2 = r#"{"name":"main_item","start":"00:00:01.000","end":"00:00:02.000","start_frame":30,"end_frame":60,"text":"\"start\":\"00:00:01.000\""}"#;
+        let merged = merge_json([(part2, offset("00:10:00")), (part1, offset("0"))]).unwrap();
+        assert_eq!(
+            merged,
+            [
+                part1.to_owned(),
+                r#"{"name":"main_item","start":"00:10:01.000","end":"00:10:02.000","start_frame":30,"end_frame":60,"text":"\"start\":\"00:00:01.000\""}"#.to_owned(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Readers and writers of the files output by `analyze`.
+//!
+//! Output files start with comment lines describing the build and optionally the environment (see [`write_header`]),
+//! and readers skip lines starting with `#`.
+
+use std::io::{self, Write};
+
+use crate::{build_info, environment::Environment};
+
+pub mod buckets;
+pub mod chapters;
+pub mod corrections;
+pub mod event_id;
+pub mod event_log;
+pub mod event_stream;
+pub mod filter;
+pub mod index;
+pub mod json;
+pub mod merge;
+pub mod spans;
+pub mod subtitles;
+pub mod timeline;
+
+/// Writes the comment lines describing the build and `environment`,
+/// so that outputs can be matched with exact builds and libraries.
+pub fn write_header(writer: &mut impl Write, environment: Option<&Environment>) -> io::Result<()> {
+    writeln!(writer, "# {}", build_info::stamp())?;
+    for (name, value) in environment.into_iter().flat_map(Environment::entries) {
+        writeln!(writer, "# {name}: {value}")?;
+    }
+    Ok(())
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#')
+}
@@ -0,0 +1,397 @@
+//! Span file output by `analyze --output-span`.
+//!
+//! Each line describes a span in which a component is shown:
+//!
+//! ```text
+//! START-END TEXT (NAME)[ @IGT][ #ID]
+//! ```
+//!
+//! `TEXT` starts with `??` if it is not reliably recognized, `IGT` is the in-game time at the start of the span,
+//! and `ID` is the [event ID](super::event_id) of the span.
+//!
+//! Intervals whose frames are not analyzed are written as spans named [`SKIPPED`], with the reason as the text
+//! ([`DROPPED`]), so that they can be distinguished from intervals without events.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::FromStr,
+};
+
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp, TimestampParseError};
+use num_rational::Ratio;
+use num_traits::Signed as _;
+
+use crate::components;
+
+use super::event_id::EventId;
+
+/// Name of spans of side items tracked across rows by [`track_side_items`]
+pub const TRACKED_SIDE_ITEM: &str = "side_item";
+
+/// Maximum gap between spans of an item moving across rows of side items, in seconds
+pub const PICKUP_WINDOW: Ratio<i64> = Ratio::new_raw(1, 2);
+
+/// Name of spans of intervals whose frames are not analyzed
+pub const SKIPPED: &str = "skipped";
+
+/// Text of [`SKIPPED`] spans where frames are dropped to keep up with a live source
+pub const DROPPED: &str = "dropped";
+
+/// A span in which a component is shown.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::spans::Span;
+///
+/// let span = "00:00:01.000-00:00:03.500 聖杯瓶 (main_item) @00:12:34.000"
+///     .parse::<Span>()
+///     .unwrap();
+/// assert_eq!(span.name, "main_item");
+/// assert_eq!(span.text, "聖杯瓶");
+/// assert!(!span.is_possible());
+/// assert_eq!(span.igt.unwrap().as_msec(), 754_000);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub name: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub text: String,
+    /// In-game time at the start of the span
+    pub igt: Option<Duration>,
+    pub id: Option<EventId>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpanParseError {
+    #[error("missing span text")]
+    MissingText,
+    #[error("missing component name")]
+    MissingName,
+    #[error("invalid timestamp range")]
+    InvalidRange,
+    #[error(transparent)]
+    Timestamp(#[from] TimestampParseError),
+}
+
+impl Span {
+    /// Returns `true` if the text of the span is not reliably recognized.
+    pub fn is_possible(&self) -> bool {
+        self.text.contains("??")
+    }
+
+    /// Returns `true` if the span is an interval whose frames are not analyzed.
+    pub fn is_skipped(&self) -> bool {
+        self.name == SKIPPED
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            name,
+            start,
+            end,
+            text,
+            igt,
+            id,
+        } = self;
+        write!(f, "{start}-{end} {text} ({name})")?;
+        if let Some(igt) = igt {
+            write!(f, " @{igt}")?;
+        }
+        if let Some(id) = id {
+            write!(f, " #{id}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Span {
+    type Err = SpanParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, rest) = s.split_once(' ').ok_or(SpanParseError::MissingText)?;
+        let (rest, id) = match rest
+            .rsplit_once(" #")
+            .and_then(|(rest, id)| Some((rest, id.parse::<EventId>().ok()?)))
+        {
+            Some((rest, id)) => (rest, Some(id)),
+            None => (rest, None),
+        };
+        let (rest, igt) = match rest.rsplit_once(" @") {
+            Some((rest, igt)) if igt.parse::<Timestamp>().is_ok() => {
+                let igt = igt.parse::<Timestamp>()?;
+                (rest, Some(Duration::new(igt.as_ratio())))
+            }
+            _ => (rest, None),
+        };
+        let (start, end) = range.split_once('-').ok_or(SpanParseError::InvalidRange)?;
+        let (text, name) = rest
+            .rsplit_once(" (")
+            .and_then(|(text, name)| Some((text, name.strip_suffix(')')?)))
+            .ok_or(SpanParseError::MissingName)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            start: start.parse()?,
+            end: end.parse()?,
+            text: text.to_owned(),
+            igt,
+            id,
+        })
+    }
+}
+
+/// Reads spans from a reader. Empty lines and comment lines are skipped.
+pub fn read(reader: impl BufRead) -> eyre::Result<Vec<Span>> {
+    let mut spans = vec![];
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || super::is_comment(&line) {
+            continue;
+        }
+        let span = line
+            .parse()
+            .wrap_err_with(|| format!("invalid span at line {}", lineno + 1))?;
+        spans.push(span);
+    }
+    Ok(spans)
+}
+
+/// Reads spans from a file.
+pub fn read_file(path: &Path) -> eyre::Result<Vec<Span>> {
+    let file = File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+    read(BufReader::new(file)).wrap_err_with(|| format!("failed to read {}", path.display()))
+}
+
+/// Joins spans of contiguous ranges analyzed separately.
+///
+/// `chunks` are the spans of each range in order.
+/// A span ending where the next range starts is joined with the span of the same component starting there,
+/// if their texts are the same or either is not reliably recognized. Joined spans may cover any number of ranges.
+/// The joined span keeps the in-game time and the ID of the former one.
+pub fn join_chunks(chunks: impl IntoIterator<Item = Vec<Span>>) -> Vec<Span> {
+    let same_time = |a: Timestamp, b: Timestamp| (a - b).as_ratio().abs() < Ratio::new(1, 1000);
+
+    let mut joined = Vec::<Span>::new();
+    // Indices in `joined` of the spans of the last chunk by name, including ones joined with earlier chunks
+    let mut tails = HashMap::<String, Vec<usize>>::new();
+    for chunk in chunks {
+        let mut next_tails = HashMap::<String, Vec<usize>>::new();
+        for span in chunk {
+            let tail = tails
+                .get(&span.name)
+                .into_iter()
+                .flatten()
+                .copied()
+                .find(|&idx| {
+                    let tail = &joined[idx];
+                    same_time(tail.end, span.start)
+                        && (tail.text == span.text || tail.is_possible() || span.is_possible())
+                });
+            let idx = match tail {
+                Some(idx) => {
+                    let tail = &mut joined[idx];
+                    if tail.is_possible() && !span.is_possible() {
+                        tail.text = span.text;
+                    }
+                    tail.end = span.end;
+                    idx
+                }
+                None => {
+                    joined.push(span);
+                    joined.len() - 1
+                }
+            };
+            next_tails
+                .entry(joined[idx].name.clone())
+                .or_default()
+                .push(idx);
+        }
+        tails = next_tails;
+    }
+    joined
+}
+
+/// Links spans of side items moving upward across rows as new items are picked up.
+///
+/// A span of a side item is linked to the span of the same text in a row above, starting within `window` of its end.
+/// Linked spans are merged into a span named [`TRACKED_SIDE_ITEM`] covering the whole interval the item is shown,
+/// which keeps the in-game time and the ID of the first one. Other spans are kept as is.
+///
+/// Returned spans are sorted by the start.
+pub fn track_side_items(spans: impl IntoIterator<Item = Span>, window: Duration) -> Vec<Span> {
+    struct Track {
+        span: Span,
+        row: usize,
+        last_end: Timestamp,
+    }
+
+    let mut spans = spans.into_iter().collect::<Vec<_>>();
+    spans.sort_by_key(|span| span.start);
+
+    let mut tracks = Vec::<Track>::new();
+    let mut others = vec![];
+    for span in spans {
+        let Some(row) = components::side_item_row(&span.name) else {
+            others.push(span);
+            continue;
+        };
+        let gap = |track: &Track| (span.start - track.last_end).as_ratio().abs();
+        let track = tracks
+            .iter_mut()
+            .filter(|track| {
+                track.row < row && track.span.text == span.text && gap(track) <= window.as_ratio()
+            })
+            .min_by_key(|track| (row - track.row, gap(track)));
+        match track {
+            Some(track) => {
+                track.span.end = track.span.end.max(span.end);
+                track.row = row;
+                track.last_end = span.end;
+            }
+            None => tracks.push(Track {
+                last_end: span.end,
+                row,
+                span: Span {
+                    name: TRACKED_SIDE_ITEM.to_owned(),
+                    ..span
+                },
+            }),
+        }
+    }
+
+    let mut tracked = others;
+    tracked.extend(tracks.into_iter().map(|track| track.span));
+    tracked.sort_by_key(|span| span.start);
+    tracked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let lines = [
+            "00:00:01.000-00:00:03.500 聖杯瓶 (main_item)",
+            "00:01:00.033-00:01:02.000 ??{黄金樹の恩寵|黄金樹の恵み} ×1 (side_item0) @01:02:03.450",
+            "00:01:00.033-00:01:02.000 ( ) (rune_count)",
+            "00:01:00.033-00:01:02.000 #1 (rune_count) #00000000000000ff-rune_count-1801",
+        ];
+        for line in lines {
+            let span = line.parse::<Span>().unwrap();
+            assert_eq!(span.to_string(), line);
+        }
+
+        let span = lines[2].parse::<Span>().unwrap();
+        assert_eq!(span.text, "( )");
+        assert_eq!(span.name, "rune_count");
+        assert!(lines[1].parse::<Span>().unwrap().is_possible());
+        let span = lines[3].parse::<Span>().unwrap();
+        assert_eq!(span.text, "#1");
+        assert_eq!(span.id.unwrap().start_frame, 1801);
+
+        assert!("00:00:01.000-00:00:03.500 text".parse::<Span>().is_err());
+        assert!("00:00:01.000 text (name)".parse::<Span>().is_err());
+    }
+
+    #[test]
+    fn read_lines() {
+        let text = "# elden-analyzer\n00:00:01.000-00:00:03.500 a (x)\n\n00:00:04.000-00:00:05.000 b (y)\n";
+        let spans = read(text.as_bytes()).unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].name, "y");
+
+        let err = read("bad\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "invalid span at line 1");
+    }
+
+    #[test]
+    fn join_chunks_at_boundaries() {
+        let chunk = |lines: &[&str]| lines.iter().map(|s| s.parse::<Span>().unwrap()).collect();
+        let joined = join_chunks([
+            chunk(&[
+                "00:00:01.000-00:00:02.000 a (x)",
+                "00:00:09.000-00:00:10.000 b (x) @00:01:00.000",
+                "00:00:09.500-00:00:10.000 c (y)",
+            ]),
+            chunk(&[
+                "00:00:10.000-00:00:11.000 b (x)",
+                "00:00:10.000-00:00:10.500 ??d (y)",
+                "00:00:12.000-00:00:20.000 e (x)",
+            ]),
+            chunk(&["00:00:20.000-00:00:21.000 ??e (x)"]),
+        ]);
+        let lines = joined.iter().map(Span::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "00:00:01.000-00:00:02.000 a (x)",
+                "00:00:09.000-00:00:11.000 b (x) @00:01:00.000",
+                "00:00:09.500-00:00:10.500 c (y)",
+                "00:00:12.000-00:00:21.000 e (x)",
+            ]
+        );
+    }
+
+    #[test]
+    fn join_chunks_across_multiple_boundaries() {
+        let chunk = |lines: &[&str]| lines.iter().map(|s| s.parse::<Span>().unwrap()).collect();
+        let joined = join_chunks([
+            chunk(&[
+                "00:00:01.000-00:00:02.000 a (x)",
+                "00:00:08.000-00:00:10.000 ??b (x) @00:01:00.000",
+            ]),
+            chunk(&["00:00:10.000-00:00:20.000 b (x)"]),
+            chunk(&[
+                "00:00:20.000-00:00:25.000 b (x)",
+                "00:00:26.000-00:00:30.000 c (x)",
+            ]),
+            chunk(&["00:00:30.000-00:00:31.000 c (x)"]),
+        ]);
+        let lines = joined.iter().map(Span::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "00:00:01.000-00:00:02.000 a (x)",
+                "00:00:08.000-00:00:25.000 b (x) @00:01:00.000",
+                "00:00:26.000-00:00:31.000 c (x)",
+            ]
+        );
+    }
+
+    #[test]
+    fn track_side_items_across_rows() {
+        let spans = [
+            "00:00:01.000-00:00:02.000 a ×1 (side_item0) #00000000000000ff-side_item0-30",
+            "00:00:02.000-00:00:04.000 b ×2 (side_item0)",
+            "00:00:02.033-00:00:05.000 a ×1 (side_item1)",
+            "00:00:04.000-00:00:06.000 b ×2 (side_item1)",
+            "00:00:05.000-00:00:07.000 a ×1 (side_item3)",
+            "00:00:03.000-00:00:04.000 x (main_item)",
+            "00:00:09.000-00:00:10.000 a ×1 (side_item1)",
+        ]
+        .map(|s| s.parse::<Span>().unwrap());
+        let window = Duration::new(Ratio::new(1, 10));
+        let lines = track_side_items(spans, window)
+            .iter()
+            .map(Span::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "00:00:01.000-00:00:07.000 a ×1 (side_item) #00000000000000ff-side_item0-30",
+                "00:00:02.000-00:00:06.000 b ×2 (side_item)",
+                "00:00:03.000-00:00:04.000 x (main_item)",
+                "00:00:09.000-00:00:10.000 a ×1 (side_item)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Subtitle files written by `analyze --output-srt` and `--output-vtt`.
+//!
+//! Each span becomes a cue showing the text with the component, so that recognitions can be checked by playing
+//! the video with the subtitles:
+//!
+//! ```text
+//! 1
+//! 00:12:01,200 --> 00:12:03,467
+//! [side item 0] 緋雫の聖杯瓶 ×3
+//! ```
+//!
+//! Cues are ordered by their start, and cues of components shown at the same time overlap.
+//! Unlike span files, subtitle files have no comment lines, since video players do not skip them.
+
+use std::io::{self, Write};
+
+use elden_analyzer_kernel::types::time::Timestamp;
+
+use crate::components;
+
+use super::spans::Span;
+
+/// Format of subtitle files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    /// SubRip (`.srt`)
+    Srt,
+    /// WebVTT (`.vtt`)
+    Vtt,
+}
+
+/// Writes `spans` as subtitle cues in `format`.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::{spans::Span, subtitles::{self, SubtitleFormat}};
+///
+/// let span = "00:00:01.000-00:00:03.500 聖杯瓶 (main_item)".parse::<Span>().unwrap();
+/// let mut output = vec![];
+/// subtitles::write(&mut output, SubtitleFormat::Vtt, &[span]).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "WEBVTT\n\n00:00:01.000 --> 00:00:03.500\n[main item] 聖杯瓶\n\n"
+/// );
+/// ```
+pub fn write(writer: &mut impl Write, format: SubtitleFormat, spans: &[Span]) -> io::Result<()> {
+    let mut spans = spans.iter().collect::<Vec<_>>();
+    spans.sort_by(|a, b| (a.start, a.end, &a.name).cmp(&(b.start, b.end, &b.name)));
+
+    if format == SubtitleFormat::Vtt {
+        writeln!(writer, "WEBVTT")?;
+        writeln!(writer)?;
+    }
+    for (idx, span) in spans.into_iter().enumerate() {
+        let text = format!("[{}] {}", components::display_name(&span.name), span.text);
+        match format {
+            SubtitleFormat::Srt => {
+                writeln!(writer, "{}", idx + 1)?;
+                writeln!(
+                    writer,
+                    "{} --> {}",
+                    srt_time(span.start),
+                    srt_time(span.end)
+                )?;
+                writeln!(writer, "{text}")?;
+            }
+            SubtitleFormat::Vtt => {
+                writeln!(writer, "{} --> {}", span.start, span.end)?;
+                writeln!(writer, "{}", escape_vtt(&text))?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Formats `ts` as `HH:MM:SS,mmm`, which SubRip separates milliseconds with a comma.
+fn srt_time(ts: Timestamp) -> String {
+    ts.to_string().replace('.', ",")
+}
+
+/// Escapes characters having special meanings in cue texts of WebVTT.
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_srt_in_order() {
+        let spans = [
+            "00:00:05.000-00:00:06.000 ??<剣> (side_item0)",
+            "00:00:01.000-00:00:03.500 聖杯瓶 & 霊薬 (main_item)",
+        ]
+        .map(|s| s.parse::<Span>().unwrap());
+
+        let mut output = vec![];
+        write(&mut output, SubtitleFormat::Srt, &spans).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1\n00:00:01,000 --> 00:00:03,500\n[main item] 聖杯瓶 & 霊薬\n\n\
+             2\n00:00:05,000 --> 00:00:06,000\n[side item 0] ??<剣>\n\n"
+        );
+
+        let mut output = vec![];
+        write(&mut output, SubtitleFormat::Vtt, &spans).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "WEBVTT\n\n\
+             00:00:01.000 --> 00:00:03.500\n[main item] 聖杯瓶 &amp; 霊薬\n\n\
+             00:00:05.000 --> 00:00:06.000\n[side item 0] ??&lt;剣&gt;\n\n"
+        );
+    }
+}
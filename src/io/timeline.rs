@@ -0,0 +1,426 @@
+//! Timeline TSV file output by `analyze --output-tsv`.
+//!
+//! The file is written in one of the [`Layout`]s.
+//! In the wide layout, the header row lists the component names, and each row has the texts of the components shown from its timestamp until the timestamp of the next row.
+//! The texts are empty while the component is absent.
+//!
+//! In the long layout, the header row is `timestamp`, `component` and `text`,
+//! and each row records that the text of the component changed at the timestamp.
+//! An empty text means the component disappeared.
+//!
+//! Texts not reliably recognized are written as chosen by [`UncertainCells`].
+//! With [`UncertainCells::Columns`], each text column of the wide layout is followed by `{name}_confidence`,
+//! and the long layout has a `confidence` column. Confidence columns are skipped by [`Timeline::read`].
+//!
+//! If the in-game time is read, it is written as the text of a component named [`IGT_COLUMN`],
+//! which is the in-game time at the timestamp of each row.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::FromStr,
+};
+
+use color_eyre::eyre::{self, bail, OptionExt as _, WrapErr as _};
+use elden_analyzer_kernel::types::time::Timestamp;
+
+/// Layout of the timeline TSV file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// A column for each component
+    #[default]
+    Wide,
+    /// A row for each change of the text of a component
+    Long,
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Wide => write!(f, "wide"),
+            Layout::Long => write!(f, "long"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown timeline layout: {0}")]
+pub struct LayoutParseError(String);
+
+impl FromStr for Layout {
+    type Err = LayoutParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wide" => Ok(Self::Wide),
+            "long" => Ok(Self::Long),
+            _ => Err(LayoutParseError(s.to_owned())),
+        }
+    }
+}
+
+/// How cells of texts not reliably recognized are written.
+///
+/// Spans keep the `??` prefix, but spreadsheets cannot tell it from the text when counting or looking up cells.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::timeline::UncertainCells;
+///
+/// let render = |cells: UncertainCells| cells.render("??聖杯瓶", Some(0.42));
+/// assert_eq!(render(UncertainCells::Prefix), ("??聖杯瓶".to_owned(), None));
+/// assert_eq!(render(UncertainCells::Empty), (String::new(), None));
+/// assert_eq!(render(UncertainCells::Suffix), ("聖杯瓶?".to_owned(), None));
+/// assert_eq!(render(UncertainCells::Percent), ("聖杯瓶 (42%)".to_owned(), None));
+/// assert_eq!(render(UncertainCells::Columns), ("聖杯瓶".to_owned(), Some("0.42".to_owned())));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UncertainCells {
+    /// `??text`, as in spans
+    #[default]
+    Prefix,
+    /// Empty, as if the component is absent
+    Empty,
+    /// `text?`
+    Suffix,
+    /// `text (NN%)`, with the confidence of the least confident line
+    Percent,
+    /// `text`, with the confidences of all texts in separate columns
+    Columns,
+}
+
+impl fmt::Display for UncertainCells {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UncertainCells::Prefix => write!(f, "prefix"),
+            UncertainCells::Empty => write!(f, "empty"),
+            UncertainCells::Suffix => write!(f, "suffix"),
+            UncertainCells::Percent => write!(f, "percent"),
+            UncertainCells::Columns => write!(f, "columns"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown rendering of uncertain cells: {0}")]
+pub struct UncertainCellsParseError(String);
+
+impl FromStr for UncertainCells {
+    type Err = UncertainCellsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(Self::Prefix),
+            "empty" => Ok(Self::Empty),
+            "suffix" => Ok(Self::Suffix),
+            "percent" => Ok(Self::Percent),
+            "columns" => Ok(Self::Columns),
+            _ => Err(UncertainCellsParseError(s.to_owned())),
+        }
+    }
+}
+
+impl UncertainCells {
+    /// Returns `true` if each text has a column of its confidence.
+    pub fn has_confidence_columns(self) -> bool {
+        self == Self::Columns
+    }
+
+    /// Returns the cell of `text`, and the cell of `confidence` if [`Self::has_confidence_columns`].
+    ///
+    /// `confidence` is the confidence of the least confident line, between `0` and `1`.
+    /// Uncertain texts without confidences are rendered as [`UncertainCells::Suffix`] by [`UncertainCells::Percent`].
+    pub fn render(self, text: &str, confidence: Option<f64>) -> (String, Option<String>) {
+        let certain = !text.contains("??");
+        let stripped = || text.replace("??", "");
+        let cell = match self {
+            Self::Prefix => text.to_owned(),
+            _ if certain && self != Self::Columns => text.to_owned(),
+            Self::Empty => String::new(),
+            Self::Suffix => format!("{}?", stripped()),
+            Self::Percent => match confidence {
+                Some(conf) => format!("{} ({:.0}%)", stripped(), conf * 100.0),
+                None => format!("{}?", stripped()),
+            },
+            Self::Columns => stripped(),
+        };
+        let confidence = self.has_confidence_columns().then(|| {
+            confidence
+                .map(|conf| format!("{conf:.2}"))
+                .unwrap_or_default()
+        });
+        (cell, confidence)
+    }
+}
+
+/// Name of the column of the in-game time at the start of each row
+pub const IGT_COLUMN: &str = "igt";
+
+/// Suffix of the names of confidence columns in the wide layout
+pub const CONFIDENCE_SUFFIX: &str = "_confidence";
+
+/// Header of the long layout
+const LONG_HEADER: &str = "timestamp\tcomponent\ttext";
+
+/// Header of the long layout with confidence columns
+const LONG_CONFIDENCE_HEADER: &str = "timestamp\tcomponent\ttext\tconfidence";
+
+/// Returns the header row of the timeline TSV file of components named `names`.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::timeline::{self, Layout, UncertainCells};
+///
+/// let names = ["main_item".to_owned(), "boss_bar".to_owned()];
+/// assert_eq!(
+///     timeline::header(&names, Layout::Wide, UncertainCells::Columns),
+///     "timestamp\tmain_item\tmain_item_confidence\tboss_bar\tboss_bar_confidence"
+/// );
+/// ```
+pub fn header(names: &[String], layout: Layout, cells: UncertainCells) -> String {
+    match (layout, cells.has_confidence_columns()) {
+        (Layout::Wide, false) => format!("timestamp\t{}", names.join("\t")),
+        (Layout::Wide, true) => {
+            let columns = names
+                .iter()
+                .map(|name| format!("{name}\t{name}{CONFIDENCE_SUFFIX}"))
+                .collect::<Vec<_>>();
+            format!("timestamp\t{}", columns.join("\t"))
+        }
+        (Layout::Long, false) => LONG_HEADER.to_owned(),
+        (Layout::Long, true) => LONG_CONFIDENCE_HEADER.to_owned(),
+    }
+}
+
+/// Texts of components over time.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::io::timeline::Timeline;
+///
+/// let tsv = "timestamp\tmain_item\tside_item0\n00:00:00.000\t\t\n00:00:01.000\t聖杯瓶\t\n";
+/// let timeline = Timeline::read(tsv.as_bytes()).unwrap();
+/// assert_eq!(timeline.names, ["main_item", "side_item0"]);
+/// assert_eq!(timeline.rows[1].texts, ["聖杯瓶", ""]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    pub names: Vec<String>,
+    pub rows: Vec<TimelineRow>,
+}
+
+/// Texts of components from `start` until the start of the next row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineRow {
+    pub start: Timestamp,
+    /// Texts in the same order as [`Timeline::names`]
+    pub texts: Vec<String>,
+}
+
+impl Timeline {
+    /// Reads a timeline from a reader.
+    ///
+    /// The layout is detected from the header. Comment lines are skipped.
+    /// Timelines read from the long layout only have the components appearing in it,
+    /// and a row for each timestamp where any text changed.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::io::timeline::Timeline;
+    ///
+    /// let tsv = "timestamp\tcomponent\ttext\n00:00:01.000\tmain_item\t聖杯瓶\n00:00:02.000\tmain_item\t\n";
+    /// let timeline = Timeline::read(tsv.as_bytes()).unwrap();
+    /// assert_eq!(timeline.names, ["main_item"]);
+    /// assert_eq!(timeline.rows[0].texts, ["聖杯瓶"]);
+    /// assert_eq!(timeline.rows[1].texts, [""]);
+    /// ```
+    pub fn read(reader: impl BufRead) -> eyre::Result<Self> {
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.as_deref().is_ok_and(super::is_comment));
+
+        let (_, header) = lines.next().ok_or_eyre("missing header")?;
+        let header = header?;
+        if header == LONG_HEADER || header == LONG_CONFIDENCE_HEADER {
+            return read_long(lines, header == LONG_CONFIDENCE_HEADER);
+        }
+        let mut columns = header.split('\t');
+        if columns.next() != Some("timestamp") {
+            bail!("header must start with `timestamp`");
+        }
+        // Confidence columns follow the columns of their components, and are not read
+        let mut prev = "";
+        let keep = columns
+            .map(|name| {
+                let is_confidence = name.strip_suffix(CONFIDENCE_SUFFIX) == Some(prev);
+                prev = name;
+                (name, !is_confidence)
+            })
+            .collect::<Vec<_>>();
+        let names = keep
+            .iter()
+            .filter(|(_, keep)| *keep)
+            .map(|(name, _)| (*name).to_owned())
+            .collect::<Vec<_>>();
+        let keep = keep.into_iter().map(|(_, keep)| keep).collect::<Vec<_>>();
+
+        let mut rows = vec![];
+        for (lineno, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row = parse_row(&line, &keep)
+                .wrap_err_with(|| format!("invalid row at line {}", lineno + 1))?;
+            rows.push(row);
+        }
+
+        Ok(Self { names, rows })
+    }
+
+    /// Reads a timeline from a file.
+    pub fn read_file(path: &Path) -> eyre::Result<Self> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+        Self::read(BufReader::new(file))
+            .wrap_err_with(|| format!("failed to read {}", path.display()))
+    }
+
+    /// Returns the index of the column of the component.
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+/// Reads the rows of the long layout, ignoring the confidences if `has_confidence`.
+fn read_long(
+    lines: impl Iterator<Item = (usize, std::io::Result<String>)>,
+    has_confidence: bool,
+) -> eyre::Result<Timeline> {
+    let mut names = vec![];
+    let mut changes = vec![];
+    for (lineno, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (start, name, text) = parse_long_row(&line, has_confidence)
+            .wrap_err_with(|| format!("invalid row at line {}", lineno + 1))?;
+        let idx = match names.iter().position(|n| *n == name) {
+            Some(idx) => idx,
+            None => {
+                names.push(name);
+                names.len() - 1
+            }
+        };
+        changes.push((start, idx, text));
+    }
+
+    // Each row starts with the texts of the previous row, and then changes are applied
+    let mut rows = Vec::<TimelineRow>::new();
+    for (start, idx, text) in changes {
+        match rows.last() {
+            Some(last) if last.start > start => bail!("rows must be sorted by timestamp"),
+            Some(last) if last.start == start => {}
+            last => {
+                let texts = last.map_or_else(
+                    || vec![String::new(); names.len()],
+                    |last| last.texts.clone(),
+                );
+                rows.push(TimelineRow { start, texts });
+            }
+        }
+        rows.last_mut().unwrap().texts[idx] = text;
+    }
+
+    Ok(Timeline { names, rows })
+}
+
+fn parse_long_row(line: &str, has_confidence: bool) -> eyre::Result<(Timestamp, String, String)> {
+    let mut columns = line.split('\t');
+    let start = columns.next().unwrap().parse()?;
+    let (Some(name), Some(text)) = (columns.next(), columns.next()) else {
+        bail!("expected a component and a text");
+    };
+    if has_confidence && columns.next().is_none() {
+        bail!("expected a confidence");
+    }
+    if columns.next().is_some() {
+        bail!("too many columns");
+    }
+    Ok((start, name.to_owned(), text.to_owned()))
+}
+
+/// Parses a row of the wide layout, keeping the columns whose `keep` is `true`.
+fn parse_row(line: &str, keep: &[bool]) -> eyre::Result<TimelineRow> {
+    let mut columns = line.split('\t');
+    let start = columns.next().unwrap().parse()?;
+    let columns = columns.collect::<Vec<_>>();
+    if columns.len() != keep.len() {
+        bail!(
+            "expected {} columns, but found {}",
+            keep.len(),
+            columns.len()
+        );
+    }
+    let texts = columns
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(text, _)| text.to_owned())
+        .collect();
+    Ok(TimelineRow { start, texts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_equals_wide() {
+        let wide = "# elden-analyzer\n\
+                    timestamp\ta\tb\n\
+                    00:00:00.000\t\t\n\
+                    00:00:01.000\tx\t\n\
+                    00:00:02.000\tx\ty\n\
+                    00:00:03.000\t\t\n";
+        let long = "timestamp\tcomponent\ttext\n\
+                    00:00:01.000\ta\tx\n\
+                    00:00:02.000\tb\ty\n\
+                    00:00:03.000\ta\t\n\
+                    00:00:03.000\tb\t\n";
+        let wide = Timeline::read(wide.as_bytes()).unwrap();
+        let long = Timeline::read(long.as_bytes()).unwrap();
+        assert_eq!(long.names, wide.names);
+        // The long layout has no row without changes
+        assert_eq!(long.rows, wide.rows[1..]);
+    }
+
+    #[test]
+    fn confidence_columns_skipped() {
+        let wide = "timestamp\ta\ta_confidence\tb\tb_confidence\n\
+                    00:00:00.000\t\t\t\t\n\
+                    00:00:01.000\tx\t0.42\t\t\n\
+                    00:00:02.000\tx\t0.42\ty\t0.95\n";
+        let long = "timestamp\tcomponent\ttext\tconfidence\n\
+                    00:00:01.000\ta\tx\t0.42\n\
+                    00:00:02.000\tb\ty\t0.95\n";
+        let wide = Timeline::read(wide.as_bytes()).unwrap();
+        let long = Timeline::read(long.as_bytes()).unwrap();
+        assert_eq!(wide.names, ["a", "b"]);
+        assert_eq!(wide.rows[2].texts, ["x", "y"]);
+        assert_eq!(long.names, wide.names);
+        assert_eq!(long.rows, wide.rows[1..]);
+    }
+
+    #[test]
+    fn long_unsorted() {
+        let long = "timestamp\tcomponent\ttext\n\
+                    00:00:02.000\ta\tx\n\
+                    00:00:01.000\ta\t\n";
+        assert!(Timeline::read(long.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,810 @@
+//! Database of known items and dictionary of their names.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    num::ParseIntError,
+    path::Path,
+    str::FromStr,
+    sync::{LazyLock, RwLock},
+};
+
+use color_eyre::eyre::{self, bail, OptionExt as _, WrapErr as _};
+
+use crate::{
+    assets::{self, Asset},
+    i18n::Lang,
+};
+
+/// Header row of item database files
+const HEADER: &str = "id\tcategory\tdlc\tname";
+
+static BUNDLED_ITEMS: LazyLock<ItemDatabase> = LazyLock::new(|| {
+    ItemDatabase::parse(assets::text(Asset::Items)).expect("item database is validated")
+});
+
+static ENGLISH_ITEMS: LazyLock<ItemDictionary> = LazyLock::new(|| {
+    ItemDictionary::with_ids(
+        assets::text(Asset::ItemNamesEn)
+            .lines()
+            .filter(|x| !x.is_empty() && !x.starts_with("#"))
+            .filter_map(|x| x.split_once('\t'))
+            .map(|(ja, en)| (en, BUNDLED_ITEMS.item(ja).map(|item| item.id))),
+    )
+});
+
+/// Items recognized in frames, chosen by [`use_item_list`] before the first use and replaced by [`reload_item_list`].
+///
+/// Lists are leaked rather than dropped when replaced, so items returned before stay valid.
+static ITEM_LIST: RwLock<Option<&'static ItemList>> = RwLock::new(None);
+
+static DEFAULT_ITEM_LIST: ItemList = ItemList::Bundled(Lang::Ja);
+
+static JAPANESE_NAMES: LazyLock<HashMap<&'static str, &'static str>> =
+    LazyLock::new(|| ENGLISH_NAMES.iter().map(|(ja, en)| (*en, *ja)).collect());
+
+static ENGLISH_NAMES: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
+    assets::text(Asset::ItemNamesEn)
+        .lines()
+        .filter(|x| !x.is_empty() && !x.starts_with("#"))
+        .filter_map(|x| x.split_once('\t'))
+        .collect()
+});
+
+/// Returns the name of a known item in `lang`, or `None` if the translation is not bundled.
+/// The name may be in either language, since the game may be displayed in either.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::{i18n::Lang, items};
+///
+/// assert_eq!(items::translate("緋雫の聖杯瓶", Lang::En), Some("Flask of Crimson Tears"));
+/// assert_eq!(items::translate("緋雫の聖杯瓶", Lang::Ja), Some("緋雫の聖杯瓶"));
+/// assert_eq!(items::translate("??緋雫の聖杯瓶", Lang::En), None);
+/// ```
+pub fn translate(name: &str, lang: Lang) -> Option<&'static str> {
+    match lang {
+        Lang::En => ENGLISH_NAMES
+            .get(name)
+            .or_else(|| JAPANESE_NAMES.get_key_value(name).map(|(en, _)| en))
+            .copied(),
+        Lang::Ja => {
+            if let Some(ja) = JAPANESE_NAMES.get(name) {
+                return Some(ja);
+            }
+            let item = BUNDLED_ITEMS.item(name)?;
+            Some(&item.name)
+        }
+    }
+}
+
+/// List of items recognized in frames.
+#[derive(Debug)]
+pub enum ItemList {
+    /// Items bundled in `assets/items.tsv`, named in Japanese or in English by `assets/item_en.tsv`
+    Bundled(Lang),
+    /// Items in a database file formatted as `assets/items.tsv`
+    File(ItemDatabase),
+}
+
+/// Error returned by [`use_item_list`] when the item names are already used.
+#[derive(Debug, thiserror::Error)]
+#[error("item list cannot be changed after item names are used")]
+pub struct ItemListInUse;
+
+impl ItemList {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        Ok(Self::File(ItemDatabase::load(path)?))
+    }
+}
+
+/// Chooses the items returned by [`ItemDatabase::get`] and [`ItemDictionary::get`], which are bundled items named in
+/// Japanese by default.
+///
+/// Item names are validated against them in post-processes, so they must be in the language the game is displayed in.
+/// Translations are only known for bundled items.
+pub fn use_item_list(list: ItemList) -> Result<(), ItemListInUse> {
+    let mut current = ITEM_LIST.write().unwrap();
+    if current.is_some() {
+        return Err(ItemListInUse);
+    }
+    *current = Some(Box::leak(Box::new(list)));
+    Ok(())
+}
+
+/// Replaces the items chosen by [`use_item_list`] while they may be in use, e.g. when the config file is reloaded.
+///
+/// Rules derived from the item names are rebuilt by [`PostProcess::rebuild_item_rules`](crate::operator::PostProcess::rebuild_item_rules).
+pub fn reload_item_list(list: ItemList) {
+    *ITEM_LIST.write().unwrap() = Some(Box::leak(Box::new(list)));
+}
+
+fn item_list() -> &'static ItemList {
+    if let Some(list) = *ITEM_LIST.read().unwrap() {
+        return list;
+    }
+    ITEM_LIST.write().unwrap().get_or_insert(&DEFAULT_ITEM_LIST)
+}
+
+/// Identifier of an item in the item database, which is never reused for other items.
+///
+/// IDs are assigned by the database rather than the game, and are stable across versions of the bundled database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct ItemId(u32);
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ItemId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// A known item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub id: ItemId,
+    /// Name in the language of the database
+    pub name: String,
+    pub category: ItemCategory,
+    /// The item is added by the DLC, Shadow of the Erdtree, or `None` if not known
+    pub dlc: Option<bool>,
+}
+
+/// Known items, bundled in `assets/items.tsv` or loaded from a file formatted the same.
+///
+/// Each line of the file is an item with its ID, category, DLC flag (`0`, `1` or empty if not known) and name, separated by tabs,
+/// following the header row `id`, `category`, `dlc` and `name`. Empty lines and lines starting with `#` are skipped.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::items::{ItemCategory, ItemDatabase};
+///
+/// let tsv = "id\tcategory\tdlc\tname\n# 道具\n1\ttools\t\t緋雫の聖杯瓶\n2\tcrafting-materials\t1\t影輪草\n";
+/// let db = ItemDatabase::parse(tsv).unwrap();
+/// let item = db.item("影輪草").unwrap();
+/// assert_eq!(item.id.to_string(), "2");
+/// assert_eq!(item.category, ItemCategory::CraftingMaterials);
+/// assert_eq!(item.dlc, Some(true));
+/// assert_eq!(db.item("緋雫の聖杯瓶").unwrap().dlc, None);
+/// assert!(db.dictionary().contains("緋雫の聖杯瓶"));
+/// ```
+#[derive(Debug)]
+pub struct ItemDatabase {
+    items: Vec<Item>,
+    /// Index of the first item of each name
+    by_name: HashMap<String, usize>,
+    dict: ItemDictionary,
+}
+
+impl ItemDatabase {
+    /// Returns the database of items chosen by [`use_item_list`].
+    ///
+    /// English names of the bundled items are not in the database, so look them up with [`item_of`].
+    pub fn get() -> &'static Self {
+        match item_list() {
+            ItemList::Bundled(_) => &BUNDLED_ITEMS,
+            ItemList::File(db) => db,
+        }
+    }
+
+    /// Returns the database of bundled items, named in Japanese.
+    pub fn bundled() -> &'static Self {
+        &BUNDLED_ITEMS
+    }
+
+    /// Reads a database file.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read item database: {}", path.display()))?;
+        Self::parse(&text)
+            .wrap_err_with(|| format!("failed to parse item database: {}", path.display()))
+    }
+
+    /// Parses a database formatted as `assets/items.tsv`.
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (_, header) = lines.next().ok_or_eyre("missing header")?;
+        if header != HEADER {
+            bail!("header must be `{}`", HEADER.replace('\t', ", "));
+        }
+
+        let mut items = vec![];
+        let mut ids = HashSet::new();
+        for (lineno, line) in lines {
+            let item = parse_item(line)
+                .wrap_err_with(|| format!("invalid item at line {}", lineno + 1))?;
+            if !ids.insert(item.id) {
+                bail!("duplicated ID {} at line {}", item.id, lineno + 1);
+            }
+            items.push(item);
+        }
+
+        let mut by_name = HashMap::new();
+        for (idx, item) in items.iter().enumerate() {
+            by_name.entry(item.name.clone()).or_insert(idx);
+        }
+        let dict = ItemDictionary::with_ids(items.iter().map(|item| (&*item.name, Some(item.id))));
+        Ok(Self {
+            items,
+            by_name,
+            dict,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns items in the order of the database file.
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter()
+    }
+
+    /// Returns the item named `name`, or the first one in the file if some items have the same name.
+    pub fn item(&self, name: &str) -> Option<&Item> {
+        self.by_name.get(name).map(|&idx| &self.items[idx])
+    }
+
+    /// Returns the dictionary of item names.
+    pub fn dictionary(&self) -> &ItemDictionary {
+        &self.dict
+    }
+}
+
+fn parse_item(line: &str) -> eyre::Result<Item> {
+    let mut columns = line.split('\t');
+    let (Some(id), Some(category), Some(dlc), Some(name), None) = (
+        columns.next(),
+        columns.next(),
+        columns.next(),
+        columns.next(),
+        columns.next(),
+    ) else {
+        bail!("expected 4 columns");
+    };
+    let dlc = match dlc {
+        "" => None,
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => bail!("DLC flag must be `0`, `1` or empty: {dlc}"),
+    };
+    if name.is_empty() {
+        bail!("empty name");
+    }
+    Ok(Item {
+        id: id.parse().wrap_err_with(|| format!("invalid ID: {id}"))?,
+        name: name.to_owned(),
+        category: category.parse()?,
+        dlc,
+    })
+}
+
+/// Category of items, used to filter outputs.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::items::{self, ItemCategory};
+///
+/// assert_eq!("talismans".parse::<ItemCategory>().unwrap(), ItemCategory::Talismans);
+/// assert_eq!(items::category("緋雫の聖杯瓶 ×3"), Some(ItemCategory::Tools));
+/// assert_eq!(items::category("Flask of Crimson Tears"), Some(ItemCategory::Tools));
+/// assert_eq!(items::category("??緋雫の聖杯瓶"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemCategory {
+    Tools,
+    /// Remembrances of bosses, which are tools in the game
+    Remembrances,
+    Ashes,
+    CraftingMaterials,
+    UpgradeMaterials,
+    KeyItems,
+    Sorceries,
+    Incantations,
+    AshesOfWar,
+    /// Melee and ranged weapons
+    Weapons,
+    /// Arrows and bolts
+    Ammunition,
+    Shields,
+    /// Helms, chest armor, gauntlets and leg armor
+    Armor,
+    Talismans,
+    Info,
+    Gestures,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown item category: {0}")]
+pub struct ItemCategoryParseError(String);
+
+impl ItemCategory {
+    pub const ALL: [Self; 16] = [
+        Self::Tools,
+        Self::Remembrances,
+        Self::Ashes,
+        Self::CraftingMaterials,
+        Self::UpgradeMaterials,
+        Self::KeyItems,
+        Self::Sorceries,
+        Self::Incantations,
+        Self::AshesOfWar,
+        Self::Weapons,
+        Self::Ammunition,
+        Self::Shields,
+        Self::Armor,
+        Self::Talismans,
+        Self::Info,
+        Self::Gestures,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tools => "tools",
+            Self::Remembrances => "remembrances",
+            Self::Ashes => "ashes",
+            Self::CraftingMaterials => "crafting-materials",
+            Self::UpgradeMaterials => "upgrade-materials",
+            Self::KeyItems => "key-items",
+            Self::Sorceries => "sorceries",
+            Self::Incantations => "incantations",
+            Self::AshesOfWar => "ashes-of-war",
+            Self::Weapons => "weapons",
+            Self::Ammunition => "ammunition",
+            Self::Shields => "shields",
+            Self::Armor => "armor",
+            Self::Talismans => "talismans",
+            Self::Info => "info",
+            Self::Gestures => "gestures",
+        }
+    }
+}
+
+impl fmt::Display for ItemCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ItemCategory {
+    type Err = ItemCategoryParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_str() == s)
+            .ok_or_else(|| ItemCategoryParseError(s.to_owned()))
+    }
+}
+
+impl serde::Serialize for ItemCategory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Rarity of an item, shown by the color of its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rarity {
+    /// White names
+    Common,
+    /// Blue names
+    Uncommon,
+    /// Purple names
+    Rare,
+    /// Gold names
+    Legendary,
+}
+
+impl Rarity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Common => "common",
+            Self::Uncommon => "uncommon",
+            Self::Rare => "rare",
+            Self::Legendary => "legendary",
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl serde::Serialize for Rarity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Returns the item that the text of a span starts with, in the database chosen by [`use_item_list`].
+///
+/// The name may be followed by segments separated by spaces, such as the count and the translation,
+/// and may be in English if the translation replaces it. The name of the returned bundled item is always in Japanese.
+/// Texts not reliably recognized are not items.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::items::{self, ItemCategory};
+///
+/// let item = items::item_of("Flask of Crimson Tears ×3").unwrap();
+/// assert_eq!(item.name, "緋雫の聖杯瓶");
+/// assert_eq!(item.category, ItemCategory::Tools);
+/// assert_eq!(item.id.to_string(), "1");
+/// ```
+pub fn item_of(text: &str) -> Option<&'static Item> {
+    if text.contains("??") {
+        return None;
+    }
+    let db = ItemDatabase::get();
+    // Names end at spaces, though English names contain spaces themselves
+    let ends = text.match_indices(' ').map(|(i, _)| i).chain([text.len()]);
+    ends.filter_map(|end| {
+        let name = &text[..end];
+        db.item(name).or_else(|| db.item(JAPANESE_NAMES.get(name)?))
+    })
+    .next()
+}
+
+/// Returns the category of the item that the text of a span starts with (see [`item_of`]).
+pub fn category(text: &str) -> Option<ItemCategory> {
+    item_of(text).map(|item| item.category)
+}
+
+/// Returns the ID of the item that the text of a span starts with (see [`item_of`]).
+pub fn item_id(text: &str) -> Option<ItemId> {
+    item_of(text).map(|item| item.id)
+}
+
+/// Returns the names of known items in `category` in the order of the database file.
+///
+/// Items sharing the name of an earlier item are skipped, as they are never returned by [`item_of`].
+pub fn items_in(category: ItemCategory) -> impl Iterator<Item = &'static str> {
+    let db = ItemDatabase::get();
+    db.iter()
+        .filter(move |item| item.category == category && db.item(&item.name) == Some(*item))
+        .map(|item| item.name.as_str())
+}
+
+/// Translation of recognized item names in the outputs.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::{i18n::Lang, items::ItemTranslation};
+///
+/// let translation = ItemTranslation { lang: Lang::En, replace: false };
+/// assert_eq!(translation.apply("緋雫の聖杯瓶".into()), "緋雫の聖杯瓶 / Flask of Crimson Tears");
+/// assert_eq!(translation.apply("??緋雫の聖杯瓶".into()), "??緋雫の聖杯瓶");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ItemTranslation {
+    pub lang: Lang,
+    /// Replace item names with the translations instead of appending them
+    pub replace: bool,
+}
+
+impl ItemTranslation {
+    /// Translates `text` if it is a known item name. Other texts, including uncertain ones, are kept as is.
+    pub fn apply(&self, text: String) -> String {
+        match translate(&text, self.lang) {
+            Some(translated) if self.replace => translated.to_owned(),
+            Some(translated) if translated != text => format!("{text} / {translated}"),
+            _ => text,
+        }
+    }
+}
+
+/// Known item names indexed by a trie, supporting prefix and wildcard lookups.
+#[derive(Debug)]
+pub struct ItemDictionary {
+    names: Vec<String>,
+    /// IDs of the items of `names`, if known
+    ids: Vec<Option<ItemId>>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: BTreeMap<char, usize>,
+    /// Index of the name ending at this node
+    name: Option<usize>,
+}
+
+impl ItemDictionary {
+    /// Returns the dictionary of item names chosen by [`use_item_list`].
+    pub fn get() -> &'static Self {
+        match item_list() {
+            ItemList::Bundled(lang) => Self::bundled(*lang),
+            ItemList::File(db) => db.dictionary(),
+        }
+    }
+
+    /// Returns the dictionary of bundled item names in `lang`.
+    pub fn bundled(lang: Lang) -> &'static Self {
+        match lang {
+            Lang::Ja => BUNDLED_ITEMS.dictionary(),
+            Lang::En => &ENGLISH_ITEMS,
+        }
+    }
+
+    /// Creates a dictionary of item names without IDs.
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        Self::with_ids(names.into_iter().map(|name| (name, None)))
+    }
+
+    /// Creates a dictionary of item names and their IDs, where the first ID is kept for duplicated names.
+    fn with_ids<'a>(items: impl IntoIterator<Item = (&'a str, Option<ItemId>)>) -> Self {
+        let mut dict = Self {
+            names: vec![],
+            ids: vec![],
+            nodes: vec![Node::default()],
+        };
+        for (name, id) in items {
+            dict.insert(name, id);
+        }
+        dict
+    }
+
+    fn insert(&mut self, name: &str, id: Option<ItemId>) {
+        let mut node = 0;
+        for ch in name.chars() {
+            node = match self.nodes[node].children.get(&ch) {
+                Some(&child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(ch, child);
+                    child
+                }
+            };
+        }
+        if self.nodes[node].name.is_none() {
+            self.nodes[node].name = Some(self.names.len());
+            self.names.push(name.to_owned());
+            self.ids.push(id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns item names in the order of the dictionary file.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    fn node(&self, prefix: &str) -> Option<usize> {
+        prefix
+            .chars()
+            .try_fold(0, |node, ch| self.nodes[node].children.get(&ch).copied())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.node(name)
+            .is_some_and(|node| self.nodes[node].name.is_some())
+    }
+
+    /// Returns the ID of the item named `name`, or `None` if it is not known or has no ID.
+    pub fn id(&self, name: &str) -> Option<ItemId> {
+        let node = self.nodes[self.node(name)?].name?;
+        self.ids[node]
+    }
+
+    /// Returns the number of leading characters of `text` that some item name starts with.
+    pub fn longest_prefix(&self, text: &str) -> usize {
+        let mut node = 0;
+        let mut len = 0;
+        for ch in text.chars() {
+            let Some(&child) = self.nodes[node].children.get(&ch) else {
+                break;
+            };
+            node = child;
+            len += 1;
+        }
+        len
+    }
+
+    /// Returns item names starting with `prefix` in lexicographic order.
+    pub fn with_prefix(&self, prefix: &str) -> Vec<&str> {
+        let mut found = vec![];
+        if let Some(node) = self.node(prefix) {
+            self.collect(node, &mut found);
+        }
+        found
+    }
+
+    /// Returns item names matching `pattern` in lexicographic order.
+    ///
+    /// In `pattern`, `?` matches any single character and `*` matches any sequence of characters.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::items::ItemDictionary;
+    ///
+    /// let dict = ItemDictionary::new(["結晶投げ矢", "結晶人の遺灰", "壊れた結晶"]);
+    /// assert_eq!(dict.find("結?投げ矢"), ["結晶投げ矢"]);
+    /// assert_eq!(dict.find("*結晶"), ["壊れた結晶"]);
+    /// ```
+    pub fn find(&self, pattern: &str) -> Vec<&str> {
+        let pattern = pattern.chars().collect::<Vec<_>>();
+        let mut found = vec![];
+        self.find_from(0, &pattern, &mut found);
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    /// Returns item names completing a partial text, such as a possible OCR result.
+    ///
+    /// `partial` is a pattern of [`ItemDictionary::find`] implicitly followed by `*`.
+    pub fn complete(&self, partial: &str) -> Vec<&str> {
+        if partial.ends_with('*') {
+            self.find(partial)
+        } else {
+            self.find(&format!("{partial}*"))
+        }
+    }
+
+    fn collect<'a>(&'a self, node: usize, found: &mut Vec<&'a str>) {
+        let node = &self.nodes[node];
+        if let Some(name) = node.name {
+            found.push(&self.names[name]);
+        }
+        for &child in node.children.values() {
+            self.collect(child, found);
+        }
+    }
+
+    fn find_from<'a>(&'a self, node: usize, pattern: &[char], found: &mut Vec<&'a str>) {
+        let Some((&ch, rest)) = pattern.split_first() else {
+            if let Some(name) = self.nodes[node].name {
+                found.push(&self.names[name]);
+            }
+            return;
+        };
+        match ch {
+            '*' if rest.is_empty() => self.collect(node, found),
+            '*' => {
+                self.find_from(node, rest, found);
+                for &child in self.nodes[node].children.values() {
+                    self.find_from(child, pattern, found);
+                }
+            }
+            '?' => {
+                for &child in self.nodes[node].children.values() {
+                    self.find_from(child, rest, found);
+                }
+            }
+            _ => {
+                if let Some(&child) = self.nodes[node].children.get(&ch) {
+                    self.find_from(child, rest, found);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        let dict = ItemDictionary::new(["ab", "abc", "abd", "b", "ab"]);
+        assert_eq!(dict.len(), 4);
+        assert!(dict.contains("ab"));
+        assert!(!dict.contains("a"));
+        assert_eq!(dict.longest_prefix("abx"), 2);
+        assert_eq!(dict.with_prefix("ab"), ["ab", "abc", "abd"]);
+        assert_eq!(dict.find("a?"), ["ab"]);
+        assert_eq!(dict.find("*b*"), ["ab", "abc", "abd", "b"]);
+        assert_eq!(dict.complete("a?c"), ["abc"]);
+        assert!(dict.complete("x").is_empty());
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!(category("祖霊の王の追憶"), Some(ItemCategory::Remembrances));
+        assert_eq!(
+            category("緋雫の聖杯瓶 / Flask of Crimson Tears ×3"),
+            Some(ItemCategory::Tools)
+        );
+        assert_eq!(category("聖杯瓶 ×3"), None);
+        assert_eq!(category("stake"), None);
+        assert_eq!(
+            item_of("Flask of Crimson Tears ×3").map(|item| (&*item.name, item.category)),
+            Some(("緋雫の聖杯瓶", ItemCategory::Tools))
+        );
+        assert!(items_in(ItemCategory::Remembrances).any(|name| name == "祖霊の王の追憶"));
+        for category in ItemCategory::ALL {
+            assert_eq!(category.as_str().parse::<ItemCategory>().unwrap(), category);
+        }
+    }
+
+    #[test]
+    fn translations_are_known_items() {
+        let dict = ItemDictionary::bundled(Lang::Ja);
+        for name in ENGLISH_NAMES.keys() {
+            assert!(dict.contains(name), "{name}");
+        }
+        assert_eq!(
+            ItemDictionary::bundled(Lang::En).len(),
+            ENGLISH_NAMES.values().collect::<HashSet<_>>().len()
+        );
+    }
+
+    #[test]
+    fn english_names_have_ids() {
+        let en = ItemDictionary::bundled(Lang::En);
+        for (ja, en_name) in ENGLISH_NAMES.iter() {
+            let id = en.id(en_name);
+            assert!(id.is_some(), "{en_name}");
+            // The first translation is kept for names shared by several items
+            if ENGLISH_NAMES
+                .values()
+                .filter(|name| *name == en_name)
+                .count()
+                == 1
+            {
+                assert_eq!(id, BUNDLED_ITEMS.item(ja).map(|item| item.id), "{en_name}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_database() {
+        let parse = |rows: &str| ItemDatabase::parse(&format!("{HEADER}\n{rows}"));
+        assert!(parse("1\ttools\t0\ta\n2\ttools\t0\ta\n").is_ok());
+        assert_eq!(
+            parse("1\ttools\t\ta\n").unwrap().item("a").unwrap().dlc,
+            None
+        );
+        assert!(parse("1\ttools\t0\ta\n1\ttools\t0\tb\n").is_err());
+        assert!(parse("1\tfood\t0\ta\n").is_err());
+        assert!(parse("1\ttools\tyes\ta\n").is_err());
+        assert!(parse("1\ttools\t0\n").is_err());
+        assert!(ItemDatabase::parse("a\nb\n").is_err());
+    }
+
+    #[test]
+    fn translate_english_names() {
+        assert_eq!(
+            translate("Flask of Crimson Tears", Lang::Ja),
+            Some("緋雫の聖杯瓶")
+        );
+        assert_eq!(
+            translate("Flask of Crimson Tears", Lang::En),
+            Some("Flask of Crimson Tears")
+        );
+    }
+}
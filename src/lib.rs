@@ -1,6 +1,23 @@
+pub mod accumulate;
 pub mod algorithm;
+pub mod analyzer;
+pub mod areas;
+pub mod assets;
+pub mod build_info;
+pub mod checklist;
 pub mod components;
+pub mod config;
+pub mod data_package;
+pub mod environment;
+pub mod i18n;
+pub mod igt;
 pub mod image_process;
+pub mod inference;
+pub mod io;
+pub mod items;
+pub mod materials;
 pub mod operator;
+pub mod profile;
+pub mod route;
 pub mod util;
 pub mod video_capture;
@@ -0,0 +1,108 @@
+//! Running totals of upgrade materials picked up in a run.
+
+use std::collections::HashMap;
+
+use elden_analyzer_kernel::types::time::Duration;
+
+use crate::{
+    io::spans::{self, Span},
+    items::{self, ItemCategory},
+};
+
+/// Pickup of an upgrade material with the total picked up so far.
+#[derive(Debug)]
+pub struct Pickup {
+    /// Name of the material in Japanese
+    pub item: &'static str,
+    /// Number of the material picked up at once
+    pub count: u32,
+    /// Number of the material picked up so far, including this pickup
+    pub total: u32,
+    pub span: Span,
+}
+
+/// Returns the pickups of upgrade materials in `spans` in order of time, with running totals of each material.
+///
+/// Spans of side items moving across rows are linked with `window` first (see [`spans::track_side_items`]),
+/// so that each pickup is counted once. The count is read from the `×N` segment at the end of the text,
+/// and is `1` if there is none.
+pub fn running_totals(spans: impl IntoIterator<Item = Span>, window: Duration) -> Vec<Pickup> {
+    let mut totals = HashMap::<&str, u32>::new();
+    spans::track_side_items(spans, window)
+        .into_iter()
+        .filter_map(|span| {
+            let item = items::item_of(&span.text)?;
+            if item.category != ItemCategory::UpgradeMaterials {
+                return None;
+            }
+            let item = item.name.as_str();
+            let count = count_of(&span.text)?;
+            let total = totals.entry(item).or_default();
+            *total += count;
+            Some(Pickup {
+                item,
+                count,
+                total: *total,
+                span,
+            })
+        })
+        .collect()
+}
+
+/// Returns the totals of each upgrade material picked up, in order of the first pickup.
+pub fn final_totals(pickups: &[Pickup]) -> Vec<(&'static str, u32)> {
+    let mut totals = Vec::<(&str, u32)>::new();
+    for pickup in pickups {
+        match totals.iter_mut().find(|(item, _)| *item == pickup.item) {
+            Some((_, total)) => *total = pickup.total,
+            None => totals.push((pickup.item, pickup.total)),
+        }
+    }
+    totals
+}
+
+/// Returns the count of the `×N` segment at the end of `text`, `Some(1)` if there is none,
+/// or `None` if the count is not a number.
+fn count_of(text: &str) -> Option<u32> {
+    let last = text.rsplit(' ').next().unwrap_or(text);
+    match last.strip_prefix('×') {
+        Some(count) => count.parse().ok(),
+        None => Some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Ratio;
+
+    use super::*;
+
+    #[test]
+    fn running_totals_of_materials() {
+        let spans = [
+            "00:01:00.000-00:01:02.000 鍛石【1】 ×2 (side_item0)",
+            "00:01:01.900-00:01:04.000 鍛石【1】 ×2 (side_item1)",
+            "00:01:02.000-00:01:03.000 緋雫の聖杯瓶 ×3 (side_item0)",
+            "00:02:00.000-00:02:02.000 鍛石【1】 (main_item)",
+            "00:03:00.000-00:03:02.000 ??鍛石【1】 ×5 (side_item0)",
+            "00:04:00.000-00:04:02.000 鍛石【2】 / Smithing Stone [2] ×3 (side_item0)",
+            "00:05:00.000-00:05:02.000 鍛石【1】 ×4 (side_item)",
+        ]
+        .map(|line| line.parse::<Span>().unwrap());
+        let pickups = running_totals(spans, Duration::new(Ratio::new(1, 2)));
+        let lines = pickups
+            .iter()
+            .map(|p| format!("{} {} {} {}", p.span.start, p.item, p.count, p.total))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "00:01:00.000 鍛石【1】 2 2",
+                "00:02:00.000 鍛石【1】 1 3",
+                "00:04:00.000 鍛石【2】 3 3",
+                "00:05:00.000 鍛石【1】 4 7",
+            ]
+        );
+        assert_eq!(final_totals(&pickups), [("鍛石【1】", 7), ("鍛石【2】", 3)]);
+    }
+}
@@ -1,12 +1,54 @@
-use std::ops::RangeInclusive;
+use std::{cell::RefCell, collections::HashMap, iter, marker::PhantomData, ops::RangeInclusive};
 
+use color_eyre::eyre;
 use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
 use elden_analyzer_video::capture::Frame;
 use imageproc::image::{Luma, Pixel as _, Rgb, RgbImage};
 use num_rational::Ratio;
 use num_traits::ToPrimitive as _;
 
-use crate::{util::ImageLogger, video_capture::FrameExt as _};
+use crate::{
+    image_process::overlay,
+    util::{Explainer, ImageLogger},
+    video_capture::FrameExt as _,
+};
+
+use super::DetectionScore;
+
+thread_local! {
+    /// Found ratios of areas measured in the current frame, cached while a [`RatioCacheGuard`] is alive
+    static RATIO_CACHE: RefCell<Option<RatioCache>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Default)]
+struct RatioCache {
+    /// Index of the frame the ratios are measured in
+    frame: Option<usize>,
+    ratios: HashMap<AreaKey, f32>,
+}
+
+/// Pixels of an area and their levels counted as found, which determine the found ratio regardless of the threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AreaKey {
+    level_width: u8,
+    /// Left, top, width and height of each rect
+    rects: Vec<(i32, i32, u32, u32)>,
+    found_range: Vec<LevelRanges>,
+}
+
+/// Guard returned by [`HistogramBasedComponentDetector::cache_ratios`].
+#[derive(Debug)]
+#[must_use]
+pub struct RatioCacheGuard {
+    /// The cache is of the thread creating the guard
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for RatioCacheGuard {
+    fn drop(&mut self) {
+        RATIO_CACHE.set(None);
+    }
+}
 
 #[derive(Debug)]
 pub struct HistogramBasedComponentDetectorBuilder {
@@ -30,33 +72,56 @@ impl HistogramBasedComponentDetectorBuilder {
                         .collect(),
                 )
             })
+            .collect::<Vec<(_, Vec<_>)>>();
+        let samples = areas
+            .iter()
+            .map(|(_, rects)| sample_points(rects))
             .collect();
         Some(HistogramBasedComponentDetector {
             base_rect,
             level_width: self.level_width,
             areas,
+            samples,
         })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct HistogramThreshold {
-    pub name: &'static str,
-    pub found_range: &'static [([RangeInclusive<u8>; 3], RangeInclusive<u8>)],
+    pub name: String,
+    /// Pixels in any of the ranges are counted as found
+    pub found_range: Vec<LevelRanges>,
     pub found_threshold: f32,
 }
 
-impl HistogramThreshold {
-    pub const fn new(
-        name: &'static str,
-        found_range: &'static [([RangeInclusive<u8>; 3], RangeInclusive<u8>)],
-        found_threshold: f32,
-    ) -> Self {
-        Self {
-            name,
-            found_range,
-            found_threshold,
-        }
+/// Ranges of levels of a pixel, all of which the pixel must be in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LevelRanges {
+    /// Any levels if `None`
+    pub rgb: Option<[RangeInclusive<u8>; 3]>,
+    pub luma: RangeInclusive<u8>,
+    /// Range of the difference between the highest and lowest levels of the RGB channels,
+    /// which tells tinted pixels from gray ones of similar levels
+    pub chroma: RangeInclusive<u8>,
+}
+
+impl LevelRanges {
+    fn contains_rgb(&self, p: Rgb<u8>) -> bool {
+        let [r, g, b] = p.0;
+        let chroma = r.max(g).max(b) - r.min(g).min(b);
+        self.rgb
+            .iter()
+            .all(|rgb| rgb.iter().zip(p.0).all(|(r, v)| r.contains(&v)))
+            && self.chroma.contains(&chroma)
+    }
+
+    /// Returns `true` if the ranges limit only luma, which can be checked without RGB.
+    fn is_luma_only(&self) -> bool {
+        self.rgb.is_none() && self.chroma == (0..=u8::MAX)
+    }
+
+    fn contains_luma(&self, p: Luma<u8>) -> bool {
+        self.luma.contains(&p.0[0])
     }
 }
 
@@ -65,36 +130,117 @@ pub struct HistogramBasedComponentDetector {
     base_rect: Rect,
     level_width: u8,
     areas: Vec<(HistogramThreshold, Vec<Rect>)>,
+    /// Pixels of each area sampled by the prefilter
+    samples: Vec<Vec<(u32, u32)>>,
+}
+
+/// Number of pixels sampled from each area by the prefilter
+const PREFILTER_SAMPLES: u32 = 256;
+/// Margin below thresholds within which sampled ratios are not rejected,
+/// since they are estimated from a few pixels
+const PREFILTER_MARGIN: f32 = 0.1;
+
+/// Returns pixels on a grid over `rects`, about [`PREFILTER_SAMPLES`] in total.
+fn sample_points(rects: &[Rect]) -> Vec<(u32, u32)> {
+    let area = rects.iter().map(|r| r.width() * r.height()).sum::<u32>();
+    let step = ((area as f32 / PREFILTER_SAMPLES as f32).sqrt().ceil() as usize).max(1);
+    rects
+        .iter()
+        .flat_map(|rect| {
+            let xs = (rect.left() as u32..=rect.right() as u32).step_by(step);
+            let ys = (rect.top() as u32..=rect.bottom() as u32).step_by(step);
+            ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+        })
+        .collect()
 }
 
 impl HistogramBasedComponentDetector {
-    pub fn detect(&self, frame: &Frame) -> bool {
+    /// Caches found ratios of areas in the current thread until the guard is dropped.
+    ///
+    /// Ratios are kept for the last frame measured, and shared by detectors whose areas differ only in thresholds,
+    /// so that the detectors of several configs measure each frame once, e.g. to sweep thresholds.
+    pub fn cache_ratios() -> RatioCacheGuard {
+        RATIO_CACHE.set(Some(RatioCache::default()));
+        RatioCacheGuard {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns `false` if the found ratio of any area is clearly below its threshold.
+    ///
+    /// Ratios are estimated from the luma of sampled pixels, which reads the luma plane without RGB conversion.
+    /// Found pixels must be in the luma ranges, so the estimates are not lower than the ratios except for sampling errors.
+    /// Frames that cannot be sampled are passed, so that the error is reported by [`detect`](Self::detect).
+    pub fn prefilter(&self, frame: &Frame) -> bool {
+        let explainer = Explainer::get();
+        iter::zip(&self.areas, &self.samples).all(|((thr, _), points)| {
+            let Ok(lumas) = frame.sample_luma(points) else {
+                return true;
+            };
+            let num_found = lumas
+                .iter()
+                .filter(|v| {
+                    let luma = Luma([self.to_level(**v)]);
+                    thr.found_range.iter().any(|r| r.contains_luma(luma))
+                })
+                .count();
+            let ratio = num_found as f32 / lumas.len().max(1) as f32;
+            let pass = ratio >= thr.found_threshold - PREFILTER_MARGIN;
+            if !pass {
+                explainer.note(|| {
+                    format!(
+                        "area {}: sampled luma ratio {ratio:.3} < threshold {:.3} - margin {PREFILTER_MARGIN} => rejected by prefilter",
+                        thr.name, thr.found_threshold
+                    )
+                });
+            }
+            pass
+        })
+    }
+
+    pub fn detect(&self, frame: &Frame) -> eyre::Result<bool> {
         let logger = ImageLogger::get();
+        let _stage = ImageLogger::enter_stage("histogram");
+        let explainer = Explainer::get();
 
         let base_rect = self.base_rect;
-        let img = tracing::trace_span!("rgb")
-            .in_scope(|| logger.log(frame.to_rgb_image_within(base_rect).unwrap()));
 
-        let u8_to_level = |v: u8| -> u8 { ((v as f32) / self.level_width as f32).round() as u8 };
+        let to_level_rgb = |p: Rgb<u8>| -> Rgb<u8> { self.to_level_pixel(p).0 };
+        let to_level_luma = |p: Rgb<u8>| -> Luma<u8> { self.to_level_pixel(p).1 };
         let level_to_u8 = |v: u8| -> u8 { v.saturating_mul(self.level_width) };
 
-        let to_level_rgb = |p: Rgb<u8>| -> Rgb<u8> { p.map(u8_to_level) };
-        let to_level_luma = |p: Rgb<u8>| -> Luma<u8> { p.to_luma().map(u8_to_level) };
-
-        let in_range_rgb = |range: &[([RangeInclusive<u8>; 3], RangeInclusive<u8>)], p: Rgb<u8>| {
-            let p = to_level_rgb(p);
-            range
-                .iter()
-                .any(|r| r.0.iter().zip(p.0).all(|(r, v)| r.contains(&v)))
-        };
+        let mut ratios = vec![];
+        for (idx, (thr, rects)) in self.areas.iter().enumerate() {
+            let found_ratio_val = self.found_ratio(frame, thr, rects)?;
+            let found = found_ratio_val >= thr.found_threshold;
+            tracing::trace!(
+                idx,
+                name = thr.name.as_str(),
+                accuracy = found_ratio_val,
+                found
+            );
+            explainer.note(|| {
+                let (op, res) = if found { (">=", "pass") } else { ("<", "fail") };
+                format!(
+                    "area {}: ratio {found_ratio_val:.3} {op} threshold {:.3} => {res}",
+                    thr.name, thr.found_threshold
+                )
+            });
+            ratios.push((thr, found_ratio_val, found));
+            if !found {
+                if idx + 1 < self.areas.len() {
+                    explainer.note(|| "remaining areas are not checked".into());
+                }
+                break;
+            }
+        }
 
-        let in_range_luma = |range: &[([RangeInclusive<u8>; 3], RangeInclusive<u8>)],
-                             p: Rgb<u8>| {
-            let v = to_level_luma(p);
-            range.iter().any(|r| r.1.contains(&v.0[0]))
-        };
+        if logger.enabled() {
+            // Converted only when displayed, as areas may be checked without RGB
+            let img =
+                tracing::trace_span!("rgb").in_scope(|| frame.to_rgb_image_within(base_rect))?;
+            let img = logger.log(img);
 
-        if logger.display_image() {
             let rgb_leveled = {
                 let mut img = img.clone();
                 img.pixels_mut()
@@ -122,14 +268,14 @@ impl HistogramBasedComponentDetector {
                             for y in area.top()..=area.bottom() {
                                 let y = (y - base_rect.top()) as u32;
                                 if rgb_out[(x, y)] != [255, 0, 0].into() {
-                                    if in_range_rgb(thr.found_range, img[(x, y)]) {
+                                    if self.in_range_rgb(&thr.found_range, img[(x, y)]) {
                                         rgb_out.put_pixel(x, y, rgb_leveled[(x, y)]);
                                     } else {
                                         rgb_out.put_pixel(x, y, [255, 0, 0].into());
                                     }
                                 }
                                 if gray_out[(x, y)] != [255, 0, 0].into() {
-                                    if in_range_luma(thr.found_range, img[(x, y)]) {
+                                    if self.in_range_luma(&thr.found_range, img[(x, y)]) {
                                         gray_out.put_pixel(x, y, gray_leveled[(x, y)]);
                                     } else {
                                         gray_out.put_pixel(x, y, [255, 0, 0].into());
@@ -139,35 +285,167 @@ impl HistogramBasedComponentDetector {
                         }
                     }
                 }
+                let caption = |kind: &str| {
+                    let ratios = ratios
+                        .iter()
+                        .map(|(thr, ratio, found)| {
+                            let op = if *found { ">=" } else { "<" };
+                            format!("{} {ratio:.3} {op} {:.3}", thr.name, thr.found_threshold)
+                        })
+                        .collect::<Vec<_>>();
+                    format!(
+                        "{kind} levels in range (red: out of range): {}",
+                        ratios.join(", ")
+                    )
+                };
                 logger.log(rgb_leveled);
-                logger.log(rgb_out);
+                logger.log_with_caption(rgb_out, || caption("RGB"));
                 logger.log(gray_leveled);
-                logger.log(gray_out);
+                logger.log_with_caption(gray_out, || caption("luma"));
             }
-        }
 
-        for (idx, (thr, rects)) in self.areas.iter().enumerate() {
-            let mut area = 0;
-            let mut num_found = 0;
-            for rect in rects {
-                let img = frame.to_rgb_image_within(*rect).unwrap();
-                area += (rect.width() * rect.height()) as i32;
-                for p in img.pixels() {
-                    if in_range_rgb(thr.found_range, *p) && in_range_luma(thr.found_range, *p) {
-                        num_found += 1;
-                    }
+            if !ratios.is_empty() {
+                const BAR_SIZE: (u32, u32) = (100, 8);
+                const BAR_GAP: u32 = 2;
+                let mut bars =
+                    RgbImage::new(BAR_SIZE.0, (BAR_SIZE.1 + BAR_GAP) * ratios.len() as u32);
+                for (idx, (thr, ratio, _)) in ratios.iter().enumerate() {
+                    let y = idx as u32 * (BAR_SIZE.1 + BAR_GAP);
+                    let rect = Rect::at(0, y as i32).of_size(BAR_SIZE.0, BAR_SIZE.1);
+                    overlay::draw_score_bar(&mut bars, rect, ratio / thr.found_threshold);
                 }
+                logger.log_with_caption(bars, || {
+                    let names = ratios
+                        .iter()
+                        .map(|(thr, _, _)| thr.name.as_str())
+                        .collect::<Vec<_>>();
+                    format!(
+                        "ratios relative to thresholds (white line), from top: {}",
+                        names.join(", ")
+                    )
+                });
             }
+        }
 
-            let found_ratio = Ratio::new(num_found, area);
-            let found_ratio_val = found_ratio.to_f32().unwrap();
-            let found = found_ratio_val >= thr.found_threshold;
-            tracing::trace!(idx, name = thr.name, accuracy = found_ratio_val, found);
-            if !found {
-                return false;
+        Ok(ratios.iter().all(|(_, _, found)| *found))
+    }
+
+    /// Returns the found ratio of every area.
+    pub fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        self.areas
+            .iter()
+            .map(|(thr, rects)| {
+                Ok(DetectionScore {
+                    name: thr.name.clone(),
+                    value: self.found_ratio(frame, thr, rects)?,
+                    threshold: thr.found_threshold,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the ratio of pixels in `rects` whose levels are in the found range,
+    /// reusing the one cached for the frame by [`cache_ratios`](Self::cache_ratios) if any.
+    fn found_ratio(
+        &self,
+        frame: &Frame,
+        thr: &HistogramThreshold,
+        rects: &[Rect],
+    ) -> eyre::Result<f32> {
+        let index = frame.position().index();
+        let key = RATIO_CACHE.with_borrow_mut(|cache| {
+            let cache = cache.as_mut()?;
+            if cache.frame != Some(index) {
+                cache.frame = Some(index);
+                cache.ratios.clear();
+            }
+            let key = AreaKey {
+                level_width: self.level_width,
+                rects: rects
+                    .iter()
+                    .map(|r| (r.left(), r.top(), r.width(), r.height()))
+                    .collect(),
+                found_range: thr.found_range.clone(),
+            };
+            Some(key)
+        });
+        let Some(key) = key else {
+            return self.measure_found_ratio(frame, thr, rects);
+        };
+        if let Some(ratio) =
+            RATIO_CACHE.with_borrow(|cache| cache.as_ref()?.ratios.get(&key).copied())
+        {
+            return Ok(ratio);
+        }
+        let ratio = self.measure_found_ratio(frame, thr, rects)?;
+        RATIO_CACHE.with_borrow_mut(|cache| {
+            if let Some(cache) = cache {
+                cache.ratios.insert(key, ratio);
+            }
+        });
+        Ok(ratio)
+    }
+
+    /// Measures the ratio of pixels in `rects` whose levels are in the found range.
+    ///
+    /// Areas with luma-only ranges are checked on the luma plane of the frame, skipping RGB conversion.
+    fn measure_found_ratio(
+        &self,
+        frame: &Frame,
+        thr: &HistogramThreshold,
+        rects: &[Rect],
+    ) -> eyre::Result<f32> {
+        let luma_only = thr.found_range.iter().all(LevelRanges::is_luma_only);
+        let mut area = 0;
+        let mut num_found = 0;
+        for rect in rects {
+            area += (rect.width() * rect.height()) as i32;
+            if luma_only {
+                let img = frame.to_luma_image_within(*rect)?;
+                num_found += img
+                    .pixels()
+                    .filter(|p| {
+                        let luma = p.map(|v| self.to_level(v));
+                        thr.found_range.iter().any(|r| r.contains_luma(luma))
+                    })
+                    .count() as i32;
+            } else {
+                let img = frame.to_rgb_image_within(*rect)?;
+                num_found += img
+                    .pixels()
+                    .filter(|p| self.in_range(&thr.found_range, **p))
+                    .count() as i32;
             }
         }
+        Ok(Ratio::new(num_found, area).to_f32().unwrap())
+    }
+
+    fn to_level(&self, v: u8) -> u8 {
+        ((v as f32) / self.level_width as f32).round() as u8
+    }
+
+    fn to_level_pixel(&self, p: Rgb<u8>) -> (Rgb<u8>, Luma<u8>) {
+        (
+            p.map(|v| self.to_level(v)),
+            p.to_luma().map(|v| self.to_level(v)),
+        )
+    }
+
+    /// Returns `true` if the levels of `p` are in all ranges of any of `ranges`.
+    fn in_range(&self, ranges: &[LevelRanges], p: Rgb<u8>) -> bool {
+        let (rgb, luma) = self.to_level_pixel(p);
+        ranges
+            .iter()
+            .any(|r| r.contains_rgb(rgb) && r.contains_luma(luma))
+    }
+
+    fn in_range_rgb(&self, ranges: &[LevelRanges], p: Rgb<u8>) -> bool {
+        let (rgb, _) = self.to_level_pixel(p);
+        ranges.iter().any(|r| r.contains_rgb(rgb))
+    }
 
-        true
+    fn in_range_luma(&self, ranges: &[LevelRanges], p: Rgb<u8>) -> bool {
+        let (_, luma) = self.to_level_pixel(p);
+        ranges.iter().any(|r| r.contains_luma(luma))
     }
 }
@@ -1,17 +1,16 @@
 use color_eyre::eyre;
 use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
 use elden_analyzer_video::capture::Frame;
-use imageproc::drawing;
 use num_rational::Ratio;
 use num_traits::ToPrimitive as _;
 
 use crate::{
-    image_process::{h_lines::HLineType, line_finder::LineFinder},
-    util::ImageLogger,
+    image_process::{h_lines::HLineType, line_finder::LineFinder, overlay},
+    util::{Explainer, ImageLogger},
     video_capture::FrameExt as _,
 };
 
-use super::{DetectComponent, DetectionKind};
+use super::{DetectComponent, DetectionKind, DetectionScore};
 
 pub struct LineBasedComponentDetectorBuilder {
     pub line_finder: LineFinder,
@@ -41,6 +40,13 @@ impl LineBasedComponentDetectorBuilder {
     }
 }
 
+/// Number of columns sampled from each line region by the prefilter
+const PREFILTER_COLUMNS: u32 = 16;
+/// Minimum difference of luma in a sampled column that may cross a line
+const PREFILTER_MIN_CONTRAST: u8 = 4;
+/// Margin below the possible threshold within which ratios of contrasted columns are not rejected
+const PREFILTER_MARGIN: f32 = 0.1;
+
 #[derive(Debug)]
 pub struct LineBasedComponentDetector {
     line_finder: LineFinder,
@@ -55,30 +61,41 @@ impl DetectComponent for LineBasedComponentDetector {
     #[tracing::instrument(level = "trace", skip_all)]
     fn detect(&self, frame: &Frame) -> eyre::Result<DetectionKind> {
         let logger = ImageLogger::get();
-        if logger.display_image() {
+        if logger.enabled() {
             let base_rect = self.base_rect;
 
-            let mut rgb_image = logger.log(frame.to_rgb_image_within(base_rect).unwrap());
+            let mut rgb_image = logger.log(frame.to_rgb_image_within(base_rect)?);
             for (_ty, rect) in &self.horizontal_line_clip_rect {
-                let rect = imageproc::rect::Rect::at(
-                    rect.left() - base_rect.left(),
-                    rect.top() - base_rect.top(),
-                )
-                .of_size(rect.width(), rect.height());
-                drawing::draw_hollow_rect_mut(&mut rgb_image, rect, [255, 0, 0].into());
+                let rect = Rect::at(rect.left() - base_rect.left(), rect.top() - base_rect.top())
+                    .of_size(rect.width(), rect.height());
+                overlay::draw_rect(&mut rgb_image, rect, [255, 0, 0].into(), 1);
             }
 
-            logger.log(rgb_image);
+            logger.log_with_caption(rgb_image, || {
+                format!(
+                    "line regions (found > {:.1}%, possible > {:.1}%)",
+                    self.found_threshold * 100.0,
+                    self.possible_threshold * 100.0
+                )
+            });
         }
 
+        let explainer = Explainer::get();
         let mut total_accuracy = Ratio::new(1, 1);
         for (ty, rect) in self.horizontal_line_clip_rect.iter().copied() {
-            let seg_len = self.line_finder.measure_in(frame, ty, rect);
-            let accuracy = Ratio::new(seg_len.filled_len(), seg_len.base_len());
+            let accuracy = self.filled_ratio(frame, ty, rect)?;
             let accuracy_val = accuracy.to_f32().unwrap();
             tracing::trace!(accuracy_val);
+            explainer
+                .note(|| format!("line {ty:?} at {rect}: filled {:.1}%", accuracy_val * 100.0));
 
             if accuracy_val < self.possible_threshold {
+                explainer.note(|| {
+                    format!(
+                        "filled ratio is below possible threshold {:.1}% => Absent",
+                        self.possible_threshold * 100.0
+                    )
+                });
                 return Ok(DetectionKind::Absent);
             }
             if accuracy < total_accuracy {
@@ -95,6 +112,75 @@ impl DetectComponent for LineBasedComponentDetector {
         } else {
             DetectionKind::Absent
         };
+        explainer.note(|| {
+            format!(
+                "minimum filled {:.1}% (found > {:.1}%, possible > {:.1}%) => {result}",
+                accuracy_val * 100.0,
+                self.found_threshold * 100.0,
+                self.possible_threshold * 100.0
+            )
+        });
         Ok(result)
     }
+
+    /// Rejects frames where too few sampled columns of any line region have contrast enough for a line.
+    ///
+    /// Columns are sampled on the luma plane, which is read without RGB conversion.
+    fn prefilter(&self, frame: &Frame) -> bool {
+        let explainer = Explainer::get();
+        self.horizontal_line_clip_rect.iter().all(|(ty, rect)| {
+            let columns = (0..PREFILTER_COLUMNS)
+                .map(|i| rect.left() as u32 + (2 * i + 1) * rect.width() / (2 * PREFILTER_COLUMNS))
+                .collect::<Vec<_>>();
+            let points = columns
+                .iter()
+                .flat_map(|&x| (rect.top() as u32..=rect.bottom() as u32).map(move |y| (x, y)))
+                .collect::<Vec<_>>();
+            // Frames that cannot be sampled are passed, so that the error is reported by `detect`
+            let Ok(lumas) = frame.sample_luma(&points) else {
+                return true;
+            };
+            let contrasted = lumas
+                .chunks(rect.height() as usize)
+                .filter(|column| {
+                    let (min, max) = column.iter().fold((u8::MAX, u8::MIN), |(min, max), v| {
+                        (min.min(*v), max.max(*v))
+                    });
+                    max.saturating_sub(min) >= PREFILTER_MIN_CONTRAST
+                })
+                .count();
+            let ratio = contrasted as f32 / PREFILTER_COLUMNS as f32;
+            let pass = ratio >= self.possible_threshold - PREFILTER_MARGIN;
+            if !pass {
+                explainer.note(|| {
+                    format!(
+                        "line {ty:?} at {rect}: contrasted columns {:.1}% => rejected by prefilter",
+                        ratio * 100.0
+                    )
+                });
+            }
+            pass
+        })
+    }
+
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>> {
+        self.horizontal_line_clip_rect
+            .iter()
+            .enumerate()
+            .map(|(idx, (ty, rect))| {
+                Ok(DetectionScore {
+                    name: format!("line{idx}"),
+                    value: self.filled_ratio(frame, *ty, *rect)?.to_f32().unwrap(),
+                    threshold: self.found_threshold,
+                })
+            })
+            .collect()
+    }
+}
+
+impl LineBasedComponentDetector {
+    fn filled_ratio(&self, frame: &Frame, ty: HLineType, rect: Rect) -> eyre::Result<Ratio<i32>> {
+        let seg_len = self.line_finder.measure_in(frame, ty, rect)?;
+        Ok(Ratio::new(seg_len.filled_len(), seg_len.base_len()))
+    }
 }
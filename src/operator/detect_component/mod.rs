@@ -3,6 +3,8 @@ use std::fmt;
 use color_eyre::eyre;
 use elden_analyzer_video::capture::Frame;
 
+use crate::i18n::Message;
+
 pub use self::{histogram_based::*, line_based::*};
 
 mod histogram_based;
@@ -10,6 +12,28 @@ mod line_based;
 
 pub trait DetectComponent: fmt::Debug + Send + Sync + 'static {
     fn detect(&self, frame: &Frame) -> eyre::Result<DetectionKind>;
+    /// Returns `false` if the component is clearly absent, checked by sampling a few pixels before `detect`.
+    fn prefilter(&self, _frame: &Frame) -> bool {
+        true
+    }
+    /// Measures the values compared with thresholds, including ones `detect` skips after failing.
+    fn scores(&self, frame: &Frame) -> eyre::Result<Vec<DetectionScore>>;
+}
+
+/// Value measured by a detector and the threshold it is compared with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionScore {
+    pub name: String,
+    pub value: f32,
+    /// Minimum value for the component to be found
+    pub threshold: f32,
+}
+
+impl DetectionScore {
+    /// Returns the value relative to the threshold, where `1.0` or more means found.
+    pub fn normalized(&self) -> f32 {
+        self.value / self.threshold
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -28,3 +52,15 @@ impl fmt::Display for DetectionKind {
         }
     }
 }
+
+impl DetectionKind {
+    /// Returns the kind shown to users in the current language.
+    pub fn label(self) -> &'static str {
+        let message = match self {
+            DetectionKind::Found => Message::new("found", "検出"),
+            DetectionKind::Possible => Message::new("possible", "候補"),
+            DetectionKind::Absent => Message::new("absent", "なし"),
+        };
+        message.get()
+    }
+}
@@ -0,0 +1,159 @@
+//! Inference of rarities of items from the colors of their names.
+
+use std::collections::HashMap;
+
+use color_eyre::eyre;
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+use elden_analyzer_video::capture::Frame;
+use imageproc::image::Rgb;
+
+use crate::{items::Rarity, util::Explainer, video_capture::FrameExt as _};
+
+/// Minimum value of the highest channel of pixels regarded as letters
+const MIN_LETTER_VALUE: u8 = 160;
+
+/// Minimum difference between the highest and lowest channels of letters regarded as tinted
+const MIN_TINT_CHROMA: u8 = 40;
+
+/// Minimum ratio of letters to the pixels of the text to infer the rarity
+const MIN_LETTER_RATIO: f32 = 0.01;
+
+/// Minimum ratio of letters tinted in a color to all letters to regard the name as tinted in the color
+const MIN_TINT_RATIO: f32 = 0.5;
+
+#[derive(Debug)]
+pub struct RarityClassifierBuilder {
+    pub base_rect: ClipRect,
+    pub text_rect: ClipRect,
+}
+
+impl RarityClassifierBuilder {
+    pub fn build(&self, frame_rect: Rect) -> Option<RarityClassifier> {
+        let base_rect = self.base_rect.clip(frame_rect)?;
+        let text_rect = self.text_rect.clip(base_rect)?;
+        Some(RarityClassifier { text_rect })
+    }
+}
+
+/// Infers the rarity of an item from the color of the letters of its name.
+#[derive(Debug)]
+pub struct RarityClassifier {
+    text_rect: Rect,
+}
+
+impl RarityClassifier {
+    /// Returns the rarity of the item whose name is in the text rect of `frame`,
+    /// or `None` if there are too few letters to tell.
+    pub fn classify(&self, frame: &Frame) -> eyre::Result<Option<Rarity>> {
+        let img = frame.to_rgb_image_within(self.text_rect)?;
+        let rarity = rarity_of_pixels(img.pixels().copied());
+        Explainer::get().note(|| match rarity {
+            Some(rarity) => format!("rarity: {rarity}"),
+            None => "rarity: unknown (too few letters)".into(),
+        });
+        Ok(rarity)
+    }
+}
+
+/// Returns the rarity of the name made of `pixels`, or `None` if there are too few letters to tell.
+///
+/// Bright pixels are regarded as letters, and the name is tinted in the color of most letters
+/// if they are at least [`MIN_TINT_RATIO`] of all letters.
+/// Dark pixels of the background are ignored, so that the name is not tinted by the scene behind it.
+pub fn rarity_of_pixels(pixels: impl IntoIterator<Item = Rgb<u8>>) -> Option<Rarity> {
+    let mut num_pixels = 0;
+    let mut num_letters = 0;
+    let mut tinted = HashMap::<Rarity, usize>::new();
+    for p in pixels {
+        num_pixels += 1;
+        let Some(tint) = letter_tint(p) else {
+            continue;
+        };
+        num_letters += 1;
+        if let Some(rarity) = tint {
+            *tinted.entry(rarity).or_default() += 1;
+        }
+    }
+    if num_letters == 0 || (num_letters as f32) < num_pixels as f32 * MIN_LETTER_RATIO {
+        return None;
+    }
+    let rarity = tinted
+        .into_iter()
+        .max_by_key(|(rarity, count)| (*count, *rarity as u8))
+        .filter(|(_, count)| *count as f32 >= num_letters as f32 * MIN_TINT_RATIO)
+        .map_or(Rarity::Common, |(rarity, _)| rarity);
+    Some(rarity)
+}
+
+/// Returns `None` if `p` is not a letter, `Some(None)` if it is a gray letter,
+/// or the rarity of the color of a tinted letter.
+fn letter_tint(p: Rgb<u8>) -> Option<Option<Rarity>> {
+    let [r, g, b] = p.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max < MIN_LETTER_VALUE {
+        return None;
+    }
+    if max - min < MIN_TINT_CHROMA {
+        return Some(None);
+    }
+    // Tints are told by the lowest channel: gold lacks blue, purple lacks green, and blue lacks red
+    let rarity = if b == min {
+        Rarity::Legendary
+    } else if g == min {
+        Rarity::Rare
+    } else {
+        Rarity::Uncommon
+    };
+    Some(Some(rarity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixels of a name with `num_letters` letter pixels of `letter` in 100 pixels of a dark background
+    fn name(letter: [u8; 3], num_letters: usize) -> impl Iterator<Item = Rgb<u8>> {
+        (0..100).map(move |i| {
+            if i < num_letters {
+                Rgb(letter)
+            } else {
+                Rgb([20, 40, 90])
+            }
+        })
+    }
+
+    #[test]
+    fn rarity_by_tint_of_letters() {
+        assert_eq!(
+            rarity_of_pixels(name([235, 235, 230], 20)),
+            Some(Rarity::Common)
+        );
+        assert_eq!(
+            rarity_of_pixels(name([230, 190, 110], 20)),
+            Some(Rarity::Legendary)
+        );
+        assert_eq!(
+            rarity_of_pixels(name([200, 120, 230], 20)),
+            Some(Rarity::Rare)
+        );
+        assert_eq!(
+            rarity_of_pixels(name([120, 170, 240], 20)),
+            Some(Rarity::Uncommon)
+        );
+    }
+
+    #[test]
+    fn rarity_ignores_background() {
+        // Blue background with white letters is not a blue name
+        let pixels = (0..100).map(|i| {
+            if i % 10 == 0 {
+                Rgb([230, 230, 230])
+            } else {
+                Rgb([60, 100, 150])
+            }
+        });
+        assert_eq!(rarity_of_pixels(pixels), Some(Rarity::Common));
+        assert_eq!(rarity_of_pixels(name([230, 190, 110], 0)), None);
+    }
+}
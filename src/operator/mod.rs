@@ -1,4 +1,5 @@
-pub use self::{detect_component::*, recognize_text::*};
+pub use self::{detect_component::*, infer_rarity::*, recognize_text::*};
 
 mod detect_component;
+mod infer_rarity;
 mod recognize_text;
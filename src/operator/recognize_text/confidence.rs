@@ -0,0 +1,293 @@
+use std::{fmt, iter, ops};
+
+use num_rational::Ratio;
+
+/// Denominator of confidences computed from floating-point numbers
+const DENOMINATOR: i32 = 10_000;
+
+/// Confidences of single observations are limited to this range when fused,
+/// so that no observation is regarded as certain
+const FUSE_RANGE: (f64, f64) = (0.01, 0.99);
+
+/// Probability that a recognized text is correct, between `0` and `1`.
+///
+/// Arithmetic operators clamp results to the range, so scale by ratios such as `conf * Ratio::new(4, 5)`
+/// rather than chaining operators like `conf * 4 / 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Confidence(Ratio<i32>);
+
+impl Confidence {
+    pub const ZERO: Self = Self(Ratio::ZERO);
+    pub const ONE: Self = Self(Ratio::ONE);
+
+    /// Creates a confidence from a percentage.
+    pub fn new(value: i32) -> Self {
+        assert!((0..=100).contains(&value));
+        Self(Ratio::new(value, 100))
+    }
+
+    /// Creates a confidence from a ratio, clamped between `0` and `1`.
+    pub fn clamped(value: Ratio<i32>) -> Self {
+        Self(value.clamp(Ratio::ZERO, Ratio::ONE))
+    }
+
+    fn from_f64(value: f64) -> Self {
+        let value = (value.clamp(0.0, 1.0) * f64::from(DENOMINATOR)).round() as i32;
+        Self(Ratio::new(value, DENOMINATOR))
+    }
+
+    pub fn as_ratio(self) -> Ratio<i32> {
+        self.0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        f64::from(*self.0.numer()) / f64::from(*self.0.denom())
+    }
+
+    /// Returns the mean of confidences weighted by the paired weights, or `None` if the total weight is zero.
+    pub fn weighted_mean(values: impl IntoIterator<Item = (Self, f64)>) -> Option<Self> {
+        let (sum, total_weight) = values
+            .into_iter()
+            .fold((0.0, 0.0), |(sum, total), (conf, weight)| {
+                (sum + conf.to_f64() * weight, total + weight)
+            });
+        (total_weight > 0.0).then(|| Self::from_f64(sum / total_weight))
+    }
+
+    /// Returns the highest confidence, or `None` if `values` is empty.
+    pub fn max_of(values: impl IntoIterator<Item = Self>) -> Option<Self> {
+        values.into_iter().max()
+    }
+
+    /// Fuses confidences of independent observations supporting the same result.
+    ///
+    /// Each observation multiplies the odds of the result, so that observations above `1/2` raise the confidence
+    /// and ones below `1/2` lower it. Returns `1/2` if `values` is empty.
+    pub fn fuse(values: impl IntoIterator<Item = Self>) -> Self {
+        let log_odds = values
+            .into_iter()
+            .map(|conf| {
+                let p = conf.to_f64().clamp(FUSE_RANGE.0, FUSE_RANGE.1);
+                (p / (1.0 - p)).ln()
+            })
+            .sum::<f64>();
+        Self::from_f64(1.0 / (1.0 + (-log_odds).exp()))
+    }
+}
+
+impl Default for Confidence {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", (self.0 * 100).round().to_integer())
+    }
+}
+
+impl<T> ops::Add<T> for Confidence
+where
+    Ratio<i32>: ops::Add<T, Output = Ratio<i32>>,
+{
+    type Output = Confidence;
+
+    fn add(self, rhs: T) -> Self::Output {
+        Self::clamped(self.0 + rhs)
+    }
+}
+
+impl<T> ops::Sub<T> for Confidence
+where
+    Ratio<i32>: ops::Sub<T, Output = Ratio<i32>>,
+{
+    type Output = Confidence;
+
+    fn sub(self, rhs: T) -> Self::Output {
+        Self::clamped(self.0 - rhs)
+    }
+}
+
+impl<T> ops::Mul<T> for Confidence
+where
+    Ratio<i32>: ops::Mul<T, Output = Ratio<i32>>,
+{
+    type Output = Confidence;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::clamped(self.0 * rhs)
+    }
+}
+
+impl<T> ops::Div<T> for Confidence
+where
+    Ratio<i32>: ops::Div<T, Output = Ratio<i32>>,
+{
+    type Output = Confidence;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Self::clamped(self.0 / rhs)
+    }
+}
+
+/// Confidences of repeated observations of the same result.
+///
+/// Confidences are summed in fixed-point numbers of `1 / 10000`,
+/// which neither overflow nor lose precision over spans of any realistic length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConfidenceTally {
+    count: u64,
+    sum: u64,
+}
+
+impl ConfidenceTally {
+    pub fn push(&mut self, conf: Confidence) {
+        let value = conf.as_ratio() * DENOMINATOR;
+        self.count += 1;
+        self.sum += value.round().to_integer() as u64;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean confidence, or `None` if nothing is observed.
+    pub fn mean(&self) -> Option<Confidence> {
+        (self.count > 0).then(|| Self::ratio(self.sum, self.count * DENOMINATOR as u64))
+    }
+
+    /// Returns the expected number of correct observations.
+    pub fn weight(&self) -> f64 {
+        self.sum as f64 / f64::from(DENOMINATOR)
+    }
+
+    /// Returns the share of this tally in the weight of `total`, or zero if `total` has no weight.
+    pub fn share_in(&self, total: &Self) -> Confidence {
+        if total.sum == 0 {
+            return Confidence::ZERO;
+        }
+        Self::ratio(self.sum, total.sum)
+    }
+
+    fn ratio(numer: u64, denom: u64) -> Confidence {
+        let numer = u128::from(numer) * DENOMINATOR as u128;
+        let value = (numer + u128::from(denom) / 2) / u128::from(denom);
+        Confidence::clamped(Ratio::new(
+            value.min(DENOMINATOR as u128) as i32,
+            DENOMINATOR,
+        ))
+    }
+}
+
+impl ops::Add for ConfidenceTally {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            count: self.count + rhs.count,
+            sum: self.sum + rhs.sum,
+        }
+    }
+}
+
+impl iter::Sum for ConfidenceTally {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), ops::Add::add)
+    }
+}
+
+impl<'a> iter::Sum<&'a ConfidenceTally> for ConfidenceTally {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamped_arithmetic() {
+        assert_eq!(Confidence::new(90) + Ratio::new(1, 5), Confidence::ONE);
+        assert_eq!(Confidence::new(10) - Ratio::new(1, 5), Confidence::ZERO);
+        assert_eq!(Confidence::new(90) * Ratio::new(4, 5), Confidence::new(72));
+        assert_eq!(Confidence::new(60) * 2, Confidence::ONE);
+    }
+
+    #[test]
+    fn weighted_mean() {
+        let mean =
+            Confidence::weighted_mean([(Confidence::new(90), 1.0), (Confidence::new(30), 2.0)]);
+        assert_eq!(mean, Some(Confidence::new(50)));
+        assert_eq!(Confidence::weighted_mean([]), None);
+        assert_eq!(
+            Confidence::weighted_mean([(Confidence::new(90), 0.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn max_of() {
+        let max = Confidence::max_of([
+            Confidence::new(30),
+            Confidence::new(90),
+            Confidence::new(60),
+        ]);
+        assert_eq!(max, Some(Confidence::new(90)));
+        assert_eq!(Confidence::max_of([]), None);
+    }
+
+    #[test]
+    fn fuse() {
+        assert_eq!(Confidence::fuse([]), Confidence::new(50));
+        assert_eq!(Confidence::fuse([Confidence::new(80)]), Confidence::new(80));
+        // Agreeing observations reinforce each other
+        assert!(Confidence::fuse([Confidence::new(80), Confidence::new(80)]) > Confidence::new(90));
+        assert!(Confidence::fuse([Confidence::new(30), Confidence::new(30)]) < Confidence::new(30));
+        // Opposite observations cancel out
+        assert_eq!(
+            Confidence::fuse([Confidence::new(80), Confidence::new(20)]),
+            Confidence::new(50)
+        );
+        // No observation is certain
+        assert_eq!(
+            Confidence::fuse([Confidence::ONE, Confidence::ZERO]),
+            Confidence::new(50)
+        );
+    }
+
+    #[test]
+    fn observations() {
+        let mut obs = ConfidenceTally::default();
+        assert_eq!(obs.mean(), None);
+        obs.push(Confidence::new(90));
+        obs.push(Confidence::new(30));
+        assert_eq!(obs.count(), 2);
+        assert_eq!(obs.mean(), Some(Confidence::new(60)));
+        assert!((obs.weight() - 1.2).abs() < 1e-9);
+
+        let mut other = ConfidenceTally::default();
+        other.push(Confidence::new(60));
+        let total = [obs, other].iter().sum::<ConfidenceTally>();
+        assert_eq!(total.count(), 3);
+        assert_eq!(obs.share_in(&total).to_string(), "67");
+        assert_eq!(other.share_in(&total).to_string(), "33");
+        assert_eq!(obs.share_in(&ConfidenceTally::default()), Confidence::ZERO);
+    }
+
+    #[test]
+    fn long_tally() {
+        // Confidences with various denominators over a span much longer than any real one
+        let mut tally = ConfidenceTally::default();
+        for i in 0..1_000_000 {
+            tally.push(Confidence::new(90) * Ratio::new(1 + i % 7, 7));
+        }
+        assert_eq!(tally.count(), 1_000_000);
+        let mean = tally.mean().unwrap();
+        assert!(
+            Confidence::new(51) <= mean && mean <= Confidence::new(52),
+            "{mean}"
+        );
+    }
+}
@@ -0,0 +1,703 @@
+//! Recognition of fixed-style HUD texts by matching against an atlas of glyphs of the game font, without Tesseract.
+//!
+//! Texts of the HUD such as counts are drawn in fixed fonts with anti-aliased edges, which generic OCR misreads
+//! in small crops. Here pixels are weighted by their coverage of the text color instead of binarized,
+//! and glyphs of the atlas slide along the line to find the sequence of glyphs matching it best,
+//! so that touching glyphs need no segmentation.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::{self, bail, OptionExt as _, WrapErr as _};
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+use elden_analyzer_video::capture::Frame;
+use imageproc::image::{
+    imageops::{self, FilterType},
+    GrayImage, Luma,
+};
+use num_rational::Ratio;
+
+use crate::{image_process::tesseract::Tesseract, util::Explainer, video_capture::FrameExt as _};
+
+use super::{Confidence, ExtractText, PostProcess, Recognition};
+
+/// Height lines and glyphs are scaled to before matching
+pub const GLYPH_HEIGHT: u32 = 24;
+
+/// Rows whose coverage is below this ratio of the densest row are outside the line
+const ROW_RATIO: f32 = 0.05;
+
+/// Columns whose coverage is below this ratio of the densest column separate glyphs of training samples
+const GAP_RATIO: f32 = 0.15;
+
+/// Segments of training samples wider than this ratio of the height are split at the faintest column
+const MAX_GLYPH_ASPECT: f32 = 0.85;
+
+/// Segments of training samples with less coverage than this ratio of the densest segment are noise
+const MIN_INK_RATIO: f32 = 0.1;
+
+/// Correlation below which matching a glyph is worse than leaving its columns unmatched
+const MIN_USEFUL_SCORE: f32 = 0.3;
+
+/// Minimum correlation with the best glyph for it to be found
+const MIN_SCORE: f32 = 0.75;
+
+/// Minimum difference of correlations between the best and the second best characters for a glyph to be found
+const MIN_MARGIN: f32 = 0.05;
+
+/// Maximum columns of a glyph overlapping the previous one
+const MAX_OVERLAP: usize = 3;
+
+/// Columns around a matched glyph where other characters are compared with it
+const MARGIN_RADIUS: usize = 2;
+
+/// Maximum ratio of the coverage not matched by any glyph for a text to be found
+const MAX_UNMATCHED_INK: f32 = 0.1;
+
+/// Gaps between glyphs at least this ratio of the height are spaces
+const SPACE_RATIO: f32 = 0.5;
+
+/// Atlas of glyphs of the game font.
+///
+/// An atlas is a TSV index file of glyphs and a PNG image with the same name, such as `glyphs.tsv` and `glyphs.png`.
+/// Each line of the index is a character and the rect of its glyph in the image (`WIDTHxHEIGHT+X+Y`),
+/// separated by a tab.
+/// Glyphs are light on a dark background and cropped to the line, not to the glyph,
+/// so that glyphs such as `×` keep their positions in the line.
+/// A character may have several glyphs. Atlases are made from crops of counts by `train-digits`.
+///
+/// ```text
+/// # char rect
+/// 0    14x24+0+0
+/// 1    9x24+14+0
+/// ×    12x24+23+0
+/// ```
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    glyphs: Vec<Glyph>,
+}
+
+#[derive(Debug)]
+struct Glyph {
+    ch: char,
+    coverage: Line,
+    /// `coverage` normalized for correlations
+    normalized: Vec<f32>,
+}
+
+impl Glyph {
+    fn new(ch: char, coverage: Line) -> Self {
+        let normalized = normalize(&coverage.values);
+        Self {
+            ch,
+            coverage,
+            normalized,
+        }
+    }
+}
+
+/// Glyph matched in a line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphMatch {
+    pub ch: char,
+    /// Correlation with the glyph, between `-1` and `1`
+    pub score: f32,
+    /// Difference of correlations to the best glyph of other characters at the same position
+    pub margin: f32,
+}
+
+impl GlyphMatch {
+    pub fn is_found(&self) -> bool {
+        self.score >= MIN_SCORE && self.margin >= MIN_MARGIN
+    }
+}
+
+/// Glyphs matched in a line, from left to right.
+#[derive(Debug, Clone, Default)]
+pub struct MatchedText {
+    pub glyphs: Vec<GlyphMatch>,
+    /// Ratio of the coverage of the line not matched by any glyph
+    pub unmatched_ink: f32,
+}
+
+impl MatchedText {
+    pub fn text(&self) -> String {
+        self.glyphs.iter().map(|g| g.ch).collect()
+    }
+
+    /// Returns the lowest correlation of the glyphs, or `0` if there is none.
+    pub fn score(&self) -> f32 {
+        if self.glyphs.is_empty() {
+            return 0.0;
+        }
+        self.glyphs.iter().map(|g| g.score).fold(1.0, f32::min)
+    }
+
+    /// Returns `true` if all glyphs are found and the whole line is matched.
+    pub fn is_found(&self) -> bool {
+        !self.glyphs.is_empty()
+            && self.glyphs.iter().all(GlyphMatch::is_found)
+            && self.unmatched_ink <= MAX_UNMATCHED_INK
+    }
+}
+
+impl GlyphAtlas {
+    /// Loads the atlas from the index file at `index` and the image next to it.
+    pub fn load(index: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(index)
+            .wrap_err_with(|| format!("failed to read glyph atlas: {}", index.display()))?;
+        let image_path = index.with_extension("png");
+        let image = imageproc::image::open(&image_path)
+            .wrap_err_with(|| format!("failed to read glyph atlas: {}", image_path.display()))?
+            .to_luma8();
+        let image_rect = Rect::at(0, 0).of_size(image.width(), image.height());
+
+        let mut glyphs = vec![];
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse = || {
+                let (ch, rect) = line
+                    .split_once('\t')
+                    .ok_or_eyre("expected `CHAR<TAB>RECT`")?;
+                let mut chars = ch.chars();
+                let (Some(ch), None) = (chars.next(), chars.next()) else {
+                    bail!("not a character: {ch:?}");
+                };
+                let rect = rect.parse::<Rect>()?;
+                if rect.intersect(image_rect) != Some(rect) {
+                    bail!("rect `{rect}` is out of the image");
+                }
+                Ok((ch, rect))
+            };
+            let (ch, rect) = parse().wrap_err_with(|| {
+                format!("invalid glyph at line {} of {}", i + 1, index.display())
+            })?;
+            let glyph = imageops::crop_imm(
+                &image,
+                rect.left() as u32,
+                rect.top() as u32,
+                rect.width(),
+                rect.height(),
+            )
+            .to_image();
+            glyphs.push(Glyph::new(ch, Line::from_image(&glyph)));
+        }
+        if glyphs.is_empty() {
+            bail!("no glyphs in {}", index.display());
+        }
+        Ok(Self { glyphs })
+    }
+
+    /// Makes an atlas from crops of texts labeled with the texts, such as `12`.
+    ///
+    /// Glyphs are split at gaps between them and averaged for each character.
+    /// Crops whose number of glyphs differs from the label are skipped, and their number is returned with the atlas.
+    pub fn train<'a>(samples: impl IntoIterator<Item = (&'a GrayImage, &'a str)>) -> (Self, usize) {
+        let mut segments = BTreeMap::<char, Vec<Line>>::new();
+        let mut skipped = 0;
+        for (image, label) in samples {
+            let glyphs = Line::new(image)
+                .map(|line| line.segments())
+                .unwrap_or_default();
+            if glyphs.len() != label.chars().count() {
+                skipped += 1;
+                continue;
+            }
+            for (ch, glyph) in label.chars().zip(glyphs) {
+                segments.entry(ch).or_default().push(glyph);
+            }
+        }
+        let glyphs = segments
+            .into_iter()
+            .map(|(ch, mut lines)| {
+                lines.sort_by_key(|line| line.width);
+                let width = lines[lines.len() / 2].width;
+                let mut sum = vec![0.0; width * GLYPH_HEIGHT as usize];
+                for line in &lines {
+                    let line = line.resized(width);
+                    sum.iter_mut().zip(&line.values).for_each(|(s, v)| *s += v);
+                }
+                let values = sum.into_iter().map(|v| v / lines.len() as f32).collect();
+                Glyph::new(ch, Line { width, values })
+            })
+            .collect();
+        (Self { glyphs }, skipped)
+    }
+
+    /// Writes the index file at `index` and the image next to it, returning the path of the image.
+    pub fn save(&self, index: &Path) -> eyre::Result<PathBuf> {
+        let width = self.glyphs.iter().map(|g| g.coverage.width as u32).sum();
+        let mut image = GrayImage::new(width, GLYPH_HEIGHT);
+        let mut text = "# char\trect\n".to_owned();
+        let mut x = 0;
+        for glyph in &self.glyphs {
+            let glyph_image = glyph.coverage.to_image();
+            imageops::replace(&mut image, &glyph_image, i64::from(x), 0);
+            let rect = Rect::at(x as i32, 0).of_size(glyph_image.width(), GLYPH_HEIGHT);
+            writeln!(text, "{}\t{rect}", glyph.ch).unwrap();
+            x += glyph_image.width();
+        }
+
+        if let Some(dir) = index.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let image_path = index.with_extension("png");
+        image
+            .save(&image_path)
+            .wrap_err_with(|| format!("failed to write {}", image_path.display()))?;
+        fs::write(index, text).wrap_err_with(|| format!("failed to write {}", index.display()))?;
+        Ok(image_path)
+    }
+
+    /// Returns the characters of the glyphs, in order of the atlas.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.iter().map(|g| g.ch)
+    }
+
+    /// Recognizes a line of light text on a dark background in `image`.
+    ///
+    /// Among sequences of glyphs placed side by side, the one covering the line with the highest correlations wins.
+    pub fn recognize(&self, image: &GrayImage) -> MatchedText {
+        let Some(line) = Line::new(image) else {
+            return MatchedText::default();
+        };
+        let width = line.width;
+        let scores = self
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let w = glyph.coverage.width;
+                (0..(width + 1).saturating_sub(w))
+                    .map(|x| correlation(line.window(x..x + w), &glyph.normalized))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let inks = (0..width).map(|x| line.ink(x)).collect::<Vec<_>>();
+
+        // Best total gain of sequences covering the columns up to each position
+        #[derive(Clone, Copy)]
+        enum Step {
+            Skip,
+            Glyph {
+                glyph: usize,
+                start: usize,
+                from: usize,
+            },
+        }
+        let mut best = vec![(f32::NEG_INFINITY, Step::Skip); width + 1];
+        best[0].0 = 0.0;
+        for x in 0..width {
+            let gain = best[x].0;
+            if gain - inks[x] > best[x + 1].0 {
+                best[x + 1] = (gain - inks[x], Step::Skip);
+            }
+            // Anti-aliased edges of touching glyphs overlap
+            for start in x.saturating_sub(MAX_OVERLAP)..=x {
+                for (i, glyph) in self.glyphs.iter().enumerate() {
+                    let Some(&score) = scores[i].get(start) else {
+                        continue;
+                    };
+                    let end = start + glyph.coverage.width;
+                    let gain = gain + (score - MIN_USEFUL_SCORE) * glyph.coverage.width as f32;
+                    if end > x && gain > best[end].0 {
+                        let step = Step::Glyph {
+                            glyph: i,
+                            start,
+                            from: x,
+                        };
+                        best[end] = (gain, step);
+                    }
+                }
+            }
+        }
+
+        let mut placed = vec![];
+        let mut unmatched = 0.0;
+        let mut x = width;
+        while x > 0 {
+            match best[x].1 {
+                Step::Skip => {
+                    x -= 1;
+                    unmatched += inks[x];
+                }
+                Step::Glyph { glyph, start, from } => {
+                    placed.push((start, glyph));
+                    x = from;
+                }
+            }
+        }
+        placed.reverse();
+
+        let mut glyphs = vec![];
+        let mut last_end = None;
+        for (x, i) in placed {
+            let glyph = &self.glyphs[i];
+            let gap = last_end.map_or(0, |end| x.saturating_sub(end));
+            if gap as f32 >= GLYPH_HEIGHT as f32 * SPACE_RATIO {
+                glyphs.push(GlyphMatch {
+                    ch: ' ',
+                    score: 1.0,
+                    margin: 1.0,
+                });
+            }
+            let score = scores[i][x];
+            let second = self
+                .glyphs
+                .iter()
+                .zip(&scores)
+                .filter(|(other, _)| other.ch != glyph.ch)
+                .flat_map(|(_, scores)| {
+                    let start = x.saturating_sub(MARGIN_RADIUS);
+                    scores.iter().skip(start).take(MARGIN_RADIUS * 2 + 1)
+                })
+                .copied()
+                .fold(-1.0, f32::max);
+            glyphs.push(GlyphMatch {
+                ch: glyph.ch,
+                score,
+                margin: score - second,
+            });
+            last_end = Some(x + glyph.coverage.width);
+        }
+
+        let total = inks.iter().sum::<f32>();
+        MatchedText {
+            glyphs,
+            unmatched_ink: if total > 0.0 { unmatched / total } else { 0.0 },
+        }
+    }
+}
+
+/// Builder of [`GlyphMatchExtractor`].
+#[derive(Debug)]
+pub struct GlyphMatchExtractorBuilder {
+    pub base_rect: ClipRect,
+    pub text_rect: ClipRect,
+    pub atlas: Arc<GlyphAtlas>,
+    pub post_process: PostProcess,
+}
+
+impl GlyphMatchExtractorBuilder {
+    pub fn build(&self, frame_rect: Rect) -> Option<GlyphMatchExtractor> {
+        let base_rect = self.base_rect.clip(frame_rect)?;
+        let text_rect = self.text_rect.clip(base_rect)?;
+        Some(GlyphMatchExtractor {
+            text_rect,
+            atlas: Arc::clone(&self.atlas),
+            post_process: self.post_process,
+        })
+    }
+}
+
+/// Extractor of texts recognized by matching against a [`GlyphAtlas`] instead of Tesseract.
+///
+/// Texts are post-processed by [`PostProcess::run_matched`], skipping corrections of misread characters.
+#[derive(Debug)]
+pub struct GlyphMatchExtractor {
+    text_rect: Rect,
+    atlas: Arc<GlyphAtlas>,
+    post_process: PostProcess,
+}
+
+impl ExtractText for GlyphMatchExtractor {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn extract_text(
+        &self,
+        _tess: &mut Tesseract,
+        frame: &Frame,
+        num_chars: Option<usize>,
+    ) -> eyre::Result<Recognition> {
+        let explainer = Explainer::get();
+        explainer.note(|| format!("text rect {}, matched with glyph atlas", self.text_rect));
+
+        let image = frame.to_gray_image_within(self.text_rect)?;
+        let matched = self.atlas.recognize(&image);
+        explainer.note(|| {
+            let glyphs = matched
+                .glyphs
+                .iter()
+                .map(|g| format!("{:?} ({:.2}, margin {:.2})", g.ch, g.score, g.margin))
+                .collect::<Vec<_>>();
+            format!(
+                "glyphs: [{}], unmatched {:.0}%",
+                glyphs.join(", "),
+                matched.unmatched_ink * 100.0
+            )
+        });
+
+        let text = matched.text();
+        let score = (matched.score() * 100.0).round() as i32;
+        let conf = Confidence::clamped(Ratio::new(score, 100));
+        if !matched.is_found() {
+            explainer.note(|| "line is not matched by glyphs => possible".into());
+            return Ok(Recognition::Possible(text, conf));
+        }
+
+        let res = explainer.scope(
+            || format!("post-process {:?}", self.post_process),
+            || self.post_process.run_matched(&text, conf),
+        );
+        explainer.note(|| format!("post-processed: {res}"));
+        let res = match res {
+            Recognition::Found(text, conf)
+                if num_chars.is_some_and(|n| text.chars().count() != n) =>
+            {
+                explainer
+                    .note(|| format!("expected {} characters => possible", num_chars.unwrap()));
+                Recognition::Possible(text, conf)
+            }
+            res => res,
+        };
+        Ok(res)
+    }
+}
+
+/// Coverage of the text color of a line scaled to [`GLYPH_HEIGHT`], column by column.
+///
+/// Coverages are between `0` (background) and `1` (text), and anti-aliased edges have partial coverages
+/// rather than being rounded to either side.
+#[derive(Debug, Clone)]
+struct Line {
+    width: usize,
+    values: Vec<f32>,
+}
+
+impl Line {
+    /// Crops `image` to the rows of the text and scales it, or returns `None` if there is no text.
+    fn new(image: &GrayImage) -> Option<Self> {
+        let (min, max) = image.pixels().fold((u8::MAX, u8::MIN), |(min, max), p| {
+            (min.min(p[0]), max.max(p[0]))
+        });
+        if max <= min {
+            return None;
+        }
+        let range = f32::from(max - min);
+        let coverage = |x, y| f32::from(image.get_pixel(x, y)[0] - min) / range;
+
+        let rows = (0..image.height())
+            .map(|y| (0..image.width()).map(|x| coverage(x, y)).sum::<f32>())
+            .collect::<Vec<_>>();
+        let densest = rows.iter().copied().fold(0.0, f32::max);
+        let top = rows.iter().position(|ink| *ink >= densest * ROW_RATIO)? as u32;
+        let bottom = rows.iter().rposition(|ink| *ink >= densest * ROW_RATIO)? as u32 + 1;
+
+        let cropped = GrayImage::from_fn(image.width(), bottom - top, |x, y| {
+            Luma([(coverage(x, top + y) * 255.0).round() as u8])
+        });
+        Some(Self::from_image(&cropped))
+    }
+
+    /// Scales coverages in `image` to [`GLYPH_HEIGHT`] keeping the aspect ratio.
+    fn from_image(image: &GrayImage) -> Self {
+        let scale = GLYPH_HEIGHT as f32 / image.height() as f32;
+        let width = ((image.width() as f32 * scale).round() as u32).max(1);
+        let scaled = imageops::resize(image, width, GLYPH_HEIGHT, FilterType::Triangle);
+        let values = (0..width)
+            .flat_map(|x| (0..GLYPH_HEIGHT).map(move |y| (x, y)))
+            .map(|(x, y)| f32::from(scaled.get_pixel(x, y)[0]) / 255.0)
+            .collect();
+        Self {
+            width: width as usize,
+            values,
+        }
+    }
+
+    fn to_image(&self) -> GrayImage {
+        GrayImage::from_fn(self.width as u32, GLYPH_HEIGHT, |x, y| {
+            let v = self.values[x as usize * GLYPH_HEIGHT as usize + y as usize];
+            Luma([(v * 255.0).round() as u8])
+        })
+    }
+
+    fn resized(&self, width: usize) -> Self {
+        if width == self.width {
+            return self.clone();
+        }
+        let image = imageops::resize(
+            &self.to_image(),
+            width as u32,
+            GLYPH_HEIGHT,
+            FilterType::Triangle,
+        );
+        Self::from_image(&image)
+    }
+
+    fn window(&self, xs: Range<usize>) -> &[f32] {
+        let h = GLYPH_HEIGHT as usize;
+        &self.values[xs.start * h..xs.end * h]
+    }
+
+    /// Returns the mean coverage of the column at `x`.
+    fn ink(&self, x: usize) -> f32 {
+        self.window(x..x + 1).iter().sum::<f32>() / GLYPH_HEIGHT as f32
+    }
+
+    /// Splits the line into glyphs at faint columns, from left to right.
+    fn segments(&self) -> Vec<Line> {
+        let inks = (0..self.width).map(|x| self.ink(x)).collect::<Vec<_>>();
+        let densest = inks.iter().copied().fold(0.0, f32::max);
+
+        let mut segments = vec![];
+        let mut start = None;
+        for (x, &ink) in inks.iter().enumerate() {
+            match (start, ink >= densest * GAP_RATIO) {
+                (None, true) => start = Some(x),
+                (Some(s), false) => {
+                    segments.push(s..x);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            segments.push(s..self.width);
+        }
+
+        let max_width = (GLYPH_HEIGHT as f32 * MAX_GLYPH_ASPECT).ceil() as usize;
+        let mut split = vec![];
+        while let Some(segment) = segments.pop() {
+            let width = segment.end - segment.start;
+            if width <= max_width || width < 4 {
+                split.push(segment);
+                continue;
+            }
+            // Touching glyphs are split at the faintest column away from the edges
+            let margin = width / 4;
+            let x = (segment.start + margin..segment.end - margin)
+                .min_by(|a, b| inks[*a].total_cmp(&inks[*b]))
+                .unwrap();
+            segments.push(segment.start..x);
+            segments.push(x..segment.end);
+        }
+        split.sort_by_key(|segment| segment.start);
+
+        let segment_inks = split
+            .iter()
+            .map(|xs| inks[xs.clone()].iter().sum::<f32>())
+            .collect::<Vec<_>>();
+        let max_ink = segment_inks.iter().copied().fold(0.0, f32::max);
+        split
+            .into_iter()
+            .zip(segment_inks)
+            .filter(|(_, ink)| *ink >= max_ink * MIN_INK_RATIO)
+            .map(|(xs, _)| Line {
+                width: xs.len(),
+                values: self.window(xs).to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the correlation between `window` and normalized values of a glyph.
+fn correlation(window: &[f32], normalized: &[f32]) -> f32 {
+    normalize(window)
+        .iter()
+        .zip(normalized)
+        .map(|(a, b)| a * b)
+        .sum()
+}
+
+/// Scales values to zero mean and unit norm, so that dot products are correlations.
+fn normalize(values: &[f32]) -> Vec<f32> {
+    let mean = values.iter().sum::<f32>() / values.len().max(1) as f32;
+    let centered = values.iter().map(|v| v - mean).collect::<Vec<_>>();
+    let norm = centered.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm <= f32::EPSILON {
+        return vec![0.0; values.len()];
+    }
+    centered.into_iter().map(|v| v / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Digits of a 3x5 pixel font
+    const FONT: [[&str; 5]; 10] = [
+        ["###", "#.#", "#.#", "#.#", "###"],
+        [".#.", "##.", ".#.", ".#.", "###"],
+        ["###", "..#", "###", "#..", "###"],
+        ["###", "..#", "###", "..#", "###"],
+        ["#.#", "#.#", "###", "..#", "..#"],
+        ["###", "#..", "###", "..#", "###"],
+        ["###", "#..", "###", "#.#", "###"],
+        ["###", "..#", ".#.", ".#.", ".#."],
+        ["###", "#.#", "###", "#.#", "###"],
+        ["###", "#.#", "###", "..#", "###"],
+    ];
+
+    /// Renders digits with the font scaled up smoothly, so that edges are anti-aliased.
+    ///
+    /// Glyphs are `gap` pixels apart before scaling.
+    fn render(text: &str, gap: u32) -> GrayImage {
+        let pitch = 3 + gap;
+        let width = text.len() as u32 * pitch + 2;
+        let image = GrayImage::from_fn(width, 7, |x, y| {
+            let (col, row) = (x as i32 - 1, y as i32 - 1);
+            let ink = col >= 0
+                && (0..5).contains(&row)
+                && (col as u32 % pitch) < 3
+                && text
+                    .as_bytes()
+                    .get(col as usize / pitch as usize)
+                    .is_some_and(|ch| {
+                        let glyph = FONT[usize::from(ch - b'0')];
+                        glyph[row as usize].as_bytes()[col as usize % pitch as usize] == b'#'
+                    });
+            Luma([if ink { 230 } else { 20 }])
+        });
+        imageops::resize(&image, width * 6, 7 * 6, FilterType::Triangle)
+    }
+
+    fn trained() -> GlyphAtlas {
+        let samples = [render("0123456789", 1), render("98", 1), render("1", 1)];
+        let labels = ["0123456789", "98", "12"];
+        let (atlas, skipped) = GlyphAtlas::train(samples.iter().zip(labels));
+        assert_eq!(skipped, 1);
+        atlas
+    }
+
+    #[test]
+    fn segment_anti_aliased_glyphs() {
+        let segments = |image: &GrayImage| Line::new(image).map_or(0, |line| line.segments().len());
+        assert_eq!(segments(&render("1234567890", 1)), 10);
+        assert_eq!(segments(&render("7", 1)), 1);
+        assert_eq!(segments(&GrayImage::new(10, 10)), 0);
+    }
+
+    #[test]
+    fn recognize_by_sliding_glyphs() {
+        let atlas = trained();
+        assert_eq!(atlas.chars().collect::<String>(), "0123456789");
+
+        let matched = atlas.recognize(&render("9076", 1));
+        assert_eq!(matched.text(), "9076");
+        assert!(matched.is_found(), "{matched:?}");
+
+        // Glyphs touching each other without gaps
+        let matched = atlas.recognize(&render("4821", 0));
+        assert_eq!(matched.text(), "4821");
+
+        assert!(!atlas.recognize(&GrayImage::new(30, 10)).is_found());
+    }
+
+    #[test]
+    fn save_and_load_atlas() {
+        let dir = std::env::temp_dir().join(format!("glyph-atlas-{}", std::process::id()));
+        let index = dir.join("glyphs.tsv");
+        trained().save(&index).unwrap();
+        let atlas = GlyphAtlas::load(&index).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(atlas.chars().collect::<String>(), "0123456789");
+        assert_eq!(atlas.recognize(&render("305", 1)).text(), "305");
+    }
+}
@@ -1,24 +1,506 @@
-use std::{borrow::Cow, collections::HashSet, sync::LazyLock};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock, RwLock},
+    time::{Duration, Instant},
+};
 
-use regex::{Captures, Regex};
+use aho_corasick::AhoCorasick;
+use color_eyre::eyre::{self, OptionExt as _, WrapErr as _};
+use num_rational::Ratio;
+use regex::{Captures, Regex, RegexSet};
+
+use crate::{
+    areas,
+    i18n::Lang,
+    items::{ItemDictionary, ItemId},
+    util::Explainer,
+};
 
 use super::{Confidence, Recognition};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Circled digits Tesseract sometimes recognizes digits as
+static CIRCLED_DIGIT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("[①②③④⑤⑥⑦⑧⑨⑩⑪⑫⑬⑭⑮⑯⑰⑱⑲⑳]").unwrap());
+
+/// Half-width punctuations normalized to full-width ones in supplementary lines
+static NOTE_PUNCT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[():!?]").unwrap());
+
+/// Item names with the rules derived from them, rebuilt by [`PostProcess::rebuild_item_rules`]
+static ITEM_DATA: RwLock<Option<Arc<ItemData>>> = RwLock::new(None);
+
+#[derive(Debug)]
+struct ItemData {
+    dict: &'static ItemDictionary,
+    /// Character pairs appearing in known item names
+    bigrams: AhoCorasick,
+    corrections: ItemCorrections,
+}
+
+impl ItemData {
+    fn new(corrections: ItemCorrections) -> Self {
+        let dict = ItemDictionary::get();
+        Self {
+            dict,
+            bigrams: bigrams(dict),
+            corrections,
+        }
+    }
+}
+
+fn item_data() -> Arc<ItemData> {
+    if let Some(data) = &*ITEM_DATA.read().unwrap() {
+        return Arc::clone(data);
+    }
+    let mut data = ITEM_DATA.write().unwrap();
+    Arc::clone(data.get_or_insert_with(|| Arc::new(ItemData::new(ItemCorrections::default()))))
+}
+
+fn bigrams(dict: &ItemDictionary) -> AhoCorasick {
+    let bigrams = dict
+        .iter()
+        .flat_map(|name| {
+            let bounds = name
+                .char_indices()
+                .map(|(idx, _)| idx)
+                .chain([name.len()])
+                .collect::<Vec<_>>();
+            bounds
+                .windows(3)
+                .map(|w| &name[w[0]..w[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect::<HashSet<_>>();
+    AhoCorasick::new(bigrams).unwrap()
+}
+
+/// Prefixes and suffixes of item names ignored when looking up known item names
+static IGNORE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:重厚な|鋭利な|上質な|魔力の|炎の|炎術の|雷の|神聖な|毒の|血の|冷たい|神秘の|)|\+\d+$",
+    )
+    .unwrap()
+});
+
+/// Replacements always applied to item names
+static REPLACE_RE: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+    vec![
+        (Regex::new(r#"[~\-_/*,\."'′\$#\^。]"#).unwrap(), ""),
+        (Regex::new(r"^[\d、ー]+").unwrap(), ""),
+        (Regex::new(r"[・]+$").unwrap(), ""),
+        (Regex::new(r"\(").unwrap(), "（"),
+        (Regex::new(r"\)").unwrap(), "）"),
+        (Regex::new(r":").unwrap(), "："),
+        (Regex::new(r"!").unwrap(), "！"),
+        (Regex::new(r"＋").unwrap(), "+"),
+        (Regex::new(r"壷").unwrap(), "壺"),
+        (Regex::new(r"蝿").unwrap(), "蠅"),
+    ]
+});
+
+/// Replacements of ambiguous characters tried until a known item name is found
+static TRY_REPLACE_RE: LazyLock<RegexTable> = LazyLock::new(|| {
+    let mut re = vec![
+        (Regex::new(r"1").unwrap(), "！"),
+        (Regex::new(r"`(.*)」").unwrap(), "「$1」"),
+        (
+            Regex::new(r"(戦灰|呼び声頭|情報|絵画)`?(.*)」").unwrap(),
+            "$1「$2」",
+        ),
+        (
+            Regex::new(r"^[ァィゥェォヵヶャュョぁぃぅぇぉゃゅょa-zA-Z]+").unwrap(),
+            "",
+        ),
+        (Regex::new(r"丿レ").unwrap(), "ル"),
+        (Regex::new(r"丿（").unwrap(), "ハ"),
+        (Regex::new(r"丿（").unwrap(), "バ"),
+        (Regex::new(r"丿（").unwrap(), "パ"),
+        (Regex::new(r"[十†一]([1-3])$").unwrap(), "+$1"),
+        (Regex::new(r"[結総][晶暁賞]").unwrap(), "結晶"),
+        (Regex::new(r"[琥琲][珀珈]").unwrap(), "琥珀"),
+        (Regex::new(r"[蟷蝉螺鏡][螂螺融]").unwrap(), "蟷螂"),
+        (Regex::new(r"蕪葉").unwrap(), "落葉"),
+        (Regex::new(r"膨敗").unwrap(), "腐敗"),
+        (Regex::new(r"王[筑箇箕符笥]").unwrap(), "王笏"),
+        (Regex::new(r"泥[澳澪澤]").unwrap(), "泥濘"),
+        (Regex::new(r"蝉血").unwrap(), "蜜血"),
+        (Regex::new(r"[息恐]寵").unwrap(), "恩寵"),
+        (Regex::new(r"[播撫]き").unwrap(), "擬き"),
+        (Regex::new(r"[番男]者").unwrap(), "勇者"),
+        (Regex::new(r"冒[淳浄浣]").unwrap(), "冒涜"),
+        (Regex::new(r"地場|坤場|堂場|在場|垂場").unwrap(), "坩堝"),
+        (Regex::new(r"諸柑|諸杷").unwrap(), "諸相"),
+        (Regex::new(r"[逃透送]る").unwrap(), "迸る"),
+        (Regex::new(r"[縺縣]り").unwrap(), "縋り"),
+        (Regex::new(r"牡").unwrap(), "牢"),
+        (Regex::new(r"獲").unwrap(), "獄"),
+        (Regex::new(r"[啓]").unwrap(), "壺"),
+        (Regex::new(r"[檜横橿楕権様椿楠]").unwrap(), "槍"),
+        (Regex::new(r"[報塁]").unwrap(), "睡"),
+        (Regex::new(r"[蛹蛸頌蛉蛇]").unwrap(), "蛆"),
+        (
+            Regex::new(r"[贄贅暫註昭説晴諏曾替暴暮智賜賀]").unwrap(),
+            "誓",
+        ),
+        (Regex::new(r"[錠銃錯錨]").unwrap(), "銛"),
+        (Regex::new(r"潰").unwrap(), "漬"),
+        (Regex::new(r"[鐙鐘鏡]").unwrap(), "鎧"),
+        (Regex::new(r"[縁繰緻]").unwrap(), "緑"),
+        (Regex::new(r"[蝦智蜜暖唱]").unwrap(), "帽"),
+        (Regex::new(r"[嚢嘆]").unwrap(), "喪"),
+        (Regex::new(r"鍬").unwrap(), "鍛"),
+        (Regex::new(r"渦").unwrap(), "湧"),
+        (Regex::new(r"桔").unwrap(), "枯"),
+        (Regex::new(r"[吉号史]").unwrap(), "古"),
+        (Regex::new(r"皿").unwrap(), "血"),
+        (Regex::new(r"絨").unwrap(), "紐"),
+        (Regex::new(r"探").unwrap(), "投"),
+        (Regex::new(r"大").unwrap(), "矢"),
+        (Regex::new(r"螺").unwrap(), "蟲"),
+        (Regex::new(r"[逢道]").unwrap(), "遺"),
+        (Regex::new(r"[曇噌噴嘗]").unwrap(), "瞳"),
+        (Regex::new(r"惠").unwrap(), "恵"),
+        (Regex::new(r"[雷管]").unwrap(), "雫"),
+        (Regex::new(r"喋り").unwrap(), "啜り"),
+        (Regex::new(r"電").unwrap(), "雷"),
+        (Regex::new(r"[縄繩楽編]").unwrap(), "紫"),
+        (Regex::new(r"[湛]").unwrap(), "泄"),
+        (Regex::new(r"[弟墟]").unwrap(), "兜"),
+        (Regex::new(r"[思忠]").unwrap(), "ぬ"),
+        (Regex::new(r"[檀操]").unwrap(), "露"),
+        (Regex::new(r"[館幽森]").unwrap(), "髪"),
+        (Regex::new(r"[瘡瘍]").unwrap(), "瘤"),
+        (Regex::new(r"[苛菰芹菩昔苫昼]").unwrap(), "苔"),
+        (Regex::new(r"薙").unwrap(), "薬"),
+        (Regex::new(r"相").unwrap(), "首"),
+        (Regex::new(r"[饂乙人]").unwrap(), "亀"),
+        (Regex::new(r"[要芋婆裳菱華製]").unwrap(), "装"),
+        (Regex::new(r"[嵌]").unwrap(), "嵐"),
+        (Regex::new(r"[翔]").unwrap(), "羽"),
+        (Regex::new(r"[蛸蠅]").unwrap(), "蛹"),
+        (Regex::new(r"[寿赦]").unwrap(), "毒"),
+        (Regex::new(r"[件]").unwrap(), "仗"),
+        (Regex::new(r"[福]").unwrap(), "禍"),
+        (Regex::new(r"[窒]").unwrap(), "空"),
+        (Regex::new(r"[棚柱]").unwrap(), "枷"),
+        (Regex::new(r"[斬]").unwrap(), "断"),
+        (Regex::new(r"[書脆脊]").unwrap(), "眷"),
+        (Regex::new(r"[身]").unwrap(), "鳥"),
+        (Regex::new(r"[霧露]").unwrap(), "覆"),
+        (Regex::new(r"[田]").unwrap(), "山"),
+        (Regex::new(r"[衰]").unwrap(), "衾"),
+        (Regex::new(r"[鋼]").unwrap(), "銅"),
+        (Regex::new(r"[箭]").unwrap(), "笛"),
+        (Regex::new(r"[站峰岳]").unwrap(), "冷"),
+        (Regex::new(r"[命]").unwrap(), "岩"),
+        (Regex::new(r"[箔]").unwrap(), "笠"),
+        (Regex::new(r"[播撚]").unwrap(), "揺"),
+    ];
+    let ambicious_pairs = &[
+        ("木", "本"),
+        ("土", "士"),
+        ("日", "目"),
+        ("白", "自"),
+        ("ェ", "ュ"),
+        ("へ", "ヘ"),
+        ("ハ", "バ"),
+        ("ヒ", "ビ"),
+        ("フ", "ブ"),
+        ("ヘ", "ベ"),
+        ("ホ", "ボ"),
+        ("ハ", "パ"),
+        ("ヒ", "ピ"),
+        ("フ", "プ"),
+        ("ヘ", "ペ"),
+        ("ホ", "ポ"),
+        ("パ", "バ"),
+        ("ピ", "ビ"),
+        ("プ", "ブ"),
+        ("ペ", "ベ"),
+        ("ポ", "ボ"),
+        ("グ", "ダ"),
+        ("レ", "ル"),
+        ("ーン", "シ"),
+        ("き", "ミ"),
+        ("き", "さ"),
+        ("ぎ", "ざ"),
+        ("ぎ", "さ"),
+        ("そ", "え"),
+        ("る", "さ"),
+        ("る", "ざ"),
+        ("る", "え"),
+        ("る", "を"),
+        ("ま", "よ"),
+        ("ぬ", "ゐ"),
+        ("イ", "ィ"),
+        ("力", "カ"),
+        ("カ", "ヵ"),
+        ("ケ", "ヶ"),
+        ("タ", "ツ"),
+    ];
+    for (a, b) in ambicious_pairs {
+        assert_ne!(a, b);
+        re.extend([
+            (Regex::new(&format!("{b}{a}|{a}{b}?")).unwrap(), *b),
+            (Regex::new(&format!("{a}{b}|{b}{a}?")).unwrap(), *a),
+        ]);
+    }
+    RegexTable::new(re)
+});
+
+/// Prefixes and suffixes of English item names ignored when looking up known item names
+static EN_IGNORE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:Heavy |Keen |Quality |Magic |Fire |Flame Art |Lightning |Sacred |Poison |Blood |Cold |Occult |)| ?\+\d+$",
+    )
+    .unwrap()
+});
+
+/// Replacements always applied to English item names
+static EN_REPLACE_RE: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+    vec![
+        (Regex::new(r"[‘’`´]").unwrap(), "'"),
+        (Regex::new(r"[“”]").unwrap(), "\""),
+        (Regex::new(r"[~_*\$#\^]").unwrap(), ""),
+        (Regex::new(r"^[^A-Za-z0-9]+").unwrap(), ""),
+        (Regex::new(r"[\s\.,;\-]+$").unwrap(), ""),
+        (Regex::new(r"\s*:\s*").unwrap(), ": "),
+        (Regex::new(r"\s*\+\s*(\d+)$").unwrap(), " +$1"),
+    ]
+});
+
+/// Replacements of letters Tesseract confuses in English item names, tried until a known item name is found
+static EN_TRY_REPLACE_RE: LazyLock<RegexTable> = LazyLock::new(|| {
+    let mut re = vec![
+        (Regex::new(r"rn").unwrap(), "m"),
+        (Regex::new(r"m").unwrap(), "rn"),
+        (Regex::new(r"cl").unwrap(), "d"),
+        (Regex::new(r"vv").unwrap(), "w"),
+        (Regex::new(r"[|!1]").unwrap(), "l"),
+        (Regex::new(r"[|!1]").unwrap(), "I"),
+        (Regex::new(r"0").unwrap(), "O"),
+        (Regex::new(r"0").unwrap(), "o"),
+        (Regex::new(r"5").unwrap(), "S"),
+        (Regex::new(r"8").unwrap(), "B"),
+        (Regex::new(r"(\w)(\p{Lu})").unwrap(), "$1 $2"),
+        (Regex::new(r" ").unwrap(), ""),
+    ];
+    let ambiguous_pairs = &[("l", "I"), ("c", "e"), ("a", "o"), ("n", "h"), ("u", "v")];
+    for (a, b) in ambiguous_pairs {
+        re.extend([(Regex::new(a).unwrap(), *b), (Regex::new(b).unwrap(), *a)]);
+    }
+    RegexTable::new(re)
+});
+
+/// Characters of place names: kana, kanji, prolonged sound marks and middle dots
+static PLACE_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[\p{Hiragana}\p{Katakana}\p{Han}ー・]+$").unwrap());
+/// Characters of boss names: ones of place names and commas separating epithets, e.g. `忌み鬼、マルギット`
+static BOSS_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[\p{Hiragana}\p{Katakana}\p{Han}ー・、]+$").unwrap());
+/// Rune counts, up to 999,999,999 runes
+static RUNE_COUNT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(0|[1-9][0-9]{0,8})$").unwrap());
+
+static PREFIX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[×xX〆くへヘべベメ＜＞※]+").unwrap());
+static TEXT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^×\d+$").unwrap());
+
+/// Regexes with a set to find ones matching a text in a single pass.
+struct RegexTable {
+    set: RegexSet,
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl RegexTable {
+    fn new(rules: Vec<(Regex, &'static str)>) -> Self {
+        let set = RegexSet::new(rules.iter().map(|(re, _)| re.as_str())).unwrap();
+        Self { set, rules }
+    }
+}
+
+/// Corrections of misread item names, given by `item_corrections` in the config.
+///
+/// Each line of the file is a regex and its replacement separated by a tab, where the replacement may refer to
+/// groups as `$1`. They are applied in order after the built-in replacements.
+/// Empty lines and lines starting with `#` are skipped.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::operator::ItemCorrections;
+///
+/// let corrections = ItemCorrections::parse("# misread in dark scenes\n^縁雫\t緋雫\n").unwrap();
+/// assert_eq!(corrections.len(), 1);
+/// assert!(ItemCorrections::parse("(\tx\n").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ItemCorrections {
+    rules: Vec<(Regex, String)>,
+}
+
+impl ItemCorrections {
+    /// Reads a corrections file.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read item corrections: {}", path.display()))?;
+        Self::parse(&text)
+            .wrap_err_with(|| format!("failed to parse item corrections: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let mut rules = vec![];
+        for (lineno, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = line
+                .split_once('\t')
+                .ok_or_eyre("missing replacement")
+                .and_then(|(pattern, replacement)| {
+                    Ok((Regex::new(pattern)?, replacement.to_owned()))
+                })
+                .wrap_err_with(|| format!("invalid correction at line {}", lineno + 1))?;
+            rules.push(rule);
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Rules correcting item names recognized in a language.
+struct ItemRules<'a> {
+    dict: &'a ItemDictionary,
+    bigrams: &'a AhoCorasick,
+    /// Prefixes and suffixes ignored when looking up known item names
+    ignore: &'a Regex,
+    replace: &'a [(Regex, &'static str)],
+    corrections: &'a ItemCorrections,
+    try_replace: &'a RegexTable,
+}
+
+impl<'a> ItemRules<'a> {
+    fn new(lang: Lang, data: &'a ItemData) -> Self {
+        let (ignore, replace, try_replace) = match lang {
+            Lang::Ja => (&*IGNORE_RE, &**REPLACE_RE, &*TRY_REPLACE_RE),
+            Lang::En => (&*EN_IGNORE_RE, &**EN_REPLACE_RE, &*EN_TRY_REPLACE_RE),
+        };
+        Self {
+            dict: data.dict,
+            bigrams: &data.bigrams,
+            ignore,
+            replace,
+            corrections: &data.corrections,
+            try_replace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum PostProcess {
+    #[default]
     None,
     ItemText,
+    /// Item names in frames where the game is displayed in English
+    EnglishItemText,
     ItemCount,
+    /// Supplementary lines under item names, which may be empty
+    ItemNote,
     Digits,
+    /// Names of places such as sites of grace, which are not in the item dictionary
+    PlaceName,
+    /// Names of areas in banners shown when entering them, validated by `assets/area.txt`
+    AreaName,
+    /// Names of bosses above their health bars
+    BossName,
+    /// Message shown when a site of grace is discovered, accepted only if it is one of [`GRACE_DISCOVERED_TEXTS`]
+    GraceDiscovered,
+    /// Number of runes held, which has no separators or leading zeros
+    RuneCount,
 }
 
 impl PostProcess {
+    /// Builds the correction tables and the item name set, and returns the time taken.
+    ///
+    /// They are built at first use otherwise, which stalls the pipeline.
+    pub fn preload() -> Duration {
+        let start = Instant::now();
+        LazyLock::force(&CIRCLED_DIGIT_RE);
+        LazyLock::force(&NOTE_PUNCT_RE);
+        item_data();
+        LazyLock::force(&IGNORE_RE);
+        LazyLock::force(&REPLACE_RE);
+        LazyLock::force(&TRY_REPLACE_RE);
+        LazyLock::force(&EN_IGNORE_RE);
+        LazyLock::force(&EN_REPLACE_RE);
+        LazyLock::force(&EN_TRY_REPLACE_RE);
+        LazyLock::force(&PLACE_NAME_RE);
+        areas::preload();
+        LazyLock::force(&BOSS_NAME_RE);
+        LazyLock::force(&RUNE_COUNT_RE);
+        LazyLock::force(&PREFIX_RE);
+        LazyLock::force(&TEXT_RE);
+        start.elapsed()
+    }
+
+    /// Rebuilds the rules correcting item names from the item list chosen by [`use_item_list`](crate::items::use_item_list) or
+    /// [`reload_item_list`](crate::items::reload_item_list), with `corrections` applied in addition to the built-in ones.
+    ///
+    /// Texts post-processed after this returns are corrected by the new rules, also while the pipeline is running.
+    pub fn rebuild_item_rules(corrections: ItemCorrections) {
+        let data = ItemData::new(corrections);
+        *ITEM_DATA.write().unwrap() = Some(Arc::new(data));
+    }
+
+    /// Returns the post-process of the same texts in frames where the game is displayed in `lang`.
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::{i18n::Lang, operator::PostProcess};
+    ///
+    /// assert_eq!(PostProcess::ItemText.localized(Lang::En), PostProcess::EnglishItemText);
+    /// assert_eq!(PostProcess::Digits.localized(Lang::En), PostProcess::Digits);
+    /// ```
+    pub fn localized(self, lang: Lang) -> Self {
+        match (self, lang) {
+            (Self::ItemText | Self::EnglishItemText, Lang::Ja) => Self::ItemText,
+            (Self::ItemText | Self::EnglishItemText, Lang::En) => Self::EnglishItemText,
+            (pp, _) => pp,
+        }
+    }
+
     pub fn run(&self, text: &str, conf: Confidence) -> Recognition {
-        static REPLACE_RE: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new("[①②③④⑤⑥⑦⑧⑨⑩⑪⑫⑬⑭⑮⑯⑰⑱⑲⑳]").unwrap());
+        self.run_with_item(text, conf).0
+    }
+
+    /// Post-processes a text like [`PostProcess::run`], and also returns the ID of the known item
+    /// the corrected text names.
+    ///
+    /// IDs are returned only by [`PostProcess::ItemText`] and [`PostProcess::EnglishItemText`].
+    ///
+    /// # Examples
+    /// ```
+    /// use elden_analyzer::operator::{Confidence, PostProcess};
+    ///
+    /// let (_, id) = PostProcess::ItemText.run_with_item("総暁投げ矢", Confidence::new(90));
+    /// assert!(id.is_some());
+    /// let (_, id) = PostProcess::PlaceName.run_with_item("ストームヴィル城", Confidence::new(90));
+    /// assert!(id.is_none());
+    /// ```
+    pub fn run_with_item(&self, text: &str, conf: Confidence) -> (Recognition, Option<ItemId>) {
         // WORKAROUND: Tesseract sometimes recognize "1" as "①" etc.
-        let text = REPLACE_RE
-            .replace_all(text, |cap: &Captures| match cap.get(0).unwrap().as_str() {
+        let replaced = CIRCLED_DIGIT_RE.replace_all(text, |cap: &Captures| {
+            match cap.get(0).unwrap().as_str() {
                 "①" => "1",
                 "②" => "2",
                 "③" => "3",
@@ -40,265 +522,536 @@ impl PostProcess {
                 "⑲" => "19",
                 "⑳" => "20",
                 _ => unreachable!(),
-            })
-            .into_owned();
+            }
+        });
+        if let Cow::Owned(replaced) = &replaced {
+            Explainer::get().note(|| format!("circled digits replaced: {replaced:?}"));
+        }
+        let text = replaced.into_owned();
 
-        match self {
+        let recognition = match self {
             PostProcess::None => Recognition::Possible(text, conf),
-            PostProcess::ItemText => item_text(&text, conf),
+            PostProcess::ItemText => {
+                return item_text(&text, conf, &ItemRules::new(Lang::Ja, &item_data()))
+            }
+            PostProcess::EnglishItemText => {
+                return item_text(&text, conf, &ItemRules::new(Lang::En, &item_data()))
+            }
             PostProcess::ItemCount => item_count(&text, conf),
+            PostProcess::ItemNote => item_note(&text, conf),
             PostProcess::Digits => digits(&text, conf),
+            PostProcess::PlaceName => place_name(&text, conf),
+            PostProcess::AreaName => area_name(&text, conf),
+            PostProcess::BossName => boss_name(&text, conf),
+            PostProcess::GraceDiscovered => grace_discovered(&text, conf),
+            PostProcess::RuneCount => rune_count(&text, conf),
+        };
+        (recognition, None)
+    }
+
+    /// Post-processes a text recognized by glyph matching.
+    ///
+    /// Glyph matching yields only characters of the game font, so corrections of misread characters are skipped
+    /// and only the format of counts is validated. Counts of items may be matched with the leading `×`,
+    /// which is dropped from digits.
+    pub fn run_matched(&self, text: &str, conf: Confidence) -> Recognition {
+        let (text, valid) = match self {
+            PostProcess::None => (text, true),
+            PostProcess::Digits => {
+                let text = text.trim_start_matches('×');
+                (text, text.chars().all(|ch| ch.is_ascii_digit()))
+            }
+            PostProcess::ItemCount => (text, TEXT_RE.is_match(text)),
+            PostProcess::RuneCount => (text, RUNE_COUNT_RE.is_match(text)),
+            pp => return pp.run(text, conf),
+        };
+        if valid {
+            Recognition::Found(text.to_owned(), conf)
+        } else {
+            Explainer::get().note(|| format!("{text:?} is not formatted as {self:?}"));
+            Recognition::Possible(text.to_owned(), conf)
         }
     }
 }
 
-fn is_valid_item_name(name: &str) -> bool {
-    static ITEM_NAMES: LazyLock<HashSet<String>> = LazyLock::new(|| {
-        let text = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/item.txt"));
-        text.lines()
-            .filter(|x| !x.is_empty() && !x.starts_with("#"))
-            .map(String::from)
-            .collect()
-    });
-
-    static IGNORE_RE: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(
-        r"^(?:重厚な|鋭利な|上質な|魔力の|炎の|炎術の|雷の|神聖な|毒の|血の|冷たい|神秘の|)|\+\d+$"
-    ).unwrap()
-    });
-
-    if ITEM_NAMES.contains(name) {
-        return true;
-    }
-    if let Cow::Owned(replaced) = IGNORE_RE.replace(name, "") {
-        if ITEM_NAMES.contains(&replaced) {
-            return true;
+fn is_valid_item_name(name: &str, rules: &ItemRules) -> bool {
+    known_item_name(name, rules).is_some()
+}
+
+/// Returns the known item name `name` is, ignoring prefixes and suffixes such as affinities and upgrade levels.
+fn known_item_name<'a>(name: &'a str, rules: &ItemRules) -> Option<Cow<'a, str>> {
+    let items = rules.dict;
+    if items.contains(name) {
+        return Some(Cow::Borrowed(name));
+    }
+    if let Cow::Owned(replaced) = rules.ignore.replace(name, "") {
+        if items.contains(&replaced) {
+            return Some(Cow::Owned(replaced));
         }
     }
-    false
+    None
 }
 
-fn item_text(text: &str, conf: Confidence) -> Recognition {
-    static REPLACE_RE: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
-        vec![
-            (Regex::new(r#"[~\-_/*,\."'′\$#\^。]"#).unwrap(), ""),
-            (Regex::new(r"^[\d、ー]+").unwrap(), ""),
-            (Regex::new(r"[・]+$").unwrap(), ""),
-            (Regex::new(r"\(").unwrap(), "（"),
-            (Regex::new(r"\)").unwrap(), "）"),
-            (Regex::new(r":").unwrap(), "："),
-            (Regex::new(r"!").unwrap(), "！"),
-            (Regex::new(r"＋").unwrap(), "+"),
-            (Regex::new(r"壷").unwrap(), "壺"),
-            (Regex::new(r"蝿").unwrap(), "蠅"),
-        ]
-    });
-
-    static TRY_REPLACE_RE: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
-        let mut re = vec![
-            (Regex::new(r"1").unwrap(), "！"),
-            (Regex::new(r"`(.*)」").unwrap(), "「$1」"),
-            (
-                Regex::new(r"(戦灰|呼び声頭|情報|絵画)`?(.*)」").unwrap(),
-                "$1「$2」",
-            ),
-            (
-                Regex::new(r"^[ァィゥェォヵヶャュョぁぃぅぇぉゃゅょa-zA-Z]+").unwrap(),
-                "",
-            ),
-            (Regex::new(r"丿レ").unwrap(), "ル"),
-            (Regex::new(r"丿（").unwrap(), "ハ"),
-            (Regex::new(r"丿（").unwrap(), "バ"),
-            (Regex::new(r"丿（").unwrap(), "パ"),
-            (Regex::new(r"[十†一]([1-3])$").unwrap(), "+$1"),
-            (Regex::new(r"[結総][晶暁賞]").unwrap(), "結晶"),
-            (Regex::new(r"[琥琲][珀珈]").unwrap(), "琥珀"),
-            (Regex::new(r"[蟷蝉螺鏡][螂螺融]").unwrap(), "蟷螂"),
-            (Regex::new(r"蕪葉").unwrap(), "落葉"),
-            (Regex::new(r"膨敗").unwrap(), "腐敗"),
-            (Regex::new(r"王[筑箇箕符笥]").unwrap(), "王笏"),
-            (Regex::new(r"泥[澳澪澤]").unwrap(), "泥濘"),
-            (Regex::new(r"蝉血").unwrap(), "蜜血"),
-            (Regex::new(r"[息恐]寵").unwrap(), "恩寵"),
-            (Regex::new(r"[播撫]き").unwrap(), "擬き"),
-            (Regex::new(r"[番男]者").unwrap(), "勇者"),
-            (Regex::new(r"冒[淳浄浣]").unwrap(), "冒涜"),
-            (Regex::new(r"地場|坤場|堂場|在場|垂場").unwrap(), "坩堝"),
-            (Regex::new(r"諸柑|諸杷").unwrap(), "諸相"),
-            (Regex::new(r"[逃透送]る").unwrap(), "迸る"),
-            (Regex::new(r"[縺縣]り").unwrap(), "縋り"),
-            (Regex::new(r"牡").unwrap(), "牢"),
-            (Regex::new(r"獲").unwrap(), "獄"),
-            (Regex::new(r"[啓]").unwrap(), "壺"),
-            (Regex::new(r"[檜横橿楕権様椿楠]").unwrap(), "槍"),
-            (Regex::new(r"[報塁]").unwrap(), "睡"),
-            (Regex::new(r"[蛹蛸頌蛉蛇]").unwrap(), "蛆"),
-            (
-                Regex::new(r"[贄贅暫註昭説晴諏曾替暴暮智賜賀]").unwrap(),
-                "誓",
-            ),
-            (Regex::new(r"[錠銃錯錨]").unwrap(), "銛"),
-            (Regex::new(r"潰").unwrap(), "漬"),
-            (Regex::new(r"[鐙鐘鏡]").unwrap(), "鎧"),
-            (Regex::new(r"[縁繰緻]").unwrap(), "緑"),
-            (Regex::new(r"[蝦智蜜暖唱]").unwrap(), "帽"),
-            (Regex::new(r"[嚢嘆]").unwrap(), "喪"),
-            (Regex::new(r"鍬").unwrap(), "鍛"),
-            (Regex::new(r"渦").unwrap(), "湧"),
-            (Regex::new(r"桔").unwrap(), "枯"),
-            (Regex::new(r"[吉号史]").unwrap(), "古"),
-            (Regex::new(r"皿").unwrap(), "血"),
-            (Regex::new(r"絨").unwrap(), "紐"),
-            (Regex::new(r"探").unwrap(), "投"),
-            (Regex::new(r"大").unwrap(), "矢"),
-            (Regex::new(r"螺").unwrap(), "蟲"),
-            (Regex::new(r"[逢道]").unwrap(), "遺"),
-            (Regex::new(r"[曇噌噴嘗]").unwrap(), "瞳"),
-            (Regex::new(r"惠").unwrap(), "恵"),
-            (Regex::new(r"[雷管]").unwrap(), "雫"),
-            (Regex::new(r"喋り").unwrap(), "啜り"),
-            (Regex::new(r"電").unwrap(), "雷"),
-            (Regex::new(r"[縄繩楽編]").unwrap(), "紫"),
-            (Regex::new(r"[湛]").unwrap(), "泄"),
-            (Regex::new(r"[弟墟]").unwrap(), "兜"),
-            (Regex::new(r"[思忠]").unwrap(), "ぬ"),
-            (Regex::new(r"[檀操]").unwrap(), "露"),
-            (Regex::new(r"[館幽森]").unwrap(), "髪"),
-            (Regex::new(r"[瘡瘍]").unwrap(), "瘤"),
-            (Regex::new(r"[苛菰芹菩昔苫昼]").unwrap(), "苔"),
-            (Regex::new(r"薙").unwrap(), "薬"),
-            (Regex::new(r"相").unwrap(), "首"),
-            (Regex::new(r"[饂乙人]").unwrap(), "亀"),
-            (Regex::new(r"[要芋婆裳菱華製]").unwrap(), "装"),
-            (Regex::new(r"[嵌]").unwrap(), "嵐"),
-            (Regex::new(r"[翔]").unwrap(), "羽"),
-            (Regex::new(r"[蛸蠅]").unwrap(), "蛹"),
-            (Regex::new(r"[寿赦]").unwrap(), "毒"),
-            (Regex::new(r"[件]").unwrap(), "仗"),
-            (Regex::new(r"[福]").unwrap(), "禍"),
-            (Regex::new(r"[窒]").unwrap(), "空"),
-            (Regex::new(r"[棚柱]").unwrap(), "枷"),
-            (Regex::new(r"[斬]").unwrap(), "断"),
-            (Regex::new(r"[書脆脊]").unwrap(), "眷"),
-            (Regex::new(r"[身]").unwrap(), "鳥"),
-            (Regex::new(r"[霧露]").unwrap(), "覆"),
-            (Regex::new(r"[田]").unwrap(), "山"),
-            (Regex::new(r"[衰]").unwrap(), "衾"),
-            (Regex::new(r"[鋼]").unwrap(), "銅"),
-            (Regex::new(r"[箭]").unwrap(), "笛"),
-            (Regex::new(r"[站峰岳]").unwrap(), "冷"),
-            (Regex::new(r"[命]").unwrap(), "岩"),
-            (Regex::new(r"[箔]").unwrap(), "笠"),
-            (Regex::new(r"[播撚]").unwrap(), "揺"),
-        ];
-        let ambicious_pairs = &[
-            ("木", "本"),
-            ("土", "士"),
-            ("日", "目"),
-            ("白", "自"),
-            ("ェ", "ュ"),
-            ("へ", "ヘ"),
-            ("ハ", "バ"),
-            ("ヒ", "ビ"),
-            ("フ", "ブ"),
-            ("ヘ", "ベ"),
-            ("ホ", "ボ"),
-            ("ハ", "パ"),
-            ("ヒ", "ピ"),
-            ("フ", "プ"),
-            ("ヘ", "ペ"),
-            ("ホ", "ポ"),
-            ("パ", "バ"),
-            ("ピ", "ビ"),
-            ("プ", "ブ"),
-            ("ペ", "ベ"),
-            ("ポ", "ボ"),
-            ("グ", "ダ"),
-            ("レ", "ル"),
-            ("ーン", "シ"),
-            ("き", "ミ"),
-            ("き", "さ"),
-            ("ぎ", "ざ"),
-            ("ぎ", "さ"),
-            ("そ", "え"),
-            ("る", "さ"),
-            ("る", "ざ"),
-            ("る", "え"),
-            ("る", "を"),
-            ("ま", "よ"),
-            ("ぬ", "ゐ"),
-            ("イ", "ィ"),
-            ("力", "カ"),
-            ("カ", "ヵ"),
-            ("ケ", "ヶ"),
-            ("タ", "ツ"),
-        ];
-        for (a, b) in ambicious_pairs {
-            assert_ne!(a, b);
-            re.extend([
-                (Regex::new(&format!("{b}{a}|{a}{b}?")).unwrap(), *b),
-                (Regex::new(&format!("{a}{b}|{b}{a}?")).unwrap(), *a),
-            ]);
-        }
-        re
-    });
+/// Returns the ID of the known item `name` is (see [`known_item_name`]).
+fn item_id(name: &str, rules: &ItemRules) -> Option<ItemId> {
+    rules.dict.id(&known_item_name(name, rules)?)
+}
 
-    let decayed_conf = conf * 8 / 10;
+fn item_text(text: &str, conf: Confidence, rules: &ItemRules) -> (Recognition, Option<ItemId>) {
+    let decayed_conf = conf * Ratio::new(4, 5);
     let mut conf = conf;
 
+    let explainer = Explainer::get();
+
     let mut text = Cow::Borrowed(text);
-    for (reg, repl) in &*REPLACE_RE {
+    for (reg, repl) in rules.replace {
         if let Cow::Owned(owned) = reg.replace_all(text.as_ref(), *repl) {
+            explainer.note(|| format!("replaced /{reg}/ with {repl:?}: {owned:?}"));
+            text = Cow::Owned(owned);
+            conf = decayed_conf;
+        }
+    }
+    for (reg, repl) in &rules.corrections.rules {
+        if let Cow::Owned(owned) = reg.replace_all(text.as_ref(), repl.as_str()) {
+            explainer.note(|| format!("corrected /{reg}/ to {repl:?}: {owned:?}"));
             text = Cow::Owned(owned);
             conf = decayed_conf;
         }
     }
 
-    if is_valid_item_name(text.as_ref()) {
-        return Recognition::Found(text.into_owned(), conf);
+    if is_valid_item_name(text.as_ref(), rules) {
+        explainer.note(|| format!("{text:?} is a known item name"));
+        let id = item_id(&text, rules);
+        return (Recognition::Found(text.into_owned(), conf), id);
+    }
+
+    match correct_item_name(&text, rules) {
+        Ok(cand) => {
+            explainer.note(|| {
+                let edits = cand
+                    .edits
+                    .iter()
+                    .map(|&idx| {
+                        let (reg, repl) = &rules.try_replace.rules[idx];
+                        format!("/{reg}/ => {repl:?}")
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "tried {}: {:?} is a known item name",
+                    edits.join(", "),
+                    cand.text
+                )
+            });
+            let id = item_id(&cand.text, rules);
+            return (Recognition::Found(cand.text, decayed_conf), id);
+        }
+        Err(num_candidates) => {
+            explainer.note(|| {
+                format!("no known item name in {num_candidates} candidates of ambiguous characters")
+            });
+        }
     }
 
-    let mut candidates = HashSet::new();
-    candidates.insert(text.clone().into_owned());
-    for (reg, repl) in &*TRY_REPLACE_RE {
-        let mut new_candidates = HashSet::with_capacity(candidates.capacity());
-        for cand in candidates {
-            for caps in reg.captures_iter(&cand) {
-                let m = caps.get(0).unwrap();
-                let mut replaced = cand[..m.start()].to_owned();
-                caps.expand(repl, &mut replaced);
-                replaced += &cand[m.end()..];
-                if is_valid_item_name(&replaced) {
-                    return Recognition::Found(replaced, decayed_conf);
+    (Recognition::Possible(text.into_owned(), conf), None)
+}
+
+/// Text with ambiguous characters replaced.
+#[derive(Debug)]
+struct Candidate {
+    text: String,
+    /// Indices of the rules in [`TRY_REPLACE_RE`] (or its English counterpart) applied, in order
+    edits: Vec<usize>,
+    score: isize,
+}
+
+/// Maximum number of candidates kept for each number of edits
+const BEAM_WIDTH: usize = 32;
+/// Maximum number of edits applied to a text
+const MAX_EDITS: usize = 4;
+
+/// Searches for a known item name by replacing ambiguous characters in `text`.
+///
+/// Each step applies one match of a rule to the candidates, and keeps the [`BEAM_WIDTH`] candidates
+/// most similar to known item names. Returns the number of tried candidates if none is found.
+fn correct_item_name(text: &str, rules: &ItemRules) -> Result<Candidate, usize> {
+    let mut seen = HashSet::from([text.to_owned()]);
+    let mut beam = vec![Candidate {
+        text: text.to_owned(),
+        edits: vec![],
+        score: dictionary_score(text, rules),
+    }];
+
+    for _ in 0..MAX_EDITS {
+        let mut next = vec![];
+        for cand in &beam {
+            for idx in &rules.try_replace.set.matches(&cand.text) {
+                let (reg, repl) = &rules.try_replace.rules[idx];
+                for caps in reg.captures_iter(&cand.text) {
+                    let m = caps.get(0).unwrap();
+                    let mut replaced = cand.text[..m.start()].to_owned();
+                    caps.expand(repl, &mut replaced);
+                    replaced += &cand.text[m.end()..];
+                    if !seen.insert(replaced.clone()) {
+                        continue;
+                    }
+                    let mut edits = cand.edits.clone();
+                    edits.push(idx);
+                    let score = dictionary_score(&replaced, rules);
+                    let new_cand = Candidate {
+                        text: replaced,
+                        edits,
+                        score,
+                    };
+                    if is_valid_item_name(&new_cand.text, rules) {
+                        return Ok(new_cand);
+                    }
+                    next.push(new_cand);
                 }
-                new_candidates.insert(replaced);
             }
-            new_candidates.insert(cand);
         }
-        candidates = new_candidates;
+        if next.is_empty() {
+            break;
+        }
+        next.sort_by_key(|cand| Reverse(cand.score));
+        next.truncate(BEAM_WIDTH);
+        beam = next;
     }
-    tracing::trace!(?candidates);
 
-    Recognition::Possible(text.into_owned(), conf)
+    tracing::trace!(candidates = ?beam.iter().map(|cand| &cand.text).collect::<Vec<_>>());
+    Err(seen.len())
 }
 
-fn item_count(text: &str, conf: Confidence) -> Recognition {
-    static PREFIX_RE: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^[×xX〆くへヘべベメ＜＞※]+").unwrap());
-    static TEXT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^×\d+$").unwrap());
+/// Scores how similar `text` is to known item names.
+///
+/// Character pairs found in known item names count up, and other pairs count down.
+/// Leading characters some known item name starts with also count up.
+fn dictionary_score(text: &str, rules: &ItemRules) -> isize {
+    let pairs = text.chars().count().saturating_sub(1) as isize;
+    let known = rules.bigrams.find_overlapping_iter(text).count() as isize;
+    let prefix = rules.dict.longest_prefix(&rules.ignore.replace(text, "")) as isize;
+    known - (pairs - known) + prefix
+}
 
+fn item_count(text: &str, conf: Confidence) -> Recognition {
+    let explainer = Explainer::get();
     let text = PREFIX_RE.replace_all(text, "×").into_owned();
+    explainer.note(|| format!("prefix normalized: {text:?}"));
     if TEXT_RE.is_match(&text) {
         Recognition::Found(text, conf)
     } else {
+        explainer.note(|| "not a count (`×` followed by digits)".into());
         Recognition::Possible(text, conf)
     }
 }
 
+/// Minimum confidence of supplementary lines, which cannot be validated by the item dictionary
+const ITEM_NOTE_MIN_CONFIDENCE: i32 = 70;
+
+fn item_note(text: &str, conf: Confidence) -> Recognition {
+    let explainer = Explainer::get();
+    // Tesseract inserts spaces between Japanese characters
+    let text = text.split_whitespace().collect::<String>();
+    let text =
+        NOTE_PUNCT_RE.replace_all(&text, |cap: &Captures| match cap.get(0).unwrap().as_str() {
+            "(" => "（",
+            ")" => "）",
+            ":" => "：",
+            "!" => "！",
+            "?" => "？",
+            _ => unreachable!(),
+        });
+    if text.is_empty() {
+        explainer.note(|| "no supplementary line".into());
+        return Recognition::Found(String::new(), conf);
+    }
+    if conf < Confidence::new(ITEM_NOTE_MIN_CONFIDENCE) {
+        explainer.note(|| format!("confidence below {ITEM_NOTE_MIN_CONFIDENCE}"));
+        return Recognition::Possible(text.into_owned(), conf);
+    }
+    Recognition::Found(text.into_owned(), conf)
+}
+
 fn digits(text: &str, conf: Confidence) -> Recognition {
     if text.chars().all(char::is_numeric) {
         Recognition::Found(text.to_owned(), conf)
     } else {
+        Explainer::get().note(|| "contains non-digit characters".into());
         Recognition::Possible(text.to_owned(), conf)
     }
 }
+
+fn rune_count(text: &str, conf: Confidence) -> Recognition {
+    let explainer = Explainer::get();
+    // Digits are drawn in a font where Tesseract confuses some of them with letters
+    let text = text
+        .chars()
+        .map(|ch| match ch {
+            'O' | 'o' | 'D' => '0',
+            'l' | 'I' | '|' => '1',
+            _ => ch,
+        })
+        .collect::<String>();
+    explainer.note(|| format!("letters replaced: {text:?}"));
+    if RUNE_COUNT_RE.is_match(&text) {
+        Recognition::Found(text, conf)
+    } else {
+        explainer.note(|| "not a rune count".into());
+        Recognition::Possible(text, conf)
+    }
+}
+
+/// Minimum confidence of names of places and bosses, which cannot be validated by a dictionary
+const NAME_MIN_CONFIDENCE: i32 = 70;
+
+fn place_name(text: &str, conf: Confidence) -> Recognition {
+    proper_name(text, conf, &PLACE_NAME_RE, "place names")
+}
+
+/// Accepts a known area name, correcting a misread character if only one known name is that close.
+///
+/// Corrected names are less confident, and unknown names are not accepted however confidently recognized.
+fn area_name(text: &str, conf: Confidence) -> Recognition {
+    let explainer = Explainer::get();
+    let text = text.split_whitespace().collect::<String>();
+    if areas::is_area_name(&text) {
+        return Recognition::Found(text, conf);
+    }
+    if let Some(corrected) = areas::correct(&text) {
+        explainer.note(|| format!("corrected to {corrected:?}"));
+        return Recognition::Found(corrected.to_owned(), conf * Ratio::new(4, 5));
+    }
+    explainer.note(|| format!("{text:?} is not a known area name"));
+    Recognition::Possible(text, conf)
+}
+
+fn boss_name(text: &str, conf: Confidence) -> Recognition {
+    proper_name(text, conf, &BOSS_NAME_RE, "boss names")
+}
+
+/// Accepts a name consisting of the characters matched by `re` and recognized confidently.
+fn proper_name(text: &str, conf: Confidence, re: &Regex, kind: &str) -> Recognition {
+    let explainer = Explainer::get();
+    // Tesseract inserts spaces between Japanese characters
+    let text = text.split_whitespace().collect::<String>();
+    if !re.is_match(&text) {
+        explainer.note(|| format!("{text:?} has characters not in {kind}"));
+        return Recognition::Possible(text, conf);
+    }
+    if conf < Confidence::new(NAME_MIN_CONFIDENCE) {
+        explainer.note(|| format!("confidence below {NAME_MIN_CONFIDENCE}"));
+        return Recognition::Possible(text, conf);
+    }
+    Recognition::Found(text, conf)
+}
+
+/// Texts of the message shown when a site of grace is discovered, in each language of the game
+pub const GRACE_DISCOVERED_TEXTS: &[&str] = &["祝福を見出した", "LOST GRACE DISCOVERED"];
+
+/// Maximum number of misread characters of the message corrected
+const GRACE_DISCOVERED_MAX_ERRORS: usize = 2;
+
+/// Accepts one of [`GRACE_DISCOVERED_TEXTS`], correcting a few misread characters.
+///
+/// Spaces and cases are ignored when comparing, and the text is replaced by the known message.
+fn grace_discovered(text: &str, conf: Confidence) -> Recognition {
+    let explainer = Explainer::get();
+    let normalized = text
+        .split_whitespace()
+        .collect::<String>()
+        .to_uppercase()
+        .chars()
+        .collect::<Vec<_>>();
+    for &known in GRACE_DISCOVERED_TEXTS {
+        let known_chars = known.chars().filter(|ch| !ch.is_whitespace());
+        if known_chars.clone().count() != normalized.len() {
+            continue;
+        }
+        let errors = known_chars
+            .zip(&normalized)
+            .filter(|(a, b)| a != *b)
+            .count();
+        if errors == 0 {
+            return Recognition::Found(known.to_owned(), conf);
+        }
+        if errors <= GRACE_DISCOVERED_MAX_ERRORS {
+            explainer.note(|| format!("{errors} characters corrected to {known:?}"));
+            return Recognition::Found(known.to_owned(), conf * Ratio::new(4, 5));
+        }
+    }
+    explainer.note(|| format!("{text:?} is not the message of discovering a site of grace"));
+    Recognition::Possible(text.split_whitespace().collect(), conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Option<String> {
+        match item_text(
+            text,
+            Confidence::new(90),
+            &ItemRules::new(Lang::Ja, &item_data()),
+        )
+        .0
+        {
+            Recognition::Found(text, _) => Some(text),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn item_text_correction() {
+        assert_eq!(found("結晶投げ矢").as_deref(), Some("結晶投げ矢"));
+        assert_eq!(found("総暁投げ矢").as_deref(), Some("結晶投げ矢"));
+        // multiple edits
+        assert_eq!(found("ィ総暁投げ矢").as_deref(), Some("結晶投げ矢"));
+        assert_eq!(found("ほげ"), None);
+    }
+
+    #[test]
+    fn item_text_ids() {
+        let data = item_data();
+        let rules = ItemRules::new(Lang::Ja, &data);
+        let id = |text| item_text(text, Confidence::new(90), &rules).1;
+        let expected = ItemDictionary::bundled(Lang::Ja).id("結晶投げ矢");
+        assert!(expected.is_some());
+        assert_eq!(id("総暁投げ矢"), expected);
+        assert_eq!(
+            id("重厚な緋雫の聖杯瓶"),
+            ItemDictionary::bundled(Lang::Ja).id("緋雫の聖杯瓶")
+        );
+        assert_eq!(id("ほげ"), None);
+    }
+
+    #[test]
+    fn english_item_text_correction() {
+        let dict = ItemDictionary::bundled(Lang::En);
+        let data = ItemData {
+            dict,
+            bigrams: bigrams(dict),
+            corrections: ItemCorrections::default(),
+        };
+        let rules = ItemRules::new(Lang::En, &data);
+        let found = |text| match item_text(text, Confidence::new(90), &rules).0 {
+            Recognition::Found(text, _) => Some(text),
+            _ => None,
+        };
+        assert_eq!(
+            found("Flask of Crimson Tears").as_deref(),
+            Some("Flask of Crimson Tears")
+        );
+        assert_eq!(
+            found("'F1ask of Crirnson Tears.").as_deref(),
+            Some("Flask of Crimson Tears")
+        );
+        assert_eq!(found("Flask of Crimson Beers"), None);
+    }
+
+    #[test]
+    fn item_note() {
+        let note = |text, conf| super::item_note(text, Confidence::new(conf));
+        assert!(matches!(note(" ", 0), Recognition::Found(text, _) if text.is_empty()));
+        assert!(
+            matches!(note("特 別 な 品 (1)", 90), Recognition::Found(text, _) if text == "特別な品（1）")
+        );
+        assert!(matches!(note("特別な品", 30), Recognition::Possible(..)));
+    }
+
+    #[test]
+    fn place_name() {
+        let place = |text, conf| super::place_name(text, Confidence::new(conf));
+        assert!(
+            matches!(place("関 門 前 の 廃 墟", 90), Recognition::Found(text, _) if text == "関門前の廃墟")
+        );
+        assert!(matches!(
+            place("ストームヴィル城・正門前", 90),
+            Recognition::Found(..)
+        ));
+        assert!(matches!(
+            place("関門前の廃墟", 30),
+            Recognition::Possible(..)
+        ));
+        assert!(matches!(
+            place("関門前の|廃墟", 90),
+            Recognition::Possible(..)
+        ));
+        assert!(matches!(place("", 90), Recognition::Possible(..)));
+    }
+
+    #[test]
+    fn area_name() {
+        let area = |text, conf| super::area_name(text, Confidence::new(conf));
+        assert!(
+            matches!(area("リ ム グ レ イ ブ", 30), Recognition::Found(text, conf) if text == "リムグレイブ" && conf == Confidence::new(30))
+        );
+        assert!(
+            matches!(area("ケイリツド", 90), Recognition::Found(text, conf) if text == "ケイリッド" && conf == Confidence::new(72))
+        );
+        // Names of sites of grace are not areas
+        assert!(matches!(
+            area("関門前の廃墟", 90),
+            Recognition::Possible(..)
+        ));
+        assert!(matches!(area("", 90), Recognition::Possible(..)));
+    }
+
+    #[test]
+    fn grace_discovered() {
+        let grace = |text| super::grace_discovered(text, Confidence::new(90));
+        assert!(
+            matches!(grace("祝 福 を 見 出 し た"), Recognition::Found(text, _) if text == "祝福を見出した")
+        );
+        assert!(
+            matches!(grace("祝福を見出した"), Recognition::Found(text, conf) if text == "祝福を見出した" && conf == Confidence::new(90))
+        );
+        assert!(
+            matches!(grace("祝福を見出Lた"), Recognition::Found(text, conf) if text == "祝福を見出した" && conf == Confidence::new(72))
+        );
+        assert!(
+            matches!(grace("Lost Grace Discovered"), Recognition::Found(text, _) if text == "LOST GRACE DISCOVERED")
+        );
+        assert!(matches!(grace("YOU DIED"), Recognition::Possible(..)));
+    }
+
+    #[test]
+    fn boss_name() {
+        let boss = |text, conf| super::boss_name(text, Confidence::new(conf));
+        assert!(
+            matches!(boss("忌 み 鬼 、 マ ル ギ ッ ト", 90), Recognition::Found(text, _) if text == "忌み鬼、マルギット")
+        );
+        assert!(matches!(
+            boss("忌み鬼、マルギット", 30),
+            Recognition::Possible(..)
+        ));
+        // Commas are not in place names
+        assert!(matches!(
+            super::place_name("忌み鬼、マルギット", Confidence::new(90)),
+            Recognition::Possible(..)
+        ));
+    }
+
+    #[test]
+    fn rune_count() {
+        let runes = |text| super::rune_count(text, Confidence::new(90));
+        assert!(matches!(runes("12345"), Recognition::Found(text, _) if text == "12345"));
+        assert!(matches!(runes("l2O45"), Recognition::Found(text, _) if text == "12045"));
+        assert!(matches!(runes("0"), Recognition::Found(..)));
+        assert!(matches!(runes("012"), Recognition::Possible(..)));
+        assert!(matches!(runes("12,345"), Recognition::Possible(..)));
+        assert!(matches!(runes(""), Recognition::Possible(..)));
+    }
+
+    #[test]
+    fn run_matched_without_corrections() {
+        let conf = Confidence::new(90);
+        let found = |pp: PostProcess, text| match pp.run_matched(text, conf) {
+            Recognition::Found(text, _) => Some(text),
+            Recognition::Possible(..) => None,
+        };
+        assert_eq!(found(PostProcess::Digits, "×12").as_deref(), Some("12"));
+        assert_eq!(found(PostProcess::Digits, "1O"), None);
+        assert_eq!(found(PostProcess::ItemCount, "×3").as_deref(), Some("×3"));
+        assert_eq!(found(PostProcess::RuneCount, "l2345"), None);
+        assert_eq!(found(PostProcess::None, "HP").as_deref(), Some("HP"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops::Range;
 
 use color_eyre::eyre;
-use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect};
+use elden_analyzer_kernel::types::{clip_rect::ClipRect, rect::Rect, time::FramePosition};
 use elden_analyzer_video::capture::Frame;
 use imageproc::{
     contrast::{self, ThresholdType},
@@ -17,26 +17,39 @@ use imageproc::{
 use tracing::trace;
 
 use crate::{
-    image_process::tesseract::Tesseract, operator::Confidence, util::ImageLogger,
+    image_process::tesseract::{RecognizeTimeout, Tesseract},
+    operator::Confidence,
+    util::{Explainer, ImageLogger},
     video_capture::FrameExt as _,
 };
 
 use super::{ExtractText, PostProcess, Recognition};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum TextAlign {
     Left,
     Right,
     Center,
+    #[default]
     Unspecified,
 }
 
+/// Height in pixels that texts are resized to before being recognized by Tesseract,
+/// unless `ocr_text_height` is given in the `[pipeline]` table of the config.
+///
+/// The x-height is about the half, which Tesseract reads best.
+/// See <https://github.com/tesseract-ocr/tessdoc/blob/main/tess3/FAQ-Old.md#is-there-a-minimum--maximum-text-size-it-wont-read-screen-text>
+pub const DEFAULT_TEXT_HEIGHT: u32 = 40;
+
 #[derive(Debug)]
 pub struct RectTextExtractorBuilder {
     pub base_rect: ClipRect,
     pub text_rect: ClipRect,
     pub post_process: PostProcess,
     pub align: TextAlign,
+    /// Height in pixels that the text is resized to before recognized
+    pub text_height: u32,
 }
 
 impl RectTextExtractorBuilder {
@@ -48,6 +61,7 @@ impl RectTextExtractorBuilder {
             text_rect,
             post_process: self.post_process,
             align: self.align,
+            text_height: self.text_height,
         })
     }
 }
@@ -58,6 +72,7 @@ pub struct RectTextExtractor {
     text_rect: Rect,
     post_process: PostProcess,
     align: TextAlign,
+    text_height: u32,
 }
 
 impl ExtractText for RectTextExtractor {
@@ -69,9 +84,10 @@ impl ExtractText for RectTextExtractor {
         num_chars: Option<usize>,
     ) -> eyre::Result<Recognition> {
         let logger = ImageLogger::get();
+        let _stage = ImageLogger::enter_stage("recognize");
 
-        if logger.display_image() {
-            logger.log(frame.to_rgb_image_within(self.base_rect).unwrap());
+        if logger.enabled() {
+            logger.log(frame.to_rgb_image_within(self.base_rect)?);
         }
 
         recognize(
@@ -79,6 +95,7 @@ impl ExtractText for RectTextExtractor {
             self.text_rect,
             self.post_process,
             self.align,
+            self.text_height,
             frame,
             num_chars,
         )
@@ -90,10 +107,10 @@ fn recognize(
     text_rect: Rect,
     pp: PostProcess,
     align: TextAlign,
+    expected_height: u32,
     frame: &Frame,
     num_chars: Option<usize>,
 ) -> eyre::Result<Recognition> {
-    let expected_height = 40; // x-height is 20px. see https://github.com/tesseract-ocr/tessdoc/blob/main/tess3/FAQ-Old.md#is-there-a-minimum--maximum-text-size-it-wont-read-screen-text
     let min_trim_width = 40;
     let trim_margin = 10;
     let clip_scale_factor = 1.2;
@@ -105,9 +122,12 @@ fn recognize(
     let mask_close_k = 2;
 
     let logger = ImageLogger::get();
+    let explainer = Explainer::get();
+    explainer.note(|| format!("text rect {text_rect}, align {align:?}, post-process {pp:?}"));
 
-    let rgb_image = tracing::trace_span!("rgb")
-        .in_scope(|| logger.log(frame.to_rgb_image_within(text_rect).unwrap()));
+    let rgb_image =
+        tracing::trace_span!("rgb").in_scope(|| frame.to_rgb_image_within(text_rect))?;
+    let rgb_image = logger.log(rgb_image);
 
     let size_scale = expected_height as f32 / text_rect.height() as f32;
     trace!(?size_scale);
@@ -139,14 +159,22 @@ fn recognize(
         tracing::trace_span!("otsu-level").in_scope(|| contrast::otsu_level(&gray_image));
     tracing::trace!(recognize_binary_threshold);
     let binary_image = tracing::trace_span!("binary").in_scope(|| {
-        logger.log(contrast::threshold(
+        contrast::threshold(
             &gray_image,
             recognize_binary_threshold,
             ThresholdType::BinaryInverted,
-        ))
+        )
     });
-    let (text1, conf1) = match do_recognize(tess, &binary_image, pp, num_chars)? {
-        Recognition::Found(text1, conf1) => return Ok(Recognition::Found(text1, conf1)),
+    let res = explainer.scope(
+        || format!("attempt 1: binarized with Otsu threshold {recognize_binary_threshold}"),
+        || do_recognize(tess, &binary_image, frame.position(), pp, num_chars),
+    )?;
+    logger.log_with_caption(binary_image, || format!("attempt 1: {res}"));
+    let (text1, conf1) = match res {
+        Recognition::Found(text1, conf1) => {
+            explainer.note(|| "attempt 1 is found => accepted".into());
+            return Ok(Recognition::Found(text1, conf1));
+        }
         Recognition::Possible(text1, conf1) => (text1, conf1),
     };
 
@@ -181,19 +209,33 @@ fn recognize(
         tracing::trace_span!("otsu-level").in_scope(|| contrast::otsu_level(&masked));
     trace!(?masked_binary_threshold);
     let masked_binary_image = tracing::trace_span!("binary").in_scope(|| {
-        logger.log(contrast::threshold(
+        contrast::threshold(
             &masked,
             masked_binary_threshold,
             ThresholdType::BinaryInverted,
-        ))
+        )
     });
 
-    let res = match do_recognize(tess, &masked_binary_image, pp, num_chars)? {
-        Recognition::Found(text2, conf2) => Recognition::Found(text2, conf2),
+    let res = explainer.scope(
+        || format!("attempt 2: masked and binarized with Otsu threshold {masked_binary_threshold}"),
+        || do_recognize(tess, &masked_binary_image, frame.position(), pp, num_chars),
+    )?;
+    logger.log_with_caption(masked_binary_image, || format!("attempt 2: {res}"));
+    let res = match res {
+        Recognition::Found(text2, conf2) => {
+            explainer.note(|| "attempt 2 is found => accepted".into());
+            Recognition::Found(text2, conf2)
+        }
         Recognition::Possible(text2, conf2) => {
             if conf1 >= conf2 {
+                explainer.note(|| {
+                    "both attempts are possible => attempt 1 has higher confidence".into()
+                });
                 Recognition::Possible(text1, conf1)
             } else {
+                explainer.note(|| {
+                    "both attempts are possible => attempt 2 has higher confidence".into()
+                });
                 Recognition::Possible(text2, conf2)
             }
         }
@@ -204,17 +246,46 @@ fn recognize(
 fn do_recognize(
     tess: &mut Tesseract,
     binary_image: &GrayImage,
+    pos: FramePosition,
+    pp: PostProcess,
+    num_chars: Option<usize>,
+) -> eyre::Result<Recognition> {
+    let result = tess.recognize(binary_image);
+    recognition_of(result, pos, pp, num_chars)
+}
+
+/// Post-processes the result of OCR of the frame at `pos`, regarding a timed-out recognition as possible.
+fn recognition_of(
+    result: eyre::Result<(String, i32)>,
+    pos: FramePosition,
     pp: PostProcess,
     num_chars: Option<usize>,
 ) -> eyre::Result<Recognition> {
-    let (text, conf) = tess.recognize(binary_image)?;
+    let explainer = Explainer::get();
+
+    let (text, conf) = match result {
+        Ok(result) => result,
+        Err(err) if err.is::<RecognizeTimeout>() => {
+            let component = ImageLogger::subject_component();
+            tracing::warn!(component, %pos, "{err}");
+            explainer.note(|| format!("OCR: {err} => possible"));
+            return Ok(Recognition::Possible(String::new(), Confidence::new(0)));
+        }
+        Err(err) => return Err(err),
+    };
     let conf = Confidence::new(conf);
-    let (text, conf) = match pp.run(&text, conf) {
+    explainer.note(|| format!("OCR: {text:?} (confidence {conf})"));
+    let res = explainer.scope(|| format!("post-process {pp:?}"), || pp.run(&text, conf));
+    explainer.note(|| format!("post-processed: {res}"));
+    let (text, conf) = match res {
         Recognition::Found(text, conf) => (text, conf),
         Recognition::Possible(text, conf) => return Ok(Recognition::Possible(text, conf)),
     };
     let res = match num_chars {
-        Some(num_chars) if text.chars().count() != num_chars => Recognition::Possible(text, conf),
+        Some(num_chars) if text.chars().count() != num_chars => {
+            explainer.note(|| format!("expected {num_chars} characters => possible"));
+            Recognition::Possible(text, conf)
+        }
         _ => Recognition::Found(text, conf),
     };
     Ok(res)
@@ -283,6 +354,7 @@ fn clip_image(
     tracing::trace_span!("clip").in_scope(|| {
         if let Some(clip_rect) = find_clip_rect(&clip_binary, align, min_trim_width, trim_margin) {
             tracing::trace!(?clip_rect);
+            Explainer::get().note(|| format!("text clipped to {clip_rect} of the resized image"));
             logger.log(GrayImage::from_fn(
                 clip_rect.width(),
                 clip_rect.height(),
@@ -359,6 +431,11 @@ fn true_segments(vs: &[bool]) -> impl Iterator<Item = Range<usize>> + '_ {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use elden_analyzer_kernel::types::time::Timestamp;
+    use num_rational::Ratio;
+
     use super::*;
 
     #[test]
@@ -378,4 +455,20 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn timed_out_recognition_is_possible() {
+        let pos = FramePosition::new(3, Timestamp::new(Ratio::new(3, 30)));
+        let timeout = RecognizeTimeout(Duration::from_millis(10));
+        let res = recognition_of(Err(timeout.into()), pos, PostProcess::Digits, None).unwrap();
+        assert!(
+            matches!(res, Recognition::Possible(text, conf) if text.is_empty() && conf == Confidence::new(0))
+        );
+
+        let res = recognition_of(Ok(("12".into(), 90)), pos, PostProcess::Digits, Some(2)).unwrap();
+        assert!(matches!(res, Recognition::Found(text, _) if text == "12"));
+
+        let err = eyre::eyre!("text recognition failed");
+        assert!(recognition_of(Err(err), pos, PostProcess::Digits, None).is_err());
+    }
 }
@@ -0,0 +1,127 @@
+//! Named profiles bundling defaults for common recording setups.
+
+use std::{collections::BTreeMap, sync::LazyLock};
+
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use serde::Deserialize;
+
+use crate::{config::Config, i18n::Lang};
+
+const PROFILES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/profiles.toml"));
+
+static BUILTIN: LazyLock<BTreeMap<String, Profile>> =
+    LazyLock::new(|| toml::from_str(PROFILES).expect("built-in profiles must be valid"));
+
+/// Defaults for a recording setup, defined in `assets/profiles.toml`.
+///
+/// Values specified on the command line take precedence over the profile.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub description: String,
+    /// Frame size of videos recorded in the setup
+    pub frame_size: (u32, u32),
+    /// Frame rate of videos recorded in the setup
+    pub fps: u32,
+    /// Default of `analyze --lead-in`
+    #[serde(default)]
+    pub lead_in: Option<usize>,
+    /// Default of `analyze --promote-window`
+    #[serde(default)]
+    pub promote_window: Option<usize>,
+    /// Default of `--translate-items`
+    #[serde(default)]
+    pub translate_items: Option<Lang>,
+    /// Thresholds of detector areas (`DETECTOR.AREA`), overriding the configuration
+    #[serde(default)]
+    pub thresholds: BTreeMap<String, f32>,
+}
+
+impl Profile {
+    /// Returns the built-in profile named `name`.
+    pub fn get(name: &str) -> Option<&'static Self> {
+        BUILTIN.get(name)
+    }
+
+    /// Returns the names of the built-in profiles.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.keys().map(String::as_str)
+    }
+
+    /// Returns the built-in profiles with their names.
+    pub fn all() -> impl Iterator<Item = (&'static str, &'static Self)> {
+        BUILTIN
+            .iter()
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    /// Returns the name of the built-in profile whose setup is the closest to videos of the frame size and rate.
+    ///
+    /// The frame height is compared first, and the frame rate breaks ties.
+    pub fn suggest((_width, height): (u32, u32), fps: f64) -> &'static str {
+        Self::all()
+            .min_by(|(_, a), (_, b)| {
+                let distance = |p: &Self| {
+                    (
+                        p.frame_size.1.abs_diff(height),
+                        (f64::from(p.fps) - fps).abs(),
+                    )
+                };
+                let (a, b) = (distance(a), distance(b));
+                a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+            })
+            .map(|(name, _)| name)
+            .expect("built-in profiles must not be empty")
+    }
+
+    /// Overrides thresholds of detector areas in `config`.
+    pub fn apply(&self, config: &mut Config) -> eyre::Result<()> {
+        for (name, threshold) in &self.thresholds {
+            let (detector, area) = name
+                .split_once('.')
+                .ok_or_else(|| eyre!("invalid threshold `{name}`: expected `DETECTOR.AREA`"))?;
+            config
+                .set_area_threshold(detector, area, *threshold)
+                .wrap_err_with(|| format!("invalid threshold `{name}`"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles() {
+        let names = Profile::names().collect::<Vec<_>>();
+        assert_eq!(names, ["obs-720p60", "pc-4k-en", "ps5-1080p-jp"]);
+        for name in names {
+            let mut config = Config::default();
+            Profile::get(name).unwrap().apply(&mut config).unwrap();
+        }
+
+        let profile = Profile::get("obs-720p60").unwrap();
+        let mut config = Config::default();
+        profile.apply(&mut config).unwrap();
+        let bg = config.detectors["side_item_one_digit"]
+            .areas
+            .iter()
+            .find(|area| area.name == "BG")
+            .unwrap();
+        assert_eq!(bg.threshold, 0.98);
+
+        assert_eq!(
+            Profile::get("pc-4k-en").unwrap().translate_items,
+            Some(Lang::En)
+        );
+    }
+
+    #[test]
+    fn suggest() {
+        assert_eq!(Profile::suggest((1920, 1080), 29.97), "ps5-1080p-jp");
+        assert_eq!(Profile::suggest((1280, 720), 60.0), "obs-720p60");
+        assert_eq!(Profile::suggest((3840, 2160), 60.0), "pc-4k-en");
+        assert_eq!(Profile::suggest((2560, 1440), 60.0), "ps5-1080p-jp");
+    }
+}
@@ -0,0 +1,394 @@
+//! Route file and comparison of detected spans against it.
+
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{self, WrapErr as _};
+use elden_analyzer_kernel::types::time::{Duration, Timestamp};
+use num_rational::Ratio;
+use serde::{Deserialize, Deserializer};
+
+use crate::{components::ComponentFilter, io::spans::Span};
+
+/// Ordered list of events expected in a run.
+///
+/// # Examples
+/// ```toml
+/// # Components or groups whose spans are compared (default: all components)
+/// components = ["main_item"]
+///
+/// [[checkpoints]]
+/// text = "聖杯瓶"
+///
+/// [[checkpoints]]
+/// label = "Margit"
+/// text = "ストームヴィルの兜"
+/// target = "00:18:30"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    #[serde(default)]
+    pub components: Vec<String>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+/// Event expected in a run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Checkpoint {
+    /// Name shown in reports (default: `text`)
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Text of the span.
+    /// Spans with additional segments such as item counts (`聖杯瓶 ×3`) also match.
+    pub text: String,
+    /// Target time of the event, compared with the in-game time if recorded, or the video time otherwise
+    #[serde(default, deserialize_with = "opt_timestamp")]
+    pub target: Option<Timestamp>,
+}
+
+impl Checkpoint {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.text)
+    }
+
+    /// Returns `true` if `span` is the event of this checkpoint.
+    pub fn matches(&self, span: &Span) -> bool {
+        match span.text.strip_prefix(&self.text) {
+            Some(rest) => rest.is_empty() || rest.starts_with(' '),
+            None => false,
+        }
+    }
+}
+
+/// Result of comparing spans against a route.
+#[derive(Debug)]
+pub struct Comparison<'a> {
+    /// Results of checkpoints in the order of the route
+    pub checkpoints: Vec<CheckpointResult<'a>>,
+    /// Spans not matched to any checkpoint, in the order of their start
+    pub extras: Vec<&'a Span>,
+}
+
+#[derive(Debug)]
+pub struct CheckpointResult<'a> {
+    pub checkpoint: &'a Checkpoint,
+    /// Span matched to the checkpoint, or `None` if missed
+    pub span: Option<&'a Span>,
+}
+
+impl CheckpointResult<'_> {
+    /// Returns the time of the matched span, in the in-game time if recorded.
+    pub fn actual(&self) -> Option<Timestamp> {
+        let span = self.span?;
+        Some(match span.igt {
+            Some(igt) => Timestamp::new(igt.as_ratio()),
+            None => span.start,
+        })
+    }
+
+    /// Returns the actual time minus the target time in seconds.
+    pub fn delta(&self) -> Option<Ratio<i64>> {
+        Some(self.actual()?.as_ratio() - self.checkpoint.target?.as_ratio())
+    }
+}
+
+impl Route {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read route file: {}", path.display()))?;
+        toml::from_str(&text)
+            .wrap_err_with(|| format!("failed to parse route file: {}", path.display()))
+    }
+
+    /// Aligns `spans` against the checkpoints.
+    ///
+    /// Checkpoints are matched to spans in order, maximizing the number of matched checkpoints.
+    /// Possible spans, skipped intervals and spans of components not selected by [`Route::components`] are ignored.
+    pub fn compare<'a>(&'a self, spans: &'a [Span]) -> Comparison<'a> {
+        self.compare_with(spans, &ComponentFilter::new(&self.components))
+    }
+
+    /// Aligns `spans` against the checkpoints like [`Route::compare`], comparing spans of components selected by
+    /// `components` instead, such as [`Route::components`] with groups resolved by [`Config::component_filter`].
+    ///
+    /// [`Config::component_filter`]: crate::config::Config::component_filter
+    pub fn compare_with<'a>(
+        &'a self,
+        spans: &'a [Span],
+        components: &ComponentFilter,
+    ) -> Comparison<'a> {
+        let mut spans = spans
+            .iter()
+            .filter(|span| !span.is_possible() && !span.is_skipped())
+            .filter(|span| components.matches(&span.name))
+            .collect::<Vec<_>>();
+        spans.sort_by_key(|span| span.start);
+
+        // Longest common subsequence, where `lcs[i][j]` is the number of matches
+        // between `checkpoints[i..]` and `spans[j..]`
+        let (n, m) = (self.checkpoints.len(), spans.len());
+        let mut lcs = vec![vec![0; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if self.checkpoints[i].matches(spans[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut checkpoints = vec![];
+        let mut extras = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            let checkpoint = &self.checkpoints[i];
+            if checkpoint.matches(spans[j]) && lcs[i][j] == lcs[i + 1][j + 1] + 1 {
+                checkpoints.push(CheckpointResult {
+                    checkpoint,
+                    span: Some(spans[j]),
+                });
+                i += 1;
+                j += 1;
+            } else if lcs[i][j + 1] >= lcs[i + 1][j] {
+                extras.push(spans[j]);
+                j += 1;
+            } else {
+                checkpoints.push(CheckpointResult {
+                    checkpoint,
+                    span: None,
+                });
+                i += 1;
+            }
+        }
+        checkpoints.extend(
+            self.checkpoints[i..]
+                .iter()
+                .map(|checkpoint| CheckpointResult {
+                    checkpoint,
+                    span: None,
+                }),
+        );
+        extras.extend(&spans[j..]);
+
+        Comparison {
+            checkpoints,
+            extras,
+        }
+    }
+}
+
+/// Result of [`Route::bisect`].
+#[derive(Debug)]
+pub enum Bisection {
+    /// Span of the target checkpoint found by a probe
+    Found(Span),
+    /// The span of the target checkpoint is in this range of the video, if the video has it
+    Window(Timestamp, Timestamp),
+}
+
+impl Route {
+    /// Returns the index of the checkpoint matching `span`, if exactly one checkpoint matches it.
+    ///
+    /// Spans whose texts appear at several checkpoints, such as items picked up again later, give no position in the route.
+    pub fn checkpoint_index(&self, span: &Span) -> Option<usize> {
+        let mut indices = self
+            .checkpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, checkpoint)| checkpoint.matches(span))
+            .map(|(idx, _)| idx);
+        let first = indices.next()?;
+        indices.next().is_none().then_some(first)
+    }
+
+    /// Narrows the range from `start` to `end` of a video where the checkpoint at `target` is passed, by bisection.
+    ///
+    /// `probe(from, to)` scans the video from `from` to `to` and returns the first span whose checkpoint is given by
+    /// [`Route::checkpoint_index`], or `None`. As checkpoints are passed in order, a checkpoint found in the latter half
+    /// of the range tells which half the target is in, so most of the video is skipped or scanned only once by probes.
+    /// Bisection ends when the range is not longer than `min_window`.
+    pub fn bisect(
+        &self,
+        target: usize,
+        start: Timestamp,
+        end: Timestamp,
+        min_window: Duration,
+        mut probe: impl FnMut(Timestamp, Timestamp) -> eyre::Result<Option<Span>>,
+    ) -> eyre::Result<Bisection> {
+        let (mut lo, mut hi) = (start, end);
+        while hi > lo && hi - lo > min_window {
+            let mid = Timestamp::new((lo.as_ratio() + hi.as_ratio()) / 2);
+            tracing::debug!(%lo, %hi, %mid, "probing");
+            let Some(span) = probe(mid, hi)? else {
+                hi = mid;
+                continue;
+            };
+            match self.checkpoint_index(&span) {
+                Some(idx) if idx == target => return Ok(Bisection::Found(span)),
+                Some(idx) if idx < target => lo = span.end.max(mid),
+                _ => hi = mid,
+            }
+        }
+        Ok(Bisection::Window(lo, hi))
+    }
+}
+
+fn opt_timestamp<'de, D>(deserializer: D) -> Result<Option<Timestamp>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(s) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    s.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(components: &[&str], texts: &[&str]) -> Route {
+        Route {
+            components: components.iter().map(|s| s.to_string()).collect(),
+            checkpoints: texts
+                .iter()
+                .map(|text| Checkpoint {
+                    label: None,
+                    text: text.to_string(),
+                    target: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn spans(spans: &[(&str, u32, &str)]) -> Vec<Span> {
+        spans
+            .iter()
+            .map(|(name, sec, text)| {
+                format!(
+                    "00:00:{sec:02}.000-00:00:{:02}.000 {text} ({name})",
+                    sec + 1
+                )
+                .parse()
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn texts<'a>(comparison: &Comparison<'a>) -> (Vec<Option<&'a str>>, Vec<&'a str>) {
+        let checkpoints = comparison
+            .checkpoints
+            .iter()
+            .map(|res| res.span.map(|span| span.text.as_str()))
+            .collect();
+        let extras = comparison
+            .extras
+            .iter()
+            .map(|span| span.text.as_str())
+            .collect();
+        (checkpoints, extras)
+    }
+
+    #[test]
+    fn missed_and_extra() {
+        let route = route(&[], &["a", "b", "c", "d"]);
+        let spans = spans(&[
+            ("main_item", 1, "a"),
+            ("main_item", 2, "x"),
+            ("main_item", 3, "c ×2"),
+            ("main_item", 4, "d"),
+            ("main_item", 5, "b"),
+        ]);
+        let comparison = route.compare(&spans);
+        assert_eq!(
+            texts(&comparison),
+            (
+                vec![Some("a"), None, Some("c ×2"), Some("d")],
+                vec!["x", "b"]
+            )
+        );
+    }
+
+    #[test]
+    fn filter_spans() {
+        let route = route(&["main_item"], &["a"]);
+        let spans = spans(&[
+            ("side_item0", 1, "a"),
+            ("main_item", 2, "??a"),
+            ("main_item", 3, "ab"),
+            ("main_item", 4, "a"),
+        ]);
+        let comparison = route.compare(&spans);
+        assert_eq!(texts(&comparison), (vec![Some("a")], vec!["ab"]));
+    }
+
+    #[test]
+    fn bisect() {
+        let route = route(&[], &["a", "b", "c", "d", "a"]);
+        let spans = spans(&[
+            ("main_item", 5, "b"),
+            ("main_item", 20, "x"),
+            ("main_item", 30, "c"),
+            ("main_item", 42, "a"),
+            ("main_item", 50, "d"),
+        ]);
+        assert_eq!(route.checkpoint_index(&spans[2]), Some(2));
+        assert_eq!(route.checkpoint_index(&spans[3]), None);
+
+        let sec = |sec: i64| Timestamp::new(Ratio::from_integer(sec));
+        let bisect = |target: usize| {
+            let mut probes = vec![];
+            let result = route
+                .bisect(
+                    target,
+                    sec(0),
+                    sec(60),
+                    Duration::new(Ratio::from_integer(10)),
+                    |from, to| {
+                        probes.push((from.as_ratio().to_integer(), to.as_ratio().to_integer()));
+                        Ok(spans
+                            .iter()
+                            .find(|span| {
+                                from <= span.start
+                                    && span.start < to
+                                    && route.checkpoint_index(span).is_some()
+                            })
+                            .cloned())
+                    },
+                )
+                .unwrap();
+            (result, probes)
+        };
+
+        // "d" is after "c" found from 30 s
+        let (result, probes) = bisect(3);
+        assert!(matches!(result, Bisection::Found(span) if span.text == "d"));
+        assert_eq!(probes, [(30, 60), (45, 60)]);
+
+        // "b" is before "c" found from 30 s and nothing from 15 s to 30 s
+        let (result, probes) = bisect(1);
+        assert!(
+            matches!(result, Bisection::Window(lo, hi) if (lo, hi) == (sec(0), Timestamp::new(Ratio::new(15, 2))))
+        );
+        assert_eq!(probes, [(30, 60), (15, 30), (7, 15)]);
+    }
+
+    #[test]
+    fn delta() {
+        let route = toml::from_str::<Route>(
+            r#"
+            [[checkpoints]]
+            text = "a"
+            target = "00:00:03"
+            "#,
+        )
+        .unwrap();
+        let spans = vec!["00:00:05.000-00:00:06.000 a (main_item) @00:00:02.500"
+            .parse::<Span>()
+            .unwrap()];
+        let comparison = route.compare(&spans);
+        assert_eq!(comparison.checkpoints[0].delta(), Some(Ratio::new(-1, 2)));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock, Mutex,
+    },
+};
+
+static EXPLAINER: LazyLock<Explainer> = LazyLock::new(Explainer::new);
+
+/// Records intermediate decisions of detection and recognition as a tree.
+///
+/// Recording is disabled until [`Explainer::start`] is called, so notes cost nothing in the analysis pipeline.
+/// Decisions made in multiple threads are interleaved, so the recording is meant for processing a single frame.
+#[derive(Debug)]
+pub struct Explainer {
+    enabled: AtomicBool,
+    /// Nodes from the root to the innermost open scope
+    stack: Mutex<Vec<ExplainNode>>,
+}
+
+/// A decision and the decisions leading to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainNode {
+    pub label: String,
+    pub children: Vec<ExplainNode>,
+}
+
+impl Explainer {
+    fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            stack: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn get() -> &'static Self {
+        &EXPLAINER
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Starts recording under a root node labeled `label`.
+    pub fn start(&self, label: impl Into<String>) {
+        let mut stack = self.stack.lock().unwrap();
+        *stack = vec![ExplainNode::new(label.into())];
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops recording and returns the recorded tree.
+    pub fn finish(&self) -> Option<ExplainNode> {
+        self.enabled.store(false, Ordering::Relaxed);
+        let mut stack = self.stack.lock().unwrap();
+        while stack.len() > 1 {
+            let node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(node);
+        }
+        stack.pop()
+    }
+
+    /// Records a decision in the current scope.
+    ///
+    /// `label` is evaluated only while recording.
+    pub fn note(&self, label: impl FnOnce() -> String) {
+        if !self.enabled() {
+            return;
+        }
+        let mut stack = self.stack.lock().unwrap();
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(ExplainNode::new(label()));
+        }
+    }
+
+    /// Runs `f` in a new scope, so that decisions recorded in `f` are nested under `label`.
+    ///
+    /// `label` is evaluated only while recording.
+    pub fn scope<T>(&self, label: impl FnOnce() -> String, f: impl FnOnce() -> T) -> T {
+        if !self.enabled() {
+            return f();
+        }
+        self.stack.lock().unwrap().push(ExplainNode::new(label()));
+        let res = f();
+        let mut stack = self.stack.lock().unwrap();
+        if stack.len() > 1 {
+            let node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(node);
+        }
+        res
+    }
+}
+
+impl ExplainNode {
+    fn new(label: String) -> Self {
+        Self {
+            label,
+            children: vec![],
+        }
+    }
+
+    fn fmt_children(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, indent) = if last {
+                ("└─ ", "   ")
+            } else {
+                ("├─ ", "│  ")
+            };
+            writeln!(f, "{prefix}{branch}{}", child.label)?;
+            child.fmt_children(f, &format!("{prefix}{indent}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExplainNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.label)?;
+        self.fmt_children(f, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_tree() {
+        let explainer = Explainer::new();
+        explainer.note(|| unreachable!());
+        explainer.start("root");
+        explainer.scope(
+            || "a".into(),
+            || {
+                explainer.note(|| "a1".into());
+                explainer.scope(|| "a2".into(), || explainer.note(|| "a2x".into()));
+            },
+        );
+        explainer.note(|| "b".into());
+        let tree = explainer.finish().unwrap();
+        assert!(!explainer.enabled());
+        assert_eq!(
+            tree.to_string(),
+            "root\n├─ a\n│  ├─ a1\n│  └─ a2\n│     └─ a2x\n└─ b\n"
+        );
+    }
+}
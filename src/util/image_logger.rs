@@ -1,34 +1,106 @@
 use std::{
+    cell::RefCell,
+    fs,
+    marker::PhantomData,
     mem,
-    sync::{Arc, LazyLock, Mutex},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex,
+    },
 };
 
-use color_eyre::eyre;
+use color_eyre::eyre::{self, WrapErr as _};
 use imageproc::image::{buffer::ConvertBuffer, imageops, Rgb, RgbImage};
 
+use crate::i18n::Message;
+
+use super::Explainer;
+
 static CONFIG: LazyLock<Mutex<Option<ImageLoggerConfig>>> = LazyLock::new(|| Mutex::new(None));
 static LOGGER: LazyLock<ImageLogger> = LazyLock::new(ImageLogger::new);
+static DISPLAY_DISABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Frame, component and stages the images logged in the thread are of
+    static CONTEXT: RefCell<Context> = const { RefCell::new(Context::new()) };
+}
 
 #[derive(Debug, Clone)]
 struct ImageLoggerConfig {
     display_image: bool,
+    dump: Option<ImageDump>,
+}
+
+/// Destination of images written as PNG files instead of or as well as being displayed.
+#[derive(Debug, Clone)]
+pub struct ImageDump {
+    pub dir: PathBuf,
+    /// Stages whose images are written, or all stages if empty (see [`ImageLogger::STAGES`])
+    pub stages: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Context {
+    frame: Option<usize>,
+    component: Option<String>,
+    stages: Vec<&'static str>,
+}
+
+impl Context {
+    const fn new() -> Self {
+        Self {
+            frame: None,
+            component: None,
+            stages: vec![],
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ImageLogger(Arc<ImageLoggerInner>);
 
 impl ImageLogger {
+    pub const DISPLAY_DISABLED_MESSAGE: Message = Message::new(
+        "displaying images is disabled",
+        "画像の表示は無効になっています",
+    );
+
+    /// Stages images are logged in, which select the images written by [`ImageDump`]
+    pub const STAGES: [&'static str; 5] =
+        ["detect", "line_finder", "h_lines", "histogram", "recognize"];
+
     fn new() -> Self {
         let conf = CONFIG.lock().unwrap().clone().unwrap();
         Self(Arc::new(ImageLoggerInner::new(conf)))
     }
 
     pub fn init(display_image: bool) -> eyre::Result<()> {
+        Self::init_with_dump(display_image, None)
+    }
+
+    /// Initializes the logger, writing images into the directory of `dump` if given.
+    ///
+    /// Images are written even if displaying images is disabled by [`ImageLogger::disable_display`].
+    pub fn init_with_dump(display_image: bool, dump: Option<ImageDump>) -> eyre::Result<()> {
         let mut conf = CONFIG.lock().unwrap();
         if conf.is_some() {
             eyre::bail!("ImageLogger is already initialized")
         }
-        *conf = Some(ImageLoggerConfig { display_image });
+        let disabled = DISPLAY_DISABLED.load(Ordering::Relaxed);
+        if display_image && disabled {
+            tracing::warn!("{}", Self::DISPLAY_DISABLED_MESSAGE);
+        }
+        let display_image = display_image && !disabled;
+        if let Some(dump) = &dump {
+            fs::create_dir_all(&dump.dir).wrap_err_with(|| {
+                format!("failed to create image directory: {}", dump.dir.display())
+            })?;
+        }
+        *conf = Some(ImageLoggerConfig {
+            display_image,
+            dump,
+        });
         Ok(())
     }
 
@@ -36,10 +108,56 @@ impl ImageLogger {
         &LOGGER
     }
 
+    /// Disables displaying images in environments without a display.
+    ///
+    /// `display_image` passed to [`ImageLogger::init`] afterwards is ignored.
+    pub fn disable_display() {
+        DISPLAY_DISABLED.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `false` if displaying images is disabled by [`ImageLogger::disable_display`].
+    pub fn display_available() -> bool {
+        !DISPLAY_DISABLED.load(Ordering::Relaxed)
+    }
+
     pub fn display_image(&self) -> bool {
         self.0.display_image()
     }
 
+    /// Returns `true` if images logged now are displayed or written, so that callers can skip making them otherwise.
+    pub fn enabled(&self) -> bool {
+        self.0.enabled()
+    }
+
+    /// Names the images logged in the thread after `frame` and `component`, until the guard is dropped.
+    pub fn enter_subject(frame: usize, component: &str) -> SubjectGuard {
+        let prev = CONTEXT.with_borrow_mut(|context| {
+            let prev = (context.frame, context.component.take());
+            context.frame = Some(frame);
+            context.component = Some(component.to_owned());
+            prev
+        });
+        SubjectGuard {
+            prev,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the component of the subject entered by the thread, to tell which component a message is about.
+    pub fn subject_component() -> Option<String> {
+        CONTEXT.with_borrow(|context| context.component.clone())
+    }
+
+    /// Makes the images logged in the thread belong to `stage`, one of [`ImageLogger::STAGES`],
+    /// nested in the stages entered before, until the guard is dropped.
+    pub fn enter_stage(stage: &'static str) -> StageGuard {
+        debug_assert!(Self::STAGES.contains(&stage), "unknown stage: {stage}");
+        CONTEXT.with_borrow_mut(|context| context.stages.push(stage));
+        StageGuard {
+            _not_send: PhantomData,
+        }
+    }
+
     pub fn log<T>(&self, img: T) -> T
     where
         T: ConvertBuffer<RgbImage>,
@@ -47,6 +165,17 @@ impl ImageLogger {
         self.0.log(img)
     }
 
+    /// Logs an image paired with the values computed from it.
+    ///
+    /// The image is stamped with a number, and the caption is emitted to the log and the explanation with the same number.
+    /// `caption` is evaluated only while images are displayed or written.
+    pub fn log_with_caption<T>(&self, img: T, caption: impl FnOnce() -> String) -> T
+    where
+        T: ConvertBuffer<RgbImage>,
+    {
+        self.0.log_with_caption(img, caption)
+    }
+
     pub fn display(&self, title: &str) {
         self.0.display(title);
     }
@@ -56,17 +185,64 @@ impl ImageLogger {
     }
 }
 
+/// Guard returned by [`ImageLogger::enter_subject`].
+#[derive(Debug)]
+#[must_use]
+pub struct SubjectGuard {
+    prev: (Option<usize>, Option<String>),
+    /// The context is of the thread entering the subject
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for SubjectGuard {
+    fn drop(&mut self) {
+        let prev = mem::take(&mut self.prev);
+        CONTEXT.with_borrow_mut(|context| (context.frame, context.component) = prev);
+    }
+}
+
+/// Guard returned by [`ImageLogger::enter_stage`].
+#[derive(Debug)]
+#[must_use]
+pub struct StageGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for StageGuard {
+    fn drop(&mut self) {
+        CONTEXT.with_borrow_mut(|context| context.stages.pop());
+    }
+}
+
 #[derive(Debug)]
 struct ImageLoggerInner {
     conf: ImageLoggerConfig,
-    images: Mutex<Vec<Vec<RgbImage>>>,
+    images: Mutex<Images>,
+    /// Number of images written, which orders the file names
+    num_dumped: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Images {
+    columns: Vec<Vec<RgbImage>>,
+    num_captions: usize,
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        Self {
+            columns: vec![vec![]],
+            num_captions: 0,
+        }
+    }
 }
 
 impl ImageLoggerInner {
     fn new(conf: ImageLoggerConfig) -> Self {
         Self {
             conf,
-            images: Mutex::new(vec![vec![]]),
+            images: Mutex::new(Images::default()),
+            num_dumped: AtomicUsize::new(0),
         }
     }
 
@@ -74,23 +250,97 @@ impl ImageLoggerInner {
         self.conf.display_image
     }
 
+    /// Returns `true` if images of the current stages are written.
+    fn dump_image(&self) -> bool {
+        let Some(dump) = &self.conf.dump else {
+            return false;
+        };
+        dump.stages.is_empty()
+            || CONTEXT.with_borrow(|context| {
+                (context.stages.iter()).any(|stage| dump.stages.iter().any(|s| s == stage))
+            })
+    }
+
+    fn enabled(&self) -> bool {
+        self.display_image() || self.dump_image()
+    }
+
     fn log<T>(&self, img: T) -> T
     where
         T: ConvertBuffer<RgbImage>,
     {
-        if self.display_image() {
+        if self.enabled() {
             let img = img.convert();
-            self.images.lock().unwrap().last_mut().unwrap().push(img);
+            self.dump(&img);
+            if self.display_image() {
+                let mut images = self.images.lock().unwrap();
+                images.columns.last_mut().unwrap().push(img);
+            }
+        }
+        img
+    }
+
+    fn log_with_caption<T>(&self, img: T, caption: impl FnOnce() -> String) -> T
+    where
+        T: ConvertBuffer<RgbImage>,
+    {
+        if self.enabled() {
+            let mut stamped = img.convert();
+            let caption = caption();
+            let mut images = self.images.lock().unwrap();
+            images.num_captions += 1;
+            let num = images.num_captions;
+            stamp_number(&mut stamped, num);
+            self.dump(&stamped);
+            if self.display_image() {
+                images.columns.last_mut().unwrap().push(stamped);
+            }
+            drop(images);
+
+            tracing::debug!(image = num, "{caption}");
+            Explainer::get().note(|| format!("image #{num}: {caption}"));
         }
         img
     }
 
+    /// Writes the image as `{number}_f{frame}_{component}_{stages}.png` if images of the current stages are written.
+    fn dump(&self, img: &RgbImage) {
+        let Some(dump) = self.conf.dump.as_ref().filter(|_| self.dump_image()) else {
+            return;
+        };
+        let num = self.num_dumped.fetch_add(1, Ordering::Relaxed);
+        let mut name = format!("{num:06}");
+        CONTEXT.with_borrow(|context| {
+            if let Some(frame) = context.frame {
+                name += &format!("_f{frame:06}");
+            }
+            if let Some(component) = &context.component {
+                // Names of custom components may contain characters not allowed in file names
+                let component = component
+                    .chars()
+                    .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+                        true => c,
+                        false => '_',
+                    })
+                    .collect::<String>();
+                name += &format!("_{component}");
+            }
+            if !context.stages.is_empty() {
+                name += &format!("_{}", context.stages.join("."));
+            }
+        });
+        let path = dump.dir.join(name + ".png");
+        if let Err(err) = img.save(&path) {
+            tracing::warn!(path = %path.display(), %err, "failed to write an image");
+        }
+    }
+
     fn display(&self, title: &str) {
         if !self.display_image() {
             return;
         }
 
-        let images = { mem::replace(&mut *self.images.lock().unwrap(), vec![vec![]]) };
+        let images = { mem::take(&mut *self.images.lock().unwrap()).columns };
         if !images.is_empty() {
             let concatenated = concat_images(&images, 10, 10, Rgb([128, 128, 128]));
             imageproc::window::display_image(
@@ -103,7 +353,56 @@ impl ImageLoggerInner {
     }
 
     fn end_column(&self) {
-        self.images.lock().unwrap().push(vec![]);
+        self.images.lock().unwrap().columns.push(vec![]);
+    }
+}
+
+/// Glyphs of digits in 3x5 pixels, one row per element
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Draws `num` at the top-left corner of the image.
+fn stamp_number(img: &mut RgbImage, num: usize) {
+    const SCALE: u32 = 2;
+    const MARGIN: u32 = 1;
+
+    let digits = num.to_string();
+    let width = (digits.len() as u32 * 4 + 1) * SCALE + MARGIN;
+    let height = 7 * SCALE + MARGIN;
+    for y in 0..height.min(img.height()) {
+        for x in 0..width.min(img.width()) {
+            img.put_pixel(x, y, Rgb([0, 0, 0]));
+        }
+    }
+    for (i, digit) in digits.bytes().enumerate() {
+        let glyph = DIGIT_GLYPHS[usize::from(digit - b'0')];
+        let x0 = (i as u32 * 4 + 1) * SCALE;
+        for (gy, row) in glyph.iter().enumerate() {
+            for gx in 0..3 {
+                if row & (0b100 >> gx) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let x = x0 + gx * SCALE + dx;
+                        let y = (gy as u32 + 1) * SCALE + dy;
+                        if x < img.width() && y < img.height() {
+                            img.put_pixel(x, y, Rgb([255, 255, 0]));
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -143,3 +442,42 @@ fn concat_images(
 
     concatenated
 }
+
+#[cfg(test)]
+mod tests {
+    use imageproc::image::GrayImage;
+
+    use super::*;
+
+    #[test]
+    fn dump_images_of_stages() {
+        let dir = std::env::temp_dir().join(format!("elden-analyzer-dump-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let logger = ImageLogger(Arc::new(ImageLoggerInner::new(ImageLoggerConfig {
+            display_image: false,
+            dump: Some(ImageDump {
+                dir: dir.clone(),
+                stages: vec!["recognize".to_owned()],
+            }),
+        })));
+
+        assert!(!logger.enabled());
+        logger.log(GrayImage::new(4, 4));
+        {
+            let _subject = ImageLogger::enter_subject(12, "main item");
+            let _detect = ImageLogger::enter_stage("detect");
+            assert!(!logger.enabled());
+            let _recognize = ImageLogger::enter_stage("recognize");
+            assert!(logger.enabled());
+            logger.log(GrayImage::new(4, 4));
+        }
+        assert!(!logger.enabled());
+
+        let names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["000000_f000012_main_item_detect.recognize.png"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
-pub use self::image_logger::*;
+pub use self::{explainer::*, image_logger::*, path::*};
 
+mod explainer;
 mod image_logger;
+mod path;
@@ -0,0 +1,121 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+/// Maximum length of a file name in bytes on most file systems
+const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Maximum length of a path accepted by Windows APIs without the `\\?\` prefix
+#[cfg(windows)]
+const MAX_PATH_LEN: usize = 260;
+
+/// Names reserved for devices on Windows, regardless of extensions
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Returns a file name usable on all platforms from `name`, such as a title of a video or a name of an item.
+///
+/// Characters not allowed on Windows and control characters are replaced with `_`, trailing dots and spaces are
+/// removed, names reserved for devices get a `_` suffix, and the name is truncated to 255 bytes at a character
+/// boundary. Other characters, including non-ASCII ones, are kept.
+///
+/// # Examples
+/// ```
+/// use elden_analyzer::util::sanitize_file_name;
+///
+/// assert_eq!(sanitize_file_name("忌み鬼、マルギット"), "忌み鬼、マルギット");
+/// assert_eq!(sanitize_file_name("part 1: Limgrave?"), "part 1_ Limgrave_");
+/// assert_eq!(sanitize_file_name("con.txt"), "con_.txt");
+/// assert_eq!(sanitize_file_name("..."), "_");
+/// ```
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    sanitized.truncate(sanitized.trim_end_matches(['.', ' ']).len());
+
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        sanitized.insert(stem.len(), '_');
+    }
+
+    if sanitized.len() > MAX_FILE_NAME_LEN {
+        let mut len = MAX_FILE_NAME_LEN;
+        while !sanitized.is_char_boundary(len) {
+            len -= 1;
+        }
+        sanitized.truncate(len);
+        sanitized.truncate(sanitized.trim_end_matches(['.', ' ']).len());
+    }
+
+    if sanitized.is_empty() {
+        sanitized.push('_');
+    }
+    sanitized
+}
+
+/// Returns `path` in the form accepted by file APIs regardless of its length.
+///
+/// On Windows, paths longer than 260 characters are made absolute with the `\\?\` prefix, which lifts the limit.
+/// On other platforms, and for short paths, `path` is returned as is.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if path.as_os_str().len() >= MAX_PATH_LEN && !path.starts_with(r"\\?\") {
+        if let Ok(absolute) = std::path::absolute(path) {
+            let mut prefixed = std::ffi::OsString::from(r"\\?\");
+            prefixed.push(absolute.as_os_str());
+            return Cow::Owned(prefixed.into());
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Creates a file at `path` like [`File::create`], also creating its parent directories.
+///
+/// Long paths are handled by [`long_path`].
+pub fn create_file(path: &Path) -> io::Result<File> {
+    let path = long_path(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_truncates_at_char_boundary() {
+        let name = "あ".repeat(100);
+        let sanitized = sanitize_file_name(&name);
+        assert_eq!(sanitized, "あ".repeat(85));
+
+        assert_eq!(sanitize_file_name("a\tb\n"), "a_b_");
+        assert_eq!(sanitize_file_name("LPT1"), "LPT1_");
+        assert_eq!(sanitize_file_name("com10.mp4"), "com10.mp4");
+        assert_eq!(sanitize_file_name(""), "_");
+    }
+
+    #[test]
+    fn create_file_with_non_ascii_path() {
+        let dir = std::env::temp_dir().join(format!("elden-analyzer-path-{}", std::process::id()));
+        let path = dir.join("エルデンリング").join("出力.txt");
+        create_file(&path).unwrap();
+        assert!(path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,63 +1,107 @@
+use color_eyre::eyre::{self, OptionExt as _};
 use elden_analyzer_kernel::types::rect::Rect;
 use elden_analyzer_video::capture::Frame;
 use imageproc::image::{ImageBuffer, Luma, Pixel as _, Rgb};
 
+/// Images of frames, which fail if the frame cannot be converted to RGB24 or `rect` is out of the frame.
 pub trait FrameExt {
-    fn to_rgb_image(&self) -> ImageBuffer<Rgb<u8>, &[u8]>;
-    fn to_rgb_image_within(&self, rect: Rect) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>>;
-    fn to_gray_image(&self) -> ImageBuffer<Luma<u8>, Vec<u8>>;
-    fn to_gray_image_within(&self, rect: Rect) -> Option<ImageBuffer<Luma<u8>, Vec<u8>>>;
-    fn to_min_gray_image_within(&self, rect: Rect) -> Option<ImageBuffer<Luma<u8>, Vec<u8>>>;
+    fn to_rgb_image(&self) -> eyre::Result<ImageBuffer<Rgb<u8>, &[u8]>>;
+    fn to_rgb_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Rgb<u8>, Vec<u8>>>;
+    fn to_gray_image(&self) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>>;
+    fn to_gray_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>>;
+    fn to_min_gray_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>>;
+    /// Returns the luma of pixels, read from the decoded Y plane without RGB conversion if available.
+    fn to_luma_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>>;
+    /// Returns the luma of pixels at `points`, read in the same way as [`to_luma_image_within`](Self::to_luma_image_within).
+    fn sample_luma(&self, points: &[(u32, u32)]) -> eyre::Result<Vec<u8>>;
+}
+
+/// Returns the part of `rect` within a `width`x`height` frame.
+fn within_frame(rect: Rect, width: u32, height: u32) -> eyre::Result<Rect> {
+    let frame_rect = Rect::at(0, 0).of_size(width, height);
+    rect.intersect(frame_rect)
+        .ok_or_eyre(format!("{rect} is out of the frame {frame_rect}"))
 }
 
 impl FrameExt for Frame {
-    fn to_rgb_image(&self) -> ImageBuffer<Rgb<u8>, &[u8]> {
-        ImageBuffer::from_raw(self.width(), self.height(), self.data(0)).unwrap()
+    fn to_rgb_image(&self) -> eyre::Result<ImageBuffer<Rgb<u8>, &[u8]>> {
+        let data = self.rgb_data(0)?;
+        Ok(ImageBuffer::from_raw(self.width(), self.height(), data).unwrap())
     }
 
-    fn to_rgb_image_within(&self, rect: Rect) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>> {
-        let frame_rect = Rect::at(0, 0).of_size(self.width(), self.height());
-        let rect = rect.intersect(frame_rect)?;
+    fn to_rgb_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+        let rect = within_frame(rect, self.width(), self.height())?;
+        let data = self.rgb_data(0)?;
         let img = ImageBuffer::from_fn(rect.width(), rect.height(), |dx, dy| {
             let x = rect.left() as u32 + dx;
             let y = rect.top() as u32 + dy;
             let idx = ((y * self.width() + x) * 3) as usize;
-            *Rgb::from_slice(&self.data(0)[idx..][..3])
+            *Rgb::from_slice(&data[idx..][..3])
         });
-        Some(img)
+        Ok(img)
     }
 
-    fn to_gray_image(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
-        ImageBuffer::from_fn(self.width(), self.height(), |x, y| {
+    fn to_gray_image(&self) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+        let data = self.rgb_data(0)?;
+        let img = ImageBuffer::from_fn(self.width(), self.height(), |x, y| {
             let idx = ((y * self.width() + x) * 3) as usize;
-            let p = Rgb::from_slice(&self.data(0)[idx..][..3]);
+            let p = Rgb::from_slice(&data[idx..][..3]);
             p.to_luma()
-        })
+        });
+        Ok(img)
     }
 
-    fn to_gray_image_within(&self, rect: Rect) -> Option<ImageBuffer<Luma<u8>, Vec<u8>>> {
-        let frame_rect = Rect::at(0, 0).of_size(self.width(), self.height());
-        let rect = rect.intersect(frame_rect)?;
+    fn to_gray_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+        let rect = within_frame(rect, self.width(), self.height())?;
+        let data = self.rgb_data(0)?;
         let img = ImageBuffer::from_fn(rect.width(), rect.height(), |dx, dy| {
             let x = rect.left() as u32 + dx;
             let y = rect.top() as u32 + dy;
             let idx = ((y * self.width() + x) * 3) as usize;
-            let p = Rgb::from_slice(&self.data(0)[idx..][..3]);
+            let p = Rgb::from_slice(&data[idx..][..3]);
             p.to_luma()
         });
-        Some(img)
+        Ok(img)
     }
 
-    fn to_min_gray_image_within(&self, rect: Rect) -> Option<ImageBuffer<Luma<u8>, Vec<u8>>> {
-        let frame_rect = Rect::at(0, 0).of_size(self.width(), self.height());
-        let rect = rect.intersect(frame_rect)?;
+    fn to_min_gray_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+        let rect = within_frame(rect, self.width(), self.height())?;
+        let data = self.rgb_data(0)?;
         let img = ImageBuffer::from_fn(rect.width(), rect.height(), |dx, dy| {
             let x = rect.left() as u32 + dx;
             let y = rect.top() as u32 + dy;
             let idx = ((y * self.width() + x) * 3) as usize;
-            let v = self.data(0)[idx..][..3].iter().min().unwrap();
+            let v = data[idx..][..3].iter().min().unwrap();
             [*v].into()
         });
-        Some(img)
+        Ok(img)
+    }
+
+    fn to_luma_image_within(&self, rect: Rect) -> eyre::Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+        let Some(plane) = self.luma_plane() else {
+            return self.to_gray_image_within(rect);
+        };
+        let rect = within_frame(rect, plane.width(), plane.height())?;
+        let img = ImageBuffer::from_fn(rect.width(), rect.height(), |dx, dy| {
+            let x = rect.left() as u32 + dx;
+            let y = rect.top() as u32 + dy;
+            [plane.get(x, y)].into()
+        });
+        Ok(img)
+    }
+
+    fn sample_luma(&self, points: &[(u32, u32)]) -> eyre::Result<Vec<u8>> {
+        if let Some(plane) = self.luma_plane() {
+            return Ok(points.iter().map(|&(x, y)| plane.get(x, y)).collect());
+        }
+        let data = self.rgb_data(0)?;
+        let lumas = points
+            .iter()
+            .map(|&(x, y)| {
+                let idx = ((y * self.width() + x) * 3) as usize;
+                Rgb::from_slice(&data[idx..][..3]).to_luma()[0]
+            })
+            .collect();
+        Ok(lumas)
     }
 }
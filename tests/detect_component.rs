@@ -3,6 +3,7 @@ use std::path::Path;
 use color_eyre::eyre;
 use elden_analyzer::{
     components::{ComponentContainer, Components},
+    config::Config,
     operator::DetectionKind,
     util::ImageLogger,
 };
@@ -19,7 +20,7 @@ fn load_image(path: impl AsRef<Path>) -> eyre::Result<Frame> {
 
 fn detect_components(path: impl AsRef<Path>) -> eyre::Result<ComponentContainer<DetectionKind>> {
     let frame = load_image(path)?;
-    let components = Components::new(frame.rect()).unwrap();
+    let components = Components::new(frame.rect(), &Config::default()).unwrap();
     components
         .iter()
         .map(|c| c.detect(&frame).map(|res| res.kind()))